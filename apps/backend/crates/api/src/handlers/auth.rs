@@ -1,13 +1,22 @@
-use axum::{extract::State, Json};
-use kingshare_core::{ApiResponse, Error, Result};
+use axum::{
+    extract::{Path, Request, State},
+    http::header,
+    Extension, Json,
+};
+use kingshare_core::{ApiResponse, Error, Id, Result};
+use kingshare_application::services::{AuthSession, LoginOutcome, OidcService, RegistrationOutcome};
 use kingshare_domain::{
-    entities::CreateUserRequest,
+    entities::{
+        ApiTokenInfo, CreateApiTokenRequest, CreateUserRequest, CreatedApiToken, MfaStatus,
+        SessionInfo, TotpSetup, UserProfile,
+    },
+    services::{Claims, MfaChallengeToken},
     value_objects::Email,
 };
 use serde::{Deserialize, Serialize};
 use tracing::{info, instrument, warn};
 use validator::Validate;
-use crate::server::AppState;
+use crate::{handlers::client::Client, middleware::auth::ClaimsExt, server::AppState};
 
 #[derive(Debug, Deserialize, Validate)]
 pub struct RegisterRequest {
@@ -41,12 +50,111 @@ pub struct RefreshTokenRequest {
     pub refresh_token: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct VerifyEmailRequest {
+    pub token: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct EmailRequest {
+    #[validate(email)]
+    pub email: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ResetPasswordRequest {
+    pub token: String,
+
+    #[validate(length(min = 8))]
+    pub password: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OidcCallbackRequest {
+    pub code: String,
+    pub state: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MfaChallengeRequest {
+    pub challenge_token: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct MfaVerifyRequest {
+    pub challenge_token: String,
+
+    /// A six-digit TOTP code or a recovery code
+    #[validate(length(min = 6, max = 32))]
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct MfaCodeRequest {
+    #[validate(length(min = 6, max = 32))]
+    pub code: String,
+}
+
 #[derive(Debug, Serialize)]
 pub struct AuthResponse {
     pub access_token: String,
     pub refresh_token: String,
     pub expires_in: i64,
+    pub session_id: String,
     pub user: UserInfo,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recovery_codes: Option<Vec<String>>,
+}
+
+/// Either a signed-in session or the challenge to answer with a second factor
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum LoginResponse {
+    Authenticated(AuthResponse),
+    MfaRequired(MfaChallengeResponse),
+}
+
+/// Either a signed-in session or word that the email address needs verifying first
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum RegisterResponse {
+    Authenticated(AuthResponse),
+    VerificationRequired(VerificationRequiredResponse),
+}
+
+#[derive(Debug, Serialize)]
+pub struct VerificationRequiredResponse {
+    pub verification_required: bool,
+    pub user: UserInfo,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MfaChallengeResponse {
+    pub mfa_required: bool,
+    pub challenge_token: String,
+    pub expires_in: i64,
+    pub enrollment_required: bool,
+}
+
+impl From<MfaChallengeToken> for MfaChallengeResponse {
+    fn from(challenge: MfaChallengeToken) -> Self {
+        Self {
+            mfa_required: true,
+            challenge_token: challenge.challenge_token,
+            expires_in: challenge.expires_in,
+            enrollment_required: challenge.enrollment_required,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct OidcAuthorizationResponse {
+    pub authorization_url: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RecoveryCodesResponse {
+    pub recovery_codes: Vec<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -58,11 +166,50 @@ pub struct UserInfo {
     pub role: String,
 }
 
-#[instrument(skip(state, payload))]
+impl From<UserProfile> for UserInfo {
+    fn from(user: UserProfile) -> Self {
+        Self {
+            id: user.id.to_string(),
+            full_name: format!("{} {}", user.first_name, user.last_name),
+            role: user.role.as_str().to_string(),
+            email: user.email,
+            username: user.username,
+        }
+    }
+}
+
+impl From<AuthSession> for AuthResponse {
+    fn from(session: AuthSession) -> Self {
+        Self {
+            access_token: session.tokens.access_token,
+            refresh_token: session.tokens.refresh_token,
+            expires_in: session.tokens.expires_in,
+            session_id: session.tokens.session_id.to_string(),
+            user: session.user.into(),
+            recovery_codes: session.recovery_codes,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct RevokedSessionsResponse {
+    pub revoked_count: u64,
+}
+
+/// The session the request's access token was issued in
+fn current_session_id(request: &Request) -> Option<Id> {
+    request
+        .claims()
+        .and_then(|claims| claims.sid.as_deref())
+        .and_then(|sid| sid.parse().ok())
+}
+
+#[instrument(skip(state, client, payload))]
 pub async fn register(
     State(state): State<AppState>,
+    Client(client): Client,
     Json(payload): Json<RegisterRequest>,
-) -> Result<Json<ApiResponse<AuthResponse>>> {
+) -> Result<Json<ApiResponse<RegisterResponse>>> {
     // Validate request
     payload.validate()
         .map_err(|e| Error::Validation(e.to_string()))?;
@@ -76,156 +223,426 @@ pub async fn register(
         password: payload.password,
     };
 
-    // Create user using the service from app state
-    let user_profile = state.user_service.create_user(create_request).await?;
-
-    // Generate tokens using the auth service
-    let role_str = match user_profile.role {
-        kingshare_domain::entities::UserRole::Admin => "Admin",
-        kingshare_domain::entities::UserRole::User => "User",
-        kingshare_domain::entities::UserRole::Guest => "Guest",
+    // Create the user and sign them in, unless they must verify their email first
+    let outcome = state
+        .auth_service
+        .register(create_request, &client)
+        .await?;
+    let user_id = match &outcome {
+        RegistrationOutcome::Authenticated(session) => session.user.id,
+        RegistrationOutcome::VerificationRequired(user) => user.id,
     };
 
-    // Get auth service from user service (we need to expose it)
-    // For now, create a new one - in production, you'd inject it properly
-    let auth_service = kingshare_infrastructure::JwtAuthService::new(state.config.auth.clone());
-    let tokens = auth_service.generate_tokens(
-        user_profile.id,
-        &user_profile.email,
-        &user_profile.username,
-        role_str,
-    ).await?;
-
-    let response = AuthResponse {
-        access_token: tokens.access_token,
-        refresh_token: tokens.refresh_token,
-        expires_in: tokens.expires_in,
-        user: UserInfo {
-            id: user_profile.id.to_string(),
-            email: user_profile.email,
-            username: user_profile.username,
-            full_name: format!("{} {}", user_profile.first_name, user_profile.last_name),
-            role: role_str.to_string(),
-        },
-    };
+    // The account exists either way; the user can ask for another link
+    if let Err(e) = state
+        .account_service
+        .send_verification_email(user_id)
+        .await
+    {
+        warn!(user_id = %user_id, error = %e, "Failed to send verification email");
+    }
 
-    info!(user_id = %user_profile.id, "User registered successfully");
+    info!(user_id = %user_id, "User registered successfully");
+    let response = match outcome {
+        RegistrationOutcome::Authenticated(session) => RegisterResponse::Authenticated(session.into()),
+        RegistrationOutcome::VerificationRequired(user) => {
+            RegisterResponse::VerificationRequired(VerificationRequiredResponse {
+                verification_required: true,
+                user: user.into(),
+            })
+        }
+    };
     Ok(Json(ApiResponse::success(response)))
 }
 
-#[instrument(skip(state, payload))]
+#[instrument(skip(state, client, payload))]
 pub async fn login(
     State(state): State<AppState>,
+    Client(client): Client,
     Json(payload): Json<LoginRequest>,
-) -> Result<Json<ApiResponse<AuthResponse>>> {
+) -> Result<Json<ApiResponse<LoginResponse>>> {
     // Validate request
     payload.validate()
         .map_err(|e| Error::Validation(e.to_string()))?;
 
-    // Authenticate user using the service
-    let user_profile = state.user_service.authenticate_user(&payload.email, &payload.password).await?;
+    // Authenticate user and start a new session, unless a second factor is needed
+    let outcome = state
+        .auth_service
+        .login(&payload.email, &payload.password, &client)
+        .await?;
 
-    // Generate tokens
-    let role_str = match user_profile.role {
-        kingshare_domain::entities::UserRole::Admin => "Admin",
-        kingshare_domain::entities::UserRole::User => "User",
-        kingshare_domain::entities::UserRole::Guest => "Guest",
+    let response = match outcome {
+        LoginOutcome::Authenticated(session) => {
+            info!(user_id = %session.user.id, "User logged in successfully");
+            LoginResponse::Authenticated(session.into())
+        }
+        LoginOutcome::MfaRequired(challenge) => LoginResponse::MfaRequired(challenge.into()),
     };
 
-    let auth_service = kingshare_infrastructure::JwtAuthService::new(state.config.auth.clone());
-    let tokens = auth_service.generate_tokens(
-        user_profile.id,
-        &user_profile.email,
-        &user_profile.username,
-        role_str,
-    ).await?;
-
-    let response = AuthResponse {
-        access_token: tokens.access_token,
-        refresh_token: tokens.refresh_token,
-        expires_in: tokens.expires_in,
-        user: UserInfo {
-            id: user_profile.id.to_string(),
-            email: user_profile.email,
-            username: user_profile.username,
-            full_name: format!("{} {}", user_profile.first_name, user_profile.last_name),
-            role: role_str.to_string(),
-        },
+    Ok(Json(ApiResponse::success(response)))
+}
+
+/// Start a single sign-on login; the client sends the browser to the returned URL
+#[instrument(skip(state))]
+pub async fn start_oidc_login(
+    State(state): State<AppState>,
+) -> Result<Json<ApiResponse<OidcAuthorizationResponse>>> {
+    let oidc_service = require_oidc(&state)?;
+    let authorization_url = oidc_service.begin_login().await?;
+
+    Ok(Json(ApiResponse::success(OidcAuthorizationResponse { authorization_url })))
+}
+
+/// Finish a single sign-on login with the code and state the identity
+/// provider redirected back with
+#[instrument(skip(state, client, payload))]
+pub async fn complete_oidc_login(
+    State(state): State<AppState>,
+    Client(client): Client,
+    Json(payload): Json<OidcCallbackRequest>,
+) -> Result<Json<ApiResponse<LoginResponse>>> {
+    let oidc_service = require_oidc(&state)?;
+    let user = oidc_service.complete_login(&payload.code, &payload.state).await?;
+
+    let outcome = state
+        .auth_service
+        .login_with_identity(user, &client)
+        .await?;
+
+    let response = match outcome {
+        LoginOutcome::Authenticated(session) => {
+            info!(user_id = %session.user.id, "User logged in through single sign-on");
+            LoginResponse::Authenticated(session.into())
+        }
+        LoginOutcome::MfaRequired(challenge) => LoginResponse::MfaRequired(challenge.into()),
     };
 
-    info!(user_id = %user_profile.id, "User logged in successfully");
     Ok(Json(ApiResponse::success(response)))
 }
 
-#[instrument(skip(state, payload))]
-pub async fn refresh_token(
+fn require_oidc(state: &AppState) -> Result<&OidcService> {
+    state
+        .oidc_service
+        .as_ref()
+        .ok_or_else(|| Error::NotFound("Single sign-on is not configured".to_string()))
+}
+
+/// Second step of a login that returned an MFA challenge
+#[instrument(skip(state, client, payload))]
+pub async fn verify_mfa_login(
     State(state): State<AppState>,
-    Json(payload): Json<RefreshTokenRequest>,
+    Client(client): Client,
+    Json(payload): Json<MfaVerifyRequest>,
 ) -> Result<Json<ApiResponse<AuthResponse>>> {
-    // Create auth service
-    let auth_service = kingshare_infrastructure::JwtAuthService::new(state.config.auth.clone());
+    payload.validate()
+        .map_err(|e| Error::Validation(e.to_string()))?;
 
-    // First, verify the refresh token to get user info
-    let refresh_claims = auth_service.verify_token(&payload.refresh_token).await
-        .map_err(|_| Error::Authentication("Invalid refresh token".to_string()))?;
+    let session = state
+        .auth_service
+        .complete_mfa_login(&payload.challenge_token, &payload.code, &client)
+        .await?;
 
-    // Parse user ID from refresh token
-    let user_id = uuid::Uuid::parse_str(&refresh_claims.sub)
+    info!(user_id = %session.user.id, "User logged in successfully");
+    Ok(Json(ApiResponse::success(session.into())))
+}
+
+/// For users whose role requires 2FA but who haven't enrolled yet: the secret
+/// to confirm through `verify_mfa_login`
+#[instrument(skip(state, payload))]
+pub async fn enroll_mfa_login(
+    State(state): State<AppState>,
+    Json(payload): Json<MfaChallengeRequest>,
+) -> Result<Json<ApiResponse<TotpSetup>>> {
+    let setup = state
+        .auth_service
+        .begin_mfa_enrollment(&payload.challenge_token)
+        .await?;
+
+    Ok(Json(ApiResponse::success(setup)))
+}
+
+#[instrument(skip(state, payload))]
+pub async fn verify_email(
+    State(state): State<AppState>,
+    Json(payload): Json<VerifyEmailRequest>,
+) -> Result<Json<ApiResponse<UserProfile>>> {
+    let profile = state.account_service.verify_email(&payload.token).await?;
+
+    info!(user_id = %profile.id, "Email address verified");
+    Ok(Json(ApiResponse::success(profile)))
+}
+
+/// Always succeeds, so the response doesn't reveal whether the address is registered
+#[instrument(skip(state, payload))]
+pub async fn resend_verification_email(
+    State(state): State<AppState>,
+    Json(payload): Json<EmailRequest>,
+) -> Result<Json<ApiResponse<String>>> {
+    payload.validate()
         .map_err(|e| Error::Validation(e.to_string()))?;
 
-    // Get user details from database
-    let user_profile = state.user_service.get_user_by_id(user_id).await?;
+    state
+        .account_service
+        .resend_verification_email(&payload.email)
+        .await?;
 
-    // Generate new tokens
-    let role_str = match user_profile.role {
-        kingshare_domain::entities::UserRole::Admin => "Admin",
-        kingshare_domain::entities::UserRole::User => "User",
-        kingshare_domain::entities::UserRole::Guest => "Guest",
-    };
+    Ok(Json(ApiResponse::success(
+        "If the address belongs to an unverified account, a verification email is on its way".to_string(),
+    )))
+}
 
-    let tokens = auth_service.generate_tokens(
-        user_profile.id,
-        &user_profile.email,
-        &user_profile.username,
-        role_str,
-    ).await?;
-
-    let response = AuthResponse {
-        access_token: tokens.access_token,
-        refresh_token: tokens.refresh_token,
-        expires_in: tokens.expires_in,
-        user: UserInfo {
-            id: user_profile.id.to_string(),
-            email: user_profile.email,
-            username: user_profile.username,
-            full_name: format!("{} {}", user_profile.first_name, user_profile.last_name),
-            role: role_str.to_string(),
-        },
-    };
+/// Always succeeds, so the response doesn't reveal whether the address is registered
+#[instrument(skip(state, payload))]
+pub async fn forgot_password(
+    State(state): State<AppState>,
+    Json(payload): Json<EmailRequest>,
+) -> Result<Json<ApiResponse<String>>> {
+    payload.validate()
+        .map_err(|e| Error::Validation(e.to_string()))?;
 
-    info!(user_id = %user_profile.id, "Token refreshed successfully");
-    Ok(Json(ApiResponse::success(response)))
+    state
+        .account_service
+        .request_password_reset(&payload.email)
+        .await?;
+
+    Ok(Json(ApiResponse::success(
+        "If the address belongs to an account, a password reset email is on its way".to_string(),
+    )))
+}
+
+#[instrument(skip(state, payload))]
+pub async fn reset_password(
+    State(state): State<AppState>,
+    Json(payload): Json<ResetPasswordRequest>,
+) -> Result<Json<ApiResponse<String>>> {
+    payload.validate()
+        .map_err(|e| Error::Validation(e.to_string()))?;
+
+    state
+        .account_service
+        .reset_password(&payload.token, &payload.password)
+        .await?;
+
+    Ok(Json(ApiResponse::success(
+        "Password reset; sign in with your new password".to_string(),
+    )))
+}
+
+#[instrument(skip(state, payload))]
+pub async fn refresh_token(
+    State(state): State<AppState>,
+    Json(payload): Json<RefreshTokenRequest>,
+) -> Result<Json<ApiResponse<AuthResponse>>> {
+    // Spend the refresh token; replaying a spent one revokes the session
+    let session = state.auth_service.refresh(&payload.refresh_token).await?;
+
+    info!(user_id = %session.user.id, "Token refreshed successfully");
+    Ok(Json(ApiResponse::success(session.into())))
 }
 
 #[instrument(skip(state, request))]
 pub async fn logout(
     State(state): State<AppState>,
-    request: axum::extract::Request,
+    request: Request,
 ) -> Result<Json<ApiResponse<String>>> {
     // Extract the token from Authorization header
     let auth_header = request
         .headers()
-        .get(axum::http::header::AUTHORIZATION)
+        .get(header::AUTHORIZATION)
         .and_then(|header| header.to_str().ok());
 
     if let Some(auth_header) = auth_header {
         if let Some(token) = auth_header.strip_prefix("Bearer ") {
-            // Create auth service and revoke the token
-            let auth_service = kingshare_infrastructure::JwtAuthService::new(state.config.auth.clone());
-            let _ = auth_service.revoke_token(token).await; // Ignore errors for logout
+            // Deny the token and end its session
+            if let Err(e) = state.auth_service.logout(token).await {
+                warn!(error = %e, "Failed to revoke token on logout");
+            }
         }
     }
 
     info!("User logged out successfully");
     Ok(Json(ApiResponse::success("Logged out successfully".to_string())))
-}
\ No newline at end of file
+}
+
+#[instrument(skip(state, request))]
+pub async fn list_sessions(
+    State(state): State<AppState>,
+    request: Request,
+) -> Result<Json<ApiResponse<Vec<SessionInfo>>>> {
+    let user_id = request.require_user_id()
+        .map_err(|_| Error::Authentication("Authentication required".to_string()))?;
+
+    let sessions = state
+        .auth_service
+        .list_sessions(user_id, current_session_id(&request))
+        .await?;
+
+    info!(user_id = %user_id, count = sessions.len(), "Sessions listed");
+    Ok(Json(ApiResponse::success(sessions)))
+}
+
+#[instrument(skip(state, request))]
+pub async fn revoke_session(
+    State(state): State<AppState>,
+    Path(session_id): Path<Id>,
+    request: Request,
+) -> Result<Json<ApiResponse<String>>> {
+    let user_id = request.require_user_id()
+        .map_err(|_| Error::Authentication("Authentication required".to_string()))?;
+
+    state.auth_service.revoke_session(user_id, session_id).await?;
+
+    info!(user_id = %user_id, session_id = %session_id, "Session signed out");
+    Ok(Json(ApiResponse::success("Session signed out".to_string())))
+}
+
+/// Sign out everywhere except the session making the request
+#[instrument(skip(state, request))]
+pub async fn revoke_other_sessions(
+    State(state): State<AppState>,
+    request: Request,
+) -> Result<Json<ApiResponse<RevokedSessionsResponse>>> {
+    let user_id = request.require_user_id()
+        .map_err(|_| Error::Authentication("Authentication required".to_string()))?;
+
+    let revoked_count = state
+        .auth_service
+        .revoke_other_sessions(user_id, current_session_id(&request))
+        .await?;
+
+    info!(user_id = %user_id, revoked_count = revoked_count, "Other sessions signed out");
+    Ok(Json(ApiResponse::success(RevokedSessionsResponse { revoked_count })))
+}
+
+#[instrument(skip(state, request))]
+pub async fn get_mfa_status(
+    State(state): State<AppState>,
+    request: Request,
+) -> Result<Json<ApiResponse<MfaStatus>>> {
+    let user_id = request.require_user_id()
+        .map_err(|_| Error::Authentication("Authentication required".to_string()))?;
+
+    let user = state.user_service.get_user_by_id(user_id).await?;
+    let status = state.mfa_service.status(&user).await?;
+
+    Ok(Json(ApiResponse::success(status)))
+}
+
+/// Start enrolling in 2FA; returns the otpauth URI to scan
+#[instrument(skip(state, request))]
+pub async fn enroll_mfa(
+    State(state): State<AppState>,
+    request: Request,
+) -> Result<Json<ApiResponse<TotpSetup>>> {
+    let user_id = request.require_user_id()
+        .map_err(|_| Error::Authentication("Authentication required".to_string()))?;
+
+    let user = state.user_service.get_user_by_id(user_id).await?;
+    let setup = state.mfa_service.begin_enrollment(&user).await?;
+
+    Ok(Json(ApiResponse::success(setup)))
+}
+
+#[instrument(skip(state, claims, payload))]
+pub async fn confirm_mfa(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<MfaCodeRequest>,
+) -> Result<Json<ApiResponse<RecoveryCodesResponse>>> {
+    payload.validate()
+        .map_err(|e| Error::Validation(e.to_string()))?;
+
+    let user_id: Id = claims.sub.parse()
+        .map_err(|_| Error::Authentication("Authentication required".to_string()))?;
+
+    let recovery_codes = state
+        .mfa_service
+        .confirm_enrollment(user_id, &payload.code)
+        .await?;
+
+    info!(user_id = %user_id, "Two-factor authentication enabled");
+    Ok(Json(ApiResponse::success(RecoveryCodesResponse { recovery_codes })))
+}
+
+#[instrument(skip(state, claims, payload))]
+pub async fn disable_mfa(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<MfaCodeRequest>,
+) -> Result<Json<ApiResponse<String>>> {
+    payload.validate()
+        .map_err(|e| Error::Validation(e.to_string()))?;
+
+    let user_id: Id = claims.sub.parse()
+        .map_err(|_| Error::Authentication("Authentication required".to_string()))?;
+
+    let user = state.user_service.get_user_by_id(user_id).await?;
+    state.mfa_service.disable(&user, &payload.code).await?;
+
+    info!(user_id = %user_id, "Two-factor authentication disabled");
+    Ok(Json(ApiResponse::success("Two-factor authentication disabled".to_string())))
+}
+
+#[instrument(skip(state, claims, payload))]
+pub async fn regenerate_recovery_codes(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<MfaCodeRequest>,
+) -> Result<Json<ApiResponse<RecoveryCodesResponse>>> {
+    payload.validate()
+        .map_err(|e| Error::Validation(e.to_string()))?;
+
+    let user_id: Id = claims.sub.parse()
+        .map_err(|_| Error::Authentication("Authentication required".to_string()))?;
+
+    let recovery_codes = state
+        .mfa_service
+        .regenerate_recovery_codes(user_id, &payload.code)
+        .await?;
+
+    Ok(Json(ApiResponse::success(RecoveryCodesResponse { recovery_codes })))
+}
+
+#[instrument(skip(state, request))]
+pub async fn list_api_tokens(
+    State(state): State<AppState>,
+    request: Request,
+) -> Result<Json<ApiResponse<Vec<ApiTokenInfo>>>> {
+    let user_id = request.require_user_id()
+        .map_err(|_| Error::Authentication("Authentication required".to_string()))?;
+
+    let tokens = state.api_token_service.list_tokens(user_id).await?;
+
+    Ok(Json(ApiResponse::success(tokens)))
+}
+
+/// Create a personal access token; the secret is only shown in this response
+#[instrument(skip(state, claims, payload))]
+pub async fn create_api_token(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<CreateApiTokenRequest>,
+) -> Result<Json<ApiResponse<CreatedApiToken>>> {
+    let user_id: Id = claims.sub.parse()
+        .map_err(|_| Error::Authentication("Authentication required".to_string()))?;
+
+    let token = state.api_token_service.create_token(user_id, payload).await?;
+
+    info!(user_id = %user_id, token_id = %token.info.id, "API token created");
+    Ok(Json(ApiResponse::success(token)))
+}
+
+#[instrument(skip(state, request))]
+pub async fn revoke_api_token(
+    State(state): State<AppState>,
+    Path(token_id): Path<Id>,
+    request: Request,
+) -> Result<Json<ApiResponse<String>>> {
+    let user_id = request.require_user_id()
+        .map_err(|_| Error::Authentication("Authentication required".to_string()))?;
+
+    state.api_token_service.revoke_token(user_id, token_id).await?;
+
+    info!(user_id = %user_id, token_id = %token_id, "API token revoked");
+    Ok(Json(ApiResponse::success("Token revoked".to_string())))
+}
@@ -0,0 +1,56 @@
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts},
+    http::{header, request::Parts, HeaderMap},
+};
+use kingshare_domain::entities::ClientInfo;
+use std::{convert::Infallible, net::SocketAddr};
+use crate::server::AppState;
+
+/// The client making a request, as recorded on sessions and counted by
+/// failed-attempt limits
+pub struct Client(pub ClientInfo);
+
+#[async_trait]
+impl FromRequestParts<AppState> for Client {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let peer = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip().to_string());
+
+        Ok(Self(client_info(
+            &parts.headers,
+            peer,
+            state.config.server.trust_proxy_headers,
+        )))
+    }
+}
+
+/// The address is the connecting peer's, or the one proxy headers report when
+/// the server is configured to trust them
+pub fn client_info(headers: &HeaderMap, peer: Option<String>, trust_proxy_headers: bool) -> ClientInfo {
+    let header_value = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+    };
+
+    let forwarded = trust_proxy_headers
+        .then(|| {
+            header_value("x-forwarded-for")
+                .and_then(|forwarded| forwarded.split(',').next())
+                .map(str::trim)
+                .or_else(|| header_value("x-real-ip"))
+        })
+        .flatten()
+        .map(str::to_string);
+    let ip_address = forwarded.or(peer);
+    let user_agent = header_value(header::USER_AGENT.as_str()).map(str::to_string);
+
+    ClientInfo::new(user_agent, ip_address)
+}
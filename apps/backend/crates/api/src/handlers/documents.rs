@@ -6,6 +6,7 @@ use axum::{
 };
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use tracing::warn;
 use validator::Validate;
 
 use kingshare_core::{Id, Result as CoreResult};
@@ -14,6 +15,7 @@ use kingshare_domain::{
     DocumentSummary, DocumentRepository, CollaborationService, CollaborationSessionResponse,
     CreateCommentRequest, CreateCommentReplyRequest, UpdateCommentRequest, CommentResponse,
     CreateSuggestionRequest, ReviewSuggestionRequest, SuggestionResponse, DocumentVersionResponse,
+    Permission, Resource,
 };
 
 use crate::{
@@ -29,6 +31,9 @@ pub async fn create_document(
     Json(request): Json<CreateDocumentRequest>,
 ) -> ApiResult<Json<Document>> {
     request.validate().map_err(ApiError::ValidationError)?;
+    state.authorization_service
+        .require(claims.user_id, Permission::DocumentsWrite, &Resource::owned_by(claims.user_id))
+        .await?;
     
     let document = Document::new(
         claims.user_id,
@@ -49,9 +54,7 @@ pub async fn get_document(
     let document = state.document_repository.get_document_by_id(document_id).await?
         .ok_or(ApiError::NotFound("Document not found".to_string()))?;
     
-    if !document.can_user_view(claims.user_id) {
-        return Err(ApiError::Forbidden("Access denied".to_string()));
-    }
+    authorize_document(&state, claims.user_id, &document, Document::can_user_view, Permission::DocumentsRead).await?;
 
     // Update last accessed time
     let _ = state.document_repository.update_access_time(document_id, claims.user_id).await;
@@ -70,9 +73,7 @@ pub async fn update_document(
     let mut document = state.document_repository.get_document_by_id(document_id).await?
         .ok_or(ApiError::NotFound("Document not found".to_string()))?;
     
-    if !document.can_user_edit(claims.user_id) {
-        return Err(ApiError::Forbidden("Access denied".to_string()));
-    }
+    authorize_document(&state, claims.user_id, &document, Document::can_user_edit, Permission::DocumentsWrite).await?;
 
     // Apply updates
     if let Some(title) = request.title {
@@ -93,6 +94,7 @@ pub async fn update_document(
 
     document.updated_at = chrono::Utc::now();
     let updated_document = state.document_repository.update_document(document).await?;
+    index_document(&state, &updated_document).await;
 
     Ok(Json(updated_document))
 }
@@ -105,9 +107,9 @@ pub async fn delete_document(
     let document = state.document_repository.get_document_by_id(document_id).await?
         .ok_or(ApiError::NotFound("Document not found".to_string()))?;
     
-    if document.owner_id != claims.user_id {
-        return Err(ApiError::Forbidden("Access denied".to_string()));
-    }
+    state.authorization_service
+        .require(claims.user_id, Permission::DocumentsWrite, &Resource::owned_by(document.owner_id))
+        .await?;
 
     state.document_repository.delete_document(document_id).await?;
     Ok(StatusCode::NO_CONTENT)
@@ -118,6 +120,10 @@ pub async fn list_documents(
     Extension(claims): Extension<Claims>,
     Query(params): Query<ListDocumentsQuery>,
 ) -> ApiResult<Json<Vec<DocumentSummary>>> {
+    state.authorization_service
+        .require(claims.user_id, Permission::DocumentsRead, &Resource::owned_by(claims.user_id))
+        .await?;
+
     let documents = if let Some(doc_type) = params.document_type {
         state.document_repository.get_documents_by_type(claims.user_id, doc_type).await?
     } else {
@@ -158,9 +164,7 @@ pub async fn start_collaboration(
     let document = state.document_repository.get_document_by_id(document_id).await?
         .ok_or(ApiError::NotFound("Document not found".to_string()))?;
     
-    if !document.can_user_view(claims.user_id) {
-        return Err(ApiError::Forbidden("Access denied".to_string()));
-    }
+    authorize_document(&state, claims.user_id, &document, Document::can_user_view, Permission::DocumentsRead).await?;
 
     let session = state.collaboration_service.start_collaboration_session(document_id, claims.user_id).await?;
     Ok(Json(session))
@@ -174,9 +178,7 @@ pub async fn join_collaboration(
     let document = state.document_repository.get_document_by_id(document_id).await?
         .ok_or(ApiError::NotFound("Document not found".to_string()))?;
     
-    if !document.can_user_view(claims.user_id) {
-        return Err(ApiError::Forbidden("Access denied".to_string()));
-    }
+    authorize_document(&state, claims.user_id, &document, Document::can_user_view, Permission::DocumentsRead).await?;
 
     // TODO: Get user details from user service
     let session = state.collaboration_service.join_collaboration_session(
@@ -207,15 +209,14 @@ pub async fn apply_operations(
     let document = state.document_repository.get_document_by_id(document_id).await?
         .ok_or(ApiError::NotFound("Document not found".to_string()))?;
     
-    if !document.can_user_edit(claims.user_id) {
-        return Err(ApiError::Forbidden("Access denied".to_string()));
-    }
+    authorize_document(&state, claims.user_id, &document, Document::can_user_edit, Permission::DocumentsWrite).await?;
 
     let new_version = state.collaboration_service.apply_text_operation(
         document_id,
         claims.user_id,
         request.operations,
     ).await?;
+    index_saved_document(&state, document_id).await?;
 
     Ok(Json(OperationResponse { version: new_version }))
 }
@@ -229,9 +230,7 @@ pub async fn get_pending_operations(
     let document = state.document_repository.get_document_by_id(document_id).await?
         .ok_or(ApiError::NotFound("Document not found".to_string()))?;
     
-    if !document.can_user_view(claims.user_id) {
-        return Err(ApiError::Forbidden("Access denied".to_string()));
-    }
+    authorize_document(&state, claims.user_id, &document, Document::can_user_view, Permission::DocumentsRead).await?;
 
     let operations = state.collaboration_service.get_pending_operations(
         document_id,
@@ -253,9 +252,7 @@ pub async fn add_comment(
     let document = state.document_repository.get_document_by_id(document_id).await?
         .ok_or(ApiError::NotFound("Document not found".to_string()))?;
     
-    if !document.can_user_comment(claims.user_id) {
-        return Err(ApiError::Forbidden("Access denied".to_string()));
-    }
+    authorize_document(&state, claims.user_id, &document, Document::can_user_comment, Permission::DocumentsWrite).await?;
 
     let comment = state.collaboration_service.add_comment(document_id, claims.user_id, request).await?;
     Ok(Json(comment))
@@ -269,9 +266,7 @@ pub async fn get_comments(
     let document = state.document_repository.get_document_by_id(document_id).await?
         .ok_or(ApiError::NotFound("Document not found".to_string()))?;
     
-    if !document.can_user_view(claims.user_id) {
-        return Err(ApiError::Forbidden("Access denied".to_string()));
-    }
+    authorize_document(&state, claims.user_id, &document, Document::can_user_view, Permission::DocumentsRead).await?;
 
     let comments = state.collaboration_service.get_document_comments(document_id, claims.user_id).await?;
     Ok(Json(comments))
@@ -322,9 +317,7 @@ pub async fn create_suggestion(
     let document = state.document_repository.get_document_by_id(document_id).await?
         .ok_or(ApiError::NotFound("Document not found".to_string()))?;
     
-    if !document.can_user_edit(claims.user_id) {
-        return Err(ApiError::Forbidden("Access denied".to_string()));
-    }
+    authorize_document(&state, claims.user_id, &document, Document::can_user_edit, Permission::DocumentsWrite).await?;
 
     let suggestion = state.collaboration_service.create_suggestion(document_id, claims.user_id, request).await?;
     Ok(Json(suggestion))
@@ -338,9 +331,7 @@ pub async fn get_suggestions(
     let document = state.document_repository.get_document_by_id(document_id).await?
         .ok_or(ApiError::NotFound("Document not found".to_string()))?;
     
-    if !document.can_user_view(claims.user_id) {
-        return Err(ApiError::Forbidden("Access denied".to_string()));
-    }
+    authorize_document(&state, claims.user_id, &document, Document::can_user_view, Permission::DocumentsRead).await?;
 
     let suggestions = state.collaboration_service.get_document_suggestions(document_id, claims.user_id).await?;
     Ok(Json(suggestions))
@@ -385,9 +376,7 @@ pub async fn create_version(
     let document = state.document_repository.get_document_by_id(document_id).await?
         .ok_or(ApiError::NotFound("Document not found".to_string()))?;
     
-    if !document.can_user_edit(claims.user_id) {
-        return Err(ApiError::Forbidden("Access denied".to_string()));
-    }
+    authorize_document(&state, claims.user_id, &document, Document::can_user_edit, Permission::DocumentsWrite).await?;
 
     let version = state.collaboration_service.create_document_version(
         document_id,
@@ -406,9 +395,7 @@ pub async fn get_versions(
     let document = state.document_repository.get_document_by_id(document_id).await?
         .ok_or(ApiError::NotFound("Document not found".to_string()))?;
     
-    if !document.can_user_view(claims.user_id) {
-        return Err(ApiError::Forbidden("Access denied".to_string()));
-    }
+    authorize_document(&state, claims.user_id, &document, Document::can_user_view, Permission::DocumentsRead).await?;
 
     let versions = state.collaboration_service.get_document_versions(document_id, claims.user_id).await?;
     Ok(Json(versions))
@@ -422,11 +409,10 @@ pub async fn restore_version(
     let document = state.document_repository.get_document_by_id(document_id).await?
         .ok_or(ApiError::NotFound("Document not found".to_string()))?;
     
-    if !document.can_user_edit(claims.user_id) {
-        return Err(ApiError::Forbidden("Access denied".to_string()));
-    }
+    authorize_document(&state, claims.user_id, &document, Document::can_user_edit, Permission::DocumentsWrite).await?;
 
     state.collaboration_service.restore_document_version(document_id, version_id, claims.user_id).await?;
+    index_saved_document(&state, document_id).await?;
     Ok(StatusCode::NO_CONTENT)
 }
 
@@ -442,9 +428,9 @@ pub async fn save_as_template(
     let mut document = state.document_repository.get_document_by_id(document_id).await?
         .ok_or(ApiError::NotFound("Document not found".to_string()))?;
     
-    if document.owner_id != claims.user_id {
-        return Err(ApiError::Forbidden("Access denied".to_string()));
-    }
+    state.authorization_service
+        .require(claims.user_id, Permission::DocumentsWrite, &Resource::owned_by(document.owner_id))
+        .await?;
 
     document.is_template = true;
     document.title = request.template_name;
@@ -464,9 +450,13 @@ pub async fn save_as_template(
 
 pub async fn get_templates(
     State(state): State<AppState>,
-    Extension(_claims): Extension<Claims>,
+    Extension(claims): Extension<Claims>,
     Query(params): Query<TemplatesQuery>,
 ) -> ApiResult<Json<Vec<TemplateResponse>>> {
+    state.authorization_service
+        .require(claims.user_id, Permission::DocumentsRead, &Resource::owned_by(claims.user_id))
+        .await?;
+
     let templates = if let Some(doc_type) = params.document_type {
         state.document_repository.get_templates_by_type(doc_type).await?
     } else {
@@ -499,6 +489,12 @@ pub async fn create_from_template(
     if !template.is_template {
         return Err(ApiError::BadRequest("Document is not a template".to_string()));
     }
+    if !template.is_public {
+        authorize_document(&state, claims.user_id, &template, Document::can_user_view, Permission::DocumentsRead).await?;
+    }
+    state.authorization_service
+        .require(claims.user_id, Permission::DocumentsWrite, &Resource::owned_by(claims.user_id))
+        .await?;
 
     let mut new_document = template.clone();
     new_document.id = uuid::Uuid::new_v4();
@@ -515,6 +511,42 @@ pub async fn create_from_template(
     Ok(Json(created_document))
 }
 
+/// Allow `permission` on a document through its own sharing, checked by
+/// `shared`, or a role that grants it on the owner's resources
+async fn authorize_document(
+    state: &AppState,
+    user_id: Id,
+    document: &Document,
+    shared: fn(&Document, Id) -> bool,
+    permission: Permission,
+) -> ApiResult<()> {
+    // Owners pass every sharing check, so they go through their role like anyone else
+    if document.owner_id != user_id && shared(document, user_id) {
+        return Ok(());
+    }
+
+    state.authorization_service
+        .require(user_id, permission, &Resource::owned_by(document.owner_id))
+        .await?;
+    Ok(())
+}
+
+/// Keep the drive items holding a document searchable by what it says now.
+/// The document is already saved, so failing to index it is only logged.
+async fn index_document(state: &AppState, document: &Document) {
+    if let Err(e) = state.drive_service.index_document_items(document.id, &document.content).await {
+        warn!(document_id = %document.id, error = %e, "Failed to index document contents");
+    }
+}
+
+/// `index_document` for changes the collaboration service saved
+async fn index_saved_document(state: &AppState, document_id: Id) -> ApiResult<()> {
+    if let Some(document) = state.document_repository.get_document_by_id(document_id).await? {
+        index_document(state, &document).await;
+    }
+    Ok(())
+}
+
 // Request/Response DTOs
 #[derive(Debug, Deserialize)]
 pub struct ListDocumentsQuery {
@@ -0,0 +1,238 @@
+use axum::{
+    body::{Body, Bytes},
+    http::{header, response::Builder, HeaderMap, HeaderName, StatusCode},
+    response::Response,
+};
+use chrono::{DateTime, Utc};
+use futures_util::stream::{self, StreamExt};
+use kingshare_core::{Error, Result, Timestamp};
+use kingshare_domain::{
+    services::FileStream,
+    value_objects::{ByteRange, RangeError},
+};
+
+const HTTP_DATE_FORMAT: &str = "%a, %d %b %Y %H:%M:%S GMT";
+
+/// Representation metadata used for validators and range handling
+#[derive(Debug, Clone)]
+pub struct DownloadInfo {
+    pub filename: String,
+    pub content_type: String,
+    pub size: u64,
+    pub etag: String,
+    pub last_modified: Timestamp,
+}
+
+/// What to send back for a download request, decided before touching storage
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DownloadPlan {
+    NotModified,
+    Unsatisfiable,
+    Full,
+    Partial(Vec<ByteRange>),
+}
+
+impl DownloadInfo {
+    pub fn new(
+        filename: &str,
+        content_type: &str,
+        size: i64,
+        checksum: &str,
+        last_modified: Timestamp,
+    ) -> Self {
+        Self {
+            filename: filename.to_string(),
+            content_type: content_type.to_string(),
+            size: size.max(0) as u64,
+            etag: format!("\"{}\"", checksum),
+            last_modified,
+        }
+    }
+
+    fn last_modified_header(&self) -> String {
+        self.last_modified.format(HTTP_DATE_FORMAT).to_string()
+    }
+
+    /// HTTP dates have one-second resolution
+    fn last_modified_secs(&self) -> i64 {
+        self.last_modified.timestamp()
+    }
+
+    /// Weak comparison, as used by If-None-Match
+    fn matches_etag(&self, value: &str) -> bool {
+        value.split(',').map(str::trim).any(|candidate| {
+            candidate == "*" || candidate.strip_prefix("W/").unwrap_or(candidate) == self.etag
+        })
+    }
+}
+
+pub fn plan_download(headers: &HeaderMap, info: &DownloadInfo) -> DownloadPlan {
+    if is_not_modified(headers, info) {
+        return DownloadPlan::NotModified;
+    }
+
+    let Some(range) = header_str(headers, header::RANGE) else {
+        return DownloadPlan::Full;
+    };
+
+    // A stale If-Range means the client's partial copy is outdated; send everything
+    if let Some(if_range) = header_str(headers, header::IF_RANGE) {
+        let still_valid = match parse_http_date(if_range) {
+            Some(date) => info.last_modified_secs() <= date.timestamp(),
+            // If-Range requires a strong comparison against a single validator
+            None => if_range.trim() == info.etag,
+        };
+        if !still_valid {
+            return DownloadPlan::Full;
+        }
+    }
+
+    match ByteRange::parse_header(range, info.size) {
+        Ok(ranges) => DownloadPlan::Partial(ranges),
+        Err(RangeError::Unsatisfiable { .. }) => DownloadPlan::Unsatisfiable,
+        // Unparseable Range headers are ignored, per RFC 9110
+        Err(RangeError::Malformed(_)) => DownloadPlan::Full,
+    }
+}
+
+pub fn not_modified_response(info: &DownloadInfo) -> Result<Response> {
+    validator_headers(Response::builder().status(StatusCode::NOT_MODIFIED), info)
+        .body(Body::empty())
+        .map_err(|e| Error::Internal(format!("Failed to create response: {}", e)))
+}
+
+pub fn range_not_satisfiable_response(info: &DownloadInfo) -> Result<Response> {
+    Response::builder()
+        .status(StatusCode::RANGE_NOT_SATISFIABLE)
+        .header(header::ACCEPT_RANGES, "bytes")
+        .header(header::CONTENT_RANGE, format!("bytes */{}", info.size))
+        .body(Body::empty())
+        .map_err(|e| Error::Internal(format!("Failed to create response: {}", e)))
+}
+
+/// Build the 200/206 response for streams opened according to `ranges`
+pub fn stream_response(
+    info: &DownloadInfo,
+    ranges: &[ByteRange],
+    mut streams: Vec<FileStream>,
+) -> Result<Response> {
+    let builder = validator_headers(Response::builder(), info).header(
+        header::CONTENT_DISPOSITION,
+        format!("attachment; filename=\"{}\"", info.filename),
+    );
+
+    let response = match ranges {
+        [] => {
+            let stream = streams
+                .pop()
+                .ok_or_else(|| Error::Internal("Missing file stream".to_string()))?;
+            builder
+                .status(StatusCode::OK)
+                .header(header::CONTENT_TYPE, &info.content_type)
+                .header(header::CONTENT_LENGTH, info.size)
+                .body(Body::from_stream(stream))
+        }
+        [range] => {
+            let stream = streams
+                .pop()
+                .ok_or_else(|| Error::Internal("Missing file stream".to_string()))?;
+            builder
+                .status(StatusCode::PARTIAL_CONTENT)
+                .header(header::CONTENT_TYPE, &info.content_type)
+                .header(header::CONTENT_RANGE, range.content_range(info.size))
+                .header(header::CONTENT_LENGTH, range.len())
+                .body(Body::from_stream(stream))
+        }
+        _ => {
+            let boundary = uuid::Uuid::new_v4().simple().to_string();
+            let (content_length, body) = multipart_body(info, ranges, streams, &boundary);
+            builder
+                .status(StatusCode::PARTIAL_CONTENT)
+                .header(
+                    header::CONTENT_TYPE,
+                    format!("multipart/byteranges; boundary={}", boundary),
+                )
+                .header(header::CONTENT_LENGTH, content_length)
+                .body(body)
+        }
+    };
+
+    response.map_err(|e| Error::Internal(format!("Failed to create response: {}", e)))
+}
+
+/// Build a 200 response for content rendered in place, such as thumbnails.
+/// Ranges aren't offered; these are small and always fetched whole.
+pub fn inline_response(info: &DownloadInfo, stream: FileStream) -> Result<Response> {
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::ETAG, &info.etag)
+        .header(header::LAST_MODIFIED, info.last_modified_header())
+        .header(header::CACHE_CONTROL, "private, max-age=86400")
+        .header(header::CONTENT_TYPE, &info.content_type)
+        .header(header::CONTENT_LENGTH, info.size)
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!("inline; filename=\"{}\"", info.filename),
+        )
+        .body(Body::from_stream(stream))
+        .map_err(|e| Error::Internal(format!("Failed to create response: {}", e)))
+}
+
+fn multipart_body(
+    info: &DownloadInfo,
+    ranges: &[ByteRange],
+    streams: Vec<FileStream>,
+    boundary: &str,
+) -> (u64, Body) {
+    let mut content_length = 0u64;
+    let mut parts: Vec<FileStream> = Vec::with_capacity(ranges.len() * 2 + 1);
+
+    for (index, (range, stream)) in ranges.iter().zip(streams).enumerate() {
+        let separator = if index == 0 { "" } else { "\r\n" };
+        let part_header = Bytes::from(format!(
+            "{}--{}\r\nContent-Type: {}\r\nContent-Range: {}\r\n\r\n",
+            separator,
+            boundary,
+            info.content_type,
+            range.content_range(info.size)
+        ));
+        content_length += part_header.len() as u64 + range.len();
+        parts.push(Box::pin(stream::once(async move { Ok(part_header) })));
+        parts.push(stream);
+    }
+
+    let closing = Bytes::from(format!("\r\n--{}--\r\n", boundary));
+    content_length += closing.len() as u64;
+    parts.push(Box::pin(stream::once(async move { Ok(closing) })));
+
+    (content_length, Body::from_stream(stream::iter(parts).flatten()))
+}
+
+fn validator_headers(builder: Builder, info: &DownloadInfo) -> Builder {
+    builder
+        .header(header::ACCEPT_RANGES, "bytes")
+        .header(header::ETAG, &info.etag)
+        .header(header::LAST_MODIFIED, info.last_modified_header())
+}
+
+pub fn is_not_modified(headers: &HeaderMap, info: &DownloadInfo) -> bool {
+    // If-None-Match takes precedence; If-Modified-Since is ignored when it is present
+    if let Some(if_none_match) = header_str(headers, header::IF_NONE_MATCH) {
+        return info.matches_etag(if_none_match);
+    }
+
+    header_str(headers, header::IF_MODIFIED_SINCE)
+        .and_then(parse_http_date)
+        .map(|since| info.last_modified_secs() <= since.timestamp())
+        .unwrap_or(false)
+}
+
+fn header_str(headers: &HeaderMap, name: HeaderName) -> Option<&str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+fn parse_http_date(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc2822(value.trim())
+        .ok()
+        .map(|date| date.with_timezone(&Utc))
+}
@@ -8,37 +8,40 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use validator::Validate;
 
-use kingshare_core::{Id, Result as CoreResult};
+use kingshare_core::{Error, Id, Result, Timestamp};
 use kingshare_domain::{
-    Drive, Folder, DriveItem, CreateDriveRequest, CreateFolderRequest, UpdateFolderRequest,
-    ShareItemRequest, CreateSharingLinkRequest, DriveItemResponse, FolderContents,
-    DriveService, DriveRepository, ActivityType, DriveActivity,
+    entities::{
+        AccessExplanation, ActivityType, ApiScope, ApiTokenGrant, CreateDriveRequest, CreateFolderRequest,
+        CreateSharingLinkRequest, Drive, DriveActivity, DriveItem, DriveItemType, DriveQuery, DriveType, Folder,
+        FolderContents, ItemAccess, Permission, Resource, SearchFilters, SearchResult, SearchScope, ShareItemRequest,
+        UpdateFolderRequest,
+    },
+    services::Claims,
 };
 
-use crate::{
-    error::{ApiError, ApiResult},
-    middleware::auth::Claims,
-    AppState,
-};
+use crate::{middleware::auth::GrantExt, server::AppState};
 
 // Drive management endpoints
 pub async fn create_drive(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
+    grant: Option<Extension<ApiTokenGrant>>,
     Json(request): Json<CreateDriveRequest>,
-) -> ApiResult<Json<Drive>> {
-    request.validate().map_err(ApiError::ValidationError)?;
+) -> Result<Json<Drive>> {
+    let user_id = claims.user_id()?;
+    grant.require_scope(ApiScope::FilesWrite)?;
+    request.validate().map_err(|e| Error::Validation(e.to_string()))?;
     
     let drive_service = state.drive_service.as_ref();
     let drive = match request.drive_type {
-        kingshare_domain::DriveType::Personal => {
-            drive_service.create_personal_drive(claims.user_id).await?
+        DriveType::Personal => {
+            drive_service.create_personal_drive(user_id).await?
         }
-        kingshare_domain::DriveType::Team => {
-            drive_service.create_team_drive(claims.user_id, request.name, vec![]).await?
+        DriveType::Team => {
+            drive_service.create_team_drive(user_id, request.name, vec![]).await?
         }
         _ => {
-            let mut drive = Drive::new(claims.user_id, request.name, request.drive_type);
+            let mut drive = Drive::new(user_id, request.name, request.drive_type);
             if let Some(description) = request.description {
                 drive.description = Some(description);
             }
@@ -55,23 +58,27 @@ pub async fn create_drive(
 pub async fn get_user_drives(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
-) -> ApiResult<Json<Vec<Drive>>> {
-    let drives = state.drive_service.get_user_drives(claims.user_id).await?;
+    grant: Option<Extension<ApiTokenGrant>>,
+) -> Result<Json<Vec<Drive>>> {
+    let user_id = claims.user_id()?;
+    grant.require_scope(ApiScope::FilesRead)?;
+    let drives = state.drive_service.get_user_drives(user_id).await?;
     Ok(Json(drives))
 }
 
 pub async fn get_drive(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
+    grant: Option<Extension<ApiTokenGrant>>,
     Path(drive_id): Path<Id>,
-) -> ApiResult<Json<Drive>> {
+) -> Result<Json<Drive>> {
+    let user_id = claims.user_id()?;
     let drive = state.drive_repository.get_drive_by_id(drive_id).await?
-        .ok_or(ApiError::NotFound("Drive not found".to_string()))?;
+        .ok_or_else(|| Error::NotFound("Drive not found".to_string()))?;
+    grant.require_drive_scope(ApiScope::FilesRead, drive.id)?;
     
     // Check permissions
-    if drive.owner_id != claims.user_id && !drive.is_shared {
-        return Err(ApiError::Forbidden("Access denied".to_string()));
-    }
+    authorize_drive(&state, user_id, &drive, Permission::DriveRead).await?;
 
     Ok(Json(drive))
 }
@@ -79,15 +86,16 @@ pub async fn get_drive(
 pub async fn update_drive(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
+    grant: Option<Extension<ApiTokenGrant>>,
     Path(drive_id): Path<Id>,
     Json(updates): Json<HashMap<String, serde_json::Value>>,
-) -> ApiResult<Json<Drive>> {
+) -> Result<Json<Drive>> {
+    let user_id = claims.user_id()?;
     let mut drive = state.drive_repository.get_drive_by_id(drive_id).await?
-        .ok_or(ApiError::NotFound("Drive not found".to_string()))?;
+        .ok_or_else(|| Error::NotFound("Drive not found".to_string()))?;
+    grant.require_drive_scope(ApiScope::FilesWrite, drive.id)?;
     
-    if drive.owner_id != claims.user_id {
-        return Err(ApiError::Forbidden("Access denied".to_string()));
-    }
+    authorize_drive(&state, user_id, &drive, Permission::DriveManage).await?;
 
     // Apply updates
     if let Some(name) = updates.get("name").and_then(|v| v.as_str()) {
@@ -106,14 +114,15 @@ pub async fn update_drive(
 pub async fn delete_drive(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
+    grant: Option<Extension<ApiTokenGrant>>,
     Path(drive_id): Path<Id>,
-) -> ApiResult<StatusCode> {
+) -> Result<StatusCode> {
+    let user_id = claims.user_id()?;
     let drive = state.drive_repository.get_drive_by_id(drive_id).await?
-        .ok_or(ApiError::NotFound("Drive not found".to_string()))?;
+        .ok_or_else(|| Error::NotFound("Drive not found".to_string()))?;
+    grant.require_drive_scope(ApiScope::FilesWrite, drive.id)?;
     
-    if drive.owner_id != claims.user_id {
-        return Err(ApiError::Forbidden("Access denied".to_string()));
-    }
+    authorize_drive(&state, user_id, &drive, Permission::DriveManage).await?;
 
     state.drive_repository.delete_drive(drive_id).await?;
     Ok(StatusCode::NO_CONTENT)
@@ -123,26 +132,27 @@ pub async fn delete_drive(
 pub async fn create_folder(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
+    grant: Option<Extension<ApiTokenGrant>>,
     Path(drive_id): Path<Id>,
     Json(request): Json<CreateFolderRequest>,
-) -> ApiResult<Json<Folder>> {
-    request.validate().map_err(ApiError::ValidationError)?;
+) -> Result<Json<Folder>> {
+    let user_id = claims.user_id()?;
+    request.validate().map_err(|e| Error::Validation(e.to_string()))?;
     
     // Verify drive access
     let drive = state.drive_repository.get_drive_by_id(drive_id).await?
-        .ok_or(ApiError::NotFound("Drive not found".to_string()))?;
+        .ok_or_else(|| Error::NotFound("Drive not found".to_string()))?;
+    grant.require_drive_scope(ApiScope::FilesWrite, drive.id)?;
     
-    if drive.owner_id != claims.user_id && !drive.is_shared {
-        return Err(ApiError::Forbidden("Access denied".to_string()));
-    }
+    authorize_drive(&state, user_id, &drive, Permission::DriveWrite).await?;
 
-    let folder = Folder::new(drive_id, claims.user_id, request.name, request.parent_id);
+    let folder = Folder::new(drive_id, user_id, request.name, request.parent_id);
     let created_folder = state.drive_repository.create_folder(folder).await?;
 
     // Log activity
     let activity = DriveActivity::new(
         drive_id,
-        claims.user_id,
+        user_id,
         ActivityType::Create,
         created_folder.name.clone(),
         "Created folder".to_string(),
@@ -156,21 +166,56 @@ pub async fn create_folder(
 pub async fn get_folder_contents(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
-    Path((drive_id, folder_id)): Path<(Id, Option<Id>)>,
+    grant: Option<Extension<ApiTokenGrant>>,
+    Path((drive_id, folder_id)): Path<(Id, Id)>,
+    Query(params): Query<FolderContentsQuery>,
+) -> Result<Json<FolderContents>> {
+    let user_id = claims.user_id()?;
+    grant.require_drive_scope(ApiScope::FilesRead, drive_id)?;
+    folder_contents(&state, user_id, drive_id, Some(folder_id), params).await
+}
+
+/// What sits at the top of the drive, outside any folder
+pub async fn get_drive_contents(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    grant: Option<Extension<ApiTokenGrant>>,
+    Path(drive_id): Path<Id>,
     Query(params): Query<FolderContentsQuery>,
-) -> ApiResult<Json<FolderContents>> {
+) -> Result<Json<FolderContents>> {
+    let user_id = claims.user_id()?;
+    grant.require_drive_scope(ApiScope::FilesRead, drive_id)?;
+    folder_contents(&state, user_id, drive_id, None, params).await
+}
+
+async fn folder_contents(
+    state: &AppState,
+    user_id: Id,
+    drive_id: Id,
+    folder_id: Option<Id>,
+    params: FolderContentsQuery,
+) -> Result<Json<FolderContents>> {
     // Verify drive access
     let drive = state.drive_repository.get_drive_by_id(drive_id).await?
-        .ok_or(ApiError::NotFound("Drive not found".to_string()))?;
+        .ok_or_else(|| Error::NotFound("Drive not found".to_string()))?;
     
-    if drive.owner_id != claims.user_id && !drive.is_shared {
-        return Err(ApiError::Forbidden("Access denied".to_string()));
+    match folder_id {
+        // A folder shared with the user can be browsed without access to the drive
+        Some(folder_id) => {
+            let folder_item = state.drive_repository.get_drive_item_by_id(folder_id).await?
+                .filter(|item| item.drive_id == drive.id)
+                .ok_or_else(|| Error::NotFound("Folder not found".to_string()))?;
+            state.authorization_service
+                .require_item(user_id, &folder_item, ItemAccess::View)
+                .await?;
+        }
+        None => authorize_drive(state, user_id, &drive, Permission::DriveRead).await?,
     }
 
     let contents = state.drive_repository.get_folder_contents(
         folder_id,
         drive_id,
-        params.limit.unwrap_or(50),
+        params.limit.unwrap_or(50).min(200),
         params.offset.unwrap_or(0),
         params.sort_by,
         params.sort_order,
@@ -182,17 +227,18 @@ pub async fn get_folder_contents(
 pub async fn update_folder(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
+    grant: Option<Extension<ApiTokenGrant>>,
     Path(folder_id): Path<Id>,
     Json(request): Json<UpdateFolderRequest>,
-) -> ApiResult<Json<Folder>> {
-    request.validate().map_err(ApiError::ValidationError)?;
+) -> Result<Json<Folder>> {
+    let user_id = claims.user_id()?;
+    request.validate().map_err(|e| Error::Validation(e.to_string()))?;
     
     let mut folder = state.drive_repository.get_folder_by_id(folder_id).await?
-        .ok_or(ApiError::NotFound("Folder not found".to_string()))?;
+        .ok_or_else(|| Error::NotFound("Folder not found".to_string()))?;
+    grant.require_drive_scope(ApiScope::FilesWrite, folder.drive_id)?;
     
-    if folder.permissions.owner_id != claims.user_id {
-        return Err(ApiError::Forbidden("Access denied".to_string()));
-    }
+    authorize_folder(&state, user_id, &folder).await?;
 
     // Apply updates
     if let Some(name) = request.name {
@@ -207,6 +253,15 @@ pub async fn update_folder(
     if let Some(parent_id) = request.parent_id {
         folder.parent_id = Some(parent_id);
     }
+    // Restricting a folder changes who can reach what is in it
+    if let Some(inherit_permissions) = request.inherit_permissions {
+        let folder_item = state.drive_repository.get_drive_item_by_id(folder_id).await?
+            .ok_or_else(|| Error::NotFound("Folder not found".to_string()))?;
+        state.authorization_service
+            .require_item(user_id, &folder_item, ItemAccess::Share)
+            .await?;
+        folder.permissions.inherit_permissions = inherit_permissions;
+    }
 
     folder.updated_at = chrono::Utc::now();
     let updated_folder = state.drive_repository.update_folder(folder).await?;
@@ -217,14 +272,15 @@ pub async fn update_folder(
 pub async fn delete_folder(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
+    grant: Option<Extension<ApiTokenGrant>>,
     Path(folder_id): Path<Id>,
-) -> ApiResult<StatusCode> {
+) -> Result<StatusCode> {
+    let user_id = claims.user_id()?;
     let folder = state.drive_repository.get_folder_by_id(folder_id).await?
-        .ok_or(ApiError::NotFound("Folder not found".to_string()))?;
+        .ok_or_else(|| Error::NotFound("Folder not found".to_string()))?;
+    grant.require_drive_scope(ApiScope::FilesWrite, folder.drive_id)?;
     
-    if folder.permissions.owner_id != claims.user_id {
-        return Err(ApiError::Forbidden("Access denied".to_string()));
-    }
+    authorize_folder(&state, user_id, &folder).await?;
 
     state.drive_repository.delete_folder(folder_id).await?;
     Ok(StatusCode::NO_CONTENT)
@@ -234,67 +290,121 @@ pub async fn delete_folder(
 pub async fn get_drive_item(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
+    grant: Option<Extension<ApiTokenGrant>>,
     Path(item_id): Path<Id>,
-) -> ApiResult<Json<DriveItem>> {
+) -> Result<Json<DriveItem>> {
+    let user_id = claims.user_id()?;
     let item = state.drive_repository.get_drive_item_by_id(item_id).await?
-        .ok_or(ApiError::NotFound("Item not found".to_string()))?;
+        .ok_or_else(|| Error::NotFound("Item not found".to_string()))?;
+    grant.require_drive_scope(ApiScope::FilesRead, item.drive_id)?;
     
-    if !item.can_user_access(claims.user_id, "view") {
-        return Err(ApiError::Forbidden("Access denied".to_string()));
-    }
+    state.authorization_service
+        .require_item(user_id, &item, ItemAccess::View)
+        .await?;
 
     Ok(Json(item))
 }
 
+/// Why a user may use an item; the caller's own access unless `user_id`
+/// names someone else, which needs share access to the item
+pub async fn explain_item_access(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    grant: Option<Extension<ApiTokenGrant>>,
+    Path(item_id): Path<Id>,
+    Query(params): Query<ItemAccessQuery>,
+) -> Result<Json<AccessExplanation>> {
+    let user_id = claims.user_id()?;
+    let item = state.drive_repository.get_drive_item_by_id(item_id).await?
+        .ok_or_else(|| Error::NotFound("Item not found".to_string()))?;
+    grant.require_drive_scope(ApiScope::FilesRead, item.drive_id)?;
+
+    let subject_id = params.user_id.unwrap_or(user_id);
+    let access = if subject_id == user_id { ItemAccess::View } else { ItemAccess::Share };
+    state.authorization_service
+        .require_item(user_id, &item, access)
+        .await?;
+
+    let explanation = state.authorization_service.explain_item_access(subject_id, &item).await?;
+    Ok(Json(explanation))
+}
+
 pub async fn move_drive_item(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
+    grant: Option<Extension<ApiTokenGrant>>,
     Path(item_id): Path<Id>,
     Json(request): Json<MoveItemRequest>,
-) -> ApiResult<StatusCode> {
+) -> Result<StatusCode> {
+    let user_id = claims.user_id()?;
     let item = state.drive_repository.get_drive_item_by_id(item_id).await?
-        .ok_or(ApiError::NotFound("Item not found".to_string()))?;
+        .ok_or_else(|| Error::NotFound("Item not found".to_string()))?;
+    grant.require_drive_scope(ApiScope::FilesWrite, item.drive_id)?;
     
-    if !item.can_user_access(claims.user_id, "edit") {
-        return Err(ApiError::Forbidden("Access denied".to_string()));
-    }
+    state.authorization_service
+        .require_item(user_id, &item, ItemAccess::Edit)
+        .await?;
 
-    state.drive_service.move_item_to_folder(item_id, request.new_parent_id, claims.user_id).await?;
+    state.drive_service.move_item_to_folder(item_id, request.new_parent_id, user_id).await?;
     Ok(StatusCode::NO_CONTENT)
 }
 
 pub async fn copy_drive_item(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
+    grant: Option<Extension<ApiTokenGrant>>,
     Path(item_id): Path<Id>,
     Json(request): Json<CopyItemRequest>,
-) -> ApiResult<Json<DriveItem>> {
+) -> Result<Json<DriveItem>> {
+    let user_id = claims.user_id()?;
     let item = state.drive_repository.get_drive_item_by_id(item_id).await?
-        .ok_or(ApiError::NotFound("Item not found".to_string()))?;
+        .ok_or_else(|| Error::NotFound("Item not found".to_string()))?;
+    grant.require_drive_scope(ApiScope::FilesWrite, item.drive_id)?;
     
-    if !item.can_user_access(claims.user_id, "view") {
-        return Err(ApiError::Forbidden("Access denied".to_string()));
-    }
+    state.authorization_service
+        .require_item(user_id, &item, ItemAccess::View)
+        .await?;
 
-    let copied_item = state.drive_service.duplicate_item(item_id, request.new_name, claims.user_id).await?;
+    // The copy belongs to the caller, so the repository charges it to their quota
+    let copied_item = state.drive_service.duplicate_item(item_id, request.new_name, user_id).await?;
     Ok(Json(copied_item))
 }
 
 pub async fn star_item(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
+    grant: Option<Extension<ApiTokenGrant>>,
     Path(item_id): Path<Id>,
-) -> ApiResult<StatusCode> {
-    state.drive_repository.star_item(item_id, claims.user_id).await?;
+) -> Result<StatusCode> {
+    let user_id = claims.user_id()?;
+    let item = state.drive_repository.get_drive_item_by_id(item_id).await?
+        .ok_or_else(|| Error::NotFound("Item not found".to_string()))?;
+    grant.require_drive_scope(ApiScope::FilesRead, item.drive_id)?;
+    
+    state.authorization_service
+        .require_item(user_id, &item, ItemAccess::View)
+        .await?;
+
+    state.drive_repository.star_item(item_id, user_id).await?;
     Ok(StatusCode::NO_CONTENT)
 }
 
 pub async fn unstar_item(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
+    grant: Option<Extension<ApiTokenGrant>>,
     Path(item_id): Path<Id>,
-) -> ApiResult<StatusCode> {
-    state.drive_repository.unstar_item(item_id, claims.user_id).await?;
+) -> Result<StatusCode> {
+    let user_id = claims.user_id()?;
+    let item = state.drive_repository.get_drive_item_by_id(item_id).await?
+        .ok_or_else(|| Error::NotFound("Item not found".to_string()))?;
+    grant.require_drive_scope(ApiScope::FilesRead, item.drive_id)?;
+    
+    state.authorization_service
+        .require_item(user_id, &item, ItemAccess::View)
+        .await?;
+
+    state.drive_repository.unstar_item(item_id, user_id).await?;
     Ok(StatusCode::NO_CONTENT)
 }
 
@@ -302,15 +412,19 @@ pub async fn unstar_item(
 pub async fn move_to_trash(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
+    grant: Option<Extension<ApiTokenGrant>>,
     Path(item_id): Path<Id>,
-) -> ApiResult<StatusCode> {
+) -> Result<StatusCode> {
+    let user_id = claims.user_id()?;
     let item = state.drive_repository.get_drive_item_by_id(item_id).await?
-        .ok_or(ApiError::NotFound("Item not found".to_string()))?;
+        .ok_or_else(|| Error::NotFound("Item not found".to_string()))?;
+    grant.require_drive_scope(ApiScope::FilesWrite, item.drive_id)?;
     
-    if !item.can_user_access(claims.user_id, "edit") {
-        return Err(ApiError::Forbidden("Access denied".to_string()));
-    }
+    state.authorization_service
+        .require_item(user_id, &item, ItemAccess::Edit)
+        .await?;
 
+    // Trashed items stop counting against quotas until they are restored
     state.drive_repository.move_to_trash(item_id).await?;
     Ok(StatusCode::NO_CONTENT)
 }
@@ -318,14 +432,17 @@ pub async fn move_to_trash(
 pub async fn restore_from_trash(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
+    grant: Option<Extension<ApiTokenGrant>>,
     Path(item_id): Path<Id>,
-) -> ApiResult<StatusCode> {
+) -> Result<StatusCode> {
+    let user_id = claims.user_id()?;
     let item = state.drive_repository.get_drive_item_by_id(item_id).await?
-        .ok_or(ApiError::NotFound("Item not found".to_string()))?;
+        .ok_or_else(|| Error::NotFound("Item not found".to_string()))?;
+    grant.require_drive_scope(ApiScope::FilesWrite, item.drive_id)?;
     
-    if !item.can_user_access(claims.user_id, "edit") {
-        return Err(ApiError::Forbidden("Access denied".to_string()));
-    }
+    state.authorization_service
+        .require_item(user_id, &item, ItemAccess::Edit)
+        .await?;
 
     state.drive_repository.restore_from_trash(item_id).await?;
     Ok(StatusCode::NO_CONTENT)
@@ -334,14 +451,15 @@ pub async fn restore_from_trash(
 pub async fn get_trash_items(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
+    grant: Option<Extension<ApiTokenGrant>>,
     Path(drive_id): Path<Id>,
-) -> ApiResult<Json<Vec<DriveItem>>> {
+) -> Result<Json<Vec<DriveItem>>> {
+    let user_id = claims.user_id()?;
     let drive = state.drive_repository.get_drive_by_id(drive_id).await?
-        .ok_or(ApiError::NotFound("Drive not found".to_string()))?;
+        .ok_or_else(|| Error::NotFound("Drive not found".to_string()))?;
+    grant.require_drive_scope(ApiScope::FilesRead, drive.id)?;
     
-    if drive.owner_id != claims.user_id && !drive.is_shared {
-        return Err(ApiError::Forbidden("Access denied".to_string()));
-    }
+    authorize_drive(&state, user_id, &drive, Permission::DriveRead).await?;
 
     let trash_items = state.drive_repository.get_trash_items(drive_id).await?;
     Ok(Json(trash_items))
@@ -350,14 +468,15 @@ pub async fn get_trash_items(
 pub async fn empty_trash(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
+    grant: Option<Extension<ApiTokenGrant>>,
     Path(drive_id): Path<Id>,
-) -> ApiResult<StatusCode> {
+) -> Result<StatusCode> {
+    let user_id = claims.user_id()?;
     let drive = state.drive_repository.get_drive_by_id(drive_id).await?
-        .ok_or(ApiError::NotFound("Drive not found".to_string()))?;
+        .ok_or_else(|| Error::NotFound("Drive not found".to_string()))?;
+    grant.require_drive_scope(ApiScope::FilesWrite, drive.id)?;
     
-    if drive.owner_id != claims.user_id {
-        return Err(ApiError::Forbidden("Access denied".to_string()));
-    }
+    authorize_drive(&state, user_id, &drive, Permission::DriveManage).await?;
 
     state.drive_repository.empty_trash(drive_id).await?;
     Ok(StatusCode::NO_CONTENT)
@@ -367,23 +486,26 @@ pub async fn empty_trash(
 pub async fn share_item(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
+    grant: Option<Extension<ApiTokenGrant>>,
     Path(item_id): Path<Id>,
     Json(request): Json<ShareItemRequest>,
-) -> ApiResult<StatusCode> {
-    request.validate().map_err(ApiError::ValidationError)?;
+) -> Result<StatusCode> {
+    let user_id = claims.user_id()?;
+    request.validate().map_err(|e| Error::Validation(e.to_string()))?;
     
     let item = state.drive_repository.get_drive_item_by_id(item_id).await?
-        .ok_or(ApiError::NotFound("Item not found".to_string()))?;
+        .ok_or_else(|| Error::NotFound("Item not found".to_string()))?;
+    grant.require_drive_scope(ApiScope::SharesManage, item.drive_id)?;
     
-    if !item.can_user_access(claims.user_id, "share") {
-        return Err(ApiError::Forbidden("Access denied".to_string()));
-    }
+    state.authorization_service
+        .require_item(user_id, &item, ItemAccess::Share)
+        .await?;
 
     state.drive_service.share_with_users(
         item_id,
         request.user_ids,
         format!("{:?}", request.role),
-        claims.user_id,
+        user_id,
     ).await?;
 
     Ok(StatusCode::NO_CONTENT)
@@ -392,22 +514,25 @@ pub async fn share_item(
 pub async fn create_sharing_link(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
+    grant: Option<Extension<ApiTokenGrant>>,
     Path(item_id): Path<Id>,
     Json(request): Json<CreateSharingLinkRequest>,
-) -> ApiResult<Json<SharingLinkResponse>> {
-    request.validate().map_err(ApiError::ValidationError)?;
+) -> Result<Json<SharingLinkResponse>> {
+    let user_id = claims.user_id()?;
+    request.validate().map_err(|e| Error::Validation(e.to_string()))?;
     
     let item = state.drive_repository.get_drive_item_by_id(item_id).await?
-        .ok_or(ApiError::NotFound("Item not found".to_string()))?;
+        .ok_or_else(|| Error::NotFound("Item not found".to_string()))?;
+    grant.require_drive_scope(ApiScope::SharesManage, item.drive_id)?;
     
-    if !item.can_user_access(claims.user_id, "share") {
-        return Err(ApiError::Forbidden("Access denied".to_string()));
-    }
+    state.authorization_service
+        .require_item(user_id, &item, ItemAccess::Share)
+        .await?;
 
     let link = state.drive_service.share_with_link(
         item_id,
         format!("{:?}", request.access_level),
-        claims.user_id,
+        user_id,
     ).await?;
 
     Ok(Json(SharingLinkResponse { link }))
@@ -416,14 +541,17 @@ pub async fn create_sharing_link(
 pub async fn revoke_sharing_link(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
+    grant: Option<Extension<ApiTokenGrant>>,
     Path(item_id): Path<Id>,
-) -> ApiResult<StatusCode> {
+) -> Result<StatusCode> {
+    let user_id = claims.user_id()?;
     let item = state.drive_repository.get_drive_item_by_id(item_id).await?
-        .ok_or(ApiError::NotFound("Item not found".to_string()))?;
+        .ok_or_else(|| Error::NotFound("Item not found".to_string()))?;
+    grant.require_drive_scope(ApiScope::SharesManage, item.drive_id)?;
     
-    if !item.can_user_access(claims.user_id, "share") {
-        return Err(ApiError::Forbidden("Access denied".to_string()));
-    }
+    state.authorization_service
+        .require_item(user_id, &item, ItemAccess::Share)
+        .await?;
 
     state.drive_repository.revoke_sharing_link(item_id).await?;
     Ok(StatusCode::NO_CONTENT)
@@ -433,60 +561,119 @@ pub async fn revoke_sharing_link(
 pub async fn search_drive(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
+    grant: Option<Extension<ApiTokenGrant>>,
     Path(drive_id): Path<Id>,
     Query(params): Query<SearchQuery>,
-) -> ApiResult<Json<Vec<DriveItemResponse>>> {
+) -> Result<Json<Vec<SearchResult>>> {
+    let user_id = claims.user_id()?;
     let drive = state.drive_repository.get_drive_by_id(drive_id).await?
-        .ok_or(ApiError::NotFound("Drive not found".to_string()))?;
-    
-    if drive.owner_id != claims.user_id && !drive.is_shared {
-        return Err(ApiError::Forbidden("Access denied".to_string()));
-    }
-
-    let query = params.q.unwrap_or_default();
-    let results = state.drive_service.search_items(drive_id, &query, claims.user_id).await?;
+        .ok_or_else(|| Error::NotFound("Drive not found".to_string()))?;
+    grant.require_drive_scope(ApiScope::FilesRead, drive.id)?;
+
+    let query = DriveQuery::parse(params.q.as_deref().unwrap_or_default())?;
+    // Without access to the whole drive only what is shared with the user is found
+    let resource = Resource::owned_by(drive.owner_id).in_drive(drive.id);
+    let readable_drives = state.authorization_service.readable_drives(user_id, &[resource]).await?;
+    let filters = SearchFilters {
+        item_type: params.item_type,
+        mime_type: params.file_type,
+        owner_id: params.owner_id,
+        modified_after: params.modified_after,
+        modified_before: params.modified_before,
+        starred: params.starred,
+        shared_with_me: params.shared_with_me.unwrap_or(false),
+        trashed: params.trashed.unwrap_or(false),
+        readable_drives: Some(readable_drives),
+    };
+    let results = state.drive_service.search_items(drive_id, &query, filters, user_id).await?;
     Ok(Json(results))
 }
 
 pub async fn get_recent_items(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
+    grant: Option<Extension<ApiTokenGrant>>,
     Query(params): Query<RecentItemsQuery>,
-) -> ApiResult<Json<Vec<DriveItem>>> {
+) -> Result<Json<Vec<DriveItem>>> {
+    let user_id = claims.user_id()?;
+    grant.require_scope(ApiScope::FilesRead)?;
     let limit = params.limit.unwrap_or(20).min(100);
-    let items = state.drive_repository.get_recent_items(claims.user_id, limit).await?;
+    let items = match params.q.as_deref() {
+        Some(query) => {
+            let mut items = search_collection(&state, SearchScope::Recent, query, user_id).await?;
+            items.truncate(limit as usize);
+            items
+        }
+        None => state.drive_repository.get_recent_items(user_id, limit).await?,
+    };
     Ok(Json(items))
 }
 
 pub async fn get_starred_items(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
-) -> ApiResult<Json<Vec<DriveItem>>> {
-    let items = state.drive_repository.get_starred_items(claims.user_id).await?;
+    grant: Option<Extension<ApiTokenGrant>>,
+    Query(params): Query<CollectionQuery>,
+) -> Result<Json<Vec<DriveItem>>> {
+    let user_id = claims.user_id()?;
+    grant.require_scope(ApiScope::FilesRead)?;
+    let items = match params.q.as_deref() {
+        Some(query) => search_collection(&state, SearchScope::Starred, query, user_id).await?,
+        None => state.drive_repository.get_starred_items(user_id).await?,
+    };
     Ok(Json(items))
 }
 
 pub async fn get_shared_with_me(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
-) -> ApiResult<Json<Vec<DriveItem>>> {
-    let items = state.drive_repository.get_shared_with_me(claims.user_id).await?;
+    grant: Option<Extension<ApiTokenGrant>>,
+    Query(params): Query<CollectionQuery>,
+) -> Result<Json<Vec<DriveItem>>> {
+    let user_id = claims.user_id()?;
+    grant.require_scope(ApiScope::FilesRead)?;
+    let items = match params.q.as_deref() {
+        Some(query) => search_collection(&state, SearchScope::SharedWithMe, query, user_id).await?,
+        None => state.drive_repository.get_shared_with_me(user_id).await?,
+    };
     Ok(Json(items))
 }
 
+/// The items of one of the user's collections that match a query, best
+/// first. Collections can name items the user no longer sees, e.g. starred
+/// before a share was revoked, so results are limited to what they can read.
+async fn search_collection(state: &AppState, scope: SearchScope, query: &str, user_id: Id) -> Result<Vec<DriveItem>> {
+    let query = DriveQuery::parse(query)?;
+    let drives = state.drive_repository.get_search_scope_drives(scope, user_id).await?;
+    let resources: Vec<Resource> = drives
+        .iter()
+        .map(|drive| Resource::owned_by(drive.owner_id).in_drive(drive.id))
+        .collect();
+    let filters = SearchFilters {
+        readable_drives: Some(state.authorization_service.readable_drives(user_id, &resources).await?),
+        ..Default::default()
+    };
+    let matches = state
+        .drive_repository
+        .search_drive_items(scope, user_id, &query, &filters)
+        .await?;
+    Ok(matches.into_iter().map(|found| found.item).collect())
+}
+
 // Analytics endpoints
 pub async fn get_drive_activity(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
+    grant: Option<Extension<ApiTokenGrant>>,
     Path(drive_id): Path<Id>,
     Query(params): Query<ActivityQuery>,
-) -> ApiResult<Json<Vec<DriveActivity>>> {
+) -> Result<Json<Vec<DriveActivity>>> {
+    let user_id = claims.user_id()?;
     let drive = state.drive_repository.get_drive_by_id(drive_id).await?
-        .ok_or(ApiError::NotFound("Drive not found".to_string()))?;
+        .ok_or_else(|| Error::NotFound("Drive not found".to_string()))?;
+    grant.require_drive_scope(ApiScope::FilesRead, drive.id)?;
     
-    if drive.owner_id != claims.user_id && !drive.is_shared {
-        return Err(ApiError::Forbidden("Access denied".to_string()));
-    }
+    authorize_drive(&state, user_id, &drive, Permission::DriveRead).await?;
 
     let limit = params.limit.unwrap_or(50);
     let offset = params.offset.unwrap_or(0);
@@ -498,14 +685,15 @@ pub async fn get_drive_activity(
 pub async fn get_storage_usage(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
+    grant: Option<Extension<ApiTokenGrant>>,
     Path(drive_id): Path<Id>,
-) -> ApiResult<Json<StorageUsageResponse>> {
+) -> Result<Json<StorageUsageResponse>> {
+    let user_id = claims.user_id()?;
     let drive = state.drive_repository.get_drive_by_id(drive_id).await?
-        .ok_or(ApiError::NotFound("Drive not found".to_string()))?;
+        .ok_or_else(|| Error::NotFound("Drive not found".to_string()))?;
+    grant.require_drive_scope(ApiScope::FilesRead, drive.id)?;
     
-    if drive.owner_id != claims.user_id && !drive.is_shared {
-        return Err(ApiError::Forbidden("Access denied".to_string()));
-    }
+    authorize_drive(&state, user_id, &drive, Permission::DriveRead).await?;
 
     let (used, total) = state.drive_service.get_drive_quota_usage(drive_id).await?;
     let usage_by_type = state.drive_repository.get_storage_usage_by_type(drive_id).await?;
@@ -519,6 +707,18 @@ pub async fn get_storage_usage(
     }))
 }
 
+/// Allow `permission` on the drive, through ownership or a role covering it
+async fn authorize_drive(state: &AppState, user_id: Id, drive: &Drive, permission: Permission) -> Result<()> {
+    let resource = Resource::owned_by(drive.owner_id).in_drive(drive.id);
+    state.authorization_service.require(user_id, permission, &resource).await
+}
+
+/// Changing a folder needs write access to its drive, or to be its owner
+async fn authorize_folder(state: &AppState, user_id: Id, folder: &Folder) -> Result<()> {
+    let resource = Resource::owned_by(folder.permissions.owner_id).in_drive(folder.drive_id);
+    state.authorization_service.require(user_id, Permission::DriveWrite, &resource).await
+}
+
 // Request/Response DTOs
 #[derive(Debug, Deserialize)]
 pub struct FolderContentsQuery {
@@ -528,6 +728,11 @@ pub struct FolderContentsQuery {
     pub sort_order: Option<String>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct ItemAccessQuery {
+    pub user_id: Option<Id>,
+}
+
 #[derive(Debug, Deserialize, Validate)]
 pub struct MoveItemRequest {
     pub new_parent_id: Option<Id>,
@@ -541,15 +746,30 @@ pub struct CopyItemRequest {
 
 #[derive(Debug, Deserialize)]
 pub struct SearchQuery {
+    /// In the search query language, such as `type:spreadsheet owner:me budget`
     pub q: Option<String>,
+    pub item_type: Option<DriveItemType>,
+    /// A MIME type or its start, such as `image/`
     pub file_type: Option<String>,
-    pub modified_after: Option<String>,
-    pub modified_before: Option<String>,
+    pub owner_id: Option<Id>,
+    pub modified_after: Option<Timestamp>,
+    pub modified_before: Option<Timestamp>,
+    pub starred: Option<bool>,
+    pub shared_with_me: Option<bool>,
+    pub trashed: Option<bool>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct RecentItemsQuery {
     pub limit: Option<u32>,
+    /// In the search query language, as for drive search
+    pub q: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CollectionQuery {
+    /// In the search query language, as for drive search
+    pub q: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -1,16 +1,22 @@
 use axum::{
     extract::{Multipart, Path, Query, Request, State},
-    http::{header, StatusCode},
     response::Response,
     Json,
 };
 use kingshare_application::services::UserStorageStats;
 use kingshare_core::{ApiResponse, Id, PaginationParams, Result};
-use kingshare_domain::{entities::UpdateFileRequest, FileMetadata};
+use kingshare_domain::{
+    entities::{ApiScope, Permission, Resource, ThumbnailSize, UpdateFileRequest},
+    FileMetadata,
+};
 use serde::Deserialize;
 use tracing::{info, instrument, warn};
 use validator::Validate;
-use crate::{middleware::auth::ClaimsExt, server::AppState};
+use crate::{
+    handlers::download::{self, DownloadInfo, DownloadPlan},
+    middleware::auth::ClaimsExt,
+    server::AppState,
+};
 
 #[derive(Debug, Deserialize)]
 pub struct FileListQuery {
@@ -40,6 +46,11 @@ pub async fn list_files(
         // User files require authentication
         let user_id = request.require_user_id()
             .map_err(|_| kingshare_core::Error::Authentication("Authentication required".to_string()))?;
+        request.require_scope(ApiScope::FilesRead)?;
+        state
+            .authorization_service
+            .require(user_id, Permission::FilesRead, &Resource::owned_by(user_id))
+            .await?;
         state.file_service.list_user_files(user_id, query.pagination).await?
     };
 
@@ -62,6 +73,12 @@ pub async fn upload_file(
     // Extract user ID from JWT claims
     let user_id = request.require_user_id()
         .map_err(|_| kingshare_core::Error::Authentication("Authentication required".to_string()))?;
+    request.require_scope(ApiScope::FilesWrite)?;
+    // Guests can look but not add
+    state
+        .authorization_service
+        .require(user_id, Permission::FilesWrite, &Resource::owned_by(user_id))
+        .await?;
 
     let mut filename = None;
     let mut content_type = None;
@@ -141,6 +158,7 @@ pub async fn update_file(
     // Extract user ID from JWT claims
     let user_id = request.require_user_id()
         .map_err(|_| kingshare_core::Error::Authentication("Authentication required".to_string()))?;
+    request.require_scope(ApiScope::FilesWrite)?;
 
     // Create update request
     let update_request = UpdateFileRequest {
@@ -170,6 +188,7 @@ pub async fn delete_file(
     // Extract user ID from JWT claims
     let user_id = request.require_user_id()
         .map_err(|_| kingshare_core::Error::Authentication("Authentication required".to_string()))?;
+    request.require_scope(ApiScope::FilesWrite)?;
 
     // Delete file using the service from app state
     state.file_service.delete_file(id, user_id).await?;
@@ -191,31 +210,74 @@ pub async fn download_file(
 ) -> Result<Response> {
     // Extract user ID from JWT claims (optional for public files)
     let user_id = request.user_id();
+    request.require_scope(ApiScope::FilesRead)?;
+
+    // Check access before evaluating validators so 304s don't leak private files
+    let file = state.file_service.authorize_download(id, user_id).await?;
+    let download_info = DownloadInfo::new(
+        &file.original_filename,
+        &file.content_type,
+        file.size,
+        &file.checksum,
+        file.updated_at,
+    );
+
+    let ranges = match download::plan_download(request.headers(), &download_info) {
+        DownloadPlan::NotModified => return download::not_modified_response(&download_info),
+        DownloadPlan::Unsatisfiable => {
+            return download::range_not_satisfiable_response(&download_info)
+        }
+        DownloadPlan::Full => Vec::new(),
+        DownloadPlan::Partial(ranges) => ranges,
+    };
 
-    // Download file using the service from app state
-    let (file, file_data) = state.file_service.download_file(id, user_id).await?;
+    let (file, streams) = state.file_service.open_download(file, user_id, &ranges).await?;
 
     info!(
         file_id = %id,
-        user_id = %user_id,
+        user_id = ?user_id,
         filename = %file.filename,
-        size = file_data.len(),
+        size = file.size,
+        range_count = ranges.len(),
         "File downloaded"
     );
 
-    // Create response with appropriate headers
-    let response = Response::builder()
-        .status(StatusCode::OK)
-        .header(header::CONTENT_TYPE, file.content_type)
-        .header(
-            header::CONTENT_DISPOSITION,
-            format!("attachment; filename=\"{}\"", file.original_filename),
-        )
-        .header(header::CONTENT_LENGTH, file_data.len())
-        .body(file_data.into())
-        .map_err(|e| kingshare_core::Error::Internal(format!("Failed to create response: {}", e)))?;
-
-    Ok(response)
+    download::stream_response(&download_info, &ranges, streams)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ThumbnailQuery {
+    pub size: Option<String>,
+}
+
+#[instrument(skip(state, request))]
+pub async fn get_thumbnail(
+    State(state): State<AppState>,
+    Path(id): Path<Id>,
+    Query(query): Query<ThumbnailQuery>,
+    request: Request,
+) -> Result<Response> {
+    let user_id = request.user_id();
+    request.require_scope(ApiScope::FilesRead)?;
+    let size = match query.size.as_deref() {
+        Some(size) => size.parse::<ThumbnailSize>()?,
+        None => ThumbnailSize::default(),
+    };
+
+    let (thumbnail, stream) = state.file_service.open_thumbnail(id, user_id, size).await?;
+    let download_info = DownloadInfo::new(
+        &format!("thumbnail-{}", size.as_str()),
+        &thumbnail.content_type,
+        thumbnail.byte_size,
+        &thumbnail.thumbnail_checksum,
+        thumbnail.created_at,
+    );
+
+    if download::is_not_modified(request.headers(), &download_info) {
+        return download::not_modified_response(&download_info);
+    }
+
+    download::inline_response(&download_info, stream)
 }
 
 #[instrument(skip(state, request))]
@@ -226,6 +288,11 @@ pub async fn get_storage_stats(
     // Extract user ID from JWT claims
     let user_id = request.require_user_id()
         .map_err(|_| kingshare_core::Error::Authentication("Authentication required".to_string()))?;
+    request.require_scope(ApiScope::FilesRead)?;
+    state
+        .authorization_service
+        .require(user_id, Permission::FilesRead, &Resource::owned_by(user_id))
+        .await?;
 
     // Get storage stats using the service from app state
     let stats = state.file_service.get_user_storage_stats(user_id).await?;
@@ -13,12 +13,14 @@ use kingshare_domain::{
     Form, FormResponse as DomainFormResponse, FormSection, FormField, CreateFormRequest,
     UpdateFormRequest, AddFormFieldRequest, SubmitFormResponseRequest, FormAnalyticsQuery,
     FormResponse as FormResponseDto, FormSummaryResponse, FormResponseResponse, FormAnalyticsResponse,
-    FormStatus, ResponseStatus, FieldValue, FormsService, FormsRepository,
+    FormStatus, ResponseStatus, FieldValue, FormsService, FormsRepository, Permission, Resource,
 };
 
+use kingshare_domain::entities::{ApiScope, ApiTokenGrant};
+
 use crate::{
     error::{ApiError, ApiResult},
-    middleware::auth::Claims,
+    middleware::auth::{check_scope, Claims},
     AppState,
 };
 
@@ -29,6 +31,9 @@ pub async fn create_form(
     Json(request): Json<CreateFormRequest>,
 ) -> ApiResult<Json<FormResponseDto>> {
     request.validate().map_err(ApiError::ValidationError)?;
+    state.authorization_service
+        .require(claims.user_id, Permission::FormsWrite, &Resource::owned_by(claims.user_id))
+        .await?;
     
     let form = if let Some(template_id) = request.template_id {
         state.forms_service.create_form_from_template(
@@ -53,6 +58,7 @@ pub async fn get_form(
     Extension(claims): Extension<Claims>,
     Path(form_id): Path<Id>,
 ) -> ApiResult<Json<FormResponseDto>> {
+    authorize_form(&state, claims.user_id, form_id, Permission::FormsRead).await?;
     let form = state.forms_service.get_form(form_id, claims.user_id).await?
         .ok_or(ApiError::NotFound("Form not found".to_string()))?;
     
@@ -66,6 +72,7 @@ pub async fn update_form(
     Json(request): Json<UpdateFormRequest>,
 ) -> ApiResult<Json<FormResponseDto>> {
     request.validate().map_err(ApiError::ValidationError)?;
+    authorize_form(&state, claims.user_id, form_id, Permission::FormsWrite).await?;
     
     let form = state.forms_service.update_form(form_id, request, claims.user_id).await?;
     Ok(Json(form))
@@ -76,6 +83,7 @@ pub async fn delete_form(
     Extension(claims): Extension<Claims>,
     Path(form_id): Path<Id>,
 ) -> ApiResult<StatusCode> {
+    authorize_form(&state, claims.user_id, form_id, Permission::FormsWrite).await?;
     state.forms_service.delete_form(form_id, claims.user_id).await?;
     Ok(StatusCode::NO_CONTENT)
 }
@@ -85,6 +93,9 @@ pub async fn list_forms(
     Extension(claims): Extension<Claims>,
     Query(params): Query<ListFormsQuery>,
 ) -> ApiResult<Json<Vec<FormSummaryResponse>>> {
+    state.authorization_service
+        .require(claims.user_id, Permission::FormsRead, &Resource::owned_by(claims.user_id))
+        .await?;
     let forms = state.forms_repository.get_forms_by_owner(claims.user_id).await?;
     
     let summaries: Vec<FormSummaryResponse> = forms.into_iter()
@@ -118,6 +129,7 @@ pub async fn add_section(
     Json(request): Json<AddSectionRequest>,
 ) -> ApiResult<Json<FormSection>> {
     request.validate().map_err(ApiError::ValidationError)?;
+    authorize_form(&state, claims.user_id, form_id, Permission::FormsWrite).await?;
     
     let section = state.forms_service.add_section(
         form_id,
@@ -136,6 +148,7 @@ pub async fn add_field(
     Json(request): Json<AddFormFieldRequest>,
 ) -> ApiResult<Json<FormField>> {
     request.validate().map_err(ApiError::ValidationError)?;
+    authorize_form(&state, claims.user_id, form_id, Permission::FormsWrite).await?;
     
     let field = state.forms_service.add_field(form_id, request, claims.user_id).await?;
     Ok(Json(field))
@@ -147,6 +160,7 @@ pub async fn update_field(
     Path(field_id): Path<String>,
     Json(request): Json<UpdateFieldRequest>,
 ) -> ApiResult<Json<FormField>> {
+    authorize_field(&state, claims.user_id, &field_id, Permission::FormsWrite).await?;
     let field = state.forms_service.update_field(&field_id, request.updates, claims.user_id).await?;
     Ok(Json(field))
 }
@@ -156,6 +170,7 @@ pub async fn delete_field(
     Extension(claims): Extension<Claims>,
     Path(field_id): Path<String>,
 ) -> ApiResult<StatusCode> {
+    authorize_field(&state, claims.user_id, &field_id, Permission::FormsWrite).await?;
     state.forms_service.delete_field(&field_id, claims.user_id).await?;
     Ok(StatusCode::NO_CONTENT)
 }
@@ -166,6 +181,7 @@ pub async fn reorder_elements(
     Path(form_id): Path<Id>,
     Json(request): Json<ReorderElementsRequest>,
 ) -> ApiResult<StatusCode> {
+    authorize_form(&state, claims.user_id, form_id, Permission::FormsWrite).await?;
     state.forms_service.reorder_form_elements(form_id, request.element_order, claims.user_id).await?;
     Ok(StatusCode::NO_CONTENT)
 }
@@ -176,6 +192,7 @@ pub async fn publish_form(
     Extension(claims): Extension<Claims>,
     Path(form_id): Path<Id>,
 ) -> ApiResult<Json<PublishResponse>> {
+    authorize_form(&state, claims.user_id, form_id, Permission::FormsWrite).await?;
     let public_url = state.forms_service.publish_form(form_id, claims.user_id).await?;
     Ok(Json(PublishResponse { public_url }))
 }
@@ -185,6 +202,7 @@ pub async fn unpublish_form(
     Extension(claims): Extension<Claims>,
     Path(form_id): Path<Id>,
 ) -> ApiResult<StatusCode> {
+    authorize_form(&state, claims.user_id, form_id, Permission::FormsWrite).await?;
     state.forms_service.unpublish_form(form_id, claims.user_id).await?;
     Ok(StatusCode::NO_CONTENT)
 }
@@ -194,6 +212,7 @@ pub async fn get_embed_code(
     Extension(claims): Extension<Claims>,
     Path(form_id): Path<Id>,
 ) -> ApiResult<Json<EmbedCodeResponse>> {
+    authorize_form(&state, claims.user_id, form_id, Permission::FormsRead).await?;
     let embed_code = state.forms_service.get_form_embed_code(form_id, claims.user_id).await?;
     Ok(Json(EmbedCodeResponse { embed_code }))
 }
@@ -203,6 +222,7 @@ pub async fn get_qr_code(
     Extension(claims): Extension<Claims>,
     Path(form_id): Path<Id>,
 ) -> ApiResult<axum::response::Response> {
+    authorize_form(&state, claims.user_id, form_id, Permission::FormsRead).await?;
     let qr_code_data = state.forms_service.get_form_qr_code(form_id, claims.user_id).await?;
     
     Ok(axum::response::Response::builder()
@@ -251,9 +271,12 @@ pub async fn save_draft_response(
 pub async fn get_form_responses(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
+    grant: Option<Extension<ApiTokenGrant>>,
     Path(form_id): Path<Id>,
     Query(params): Query<ResponseFiltersQuery>,
 ) -> ApiResult<Json<kingshare_domain::PaginatedResponses>> {
+    check_scope(grant.as_deref(), ApiScope::FormsResponsesRead, None)?;
+    authorize_form(&state, claims.user_id, form_id, Permission::FormsResponsesRead).await?;
     let filters = kingshare_domain::ResponseFilters {
         status: params.status,
         start_date: params.start_date,
@@ -273,8 +296,11 @@ pub async fn get_form_responses(
 pub async fn get_response(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
+    grant: Option<Extension<ApiTokenGrant>>,
     Path(response_id): Path<Id>,
 ) -> ApiResult<Json<FormResponseResponse>> {
+    check_scope(grant.as_deref(), ApiScope::FormsResponsesRead, None)?;
+    authorize_response(&state, claims.user_id, response_id, Permission::FormsResponsesRead).await?;
     let response = state.forms_service.get_response(response_id, claims.user_id).await?
         .ok_or(ApiError::NotFound("Response not found".to_string()))?;
     
@@ -287,6 +313,7 @@ pub async fn update_response(
     Path(response_id): Path<Id>,
     Json(request): Json<UpdateResponseRequest>,
 ) -> ApiResult<Json<FormResponseResponse>> {
+    authorize_response(&state, claims.user_id, response_id, Permission::FormsWrite).await?;
     let response = state.forms_service.update_response(response_id, request.updates, claims.user_id).await?;
     Ok(Json(response))
 }
@@ -296,6 +323,7 @@ pub async fn delete_response(
     Extension(claims): Extension<Claims>,
     Path(response_id): Path<Id>,
 ) -> ApiResult<StatusCode> {
+    authorize_response(&state, claims.user_id, response_id, Permission::FormsWrite).await?;
     state.forms_service.delete_response(response_id, claims.user_id).await?;
     Ok(StatusCode::NO_CONTENT)
 }
@@ -305,6 +333,7 @@ pub async fn approve_response(
     Extension(claims): Extension<Claims>,
     Path(response_id): Path<Id>,
 ) -> ApiResult<StatusCode> {
+    authorize_response(&state, claims.user_id, response_id, Permission::FormsWrite).await?;
     state.forms_service.approve_response(response_id, claims.user_id).await?;
     Ok(StatusCode::NO_CONTENT)
 }
@@ -315,6 +344,7 @@ pub async fn reject_response(
     Path(response_id): Path<Id>,
     Json(request): Json<RejectResponseRequest>,
 ) -> ApiResult<StatusCode> {
+    authorize_response(&state, claims.user_id, response_id, Permission::FormsWrite).await?;
     state.forms_service.reject_response(response_id, request.reason, claims.user_id).await?;
     Ok(StatusCode::NO_CONTENT)
 }
@@ -326,6 +356,7 @@ pub async fn get_form_analytics(
     Path(form_id): Path<Id>,
     Query(query): Query<FormAnalyticsQuery>,
 ) -> ApiResult<Json<FormAnalyticsResponse>> {
+    authorize_form(&state, claims.user_id, form_id, Permission::FormsResponsesRead).await?;
     let analytics = state.forms_service.get_form_analytics(form_id, query, claims.user_id).await?;
     Ok(Json(analytics))
 }
@@ -335,6 +366,7 @@ pub async fn get_response_summary(
     Extension(claims): Extension<Claims>,
     Path(form_id): Path<Id>,
 ) -> ApiResult<Json<kingshare_domain::ResponseSummary>> {
+    authorize_form(&state, claims.user_id, form_id, Permission::FormsResponsesRead).await?;
     let summary = state.forms_service.get_response_summary(form_id, claims.user_id).await?;
     Ok(Json(summary))
 }
@@ -344,6 +376,7 @@ pub async fn generate_insights(
     Extension(claims): Extension<Claims>,
     Path(form_id): Path<Id>,
 ) -> ApiResult<Json<Vec<kingshare_domain::FormInsight>>> {
+    authorize_form(&state, claims.user_id, form_id, Permission::FormsResponsesRead).await?;
     let insights = state.forms_service.generate_insights(form_id, claims.user_id).await?;
     Ok(Json(insights))
 }
@@ -352,9 +385,12 @@ pub async fn generate_insights(
 pub async fn export_responses(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
+    grant: Option<Extension<ApiTokenGrant>>,
     Path(form_id): Path<Id>,
     Json(request): Json<ExportDataRequest>,
 ) -> ApiResult<Json<kingshare_domain::ExportResult>> {
+    check_scope(grant.as_deref(), ApiScope::FormsResponsesRead, None)?;
+    authorize_form(&state, claims.user_id, form_id, Permission::FormsResponsesRead).await?;
     let export_options = kingshare_domain::DataExportOptions {
         format: request.format,
         include_metadata: request.include_metadata.unwrap_or(false),
@@ -376,6 +412,7 @@ pub async fn save_as_template(
     Json(request): Json<SaveTemplateRequest>,
 ) -> ApiResult<Json<TemplateResponse>> {
     request.validate().map_err(ApiError::ValidationError)?;
+    authorize_form(&state, claims.user_id, form_id, Permission::FormsWrite).await?;
     
     let template_id = state.forms_service.save_as_template(
         form_id,
@@ -395,6 +432,9 @@ pub async fn get_templates(
     Extension(claims): Extension<Claims>,
     Query(params): Query<TemplatesQuery>,
 ) -> ApiResult<Json<Vec<kingshare_domain::FormTemplate>>> {
+    state.authorization_service
+        .require(claims.user_id, Permission::FormsRead, &Resource::owned_by(claims.user_id))
+        .await?;
     let templates = state.forms_service.get_available_templates(params.category, claims.user_id).await?;
     Ok(Json(templates))
 }
@@ -404,6 +444,9 @@ pub async fn preview_template(
     Extension(claims): Extension<Claims>,
     Path(template_id): Path<Id>,
 ) -> ApiResult<Json<kingshare_domain::FormPreview>> {
+    state.authorization_service
+        .require(claims.user_id, Permission::FormsRead, &Resource::owned_by(claims.user_id))
+        .await?;
     let preview = state.forms_service.preview_template(template_id, claims.user_id).await?;
     Ok(Json(preview))
 }
@@ -415,6 +458,7 @@ pub async fn setup_conditional_logic(
     Path(form_id): Path<Id>,
     Json(request): Json<ConditionalLogicRequest>,
 ) -> ApiResult<StatusCode> {
+    authorize_form(&state, claims.user_id, form_id, Permission::FormsWrite).await?;
     state.forms_service.setup_conditional_logic(form_id, request.logic_rules, claims.user_id).await?;
     Ok(StatusCode::NO_CONTENT)
 }
@@ -425,6 +469,7 @@ pub async fn configure_notifications(
     Path(form_id): Path<Id>,
     Json(request): Json<NotificationConfigRequest>,
 ) -> ApiResult<StatusCode> {
+    authorize_form(&state, claims.user_id, form_id, Permission::FormsWrite).await?;
     state.forms_service.configure_notifications(form_id, request.notification_settings, claims.user_id).await?;
     Ok(StatusCode::NO_CONTENT)
 }
@@ -435,6 +480,7 @@ pub async fn setup_integrations(
     Path(form_id): Path<Id>,
     Json(request): Json<IntegrationsRequest>,
 ) -> ApiResult<StatusCode> {
+    authorize_form(&state, claims.user_id, form_id, Permission::FormsWrite).await?;
     state.forms_service.setup_integrations(form_id, request.integrations, claims.user_id).await?;
     Ok(StatusCode::NO_CONTENT)
 }
@@ -445,6 +491,7 @@ pub async fn optimize_form(
     Extension(claims): Extension<Claims>,
     Path(form_id): Path<Id>,
 ) -> ApiResult<Json<kingshare_domain::OptimizationReport>> {
+    authorize_form(&state, claims.user_id, form_id, Permission::FormsWrite).await?;
     let report = state.forms_service.optimize_form_performance(form_id, claims.user_id).await?;
     Ok(Json(report))
 }
@@ -454,6 +501,7 @@ pub async fn validate_form(
     Extension(claims): Extension<Claims>,
     Path(form_id): Path<Id>,
 ) -> ApiResult<Json<kingshare_domain::ValidationReport>> {
+    authorize_form(&state, claims.user_id, form_id, Permission::FormsRead).await?;
     let report = state.forms_service.validate_form_structure(form_id, claims.user_id).await?;
     Ok(Json(report))
 }
@@ -463,10 +511,32 @@ pub async fn get_health_score(
     Extension(claims): Extension<Claims>,
     Path(form_id): Path<Id>,
 ) -> ApiResult<Json<kingshare_domain::HealthScore>> {
+    authorize_form(&state, claims.user_id, form_id, Permission::FormsRead).await?;
     let score = state.forms_service.get_form_health_score(form_id, claims.user_id).await?;
     Ok(Json(score))
 }
 
+async fn authorize_form(state: &AppState, user_id: Id, form_id: Id, permission: Permission) -> ApiResult<()> {
+    let owner_id = state.forms_repository.get_form_owner(form_id).await?
+        .ok_or(ApiError::NotFound("Form not found".to_string()))?;
+    state.authorization_service
+        .require(user_id, permission, &Resource::owned_by(owner_id))
+        .await?;
+    Ok(())
+}
+
+async fn authorize_field(state: &AppState, user_id: Id, field_id: &str, permission: Permission) -> ApiResult<()> {
+    let form_id = state.forms_repository.get_field_form_id(field_id).await?
+        .ok_or(ApiError::NotFound("Field not found".to_string()))?;
+    authorize_form(state, user_id, form_id, permission).await
+}
+
+async fn authorize_response(state: &AppState, user_id: Id, response_id: Id, permission: Permission) -> ApiResult<()> {
+    let response = state.forms_repository.get_response_by_id(response_id).await?
+        .ok_or(ApiError::NotFound("Response not found".to_string()))?;
+    authorize_form(state, user_id, response.form_id, permission).await
+}
+
 // Request/Response DTOs
 #[derive(Debug, Deserialize)]
 pub struct ListFormsQuery {
@@ -0,0 +1,83 @@
+use axum::{
+    extract::{Path, Query, Request, State},
+    Extension, Json,
+};
+use kingshare_core::{ApiResponse, Id, Result};
+use kingshare_domain::{
+    entities::{AttemptKey, FailedAttempts, Permission, SecurityEvent, SecurityEventType},
+    services::Claims,
+};
+use serde::Deserialize;
+use tracing::{info, instrument};
+use crate::{
+    middleware::auth::{ensure_permission, require_permission},
+    server::AppState,
+};
+
+const DEFAULT_EVENT_LIMIT: u32 = 100;
+const MAX_EVENT_LIMIT: u32 = 1000;
+
+#[derive(Debug, Deserialize)]
+pub struct SecurityEventQuery {
+    pub event_type: Option<SecurityEventType>,
+    pub limit: Option<u32>,
+}
+
+/// Accounts, shares and addresses that are currently backed off or locked
+#[instrument(skip(state, request))]
+pub async fn list_lockouts(
+    State(state): State<AppState>,
+    request: Request,
+) -> Result<Json<ApiResponse<Vec<FailedAttempts>>>> {
+    require_permission(&state, &request, Permission::SecurityManage).await?;
+
+    let lockouts = state.lockout_service.list_blocked().await?;
+
+    Ok(Json(ApiResponse::success(lockouts)))
+}
+
+#[instrument(skip(state, request))]
+pub async fn unlock_user(
+    State(state): State<AppState>,
+    Path(user_id): Path<Id>,
+    request: Request,
+) -> Result<Json<ApiResponse<String>>> {
+    let admin_id = require_permission(&state, &request, Permission::SecurityManage).await?;
+
+    let user = state.user_service.get_user_by_id(user_id).await?;
+    state
+        .lockout_service
+        .unlock(admin_id, &AttemptKey::account(user.email.as_str()))
+        .await?;
+
+    info!(admin_id = %admin_id, user_id = %user_id, "Account unlocked");
+    Ok(Json(ApiResponse::success("Account unlocked".to_string())))
+}
+
+/// Lift the block on any key, such as a share or an address
+#[instrument(skip(state, claims))]
+pub async fn unlock(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(key): Json<AttemptKey>,
+) -> Result<Json<ApiResponse<String>>> {
+    let admin_id = ensure_permission(&state, &claims, Permission::SecurityManage).await?;
+
+    state.lockout_service.unlock(admin_id, &key).await?;
+
+    Ok(Json(ApiResponse::success("Unlocked".to_string())))
+}
+
+#[instrument(skip(state, request))]
+pub async fn list_security_events(
+    State(state): State<AppState>,
+    Query(query): Query<SecurityEventQuery>,
+    request: Request,
+) -> Result<Json<ApiResponse<Vec<SecurityEvent>>>> {
+    require_permission(&state, &request, Permission::SecurityManage).await?;
+
+    let limit = query.limit.unwrap_or(DEFAULT_EVENT_LIMIT).min(MAX_EVENT_LIMIT);
+    let events = state.lockout_service.list_events(query.event_type, limit).await?;
+
+    Ok(Json(ApiResponse::success(events)))
+}
@@ -1,7 +1,15 @@
 pub mod auth;
+pub mod client;
+pub mod download;
 pub mod files;
 pub mod health;
+pub mod lockouts;
+pub mod quotas;
+pub mod roles;
 pub mod shares;
+pub mod storage;
+pub mod uploads;
+pub mod user_admin;
 pub mod users;
 pub mod websocket;
 pub mod drive;
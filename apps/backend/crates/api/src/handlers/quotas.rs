@@ -0,0 +1,88 @@
+use axum::{
+    extract::{Path, Request, State},
+    Extension, Json,
+};
+use kingshare_core::{ApiResponse, Id, Result};
+use kingshare_domain::{
+    entities::{Permission, QuotaScope, QuotaUsage, SetQuotaRequest},
+    services::Claims,
+};
+use tracing::{info, instrument};
+use crate::{
+    middleware::auth::{ensure_permission, require_permission},
+    server::AppState,
+};
+
+#[instrument(skip(state, request))]
+pub async fn get_user_quota(
+    State(state): State<AppState>,
+    Path(user_id): Path<Id>,
+    request: Request,
+) -> Result<Json<ApiResponse<QuotaUsage>>> {
+    require_permission(&state, &request, Permission::QuotasManage).await?;
+
+    let usage = state.quota_service.get_usage(QuotaScope::User(user_id)).await?;
+
+    Ok(Json(ApiResponse::success(usage)))
+}
+
+#[instrument(skip(state, claims, payload))]
+pub async fn set_user_quota(
+    State(state): State<AppState>,
+    Path(user_id): Path<Id>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<SetQuotaRequest>,
+) -> Result<Json<ApiResponse<QuotaUsage>>> {
+    let admin_id = ensure_permission(&state, &claims, Permission::QuotasManage).await?;
+
+    let usage = state
+        .quota_service
+        .set_quota(QuotaScope::User(user_id), payload.quota)
+        .await?;
+
+    info!(
+        admin_id = %admin_id,
+        user_id = %user_id,
+        quota = ?usage.quota,
+        "User storage quota set"
+    );
+
+    Ok(Json(ApiResponse::success(usage)))
+}
+
+#[instrument(skip(state, request))]
+pub async fn get_drive_quota(
+    State(state): State<AppState>,
+    Path(drive_id): Path<Id>,
+    request: Request,
+) -> Result<Json<ApiResponse<QuotaUsage>>> {
+    require_permission(&state, &request, Permission::QuotasManage).await?;
+
+    let usage = state.quota_service.get_usage(QuotaScope::Drive(drive_id)).await?;
+
+    Ok(Json(ApiResponse::success(usage)))
+}
+
+#[instrument(skip(state, claims, payload))]
+pub async fn set_drive_quota(
+    State(state): State<AppState>,
+    Path(drive_id): Path<Id>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<SetQuotaRequest>,
+) -> Result<Json<ApiResponse<QuotaUsage>>> {
+    let admin_id = ensure_permission(&state, &claims, Permission::QuotasManage).await?;
+
+    let usage = state
+        .quota_service
+        .set_quota(QuotaScope::Drive(drive_id), payload.quota)
+        .await?;
+
+    info!(
+        admin_id = %admin_id,
+        drive_id = %drive_id,
+        quota = ?usage.quota,
+        "Drive storage quota set"
+    );
+
+    Ok(Json(ApiResponse::success(usage)))
+}
@@ -0,0 +1,176 @@
+use axum::{
+    extract::{Path, Request, State},
+    Extension, Json,
+};
+use kingshare_core::{ApiResponse, Error, Id, Result};
+use kingshare_domain::{
+    entities::{
+        AssignRoleRequest, CreateRoleRequest, CreateTeamRequest, Permission, Resource, Role, RoleAssignment, Team,
+        UpdateRoleRequest,
+    },
+    services::Claims,
+};
+use tracing::{info, instrument};
+use crate::{
+    middleware::auth::{ensure_permission, require_permission, ClaimsExt},
+    server::AppState,
+};
+
+/// What the caller may do outside any particular drive or team
+#[instrument(skip(state, request))]
+pub async fn get_my_permissions(
+    State(state): State<AppState>,
+    request: Request,
+) -> Result<Json<ApiResponse<Vec<Permission>>>> {
+    let user_id = request
+        .require_user_id()
+        .map_err(|_| Error::Authentication("Authentication required".to_string()))?;
+
+    let permissions = state
+        .authorization_service
+        .permissions(user_id, &Resource::owned_by(user_id))
+        .await?;
+
+    Ok(Json(ApiResponse::success(permissions)))
+}
+
+#[instrument(skip(state, request))]
+pub async fn list_roles(
+    State(state): State<AppState>,
+    request: Request,
+) -> Result<Json<ApiResponse<Vec<Role>>>> {
+    require_permission(&state, &request, Permission::RolesManage).await?;
+
+    let roles = state.authorization_service.list_roles().await?;
+
+    Ok(Json(ApiResponse::success(roles)))
+}
+
+#[instrument(skip(state, claims, payload))]
+pub async fn create_role(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<CreateRoleRequest>,
+) -> Result<Json<ApiResponse<Role>>> {
+    let admin_id = ensure_permission(&state, &claims, Permission::RolesManage).await?;
+
+    let role = state.authorization_service.create_role(payload).await?;
+
+    info!(admin_id = %admin_id, role_id = %role.id, "Role created");
+    Ok(Json(ApiResponse::success(role)))
+}
+
+#[instrument(skip(state, claims, payload))]
+pub async fn update_role(
+    State(state): State<AppState>,
+    Path(role_id): Path<Id>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<UpdateRoleRequest>,
+) -> Result<Json<ApiResponse<Role>>> {
+    let admin_id = ensure_permission(&state, &claims, Permission::RolesManage).await?;
+
+    let role = state.authorization_service.update_role(role_id, payload).await?;
+
+    info!(admin_id = %admin_id, role_id = %role_id, "Role updated");
+    Ok(Json(ApiResponse::success(role)))
+}
+
+#[instrument(skip(state, request))]
+pub async fn delete_role(
+    State(state): State<AppState>,
+    Path(role_id): Path<Id>,
+    request: Request,
+) -> Result<Json<ApiResponse<String>>> {
+    let admin_id = require_permission(&state, &request, Permission::RolesManage).await?;
+
+    state.authorization_service.delete_role(role_id).await?;
+
+    info!(admin_id = %admin_id, role_id = %role_id, "Role deleted");
+    Ok(Json(ApiResponse::success("Role deleted".to_string())))
+}
+
+#[instrument(skip(state, request))]
+pub async fn list_user_roles(
+    State(state): State<AppState>,
+    Path(user_id): Path<Id>,
+    request: Request,
+) -> Result<Json<ApiResponse<Vec<RoleAssignment>>>> {
+    require_permission(&state, &request, Permission::RolesManage).await?;
+
+    let assignments = state.authorization_service.list_assignments(user_id).await?;
+
+    Ok(Json(ApiResponse::success(assignments)))
+}
+
+#[instrument(skip(state, claims, payload))]
+pub async fn assign_role(
+    State(state): State<AppState>,
+    Path(user_id): Path<Id>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<AssignRoleRequest>,
+) -> Result<Json<ApiResponse<RoleAssignment>>> {
+    let admin_id = ensure_permission(&state, &claims, Permission::RolesManage).await?;
+
+    let assignment = state
+        .authorization_service
+        .assign_role(admin_id, user_id, payload)
+        .await?;
+
+    Ok(Json(ApiResponse::success(assignment)))
+}
+
+#[instrument(skip(state, request))]
+pub async fn revoke_role_assignment(
+    State(state): State<AppState>,
+    Path(assignment_id): Path<Id>,
+    request: Request,
+) -> Result<Json<ApiResponse<String>>> {
+    let admin_id = require_permission(&state, &request, Permission::RolesManage).await?;
+
+    state
+        .authorization_service
+        .revoke_assignment(admin_id, assignment_id)
+        .await?;
+
+    Ok(Json(ApiResponse::success("Role revoked".to_string())))
+}
+
+#[instrument(skip(state, request))]
+pub async fn list_teams(
+    State(state): State<AppState>,
+    request: Request,
+) -> Result<Json<ApiResponse<Vec<Team>>>> {
+    require_permission(&state, &request, Permission::RolesManage).await?;
+
+    let teams = state.authorization_service.list_teams().await?;
+
+    Ok(Json(ApiResponse::success(teams)))
+}
+
+#[instrument(skip(state, claims, payload))]
+pub async fn create_team(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<CreateTeamRequest>,
+) -> Result<Json<ApiResponse<Team>>> {
+    let admin_id = ensure_permission(&state, &claims, Permission::RolesManage).await?;
+
+    let team = state.authorization_service.create_team(admin_id, payload).await?;
+
+    info!(admin_id = %admin_id, team_id = %team.id, "Team created");
+    Ok(Json(ApiResponse::success(team)))
+}
+
+#[instrument(skip(state, request))]
+pub async fn delete_team(
+    State(state): State<AppState>,
+    Path(team_id): Path<Id>,
+    request: Request,
+) -> Result<Json<ApiResponse<String>>> {
+    let admin_id = require_permission(&state, &request, Permission::RolesManage).await?;
+
+    state.authorization_service.delete_team(team_id).await?;
+
+    info!(admin_id = %admin_id, team_id = %team_id, "Team deleted");
+    Ok(Json(ApiResponse::success("Team deleted".to_string())))
+}
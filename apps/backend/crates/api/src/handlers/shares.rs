@@ -1,15 +1,24 @@
 use axum::{
     extract::{Path, Query, Request, State},
-    http::{header, StatusCode},
+    http::HeaderMap,
     response::Response,
     Json,
 };
 use kingshare_core::{ApiResponse, Id, PaginationParams, Result};
-use kingshare_domain::entities::{AccessShareRequest, CreateShareRequest, ShareInfo, UpdateShareRequest};
+use kingshare_domain::entities::{
+    AccessShareRequest, ApiScope, CreateShareRequest, Permission, Resource, ShareInfo, UpdateShareRequest,
+};
 use serde::Deserialize;
 use tracing::{info, instrument};
 use validator::Validate;
-use crate::{middleware::auth::ClaimsExt, server::AppState};
+use crate::{
+    handlers::{
+        client::Client,
+        download::{self, DownloadInfo, DownloadPlan},
+    },
+    middleware::auth::ClaimsExt,
+    server::AppState,
+};
 
 #[derive(Debug, Deserialize)]
 pub struct ShareListQuery {
@@ -26,6 +35,7 @@ pub async fn list_shares(
     // Extract user ID from JWT claims
     let user_id = request.require_user_id()
         .map_err(|_| kingshare_core::Error::Authentication("Authentication required".to_string()))?;
+    request.require_scope(ApiScope::SharesManage)?;
 
     // List user shares using the service from app state
     let shares = state.share_service.list_user_shares(user_id, query.pagination).await?;
@@ -52,6 +62,12 @@ pub async fn create_share(
     // Extract user ID from JWT claims
     let user_id = request.require_user_id()
         .map_err(|_| kingshare_core::Error::Authentication("Authentication required".to_string()))?;
+    request.require_scope(ApiScope::SharesManage)?;
+    // Guests can look but not add
+    state
+        .authorization_service
+        .require(user_id, Permission::SharesManage, &Resource::owned_by(user_id))
+        .await?;
 
     // Create share using the service from app state
     let share_info = state.share_service.create_share(user_id, payload).await?;
@@ -76,6 +92,7 @@ pub async fn get_share(
     // Extract user ID from JWT claims
     let user_id = request.require_user_id()
         .map_err(|_| kingshare_core::Error::Authentication("Authentication required".to_string()))?;
+    request.require_scope(ApiScope::SharesManage)?;
 
     // Get share using the service from app state
     let share_info = state.share_service.get_share(id, user_id).await?;
@@ -103,6 +120,7 @@ pub async fn update_share(
     // Extract user ID from JWT claims
     let user_id = request.require_user_id()
         .map_err(|_| kingshare_core::Error::Authentication("Authentication required".to_string()))?;
+    request.require_scope(ApiScope::SharesManage)?;
 
     // Update share using the service from app state
     let updated_share = state.share_service.update_share(id, user_id, payload).await?;
@@ -125,6 +143,7 @@ pub async fn delete_share(
     // Extract user ID from JWT claims
     let user_id = request.require_user_id()
         .map_err(|_| kingshare_core::Error::Authentication("Authentication required".to_string()))?;
+    request.require_scope(ApiScope::SharesManage)?;
 
     // Delete share using the service from app state
     state.share_service.delete_share(id, user_id).await?;
@@ -155,41 +174,56 @@ pub async fn get_share_by_token(
     Ok(Json(ApiResponse::success(share_info)))
 }
 
-#[instrument(skip(state, payload))]
+#[instrument(skip(state, headers, client, payload))]
 pub async fn download_shared_file(
     State(state): State<AppState>,
     Path(token): Path<String>,
+    headers: HeaderMap,
+    Client(client): Client,
     Json(payload): Json<AccessShareRequest>,
 ) -> Result<Response> {
     // Validate payload
     payload.validate()
         .map_err(|e| kingshare_core::Error::Validation(e.to_string()))?;
 
-    // Access shared file using the service from app state
-    let (share_info, file_data) = state.share_service.access_shared_file(&token, payload).await?;
+    // Check token and password before evaluating validators
+    let access = state
+        .share_service
+        .authorize_shared_download(&token, payload, &client)
+        .await?;
+    let download_info = DownloadInfo::new(
+        &access.share_info.file.filename,
+        &access.share_info.file.content_type,
+        access.file.size,
+        &access.file.checksum,
+        access.file.updated_at,
+    );
+
+    let ranges = match download::plan_download(&headers, &download_info) {
+        DownloadPlan::NotModified => return download::not_modified_response(&download_info),
+        DownloadPlan::Unsatisfiable => {
+            return download::range_not_satisfiable_response(&download_info)
+        }
+        DownloadPlan::Full => Vec::new(),
+        DownloadPlan::Partial(ranges) => ranges,
+    };
+
+    let (share_info, streams) = state
+        .share_service
+        .open_shared_download(access, &token, &ranges)
+        .await?;
 
     info!(
         token = %token,
         share_id = %share_info.id,
         file_id = %share_info.file.id,
         filename = %share_info.file.filename,
-        size = file_data.len(),
+        size = share_info.file.size,
+        range_count = ranges.len(),
         "Shared file downloaded"
     );
 
-    // Create response with appropriate headers
-    let response = Response::builder()
-        .status(StatusCode::OK)
-        .header(header::CONTENT_TYPE, share_info.file.content_type)
-        .header(
-            header::CONTENT_DISPOSITION,
-            format!("attachment; filename=\"{}\"", share_info.file.filename),
-        )
-        .header(header::CONTENT_LENGTH, file_data.len())
-        .body(file_data.into())
-        .map_err(|e| kingshare_core::Error::Internal(format!("Failed to create response: {}", e)))?;
-
-    Ok(response)
+    download::stream_response(&download_info, &ranges, streams)
 }
 
 #[instrument(skip(state, request))]
@@ -201,6 +235,7 @@ pub async fn regenerate_share_token(
     // Extract user ID from JWT claims
     let user_id = request.require_user_id()
         .map_err(|_| kingshare_core::Error::Authentication("Authentication required".to_string()))?;
+    request.require_scope(ApiScope::SharesManage)?;
 
     // Regenerate token using the service from app state
     let updated_share = state.share_service.regenerate_share_token(id, user_id).await?;
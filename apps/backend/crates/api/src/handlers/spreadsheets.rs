@@ -6,13 +6,14 @@ use axum::{
 };
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use tracing::warn;
 use validator::Validate;
 
 use kingshare_core::{Id, Result as CoreResult};
 use kingshare_domain::{
     Spreadsheet, Sheet, Cell, CellValue, Chart, PivotTable, CreateSheetRequest, CreateChartRequest,
     CreatePivotTableRequest, UpdateCellRequest, BatchUpdateCellsRequest, SpreadsheetResponse,
-    GridRange, CellFormat, SpreadsheetService, SpreadsheetRepository,
+    GridRange, CellFormat, SpreadsheetService, SpreadsheetRepository, Permission, Resource,
 };
 
 use crate::{
@@ -28,6 +29,11 @@ pub async fn create_spreadsheet(
     Json(request): Json<CreateSpreadsheetRequest>,
 ) -> ApiResult<Json<SpreadsheetResponse>> {
     request.validate().map_err(ApiError::ValidationError)?;
+    let owner_id = state.spreadsheet_repository.get_document_owner(request.document_id).await?
+        .ok_or(ApiError::NotFound("Document not found".to_string()))?;
+    state.authorization_service
+        .require(claims.user_id, Permission::SpreadsheetsWrite, &Resource::owned_by(owner_id))
+        .await?;
     
     let spreadsheet = if let Some(template_id) = request.template_id {
         state.spreadsheet_service.create_from_template(
@@ -52,6 +58,8 @@ pub async fn get_spreadsheet(
     Extension(claims): Extension<Claims>,
     Path(spreadsheet_id): Path<Id>,
 ) -> ApiResult<Json<SpreadsheetResponse>> {
+    authorize_spreadsheet(&state, claims.user_id, spreadsheet_id, Permission::SpreadsheetsRead).await?;
+    
     let spreadsheet = state.spreadsheet_service.get_spreadsheet(spreadsheet_id, claims.user_id).await?
         .ok_or(ApiError::NotFound("Spreadsheet not found".to_string()))?;
     
@@ -67,8 +75,7 @@ pub async fn update_spreadsheet(
     let mut spreadsheet = state.spreadsheet_repository.get_spreadsheet_by_id(spreadsheet_id).await?
         .ok_or(ApiError::NotFound("Spreadsheet not found".to_string()))?;
     
-    // Check permissions (assuming document-level permissions)
-    // TODO: Implement proper permission checking
+    authorize_spreadsheet(&state, claims.user_id, spreadsheet_id, Permission::SpreadsheetsWrite).await?;
     
     if let Some(title) = request.title {
         spreadsheet.title = title;
@@ -112,7 +119,7 @@ pub async fn delete_spreadsheet(
     let spreadsheet = state.spreadsheet_repository.get_spreadsheet_by_id(spreadsheet_id).await?
         .ok_or(ApiError::NotFound("Spreadsheet not found".to_string()))?;
     
-    // TODO: Check permissions
+    authorize_spreadsheet(&state, claims.user_id, spreadsheet_id, Permission::SpreadsheetsWrite).await?;
     
     state.spreadsheet_repository.delete_spreadsheet(spreadsheet_id).await?;
     Ok(StatusCode::NO_CONTENT)
@@ -126,6 +133,7 @@ pub async fn create_sheet(
     Json(request): Json<CreateSheetRequest>,
 ) -> ApiResult<Json<Sheet>> {
     request.validate().map_err(ApiError::ValidationError)?;
+    authorize_spreadsheet(&state, claims.user_id, spreadsheet_id, Permission::SpreadsheetsWrite).await?;
     
     let sheet = state.spreadsheet_service.add_sheet(spreadsheet_id, request, claims.user_id).await?;
     Ok(Json(sheet))
@@ -139,7 +147,7 @@ pub async fn get_sheet(
     let sheet = state.spreadsheet_repository.get_sheet_by_id(&sheet_id).await?
         .ok_or(ApiError::NotFound("Sheet not found".to_string()))?;
     
-    // TODO: Check permissions
+    authorize_sheet(&state, claims.user_id, &sheet_id, Permission::SpreadsheetsRead).await?;
     
     Ok(Json(sheet))
 }
@@ -153,7 +161,7 @@ pub async fn update_sheet(
     let mut sheet = state.spreadsheet_repository.get_sheet_by_id(&sheet_id).await?
         .ok_or(ApiError::NotFound("Sheet not found".to_string()))?;
     
-    // TODO: Check permissions
+    authorize_sheet(&state, claims.user_id, &sheet_id, Permission::SpreadsheetsWrite).await?;
     
     if let Some(title) = request.title {
         sheet.title = title;
@@ -174,6 +182,8 @@ pub async fn delete_sheet(
     Extension(claims): Extension<Claims>,
     Path(sheet_id): Path<String>,
 ) -> ApiResult<StatusCode> {
+    authorize_sheet(&state, claims.user_id, &sheet_id, Permission::SpreadsheetsWrite).await?;
+    
     state.spreadsheet_service.delete_sheet(&sheet_id, claims.user_id).await?;
     Ok(StatusCode::NO_CONTENT)
 }
@@ -185,6 +195,7 @@ pub async fn duplicate_sheet(
     Json(request): Json<DuplicateSheetRequest>,
 ) -> ApiResult<Json<Sheet>> {
     request.validate().map_err(ApiError::ValidationError)?;
+    authorize_sheet(&state, claims.user_id, &sheet_id, Permission::SpreadsheetsWrite).await?;
     
     let duplicated_sheet = state.spreadsheet_repository.duplicate_sheet(&sheet_id, request.new_title).await?;
     Ok(Json(duplicated_sheet))
@@ -197,7 +208,7 @@ pub async fn update_cell(
     Path((sheet_id, cell_ref)): Path<(String, String)>,
     Json(request): Json<UpdateCellRequest>,
 ) -> ApiResult<StatusCode> {
-    // TODO: Check permissions
+    authorize_sheet(&state, claims.user_id, &sheet_id, Permission::SpreadsheetsWrite).await?;
     
     let cell = Cell {
         value: request.value.unwrap_or(CellValue::Empty),
@@ -209,6 +220,7 @@ pub async fn update_cell(
     };
     
     state.spreadsheet_repository.update_cell(&sheet_id, &cell_ref, cell).await?;
+    index_sheet(&state, &sheet_id).await?;
     Ok(StatusCode::NO_CONTENT)
 }
 
@@ -218,7 +230,10 @@ pub async fn batch_update_cells(
     Path(sheet_id): Path<String>,
     Json(request): Json<BatchUpdateCellsRequest>,
 ) -> ApiResult<StatusCode> {
+    authorize_sheet(&state, claims.user_id, &sheet_id, Permission::SpreadsheetsWrite).await?;
+    
     state.spreadsheet_service.update_cells(&sheet_id, request, claims.user_id).await?;
+    index_sheet(&state, &sheet_id).await?;
     Ok(StatusCode::NO_CONTENT)
 }
 
@@ -227,7 +242,7 @@ pub async fn get_cell(
     Extension(claims): Extension<Claims>,
     Path((sheet_id, cell_ref)): Path<(String, String)>,
 ) -> ApiResult<Json<Option<Cell>>> {
-    // TODO: Check permissions
+    authorize_sheet(&state, claims.user_id, &sheet_id, Permission::SpreadsheetsRead).await?;
     
     let cell = state.spreadsheet_repository.get_cell(&sheet_id, &cell_ref).await?;
     Ok(Json(cell))
@@ -239,7 +254,7 @@ pub async fn get_range(
     Path(sheet_id): Path<String>,
     Query(params): Query<RangeQuery>,
 ) -> ApiResult<Json<RangeResponse>> {
-    // TODO: Check permissions
+    authorize_sheet(&state, claims.user_id, &sheet_id, Permission::SpreadsheetsRead).await?;
     
     let range = GridRange {
         sheet_id: sheet_id.clone(),
@@ -265,9 +280,10 @@ pub async fn clear_range(
     Path(sheet_id): Path<String>,
     Json(request): Json<ClearRangeRequest>,
 ) -> ApiResult<StatusCode> {
-    // TODO: Check permissions
+    authorize_sheet(&state, claims.user_id, &sheet_id, Permission::SpreadsheetsWrite).await?;
     
     state.spreadsheet_repository.clear_range(&sheet_id, &request.range).await?;
+    index_sheet(&state, &sheet_id).await?;
     Ok(StatusCode::NO_CONTENT)
 }
 
@@ -278,7 +294,7 @@ pub async fn calculate_formula(
     Path(sheet_id): Path<String>,
     Json(request): Json<CalculateFormulaRequest>,
 ) -> ApiResult<Json<CalculationResponse>> {
-    // TODO: Check permissions
+    authorize_sheet(&state, claims.user_id, &sheet_id, Permission::SpreadsheetsRead).await?;
     
     let result = state.spreadsheet_repository.calculate_formula(&sheet_id, &request.formula).await?;
     
@@ -293,7 +309,7 @@ pub async fn recalculate_sheet(
     Extension(claims): Extension<Claims>,
     Path(sheet_id): Path<String>,
 ) -> ApiResult<StatusCode> {
-    // TODO: Check permissions
+    authorize_sheet(&state, claims.user_id, &sheet_id, Permission::SpreadsheetsWrite).await?;
     
     state.spreadsheet_repository.recalculate_sheet(&sheet_id).await?;
     Ok(StatusCode::NO_CONTENT)
@@ -307,6 +323,7 @@ pub async fn create_chart(
     Json(request): Json<CreateChartRequest>,
 ) -> ApiResult<Json<Chart>> {
     request.validate().map_err(ApiError::ValidationError)?;
+    authorize_spreadsheet(&state, claims.user_id, spreadsheet_id, Permission::SpreadsheetsWrite).await?;
     
     let chart = state.spreadsheet_service.create_chart(spreadsheet_id, request, claims.user_id).await?;
     Ok(Json(chart))
@@ -320,7 +337,7 @@ pub async fn get_chart(
     let chart = state.spreadsheet_repository.get_chart_by_id(&chart_id).await?
         .ok_or(ApiError::NotFound("Chart not found".to_string()))?;
     
-    // TODO: Check permissions
+    authorize_chart(&state, claims.user_id, &chart_id, Permission::SpreadsheetsRead).await?;
     
     Ok(Json(chart))
 }
@@ -334,7 +351,7 @@ pub async fn update_chart(
     let mut chart = state.spreadsheet_repository.get_chart_by_id(&chart_id).await?
         .ok_or(ApiError::NotFound("Chart not found".to_string()))?;
     
-    // TODO: Check permissions
+    authorize_chart(&state, claims.user_id, &chart_id, Permission::SpreadsheetsWrite).await?;
     
     if let Some(title) = request.title {
         chart.title = title;
@@ -352,7 +369,7 @@ pub async fn delete_chart(
     Extension(claims): Extension<Claims>,
     Path(chart_id): Path<String>,
 ) -> ApiResult<StatusCode> {
-    // TODO: Check permissions
+    authorize_chart(&state, claims.user_id, &chart_id, Permission::SpreadsheetsWrite).await?;
     
     state.spreadsheet_repository.delete_chart(&chart_id).await?;
     Ok(StatusCode::NO_CONTENT)
@@ -366,6 +383,7 @@ pub async fn create_pivot_table(
     Json(request): Json<CreatePivotTableRequest>,
 ) -> ApiResult<Json<PivotTable>> {
     request.validate().map_err(ApiError::ValidationError)?;
+    authorize_spreadsheet(&state, claims.user_id, spreadsheet_id, Permission::SpreadsheetsWrite).await?;
     
     let pivot_table = state.spreadsheet_service.create_pivot_table(spreadsheet_id, request, claims.user_id).await?;
     Ok(Json(pivot_table))
@@ -379,7 +397,7 @@ pub async fn get_pivot_table(
     let pivot_table = state.spreadsheet_repository.get_pivot_table_by_id(&pivot_table_id).await?
         .ok_or(ApiError::NotFound("Pivot table not found".to_string()))?;
     
-    // TODO: Check permissions
+    authorize_pivot_table(&state, claims.user_id, &pivot_table_id, Permission::SpreadsheetsRead).await?;
     
     Ok(Json(pivot_table))
 }
@@ -389,7 +407,7 @@ pub async fn refresh_pivot_table(
     Extension(claims): Extension<Claims>,
     Path(pivot_table_id): Path<String>,
 ) -> ApiResult<StatusCode> {
-    // TODO: Check permissions
+    authorize_pivot_table(&state, claims.user_id, &pivot_table_id, Permission::SpreadsheetsWrite).await?;
     
     state.spreadsheet_repository.refresh_pivot_table(&pivot_table_id).await?;
     Ok(StatusCode::NO_CONTENT)
@@ -402,7 +420,7 @@ pub async fn import_csv(
     Path((spreadsheet_id, sheet_id)): Path<(Id, String)>,
     Json(request): Json<ImportCsvRequest>,
 ) -> ApiResult<StatusCode> {
-    // TODO: Check permissions
+    authorize_spreadsheet(&state, claims.user_id, spreadsheet_id, Permission::SpreadsheetsWrite).await?;
     
     state.spreadsheet_repository.import_csv(
         spreadsheet_id,
@@ -410,6 +428,7 @@ pub async fn import_csv(
         &request.csv_data,
         &request.start_cell.unwrap_or_else(|| "A1".to_string()),
     ).await?;
+    index_spreadsheet(&state, spreadsheet_id).await?;
     
     Ok(StatusCode::NO_CONTENT)
 }
@@ -420,7 +439,7 @@ pub async fn export_csv(
     Path(sheet_id): Path<String>,
     Query(params): Query<ExportQuery>,
 ) -> ApiResult<Json<ExportResponse>> {
-    // TODO: Check permissions
+    authorize_sheet(&state, claims.user_id, &sheet_id, Permission::SpreadsheetsRead).await?;
     
     let range = params.range.map(|r| GridRange {
         sheet_id: sheet_id.clone(),
@@ -445,7 +464,7 @@ pub async fn get_spreadsheet_analytics(
     Extension(claims): Extension<Claims>,
     Path(spreadsheet_id): Path<Id>,
 ) -> ApiResult<Json<kingshare_domain::SpreadsheetAnalytics>> {
-    // TODO: Check permissions
+    authorize_spreadsheet(&state, claims.user_id, spreadsheet_id, Permission::SpreadsheetsRead).await?;
     
     let analytics = state.spreadsheet_repository.get_spreadsheet_analytics(spreadsheet_id).await?;
     Ok(Json(analytics))
@@ -456,12 +475,72 @@ pub async fn detect_circular_references(
     Extension(claims): Extension<Claims>,
     Path(spreadsheet_id): Path<Id>,
 ) -> ApiResult<Json<Vec<kingshare_domain::CircularReference>>> {
-    // TODO: Check permissions
+    authorize_spreadsheet(&state, claims.user_id, spreadsheet_id, Permission::SpreadsheetsRead).await?;
     
     let circular_refs = state.spreadsheet_repository.detect_circular_references(spreadsheet_id).await?;
     Ok(Json(circular_refs))
 }
 
+// Access control: a spreadsheet is governed by the owner of its document
+async fn authorize_spreadsheet(
+    state: &AppState,
+    user_id: Id,
+    spreadsheet_id: Id,
+    permission: Permission,
+) -> ApiResult<()> {
+    let owner_id = state.spreadsheet_repository.get_spreadsheet_owner(spreadsheet_id).await?
+        .ok_or(ApiError::NotFound("Spreadsheet not found".to_string()))?;
+    state.authorization_service
+        .require(user_id, permission, &Resource::owned_by(owner_id))
+        .await?;
+    Ok(())
+}
+
+async fn authorize_sheet(state: &AppState, user_id: Id, sheet_id: &str, permission: Permission) -> ApiResult<()> {
+    let spreadsheet_id = state.spreadsheet_repository.get_sheet_spreadsheet_id(sheet_id).await?
+        .ok_or(ApiError::NotFound("Sheet not found".to_string()))?;
+    authorize_spreadsheet(state, user_id, spreadsheet_id, permission).await
+}
+
+async fn authorize_chart(state: &AppState, user_id: Id, chart_id: &str, permission: Permission) -> ApiResult<()> {
+    let spreadsheet_id = state.spreadsheet_repository.get_chart_spreadsheet_id(chart_id).await?
+        .ok_or(ApiError::NotFound("Chart not found".to_string()))?;
+    authorize_spreadsheet(state, user_id, spreadsheet_id, permission).await
+}
+
+async fn authorize_pivot_table(
+    state: &AppState,
+    user_id: Id,
+    pivot_table_id: &str,
+    permission: Permission,
+) -> ApiResult<()> {
+    let spreadsheet_id = state.spreadsheet_repository.get_pivot_table_spreadsheet_id(pivot_table_id).await?
+        .ok_or(ApiError::NotFound("Pivot table not found".to_string()))?;
+    authorize_spreadsheet(state, user_id, spreadsheet_id, permission).await
+}
+
+/// Keep the drive items holding a spreadsheet's document searchable by its
+/// cells. The cells are already saved, so failing to index them is only logged.
+async fn index_spreadsheet(state: &AppState, spreadsheet_id: Id) -> ApiResult<()> {
+    let Some(spreadsheet) = state.spreadsheet_repository.get_spreadsheet_by_id(spreadsheet_id).await? else {
+        return Ok(());
+    };
+    if let Err(e) = state.drive_service
+        .index_document_items(spreadsheet.document_id, &spreadsheet.document_content())
+        .await
+    {
+        warn!(spreadsheet_id = %spreadsheet_id, error = %e, "Failed to index spreadsheet cells");
+    }
+    Ok(())
+}
+
+async fn index_sheet(state: &AppState, sheet_id: &str) -> ApiResult<()> {
+    if let Some(spreadsheet_id) = state.spreadsheet_repository.get_sheet_spreadsheet_id(sheet_id).await? {
+        index_spreadsheet(state, spreadsheet_id).await?;
+    }
+    Ok(())
+}
+
 // Request/Response DTOs
 #[derive(Debug, Deserialize, Validate)]
 pub struct CreateSpreadsheetRequest {
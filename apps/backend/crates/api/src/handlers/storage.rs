@@ -0,0 +1,82 @@
+use axum::{
+    extract::{Request, State},
+    Json,
+};
+use kingshare_application::services::ScanSummary;
+use kingshare_core::{ApiResponse, Result};
+use kingshare_domain::entities::Permission;
+use serde::Serialize;
+use tracing::{info, instrument};
+use crate::{middleware::auth::require_permission, server::AppState};
+
+#[derive(Debug, Serialize)]
+pub struct StorageCleanupResult {
+    pub blobs_deleted: u64,
+    pub orphans_removed: u64,
+}
+
+#[instrument(skip(state, request))]
+pub async fn cleanup_storage(
+    State(state): State<AppState>,
+    request: Request,
+) -> Result<Json<ApiResponse<StorageCleanupResult>>> {
+    let user_id = require_permission(&state, &request, Permission::StorageManage).await?;
+
+    let blobs_deleted = state.blob_service.collect_garbage().await?;
+    let orphans_removed = state.blob_service.cleanup_orphaned_files().await?;
+
+    info!(
+        user_id = %user_id,
+        blobs_deleted = blobs_deleted,
+        orphans_removed = orphans_removed,
+        "Storage cleanup completed"
+    );
+
+    Ok(Json(ApiResponse::success(StorageCleanupResult {
+        blobs_deleted,
+        orphans_removed,
+    })))
+}
+
+#[derive(Debug, Serialize)]
+pub struct KeyRotationResult {
+    pub keys_rewrapped: u64,
+}
+
+#[instrument(skip(state, request))]
+pub async fn rotate_encryption_keys(
+    State(state): State<AppState>,
+    request: Request,
+) -> Result<Json<ApiResponse<KeyRotationResult>>> {
+    let user_id = require_permission(&state, &request, Permission::StorageManage).await?;
+
+    let keys_rewrapped = state.blob_service.rotate_encryption_keys().await?;
+
+    info!(
+        user_id = %user_id,
+        keys_rewrapped = keys_rewrapped,
+        "Encryption key rotation completed"
+    );
+
+    Ok(Json(ApiResponse::success(KeyRotationResult { keys_rewrapped })))
+}
+
+#[instrument(skip(state, request))]
+pub async fn scan_pending_files(
+    State(state): State<AppState>,
+    request: Request,
+) -> Result<Json<ApiResponse<ScanSummary>>> {
+    let user_id = require_permission(&state, &request, Permission::StorageManage).await?;
+
+    let summary = state.file_service.scan_pending_files().await?;
+
+    info!(
+        user_id = %user_id,
+        clean = summary.clean,
+        infected = summary.infected,
+        failed = summary.failed,
+        "Pending file scan completed"
+    );
+
+    Ok(Json(ApiResponse::success(summary)))
+}
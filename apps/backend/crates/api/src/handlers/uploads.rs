@@ -0,0 +1,187 @@
+use axum::{
+    body::Body,
+    extract::{Path, Request, State},
+    http::{header, StatusCode},
+    response::Response,
+    Extension, Json,
+};
+use kingshare_core::{ApiResponse, Error, Id, Result};
+use kingshare_domain::{
+    entities::{ApiScope, ApiTokenGrant, CreateUploadSessionRequest, Permission, Resource, UploadSessionInfo},
+    services::{Claims, FileReader},
+    FileMetadata,
+};
+use futures_util::TryStreamExt;
+use tokio_util::io::StreamReader;
+use tracing::{info, instrument};
+use validator::Validate;
+use crate::{
+    middleware::auth::{ClaimsExt, GrantExt},
+    server::AppState,
+};
+
+pub const TUS_RESUMABLE: &str = "Tus-Resumable";
+pub const TUS_VERSION: &str = "1.0.0";
+pub const UPLOAD_OFFSET: &str = "Upload-Offset";
+pub const UPLOAD_LENGTH: &str = "Upload-Length";
+pub const OFFSET_OCTET_STREAM: &str = "application/offset+octet-stream";
+
+fn upload_headers_response(status: StatusCode, session: &UploadSessionInfo) -> Result<Response> {
+    Response::builder()
+        .status(status)
+        .header(TUS_RESUMABLE, TUS_VERSION)
+        .header(UPLOAD_OFFSET, session.offset)
+        .header(UPLOAD_LENGTH, session.total_size)
+        .header(header::CACHE_CONTROL, "no-store")
+        .body(Body::empty())
+        .map_err(|e| Error::Internal(format!("Failed to create response: {}", e)))
+}
+
+#[instrument(skip(state, claims, grant, payload))]
+pub async fn create_upload(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    grant: Option<Extension<ApiTokenGrant>>,
+    Json(payload): Json<CreateUploadSessionRequest>,
+) -> Result<Response> {
+    // Validate payload
+    payload.validate()
+        .map_err(|e| Error::Validation(e.to_string()))?;
+
+    let user_id = claims.user_id()?;
+    grant.require_scope(ApiScope::FilesWrite)?;
+    // Guests can look but not add
+    state
+        .authorization_service
+        .require(user_id, Permission::FilesWrite, &Resource::owned_by(user_id))
+        .await?;
+
+    let session = state.upload_service.create_session(user_id, payload).await?;
+
+    info!(
+        user_id = %user_id,
+        upload_id = %session.id,
+        total_size = session.total_size,
+        "Upload session created"
+    );
+
+    let body = serde_json::to_vec(&ApiResponse::success(&session))?;
+
+    Response::builder()
+        .status(StatusCode::CREATED)
+        .header(TUS_RESUMABLE, TUS_VERSION)
+        .header(UPLOAD_OFFSET, session.offset)
+        .header(UPLOAD_LENGTH, session.total_size)
+        .header(header::LOCATION, format!("/api/v1/uploads/{}", session.id))
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body))
+        .map_err(|e| Error::Internal(format!("Failed to create response: {}", e)))
+}
+
+#[instrument(skip(state, request))]
+pub async fn get_upload_offset(
+    State(state): State<AppState>,
+    Path(id): Path<Id>,
+    request: Request,
+) -> Result<Response> {
+    // Extract user ID from JWT claims
+    let user_id = request.require_user_id()
+        .map_err(|_| Error::Authentication("Authentication required".to_string()))?;
+    request.require_scope(ApiScope::FilesWrite)?;
+
+    let session = state.upload_service.get_session(id, user_id).await?;
+
+    upload_headers_response(StatusCode::OK, &session)
+}
+
+#[instrument(skip(state, request))]
+pub async fn upload_chunk(
+    State(state): State<AppState>,
+    Path(id): Path<Id>,
+    request: Request,
+) -> Result<Response> {
+    // Extract user ID from JWT claims
+    let user_id = request.require_user_id()
+        .map_err(|_| Error::Authentication("Authentication required".to_string()))?;
+    request.require_scope(ApiScope::FilesWrite)?;
+
+    let content_type = request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("");
+
+    if content_type != OFFSET_OCTET_STREAM {
+        return Err(Error::BadRequest(format!(
+            "Content-Type must be {}",
+            OFFSET_OCTET_STREAM
+        )));
+    }
+
+    let offset = request
+        .headers()
+        .get(UPLOAD_OFFSET)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<i64>().ok())
+        .ok_or_else(|| Error::BadRequest("Missing or invalid Upload-Offset header".to_string()))?;
+
+    // Streamed through to storage, which stops reading at the remaining upload length
+    let body = request.into_body().into_data_stream().map_err(std::io::Error::other);
+    let reader: FileReader = Box::pin(StreamReader::new(body));
+
+    let session = state
+        .upload_service
+        .append_chunk(id, user_id, offset, reader)
+        .await?;
+
+    info!(
+        user_id = %user_id,
+        upload_id = %id,
+        offset = session.offset,
+        total_size = session.total_size,
+        "Upload chunk received"
+    );
+
+    upload_headers_response(StatusCode::NO_CONTENT, &session)
+}
+
+#[instrument(skip(state, request))]
+pub async fn finalize_upload(
+    State(state): State<AppState>,
+    Path(id): Path<Id>,
+    request: Request,
+) -> Result<Json<ApiResponse<FileMetadata>>> {
+    // Extract user ID from JWT claims
+    let user_id = request.require_user_id()
+        .map_err(|_| Error::Authentication("Authentication required".to_string()))?;
+    request.require_scope(ApiScope::FilesWrite)?;
+
+    let file_metadata = state.upload_service.finalize(id, user_id).await?;
+
+    info!(
+        user_id = %user_id,
+        upload_id = %id,
+        file_id = %file_metadata.id,
+        "Resumable upload finalized"
+    );
+
+    Ok(Json(ApiResponse::success(file_metadata)))
+}
+
+#[instrument(skip(state, request))]
+pub async fn abort_upload(
+    State(state): State<AppState>,
+    Path(id): Path<Id>,
+    request: Request,
+) -> Result<Json<ApiResponse<String>>> {
+    // Extract user ID from JWT claims
+    let user_id = request.require_user_id()
+        .map_err(|_| Error::Authentication("Authentication required".to_string()))?;
+    request.require_scope(ApiScope::FilesWrite)?;
+
+    state.upload_service.abort(id, user_id).await?;
+
+    info!(user_id = %user_id, upload_id = %id, "Upload session aborted");
+
+    Ok(Json(ApiResponse::success("Upload aborted successfully".to_string())))
+}
@@ -0,0 +1,157 @@
+use axum::{
+    extract::{Path, Query, Request, State},
+    Extension, Json,
+};
+use kingshare_application::services::ImpersonationSession;
+use kingshare_core::{ApiResponse, Id, PaginatedResponse, PaginationParams, Result};
+use kingshare_domain::{
+    entities::{ChangeUserRoleRequest, OwnershipTransfer, Permission, TransferOwnershipRequest, UserFilter},
+    services::Claims,
+    UserProfile,
+};
+use tracing::{info, instrument};
+use crate::{
+    handlers::client::Client,
+    middleware::auth::{ensure_permission, require_permission},
+    server::AppState,
+};
+
+/// Find users by name or email address, role and account state
+#[instrument(skip(state, request))]
+pub async fn search_users(
+    State(state): State<AppState>,
+    Query(filter): Query<UserFilter>,
+    Query(params): Query<PaginationParams>,
+    request: Request,
+) -> Result<Json<ApiResponse<PaginatedResponse<UserProfile>>>> {
+    require_permission(&state, &request, Permission::UsersManage).await?;
+
+    let users = state.user_admin_service.search_users(&filter, params).await?;
+
+    Ok(Json(ApiResponse::success(users)))
+}
+
+#[instrument(skip(state, request))]
+pub async fn get_user(
+    State(state): State<AppState>,
+    Path(user_id): Path<Id>,
+    request: Request,
+) -> Result<Json<ApiResponse<UserProfile>>> {
+    require_permission(&state, &request, Permission::UsersManage).await?;
+
+    let user = state.user_admin_service.get_user(user_id).await?;
+
+    Ok(Json(ApiResponse::success(user)))
+}
+
+#[instrument(skip(state, claims, payload))]
+pub async fn change_user_role(
+    State(state): State<AppState>,
+    Path(user_id): Path<Id>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<ChangeUserRoleRequest>,
+) -> Result<Json<ApiResponse<UserProfile>>> {
+    let admin_id = ensure_permission(&state, &claims, Permission::UsersManage).await?;
+
+    let user = state
+        .user_admin_service
+        .change_role(admin_id, user_id, payload.role)
+        .await?;
+
+    Ok(Json(ApiResponse::success(user)))
+}
+
+#[instrument(skip(state, request))]
+pub async fn deactivate_user(
+    State(state): State<AppState>,
+    Path(user_id): Path<Id>,
+    request: Request,
+) -> Result<Json<ApiResponse<UserProfile>>> {
+    let admin_id = require_permission(&state, &request, Permission::UsersManage).await?;
+
+    let user = state.user_admin_service.deactivate_user(admin_id, user_id).await?;
+
+    Ok(Json(ApiResponse::success(user)))
+}
+
+#[instrument(skip(state, request))]
+pub async fn reactivate_user(
+    State(state): State<AppState>,
+    Path(user_id): Path<Id>,
+    request: Request,
+) -> Result<Json<ApiResponse<UserProfile>>> {
+    let admin_id = require_permission(&state, &request, Permission::UsersManage).await?;
+
+    let user = state.user_admin_service.reactivate_user(admin_id, user_id).await?;
+
+    Ok(Json(ApiResponse::success(user)))
+}
+
+#[instrument(skip(state, request))]
+pub async fn force_password_reset(
+    State(state): State<AppState>,
+    Path(user_id): Path<Id>,
+    request: Request,
+) -> Result<Json<ApiResponse<String>>> {
+    let admin_id = require_permission(&state, &request, Permission::UsersManage).await?;
+
+    state
+        .user_admin_service
+        .force_password_reset(admin_id, user_id)
+        .await?;
+
+    Ok(Json(ApiResponse::success(
+        "The user has been signed out and sent a password reset link".to_string(),
+    )))
+}
+
+/// Act as the user with a short-lived access token, such as to reproduce
+/// a problem they report. Every use is recorded against the administrator.
+#[instrument(skip(state, client, request))]
+pub async fn impersonate_user(
+    State(state): State<AppState>,
+    Path(user_id): Path<Id>,
+    Client(client): Client,
+    request: Request,
+) -> Result<Json<ApiResponse<ImpersonationSession>>> {
+    let admin_id = require_permission(&state, &request, Permission::UsersManage).await?;
+
+    let session = state
+        .user_admin_service
+        .impersonate(admin_id, user_id, &client)
+        .await?;
+
+    Ok(Json(ApiResponse::success(session)))
+}
+
+/// Hand the user's files, shares, drives and documents to another user
+#[instrument(skip(state, claims, payload))]
+pub async fn transfer_ownership(
+    State(state): State<AppState>,
+    Path(user_id): Path<Id>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<TransferOwnershipRequest>,
+) -> Result<Json<ApiResponse<OwnershipTransfer>>> {
+    let admin_id = ensure_permission(&state, &claims, Permission::UsersManage).await?;
+
+    let transfer = state
+        .user_admin_service
+        .transfer_ownership(admin_id, user_id, payload.to_user_id, payload.delete_user)
+        .await?;
+
+    info!(admin_id = %admin_id, user_id = %user_id, files = transfer.files, "Ownership transferred");
+    Ok(Json(ApiResponse::success(transfer)))
+}
+
+#[instrument(skip(state, request))]
+pub async fn delete_user(
+    State(state): State<AppState>,
+    Path(user_id): Path<Id>,
+    request: Request,
+) -> Result<Json<ApiResponse<String>>> {
+    let admin_id = require_permission(&state, &request, Permission::UsersManage).await?;
+
+    state.user_admin_service.delete_user(admin_id, user_id).await?;
+
+    Ok(Json(ApiResponse::success("User deleted".to_string())))
+}
@@ -1,10 +1,10 @@
 use axum::{extract::{Request, State}, Json};
 use kingshare_core::{ApiResponse, Error, Result};
-use kingshare_domain::{entities::UpdateUserRequest, UserProfile};
+use kingshare_domain::{entities::{Permission, UpdateUserRequest}, UserProfile};
 use serde::Deserialize;
 use tracing::{info, instrument};
 use validator::Validate;
-use crate::{middleware::auth::ClaimsExt, server::AppState};
+use crate::{middleware::auth::{require_permission, ClaimsExt}, server::AppState};
 
 #[derive(Debug, Deserialize, Validate)]
 pub struct UpdateProfileRequest {
@@ -62,11 +62,14 @@ pub async fn update_profile(
     Ok(Json(ApiResponse::success(updated_profile)))
 }
 
-#[instrument(skip(state))]
+#[instrument(skip(state, request))]
 pub async fn list_users(
     State(state): State<AppState>,
     // Query(params): Query<PaginationParams>
+    request: Request,
 ) -> Result<Json<ApiResponse<Vec<UserProfile>>>> {
+    require_permission(&state, &request, Permission::UsersManage).await?;
+
     // Use default pagination for now
     let params = kingshare_core::PaginationParams::default();
     let paginated_users = state.user_service.list_users(params).await?;
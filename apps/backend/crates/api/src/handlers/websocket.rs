@@ -5,9 +5,9 @@ use axum::{
     },
     response::Response,
 };
-use kingshare_core::{Error, Result};
+use kingshare_core::{Error, Id, Result};
 use serde::Deserialize;
-use tracing::{info, instrument};
+use tracing::{info, instrument, warn};
 use crate::server::AppState;
 
 #[derive(Debug, Deserialize)]
@@ -15,7 +15,7 @@ pub struct WebSocketQuery {
     token: Option<String>,
 }
 
-#[instrument(skip(ws, state))]
+#[instrument(skip(ws, params, state))]
 pub async fn websocket_handler(
     ws: WebSocketUpgrade,
     Query(params): Query<WebSocketQuery>,
@@ -29,31 +29,39 @@ pub async fn websocket_handler(
         "WebSocket connection request"
     );
 
-    // Verify token if provided
-    let user_id = if let Some(token) = params.token {
-        // In a real implementation, you'd verify the JWT token here
-        // For now, we'll just parse it as a UUID for demonstration
-        match uuid::Uuid::parse_str(&token) {
-            Ok(id) => Some(id),
-            Err(_) => {
+    // Verify token if provided; the session lets a sign-out close this connection
+    let claims = match params.token {
+        Some(token) => match state.auth_service.verify_token(&token).await {
+            Ok(claims) => Some(claims),
+            Err(e) => {
                 // Invalid token, but we can still allow anonymous connections
+                warn!(connection_id = %connection_id, error = %e, "WebSocket token rejected");
                 None
             }
-        }
-    } else {
-        None
+        },
+        None => None,
     };
+    let user_id = claims.as_ref().and_then(|claims| claims.sub.parse::<Id>().ok());
+    let session_id = claims
+        .as_ref()
+        .and_then(|claims| claims.sid.as_deref())
+        .and_then(|sid| sid.parse::<Id>().ok());
 
-    Ok(ws.on_upgrade(move |socket| handle_socket(socket, connection_id, user_id, state)))
+    Ok(ws.on_upgrade(move |socket| handle_socket(socket, connection_id, user_id, session_id, state)))
 }
 
 async fn handle_socket(
     socket: WebSocket,
     connection_id: String,
-    user_id: Option<uuid::Uuid>,
+    user_id: Option<Id>,
+    session_id: Option<Id>,
     state: AppState,
 ) {
-    if let Err(e) = state.websocket_service.handle_connection(socket, connection_id.clone(), user_id).await {
+    if let Err(e) = state
+        .websocket_service
+        .handle_connection(socket, connection_id.clone(), user_id, session_id)
+        .await
+    {
         tracing::error!(
             connection_id = %connection_id,
             error = %e,
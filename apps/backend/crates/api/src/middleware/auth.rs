@@ -3,13 +3,32 @@ use axum::{
     http::{header, StatusCode},
     middleware::Next,
     response::Response,
+    Extension,
 };
-use kingshare_domain::services::Claims;
-use kingshare_infrastructure::JwtAuthService;
-use std::sync::Arc;
+use kingshare_core::{Error, Id};
+use kingshare_domain::{
+    entities::{ApiScope, ApiTokenGrant, Permission, Resource, API_TOKEN_PREFIX},
+    services::Claims,
+};
+use std::future::Future;
 use tracing::{info, warn};
 use crate::server::AppState;
 
+/// Verify a bearer token: personal access tokens by their prefix, anything
+/// else as a JWT. Access tokens also yield what they are allowed to do.
+async fn authenticate_bearer(
+    state: &AppState,
+    token: &str,
+) -> kingshare_core::Result<(Claims, Option<ApiTokenGrant>)> {
+    if token.starts_with(API_TOKEN_PREFIX) {
+        let (claims, grant) = state.api_token_service.authenticate(token).await?;
+        Ok((claims, Some(grant)))
+    } else {
+        // Revocations are shared by every request and replica
+        Ok((state.auth_service.verify_token(token).await?, None))
+    }
+}
+
 pub async fn auth_middleware(
     State(state): State<AppState>,
     mut request: Request,
@@ -23,20 +42,21 @@ pub async fn auth_middleware(
 
     if let Some(auth_header) = auth_header {
         if let Some(token) = auth_header.strip_prefix("Bearer ") {
-            // Create auth service
-            let auth_service = Arc::new(JwtAuthService::new(state.config.auth.clone()));
-
-            // Verify token
-            match auth_service.verify_token(token).await {
-                Ok(claims) => {
-                    // Add claims to request extensions
-                    request.extensions_mut().insert(claims);
-                    
+            match authenticate_bearer(&state, token).await {
+                Ok((claims, grant)) => {
                     info!(
                         user_id = %claims.sub,
                         username = %claims.username,
+                        api_token = grant.is_some(),
+                        impersonator = ?claims.act,
                         "User authenticated successfully"
                     );
+
+                    // Add claims to request extensions
+                    request.extensions_mut().insert(claims);
+                    if let Some(grant) = grant {
+                        request.extensions_mut().insert(grant);
+                    }
                 }
                 Err(e) => {
                     warn!(error = %e, "Token verification failed");
@@ -68,18 +88,20 @@ pub async fn optional_auth_middleware(
 
     if let Some(auth_header) = auth_header {
         if let Some(token) = auth_header.strip_prefix("Bearer ") {
-            // Create auth service
-            let auth_service = Arc::new(JwtAuthService::new(state.config.auth.clone()));
-
             // Verify token (but don't fail if invalid)
-            if let Ok(claims) = auth_service.verify_token(token).await {
-                request.extensions_mut().insert(claims);
-                
+            if let Ok((claims, grant)) = authenticate_bearer(&state, token).await {
                 info!(
                     user_id = %claims.sub,
                     username = %claims.username,
+                    api_token = grant.is_some(),
+                    impersonator = ?claims.act,
                     "User authenticated successfully (optional)"
                 );
+
+                request.extensions_mut().insert(claims);
+                if let Some(grant) = grant {
+                    request.extensions_mut().insert(grant);
+                }
             }
         }
     }
@@ -87,11 +109,80 @@ pub async fn optional_auth_middleware(
     next.run(request).await
 }
 
+/// Keeps personal access tokens away from routes that manage the account
+/// itself, such as sessions, MFA and the tokens themselves. Runs after
+/// `auth_middleware`.
+pub async fn require_session(request: Request, next: Next) -> Result<Response, StatusCode> {
+    if request.api_token().is_some() {
+        warn!("Personal access token used on a session-only route");
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    Ok(next.run(request).await)
+}
+
+/// Keeps administrators acting as a user away from that user's sessions,
+/// second factors and access tokens. Runs after `auth_middleware`.
+pub async fn require_account_holder(request: Request, next: Next) -> Result<Response, StatusCode> {
+    if let Some(actor) = request.claims().and_then(|claims| claims.act.as_deref()) {
+        warn!(impersonator = %actor, "Impersonation token used on an account security route");
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    Ok(next.run(request).await)
+}
+
+/// The user making the request, provided they hold `permission` globally.
+/// The claims are copied out first: a request body isn't `Sync`, so
+/// borrowing the request across the check would make handlers `!Send`.
+pub fn require_permission<'a>(
+    state: &'a AppState,
+    request: &Request,
+    permission: Permission,
+) -> impl Future<Output = kingshare_core::Result<Id>> + Send + 'a {
+    let claims = request.claims().cloned();
+
+    async move {
+        let claims = claims.ok_or_else(|| Error::Authentication("Authentication required".to_string()))?;
+        ensure_permission(state, &claims, permission).await
+    }
+}
+
+/// For handlers that read the body and so take `Extension<Claims>` rather than the request
+pub async fn ensure_permission(
+    state: &AppState,
+    claims: &Claims,
+    permission: Permission,
+) -> kingshare_core::Result<Id> {
+    let user_id = claims.user_id()?;
+
+    state
+        .authorization_service
+        .require(user_id, permission, &Resource::global())
+        .await?;
+    Ok(user_id)
+}
+
+
+/// Whether a request may act with `scope` on a resource in `drive_id`.
+/// Requests signed in with a session may do anything their user can.
+pub fn check_scope(
+    grant: Option<&ApiTokenGrant>,
+    scope: ApiScope,
+    drive_id: Option<Id>,
+) -> kingshare_core::Result<()> {
+    grant.map_or(Ok(()), |grant| grant.require(scope, drive_id))
+}
+
 // Extension trait to easily extract claims from request
 pub trait ClaimsExt {
     fn claims(&self) -> Option<&Claims>;
     fn user_id(&self) -> Option<uuid::Uuid>;
     fn require_user_id(&self) -> Result<uuid::Uuid, StatusCode>;
+    fn api_token(&self) -> Option<&ApiTokenGrant>;
+    /// Scope check for resources outside any drive
+    fn require_scope(&self, scope: ApiScope) -> kingshare_core::Result<()>;
+    fn require_drive_scope(&self, scope: ApiScope, drive_id: Id) -> kingshare_core::Result<()>;
 }
 
 impl ClaimsExt for Request {
@@ -107,4 +198,33 @@ impl ClaimsExt for Request {
     fn require_user_id(&self) -> Result<uuid::Uuid, StatusCode> {
         self.user_id().ok_or(StatusCode::UNAUTHORIZED)
     }
+
+    fn api_token(&self) -> Option<&ApiTokenGrant> {
+        self.extensions().get::<ApiTokenGrant>()
+    }
+
+    fn require_scope(&self, scope: ApiScope) -> kingshare_core::Result<()> {
+        check_scope(self.api_token(), scope, None)
+    }
+
+    fn require_drive_scope(&self, scope: ApiScope, drive_id: Id) -> kingshare_core::Result<()> {
+        check_scope(self.api_token(), scope, Some(drive_id))
+    }
+}
+
+/// The scope checks of `ClaimsExt` for handlers that take their claims as
+/// extractors, with the grant as `Option<Extension<ApiTokenGrant>>`
+pub trait GrantExt {
+    fn require_scope(&self, scope: ApiScope) -> kingshare_core::Result<()>;
+    fn require_drive_scope(&self, scope: ApiScope, drive_id: Id) -> kingshare_core::Result<()>;
+}
+
+impl GrantExt for Option<Extension<ApiTokenGrant>> {
+    fn require_scope(&self, scope: ApiScope) -> kingshare_core::Result<()> {
+        check_scope(self.as_deref(), scope, None)
+    }
+
+    fn require_drive_scope(&self, scope: ApiScope, drive_id: Id) -> kingshare_core::Result<()> {
+        check_scope(self.as_deref(), scope, Some(drive_id))
+    }
 }
\ No newline at end of file
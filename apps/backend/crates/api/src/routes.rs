@@ -15,6 +15,14 @@ pub fn create_routes(state: AppState) -> Router {
         .route("/api/v1/auth/register", post(handlers::auth::register))
         .route("/api/v1/auth/login", post(handlers::auth::login))
         .route("/api/v1/auth/refresh", post(handlers::auth::refresh_token))
+        .route("/api/v1/auth/mfa/verify", post(handlers::auth::verify_mfa_login))
+        .route("/api/v1/auth/mfa/challenge/enroll", post(handlers::auth::enroll_mfa_login))
+        .route("/api/v1/auth/verify-email", post(handlers::auth::verify_email))
+        .route("/api/v1/auth/verify-email/resend", post(handlers::auth::resend_verification_email))
+        .route("/api/v1/auth/password/forgot", post(handlers::auth::forgot_password))
+        .route("/api/v1/auth/password/reset", post(handlers::auth::reset_password))
+        .route("/api/v1/auth/oidc/authorize", get(handlers::auth::start_oidc_login))
+        .route("/api/v1/auth/oidc/callback", post(handlers::auth::complete_oidc_login))
         
         // Public share access
         .route("/api/v1/shares/token/:token", get(handlers::shares::get_share_by_token))
@@ -23,68 +31,70 @@ pub fn create_routes(state: AppState) -> Router {
         // WebSocket (handles auth internally)
         .route("/ws", get(handlers::websocket::websocket_handler));
 
+    // Account security, for the account holder's own sessions only
+    let account_routes = Router::new()
+        .route("/api/v1/auth/sessions", get(handlers::auth::list_sessions))
+        .route("/api/v1/auth/sessions", axum::routing::delete(handlers::auth::revoke_other_sessions))
+        .route("/api/v1/auth/sessions/:id", axum::routing::delete(handlers::auth::revoke_session))
+        .route("/api/v1/auth/mfa", get(handlers::auth::get_mfa_status))
+        .route("/api/v1/auth/mfa/enroll", post(handlers::auth::enroll_mfa))
+        .route("/api/v1/auth/mfa/confirm", post(handlers::auth::confirm_mfa))
+        .route("/api/v1/auth/mfa/disable", post(handlers::auth::disable_mfa))
+        .route("/api/v1/auth/mfa/recovery-codes", post(handlers::auth::regenerate_recovery_codes))
+        .route("/api/v1/auth/tokens", get(handlers::auth::list_api_tokens))
+        .route("/api/v1/auth/tokens", post(handlers::auth::create_api_token))
+        .route("/api/v1/auth/tokens/:id", axum::routing::delete(handlers::auth::revoke_api_token))
+        
+        .route_layer(middleware::from_fn(auth::require_account_holder))
+        .route_layer(middleware::from_fn(auth::require_session))
+        .layer(middleware::from_fn_with_state(state.clone(), auth::auth_middleware));
+
     // Protected routes (authentication required)
     let protected_routes = Router::new()
         // Authentication
         .route("/api/v1/auth/logout", post(handlers::auth::logout))
+        .route("/api/v1/auth/permissions", get(handlers::roles::get_my_permissions))
         
         // User routes
         .route("/api/v1/users", get(handlers::users::list_users))
         .route("/api/v1/users/profile", get(handlers::users::get_profile))
         .route("/api/v1/users/profile", post(handlers::users::update_profile))
         
-        // File routes
-        .route("/api/v1/files", post(handlers::files::upload_file))
-        .route("/api/v1/files/:id", post(handlers::files::update_file))
-        .route("/api/v1/files/:id", axum::routing::delete(handlers::files::delete_file))
-        .route("/api/v1/files/stats", get(handlers::files::get_storage_stats))
-        
-        // Share routes
-        .route("/api/v1/shares", get(handlers::shares::list_shares))
-        .route("/api/v1/shares", post(handlers::shares::create_share))
-        .route("/api/v1/shares/:id", get(handlers::shares::get_share))
-        .route("/api/v1/shares/:id", post(handlers::shares::update_share))
-        .route("/api/v1/shares/:id", axum::routing::delete(handlers::shares::delete_share))
-        .route("/api/v1/shares/:id/regenerate", post(handlers::shares::regenerate_share_token))
-        
         // WebSocket management
         .route("/api/v1/ws/stats", get(handlers::websocket::get_websocket_stats))
         .route("/api/v1/ws/cleanup", post(handlers::websocket::cleanup_websocket_connections))
         
-        // Drive routes
-        .route("/api/v1/drives", post(handlers::drive::create_drive))
-        .route("/api/v1/drives", get(handlers::drive::get_user_drives))
-        .route("/api/v1/drives/:drive_id", get(handlers::drive::get_drive))
-        .route("/api/v1/drives/:drive_id", axum::routing::patch(handlers::drive::update_drive))
-        .route("/api/v1/drives/:drive_id", axum::routing::delete(handlers::drive::delete_drive))
-        .route("/api/v1/drives/:drive_id/folders", post(handlers::drive::create_folder))
-        .route("/api/v1/drives/:drive_id/folders/:folder_id/contents", get(handlers::drive::get_folder_contents))
-        .route("/api/v1/drives/:drive_id/search", get(handlers::drive::search_drive))
-        .route("/api/v1/drives/:drive_id/activity", get(handlers::drive::get_drive_activity))
-        .route("/api/v1/drives/:drive_id/storage", get(handlers::drive::get_storage_usage))
-        .route("/api/v1/drives/:drive_id/trash", get(handlers::drive::get_trash_items))
-        .route("/api/v1/drives/:drive_id/trash", axum::routing::delete(handlers::drive::empty_trash))
-        
-        // Folder routes
-        .route("/api/v1/folders/:folder_id", axum::routing::patch(handlers::drive::update_folder))
-        .route("/api/v1/folders/:folder_id", axum::routing::delete(handlers::drive::delete_folder))
-        
-        // Drive item routes
-        .route("/api/v1/items/:item_id", get(handlers::drive::get_drive_item))
-        .route("/api/v1/items/:item_id/move", post(handlers::drive::move_drive_item))
-        .route("/api/v1/items/:item_id/copy", post(handlers::drive::copy_drive_item))
-        .route("/api/v1/items/:item_id/star", post(handlers::drive::star_item))
-        .route("/api/v1/items/:item_id/star", axum::routing::delete(handlers::drive::unstar_item))
-        .route("/api/v1/items/:item_id/trash", post(handlers::drive::move_to_trash))
-        .route("/api/v1/items/:item_id/restore", post(handlers::drive::restore_from_trash))
-        .route("/api/v1/items/:item_id/share", post(handlers::drive::share_item))
-        .route("/api/v1/items/:item_id/share/link", post(handlers::drive::create_sharing_link))
-        .route("/api/v1/items/:item_id/share/link", axum::routing::delete(handlers::drive::revoke_sharing_link))
-        
-        // Special collections
-        .route("/api/v1/recent", get(handlers::drive::get_recent_items))
-        .route("/api/v1/starred", get(handlers::drive::get_starred_items))
-        .route("/api/v1/shared-with-me", get(handlers::drive::get_shared_with_me))
+        // Storage maintenance
+        .route("/api/v1/admin/storage/cleanup", post(handlers::storage::cleanup_storage))
+        .route("/api/v1/admin/storage/rotate-keys", post(handlers::storage::rotate_encryption_keys))
+        .route("/api/v1/admin/storage/scan-pending", post(handlers::storage::scan_pending_files))
+        .route("/api/v1/admin/quotas/users/:user_id", get(handlers::quotas::get_user_quota))
+        .route("/api/v1/admin/quotas/users/:user_id", axum::routing::put(handlers::quotas::set_user_quota))
+        .route("/api/v1/admin/quotas/drives/:drive_id", get(handlers::quotas::get_drive_quota))
+        .route("/api/v1/admin/quotas/drives/:drive_id", axum::routing::put(handlers::quotas::set_drive_quota))
+        .route("/api/v1/admin/lockouts", get(handlers::lockouts::list_lockouts))
+        .route("/api/v1/admin/lockouts/unlock", post(handlers::lockouts::unlock))
+        .route("/api/v1/admin/users/:user_id/unlock", post(handlers::lockouts::unlock_user))
+        .route("/api/v1/admin/security-events", get(handlers::lockouts::list_security_events))
+        .route("/api/v1/admin/users", get(handlers::user_admin::search_users))
+        .route("/api/v1/admin/users/:user_id", get(handlers::user_admin::get_user))
+        .route("/api/v1/admin/users/:user_id", axum::routing::delete(handlers::user_admin::delete_user))
+        .route("/api/v1/admin/users/:user_id/role", axum::routing::put(handlers::user_admin::change_user_role))
+        .route("/api/v1/admin/users/:user_id/deactivate", post(handlers::user_admin::deactivate_user))
+        .route("/api/v1/admin/users/:user_id/reactivate", post(handlers::user_admin::reactivate_user))
+        .route("/api/v1/admin/users/:user_id/password-reset", post(handlers::user_admin::force_password_reset))
+        .route("/api/v1/admin/users/:user_id/impersonate", post(handlers::user_admin::impersonate_user))
+        .route("/api/v1/admin/users/:user_id/transfer", post(handlers::user_admin::transfer_ownership))
+        .route("/api/v1/admin/roles", get(handlers::roles::list_roles))
+        .route("/api/v1/admin/roles", post(handlers::roles::create_role))
+        .route("/api/v1/admin/roles/:role_id", axum::routing::put(handlers::roles::update_role))
+        .route("/api/v1/admin/roles/:role_id", axum::routing::delete(handlers::roles::delete_role))
+        .route("/api/v1/admin/users/:user_id/roles", get(handlers::roles::list_user_roles))
+        .route("/api/v1/admin/users/:user_id/roles", post(handlers::roles::assign_role))
+        .route("/api/v1/admin/role-assignments/:assignment_id", axum::routing::delete(handlers::roles::revoke_role_assignment))
+        .route("/api/v1/admin/teams", get(handlers::roles::list_teams))
+        .route("/api/v1/admin/teams", post(handlers::roles::create_team))
+        .route("/api/v1/admin/teams/:team_id", axum::routing::delete(handlers::roles::delete_team))
         
         // Document routes
         .route("/api/v1/documents", post(handlers::documents::create_document))
@@ -184,8 +194,6 @@ pub fn create_routes(state: AppState) -> Router {
         .route("/api/v1/forms/:form_id/qr-code", get(handlers::forms::get_qr_code))
         
         // Form responses (authenticated)
-        .route("/api/v1/forms/:form_id/responses", get(handlers::forms::get_form_responses))
-        .route("/api/v1/responses/:response_id", get(handlers::forms::get_response))
         .route("/api/v1/responses/:response_id", axum::routing::patch(handlers::forms::update_response))
         .route("/api/v1/responses/:response_id", axum::routing::delete(handlers::forms::delete_response))
         .route("/api/v1/responses/:response_id/approve", post(handlers::forms::approve_response))
@@ -195,7 +203,6 @@ pub fn create_routes(state: AppState) -> Router {
         .route("/api/v1/forms/:form_id/analytics", get(handlers::forms::get_form_analytics))
         .route("/api/v1/forms/:form_id/summary", get(handlers::forms::get_response_summary))
         .route("/api/v1/forms/:form_id/insights", get(handlers::forms::generate_insights))
-        .route("/api/v1/forms/:form_id/export", post(handlers::forms::export_responses))
         
         // Form templates
         .route("/api/v1/forms/:form_id/template", post(handlers::forms::save_as_template))
@@ -210,6 +217,76 @@ pub fn create_routes(state: AppState) -> Router {
         .route("/api/v1/forms/:form_id/validate", get(handlers::forms::validate_form))
         .route("/api/v1/forms/:form_id/health", get(handlers::forms::get_health_score))
         
+        // Personal access tokens only reach the routes below
+        .route_layer(middleware::from_fn(auth::require_session))
+        .layer(middleware::from_fn_with_state(state.clone(), auth::auth_middleware));
+
+    // Routes open to personal access tokens as well as sessions; handlers
+    // check the token's scopes
+    let token_routes = Router::new()
+        // File routes
+        .route("/api/v1/files", post(handlers::files::upload_file))
+        .route("/api/v1/files/:id", post(handlers::files::update_file))
+        .route("/api/v1/files/:id", axum::routing::delete(handlers::files::delete_file))
+        .route("/api/v1/files/stats", get(handlers::files::get_storage_stats))
+        
+        // Resumable upload routes
+        .route("/api/v1/uploads", post(handlers::uploads::create_upload))
+        .route("/api/v1/uploads/:id", axum::routing::head(handlers::uploads::get_upload_offset))
+        .route("/api/v1/uploads/:id", axum::routing::patch(handlers::uploads::upload_chunk))
+        .route("/api/v1/uploads/:id", axum::routing::delete(handlers::uploads::abort_upload))
+        .route("/api/v1/uploads/:id/finalize", post(handlers::uploads::finalize_upload))
+        
+        // Share routes
+        .route("/api/v1/shares", get(handlers::shares::list_shares))
+        .route("/api/v1/shares", post(handlers::shares::create_share))
+        .route("/api/v1/shares/:id", get(handlers::shares::get_share))
+        .route("/api/v1/shares/:id", post(handlers::shares::update_share))
+        .route("/api/v1/shares/:id", axum::routing::delete(handlers::shares::delete_share))
+        .route("/api/v1/shares/:id/regenerate", post(handlers::shares::regenerate_share_token))
+        
+        // Drive routes
+        .route("/api/v1/drives", post(handlers::drive::create_drive))
+        .route("/api/v1/drives", get(handlers::drive::get_user_drives))
+        .route("/api/v1/drives/:drive_id", get(handlers::drive::get_drive))
+        .route("/api/v1/drives/:drive_id", axum::routing::patch(handlers::drive::update_drive))
+        .route("/api/v1/drives/:drive_id", axum::routing::delete(handlers::drive::delete_drive))
+        .route("/api/v1/drives/:drive_id/folders", post(handlers::drive::create_folder))
+        .route("/api/v1/drives/:drive_id/contents", get(handlers::drive::get_drive_contents))
+        .route("/api/v1/drives/:drive_id/folders/:folder_id/contents", get(handlers::drive::get_folder_contents))
+        .route("/api/v1/drives/:drive_id/search", get(handlers::drive::search_drive))
+        .route("/api/v1/drives/:drive_id/activity", get(handlers::drive::get_drive_activity))
+        .route("/api/v1/drives/:drive_id/storage", get(handlers::drive::get_storage_usage))
+        .route("/api/v1/drives/:drive_id/trash", get(handlers::drive::get_trash_items))
+        .route("/api/v1/drives/:drive_id/trash", axum::routing::delete(handlers::drive::empty_trash))
+        
+        // Folder routes
+        .route("/api/v1/folders/:folder_id", axum::routing::patch(handlers::drive::update_folder))
+        .route("/api/v1/folders/:folder_id", axum::routing::delete(handlers::drive::delete_folder))
+        
+        // Drive item routes
+        .route("/api/v1/items/:item_id", get(handlers::drive::get_drive_item))
+        .route("/api/v1/items/:item_id/access", get(handlers::drive::explain_item_access))
+        .route("/api/v1/items/:item_id/move", post(handlers::drive::move_drive_item))
+        .route("/api/v1/items/:item_id/copy", post(handlers::drive::copy_drive_item))
+        .route("/api/v1/items/:item_id/star", post(handlers::drive::star_item))
+        .route("/api/v1/items/:item_id/star", axum::routing::delete(handlers::drive::unstar_item))
+        .route("/api/v1/items/:item_id/trash", post(handlers::drive::move_to_trash))
+        .route("/api/v1/items/:item_id/restore", post(handlers::drive::restore_from_trash))
+        .route("/api/v1/items/:item_id/share", post(handlers::drive::share_item))
+        .route("/api/v1/items/:item_id/share/link", post(handlers::drive::create_sharing_link))
+        .route("/api/v1/items/:item_id/share/link", axum::routing::delete(handlers::drive::revoke_sharing_link))
+        
+        // Special collections
+        .route("/api/v1/recent", get(handlers::drive::get_recent_items))
+        .route("/api/v1/starred", get(handlers::drive::get_starred_items))
+        .route("/api/v1/shared-with-me", get(handlers::drive::get_shared_with_me))
+        
+        // Form responses
+        .route("/api/v1/forms/:form_id/responses", get(handlers::forms::get_form_responses))
+        .route("/api/v1/responses/:response_id", get(handlers::forms::get_response))
+        .route("/api/v1/forms/:form_id/export", post(handlers::forms::export_responses))
+        
         .layer(middleware::from_fn_with_state(state.clone(), auth::auth_middleware));
 
     // Optional auth routes (authentication optional)
@@ -218,6 +295,7 @@ pub fn create_routes(state: AppState) -> Router {
         .route("/api/v1/files", get(handlers::files::list_files))
         .route("/api/v1/files/:id", get(handlers::files::get_file))
         .route("/api/v1/files/:id/download", get(handlers::files::download_file))
+        .route("/api/v1/files/:id/thumbnail", get(handlers::files::get_thumbnail))
         
         .layer(middleware::from_fn_with_state(state.clone(), auth::optional_auth_middleware));
 
@@ -229,7 +307,9 @@ pub fn create_routes(state: AppState) -> Router {
     // Combine all routes
     Router::new()
         .merge(public_routes)
+        .merge(account_routes)
         .merge(protected_routes)
+        .merge(token_routes)
         .merge(optional_auth_routes)
         .merge(public_form_routes)
         .with_state(state)
@@ -1,31 +1,59 @@
 use crate::routes::create_routes;
 use axum::Router;
-use kingshare_core::{config::Config, Error, Result};
+use kingshare_core::{
+    config::{Config, LockoutConfig, MailConfig, MailTransport, StorageBackend, StorageConfig},
+    Error, Result,
+};
 use kingshare_infrastructure::{
-    Database, DefaultFileService, InMemoryWebSocketService, JwtAuthService, LocalStorageService,
-    PostgresFileRepository, PostgresShareRepository, PostgresUserRepository,
+    ClamdScanner, Database, DefaultDriveService, DefaultFileService, DefaultTotpService, FileMailer,
+    HandlebarsMailTemplates, InMemoryMailer, InMemoryWebSocketService, JwtAuthService, KeyRing, LocalStorageService,
+    OidcClient, PostgresApiTokenRepository, PostgresBlobRepository, PostgresDriveRepository, PostgresFileRepository,
+    PostgresIdentityRepository, PostgresLockoutRepository, PostgresMfaRepository, PostgresQuotaRepository,
+    PostgresRoleRepository, PostgresSecurityEventRepository, PostgresShareRepository, PostgresTeamRepository,
+    PostgresThumbnailRepository, PostgresTokenRepository, PostgresUploadSessionRepository, PostgresUserRepository,
+    S3StorageService, SmtpMailer,
+};
+use kingshare_application::services::{
+    AccountService, ApiTokenService, AuthService, AuthorizationService, BlobService, FileService, LockoutService,
+    MfaService, OidcService, QuotaService, ScanService, ShareService, ThumbnailService, UploadService,
+    UserAdminService, UserService,
 };
-use kingshare_application::services::{FileService, ShareService, UserService};
 use kingshare_domain::{
+    entities::{AttemptLimits, LockoutPolicy, OidcRoleMapping},
     DriveRepository, DriveService, CollaborationRepository, CollaborationService,
     SpreadsheetRepository, SpreadsheetService, FormsRepository, FormsService,
-    DocumentRepository,
+    DocumentRepository, Mailer, MalwareScanner, StorageService,
 };
-use std::{net::SocketAddr, sync::Arc};
+use std::{net::SocketAddr, sync::Arc, time::Duration};
 use tower::ServiceBuilder;
 use tower_http::{
     cors::{Any, CorsLayer},
     trace::TraceLayer,
 };
-use tracing::{info, instrument};
+use tracing::{info, instrument, warn};
+
+const TOKEN_PRUNE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const SEARCH_BACKFILL_INTERVAL: Duration = Duration::from_secs(10 * 60);
 
 #[derive(Clone)]
 pub struct AppState {
     pub database: Database,
     pub config: Config,
     pub user_service: UserService,
+    pub auth_service: AuthService,
+    pub mfa_service: MfaService,
+    pub account_service: AccountService,
+    // Set when single sign-on is configured
+    pub oidc_service: Option<OidcService>,
+    pub api_token_service: ApiTokenService,
+    pub lockout_service: LockoutService,
+    pub authorization_service: AuthorizationService,
+    pub user_admin_service: UserAdminService,
     pub file_service: FileService,
     pub share_service: ShareService,
+    pub upload_service: UploadService,
+    pub blob_service: BlobService,
+    pub quota_service: QuotaService,
     pub websocket_service: Arc<InMemoryWebSocketService>,
     
     // New Google Drive-like services
@@ -58,39 +86,238 @@ impl Server {
         let user_repo = Arc::new(PostgresUserRepository::new(database.pool().clone()));
         let file_repo = Arc::new(PostgresFileRepository::new(database.pool().clone()));
         let share_repo = Arc::new(PostgresShareRepository::new(database.pool().clone()));
+        let upload_session_repo = Arc::new(PostgresUploadSessionRepository::new(database.pool().clone()));
+        let blob_repo = Arc::new(PostgresBlobRepository::new(database.pool().clone()));
+        let thumbnail_repo = Arc::new(PostgresThumbnailRepository::new(database.pool().clone()));
+        let token_repo = Arc::new(PostgresTokenRepository::new(database.pool().clone()));
+        let mfa_repo = Arc::new(PostgresMfaRepository::new(database.pool().clone()));
+        let drive_repo = Arc::new(
+            PostgresDriveRepository::new(database.pool().clone())
+                .with_default_user_quota(config.storage.quota.default_user_quota),
+        );
+        let quota_repo = Arc::new(PostgresQuotaRepository::new(
+            database.pool().clone(),
+            config.storage.quota.default_user_quota,
+        ));
 
         // Create domain services
-        let auth_service = Arc::new(JwtAuthService::new(config.auth.clone()));
-        let storage_service = Arc::new(LocalStorageService::new(
-            "./uploads",
-            100 * 1024 * 1024, // 100MB max file size
-        )?);
-        let file_domain_service = Arc::new(DefaultFileService::new(100 * 1024 * 1024));
+        let jwt_auth_service = Arc::new(JwtAuthService::new(config.auth.clone(), token_repo.clone()));
+        let storage_service = Self::create_storage_service(&config.storage).await?;
+        let file_domain_service = Arc::new(
+            DefaultFileService::new(config.storage.max_file_size)
+                .with_content_type_policy(config.storage.content_type_policy),
+        );
         let websocket_service = Arc::new(InMemoryWebSocketService::new());
+        let totp_service = Arc::new(DefaultTotpService::new(config.auth.mfa.issuer.clone()));
+        let mailer = Self::create_mailer(&config.mail).await?;
+        let mail_templates = Arc::new(HandlebarsMailTemplates::new(config.mail.templates_path.as_deref())?);
 
         // Create application services
-        let user_service = UserService::new(user_repo.clone(), auth_service.clone());
-        let file_service = FileService::new(
+        let user_service = UserService::new(user_repo.clone(), jwt_auth_service.clone());
+        let mfa_service = MfaService::new(mfa_repo, totp_service, config.auth.mfa.require_for_admins);
+        let security_event_repo = Arc::new(PostgresSecurityEventRepository::new(database.pool().clone()));
+        let lockout_service = LockoutService::new(
+            Arc::new(PostgresLockoutRepository::new(database.pool().clone())),
+            security_event_repo.clone(),
+            Self::lockout_policy(&config.auth.lockout),
+        );
+        let role_repo = Arc::new(PostgresRoleRepository::new(database.pool().clone()));
+        let authorization_service = AuthorizationService::new(
+            role_repo.clone(),
+            Arc::new(PostgresTeamRepository::new(database.pool().clone())),
+            user_repo.clone(),
+            security_event_repo.clone(),
+        )
+        .with_drive_repository(drive_repo.clone());
+        let mut auth_service = AuthService::new(
+            user_service.clone(),
+            jwt_auth_service.clone(),
+            token_repo,
+            Some(websocket_service.clone()),
+        )
+        .with_mfa(mfa_service.clone());
+        if !config.auth.unverified.allow_login {
+            auth_service = auth_service.with_verified_email_required();
+        }
+        if config.auth.lockout.enabled {
+            auth_service = auth_service.with_lockout(lockout_service.clone());
+        }
+        let oidc_service = match &config.auth.oidc {
+            Some(oidc_config) => {
+                let client = OidcClient::new(oidc_config.clone())?;
+                if let Err(e) = client.discover().await {
+                    // Discovery is retried at the next login
+                    warn!(error = %e, "Identity provider is not reachable");
+                }
+                info!(issuer = %oidc_config.issuer_url, "Single sign-on enabled");
+
+                Some(OidcService::new(
+                    Arc::new(client),
+                    Arc::new(PostgresIdentityRepository::new(database.pool().clone())),
+                    user_repo.clone(),
+                    jwt_auth_service.clone(),
+                    OidcRoleMapping {
+                        admin_groups: oidc_config.admin_groups.clone(),
+                        guest_groups: oidc_config.guest_groups.clone(),
+                    },
+                    oidc_config.allow_provisioning,
+                ))
+            }
+            None => None,
+        };
+        let api_token_service = ApiTokenService::new(
+            Arc::new(PostgresApiTokenRepository::new(database.pool().clone())),
+            user_repo.clone(),
+            jwt_auth_service.clone(),
+            config.auth.api_tokens.default_expiration_days,
+            config.auth.api_tokens.max_expiration_days,
+        )
+        .with_max_tokens_per_user(config.auth.api_tokens.max_tokens_per_user);
+        let account_service = AccountService::new(
+            user_repo.clone(),
+            jwt_auth_service.clone(),
+            auth_service.clone(),
+            mailer,
+            mail_templates,
+            config.mail.public_url.clone(),
+        );
+        let user_admin_service = UserAdminService::new(
+            user_repo.clone(),
+            jwt_auth_service.clone(),
+            auth_service.clone(),
+            account_service.clone(),
+            security_event_repo,
+        );
+
+        // Spent refresh tokens are only needed until they would have expired,
+        // failed attempts until they fall out of the window
+        let token_pruning = auth_service.clone();
+        let attempt_pruning = lockout_service.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(TOKEN_PRUNE_INTERVAL);
+            loop {
+                interval.tick().await;
+                if let Err(e) = token_pruning.prune_expired_tokens().await {
+                    warn!(error = %e, "Failed to prune expired tokens");
+                }
+                if let Err(e) = attempt_pruning.prune().await {
+                    warn!(error = %e, "Failed to prune failed attempts");
+                }
+            }
+        });
+        let blob_service = BlobService::new(blob_repo, storage_service.clone());
+        let thumbnail_service = ThumbnailService::new(
+            thumbnail_repo,
+            storage_service.clone(),
+            blob_service.clone(),
+            file_domain_service.clone(),
+        );
+        let quota_service = QuotaService::new(
+            quota_repo,
+            Some(websocket_service.clone()),
+            config.storage.quota.soft_limit_percent,
+        );
+        let drive_service: Arc<dyn DriveService> = Arc::new(
+            DefaultDriveService::new(drive_repo.clone(), role_repo, user_repo.clone())
+                .with_storage(storage_service.clone()),
+        );
+        let mut file_service = FileService::new(
             file_repo.clone(),
-            storage_service,
+            storage_service.clone(),
+            blob_service.clone(),
+            file_domain_service.clone(),
+            Some(websocket_service.clone()),
+        )
+        .with_thumbnails(thumbnail_service)
+        .with_quotas(quota_service.clone())
+        .with_search_index(drive_service.clone());
+
+        // Index what items hold when it was saved before the item existed,
+        // or before search
+        let search_backfill = drive_service.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(SEARCH_BACKFILL_INTERVAL);
+            loop {
+                interval.tick().await;
+                if let Err(e) = search_backfill.backfill_index().await {
+                    warn!(error = %e, "Failed to index drive item contents");
+                }
+            }
+        });
+        if let Some(scanner_config) = &config.storage.scanner {
+            let scanner = ClamdScanner::from_config(scanner_config)?;
+            if let Err(e) = scanner.ping().await {
+                // Uploads stay pending until clamd is reachable again
+                warn!(error = %e, "Malware scanner is not reachable");
+            }
+            info!(address = %scanner_config.clamd_address, "Scanning uploads for malware");
+
+            let scan_service = ScanService::new(
+                file_repo.clone(),
+                storage_service.clone(),
+                Arc::new(scanner),
+                Some(websocket_service.clone()),
+            );
+            file_service = file_service.with_malware_scanning(scan_service);
+
+            // Pick up files left pending by a restart or a scanner outage
+            let pending_scans = file_service.clone();
+            tokio::spawn(async move {
+                if let Err(e) = pending_scans.scan_pending_files().await {
+                    warn!(error = %e, "Failed to scan pending files");
+                }
+            });
+        }
+        let upload_service = UploadService::new(
+            upload_session_repo,
+            storage_service.clone(),
             file_domain_service,
+            file_service.clone(),
             Some(websocket_service.clone()),
         );
-        let share_service = ShareService::with_storage(
+        let mut share_service = ShareService::with_storage(
             share_repo,
             file_repo.clone(),
+            jwt_auth_service,
             storage_service.clone(),
             Some(websocket_service.clone()),
         );
+        if !config.auth.unverified.allow_public_shares {
+            share_service = share_service.with_verified_owners_required(user_repo.clone());
+        }
+        if config.auth.lockout.enabled {
+            share_service = share_service.with_lockout(lockout_service.clone());
+        }
+
+        // Shares from before passwords were hashed still hold the plaintext
+        let legacy_passwords = share_service.clone();
+        tokio::spawn(async move {
+            if let Err(e) = legacy_passwords.upgrade_legacy_passwords().await {
+                warn!(error = %e, "Failed to hash legacy share passwords");
+            }
+        });
 
         // Create application state
         let state = AppState {
             database,
             config: config.clone(),
             user_service,
+            auth_service,
+            mfa_service,
+            account_service,
+            oidc_service,
+            api_token_service,
+            lockout_service,
+            authorization_service,
+            user_admin_service,
             file_service,
             share_service,
+            upload_service,
+            blob_service,
+            quota_service,
             websocket_service,
+            drive_repository: drive_repo,
+            drive_service,
         };
 
         // Build the application with routes and middleware
@@ -113,6 +340,79 @@ impl Server {
         Ok(Self { app, addr })
     }
 
+    async fn create_storage_service(config: &StorageConfig) -> Result<Arc<dyn StorageService>> {
+        match config.backend {
+            StorageBackend::Local => {
+                let mut storage = LocalStorageService::new(
+                    &config.storage_path,
+                    config.max_file_size,
+                )?;
+
+                if let Some(encryption) = &config.encryption {
+                    let key_ring = KeyRing::from_config(encryption)?;
+                    info!(key_id = %key_ring.current_key_id(), "Encrypting stored files at rest");
+                    storage = storage.with_encryption(key_ring);
+                }
+
+                info!(path = %config.storage_path, "Using local file storage");
+                Ok(Arc::new(storage))
+            }
+            StorageBackend::S3 => {
+                let s3_config = config.s3_config()?;
+
+                let storage = S3StorageService::new(s3_config, config.max_file_size);
+                storage.ensure_bucket().await?;
+
+                info!(bucket = %s3_config.bucket, "Using S3 object storage");
+                Ok(Arc::new(storage))
+            }
+        }
+    }
+
+    fn lockout_policy(config: &LockoutConfig) -> LockoutPolicy {
+        LockoutPolicy {
+            account: AttemptLimits {
+                free_attempts: config.free_attempts,
+                lockout_threshold: config.lockout_threshold,
+            },
+            ip: AttemptLimits {
+                free_attempts: config.ip_free_attempts,
+                lockout_threshold: config.ip_lockout_threshold,
+            },
+            base_delay: chrono::Duration::seconds(config.base_delay),
+            max_delay: chrono::Duration::seconds(config.max_delay),
+            lockout_duration: chrono::Duration::seconds(config.lockout_duration),
+            attempt_window: chrono::Duration::seconds(config.attempt_window),
+        }
+    }
+
+    async fn create_mailer(config: &MailConfig) -> Result<Arc<dyn Mailer>> {
+        match config.transport {
+            MailTransport::Smtp => {
+                let smtp_config = config.smtp_config()?;
+
+                let mailer = SmtpMailer::new(smtp_config, &config.from_address)?;
+                if let Err(e) = mailer.ping().await {
+                    // Every send opens its own connection, so start up regardless
+                    warn!(error = %e, "SMTP server is not reachable");
+                }
+
+                info!(host = %smtp_config.host, "Sending email through SMTP");
+                Ok(Arc::new(mailer))
+            }
+            MailTransport::File => {
+                let mailer = FileMailer::new(&config.outbox_path, &config.from_address)?;
+
+                info!(path = %config.outbox_path, "Writing outgoing email to files");
+                Ok(Arc::new(mailer))
+            }
+            MailTransport::Memory => {
+                warn!("Outgoing email is kept in memory and never delivered");
+                Ok(Arc::new(InMemoryMailer::new()))
+            }
+        }
+    }
+
     #[instrument(skip(self))]
     pub async fn run(self) -> Result<()> {
         info!("Starting server on {}", self.addr);
@@ -121,7 +421,8 @@ impl Server {
             .await
             .map_err(Error::Io)?;
 
-        axum::serve(listener, self.app)
+        // Peer addresses feed session records and failed-attempt limits
+        axum::serve(listener, self.app.into_make_service_with_connect_info::<SocketAddr>())
             .await
             .map_err(Error::Io)?;
 
@@ -0,0 +1,228 @@
+use crate::services::AuthService;
+use kingshare_core::{Error, Id, Result};
+use kingshare_domain::{
+    entities::{AccountTokenPurpose, RevocationReason, User, UserProfile},
+    repositories::UserRepository,
+    services::{AuthService as DomainAuthService, EmailTemplate, MailTemplates, Mailer},
+    value_objects::Email,
+};
+use serde_json::json;
+use std::sync::Arc;
+use tracing::{info, instrument, warn};
+
+/// Email verification and password reset, both driven by signed single-use links
+#[derive(Clone)]
+pub struct AccountService {
+    user_repository: Arc<dyn UserRepository>,
+    auth_service: Arc<dyn DomainAuthService>,
+    sessions: AuthService,
+    mailer: Arc<dyn Mailer>,
+    templates: Arc<dyn MailTemplates>,
+    public_url: String,
+}
+
+impl AccountService {
+    /// Links in emails point to `public_url`, the frontend that handles them
+    pub fn new(
+        user_repository: Arc<dyn UserRepository>,
+        auth_service: Arc<dyn DomainAuthService>,
+        sessions: AuthService,
+        mailer: Arc<dyn Mailer>,
+        templates: Arc<dyn MailTemplates>,
+        public_url: impl Into<String>,
+    ) -> Self {
+        Self {
+            user_repository,
+            auth_service,
+            sessions,
+            mailer,
+            templates,
+            public_url: public_url.into().trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Email the user a link confirming their address. Does nothing for users
+    /// who are already verified.
+    #[instrument(skip(self))]
+    pub async fn send_verification_email(&self, user_id: Id) -> Result<()> {
+        let user = self.find_user(user_id).await?;
+        if user.is_verified {
+            return Ok(());
+        }
+
+        // Changing the address invalidates links sent to the old one
+        let token = self
+            .auth_service
+            .issue_account_token(user.id, AccountTokenPurpose::VerifyEmail, user.email.as_str())
+            .await?;
+        let context = json!({
+            "name": user.first_name,
+            "email": user.email.as_str(),
+            "link": format!("{}/verify-email?token={}", self.public_url, token.token),
+            "expires_in": human_duration(token.expires_in),
+        });
+        self.send(EmailTemplate::VerifyEmail, user.email.as_str(), &context).await?;
+
+        info!(user_id = %user.id, "Verification email sent");
+        Ok(())
+    }
+
+    /// Resend the verification link by address. Unknown and verified addresses
+    /// are ignored, so callers can't tell which accounts exist.
+    #[instrument(skip(self, email))]
+    pub async fn resend_verification_email(&self, email: &str) -> Result<()> {
+        let Some(user) = self.find_user_by_email(email).await? else {
+            return Ok(());
+        };
+        self.send_verification_email(user.id).await
+    }
+
+    #[instrument(skip(self, token))]
+    pub async fn verify_email(&self, token: &str) -> Result<UserProfile> {
+        let account_token = self
+            .auth_service
+            .verify_account_token(token, AccountTokenPurpose::VerifyEmail)
+            .await?;
+        let mut user = self.find_user(account_token.user_id).await?;
+
+        self.auth_service
+            .consume_account_token(&account_token, user.email.as_str())
+            .await?;
+
+        if !user.is_verified {
+            user.verify();
+            user = self.user_repository.update(user).await?;
+            info!(user_id = %user.id, "Email address verified");
+        }
+
+        Ok(user.into())
+    }
+
+    /// Email a password reset link. Unknown and deactivated accounts are
+    /// ignored, so callers can't tell which accounts exist.
+    #[instrument(skip(self, email))]
+    pub async fn request_password_reset(&self, email: &str) -> Result<()> {
+        let Some(user) = self.find_user_by_email(email).await? else {
+            return Ok(());
+        };
+        if !user.is_active {
+            warn!(user_id = %user.id, "Password reset requested for a deactivated account");
+            return Ok(());
+        }
+
+        self.send_reset_link(&user).await?;
+
+        info!(user_id = %user.id, "Password reset email sent");
+        Ok(())
+    }
+
+    /// Make the user choose a new password: the current one stops working,
+    /// every session ends and a reset link is emailed to them
+    #[instrument(skip(self))]
+    pub async fn force_password_reset(&self, user_id: Id) -> Result<()> {
+        let mut user = self.find_user(user_id).await?;
+
+        // A password nobody knows; the new hash also voids earlier reset links
+        let unknown = format!("{}{}", Id::new_v4().simple(), Id::new_v4().simple());
+        user.password_hash = self.auth_service.hash_password(&unknown).await?;
+        user.updated_at = chrono::Utc::now();
+        let user = self.user_repository.update(user).await?;
+
+        self.sessions
+            .revoke_all_sessions(user.id, RevocationReason::PasswordReset)
+            .await?;
+        self.send_reset_link(&user).await?;
+
+        info!(user_id = %user.id, "Password reset forced");
+        Ok(())
+    }
+
+    /// Set a new password from a reset link and sign out every session
+    #[instrument(skip(self, token, new_password))]
+    pub async fn reset_password(&self, token: &str, new_password: &str) -> Result<()> {
+        let account_token = self
+            .auth_service
+            .verify_account_token(token, AccountTokenPurpose::ResetPassword)
+            .await?;
+        let mut user = self.find_user(account_token.user_id).await?;
+        if !user.is_active {
+            return Err(Error::Authentication("Account is deactivated".to_string()));
+        }
+
+        self.auth_service
+            .consume_account_token(&account_token, &user.password_hash)
+            .await?;
+
+        user.password_hash = self.auth_service.hash_password(new_password).await?;
+        // The link arrived by email, which proves the address as well
+        user.is_verified = true;
+        user.updated_at = chrono::Utc::now();
+        let user = self.user_repository.update(user).await?;
+
+        self.sessions
+            .revoke_all_sessions(user.id, RevocationReason::PasswordReset)
+            .await?;
+
+        let context = json!({
+            "name": user.first_name,
+            "email": user.email.as_str(),
+        });
+        if let Err(e) = self
+            .send(EmailTemplate::PasswordChanged, user.email.as_str(), &context)
+            .await
+        {
+            warn!(user_id = %user.id, error = %e, "Failed to send password change notice");
+        }
+
+        info!(user_id = %user.id, "Password reset");
+        Ok(())
+    }
+
+    async fn send_reset_link(&self, user: &User) -> Result<()> {
+        // Bound to the current hash, so the link dies once the password changes
+        let token = self
+            .auth_service
+            .issue_account_token(user.id, AccountTokenPurpose::ResetPassword, &user.password_hash)
+            .await?;
+        let context = json!({
+            "name": user.first_name,
+            "email": user.email.as_str(),
+            "link": format!("{}/reset-password?token={}", self.public_url, token.token),
+            "expires_in": human_duration(token.expires_in),
+        });
+        self.send(EmailTemplate::PasswordReset, user.email.as_str(), &context).await
+    }
+
+    async fn send(&self, template: EmailTemplate, to: &str, context: &serde_json::Value) -> Result<()> {
+        let message = self.templates.render(template, to, context)?;
+        self.mailer.send(&message).await
+    }
+
+    async fn find_user(&self, user_id: Id) -> Result<User> {
+        self.user_repository
+            .find_by_id(user_id)
+            .await?
+            .ok_or_else(|| Error::NotFound("User not found".to_string()))
+    }
+
+    async fn find_user_by_email(&self, email: &str) -> Result<Option<User>> {
+        let email = Email::new(email.to_string())
+            .map_err(|e| Error::Validation(e.to_string()))?;
+        self.user_repository.find_by_email(&email).await
+    }
+}
+
+/// "24 hours", "1 hour", "30 minutes"
+fn human_duration(seconds: i64) -> String {
+    let (value, unit) = if seconds >= 3600 && seconds % 3600 == 0 {
+        (seconds / 3600, "hour")
+    } else {
+        ((seconds + 59) / 60, "minute")
+    };
+
+    if value == 1 {
+        format!("1 {}", unit)
+    } else {
+        format!("{} {}s", value, unit)
+    }
+}
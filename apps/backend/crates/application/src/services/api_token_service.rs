@@ -0,0 +1,159 @@
+use chrono::{Duration, Utc};
+use kingshare_core::{Error, Id, Result};
+use kingshare_domain::{
+    entities::{ApiToken, ApiTokenGrant, ApiTokenInfo, CreateApiTokenRequest, CreatedApiToken, API_TOKEN_PREFIX},
+    repositories::{ApiTokenRepository, UserRepository},
+    services::{AuthService as DomainAuthService, Claims},
+};
+use std::sync::Arc;
+use tracing::{info, instrument};
+use validator::Validate;
+
+/// Characters of the secret kept after the prefix so users can tell tokens apart
+const DISPLAY_PREFIX_CHARS: usize = 8;
+
+/// Long-lived personal access tokens for scripts and CI jobs. A token acts as
+/// its owner, narrowed to its scopes and optionally to a single drive.
+#[derive(Clone)]
+pub struct ApiTokenService {
+    token_repository: Arc<dyn ApiTokenRepository>,
+    user_repository: Arc<dyn UserRepository>,
+    auth_service: Arc<dyn DomainAuthService>,
+    default_expiration_days: i64,
+    max_expiration_days: i64,
+    max_tokens_per_user: u64,
+}
+
+impl ApiTokenService {
+    pub fn new(
+        token_repository: Arc<dyn ApiTokenRepository>,
+        user_repository: Arc<dyn UserRepository>,
+        auth_service: Arc<dyn DomainAuthService>,
+        default_expiration_days: i64,
+        max_expiration_days: i64,
+    ) -> Self {
+        Self {
+            token_repository,
+            user_repository,
+            auth_service,
+            default_expiration_days,
+            max_expiration_days,
+            max_tokens_per_user: 50,
+        }
+    }
+
+    pub fn with_max_tokens_per_user(mut self, max_tokens_per_user: u64) -> Self {
+        self.max_tokens_per_user = max_tokens_per_user;
+        self
+    }
+
+    /// Create a token. The secret is only returned here; afterwards only its
+    /// hash is kept.
+    #[instrument(skip(self, request))]
+    pub async fn create_token(&self, user_id: Id, request: CreateApiTokenRequest) -> Result<CreatedApiToken> {
+        request
+            .validate()
+            .map_err(|e| Error::Validation(e.to_string()))?;
+
+        let expires_in_days = request.expires_in_days.unwrap_or(self.default_expiration_days);
+        if expires_in_days > self.max_expiration_days {
+            return Err(Error::Validation(format!(
+                "Tokens can last at most {} days",
+                self.max_expiration_days
+            )));
+        }
+
+        if self.token_repository.count_active(user_id).await? >= self.max_tokens_per_user {
+            return Err(Error::QuotaExceeded(format!(
+                "At most {} tokens can be active at once; revoke one first",
+                self.max_tokens_per_user
+            )));
+        }
+
+        let mut scopes = request.scopes;
+        scopes.sort_by_key(|scope| scope.as_str());
+        scopes.dedup();
+
+        let secret = self.auth_service.generate_api_token();
+        let now = Utc::now();
+        // The drive isn't checked here: handlers still apply the owner's own
+        // permissions, so a token bound to a foreign drive can't reach into it
+        let token = ApiToken {
+            id: uuid::Uuid::new_v4(),
+            user_id,
+            name: request.name.trim().to_string(),
+            display_prefix: secret.token[..API_TOKEN_PREFIX.len() + DISPLAY_PREFIX_CHARS].to_string(),
+            token_hash: secret.token_hash,
+            scopes,
+            drive_id: request.drive_id,
+            created_at: now,
+            expires_at: now + Duration::days(expires_in_days),
+            last_used_at: None,
+            revoked_at: None,
+        };
+        let token = self.token_repository.create(token).await?;
+
+        info!(user_id = %user_id, token_id = %token.id, "API token issued");
+        Ok(CreatedApiToken {
+            token: secret.token,
+            info: token.into(),
+        })
+    }
+
+    pub async fn list_tokens(&self, user_id: Id) -> Result<Vec<ApiTokenInfo>> {
+        let tokens = self.token_repository.list_active(user_id).await?;
+        Ok(tokens.into_iter().map(ApiTokenInfo::from).collect())
+    }
+
+    #[instrument(skip(self))]
+    pub async fn revoke_token(&self, user_id: Id, token_id: Id) -> Result<()> {
+        if !self.token_repository.revoke(token_id, user_id).await? {
+            return Err(Error::NotFound("Token not found".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Check a bearer token and return the claims the request runs with and
+    /// what the token is allowed to do
+    #[instrument(skip(self, token))]
+    pub async fn authenticate(&self, token: &str) -> Result<(Claims, ApiTokenGrant)> {
+        let invalid = || Error::Authentication("Invalid or expired token".to_string());
+
+        let token_hash = self.auth_service.hash_api_token(token);
+        let token = self
+            .token_repository
+            .find_by_hash(&token_hash)
+            .await?
+            .filter(|token| !token.is_revoked() && !token.is_expired())
+            .ok_or_else(invalid)?;
+
+        let user = self
+            .user_repository
+            .find_by_id(token.user_id)
+            .await?
+            .filter(|user| user.is_active)
+            .ok_or_else(invalid)?;
+
+        self.token_repository.record_use(token.id).await?;
+
+        let claims = Claims {
+            sub: user.id.to_string(),
+            email: user.email.as_str().to_string(),
+            username: user.username.clone(),
+            role: user.role.as_str().to_string(),
+            exp: token.expires_at.timestamp(),
+            iat: token.created_at.timestamp(),
+            jti: token.id.to_string(),
+            sid: None,
+            act: None,
+        };
+        let grant = ApiTokenGrant {
+            token_id: token.id,
+            user_id: user.id,
+            scopes: token.scopes,
+            drive_id: token.drive_id,
+        };
+
+        Ok((claims, grant))
+    }
+}
@@ -1 +1,364 @@
-// Application auth service will be added here
\ No newline at end of file
+use crate::services::{LockoutService, MfaService, UserService};
+use kingshare_core::{Error, Id, Result};
+use kingshare_domain::{
+    entities::{
+        AttemptKey, ClientInfo, CreateUserRequest, RevocationReason, SessionInfo, TotpSetup, UserProfile,
+    },
+    repositories::TokenRepository,
+    services::{AuthService as DomainAuthService, Claims, MfaChallengeToken, TokenPair, WebSocketService},
+};
+use std::sync::Arc;
+use tracing::{info, instrument, warn};
+
+/// A signed-in user and the token pair for their session
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct AuthSession {
+    pub tokens: TokenPair,
+    pub user: UserProfile,
+    /// Set when 2FA was enabled while signing in; shown to the user this once
+    pub recovery_codes: Option<Vec<String>>,
+}
+
+/// The result of a password login
+#[derive(Debug, Clone)]
+pub enum LoginOutcome {
+    Authenticated(AuthSession),
+    /// The password was right; answer the challenge with a second factor to finish
+    MfaRequired(MfaChallengeToken),
+}
+
+/// The result of registering
+#[derive(Debug, Clone)]
+pub enum RegistrationOutcome {
+    Authenticated(AuthSession),
+    /// The account was created but can't sign in until its email address is verified
+    VerificationRequired(UserProfile),
+}
+
+#[derive(Clone)]
+pub struct AuthService {
+    user_service: UserService,
+    auth_service: Arc<dyn DomainAuthService>,
+    token_repository: Arc<dyn TokenRepository>,
+    websocket_service: Option<Arc<dyn WebSocketService>>,
+    mfa_service: Option<MfaService>,
+    lockout_service: Option<LockoutService>,
+    require_verified_email: bool,
+}
+
+impl AuthService {
+    pub fn new(
+        user_service: UserService,
+        auth_service: Arc<dyn DomainAuthService>,
+        token_repository: Arc<dyn TokenRepository>,
+        websocket_service: Option<Arc<dyn WebSocketService>>,
+    ) -> Self {
+        Self {
+            user_service,
+            auth_service,
+            token_repository,
+            websocket_service,
+            mfa_service: None,
+            lockout_service: None,
+            require_verified_email: false,
+        }
+    }
+
+    /// Ask users with 2FA enabled, or required for their role, for a second factor at login
+    pub fn with_mfa(mut self, mfa_service: MfaService) -> Self {
+        self.mfa_service = Some(mfa_service);
+        self
+    }
+
+    /// Back off and lock out repeated wrong passwords, per account and per address
+    pub fn with_lockout(mut self, lockout_service: LockoutService) -> Self {
+        self.lockout_service = Some(lockout_service);
+        self
+    }
+
+    /// Refuse logins until the user has verified their email address
+    pub fn with_verified_email_required(mut self) -> Self {
+        self.require_verified_email = true;
+        self
+    }
+
+    /// Create an account and sign it in, unless logins wait for a verified
+    /// email address. Then no tokens are issued until the user follows the link.
+    #[instrument(skip(self, request, client))]
+    pub async fn register(&self, request: CreateUserRequest, client: &ClientInfo) -> Result<RegistrationOutcome> {
+        let user = self.user_service.create_user(request).await?;
+        if self.require_verified_email && !user.is_verified {
+            info!(user_id = %user.id, "Registration awaiting email verification");
+            return Ok(RegistrationOutcome::VerificationRequired(user));
+        }
+
+        Ok(RegistrationOutcome::Authenticated(self.start_session(user, client).await?))
+    }
+
+    #[instrument(skip(self, password, client))]
+    pub async fn login(&self, email: &str, password: &str, client: &ClientInfo) -> Result<LoginOutcome> {
+        let Some(lockout_service) = &self.lockout_service else {
+            let user = self.user_service.authenticate_user(email, password).await?;
+            return self.finish_login(user, client).await;
+        };
+
+        let mut keys = vec![AttemptKey::account(email)];
+        keys.extend(client.ip_address.as_deref().map(AttemptKey::ip));
+        let attempt = lockout_service.begin_attempt(&keys).await?;
+
+        let user = match self.user_service.authenticate_user(email, password).await {
+            Ok(user) => user,
+            Err(e @ Error::Authentication(_)) => {
+                lockout_service
+                    .attempt_failed(&attempt, client.ip_address.as_deref())
+                    .await?;
+                return Err(e);
+            }
+            Err(e) => {
+                lockout_service.cancel_attempt(&attempt).await?;
+                return Err(e);
+            }
+        };
+        lockout_service.attempt_succeeded(&attempt).await?;
+
+        self.finish_login(user, client).await
+    }
+
+    /// Sign in a user an identity provider has already authenticated. The same
+    /// checks as a password login apply, including a second factor.
+    #[instrument(skip(self, user, client), fields(user_id = %user.id))]
+    pub async fn login_with_identity(&self, user: UserProfile, client: &ClientInfo) -> Result<LoginOutcome> {
+        self.finish_login(user, client).await
+    }
+
+    async fn finish_login(&self, user: UserProfile, client: &ClientInfo) -> Result<LoginOutcome> {
+        if self.require_verified_email && !user.is_verified {
+            return Err(Error::Authorization(
+                "Verify your email address before signing in".to_string(),
+            ));
+        }
+
+        if let Some(mfa_service) = &self.mfa_service {
+            let enrolled = mfa_service.is_enabled(user.id).await?;
+            if enrolled || mfa_service.is_required(&user.role) {
+                let challenge = self
+                    .auth_service
+                    .issue_mfa_challenge(user.id, !enrolled)
+                    .await?;
+
+                info!(user_id = %user.id, enrollment_required = !enrolled, "Login awaiting second factor");
+                return Ok(LoginOutcome::MfaRequired(challenge));
+            }
+        }
+
+        Ok(LoginOutcome::Authenticated(self.start_session(user, client).await?))
+    }
+
+    /// For a login challenge that requires enrollment: the secret to confirm
+    /// with `complete_mfa_login`
+    #[instrument(skip(self, challenge_token))]
+    pub async fn begin_mfa_enrollment(&self, challenge_token: &str) -> Result<TotpSetup> {
+        let mfa_service = self.require_mfa()?;
+        let challenge = self.auth_service.verify_mfa_challenge(challenge_token).await?;
+        if !challenge.enrollment_required {
+            return Err(Error::BadRequest("Two-factor authentication is already enabled".to_string()));
+        }
+
+        let user = self.user_service.get_user_by_id(challenge.user_id).await?;
+        mfa_service.begin_enrollment(&user).await
+    }
+
+    /// Finish a login with a TOTP or recovery code. The challenge is spent
+    /// before the code is checked, so every guess costs a password login and
+    /// concurrent answers can't both succeed.
+    #[instrument(skip(self, challenge_token, code, client))]
+    pub async fn complete_mfa_login(
+        &self,
+        challenge_token: &str,
+        code: &str,
+        client: &ClientInfo,
+    ) -> Result<AuthSession> {
+        let mfa_service = self.require_mfa()?;
+        let challenge = self.auth_service.verify_mfa_challenge(challenge_token).await?;
+        self.auth_service.consume_mfa_challenge(&challenge).await?;
+        let user = self.user_service.get_user_by_id(challenge.user_id).await?;
+
+        if !user.is_active {
+            return Err(Error::Authentication("Account is deactivated".to_string()));
+        }
+
+        let recovery_codes = if challenge.enrollment_required {
+            Some(mfa_service.confirm_enrollment(user.id, code).await?)
+        } else {
+            if !mfa_service.verify(user.id, code).await? {
+                warn!(user_id = %user.id, "Invalid second factor at login");
+                return Err(Error::Authentication("Invalid verification code".to_string()));
+            }
+            None
+        };
+
+        let mut session = self.start_session(user, client).await?;
+        session.recovery_codes = recovery_codes;
+
+        info!(user_id = %session.user.id, "Login completed with second factor");
+        Ok(session)
+    }
+
+    /// Rotate a refresh token. User details are reloaded so role changes and
+    /// deactivation take effect at the next refresh.
+    #[instrument(skip(self, refresh_token))]
+    pub async fn refresh(&self, refresh_token: &str) -> Result<AuthSession> {
+        let grant = self.auth_service.redeem_refresh_token(refresh_token).await?;
+        let user = self.user_service.get_user_by_id(grant.user_id).await?;
+
+        if !user.is_active {
+            warn!(user_id = %user.id, "Refresh attempted for a deactivated account");
+            return Err(Error::Authentication("Account is deactivated".to_string()));
+        }
+
+        let tokens = self
+            .auth_service
+            .rotate_tokens(&grant, &user.email, &user.username, user.role.as_str())
+            .await?;
+
+        info!(user_id = %user.id, session_id = %grant.session_id, "Token refreshed successfully");
+        Ok(AuthSession {
+            tokens,
+            user,
+            recovery_codes: None,
+        })
+    }
+
+    #[instrument(skip(self, token))]
+    pub async fn verify_token(&self, token: &str) -> Result<Claims> {
+        self.auth_service.verify_token(token).await
+    }
+
+    /// Revoke the presented token and end the session it belongs to
+    #[instrument(skip(self, token))]
+    pub async fn logout(&self, token: &str) -> Result<()> {
+        // Read the claims first: once revoked, the token no longer verifies
+        let claims = self.auth_service.verify_token(token).await.ok();
+        self.auth_service.revoke_token(token).await?;
+
+        if let Some(claims) = claims {
+            if let (Ok(user_id), Some(Ok(session_id))) = (
+                claims.sub.parse::<Id>(),
+                claims.sid.as_deref().map(str::parse::<Id>),
+            ) {
+                self.close_connections(user_id, vec![session_id]).await;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The user's live sessions, most recently used first
+    #[instrument(skip(self))]
+    pub async fn list_sessions(
+        &self,
+        user_id: Id,
+        current_session_id: Option<Id>,
+    ) -> Result<Vec<SessionInfo>> {
+        let families = self.token_repository.find_active_families(user_id).await?;
+
+        Ok(families
+            .into_iter()
+            .map(|family| SessionInfo::from_family(family, current_session_id))
+            .collect())
+    }
+
+    /// End one of the user's sessions: its refresh token stops working, its
+    /// access tokens are rejected and its WebSocket connections are closed
+    #[instrument(skip(self))]
+    pub async fn revoke_session(&self, user_id: Id, session_id: Id) -> Result<()> {
+        let family = self
+            .token_repository
+            .find_family(session_id)
+            .await?
+            .filter(|family| family.user_id == user_id && !family.is_revoked())
+            .ok_or_else(|| Error::NotFound("Session not found".to_string()))?;
+
+        self.token_repository
+            .revoke_family(family.id, RevocationReason::SessionEnded)
+            .await?;
+        self.close_connections(user_id, vec![family.id]).await;
+
+        info!(user_id = %user_id, session_id = %session_id, "Session revoked");
+        Ok(())
+    }
+
+    /// End every session of the user except `current_session_id`. Returns how
+    /// many sessions were ended.
+    #[instrument(skip(self))]
+    pub async fn revoke_other_sessions(&self, user_id: Id, current_session_id: Option<Id>) -> Result<u64> {
+        let revoked_count = self
+            .revoke_sessions(user_id, current_session_id, RevocationReason::SessionEnded)
+            .await?;
+
+        info!(user_id = %user_id, revoked_count = revoked_count, "Other sessions revoked");
+        Ok(revoked_count)
+    }
+
+    /// End every session of the user, such as after their password changed
+    #[instrument(skip(self))]
+    pub async fn revoke_all_sessions(&self, user_id: Id, reason: RevocationReason) -> Result<u64> {
+        let revoked_count = self.revoke_sessions(user_id, None, reason).await?;
+
+        info!(user_id = %user_id, revoked_count = revoked_count, reason = reason.as_str(), "All sessions revoked");
+        Ok(revoked_count)
+    }
+
+    #[instrument(skip(self))]
+    pub async fn prune_expired_tokens(&self) -> Result<u64> {
+        self.auth_service.prune_expired_tokens().await
+    }
+
+    async fn start_session(&self, user: UserProfile, client: &ClientInfo) -> Result<AuthSession> {
+        let tokens = self
+            .auth_service
+            .generate_tokens(user.id, &user.email, &user.username, user.role.as_str(), client)
+            .await?;
+
+        Ok(AuthSession {
+            tokens,
+            user,
+            recovery_codes: None,
+        })
+    }
+
+    async fn revoke_sessions(
+        &self,
+        user_id: Id,
+        keep_session_id: Option<Id>,
+        reason: RevocationReason,
+    ) -> Result<u64> {
+        let revoked = self
+            .token_repository
+            .revoke_user_families(user_id, keep_session_id, reason)
+            .await?;
+        let revoked_count = revoked.len() as u64;
+
+        if !revoked.is_empty() {
+            self.close_connections(user_id, revoked).await;
+        }
+
+        Ok(revoked_count)
+    }
+
+    fn require_mfa(&self) -> Result<&MfaService> {
+        self.mfa_service
+            .as_ref()
+            .ok_or_else(|| Error::BadRequest("Two-factor authentication is not available".to_string()))
+    }
+
+    /// Sessions are already revoked in the repository, so a failure here only
+    /// leaves a socket open until its next authenticated request
+    async fn close_connections(&self, user_id: Id, session_ids: Vec<Id>) {
+        if let Some(ws_service) = &self.websocket_service {
+            if let Err(e) = ws_service.close_session_connections(user_id, session_ids).await {
+                warn!(user_id = %user_id, error = %e, "Failed to close WebSocket connections of revoked sessions");
+            }
+        }
+    }
+}
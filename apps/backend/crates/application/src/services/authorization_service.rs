@@ -0,0 +1,389 @@
+use kingshare_core::{Error, Id, Result};
+use kingshare_domain::{
+    entities::{
+        normalize_permissions, AccessExplanation, AccessGrant, AccessSource, AssignRoleRequest, CreateRoleRequest,
+        CreateTeamRequest, DriveItem, EffectiveAccess, ExplainedGrant, Folder, ItemAccess, Permission, Resource,
+        Role, RoleAssignment, RoleScope, SecurityEvent, SecurityEventType, SharingPermissions, Team,
+        UpdateRoleRequest, UserRole,
+    },
+    repositories::{DriveRepository, RoleRepository, SecurityEventRepository, TeamRepository, UserRepository},
+};
+use serde_json::json;
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::Arc,
+};
+use tracing::{info, instrument};
+use validator::Validate;
+
+/// Decides what users may do, and manages the roles that decide it.
+///
+/// A user's permissions on a resource come from their account role (admins
+/// everywhere, users and guests on what they own) plus every role assignment
+/// whose scope covers the resource. Administrative permissions only count
+/// when granted globally.
+///
+/// Drive items are also reachable through their own sharing, and with a drive
+/// repository, through the sharing of the folders holding them.
+#[derive(Clone)]
+pub struct AuthorizationService {
+    role_repository: Arc<dyn RoleRepository>,
+    team_repository: Arc<dyn TeamRepository>,
+    user_repository: Arc<dyn UserRepository>,
+    event_repository: Arc<dyn SecurityEventRepository>,
+    drive_repository: Option<Arc<dyn DriveRepository>>,
+}
+
+impl AuthorizationService {
+    pub fn new(
+        role_repository: Arc<dyn RoleRepository>,
+        team_repository: Arc<dyn TeamRepository>,
+        user_repository: Arc<dyn UserRepository>,
+        event_repository: Arc<dyn SecurityEventRepository>,
+    ) -> Self {
+        Self {
+            role_repository,
+            team_repository,
+            user_repository,
+            event_repository,
+            drive_repository: None,
+        }
+    }
+
+    /// Let folder shares reach the items in the folder
+    pub fn with_drive_repository(mut self, drive_repository: Arc<dyn DriveRepository>) -> Self {
+        self.drive_repository = Some(drive_repository);
+        self
+    }
+
+    /// Everything `user_id` may do with `resource`. Inactive users may do nothing.
+    #[instrument(skip(self))]
+    pub async fn permissions(&self, user_id: Id, resource: &Resource) -> Result<Vec<Permission>> {
+        let Some(user) = self
+            .user_repository
+            .find_by_id(user_id)
+            .await?
+            .filter(|user| user.is_active)
+        else {
+            return Ok(Vec::new());
+        };
+
+        let assignments = self.role_repository.list_assignments(user_id).await?;
+        // Only look up the drive's team when a team assignment could use it
+        let drive_team_id = match resource.drive_id {
+            Some(drive_id) if assignments.iter().any(|a| matches!(a.scope, RoleScope::Team(_))) => {
+                self.team_repository.find_drive_team(drive_id).await?
+            }
+            _ => None,
+        };
+
+        // Each granting role, and whether it was granted globally
+        let mut grants: Vec<(Id, bool)> = assignments
+            .iter()
+            .filter(|assignment| assignment.scope.covers(resource, drive_team_id))
+            .map(|assignment| (assignment.role_id, assignment.scope == RoleScope::Global))
+            .collect();
+
+        let owns_resource = resource.owner_id == Some(user_id);
+        if user.role == UserRole::Admin || owns_resource {
+            if let Some(base_role) = self.role_repository.find_role_by_name(user.role.system_role()).await? {
+                grants.push((base_role.id, user.role == UserRole::Admin));
+            }
+        }
+        if grants.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let role_ids: Vec<Id> = grants.iter().map(|(role_id, _)| *role_id).collect();
+        let roles: HashMap<Id, Role> = self
+            .role_repository
+            .find_roles(&role_ids)
+            .await?
+            .into_iter()
+            .map(|role| (role.id, role))
+            .collect();
+
+        let mut permissions = BTreeSet::new();
+        for (role_id, global) in grants {
+            let Some(role) = roles.get(&role_id) else {
+                continue;
+            };
+            permissions.extend(
+                role.permissions
+                    .iter()
+                    .filter(|permission| global || !permission.is_administrative()),
+            );
+        }
+
+        Ok(permissions.into_iter().collect())
+    }
+
+    pub async fn has_permission(&self, user_id: Id, permission: Permission, resource: &Resource) -> Result<bool> {
+        Ok(self.permissions(user_id, resource).await?.contains(&permission))
+    }
+
+    /// Fail unless `user_id` holds `permission` on `resource`
+    #[instrument(skip(self))]
+    pub async fn require(&self, user_id: Id, permission: Permission, resource: &Resource) -> Result<()> {
+        if !self.has_permission(user_id, permission, resource).await? {
+            return Err(Error::Authorization(format!("Missing the {} permission", permission)));
+        }
+        Ok(())
+    }
+
+    /// Allow `access` to a drive item through its sharing or that of the
+    /// folders holding it, or a role covering its drive
+    #[instrument(skip(self, item), fields(item_id = %item.id))]
+    pub async fn require_item(&self, user_id: Id, item: &DriveItem, access: ItemAccess) -> Result<()> {
+        if self.effective_access(user_id, item).await?.allows(access) {
+            return Ok(());
+        }
+
+        let resource = Resource::owned_by(item.permissions.owner_id).in_drive(item.drive_id);
+        self.require(user_id, access.permission(), &resource).await
+    }
+
+    /// What `user_id` may do with a drive item through sharing, leaving out roles
+    pub async fn effective_access(&self, user_id: Id, item: &DriveItem) -> Result<EffectiveAccess> {
+        let ancestors = self.item_ancestors(item.id).await?;
+        Ok(item.effective_access(user_id, &ancestors))
+    }
+
+    /// The drives among `drives` whose every item `user_id` may read, for
+    /// `SearchFilters::readable_drives`. Elsewhere searches only find what
+    /// the user owns or reaches through sharing.
+    #[instrument(skip(self, drives), fields(drives = drives.len()))]
+    pub async fn readable_drives(&self, user_id: Id, drives: &[Resource]) -> Result<Vec<Id>> {
+        let mut readable = Vec::new();
+        for drive in drives {
+            if let Some(drive_id) = drive.drive_id {
+                if self.has_permission(user_id, Permission::DriveRead, drive).await? {
+                    readable.push(drive_id);
+                }
+            }
+        }
+        Ok(readable)
+    }
+
+    /// Every way `user_id` may use a drive item, each with a reason such as
+    /// "Editor via folder /Finance granted by Ann Example"
+    #[instrument(skip(self, item), fields(item_id = %item.id))]
+    pub async fn explain_item_access(&self, user_id: Id, item: &DriveItem) -> Result<AccessExplanation> {
+        let mut access = self.effective_access(user_id, item).await?;
+
+        let resource = Resource::owned_by(item.permissions.owner_id).in_drive(item.drive_id);
+        let role_permissions = SharingPermissions::from_drive_permissions(&self.permissions(user_id, &resource).await?);
+        if !role_permissions.is_empty() {
+            access.add(AccessGrant {
+                source: AccessSource::Role,
+                role: None,
+                permissions: role_permissions,
+                granted_by: None,
+                expires_at: None,
+                inherited_from: None,
+            });
+        }
+
+        let mut names: HashMap<Id, String> = HashMap::new();
+        for granted_by in access.grants.iter().filter_map(|grant| grant.granted_by) {
+            if names.contains_key(&granted_by) {
+                continue;
+            }
+            if let Some(user) = self.user_repository.find_by_id(granted_by).await? {
+                names.insert(granted_by, user.full_name());
+            }
+        }
+
+        let grants = access
+            .grants
+            .into_iter()
+            .map(|grant| {
+                let granted_by = grant.granted_by.and_then(|id| names.get(&id)).map(String::as_str);
+                ExplainedGrant {
+                    explanation: grant.describe(granted_by),
+                    grant,
+                }
+            })
+            .collect();
+        Ok(AccessExplanation {
+            item_id: access.item_id,
+            user_id: access.user_id,
+            permissions: access.permissions,
+            grants,
+        })
+    }
+
+    pub async fn list_roles(&self) -> Result<Vec<Role>> {
+        self.role_repository.list_roles().await
+    }
+
+    #[instrument(skip(self, request))]
+    pub async fn create_role(&self, request: CreateRoleRequest) -> Result<Role> {
+        request
+            .validate()
+            .map_err(|e| Error::Validation(e.to_string()))?;
+
+        let name = request.name.trim().to_lowercase();
+        if self.role_repository.find_role_by_name(&name).await?.is_some() {
+            return Err(Error::Conflict(format!("A role named '{}' already exists", name)));
+        }
+
+        let role = Role::new(name, request.description, request.permissions);
+        let role = self.role_repository.create_role(role).await?;
+
+        info!(role_id = %role.id, name = %role.name, "Role created");
+        Ok(role)
+    }
+
+    #[instrument(skip(self, request))]
+    pub async fn update_role(&self, role_id: Id, request: UpdateRoleRequest) -> Result<Role> {
+        request
+            .validate()
+            .map_err(|e| Error::Validation(e.to_string()))?;
+
+        let mut role = self.find_custom_role(role_id).await?;
+        if let Some(description) = request.description {
+            role.description = Some(description);
+        }
+        if let Some(permissions) = request.permissions {
+            role.permissions = normalize_permissions(permissions);
+        }
+        role.updated_at = chrono::Utc::now();
+
+        self.role_repository.update_role(role).await
+    }
+
+    /// Delete a custom role; its assignments go with it
+    #[instrument(skip(self))]
+    pub async fn delete_role(&self, role_id: Id) -> Result<()> {
+        self.find_custom_role(role_id).await?;
+        self.role_repository.delete_role(role_id).await?;
+
+        info!(role_id = %role_id, "Role deleted");
+        Ok(())
+    }
+
+    pub async fn list_assignments(&self, user_id: Id) -> Result<Vec<RoleAssignment>> {
+        self.role_repository.list_assignments(user_id).await
+    }
+
+    #[instrument(skip(self, request))]
+    pub async fn assign_role(&self, admin_id: Id, user_id: Id, request: AssignRoleRequest) -> Result<RoleAssignment> {
+        self.user_repository
+            .find_by_id(user_id)
+            .await?
+            .ok_or_else(|| Error::NotFound("User not found".to_string()))?;
+        let role = self
+            .role_repository
+            .find_role(request.role_id)
+            .await?
+            .ok_or_else(|| Error::NotFound("Role not found".to_string()))?;
+        if let RoleScope::Team(team_id) = request.scope {
+            self.team_repository
+                .find_by_id(team_id)
+                .await?
+                .ok_or_else(|| Error::NotFound("Team not found".to_string()))?;
+        }
+
+        let assignment = RoleAssignment::new(user_id, role.id, request.scope, Some(admin_id));
+        if self.role_repository.find_assignment(&assignment).await?.is_some() {
+            return Err(Error::Conflict("The user already has this role here".to_string()));
+        }
+        let assignment = self.role_repository.create_assignment(assignment).await?;
+
+        self.record_event(SecurityEventType::RoleAssigned, admin_id, &assignment, &role.name)
+            .await?;
+        info!(user_id = %user_id, role = %role.name, scope = %assignment.scope, "Role assigned");
+        Ok(assignment)
+    }
+
+    #[instrument(skip(self))]
+    pub async fn revoke_assignment(&self, admin_id: Id, assignment_id: Id) -> Result<()> {
+        let assignment = self
+            .role_repository
+            .delete_assignment(assignment_id)
+            .await?
+            .ok_or_else(|| Error::NotFound("Role assignment not found".to_string()))?;
+        let role_name = self
+            .role_repository
+            .find_role(assignment.role_id)
+            .await?
+            .map(|role| role.name)
+            .unwrap_or_default();
+
+        self.record_event(SecurityEventType::RoleRevoked, admin_id, &assignment, &role_name)
+            .await?;
+        info!(user_id = %assignment.user_id, role = %role_name, "Role revoked");
+        Ok(())
+    }
+
+    pub async fn list_teams(&self) -> Result<Vec<Team>> {
+        self.team_repository.list().await
+    }
+
+    #[instrument(skip(self, request))]
+    pub async fn create_team(&self, admin_id: Id, request: CreateTeamRequest) -> Result<Team> {
+        request
+            .validate()
+            .map_err(|e| Error::Validation(e.to_string()))?;
+
+        let name = request.name.trim().to_string();
+        if self.team_repository.find_by_name(&name).await?.is_some() {
+            return Err(Error::Conflict(format!("A team named '{}' already exists", name)));
+        }
+
+        let team = Team::new(name, request.description, Some(admin_id));
+        self.team_repository.create(team).await
+    }
+
+    /// Delete a team and the role assignments scoped to it
+    #[instrument(skip(self))]
+    pub async fn delete_team(&self, team_id: Id) -> Result<()> {
+        if !self.team_repository.delete(team_id).await? {
+            return Err(Error::NotFound("Team not found".to_string()));
+        }
+        Ok(())
+    }
+
+    /// The folders whose sharing reaches an item; none without a drive repository
+    async fn item_ancestors(&self, item_id: Id) -> Result<Vec<Folder>> {
+        match &self.drive_repository {
+            Some(drive_repository) => drive_repository.get_item_ancestors(item_id).await,
+            None => Ok(Vec::new()),
+        }
+    }
+
+    async fn find_custom_role(&self, role_id: Id) -> Result<Role> {
+        let role = self
+            .role_repository
+            .find_role(role_id)
+            .await?
+            .ok_or_else(|| Error::NotFound("Role not found".to_string()))?;
+        if role.is_system {
+            return Err(Error::BadRequest("Built-in roles can't be changed".to_string()));
+        }
+        Ok(role)
+    }
+
+    async fn record_event(
+        &self,
+        event_type: SecurityEventType,
+        admin_id: Id,
+        assignment: &RoleAssignment,
+        role_name: &str,
+    ) -> Result<()> {
+        self.event_repository
+            .record(SecurityEvent::new(
+                event_type,
+                Some(admin_id),
+                format!("user:{}", assignment.user_id),
+                None,
+                json!({
+                    "assignment_id": assignment.id,
+                    "role": role_name,
+                    "scope": assignment.scope,
+                }),
+            ))
+            .await
+    }
+}
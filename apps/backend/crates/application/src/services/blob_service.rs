@@ -0,0 +1,166 @@
+use kingshare_core::{Error, Result};
+use kingshare_domain::{
+    entities::BLOB_GC_GRACE_MINUTES,
+    repositories::BlobRepository,
+    services::{StorageService, StoredFile},
+};
+use std::collections::HashSet;
+use std::sync::Arc;
+use tracing::{info, instrument, warn};
+
+const GC_BATCH_SIZE: i64 = 500;
+const ORPHAN_SCAN_BATCH_SIZE: usize = 500;
+
+#[derive(Clone)]
+pub struct BlobService {
+    blob_repository: Arc<dyn BlobRepository>,
+    storage_service: Arc<dyn StorageService>,
+}
+
+impl BlobService {
+    pub fn new(
+        blob_repository: Arc<dyn BlobRepository>,
+        storage_service: Arc<dyn StorageService>,
+    ) -> Self {
+        Self {
+            blob_repository,
+            storage_service,
+        }
+    }
+
+    /// Take a reference on freshly stored content. If the same content is already
+    /// known under another path, the new copy is discarded in favour of it.
+    #[instrument(skip(self, stored_file), fields(checksum = %stored_file.checksum))]
+    pub async fn acquire(&self, stored_file: StoredFile) -> Result<StoredFile> {
+        let blob = self
+            .blob_repository
+            .acquire(&stored_file.checksum, &stored_file.path, stored_file.size as i64)
+            .await?;
+
+        if blob.storage_path != stored_file.path {
+            if let Err(e) = self.storage_service.delete_file(&stored_file.path).await {
+                warn!(
+                    storage_path = %stored_file.path,
+                    error = %e,
+                    "Failed to delete duplicate blob copy"
+                );
+            }
+        }
+
+        Ok(StoredFile {
+            path: blob.storage_path,
+            size: blob.size as u64,
+            checksum: blob.checksum,
+        })
+    }
+
+    /// Drop a file's reference; the content itself is removed by `collect_garbage`
+    #[instrument(skip(self))]
+    pub async fn release(&self, checksum: &str) -> Result<()> {
+        match self.blob_repository.release(checksum).await? {
+            Some(blob) if !blob.is_referenced() => {
+                info!(checksum = %checksum, "Blob is no longer referenced");
+            }
+            Some(_) => {}
+            None => {
+                warn!(checksum = %checksum, "Released a blob that had no references");
+            }
+        }
+        Ok(())
+    }
+
+    /// Delete blobs that have stayed unreferenced for longer than the grace period
+    #[instrument(skip(self))]
+    pub async fn collect_garbage(&self) -> Result<u64> {
+        let idle_since = chrono::Utc::now() - chrono::Duration::minutes(BLOB_GC_GRACE_MINUTES);
+        let mut deleted_count = 0;
+
+        loop {
+            let blobs = self
+                .blob_repository
+                .find_unreferenced(idle_since, GC_BATCH_SIZE)
+                .await?;
+            if blobs.is_empty() {
+                break;
+            }
+
+            for blob in blobs {
+                // Marked first so no new file can reference the content, and the
+                // row kept until the content is gone so it is never left untracked
+                if !self.blob_repository.mark_collecting(&blob.checksum).await? {
+                    continue;
+                }
+
+                match self.storage_service.delete_file(&blob.storage_path).await {
+                    Ok(()) | Err(Error::NotFound(_)) => {}
+                    Err(e) => {
+                        // Marking moved it past this run's cutoff; a later run retries it
+                        warn!(
+                            checksum = %blob.checksum,
+                            storage_path = %blob.storage_path,
+                            error = %e,
+                            "Failed to delete unreferenced blob from storage"
+                        );
+                        continue;
+                    }
+                }
+
+                if self.blob_repository.delete_unreferenced(&blob.checksum).await? {
+                    deleted_count += 1;
+                }
+            }
+        }
+
+        info!(deleted_count = deleted_count, "Blob garbage collection completed");
+        Ok(deleted_count)
+    }
+
+    /// Remove stored objects that no blob row points at, e.g. leftovers from a
+    /// crash between writing content and registering it.
+    #[instrument(skip(self))]
+    pub async fn cleanup_orphaned_files(&self) -> Result<u64> {
+        let cutoff = chrono::Utc::now() - chrono::Duration::minutes(BLOB_GC_GRACE_MINUTES);
+
+        // Recent objects may belong to an upload that has not been registered yet
+        let candidates: Vec<_> = self
+            .storage_service
+            .list_files()
+            .await?
+            .into_iter()
+            .filter(|object| object.last_modified < cutoff)
+            .collect();
+
+        let mut removed_count = 0;
+        for batch in candidates.chunks(ORPHAN_SCAN_BATCH_SIZE) {
+            let paths = batch.iter().map(|object| object.path.clone()).collect();
+            let known: HashSet<String> = self
+                .blob_repository
+                .find_known_paths(paths)
+                .await?
+                .into_iter()
+                .collect();
+
+            for object in batch.iter().filter(|object| !known.contains(&object.path)) {
+                match self.storage_service.delete_file(&object.path).await {
+                    Ok(()) => {
+                        removed_count += 1;
+                        info!(path = %object.path, "Orphaned file removed");
+                    }
+                    Err(e) => {
+                        warn!(path = %object.path, error = %e, "Failed to remove orphaned file");
+                    }
+                }
+            }
+        }
+
+        info!(removed_count = removed_count, "Orphaned files cleanup completed");
+        Ok(removed_count)
+    }
+
+    /// Re-wrap every data key under the current master key. Blob contents are
+    /// untouched; afterwards the retired master key can be removed from config.
+    #[instrument(skip(self))]
+    pub async fn rotate_encryption_keys(&self) -> Result<u64> {
+        self.storage_service.rotate_encryption_keys().await
+    }
+}
@@ -1,36 +1,79 @@
 use kingshare_core::{Error, Id, PaginatedResponse, PaginationParams, Result};
 use kingshare_domain::{
-    entities::{CreateFileRequest, File, FileMetadata, UpdateFileRequest, WebSocketMessage},
-    repositories::FileRepository,
-    services::{FileService as DomainFileService, FileUpload, StorageService, WebSocketService},
+    entities::{
+        File, FileMetadata, QuotaScope, ScanStatus, Thumbnail, ThumbnailSize, UpdateFileRequest,
+        WebSocketMessage,
+    },
+    repositories::{DriveService, FileRepository},
+    services::{
+        FileService as DomainFileService, FileStream, FileUpload, StorageService, StoredFile,
+        WebSocketService,
+    },
+    value_objects::ByteRange,
 };
+use crate::services::{BlobService, QuotaService, ScanService, ScanSummary, ThumbnailService};
 use std::sync::Arc;
-use tracing::{info, instrument};
+use tracing::{info, instrument, warn};
 use validator::Validate;
 
 #[derive(Clone)]
 pub struct FileService {
     file_repository: Arc<dyn FileRepository>,
     storage_service: Arc<dyn StorageService>,
+    blob_service: BlobService,
     file_service: Arc<dyn DomainFileService>,
     websocket_service: Option<Arc<dyn WebSocketService>>,
+    thumbnail_service: Option<ThumbnailService>,
+    scan_service: Option<ScanService>,
+    quota_service: Option<QuotaService>,
+    drive_service: Option<Arc<dyn DriveService>>,
 }
 
 impl FileService {
     pub fn new(
         file_repository: Arc<dyn FileRepository>,
         storage_service: Arc<dyn StorageService>,
+        blob_service: BlobService,
         file_service: Arc<dyn DomainFileService>,
         websocket_service: Option<Arc<dyn WebSocketService>>,
     ) -> Self {
         Self {
             file_repository,
             storage_service,
+            blob_service,
             file_service,
             websocket_service,
+            thumbnail_service: None,
+            scan_service: None,
+            quota_service: None,
+            drive_service: None,
         }
     }
 
+    /// Render thumbnails for image uploads in the background
+    pub fn with_thumbnails(mut self, thumbnail_service: ThumbnailService) -> Self {
+        self.thumbnail_service = Some(thumbnail_service);
+        self
+    }
+
+    /// Scan every upload for malware; files can't be downloaded until they pass
+    pub fn with_malware_scanning(mut self, scan_service: ScanService) -> Self {
+        self.scan_service = Some(scan_service);
+        self
+    }
+
+    /// Charge stored files against their owner's storage quota
+    pub fn with_quotas(mut self, quota_service: QuotaService) -> Self {
+        self.quota_service = Some(quota_service);
+        self
+    }
+
+    /// Index the text of stored files for the drive items holding them
+    pub fn with_search_index(mut self, drive_service: Arc<dyn DriveService>) -> Self {
+        self.drive_service = Some(drive_service);
+        self
+    }
+
     #[instrument(skip(self, file_data))]
     pub async fn upload_file(
         &self,
@@ -39,10 +82,38 @@ impl FileService {
         content_type: String,
         file_data: Vec<u8>,
     ) -> Result<FileMetadata> {
-        // Validate file
+        // Validate file; the stored type comes from the content, not the client
+        let content_type = self
+            .check_upload(&filename, &content_type, file_data.len() as u64, &file_data)
+            .await?;
+
+        // Store file
+        let upload = FileUpload {
+            filename: filename.clone(),
+            content_type: content_type.clone(),
+            data: file_data,
+        };
+
+        let stored_file = self.storage_service.store_file(upload).await?;
+
+        self.register_stored_file(owner_id, filename, content_type, stored_file)
+            .await
+    }
+
+    /// Validate an upload against its name, declared type and content. `sample` is
+    /// the whole file or its first and last `CONTENT_SNIFF_WINDOW` bytes. Returns
+    /// the content type to store the file as.
+    #[instrument(skip(self, sample))]
+    pub async fn check_upload(
+        &self,
+        filename: &str,
+        content_type: &str,
+        size: u64,
+        sample: &[u8],
+    ) -> Result<String> {
         let validation_result = self
             .file_service
-            .validate_file(&filename, &content_type, file_data.len() as u64, &file_data)
+            .validate_file(filename, content_type, size, sample)
             .await?;
 
         if !validation_result.is_valid {
@@ -52,17 +123,41 @@ impl FileService {
             )));
         }
 
-        // Store file
-        let upload = FileUpload {
-            filename: filename.clone(),
-            content_type: content_type.clone(),
-            data: file_data,
-        };
+        for warning in &validation_result.warnings {
+            warn!(filename = %filename, warning = %warning, "File validation warning");
+        }
 
-        let stored_file = self.storage_service.store_file(upload).await?;
+        Ok(validation_result.content_type)
+    }
+
+    /// Creates the `File` row for a blob that has already been written to storage
+    /// and notifies the owner. Shared by direct and resumable uploads.
+    #[instrument(skip(self, stored_file))]
+    pub async fn register_stored_file(
+        &self,
+        owner_id: Id,
+        filename: String,
+        content_type: String,
+        stored_file: StoredFile,
+    ) -> Result<FileMetadata> {
+        // Charge the owner first; content over quota is left to the orphan scan
+        let size = stored_file.size as i64;
+        if let Some(quota_service) = &self.quota_service {
+            quota_service.reserve(owner_id, None, size).await?;
+        }
+
+        // Point the file at a shared blob, reusing existing content when possible
+        let stored_file = match self.blob_service.acquire(stored_file).await {
+            Ok(stored_file) => stored_file,
+            Err(e) => {
+                self.release_quota(owner_id, size).await;
+                return Err(e);
+            }
+        };
+        let checksum = stored_file.checksum.clone();
 
         // Create file entity
-        let file = File::new(
+        let mut file = File::new(
             owner_id,
             filename.clone(),
             filename,
@@ -71,9 +166,22 @@ impl FileService {
             stored_file.path,
             stored_file.checksum,
         );
+        if self.scan_service.is_none() {
+            file.scan_status = ScanStatus::Clean;
+        }
 
         // Save to database
-        let created_file = self.file_repository.create(file).await?;
+        let created_file = match self.file_repository.create(file).await {
+            Ok(file) => file,
+            Err(e) => {
+                let _ = self.blob_service.release(&checksum).await;
+                self.release_quota(owner_id, size).await;
+                return Err(e);
+            }
+        };
+
+        self.spawn_processing(&created_file);
+        self.spawn_indexing(&created_file);
 
         // Get file metadata for response
         let metadata = self.get_file_metadata(created_file.id).await?;
@@ -82,7 +190,7 @@ impl FileService {
         if let Some(ws_service) = &self.websocket_service {
             let message = WebSocketMessage::FileUploaded {
                 file_id: created_file.id,
-                filename: created_file.filename,
+                filename: created_file.filename.clone(),
                 size: created_file.size,
             };
             let _ = ws_service.send_to_user(owner_id, message).await;
@@ -139,6 +247,7 @@ impl FileService {
             size: file.size,
             is_public: file.is_public,
             download_count: file.download_count,
+            scan_status: file.scan_status,
             created_at: file.created_at,
             expires_at: file.expires_at,
             owner,
@@ -223,18 +332,10 @@ impl FileService {
             return Err(Error::Authorization("Not authorized to delete this file".to_string()));
         }
 
-        // Delete from storage
-        if let Err(e) = self.storage_service.delete_file(&file.storage_path).await {
-            tracing::warn!(
-                file_id = %file_id,
-                storage_path = %file.storage_path,
-                error = %e,
-                "Failed to delete file from storage"
-            );
-        }
-
-        // Delete from database
+        // Delete from database; the row's blob reference goes with it, and the
+        // content is removed once nothing uses it
         self.file_repository.delete(file_id).await?;
+        self.release_quota(file.owner_id, file.size).await;
 
         // Send WebSocket notification
         if let Some(ws_service) = &self.websocket_service {
@@ -257,7 +358,84 @@ impl FileService {
 
     #[instrument(skip(self))]
     pub async fn download_file(&self, file_id: Id, user_id: Option<Id>) -> Result<(File, Vec<u8>)> {
-        let mut file = self.get_file(file_id).await?;
+        let file = self.authorize_download(file_id, user_id).await?;
+
+        // Get file data from storage
+        let file_data = self.storage_service.get_file(&file.storage_path).await?;
+
+        let file = self.record_download(file, user_id).await;
+        Ok((file, file_data))
+    }
+
+    #[instrument(skip(self))]
+    pub async fn download_file_stream(
+        &self,
+        file_id: Id,
+        user_id: Option<Id>,
+    ) -> Result<(File, FileStream)> {
+        let file = self.authorize_download(file_id, user_id).await?;
+        let (file, mut streams) = self.open_download(file, user_id, &[]).await?;
+
+        let stream = streams
+            .pop()
+            .ok_or_else(|| Error::Internal("Failed to open file stream".to_string()))?;
+        Ok((file, stream))
+    }
+
+    /// Open streams for a file that already passed `authorize_download`.
+    ///
+    /// An empty `ranges` slice opens the whole file as a single stream.
+    #[instrument(skip(self, file), fields(file_id = %file.id))]
+    pub async fn open_download(
+        &self,
+        file: File,
+        user_id: Option<Id>,
+        ranges: &[ByteRange],
+    ) -> Result<(File, Vec<FileStream>)> {
+        let streams = if ranges.is_empty() {
+            vec![self.storage_service.get_file_stream(&file.storage_path).await?]
+        } else {
+            let mut streams = Vec::with_capacity(ranges.len());
+            for range in ranges {
+                streams.push(
+                    self.storage_service
+                        .get_file_range_stream(&file.storage_path, *range)
+                        .await?,
+                );
+            }
+            streams
+        };
+
+        // Players issue many range requests while seeking; only count the one
+        // that starts at the beginning of the file.
+        let file = if ranges.is_empty() || ranges[0].start == 0 {
+            self.record_download(file, user_id).await
+        } else {
+            file
+        };
+
+        Ok((file, streams))
+    }
+
+    /// Open a thumbnail of a file the user is allowed to download
+    #[instrument(skip(self))]
+    pub async fn open_thumbnail(
+        &self,
+        file_id: Id,
+        user_id: Option<Id>,
+        size: ThumbnailSize,
+    ) -> Result<(Thumbnail, FileStream)> {
+        let thumbnail_service = self
+            .thumbnail_service
+            .as_ref()
+            .ok_or_else(|| Error::NotFound("Thumbnails are not enabled".to_string()))?;
+
+        let file = self.authorize_download(file_id, user_id).await?;
+        thumbnail_service.open_thumbnail(&file, size).await
+    }
+
+    pub async fn authorize_download(&self, file_id: Id, user_id: Option<Id>) -> Result<File> {
+        let file = self.get_file(file_id).await?;
 
         // Check access permissions
         if !file.is_public {
@@ -275,9 +453,77 @@ impl FileService {
             return Err(Error::BadRequest("File has expired".to_string()));
         }
 
-        // Get file data from storage
-        let file_data = self.storage_service.get_file(&file.storage_path).await?;
+        file.ensure_scan_passed()?;
+
+        Ok(file)
+    }
 
+    /// Background work after an upload: the malware scan, then thumbnails once
+    /// the content is known to be clean
+    fn spawn_processing(&self, file: &File) {
+        let Some(scan_service) = self.scan_service.clone() else {
+            if let Some(thumbnail_service) = &self.thumbnail_service {
+                thumbnail_service.spawn_generation(file);
+            }
+            return;
+        };
+
+        let thumbnail_service = self.thumbnail_service.clone();
+        let file = file.clone();
+        tokio::spawn(async move {
+            match scan_service.scan_file(&file).await {
+                Ok(ScanStatus::Clean) => {
+                    if let Some(thumbnail_service) = &thumbnail_service {
+                        thumbnail_service.spawn_generation(&file);
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    warn!(file_id = %file.id, error = %e, "Malware scan failed, file stays pending");
+                }
+            }
+        });
+    }
+
+    fn spawn_indexing(&self, file: &File) {
+        let Some(drive_service) = self.drive_service.clone() else {
+            return;
+        };
+
+        let file_id = file.id;
+        let storage_path = file.storage_path.clone();
+        tokio::spawn(async move {
+            if let Err(e) = drive_service.index_file_items(file_id, &storage_path).await {
+                warn!(file_id = %file_id, error = %e, "Indexing file contents failed");
+            }
+        });
+    }
+
+    /// Retry scans that didn't complete, e.g. while the scanner was unreachable
+    #[instrument(skip(self))]
+    pub async fn scan_pending_files(&self) -> Result<ScanSummary> {
+        match &self.scan_service {
+            Some(scan_service) => scan_service.scan_pending_files().await,
+            None => Ok(ScanSummary::default()),
+        }
+    }
+
+    /// Fail before any bytes are accepted when `size` can't fit in the owner's quota
+    #[instrument(skip(self))]
+    pub async fn ensure_quota_available(&self, owner_id: Id, size: i64) -> Result<()> {
+        match &self.quota_service {
+            Some(quota_service) => quota_service.ensure_available(owner_id, size).await,
+            None => Ok(()),
+        }
+    }
+
+    async fn release_quota(&self, owner_id: Id, size: i64) {
+        if let Some(quota_service) = &self.quota_service {
+            quota_service.release(owner_id, None, size).await;
+        }
+    }
+
+    async fn record_download(&self, mut file: File, user_id: Option<Id>) -> File {
         // Increment download count
         file.increment_download_count();
         let _ = self.file_repository.update(file.clone()).await;
@@ -285,7 +531,7 @@ impl FileService {
         // Send WebSocket notification
         if let Some(ws_service) = &self.websocket_service {
             let message = WebSocketMessage::DownloadStarted {
-                file_id,
+                file_id: file.id,
                 filename: file.filename.clone(),
             };
             
@@ -295,25 +541,30 @@ impl FileService {
         }
 
         info!(
-            file_id = %file_id,
+            file_id = %file.id,
             user_id = ?user_id,
             filename = %file.filename,
-            size = file_data.len(),
+            size = file.size,
             "File downloaded"
         );
 
-        Ok((file, file_data))
+        file
     }
 
     #[instrument(skip(self))]
     pub async fn get_user_storage_stats(&self, owner_id: Id) -> Result<UserStorageStats> {
         let file_count = self.file_repository.count_by_owner(owner_id).await?;
         let total_size = self.file_repository.get_total_size_by_owner(owner_id).await?;
+        let quota = match &self.quota_service {
+            Some(quota_service) => quota_service.get_usage(QuotaScope::User(owner_id)).await?.quota,
+            None => None,
+        };
 
         Ok(UserStorageStats {
             file_count,
             total_size,
             max_file_size: self.file_service.get_max_file_size().await,
+            quota,
         })
     }
 
@@ -323,26 +574,20 @@ impl FileService {
         let mut deleted_count = 0;
 
         for file in expired_files {
-            // Delete from storage
-            if let Err(e) = self.storage_service.delete_file(&file.storage_path).await {
-                tracing::warn!(
+            // Delete each row individually so its quota is released exactly once
+            if let Err(e) = self.file_repository.delete(file.id).await {
+                warn!(
                     file_id = %file.id,
-                    storage_path = %file.storage_path,
                     error = %e,
-                    "Failed to delete expired file from storage"
+                    "Failed to delete expired file"
                 );
+                continue;
             }
+            self.release_quota(file.owner_id, file.size).await;
             deleted_count += 1;
         }
 
-        // Delete from database
-        let db_deleted = self.file_repository.cleanup_expired_files().await?;
-
-        info!(
-            deleted_count = deleted_count,
-            db_deleted = db_deleted,
-            "Expired files cleanup completed"
-        );
+        info!(deleted_count = deleted_count, "Expired files cleanup completed");
 
         Ok(deleted_count)
     }
@@ -353,4 +598,5 @@ pub struct UserStorageStats {
     pub file_count: u64,
     pub total_size: i64,
     pub max_file_size: u64,
+    pub quota: Option<i64>, // bytes, `None` when unlimited
 }
\ No newline at end of file
@@ -0,0 +1,192 @@
+use chrono::Utc;
+use kingshare_core::{Error, Id, Result};
+use kingshare_domain::{
+    entities::{
+        AttemptBlock, AttemptKey, AttemptKind, FailedAttempts, LockoutPolicy, SecurityEvent, SecurityEventType,
+    },
+    repositories::{LockoutRepository, SecurityEventRepository},
+};
+use serde_json::json;
+use std::sync::Arc;
+use tracing::{info, instrument, warn};
+
+/// Slows down password guessing on logins and shares. Failures are counted per
+/// key; past the free attempts each one blocks the key for a doubling delay,
+/// and at the threshold the key is locked until it expires or an admin lifts it.
+#[derive(Clone)]
+pub struct LockoutService {
+    lockout_repository: Arc<dyn LockoutRepository>,
+    event_repository: Arc<dyn SecurityEventRepository>,
+    policy: LockoutPolicy,
+}
+
+impl LockoutService {
+    pub fn new(
+        lockout_repository: Arc<dyn LockoutRepository>,
+        event_repository: Arc<dyn SecurityEventRepository>,
+        policy: LockoutPolicy,
+    ) -> Self {
+        Self {
+            lockout_repository,
+            event_repository,
+            policy,
+        }
+    }
+
+    /// Count an attempt against every key before its password is checked, so
+    /// guesses sent in parallel can't all get in ahead of the first failure.
+    /// Refused while any key is blocked. Follow with `attempt_failed`,
+    /// `attempt_succeeded` or `cancel_attempt`.
+    #[instrument(skip(self, keys))]
+    pub async fn begin_attempt(&self, keys: &[AttemptKey]) -> Result<Vec<FailedAttempts>> {
+        let window_start = Utc::now() - self.policy.attempt_window;
+        let mut reserved = Vec::with_capacity(keys.len());
+
+        for key in keys {
+            let (attempts, counted) = self
+                .lockout_repository
+                .reserve_attempt(key, window_start, &self.policy)
+                .await?;
+            if !counted {
+                self.cancel_attempt(&reserved).await?;
+                return Err(Self::blocked(&attempts));
+            }
+            reserved.push(attempts);
+        }
+
+        Ok(reserved)
+    }
+
+    /// The attempt `begin_attempt` counted failed; record the lockouts it started
+    #[instrument(skip(self, reserved, ip_address))]
+    pub async fn attempt_failed(&self, reserved: &[FailedAttempts], ip_address: Option<&str>) -> Result<()> {
+        for attempts in reserved.iter().filter(|attempts| attempts.locked) {
+            self.lockout_started(attempts, self.policy.lockout_duration.num_seconds(), ip_address)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// The attempt `begin_attempt` counted never got as far as its password
+    #[instrument(skip(self, reserved))]
+    pub async fn cancel_attempt(&self, reserved: &[FailedAttempts]) -> Result<()> {
+        for attempts in reserved {
+            self.release(attempts).await?;
+        }
+        Ok(())
+    }
+
+    /// The attempt `begin_attempt` counted succeeded. Accounts and shares start
+    /// over; addresses get the attempt back but keep their earlier failures,
+    /// so signing in to one account doesn't reset guessing at others.
+    #[instrument(skip(self, reserved))]
+    pub async fn attempt_succeeded(&self, reserved: &[FailedAttempts]) -> Result<()> {
+        for attempts in reserved {
+            if attempts.key.kind == AttemptKind::Ip {
+                self.release(attempts).await?;
+            } else {
+                self.lockout_repository.clear(&attempts.key).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Count a failure against every key outright, blocked or not, blocking
+    /// those past their free attempts. Lockouts are recorded as security events.
+    #[instrument(skip(self, keys, ip_address))]
+    pub async fn record_failure(&self, keys: &[AttemptKey], ip_address: Option<&str>) -> Result<()> {
+        let now = Utc::now();
+        let window_start = now - self.policy.attempt_window;
+
+        for key in keys {
+            let attempts = self.lockout_repository.record_failure(key, window_start).await?;
+            let Some(block) = self.policy.block_after(key.kind, attempts.failures) else {
+                continue;
+            };
+
+            match block {
+                AttemptBlock::Backoff(delay) => {
+                    self.lockout_repository.block(key, now + delay, false).await?;
+                }
+                AttemptBlock::Lockout(duration) => {
+                    let already_locked = attempts.locked && attempts.retry_after(now).is_some();
+                    self.lockout_repository.block(key, now + duration, true).await?;
+                    if !already_locked {
+                        self.lockout_started(&attempts, duration.num_seconds(), ip_address).await?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub async fn list_blocked(&self) -> Result<Vec<FailedAttempts>> {
+        self.lockout_repository.list_blocked().await
+    }
+
+    /// Lift a lockout or backoff on behalf of an admin
+    #[instrument(skip(self), fields(key = %key))]
+    pub async fn unlock(&self, admin_id: Id, key: &AttemptKey) -> Result<()> {
+        if !self.lockout_repository.clear(key).await? {
+            return Err(Error::NotFound("No failed attempts recorded".to_string()));
+        }
+
+        self.event_repository
+            .record(SecurityEvent::new(
+                SecurityEventType::LockoutCleared,
+                Some(admin_id),
+                key.to_string(),
+                None,
+                json!({ "kind": key.kind }),
+            ))
+            .await?;
+
+        info!(admin_id = %admin_id, key = %key, "Lockout cleared");
+        Ok(())
+    }
+
+    pub async fn list_events(&self, event_type: Option<SecurityEventType>, limit: u32) -> Result<Vec<SecurityEvent>> {
+        self.event_repository.list_recent(event_type, limit).await
+    }
+
+    pub async fn prune(&self) -> Result<u64> {
+        self.lockout_repository
+            .prune(Utc::now() - self.policy.attempt_window)
+            .await
+    }
+
+    async fn release(&self, attempts: &FailedAttempts) -> Result<()> {
+        self.lockout_repository
+            .release_attempt(&attempts.key, attempts.blocked_until)
+            .await
+    }
+
+    fn blocked(attempts: &FailedAttempts) -> Error {
+        let retry_after = attempts.retry_after(Utc::now()).unwrap_or(1);
+        let message = if attempts.locked {
+            "Too many failed attempts; try again later".to_string()
+        } else {
+            format!("Too many failed attempts; try again in {} seconds", retry_after)
+        };
+        Error::TooManyRequests { message, retry_after }
+    }
+
+    async fn lockout_started(&self, attempts: &FailedAttempts, seconds: i64, ip_address: Option<&str>) -> Result<()> {
+        warn!(key = %attempts.key, failures = attempts.failures, "Locked out after repeated failures");
+
+        self.event_repository
+            .record(SecurityEvent::new(
+                SecurityEventType::LockoutStarted,
+                None,
+                attempts.key.to_string(),
+                ip_address.map(str::to_string),
+                json!({
+                    "kind": attempts.key.kind,
+                    "failures": attempts.failures,
+                    "duration_seconds": seconds,
+                }),
+            ))
+            .await
+    }
+}
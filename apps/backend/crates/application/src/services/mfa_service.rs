@@ -0,0 +1,178 @@
+use kingshare_core::{Error, Id, Result};
+use kingshare_domain::{
+    entities::{MfaStatus, RecoveryCode, TotpEnrollment, TotpSetup, UserProfile, UserRole, RECOVERY_CODE_COUNT},
+    repositories::MfaRepository,
+    services::TotpService,
+};
+use std::sync::Arc;
+use tracing::{info, instrument, warn};
+
+#[derive(Clone)]
+pub struct MfaService {
+    mfa_repository: Arc<dyn MfaRepository>,
+    totp_service: Arc<dyn TotpService>,
+    require_for_admins: bool,
+}
+
+impl MfaService {
+    pub fn new(
+        mfa_repository: Arc<dyn MfaRepository>,
+        totp_service: Arc<dyn TotpService>,
+        require_for_admins: bool,
+    ) -> Self {
+        Self {
+            mfa_repository,
+            totp_service,
+            require_for_admins,
+        }
+    }
+
+    /// Whether users with this role must have 2FA enabled
+    pub fn is_required(&self, role: &UserRole) -> bool {
+        self.require_for_admins && *role == UserRole::Admin
+    }
+
+    #[instrument(skip(self))]
+    pub async fn is_enabled(&self, user_id: Id) -> Result<bool> {
+        Ok(self
+            .mfa_repository
+            .find_enrollment(user_id)
+            .await?
+            .is_some_and(|enrollment| enrollment.is_confirmed()))
+    }
+
+    #[instrument(skip(self, user), fields(user_id = %user.id))]
+    pub async fn status(&self, user: &UserProfile) -> Result<MfaStatus> {
+        Ok(MfaStatus {
+            enabled: self.is_enabled(user.id).await?,
+            required: self.is_required(&user.role),
+            recovery_codes_remaining: self.mfa_repository.count_unused_recovery_codes(user.id).await?,
+        })
+    }
+
+    /// Start enrolling with a new secret. Nothing changes for logins until a code
+    /// from it is confirmed; starting again replaces an unconfirmed secret.
+    #[instrument(skip(self, user), fields(user_id = %user.id))]
+    pub async fn begin_enrollment(&self, user: &UserProfile) -> Result<TotpSetup> {
+        let secret = self.totp_service.generate_secret();
+        let enrollment = self
+            .mfa_repository
+            .save_pending_enrollment(TotpEnrollment::new(user.id, secret))
+            .await?;
+
+        info!(user_id = %user.id, "Two-factor enrollment started");
+        Ok(TotpSetup {
+            otpauth_uri: self.totp_service.otpauth_uri(&enrollment.secret, &user.email),
+            secret: enrollment.secret,
+        })
+    }
+
+    /// Turn 2FA on with a code from the pending secret. Returns the recovery codes,
+    /// which are shown this once.
+    #[instrument(skip(self, code))]
+    pub async fn confirm_enrollment(&self, user_id: Id, code: &str) -> Result<Vec<String>> {
+        let enrollment = self
+            .mfa_repository
+            .find_enrollment(user_id)
+            .await?
+            .filter(|enrollment| !enrollment.is_confirmed())
+            .ok_or_else(|| Error::BadRequest("No two-factor enrollment is pending".to_string()))?;
+
+        let step = self
+            .totp_service
+            .verify_code(&enrollment.secret, code, chrono::Utc::now())?
+            .ok_or_else(|| Error::Authentication("Invalid verification code".to_string()))?;
+
+        let (codes, hashed) = self.new_recovery_codes(user_id);
+        if !self
+            .mfa_repository
+            .confirm_enrollment(user_id, step, hashed)
+            .await?
+        {
+            return Err(Error::Conflict("Two-factor enrollment was changed concurrently".to_string()));
+        }
+
+        info!(user_id = %user_id, "Two-factor authentication enabled");
+        Ok(codes)
+    }
+
+    /// Check a second factor: a current TOTP code or an unused recovery code.
+    /// Either is spent by a successful check.
+    #[instrument(skip(self, code))]
+    pub async fn verify(&self, user_id: Id, code: &str) -> Result<bool> {
+        let Some(enrollment) = self
+            .mfa_repository
+            .find_enrollment(user_id)
+            .await?
+            .filter(TotpEnrollment::is_confirmed)
+        else {
+            return Ok(false);
+        };
+
+        if let Some(step) = self
+            .totp_service
+            .verify_code(&enrollment.secret, code, chrono::Utc::now())?
+        {
+            // A code is only good once, even within its 30 seconds
+            return self.mfa_repository.record_totp_step(user_id, step).await;
+        }
+
+        let code_hash = self.totp_service.hash_recovery_code(code);
+        let used = self
+            .mfa_repository
+            .consume_recovery_code(user_id, &code_hash)
+            .await?;
+        if used {
+            warn!(user_id = %user_id, "Recovery code used");
+        }
+        Ok(used)
+    }
+
+    #[instrument(skip(self, user, code), fields(user_id = %user.id))]
+    pub async fn disable(&self, user: &UserProfile, code: &str) -> Result<()> {
+        if self.is_required(&user.role) {
+            return Err(Error::Authorization(
+                "Two-factor authentication is required for administrators".to_string(),
+            ));
+        }
+
+        self.require_valid_code(user.id, code).await?;
+        self.mfa_repository.delete_enrollment(user.id).await?;
+
+        info!(user_id = %user.id, "Two-factor authentication disabled");
+        Ok(())
+    }
+
+    /// Replace all recovery codes, invalidating the old ones
+    #[instrument(skip(self, code))]
+    pub async fn regenerate_recovery_codes(&self, user_id: Id, code: &str) -> Result<Vec<String>> {
+        self.require_valid_code(user_id, code).await?;
+
+        let (codes, hashed) = self.new_recovery_codes(user_id);
+        self.mfa_repository
+            .replace_recovery_codes(user_id, hashed)
+            .await?;
+
+        info!(user_id = %user_id, "Recovery codes regenerated");
+        Ok(codes)
+    }
+
+    async fn require_valid_code(&self, user_id: Id, code: &str) -> Result<()> {
+        if !self.is_enabled(user_id).await? {
+            return Err(Error::BadRequest("Two-factor authentication is not enabled".to_string()));
+        }
+        if !self.verify(user_id, code).await? {
+            return Err(Error::Authentication("Invalid verification code".to_string()));
+        }
+        Ok(())
+    }
+
+    fn new_recovery_codes(&self, user_id: Id) -> (Vec<String>, Vec<RecoveryCode>) {
+        let codes = self.totp_service.generate_recovery_codes(RECOVERY_CODE_COUNT);
+        let hashed = codes
+            .iter()
+            .map(|code| RecoveryCode::new(user_id, self.totp_service.hash_recovery_code(code)))
+            .collect();
+        (codes, hashed)
+    }
+}
@@ -2,7 +2,33 @@ pub mod user_service;
 pub mod file_service;
 pub mod share_service;
 pub mod auth_service;
+pub mod upload_service;
+pub mod blob_service;
+pub mod thumbnail_service;
+pub mod scan_service;
+pub mod quota_service;
+pub mod mfa_service;
+pub mod account_service;
+pub mod oidc_service;
+pub mod api_token_service;
+pub mod lockout_service;
+pub mod authorization_service;
+pub mod user_admin_service;
 
 pub use user_service::UserService;
+pub use auth_service::{AuthService, AuthSession, LoginOutcome, RegistrationOutcome};
 pub use file_service::{FileService, UserStorageStats};
-pub use share_service::ShareService;
\ No newline at end of file
+pub use share_service::{ShareService, SharedFileAccess};
+pub use upload_service::UploadService;
+
+pub use blob_service::BlobService;
+pub use thumbnail_service::ThumbnailService;
+pub use scan_service::{ScanService, ScanSummary};
+pub use quota_service::QuotaService;
+pub use mfa_service::MfaService;
+pub use account_service::AccountService;
+pub use oidc_service::OidcService;
+pub use api_token_service::ApiTokenService;
+pub use lockout_service::LockoutService;
+pub use authorization_service::AuthorizationService;
+pub use user_admin_service::{ImpersonationSession, UserAdminService};
\ No newline at end of file
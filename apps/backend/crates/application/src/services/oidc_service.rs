@@ -0,0 +1,240 @@
+use kingshare_core::{Error, Result};
+use kingshare_domain::{
+    entities::{OidcIdentity, OidcRoleMapping, User, UserIdentity, UserProfile},
+    repositories::{IdentityRepository, UserRepository},
+    services::{AuthService as DomainAuthService, OidcProvider},
+    value_objects::Email,
+};
+use std::sync::Arc;
+use tracing::{info, instrument, warn};
+
+const MIN_USERNAME_LENGTH: usize = 3;
+const MAX_USERNAME_LENGTH: usize = 50;
+/// Numbered usernames tried before giving up when the preferred one is taken
+const USERNAME_ATTEMPTS: u32 = 20;
+
+/// Single sign-on through an OpenID Connect identity provider. IdP accounts are
+/// linked to local users by subject; the first login links an existing account
+/// with the same verified email, or provisions a new one.
+#[derive(Clone)]
+pub struct OidcService {
+    provider: Arc<dyn OidcProvider>,
+    identity_repository: Arc<dyn IdentityRepository>,
+    user_repository: Arc<dyn UserRepository>,
+    auth_service: Arc<dyn DomainAuthService>,
+    role_mapping: OidcRoleMapping,
+    allow_provisioning: bool,
+}
+
+impl OidcService {
+    pub fn new(
+        provider: Arc<dyn OidcProvider>,
+        identity_repository: Arc<dyn IdentityRepository>,
+        user_repository: Arc<dyn UserRepository>,
+        auth_service: Arc<dyn DomainAuthService>,
+        role_mapping: OidcRoleMapping,
+        allow_provisioning: bool,
+    ) -> Self {
+        Self {
+            provider,
+            identity_repository,
+            user_repository,
+            auth_service,
+            role_mapping,
+            allow_provisioning,
+        }
+    }
+
+    /// Start a login: returns the IdP URL to send the browser to
+    #[instrument(skip(self))]
+    pub async fn begin_login(&self) -> Result<String> {
+        let authorization = self.provider.authorization_request().await?;
+        self.identity_repository
+            .save_login_state(authorization.login)
+            .await?;
+
+        Ok(authorization.authorization_url)
+    }
+
+    /// Finish a login with the code and state the IdP redirected back with.
+    /// Returns the local user the IdP account signs in as.
+    #[instrument(skip(self, code, state))]
+    pub async fn complete_login(&self, code: &str, state: &str) -> Result<UserProfile> {
+        let login = self
+            .identity_repository
+            .take_login_state(state)
+            .await?
+            .ok_or_else(|| Error::Authentication("Login has expired or was already completed".to_string()))?;
+        let identity = self.provider.exchange_code(code, &login).await?;
+
+        let (mut user, identity_id) = match self
+            .identity_repository
+            .find_identity(&identity.issuer, &identity.subject)
+            .await?
+        {
+            Some(link) => {
+                let user = self
+                    .user_repository
+                    .find_by_id(link.user_id)
+                    .await?
+                    .ok_or_else(|| Error::Authentication("Account no longer exists".to_string()))?;
+                (user, Some(link.id))
+            }
+            None => (self.link_or_provision(&identity).await?, None),
+        };
+
+        if !user.is_active {
+            return Err(Error::Authentication("Account is deactivated".to_string()));
+        }
+
+        if let Some(role) = self.role_mapping.role_for(&identity.groups) {
+            if role != user.role {
+                info!(user_id = %user.id, from = user.role.as_str(), to = role.as_str(), "Role changed by identity provider groups");
+                user.role = role;
+            }
+        }
+        if identity.email_verified && !user.is_verified && emails_match(&user, &identity) {
+            user.verify();
+        }
+        user.update_last_login();
+        let user = self.user_repository.update(user).await?;
+
+        if let Some(identity_id) = identity_id {
+            self.identity_repository
+                .record_identity_login(identity_id, identity.email.clone())
+                .await?;
+        }
+
+        info!(user_id = %user.id, issuer = %identity.issuer, "Signed in through identity provider");
+        Ok(user.into())
+    }
+
+    /// Link a first-time IdP account to the user with its email, or create one.
+    /// Only a verified email is trusted to prove ownership of an existing account.
+    async fn link_or_provision(&self, identity: &OidcIdentity) -> Result<User> {
+        let email = identity
+            .email
+            .as_deref()
+            .ok_or_else(|| Error::Authentication("Identity provider did not share an email address".to_string()))
+            .and_then(|email| {
+                Email::new(email.to_string()).map_err(|e| Error::Authentication(e.to_string()))
+            })?;
+
+        let user = match self.user_repository.find_by_email(&email).await? {
+            Some(user) => {
+                if !identity.email_verified {
+                    warn!(user_id = %user.id, "Refused to link identity with an unverified email");
+                    return Err(Error::Conflict(
+                        "An account with this email already exists, but the identity provider has not verified the address"
+                            .to_string(),
+                    ));
+                }
+                user
+            }
+            None => {
+                if !self.allow_provisioning {
+                    return Err(Error::Authorization(
+                        "No account exists for this identity; ask an administrator for access".to_string(),
+                    ));
+                }
+                self.provision_user(identity, email).await?
+            }
+        };
+
+        self.identity_repository
+            .create_identity(UserIdentity::new(
+                user.id,
+                identity.issuer.clone(),
+                identity.subject.clone(),
+                identity.email.clone(),
+            ))
+            .await?;
+
+        info!(user_id = %user.id, issuer = %identity.issuer, "Linked identity provider account");
+        Ok(user)
+    }
+
+    async fn provision_user(&self, identity: &OidcIdentity, email: Email) -> Result<User> {
+        let username = self.available_username(identity, &email).await?;
+        let (first_name, last_name) = display_names(identity, &username);
+
+        // Nobody knows this password; the account signs in through the IdP
+        // until a password is set with a reset link
+        let unusable_password = format!("{}{}", uuid::Uuid::new_v4(), uuid::Uuid::new_v4());
+        let password_hash = self.auth_service.hash_password(&unusable_password).await?;
+
+        let mut user = User::new(email, username, first_name, last_name, password_hash);
+        if identity.email_verified {
+            user.verify();
+        }
+        let user = self.user_repository.create(user).await?;
+
+        info!(user_id = %user.id, "Provisioned account from identity provider");
+        Ok(user)
+    }
+
+    async fn available_username(&self, identity: &OidcIdentity, email: &Email) -> Result<String> {
+        let local_part = email.as_str().split('@').next().unwrap_or_default();
+        let base = identity
+            .preferred_username
+            .as_deref()
+            .map(sanitize_username)
+            .filter(|name| name.len() >= MIN_USERNAME_LENGTH)
+            .unwrap_or_else(|| sanitize_username(local_part));
+        let base = format!("{:_<width$}", base, width = MIN_USERNAME_LENGTH);
+
+        for attempt in 0..USERNAME_ATTEMPTS {
+            let candidate = if attempt == 0 {
+                base.clone()
+            } else {
+                let suffix = attempt + 1;
+                let keep = MAX_USERNAME_LENGTH - suffix.to_string().len();
+                format!("{}{}", &base[..base.len().min(keep)], suffix)
+            };
+
+            if !self.user_repository.exists_by_username(&candidate).await? {
+                return Ok(candidate);
+            }
+        }
+
+        Err(Error::Conflict(format!("No username based on {} is available", base)))
+    }
+}
+
+fn emails_match(user: &User, identity: &OidcIdentity) -> bool {
+    identity
+        .email
+        .as_deref()
+        .is_some_and(|email| email.eq_ignore_ascii_case(user.email.as_str()))
+}
+
+/// Usernames keep to ASCII letters, digits, `.`, `-` and `_`
+fn sanitize_username(name: &str) -> String {
+    name.chars()
+        .filter_map(|c| match c {
+            c if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') => Some(c.to_ascii_lowercase()),
+            ' ' => Some('_'),
+            _ => None,
+        })
+        .take(MAX_USERNAME_LENGTH)
+        .collect()
+}
+
+/// Given and family name, falling back to splitting `name`, then to the username
+fn display_names(identity: &OidcIdentity, username: &str) -> (String, String) {
+    let full_name = identity.name.as_deref().unwrap_or_default().trim();
+    let (split_first, split_last) = full_name.split_once(' ').unwrap_or((full_name, ""));
+
+    let first_name = non_empty(identity.given_name.as_deref())
+        .or_else(|| non_empty(Some(split_first)))
+        .unwrap_or(username);
+    let last_name = non_empty(identity.family_name.as_deref())
+        .or_else(|| non_empty(Some(split_last)))
+        .unwrap_or_default();
+
+    (first_name.to_string(), last_name.to_string())
+}
+
+fn non_empty(value: Option<&str>) -> Option<&str> {
+    value.map(str::trim).filter(|value| !value.is_empty())
+}
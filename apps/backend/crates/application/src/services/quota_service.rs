@@ -0,0 +1,146 @@
+use kingshare_core::{Error, Id, Result};
+use kingshare_domain::{
+    entities::{NotificationLevel, QuotaScope, QuotaUsage, WebSocketMessage},
+    repositories::QuotaRepository,
+    services::WebSocketService,
+};
+use std::sync::Arc;
+use tracing::{info, instrument, warn};
+
+#[derive(Clone)]
+pub struct QuotaService {
+    quota_repository: Arc<dyn QuotaRepository>,
+    websocket_service: Option<Arc<dyn WebSocketService>>,
+    soft_limit_percent: u8,
+}
+
+impl QuotaService {
+    pub fn new(
+        quota_repository: Arc<dyn QuotaRepository>,
+        websocket_service: Option<Arc<dyn WebSocketService>>,
+        soft_limit_percent: u8,
+    ) -> Self {
+        Self {
+            quota_repository,
+            websocket_service,
+            soft_limit_percent,
+        }
+    }
+
+    /// Charge `bytes` to the user and, for drive content, to the drive. Either both
+    /// reservations succeed or neither is kept. Owners are warned when their usage
+    /// crosses the soft limit.
+    #[instrument(skip(self))]
+    pub async fn reserve(&self, owner_id: Id, drive_id: Option<Id>, bytes: i64) -> Result<()> {
+        let user_usage = self
+            .quota_repository
+            .reserve(QuotaScope::User(owner_id), bytes)
+            .await?;
+
+        let drive_usage = match drive_id {
+            Some(drive_id) => {
+                match self
+                    .quota_repository
+                    .reserve(QuotaScope::Drive(drive_id), bytes)
+                    .await
+                {
+                    Ok(usage) => Some(usage),
+                    Err(e) => {
+                        self.release_scope(QuotaScope::User(owner_id), bytes).await;
+                        return Err(e);
+                    }
+                }
+            }
+            None => None,
+        };
+
+        for usage in std::iter::once(user_usage).chain(drive_usage) {
+            if usage.crossed_soft_limit(bytes, self.soft_limit_percent) {
+                self.notify_soft_limit(&usage).await;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Return storage charged by `reserve`. Failures are logged, not returned, so
+    /// callers cleaning up after a delete don't fail on quota bookkeeping.
+    #[instrument(skip(self))]
+    pub async fn release(&self, owner_id: Id, drive_id: Option<Id>, bytes: i64) {
+        self.release_scope(QuotaScope::User(owner_id), bytes).await;
+        if let Some(drive_id) = drive_id {
+            self.release_scope(QuotaScope::Drive(drive_id), bytes).await;
+        }
+    }
+
+    /// Fail early when `bytes` can't fit, without reserving anything. Used before
+    /// accepting content whose size is known up front, like resumable uploads.
+    #[instrument(skip(self))]
+    pub async fn ensure_available(&self, owner_id: Id, bytes: i64) -> Result<()> {
+        let usage = self.get_usage(QuotaScope::User(owner_id)).await?;
+
+        match usage.available() {
+            Some(available) if available < bytes => Err(Error::QuotaExceeded(format!(
+                "{} bytes requested but only {} of the user's {} byte quota are available",
+                bytes,
+                available,
+                usage.quota.unwrap_or(0)
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    #[instrument(skip(self))]
+    pub async fn get_usage(&self, scope: QuotaScope) -> Result<QuotaUsage> {
+        self.quota_repository.get_usage(scope).await
+    }
+
+    #[instrument(skip(self))]
+    pub async fn set_quota(&self, scope: QuotaScope, quota: Option<i64>) -> Result<QuotaUsage> {
+        let usage = self.quota_repository.set_quota(scope, quota).await?;
+
+        info!(
+            scope = scope.describe(),
+            id = %scope.id(),
+            quota = ?usage.quota,
+            used = usage.used,
+            "Storage quota updated"
+        );
+
+        Ok(usage)
+    }
+
+    async fn release_scope(&self, scope: QuotaScope, bytes: i64) {
+        if let Err(e) = self.quota_repository.release(scope, bytes).await {
+            warn!(
+                scope = scope.describe(),
+                id = %scope.id(),
+                bytes = bytes,
+                error = %e,
+                "Failed to release storage quota"
+            );
+        }
+    }
+
+    async fn notify_soft_limit(&self, usage: &QuotaUsage) {
+        let Some(ws_service) = &self.websocket_service else {
+            return;
+        };
+
+        let subject = match usage.scope {
+            QuotaScope::User(_) => "Your storage",
+            QuotaScope::Drive(_) => "Drive storage",
+        };
+        let message = WebSocketMessage::SystemNotification {
+            message: format!(
+                "{} is {:.0}% full ({} of {} bytes used)",
+                subject,
+                usage.usage_percentage().unwrap_or_default(),
+                usage.used,
+                usage.quota.unwrap_or_default()
+            ),
+            level: NotificationLevel::Warning,
+        };
+        let _ = ws_service.send_to_user(usage.owner_id, message).await;
+    }
+}
@@ -0,0 +1,114 @@
+use kingshare_core::Result;
+use kingshare_domain::{
+    entities::{File, ScanStatus, WebSocketMessage},
+    repositories::FileRepository,
+    services::{MalwareScanner, ScanVerdict, StorageService, WebSocketService},
+};
+use std::sync::Arc;
+use tracing::{info, instrument, warn};
+
+const RESCAN_BATCH_SIZE: i64 = 500;
+
+#[derive(Debug, Default, serde::Serialize)]
+pub struct ScanSummary {
+    pub clean: u64,
+    pub infected: u64,
+    pub failed: u64,
+}
+
+#[derive(Clone)]
+pub struct ScanService {
+    file_repository: Arc<dyn FileRepository>,
+    storage_service: Arc<dyn StorageService>,
+    scanner: Arc<dyn MalwareScanner>,
+    websocket_service: Option<Arc<dyn WebSocketService>>,
+}
+
+impl ScanService {
+    pub fn new(
+        file_repository: Arc<dyn FileRepository>,
+        storage_service: Arc<dyn StorageService>,
+        scanner: Arc<dyn MalwareScanner>,
+        websocket_service: Option<Arc<dyn WebSocketService>>,
+    ) -> Self {
+        Self {
+            file_repository,
+            storage_service,
+            scanner,
+            websocket_service,
+        }
+    }
+
+    /// Scan a file's content and record the verdict on every file sharing it.
+    /// Infected content is moved to quarantine and its owners are notified.
+    #[instrument(skip(self, file), fields(file_id = %file.id, checksum = %file.checksum))]
+    pub async fn scan_file(&self, file: &File) -> Result<ScanStatus> {
+        let stream = self.storage_service.get_file_stream(&file.storage_path).await?;
+
+        match self.scanner.scan_stream(stream).await? {
+            ScanVerdict::Clean => {
+                self.file_repository
+                    .record_scan_result(&file.checksum, ScanStatus::Clean, None, None)
+                    .await?;
+                Ok(ScanStatus::Clean)
+            }
+            ScanVerdict::Infected { signature } => {
+                let quarantine_path = self.storage_service.quarantine_file(&file.storage_path).await?;
+                let files = self
+                    .file_repository
+                    .record_scan_result(
+                        &file.checksum,
+                        ScanStatus::Infected,
+                        Some(signature.clone()),
+                        Some(quarantine_path),
+                    )
+                    .await?;
+
+                if let Some(ws_service) = &self.websocket_service {
+                    for file in &files {
+                        let message = WebSocketMessage::FileQuarantined {
+                            file_id: file.id,
+                            filename: file.filename.clone(),
+                            signature: signature.clone(),
+                        };
+                        let _ = ws_service.send_to_user(file.owner_id, message).await;
+                    }
+                }
+
+                warn!(
+                    signature = %signature,
+                    file_count = files.len(),
+                    "Infected content quarantined"
+                );
+                Ok(ScanStatus::Infected)
+            }
+        }
+    }
+
+    /// Scan files still waiting for a verdict, e.g. because the scanner was
+    /// unreachable when they were uploaded
+    #[instrument(skip(self))]
+    pub async fn scan_pending_files(&self) -> Result<ScanSummary> {
+        let mut summary = ScanSummary::default();
+
+        for file in self.file_repository.find_pending_scan(RESCAN_BATCH_SIZE).await? {
+            match self.scan_file(&file).await {
+                Ok(ScanStatus::Clean) => summary.clean += 1,
+                Ok(ScanStatus::Infected) => summary.infected += 1,
+                Ok(ScanStatus::Pending) => {}
+                Err(e) => {
+                    warn!(file_id = %file.id, error = %e, "Malware scan failed");
+                    summary.failed += 1;
+                }
+            }
+        }
+
+        info!(
+            clean = summary.clean,
+            infected = summary.infected,
+            failed = summary.failed,
+            "Pending file scan completed"
+        );
+        Ok(summary)
+    }
+}
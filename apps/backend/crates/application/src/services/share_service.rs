@@ -1,51 +1,87 @@
+use crate::services::LockoutService;
 use kingshare_core::{Error, Id, PaginatedResponse, PaginationParams, Result};
 use kingshare_domain::{
     entities::{
-        AccessShareRequest, CreateShareRequest, Share, ShareInfo, UpdateShareRequest, WebSocketMessage,
+        AccessShareRequest, AttemptKey, ClientInfo, CreateShareRequest, File, Share, ShareInfo,
+        UpdateShareRequest, WebSocketMessage,
     },
-    repositories::{FileRepository, ShareRepository},
-    services::{StorageService, WebSocketService},
+    repositories::{FileRepository, ShareRepository, UserRepository},
+    services::{AuthService as DomainAuthService, FileStream, StorageService, WebSocketService},
+    value_objects::ByteRange,
 };
 use std::sync::Arc;
 use tracing::{info, instrument, warn};
 use validator::Validate;
 
+/// A share that passed token, password and limit checks
+#[derive(Debug, Clone)]
+pub struct SharedFileAccess {
+    pub share_info: ShareInfo,
+    pub file: File,
+    share: Share,
+}
+
 #[derive(Clone)]
 pub struct ShareService {
     share_repository: Arc<dyn ShareRepository>,
     file_repository: Arc<dyn FileRepository>,
+    // Hashes and checks share passwords
+    auth_service: Arc<dyn DomainAuthService>,
     storage_service: Option<Arc<dyn StorageService>>,
     websocket_service: Option<Arc<dyn WebSocketService>>,
+    // Set when unverified users may not publish share links
+    verified_owners: Option<Arc<dyn UserRepository>>,
+    lockout_service: Option<LockoutService>,
 }
 
 impl ShareService {
     pub fn new(
         share_repository: Arc<dyn ShareRepository>,
         file_repository: Arc<dyn FileRepository>,
+        auth_service: Arc<dyn DomainAuthService>,
         websocket_service: Option<Arc<dyn WebSocketService>>,
     ) -> Self {
         Self {
             share_repository,
             file_repository,
+            auth_service,
             storage_service: None,
             websocket_service,
+            verified_owners: None,
+            lockout_service: None,
         }
     }
 
     pub fn with_storage(
         share_repository: Arc<dyn ShareRepository>,
         file_repository: Arc<dyn FileRepository>,
+        auth_service: Arc<dyn DomainAuthService>,
         storage_service: Arc<dyn StorageService>,
         websocket_service: Option<Arc<dyn WebSocketService>>,
     ) -> Self {
         Self {
             share_repository,
             file_repository,
+            auth_service,
             storage_service: Some(storage_service),
             websocket_service,
+            verified_owners: None,
+            lockout_service: None,
         }
     }
 
+    /// Only let users with a verified email address create share links
+    pub fn with_verified_owners_required(mut self, user_repository: Arc<dyn UserRepository>) -> Self {
+        self.verified_owners = Some(user_repository);
+        self
+    }
+
+    /// Back off and lock out repeated wrong share passwords, per share and per address
+    pub fn with_lockout(mut self, lockout_service: LockoutService) -> Self {
+        self.lockout_service = Some(lockout_service);
+        self
+    }
+
     #[instrument(skip(self, request))]
     pub async fn create_share(
         &self,
@@ -67,13 +103,15 @@ impl ShareService {
         if file.owner_id != owner_id {
             return Err(Error::Authorization("Not authorized to share this file".to_string()));
         }
+        self.require_verified_owner(owner_id).await?;
 
         // Create share
         let mut share = Share::new(request.file_id, owner_id);
 
         // Set optional fields
         if let Some(password) = request.password {
-            share.set_password(Some(password));
+            let password_hash = self.auth_service.hash_password(&password).await?;
+            share.set_password_hash(Some(password_hash));
         }
 
         if let Some(max_downloads) = request.max_downloads {
@@ -184,7 +222,8 @@ impl ShareService {
 
         // Update fields
         if let Some(password) = request.password {
-            share.set_password(Some(password));
+            let password_hash = self.auth_service.hash_password(&password).await?;
+            share.set_password_hash(Some(password_hash));
         }
 
         if let Some(max_downloads) = request.max_downloads {
@@ -249,6 +288,48 @@ impl ShareService {
         token: &str,
         request: AccessShareRequest,
     ) -> Result<(ShareInfo, Vec<u8>)> {
+        let access = self
+            .authorize_shared_download(token, request, &ClientInfo::default())
+            .await?;
+
+        // Get file data from storage
+        let file_data = if let Some(storage_service) = &self.storage_service {
+            storage_service.get_file(&access.file.storage_path).await?
+        } else {
+            // Fallback to empty data if no storage service is configured
+            vec![]
+        };
+
+        self.record_share_access(access.share, &access.file, token).await;
+        Ok((access.share_info, file_data))
+    }
+
+    #[instrument(skip(self, request))]
+    pub async fn access_shared_file_stream(
+        &self,
+        token: &str,
+        request: AccessShareRequest,
+    ) -> Result<(ShareInfo, FileStream)> {
+        let access = self
+            .authorize_shared_download(token, request, &ClientInfo::default())
+            .await?;
+        let (share_info, mut streams) = self.open_shared_download(access, token, &[]).await?;
+
+        let stream = streams
+            .pop()
+            .ok_or_else(|| Error::Internal("Failed to open file stream".to_string()))?;
+        Ok((share_info, stream))
+    }
+
+    /// Validate the share token and password without touching storage or
+    /// counters. Wrong passwords count against the share and `client`'s address.
+    #[instrument(skip(self, request, client))]
+    pub async fn authorize_shared_download(
+        &self,
+        token: &str,
+        request: AccessShareRequest,
+        client: &ClientInfo,
+    ) -> Result<SharedFileAccess> {
         // Validate request
         request
             .validate()
@@ -258,7 +339,7 @@ impl ShareService {
         let share_info = self.get_share_by_token(token).await?;
 
         // Get full share for validation
-        let mut share = self
+        let share = self
             .share_repository
             .find_by_id(share_info.id)
             .await?
@@ -266,10 +347,8 @@ impl ShareService {
 
         // Verify password if required
         if let Some(password) = &request.password {
-            if !share.verify_password(password) {
-                return Err(Error::Authentication("Invalid password".to_string()));
-            }
-        } else if share.password.is_some() {
+            self.verify_share_password(&share, password, client).await?;
+        } else if share.has_password() {
             return Err(Error::Authentication("Password required".to_string()));
         }
 
@@ -285,14 +364,88 @@ impl ShareService {
             .await?
             .ok_or_else(|| Error::NotFound("File not found".to_string()))?;
 
-        // Get file data from storage
-        let file_data = if let Some(storage_service) = &self.storage_service {
-            storage_service.get_file(&file.storage_path).await?
+        file.ensure_scan_passed()?;
+
+        Ok(SharedFileAccess {
+            share_info,
+            file,
+            share,
+        })
+    }
+
+    async fn verify_share_password(&self, share: &Share, password: &str, client: &ClientInfo) -> Result<()> {
+        let Some(lockout_service) = &self.lockout_service else {
+            if !self.password_matches(share, password).await? {
+                return Err(Error::Authentication("Invalid password".to_string()));
+            }
+            return Ok(());
+        };
+
+        let mut keys = vec![AttemptKey::share(share.id)];
+        keys.extend(client.ip_address.as_deref().map(AttemptKey::ip));
+        let attempt = lockout_service.begin_attempt(&keys).await?;
+
+        let matches = match self.password_matches(share, password).await {
+            Ok(matches) => matches,
+            Err(e) => {
+                lockout_service.cancel_attempt(&attempt).await?;
+                return Err(e);
+            }
+        };
+        if !matches {
+            lockout_service
+                .attempt_failed(&attempt, client.ip_address.as_deref())
+                .await?;
+            return Err(Error::Authentication("Invalid password".to_string()));
+        }
+        lockout_service.attempt_succeeded(&attempt).await
+    }
+
+    async fn password_matches(&self, share: &Share, password: &str) -> Result<bool> {
+        match &share.password_hash {
+            None => Ok(true), // No password required
+            Some(_) if share.has_legacy_password() => Ok(share.matches_legacy_password(password)),
+            Some(password_hash) => self.auth_service.verify_password(password, password_hash).await,
+        }
+    }
+
+    /// Open streams for an authorized share; an empty `ranges` slice opens the whole file
+    #[instrument(skip(self, access), fields(share_id = %access.share_info.id))]
+    pub async fn open_shared_download(
+        &self,
+        access: SharedFileAccess,
+        token: &str,
+        ranges: &[ByteRange],
+    ) -> Result<(ShareInfo, Vec<FileStream>)> {
+        let storage_service = self
+            .storage_service
+            .as_ref()
+            .ok_or_else(|| Error::Internal("Storage service not configured".to_string()))?;
+
+        let streams = if ranges.is_empty() {
+            vec![storage_service.get_file_stream(&access.file.storage_path).await?]
         } else {
-            // Fallback to empty data if no storage service is configured
-            vec![]
+            let mut streams = Vec::with_capacity(ranges.len());
+            for range in ranges {
+                streams.push(
+                    storage_service
+                        .get_file_range_stream(&access.file.storage_path, *range)
+                        .await?,
+                );
+            }
+            streams
         };
 
+        // Seeking issues many range requests; only the one from byte 0 counts
+        // against the share's download limit.
+        if ranges.is_empty() || ranges[0].start == 0 {
+            self.record_share_access(access.share, &access.file, token).await;
+        }
+
+        Ok((access.share_info, streams))
+    }
+
+    async fn record_share_access(&self, mut share: Share, file: &File, token: &str) {
         // Increment download count
         share.increment_download_count();
         let _ = self.share_repository.update(share.clone()).await;
@@ -313,8 +466,6 @@ impl ShareService {
             token = %token,
             "Shared file accessed"
         );
-
-        Ok((share_info, file_data))
     }
 
     #[instrument(skip(self))]
@@ -330,6 +481,7 @@ impl ShareService {
         if share.owner_id != owner_id {
             return Err(Error::Authorization("Not authorized to regenerate token for this share".to_string()));
         }
+        self.require_verified_owner(owner_id).await?;
 
         // Regenerate token
         share.regenerate_token();
@@ -373,7 +525,50 @@ impl ShareService {
         Ok(deleted_count)
     }
 
+    /// Hash share passwords still stored as plaintext from before they were
+    /// hashed. Returns how many were upgraded.
+    #[instrument(skip(self))]
+    pub async fn upgrade_legacy_passwords(&self) -> Result<u64> {
+        let mut upgraded = 0;
+        for share in self.share_repository.find_with_legacy_passwords().await? {
+            let Some(password) = share.password_hash.as_deref() else {
+                continue;
+            };
+            let password_hash = self.auth_service.hash_password(password).await?;
+            if self
+                .share_repository
+                .replace_password_hash(share.id, password, &password_hash)
+                .await?
+            {
+                upgraded += 1;
+            }
+        }
+
+        if upgraded > 0 {
+            info!(upgraded = upgraded, "Legacy share passwords hashed");
+        }
+        Ok(upgraded)
+    }
+
     #[instrument(skip(self))]
+    async fn require_verified_owner(&self, owner_id: Id) -> Result<()> {
+        let Some(user_repository) = &self.verified_owners else {
+            return Ok(());
+        };
+
+        let owner = user_repository
+            .find_by_id(owner_id)
+            .await?
+            .ok_or_else(|| Error::NotFound("User not found".to_string()))?;
+        if !owner.is_verified {
+            return Err(Error::Authorization(
+                "Verify your email address before sharing files publicly".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
     async fn get_share_info(&self, share_id: Id) -> Result<ShareInfo> {
         // This is a simplified implementation
         // In a real implementation, you'd join with file and user tables
@@ -389,10 +584,12 @@ impl ShareService {
             .await?
             .ok_or_else(|| Error::NotFound("File not found".to_string()))?;
 
+        let human_readable_size = file.human_readable_size();
+
         Ok(ShareInfo {
             id: share.id,
             share_token: share.share_token,
-            has_password: share.password.is_some(),
+            has_password: share.password_hash.is_some(),
             max_downloads: share.max_downloads,
             download_count: share.download_count,
             is_active: share.is_active,
@@ -403,7 +600,7 @@ impl ShareService {
                 filename: file.filename,
                 content_type: file.content_type,
                 size: file.size,
-                human_readable_size: file.human_readable_size(),
+                human_readable_size,
             },
         })
     }
@@ -0,0 +1,158 @@
+use kingshare_core::{Error, Result};
+use kingshare_domain::{
+    entities::{supports_thumbnail, File, Thumbnail, ThumbnailSize},
+    repositories::ThumbnailRepository,
+    services::{FileService as DomainFileService, FileStream, FileUpload, StorageService},
+};
+use crate::services::BlobService;
+use std::sync::Arc;
+use tracing::{info, instrument, warn};
+
+#[derive(Clone)]
+pub struct ThumbnailService {
+    thumbnail_repository: Arc<dyn ThumbnailRepository>,
+    storage_service: Arc<dyn StorageService>,
+    blob_service: BlobService,
+    file_service: Arc<dyn DomainFileService>,
+}
+
+impl ThumbnailService {
+    pub fn new(
+        thumbnail_repository: Arc<dyn ThumbnailRepository>,
+        storage_service: Arc<dyn StorageService>,
+        blob_service: BlobService,
+        file_service: Arc<dyn DomainFileService>,
+    ) -> Self {
+        Self {
+            thumbnail_repository,
+            storage_service,
+            blob_service,
+            file_service,
+        }
+    }
+
+    /// Render every thumbnail size for a freshly uploaded file in the background
+    pub fn spawn_generation(&self, file: &File) {
+        if !supports_thumbnail(&file.content_type) {
+            return;
+        }
+
+        let service = self.clone();
+        let file_id = file.id;
+        let checksum = file.checksum.clone();
+        let storage_path = file.storage_path.clone();
+        let content_type = file.content_type.clone();
+
+        tokio::spawn(async move {
+            if let Err(e) = service
+                .generate_all(&checksum, &storage_path, &content_type)
+                .await
+            {
+                warn!(file_id = %file_id, error = %e, "Thumbnail generation failed");
+            }
+        });
+    }
+
+    /// Open a file's thumbnail, rendering it on the spot if the background job
+    /// hasn't run yet (e.g. files uploaded before thumbnails existed).
+    #[instrument(skip(self, file), fields(file_id = %file.id))]
+    pub async fn open_thumbnail(&self, file: &File, size: ThumbnailSize) -> Result<(Thumbnail, FileStream)> {
+        if !supports_thumbnail(&file.content_type) {
+            return Err(Error::NotFound("No thumbnail for this file type".to_string()));
+        }
+
+        let thumbnail = match self.thumbnail_repository.find(&file.checksum, size).await? {
+            Some(thumbnail) => thumbnail,
+            None => {
+                let data = self.storage_service.get_file(&file.storage_path).await?;
+                self.generate(&file.checksum, &data, &file.content_type, size)
+                    .await?
+                    .ok_or_else(|| Error::NotFound("No thumbnail for this file type".to_string()))?
+            }
+        };
+
+        let stream = self.storage_service.get_file_stream(&thumbnail.storage_path).await?;
+        Ok((thumbnail, stream))
+    }
+
+    #[instrument(skip(self))]
+    async fn generate_all(&self, checksum: &str, storage_path: &str, content_type: &str) -> Result<()> {
+        let existing: Vec<ThumbnailSize> = self
+            .thumbnail_repository
+            .find_by_checksum(checksum)
+            .await?
+            .into_iter()
+            .map(|thumbnail| thumbnail.size)
+            .collect();
+
+        // Deduplicated uploads already have their thumbnails
+        let missing: Vec<ThumbnailSize> = ThumbnailSize::ALL
+            .into_iter()
+            .filter(|size| !existing.contains(size))
+            .collect();
+        if missing.is_empty() {
+            return Ok(());
+        }
+
+        let data = self.storage_service.get_file(storage_path).await?;
+        for size in missing {
+            self.generate(checksum, &data, content_type, size).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn generate(
+        &self,
+        checksum: &str,
+        data: &[u8],
+        content_type: &str,
+        size: ThumbnailSize,
+    ) -> Result<Option<Thumbnail>> {
+        let Some(generated) = self
+            .file_service
+            .generate_thumbnail(data, content_type, size.max_dimension())
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        let stored_file = self
+            .storage_service
+            .store_file(FileUpload {
+                filename: format!("thumbnail-{}", size.as_str()),
+                content_type: generated.content_type.clone(),
+                data: generated.data,
+            })
+            .await?;
+        let stored_file = self.blob_service.acquire(stored_file).await?;
+
+        let thumbnail = Thumbnail {
+            checksum: checksum.to_string(),
+            size,
+            storage_path: stored_file.path,
+            thumbnail_checksum: stored_file.checksum.clone(),
+            content_type: generated.content_type,
+            byte_size: stored_file.size as i64,
+            width: generated.width as i32,
+            height: generated.height as i32,
+            created_at: chrono::Utc::now(),
+        };
+
+        match self.thumbnail_repository.create(thumbnail).await {
+            Ok(thumbnail) => {
+                info!(checksum = %checksum, size = size.as_str(), "Thumbnail stored");
+                Ok(Some(thumbnail))
+            }
+            Err(Error::Conflict(_)) => {
+                // Rendered concurrently by another request; keep theirs
+                let _ = self.blob_service.release(&stored_file.checksum).await;
+                self.thumbnail_repository.find(checksum, size).await
+            }
+            Err(e) => {
+                let _ = self.blob_service.release(&stored_file.checksum).await;
+                Err(e)
+            }
+        }
+    }
+}
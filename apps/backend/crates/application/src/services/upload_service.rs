@@ -0,0 +1,373 @@
+use crate::services::FileService;
+use kingshare_core::{Error, Id, Result};
+use kingshare_domain::{
+    entities::{
+        CreateUploadSessionRequest, FileMetadata, UploadSession, UploadSessionInfo,
+        WebSocketMessage,
+    },
+    repositories::UploadSessionRepository,
+    services::{
+        FileReader, FileService as DomainFileService, StorageService, StoredFile,
+        WebSocketService, CONTENT_SNIFF_WINDOW,
+    },
+    value_objects::ByteRange,
+};
+use futures_util::TryStreamExt;
+use std::sync::Arc;
+use tracing::{info, instrument, warn};
+use validator::Validate;
+
+#[derive(Clone)]
+pub struct UploadService {
+    upload_session_repository: Arc<dyn UploadSessionRepository>,
+    storage_service: Arc<dyn StorageService>,
+    file_domain_service: Arc<dyn DomainFileService>,
+    file_service: FileService,
+    websocket_service: Option<Arc<dyn WebSocketService>>,
+}
+
+impl UploadService {
+    pub fn new(
+        upload_session_repository: Arc<dyn UploadSessionRepository>,
+        storage_service: Arc<dyn StorageService>,
+        file_domain_service: Arc<dyn DomainFileService>,
+        file_service: FileService,
+        websocket_service: Option<Arc<dyn WebSocketService>>,
+    ) -> Self {
+        Self {
+            upload_session_repository,
+            storage_service,
+            file_domain_service,
+            file_service,
+            websocket_service,
+        }
+    }
+
+    #[instrument(skip(self, request))]
+    pub async fn create_session(
+        &self,
+        owner_id: Id,
+        request: CreateUploadSessionRequest,
+    ) -> Result<UploadSessionInfo> {
+        // Validate request
+        request
+            .validate()
+            .map_err(|e| Error::Validation(e.to_string()))?;
+
+        // Validate what we can before any bytes arrive
+        let validation_result = self
+            .file_domain_service
+            .validate_file(&request.filename, &request.content_type, request.size as u64, &[])
+            .await?;
+
+        if !validation_result.is_valid {
+            return Err(Error::Validation(format!(
+                "File validation failed: {}",
+                validation_result.errors.join(", ")
+            )));
+        }
+
+        // The quota is charged when the upload is finalized; this only avoids
+        // accepting bytes that can never be stored
+        self.file_service
+            .ensure_quota_available(owner_id, request.size)
+            .await?;
+
+        let handle = self
+            .storage_service
+            .begin_upload(&request.filename, &request.content_type)
+            .await?;
+
+        let session = UploadSession::new(
+            owner_id,
+            request.filename,
+            request.content_type,
+            request.size,
+            handle,
+        );
+
+        let created_session = self.upload_session_repository.create(session).await?;
+
+        info!(
+            upload_id = %created_session.id,
+            owner_id = %owner_id,
+            total_size = created_session.total_size,
+            "Upload session created"
+        );
+
+        Ok(created_session.into())
+    }
+
+    #[instrument(skip(self))]
+    pub async fn get_session(&self, upload_id: Id, owner_id: Id) -> Result<UploadSessionInfo> {
+        let session = self.get_active_session(upload_id, owner_id).await?;
+        Ok(session.into())
+    }
+
+    /// Stream one chunk to storage. It may not run past the declared upload length.
+    #[instrument(skip(self, reader))]
+    pub async fn append_chunk(
+        &self,
+        upload_id: Id,
+        owner_id: Id,
+        offset: i64,
+        reader: FileReader,
+    ) -> Result<UploadSessionInfo> {
+        let mut session = self.get_active_session(upload_id, owner_id).await?;
+
+        if session.finalizing || session.is_finalized() {
+            return Err(Error::Conflict("Upload is already finalized".to_string()));
+        }
+
+        if offset != session.offset {
+            return Err(Error::Conflict(format!(
+                "Upload offset mismatch: expected {}, got {}",
+                session.offset, offset
+            )));
+        }
+
+        // Claimed before anything is written, so a chunk sent twice for the
+        // same offset reaches storage once
+        let Some(claimed_at) = self
+            .upload_session_repository
+            .claim_append(upload_id, offset)
+            .await?
+        else {
+            return Err(Error::Conflict("Upload was modified concurrently".to_string()));
+        };
+
+        // Storage rolls a failed chunk back to the claimed offset
+        let new_offset = match self
+            .storage_service
+            .append_upload_chunk(
+                &session.storage_handle,
+                offset as u64,
+                reader,
+                session.remaining() as u64,
+            )
+            .await
+        {
+            Ok(new_offset) => new_offset as i64,
+            Err(e) => {
+                self.upload_session_repository.release_append(upload_id, claimed_at).await?;
+                return Err(e);
+            }
+        };
+
+        if !self
+            .upload_session_repository
+            .finish_append(upload_id, claimed_at, new_offset)
+            .await?
+        {
+            return Err(Error::Conflict("Upload was modified concurrently".to_string()));
+        }
+
+        session.advance(new_offset);
+
+        // Send WebSocket notification
+        if let Some(ws_service) = &self.websocket_service {
+            let message = WebSocketMessage::UploadProgress {
+                file_id: session.id,
+                progress: (session.offset as f64 / session.total_size as f64 * 100.0) as f32,
+                bytes_uploaded: session.offset as u64,
+                total_bytes: session.total_size as u64,
+            };
+            let _ = ws_service.send_to_user(owner_id, message).await;
+        }
+
+        Ok(session.into())
+    }
+
+    /// Turn a complete upload into a file. Finalizing again returns the same file.
+    #[instrument(skip(self))]
+    pub async fn finalize(&self, upload_id: Id, owner_id: Id) -> Result<FileMetadata> {
+        let session = self.get_active_session(upload_id, owner_id).await?;
+
+        if let Some(file_id) = session.file_id {
+            return self.file_service.get_file_metadata(file_id).await;
+        }
+
+        if !session.is_complete() {
+            return Err(Error::BadRequest(format!(
+                "Upload is incomplete: {} of {} bytes received",
+                session.offset, session.total_size
+            )));
+        }
+
+        if !self.upload_session_repository.claim_finalize(upload_id).await? {
+            return Err(Error::Conflict("Upload is already being finalized".to_string()));
+        }
+
+        let metadata = match self.store_upload(&session).await {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                self.upload_session_repository.release_finalize(upload_id).await?;
+                return Err(e);
+            }
+        };
+
+        self.upload_session_repository
+            .mark_finalized(upload_id, metadata.id)
+            .await?;
+
+        info!(
+            upload_id = %upload_id,
+            file_id = %metadata.id,
+            owner_id = %owner_id,
+            "Upload session finalized"
+        );
+
+        Ok(metadata)
+    }
+
+    #[instrument(skip(self))]
+    pub async fn abort(&self, upload_id: Id, owner_id: Id) -> Result<()> {
+        let session = self.get_owned_session(upload_id, owner_id).await?;
+
+        if session.finalizing {
+            return Err(Error::Conflict("Upload is being finalized".to_string()));
+        }
+
+        // A finalized upload's content now belongs to its file
+        if !session.is_finalized() {
+            self.storage_service.abort_upload(&session.storage_handle).await?;
+        }
+        self.upload_session_repository.delete(upload_id).await?;
+
+        info!(upload_id = %upload_id, owner_id = %owner_id, "Upload session aborted");
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    pub async fn cleanup_expired_sessions(&self) -> Result<u64> {
+        let expired_sessions = self.upload_session_repository.find_expired().await?;
+        let mut deleted_count = 0;
+
+        for session in expired_sessions {
+            if !session.is_finalized() {
+                if let Err(e) = self.storage_service.abort_upload(&session.storage_handle).await {
+                    warn!(
+                        upload_id = %session.id,
+                        error = %e,
+                        "Failed to remove expired upload from storage"
+                    );
+                }
+            }
+            self.upload_session_repository.delete(session.id).await?;
+            deleted_count += 1;
+        }
+
+        info!(deleted_count = deleted_count, "Expired upload sessions cleanup completed");
+        Ok(deleted_count)
+    }
+
+    /// Move a complete upload into storage and register its file
+    async fn store_upload(&self, session: &UploadSession) -> Result<FileMetadata> {
+        let stored_file = self
+            .storage_service
+            .complete_upload(&session.storage_handle, &session.filename)
+            .await?;
+
+        if stored_file.size as i64 != session.total_size {
+            return Err(Error::Internal(format!(
+                "Stored upload size {} does not match declared size {}",
+                stored_file.size, session.total_size
+            )));
+        }
+
+        // The declared type was only checked against the filename when the session
+        // was created; now the content can be sniffed too
+        let sample = self.read_content_sample(&stored_file).await?;
+        let content_type = match self
+            .file_service
+            .check_upload(&session.filename, &session.content_type, stored_file.size, &sample)
+            .await
+        {
+            Ok(content_type) => content_type,
+            Err(e) => {
+                // The stored content is left to the orphan scan, which keeps it
+                // if an existing blob happens to share the path
+                self.upload_session_repository.delete(session.id).await?;
+                return Err(e);
+            }
+        };
+
+        self.file_service
+            .register_stored_file(session.owner_id, session.filename.clone(), content_type, stored_file)
+            .await
+    }
+
+    /// The first and last `CONTENT_SNIFF_WINDOW` bytes of a stored file
+    async fn read_content_sample(&self, stored_file: &StoredFile) -> Result<Vec<u8>> {
+        let window = CONTENT_SNIFF_WINDOW as u64;
+        if stored_file.size == 0 {
+            return Ok(Vec::new());
+        }
+
+        let mut ranges = vec![ByteRange::new(0, stored_file.size.min(window) - 1)];
+        if stored_file.size > window {
+            let tail_start = stored_file.size.saturating_sub(window).max(window);
+            ranges.push(ByteRange::new(tail_start, stored_file.size - 1));
+        }
+
+        let mut sample = Vec::new();
+        for range in ranges {
+            let mut stream = self
+                .storage_service
+                .get_file_range_stream(&stored_file.path, range)
+                .await?;
+            while let Some(chunk) = stream.try_next().await? {
+                sample.extend_from_slice(&chunk);
+            }
+        }
+
+        Ok(sample)
+    }
+
+    async fn get_owned_session(&self, upload_id: Id, owner_id: Id) -> Result<UploadSession> {
+        let session = self
+            .upload_session_repository
+            .find_by_id(upload_id)
+            .await?
+            .ok_or_else(|| Error::NotFound("Upload session not found".to_string()))?;
+
+        // Check ownership
+        if session.owner_id != owner_id {
+            return Err(Error::Authorization("Not authorized to access this upload".to_string()));
+        }
+
+        Ok(session)
+    }
+
+    async fn get_active_session(&self, upload_id: Id, owner_id: Id) -> Result<UploadSession> {
+        let mut session = self.get_owned_session(upload_id, owner_id).await?;
+
+        if session.is_expired() {
+            return Err(Error::NotFound("Upload session has expired".to_string()));
+        }
+
+        // Its content has left the upload area
+        if session.finalizing || session.is_finalized() {
+            return Ok(session);
+        }
+
+        // Storage is authoritative: a crash between writing a chunk and recording the
+        // new offset must not make the client resend bytes we already have. The
+        // offset is left alone while a chunk is still being appended.
+        let stored_offset = self
+            .storage_service
+            .upload_offset(&session.storage_handle)
+            .await? as i64;
+
+        if stored_offset != session.offset
+            && self
+                .upload_session_repository
+                .update_offset(upload_id, session.offset, stored_offset)
+                .await?
+        {
+            session.advance(stored_offset);
+        }
+
+        Ok(session)
+    }
+}
@@ -0,0 +1,295 @@
+use crate::services::{AccountService, AuthService};
+use kingshare_core::{Error, Id, PaginatedResponse, PaginationParams, Result};
+use kingshare_domain::{
+    entities::{
+        ClientInfo, OwnershipTransfer, RevocationReason, SecurityEvent, SecurityEventType, User, UserFilter,
+        UserProfile, UserRole,
+    },
+    repositories::{SecurityEventRepository, UserRepository},
+    services::{AuthService as DomainAuthService, ImpersonationToken},
+};
+use serde::Serialize;
+use serde_json::json;
+use std::sync::Arc;
+use tracing::{info, instrument};
+
+/// An administrator signed in as another user
+#[derive(Debug, Clone, Serialize)]
+pub struct ImpersonationSession {
+    pub token: ImpersonationToken,
+    pub user: UserProfile,
+}
+
+/// Account administration. Every change is recorded as a security event
+/// with the acting administrator.
+#[derive(Clone)]
+pub struct UserAdminService {
+    user_repository: Arc<dyn UserRepository>,
+    auth_service: Arc<dyn DomainAuthService>,
+    sessions: AuthService,
+    account_service: AccountService,
+    event_repository: Arc<dyn SecurityEventRepository>,
+}
+
+impl UserAdminService {
+    pub fn new(
+        user_repository: Arc<dyn UserRepository>,
+        auth_service: Arc<dyn DomainAuthService>,
+        sessions: AuthService,
+        account_service: AccountService,
+        event_repository: Arc<dyn SecurityEventRepository>,
+    ) -> Self {
+        Self {
+            user_repository,
+            auth_service,
+            sessions,
+            account_service,
+            event_repository,
+        }
+    }
+
+    #[instrument(skip(self))]
+    pub async fn search_users(
+        &self,
+        filter: &UserFilter,
+        params: PaginationParams,
+    ) -> Result<PaginatedResponse<UserProfile>> {
+        self.user_repository.search(filter, params).await
+    }
+
+    pub async fn get_user(&self, user_id: Id) -> Result<UserProfile> {
+        Ok(self.find_user(user_id).await?.into())
+    }
+
+    /// Change a user's account role. Takes effect at their next request;
+    /// the role in tokens already issued isn't trusted for authorization.
+    #[instrument(skip(self))]
+    pub async fn change_role(&self, admin_id: Id, user_id: Id, role: UserRole) -> Result<UserProfile> {
+        self.ensure_not_self(admin_id, user_id, "change your own role")?;
+        let mut user = self.find_user(user_id).await?;
+        if user.role == role {
+            return Ok(user.into());
+        }
+        self.ensure_other_admin(&user).await?;
+
+        let previous = user.role.clone();
+        user.role = role;
+        user.updated_at = chrono::Utc::now();
+        let user = self.user_repository.update(user).await?;
+
+        self.record_event(
+            SecurityEventType::UserRoleChanged,
+            admin_id,
+            user_id,
+            None,
+            json!({ "from": previous.as_str(), "to": user.role.as_str() }),
+        )
+        .await?;
+        info!(admin_id = %admin_id, user_id = %user_id, role = user.role.as_str(), "User role changed");
+        Ok(user.into())
+    }
+
+    /// Block sign-in and end every session of the user
+    #[instrument(skip(self))]
+    pub async fn deactivate_user(&self, admin_id: Id, user_id: Id) -> Result<UserProfile> {
+        self.ensure_not_self(admin_id, user_id, "deactivate your own account")?;
+        let mut user = self.find_user(user_id).await?;
+        if !user.is_active {
+            return Ok(user.into());
+        }
+        self.ensure_other_admin(&user).await?;
+
+        user.deactivate();
+        let user = self.user_repository.update(user).await?;
+        let revoked = self
+            .sessions
+            .revoke_all_sessions(user_id, RevocationReason::AccountDeactivated)
+            .await?;
+
+        self.record_event(
+            SecurityEventType::UserDeactivated,
+            admin_id,
+            user_id,
+            None,
+            json!({ "sessions_revoked": revoked }),
+        )
+        .await?;
+        info!(admin_id = %admin_id, user_id = %user_id, "User deactivated");
+        Ok(user.into())
+    }
+
+    #[instrument(skip(self))]
+    pub async fn reactivate_user(&self, admin_id: Id, user_id: Id) -> Result<UserProfile> {
+        let mut user = self.find_user(user_id).await?;
+        if user.is_active {
+            return Ok(user.into());
+        }
+
+        user.activate();
+        let user = self.user_repository.update(user).await?;
+
+        self.record_event(SecurityEventType::UserReactivated, admin_id, user_id, None, json!({}))
+            .await?;
+        info!(admin_id = %admin_id, user_id = %user_id, "User reactivated");
+        Ok(user.into())
+    }
+
+    /// Sign the user out and make them choose a new password through an emailed link
+    #[instrument(skip(self))]
+    pub async fn force_password_reset(&self, admin_id: Id, user_id: Id) -> Result<()> {
+        self.account_service.force_password_reset(user_id).await?;
+
+        self.record_event(SecurityEventType::PasswordResetForced, admin_id, user_id, None, json!({}))
+            .await?;
+        Ok(())
+    }
+
+    /// Issue a short-lived token for acting as the user. Other administrators
+    /// and deactivated accounts can't be impersonated.
+    #[instrument(skip(self, client))]
+    pub async fn impersonate(&self, admin_id: Id, user_id: Id, client: &ClientInfo) -> Result<ImpersonationSession> {
+        self.ensure_not_self(admin_id, user_id, "impersonate yourself")?;
+        let user = self.find_user(user_id).await?;
+        if user.role == UserRole::Admin {
+            return Err(Error::Authorization("Administrators can't be impersonated".to_string()));
+        }
+        if !user.is_active {
+            return Err(Error::BadRequest("Deactivated users can't be impersonated".to_string()));
+        }
+
+        let token = self
+            .auth_service
+            .issue_impersonation_token(user.id, user.email.as_str(), &user.username, user.role.as_str(), admin_id)
+            .await?;
+
+        self.record_event(
+            SecurityEventType::UserImpersonated,
+            admin_id,
+            user_id,
+            client.ip_address.clone(),
+            json!({ "expires_in": token.expires_in, "user_agent": client.user_agent }),
+        )
+        .await?;
+        info!(admin_id = %admin_id, user_id = %user_id, "Impersonation started");
+        Ok(ImpersonationSession {
+            token,
+            user: user.into(),
+        })
+    }
+
+    /// Hand everything the user owns to another user, such as before they
+    /// leave. With `delete_user` the emptied account is deleted as well.
+    #[instrument(skip(self))]
+    pub async fn transfer_ownership(
+        &self,
+        admin_id: Id,
+        from_user_id: Id,
+        to_user_id: Id,
+        delete_user: bool,
+    ) -> Result<OwnershipTransfer> {
+        if from_user_id == to_user_id {
+            return Err(Error::BadRequest("Choose a different user to transfer to".to_string()));
+        }
+        let from_user = self.find_user(from_user_id).await?;
+        let to_user = self.find_user(to_user_id).await?;
+        if !to_user.is_active {
+            return Err(Error::BadRequest("Content can't be transferred to a deactivated user".to_string()));
+        }
+        if delete_user {
+            self.ensure_not_self(admin_id, from_user_id, "delete your own account")?;
+            self.ensure_other_admin(&from_user).await?;
+        }
+
+        let transfer = self
+            .user_repository
+            .transfer_ownership(from_user_id, to_user_id)
+            .await?;
+        self.record_event(
+            SecurityEventType::OwnershipTransferred,
+            admin_id,
+            from_user_id,
+            None,
+            json!({ "to_user_id": to_user_id, "transfer": transfer }),
+        )
+        .await?;
+        info!(admin_id = %admin_id, from_user_id = %from_user_id, to_user_id = %to_user_id, "Ownership transferred");
+
+        if delete_user {
+            self.remove_user(admin_id, &from_user).await?;
+        }
+        Ok(transfer)
+    }
+
+    /// Delete an account and, with it, everything it still owns
+    #[instrument(skip(self))]
+    pub async fn delete_user(&self, admin_id: Id, user_id: Id) -> Result<()> {
+        self.ensure_not_self(admin_id, user_id, "delete your own account")?;
+        let user = self.find_user(user_id).await?;
+        self.ensure_other_admin(&user).await?;
+
+        self.remove_user(admin_id, &user).await
+    }
+
+    async fn remove_user(&self, admin_id: Id, user: &User) -> Result<()> {
+        // Close live connections before the sessions disappear with the account
+        self.sessions
+            .revoke_all_sessions(user.id, RevocationReason::AccountDeactivated)
+            .await?;
+        self.user_repository.delete(user.id).await?;
+
+        self.record_event(
+            SecurityEventType::UserDeleted,
+            admin_id,
+            user.id,
+            None,
+            json!({ "email": user.email.as_str(), "username": user.username }),
+        )
+        .await?;
+        info!(admin_id = %admin_id, user_id = %user.id, "User deleted");
+        Ok(())
+    }
+
+    fn ensure_not_self(&self, admin_id: Id, user_id: Id, action: &str) -> Result<()> {
+        if admin_id == user_id {
+            return Err(Error::BadRequest(format!("You can't {}", action)));
+        }
+        Ok(())
+    }
+
+    /// Keep at least one active administrator when `user` stops being one
+    async fn ensure_other_admin(&self, user: &User) -> Result<()> {
+        if user.role == UserRole::Admin
+            && user.is_active
+            && self.user_repository.count_active_by_role(UserRole::Admin).await? <= 1
+        {
+            return Err(Error::Conflict("The last active administrator can't be removed".to_string()));
+        }
+        Ok(())
+    }
+
+    async fn find_user(&self, user_id: Id) -> Result<User> {
+        self.user_repository
+            .find_by_id(user_id)
+            .await?
+            .ok_or_else(|| Error::NotFound("User not found".to_string()))
+    }
+
+    async fn record_event(
+        &self,
+        event_type: SecurityEventType,
+        admin_id: Id,
+        user_id: Id,
+        ip_address: Option<String>,
+        details: serde_json::Value,
+    ) -> Result<()> {
+        self.event_repository
+            .record(SecurityEvent::new(
+                event_type,
+                Some(admin_id),
+                format!("user:{}", user_id),
+                ip_address,
+                details,
+            ))
+            .await
+    }
+}
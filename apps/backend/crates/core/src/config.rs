@@ -10,6 +10,10 @@ pub struct Config {
     pub logging: LoggingConfig,
     pub cors: CorsConfig,
     pub websocket: WebSocketConfig,
+    #[serde(default)]
+    pub storage: StorageConfig,
+    #[serde(default)]
+    pub mail: MailConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -17,6 +21,10 @@ pub struct ServerConfig {
     pub host: String,
     pub port: u16,
     pub workers: Option<usize>,
+    // Take the client address from X-Forwarded-For / X-Real-IP. Only enable
+    // behind a proxy that sets them, or clients can pick their own address.
+    #[serde(default)]
+    pub trust_proxy_headers: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -34,6 +42,251 @@ pub struct AuthConfig {
     pub jwt_expiration: i64,
     pub refresh_token_expiration: i64,
     pub password_hash_cost: u32,
+    #[serde(default)]
+    pub mfa: MfaConfig,
+    // Seconds an email verification link stays valid
+    #[serde(default = "default_email_verification_expiration")]
+    pub email_verification_expiration: i64,
+    // Seconds a password reset link stays valid
+    #[serde(default = "default_password_reset_expiration")]
+    pub password_reset_expiration: i64,
+    // Seconds an administrator's impersonation token stays valid
+    #[serde(default = "default_impersonation_expiration")]
+    pub impersonation_expiration: i64,
+    #[serde(default)]
+    pub unverified: UnverifiedAccountConfig,
+    pub oidc: Option<OidcConfig>,
+    #[serde(default)]
+    pub api_tokens: ApiTokenConfig,
+    #[serde(default)]
+    pub lockout: LockoutConfig,
+}
+
+fn default_email_verification_expiration() -> i64 {
+    86400
+}
+
+fn default_password_reset_expiration() -> i64 {
+    3600
+}
+
+fn default_impersonation_expiration() -> i64 {
+    900
+}
+
+/// What accounts may do before their email address is verified
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnverifiedAccountConfig {
+    #[serde(default = "default_true")]
+    pub allow_login: bool,
+    // Share links can be opened by anyone, so they are off until the owner is verified
+    #[serde(default)]
+    pub allow_public_shares: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for UnverifiedAccountConfig {
+    fn default() -> Self {
+        Self {
+            allow_login: true,
+            allow_public_shares: false,
+        }
+    }
+}
+
+/// TOTP two-factor authentication (RFC 6238)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MfaConfig {
+    // Shown next to the account name in authenticator apps
+    #[serde(default = "default_mfa_issuer")]
+    pub issuer: String,
+    // Seconds a client has to answer the challenge issued at password login
+    #[serde(default = "default_mfa_challenge_expiration")]
+    pub challenge_expiration: i64,
+    // Admins without 2FA must enroll before their login completes
+    #[serde(default)]
+    pub require_for_admins: bool,
+}
+
+fn default_mfa_issuer() -> String {
+    "KingShare".to_string()
+}
+
+fn default_mfa_challenge_expiration() -> i64 {
+    300
+}
+
+impl Default for MfaConfig {
+    fn default() -> Self {
+        Self {
+            issuer: default_mfa_issuer(),
+            challenge_expiration: default_mfa_challenge_expiration(),
+            require_for_admins: false,
+        }
+    }
+}
+
+/// Failed-attempt tracking for logins and share passwords. After the free
+/// attempts each failure doubles the wait before the next try; at the
+/// threshold the account, share or address is locked outright.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockoutConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    // Failures per account or share before backoff starts
+    #[serde(default = "default_lockout_free_attempts")]
+    pub free_attempts: u32,
+    // Failures per account or share that lock it
+    #[serde(default = "default_lockout_threshold")]
+    pub lockout_threshold: u32,
+    // Addresses get more room, since offices and NATs share one
+    #[serde(default = "default_lockout_ip_free_attempts")]
+    pub ip_free_attempts: u32,
+    #[serde(default = "default_lockout_ip_threshold")]
+    pub ip_lockout_threshold: u32,
+    // Seconds of the first backoff, doubled with every further failure
+    #[serde(default = "default_lockout_base_delay")]
+    pub base_delay: i64,
+    #[serde(default = "default_lockout_max_delay")]
+    pub max_delay: i64,
+    // Seconds a lockout lasts
+    #[serde(default = "default_lockout_duration")]
+    pub lockout_duration: i64,
+    // Seconds without failures after which the count starts over
+    #[serde(default = "default_lockout_attempt_window")]
+    pub attempt_window: i64,
+}
+
+fn default_lockout_free_attempts() -> u32 {
+    3
+}
+
+fn default_lockout_threshold() -> u32 {
+    10
+}
+
+fn default_lockout_ip_free_attempts() -> u32 {
+    10
+}
+
+fn default_lockout_ip_threshold() -> u32 {
+    50
+}
+
+fn default_lockout_base_delay() -> i64 {
+    1
+}
+
+fn default_lockout_max_delay() -> i64 {
+    300
+}
+
+fn default_lockout_duration() -> i64 {
+    900
+}
+
+fn default_lockout_attempt_window() -> i64 {
+    3600
+}
+
+impl Default for LockoutConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            free_attempts: default_lockout_free_attempts(),
+            lockout_threshold: default_lockout_threshold(),
+            ip_free_attempts: default_lockout_ip_free_attempts(),
+            ip_lockout_threshold: default_lockout_ip_threshold(),
+            base_delay: default_lockout_base_delay(),
+            max_delay: default_lockout_max_delay(),
+            lockout_duration: default_lockout_duration(),
+            attempt_window: default_lockout_attempt_window(),
+        }
+    }
+}
+
+/// Personal access tokens for scripts and CI jobs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiTokenConfig {
+    // Days a token lives when its creator doesn't choose
+    #[serde(default = "default_api_token_expiration_days")]
+    pub default_expiration_days: i64,
+    // Longest lifetime a token may be given
+    #[serde(default = "default_api_token_max_expiration_days")]
+    pub max_expiration_days: i64,
+    // Live tokens a single user may hold
+    #[serde(default = "default_api_tokens_per_user")]
+    pub max_tokens_per_user: u64,
+}
+
+fn default_api_token_expiration_days() -> i64 {
+    90
+}
+
+fn default_api_token_max_expiration_days() -> i64 {
+    365
+}
+
+fn default_api_tokens_per_user() -> u64 {
+    50
+}
+
+impl Default for ApiTokenConfig {
+    fn default() -> Self {
+        Self {
+            default_expiration_days: default_api_token_expiration_days(),
+            max_expiration_days: default_api_token_max_expiration_days(),
+            max_tokens_per_user: default_api_tokens_per_user(),
+        }
+    }
+}
+
+/// Single sign-on through an OpenID Connect identity provider, using the
+/// authorization code flow with PKCE. Without it only password logins are offered.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OidcConfig {
+    pub issuer_url: String, // Discovery is read from `{issuer_url}/.well-known/openid-configuration`
+    pub client_id: String,
+    pub client_secret: Option<String>, // Unset for public clients, which rely on PKCE alone
+    pub redirect_uri: String,
+    #[serde(default = "default_oidc_scopes")]
+    pub scopes: Vec<String>,
+    // ID token claim holding the user's groups
+    #[serde(default = "default_oidc_groups_claim")]
+    pub groups_claim: String,
+    // Members of these groups become admins, or guests; everyone else is a regular user.
+    // With neither set, roles are managed locally and never touched at login.
+    #[serde(default)]
+    pub admin_groups: Vec<String>,
+    #[serde(default)]
+    pub guest_groups: Vec<String>,
+    // Create accounts for people signing in for the first time
+    #[serde(default = "default_true")]
+    pub allow_provisioning: bool,
+    // Seconds a user has to finish signing in at the identity provider
+    #[serde(default = "default_oidc_login_expiration")]
+    pub login_expiration: i64,
+    #[serde(default = "default_oidc_timeout")]
+    pub timeout_seconds: u64,
+}
+
+fn default_oidc_scopes() -> Vec<String> {
+    vec!["openid".to_string(), "email".to_string(), "profile".to_string()]
+}
+
+fn default_oidc_groups_claim() -> String {
+    "groups".to_string()
+}
+
+fn default_oidc_login_expiration() -> i64 {
+    600
+}
+
+fn default_oidc_timeout() -> u64 {
+    10
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -59,6 +312,233 @@ pub struct WebSocketConfig {
     pub max_message_size: usize,
 }
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    #[default]
+    Local,
+    S3,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageConfig {
+    #[serde(default)]
+    pub backend: StorageBackend,
+    #[serde(default = "default_storage_path")]
+    pub storage_path: String,
+    #[serde(default = "default_max_file_size")]
+    pub max_file_size: u64,
+    #[serde(default)]
+    pub s3: Option<S3Config>,
+    #[serde(default)]
+    pub encryption: Option<EncryptionConfig>,
+    #[serde(default)]
+    pub scanner: Option<ScannerConfig>,
+    #[serde(default)]
+    pub content_type_policy: ContentTypePolicy,
+    #[serde(default)]
+    pub quota: QuotaConfig,
+}
+
+/// How uploads are checked against the content type sniffed from their bytes.
+/// Executables are refused under every policy.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContentTypePolicy {
+    /// Mismatches are only reported as warnings and the declared type is kept
+    Permissive,
+    /// The sniffed type replaces the declared one; content that contradicts
+    /// the file extension or a declared signature type is rejected
+    #[default]
+    Override,
+    /// Any disagreement between extension, declared and sniffed type is rejected
+    Strict,
+}
+
+fn default_storage_path() -> String {
+    "./uploads".to_string()
+}
+
+fn default_max_file_size() -> u64 {
+    100 * 1024 * 1024 // 100MB
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct S3Config {
+    pub bucket: String,
+    #[serde(default = "default_s3_region")]
+    pub region: String,
+    #[serde(default)]
+    pub endpoint: Option<String>, // Set for MinIO and other S3-compatible services
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    #[serde(default)]
+    pub force_path_style: bool,
+    #[serde(default = "default_presigned_url_expiration")]
+    pub presigned_url_expiration: u64, // seconds
+}
+
+fn default_s3_region() -> String {
+    "us-east-1".to_string()
+}
+
+fn default_presigned_url_expiration() -> u64 {
+    3600
+}
+
+/// Envelope encryption for the local backend. Master keys are 32 bytes, base64 encoded;
+/// a key file may also hold the raw bytes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptionConfig {
+    #[serde(default)]
+    pub master_key: Option<String>,
+    #[serde(default)]
+    pub master_key_file: Option<String>,
+    // Keys retired by a rotation, kept until every data key has been re-wrapped
+    #[serde(default)]
+    pub previous_master_keys: Vec<String>,
+    #[serde(default)]
+    pub previous_master_key_files: Vec<String>,
+}
+
+/// ClamAV daemon that scans every upload. Without it uploads are not scanned
+/// and are served as soon as they are stored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScannerConfig {
+    pub clamd_address: String, // `host:port`, or `unix:/path/to/clamd.sock`
+    #[serde(default = "default_scan_timeout")]
+    pub timeout_seconds: u64,
+}
+
+fn default_scan_timeout() -> u64 {
+    300
+}
+
+/// Per-user storage limits. Users without a quota of their own get
+/// `default_user_quota`; leaving it unset means unlimited.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuotaConfig {
+    #[serde(default)]
+    pub default_user_quota: Option<i64>, // bytes
+    // Usage percentage at which owners are warned they are running out of space
+    #[serde(default = "default_soft_limit_percent")]
+    pub soft_limit_percent: u8,
+}
+
+fn default_soft_limit_percent() -> u8 {
+    90
+}
+
+impl Default for QuotaConfig {
+    fn default() -> Self {
+        Self {
+            default_user_quota: None,
+            soft_limit_percent: default_soft_limit_percent(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MailTransport {
+    Smtp,
+    /// Write each message as an `.eml` file to `outbox_path`, for development
+    File,
+    /// Keep messages in memory, for tests
+    Memory,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MailConfig {
+    pub transport: MailTransport,
+    pub from_address: String, // e.g. `KingShare <no-reply@example.com>`
+    // Frontend URL that links in emails point to
+    pub public_url: String,
+    pub smtp: Option<SmtpConfig>,
+    pub outbox_path: String,
+    // Directory with `.hbs` files overriding the built-in templates
+    pub templates_path: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpSecurity {
+    None,
+    StartTls,
+    Tls,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmtpConfig {
+    pub host: String,
+    #[serde(default = "default_smtp_port")]
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    #[serde(default = "default_smtp_security")]
+    pub security: SmtpSecurity,
+    #[serde(default = "default_smtp_timeout")]
+    pub timeout_seconds: u64,
+}
+
+fn default_smtp_port() -> u16 {
+    587
+}
+
+fn default_smtp_security() -> SmtpSecurity {
+    SmtpSecurity::StartTls
+}
+
+fn default_smtp_timeout() -> u64 {
+    30
+}
+
+impl Default for MailConfig {
+    fn default() -> Self {
+        Self {
+            transport: MailTransport::File,
+            from_address: "KingShare <no-reply@localhost>".to_string(),
+            public_url: "http://localhost:3000".to_string(),
+            smtp: None,
+            outbox_path: "./mail-outbox".to_string(),
+            templates_path: None,
+        }
+    }
+}
+
+impl MailConfig {
+    /// The `mail.smtp` section, required when `transport` is `smtp`
+    pub fn smtp_config(&self) -> std::result::Result<&SmtpConfig, config::ConfigError> {
+        self.smtp
+            .as_ref()
+            .ok_or_else(|| config::ConfigError::NotFound("mail.smtp".to_string()))
+    }
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            backend: StorageBackend::default(),
+            storage_path: default_storage_path(),
+            max_file_size: default_max_file_size(),
+            s3: None,
+            encryption: None,
+            scanner: None,
+            content_type_policy: ContentTypePolicy::default(),
+            quota: QuotaConfig::default(),
+        }
+    }
+}
+
+impl StorageConfig {
+    /// The `storage.s3` section, required when `backend` is `s3`
+    pub fn s3_config(&self) -> std::result::Result<&S3Config, config::ConfigError> {
+        self.s3
+            .as_ref()
+            .ok_or_else(|| config::ConfigError::NotFound("storage.s3".to_string()))
+    }
+}
+
 impl Config {
     pub fn load() -> Result<Self> {
         let mut settings = config::Config::builder()
@@ -85,6 +565,7 @@ impl Default for Config {
                 host: "0.0.0.0".to_string(),
                 port: 8080,
                 workers: None,
+                trust_proxy_headers: false,
             },
             database: DatabaseConfig {
                 url: env::var("DATABASE_URL")
@@ -100,6 +581,14 @@ impl Default for Config {
                 jwt_expiration: 3600, // 1 hour
                 refresh_token_expiration: 604800, // 1 week
                 password_hash_cost: 12,
+                mfa: MfaConfig::default(),
+                email_verification_expiration: default_email_verification_expiration(),
+                password_reset_expiration: default_password_reset_expiration(),
+                impersonation_expiration: default_impersonation_expiration(),
+                unverified: UnverifiedAccountConfig::default(),
+                oidc: None,
+                api_tokens: ApiTokenConfig::default(),
+                lockout: LockoutConfig::default(),
             },
             logging: LoggingConfig {
                 level: "info".to_string(),
@@ -128,6 +617,8 @@ impl Default for Config {
                 connection_timeout: 300,
                 max_message_size: 1024 * 1024, // 1MB
             },
+            storage: StorageConfig::default(),
+            mail: MailConfig::default(),
         }
     }
 }
\ No newline at end of file
@@ -34,6 +34,12 @@ pub enum Error {
     #[error("Bad request: {0}")]
     BadRequest(String),
 
+    #[error("Quota exceeded: {0}")]
+    QuotaExceeded(String),
+
+    #[error("Too many requests: {message}")]
+    TooManyRequests { message: String, retry_after: u64 },
+
     #[error("Internal server error: {0}")]
     Internal(String),
 
@@ -67,6 +73,8 @@ impl Error {
             Error::NotFound(_) => StatusCode::NOT_FOUND,
             Error::Conflict(_) => StatusCode::CONFLICT,
             Error::BadRequest(_) => StatusCode::BAD_REQUEST,
+            Error::QuotaExceeded(_) => StatusCode::INSUFFICIENT_STORAGE,
+            Error::TooManyRequests { .. } => StatusCode::TOO_MANY_REQUESTS,
             Error::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Error::Config(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Error::Jwt(_) => StatusCode::UNAUTHORIZED,
@@ -86,6 +94,8 @@ impl Error {
             Error::NotFound(_) => "NOT_FOUND",
             Error::Conflict(_) => "CONFLICT",
             Error::BadRequest(_) => "BAD_REQUEST",
+            Error::QuotaExceeded(_) => "QUOTA_EXCEEDED",
+            Error::TooManyRequests { .. } => "TOO_MANY_REQUESTS",
             Error::Internal(_) => "INTERNAL_ERROR",
             Error::Config(_) => "CONFIG_ERROR",
             Error::Jwt(_) => "JWT_ERROR",
@@ -119,6 +129,12 @@ impl IntoResponse for Error {
             }
         }));
 
-        (status, body).into_response()
+        let mut response = (status, body).into_response();
+        if let Error::TooManyRequests { retry_after, .. } = self {
+            response
+                .headers_mut()
+                .insert(axum::http::header::RETRY_AFTER, retry_after.into());
+        }
+        response
     }
 }
\ No newline at end of file
@@ -0,0 +1,44 @@
+use kingshare_core::{Error, Id, Timestamp};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// What an emailed account link lets its holder do
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum AccountTokenPurpose {
+    VerifyEmail,
+    ResetPassword,
+}
+
+impl AccountTokenPurpose {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::VerifyEmail => "verify_email",
+            Self::ResetPassword => "reset_password",
+        }
+    }
+}
+
+impl FromStr for AccountTokenPurpose {
+    type Err = Error;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value {
+            "verify_email" => Ok(Self::VerifyEmail),
+            "reset_password" => Ok(Self::ResetPassword),
+            other => Err(Error::Authentication(format!("Unknown account token purpose '{}'", other))),
+        }
+    }
+}
+
+/// A verified, unspent account link
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AccountToken {
+    /// Token ID, denied once the link has been used
+    pub token_id: Id,
+    pub user_id: Id,
+    pub purpose: AccountTokenPurpose,
+    /// Hash of the account state the link was issued for; the link stops
+    /// working once that state changes
+    pub fingerprint: String,
+    pub expires_at: Timestamp,
+}
@@ -0,0 +1,169 @@
+use kingshare_core::{Error, Id, Timestamp};
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+use validator::Validate;
+
+/// Personal access tokens start with this, so they can be told apart from JWTs
+/// and spotted by secret scanners
+pub const API_TOKEN_PREFIX: &str = "ksp_";
+
+/// What a personal access token may be used for. Scopes don't imply each
+/// other: a token that uploads and lists files needs both file scopes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ApiScope {
+    #[serde(rename = "files:read")]
+    FilesRead,
+    #[serde(rename = "files:write")]
+    FilesWrite,
+    #[serde(rename = "shares:manage")]
+    SharesManage,
+    #[serde(rename = "forms:responses:read")]
+    FormsResponsesRead,
+}
+
+impl ApiScope {
+    pub const ALL: [ApiScope; 4] = [
+        Self::FilesRead,
+        Self::FilesWrite,
+        Self::SharesManage,
+        Self::FormsResponsesRead,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::FilesRead => "files:read",
+            Self::FilesWrite => "files:write",
+            Self::SharesManage => "shares:manage",
+            Self::FormsResponsesRead => "forms:responses:read",
+        }
+    }
+}
+
+impl fmt::Display for ApiScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ApiScope {
+    type Err = Error;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|scope| scope.as_str() == value)
+            .ok_or_else(|| Error::Validation(format!("Unknown scope '{}'", value)))
+    }
+}
+
+/// A personal access token. Only a hash of the secret is stored.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ApiToken {
+    pub id: Id,
+    pub user_id: Id,
+    pub name: String,
+    /// The start of the secret, shown so users can tell their tokens apart
+    pub display_prefix: String,
+    pub token_hash: String,
+    pub scopes: Vec<ApiScope>,
+    /// Set to confine the token to one drive
+    pub drive_id: Option<Id>,
+    pub created_at: Timestamp,
+    pub expires_at: Timestamp,
+    pub last_used_at: Option<Timestamp>,
+    pub revoked_at: Option<Timestamp>,
+}
+
+impl ApiToken {
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= chrono::Utc::now()
+    }
+
+    pub fn is_revoked(&self) -> bool {
+        self.revoked_at.is_some()
+    }
+}
+
+/// A personal access token as shown to its owner
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ApiTokenInfo {
+    pub id: Id,
+    pub name: String,
+    pub display_prefix: String,
+    pub scopes: Vec<ApiScope>,
+    pub drive_id: Option<Id>,
+    pub created_at: Timestamp,
+    pub expires_at: Timestamp,
+    pub last_used_at: Option<Timestamp>,
+}
+
+impl From<ApiToken> for ApiTokenInfo {
+    fn from(token: ApiToken) -> Self {
+        Self {
+            id: token.id,
+            name: token.name,
+            display_prefix: token.display_prefix,
+            scopes: token.scopes,
+            drive_id: token.drive_id,
+            created_at: token.created_at,
+            expires_at: token.expires_at,
+            last_used_at: token.last_used_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct CreateApiTokenRequest {
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+
+    #[validate(length(min = 1))]
+    pub scopes: Vec<ApiScope>,
+
+    pub drive_id: Option<Id>,
+
+    /// Defaults to the configured lifetime
+    #[validate(range(min = 1))]
+    pub expires_in_days: Option<i64>,
+}
+
+/// A newly created token. The secret is only ever returned here.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreatedApiToken {
+    pub token: String,
+    #[serde(flatten)]
+    pub info: ApiTokenInfo,
+}
+
+/// A freshly generated secret and the hash it is stored and looked up by
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiTokenSecret {
+    pub token: String,
+    pub token_hash: String,
+}
+
+/// What a request authenticated with a personal access token may do
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ApiTokenGrant {
+    pub token_id: Id,
+    pub user_id: Id,
+    pub scopes: Vec<ApiScope>,
+    pub drive_id: Option<Id>,
+}
+
+impl ApiTokenGrant {
+    /// Whether the token may act on a resource with `scope`. Resources outside
+    /// any drive (`drive_id` of `None`) are off limits to drive-bound tokens.
+    pub fn allows(&self, scope: ApiScope, drive_id: Option<Id>) -> bool {
+        self.scopes.contains(&scope) && (self.drive_id.is_none() || self.drive_id == drive_id)
+    }
+
+    /// `allows` as an `Error::Authorization` for handlers to return
+    pub fn require(&self, scope: ApiScope, drive_id: Option<Id>) -> kingshare_core::Result<()> {
+        if self.allows(scope, drive_id) {
+            Ok(())
+        } else {
+            Err(Error::Authorization(format!("Token lacks the {} scope for this resource", scope)))
+        }
+    }
+}
@@ -0,0 +1,22 @@
+use kingshare_core::Timestamp;
+use serde::{Deserialize, Serialize};
+
+/// Minimum time a blob must stay unreferenced before garbage collection removes it
+pub const BLOB_GC_GRACE_MINUTES: i64 = 60;
+
+/// Stored content addressed by its SHA-256, shared by every `File` with that checksum
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Blob {
+    pub checksum: String,
+    pub storage_path: String,
+    pub size: i64,
+    pub ref_count: i64,
+    pub created_at: Timestamp,
+    pub updated_at: Timestamp,
+}
+
+impl Blob {
+    pub fn is_referenced(&self) -> bool {
+        self.ref_count > 0
+    }
+}
@@ -407,6 +407,57 @@ impl Document {
     }
 }
 
+impl DocumentContent {
+    /// The words in the content, for search. Spreadsheet cells come row by row.
+    pub fn plain_text(&self) -> String {
+        let mut parts: Vec<String> = Vec::new();
+        match self {
+            Self::Text { content, .. } => parts.push(content.clone()),
+            Self::Spreadsheet { sheets } => {
+                for sheet in sheets {
+                    parts.push(sheet.name.clone());
+                    let mut references: Vec<&String> = sheet.cells.keys().collect();
+                    references.sort_by_key(|reference| cell_position(reference));
+                    for reference in references {
+                        match &sheet.cells[reference] {
+                            CellValue::Text(text) => parts.push(text.clone()),
+                            CellValue::Number(number) => parts.push(number.to_string()),
+                            CellValue::Boolean(_) | CellValue::Date(_) | CellValue::Formula(_) | CellValue::Empty => {}
+                        }
+                    }
+                }
+            }
+            Self::Presentation { slides } => {
+                for slide in slides {
+                    parts.push(slide.title.clone());
+                    parts.push(slide.content.clone());
+                    parts.extend(slide.elements.iter().map(|element| element.content.clone()));
+                    parts.push(slide.notes.clone());
+                }
+            }
+            Self::Form { fields, .. } => {
+                for field in fields {
+                    parts.push(field.label.clone());
+                    parts.extend(field.description.clone());
+                    parts.extend(field.options.iter().cloned());
+                }
+            }
+            Self::Drawing { elements, .. } => {
+                parts.extend(elements.iter().map(|element| element.content.clone()));
+            }
+        }
+        parts.retain(|part| !part.trim().is_empty());
+        parts.join("\n")
+    }
+}
+
+/// Row, then column, of a reference such as `B12`
+fn cell_position(reference: &str) -> (u32, usize, String) {
+    let split = reference.find(|c: char| c.is_ascii_digit()).unwrap_or(reference.len());
+    let (column, row) = reference.split_at(split);
+    (row.parse().unwrap_or(u32::MAX), column.len(), column.to_string())
+}
+
 impl SpreadsheetSheet {
     pub fn new(name: String) -> Self {
         Self {
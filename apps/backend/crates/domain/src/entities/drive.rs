@@ -1,8 +1,12 @@
+use super::{DocumentContent, Permission};
 use kingshare_core::{Id, Timestamp};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use validator::Validate;
 
+/// MIME type of the drive item that stands for a folder in listings
+pub const FOLDER_MIME_TYPE: &str = "application/vnd.kingshare.folder";
+
 /// Drive represents a user's workspace containing folders and files
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Drive {
@@ -15,6 +19,8 @@ pub struct Drive {
     pub storage_used: i64,  // bytes
     pub settings: DriveSettings,
     pub is_shared: bool,
+    /// The team the drive belongs to; team role assignments apply to it
+    pub team_id: Option<Id>,
     pub created_at: Timestamp,
     pub updated_at: Timestamp,
 }
@@ -69,13 +75,17 @@ pub struct FolderPermissions {
     pub owner_id: Id,
     pub shared_with: Vec<FolderShare>,
     pub public_access: PublicAccessLevel,
+    /// Whether shares of the folders above reach this one. Turning it off
+    /// restricts the folder and its contents to what is granted from here down.
     pub inherit_permissions: bool,
 }
 
+/// A share of a folder, which reaches everything in it
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct FolderShare {
     pub user_id: Id,
     pub permissions: SharingPermissions,
+    pub role: ShareRole,
     pub granted_by: Id,
     pub granted_at: Timestamp,
     pub expires_at: Option<Timestamp>,
@@ -102,6 +112,10 @@ pub struct DriveItem {
     pub path: String,
     pub permissions: ItemPermissions,
     pub metadata: ItemMetadata,
+    /// The document or uploaded file the item holds, whose contents it's
+    /// searched by
+    #[serde(default)]
+    pub source_id: Option<Id>,
     pub is_starred: bool,
     pub is_trashed: bool,
     pub created_at: Timestamp,
@@ -141,6 +155,29 @@ pub struct ItemShare {
     pub notification_sent: bool,
 }
 
+/// What a user wants to do with a drive item
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemAccess {
+    View,
+    Comment,
+    Edit,
+    Share,
+    Download,
+}
+
+impl ItemAccess {
+    /// The role permission that allows the same on items in a drive
+    pub fn permission(&self) -> Permission {
+        match self {
+            Self::View | Self::Download => Permission::DriveRead,
+            Self::Comment => Permission::DriveComment,
+            Self::Edit => Permission::DriveWrite,
+            Self::Share => Permission::DriveShare,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum ShareRole {
     Viewer,
@@ -149,6 +186,186 @@ pub enum ShareRole {
     Owner,
 }
 
+impl SharingPermissions {
+    pub fn none() -> Self {
+        Self {
+            can_view: false,
+            can_comment: false,
+            can_edit: false,
+            can_share: false,
+            can_download: false,
+        }
+    }
+
+    /// What the drive permissions of a user's roles allow on an item
+    pub fn from_drive_permissions(permissions: &[Permission]) -> Self {
+        Self {
+            can_view: permissions.contains(&ItemAccess::View.permission()),
+            can_comment: permissions.contains(&ItemAccess::Comment.permission()),
+            can_edit: permissions.contains(&ItemAccess::Edit.permission()),
+            can_share: permissions.contains(&ItemAccess::Share.permission()),
+            can_download: permissions.contains(&ItemAccess::Download.permission()),
+        }
+    }
+
+    pub fn allows(&self, access: ItemAccess) -> bool {
+        match access {
+            ItemAccess::View => self.can_view,
+            ItemAccess::Comment => self.can_comment,
+            ItemAccess::Edit => self.can_edit,
+            ItemAccess::Share => self.can_share,
+            ItemAccess::Download => self.can_download,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::none()
+    }
+
+    /// Everything either allows
+    pub fn union(&self, other: &Self) -> Self {
+        Self {
+            can_view: self.can_view || other.can_view,
+            can_comment: self.can_comment || other.can_comment,
+            can_edit: self.can_edit || other.can_edit,
+            can_share: self.can_share || other.can_share,
+            can_download: self.can_download || other.can_download,
+        }
+    }
+}
+
+impl PublicAccessLevel {
+    /// What anyone may do; `None` when the item is private
+    pub fn permissions(&self) -> Option<SharingPermissions> {
+        let view = SharingPermissions {
+            can_view: true,
+            ..SharingPermissions::none()
+        };
+        match self {
+            Self::Private => None,
+            Self::ViewOnly => Some(view),
+            Self::CommentOnly => Some(SharingPermissions {
+                can_comment: true,
+                ..view
+            }),
+            Self::EditAccess => Some(SharingPermissions {
+                can_comment: true,
+                can_edit: true,
+                ..view
+            }),
+        }
+    }
+}
+
+impl ShareRole {
+    /// What a share with this role allows
+    pub fn permissions(&self) -> SharingPermissions {
+        SharingPermissions {
+            can_view: true,
+            can_comment: !matches!(self, Self::Viewer),
+            can_edit: matches!(self, Self::Editor | Self::Owner),
+            can_share: matches!(self, Self::Editor | Self::Owner),
+            can_download: true,
+        }
+    }
+}
+
+/// How a user came by access to an item
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccessSource {
+    Owner,
+    Share,
+    PublicAccess,
+    /// Roles held on the item's drive
+    Role,
+}
+
+/// The folder a grant was inherited from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InheritedFrom {
+    pub folder_id: Id,
+    pub path: String,
+}
+
+/// One reason a user may use an item
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccessGrant {
+    pub source: AccessSource,
+    pub role: Option<ShareRole>,
+    pub permissions: SharingPermissions,
+    pub granted_by: Option<Id>,
+    pub expires_at: Option<Timestamp>,
+    /// Unset for grants on the item itself
+    pub inherited_from: Option<InheritedFrom>,
+}
+
+impl AccessGrant {
+    /// Such as "Editor via folder /Finance granted by Ann Example"
+    pub fn describe(&self, granted_by: Option<&str>) -> String {
+        let mut description = match (self.source, &self.role) {
+            (AccessSource::Owner, _) => "Owner".to_string(),
+            (AccessSource::Share, Some(role)) => format!("{:?}", role),
+            (AccessSource::Share, None) => "Shared".to_string(),
+            (AccessSource::PublicAccess, _) => "Public access".to_string(),
+            (AccessSource::Role, _) => "Roles on the drive".to_string(),
+        };
+        if let Some(inherited_from) = &self.inherited_from {
+            let via = match self.source {
+                AccessSource::Owner => " of folder ",
+                _ => " via folder ",
+            };
+            description.push_str(via);
+            description.push_str(&inherited_from.path);
+        }
+        if let Some(granted_by) = granted_by {
+            let by = match self.inherited_from {
+                Some(_) => " granted by ",
+                None => ", shared by ",
+            };
+            description.push_str(by);
+            description.push_str(granted_by);
+        }
+        description
+    }
+}
+
+/// Everything a user may do with an item, and why
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EffectiveAccess {
+    pub item_id: Id,
+    pub user_id: Id,
+    pub permissions: SharingPermissions,
+    pub grants: Vec<AccessGrant>,
+}
+
+impl EffectiveAccess {
+    pub fn allows(&self, access: ItemAccess) -> bool {
+        self.permissions.allows(access)
+    }
+
+    pub fn add(&mut self, grant: AccessGrant) {
+        self.permissions = self.permissions.union(&grant.permissions);
+        self.grants.push(grant);
+    }
+}
+
+/// A grant with the reason spelled out
+#[derive(Debug, Clone, Serialize)]
+pub struct ExplainedGrant {
+    #[serde(flatten)]
+    pub grant: AccessGrant,
+    pub explanation: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AccessExplanation {
+    pub item_id: Id,
+    pub user_id: Id,
+    pub permissions: SharingPermissions,
+    pub grants: Vec<ExplainedGrant>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct SharingLink {
     pub token: String,
@@ -239,6 +456,7 @@ pub struct UpdateFolderRequest {
     pub description: Option<String>,
     pub color: Option<String>,
     pub parent_id: Option<Id>,
+    pub inherit_permissions: Option<bool>,
 }
 
 #[derive(Debug, Deserialize, Validate)]
@@ -274,7 +492,7 @@ pub struct DriveItemResponse {
     pub owner: ItemOwner,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 pub struct ItemOwner {
     pub id: Id,
     pub username: String,
@@ -290,6 +508,84 @@ pub struct FolderContents {
     pub has_more: bool,
 }
 
+/// Typed filters for drive search
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SearchFilters {
+    pub item_type: Option<DriveItemType>,
+    /// Matched against the start of the MIME type, such as `image/`
+    pub mime_type: Option<String>,
+    pub owner_id: Option<Id>,
+    pub modified_after: Option<Timestamp>,
+    pub modified_before: Option<Timestamp>,
+    /// Starred by the searching user
+    pub starred: Option<bool>,
+    /// Shared with the searching user, directly or through a folder
+    #[serde(default)]
+    pub shared_with_me: bool,
+    #[serde(default)]
+    pub trashed: bool,
+    /// When set, only what the searching user may view: everything in these
+    /// drives, and elsewhere what they own or reach through sharing. Set from
+    /// their roles, never by the client.
+    #[serde(skip)]
+    pub readable_drives: Option<Vec<Id>>,
+}
+
+/// The items a search looks through
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchScope {
+    Drive(Id),
+    /// Items the searching user worked with
+    Recent,
+    /// Items the searching user starred
+    Starred,
+    /// Items shared directly with the searching user
+    SharedWithMe,
+}
+
+/// An item found by search, with how well it matched
+#[derive(Debug, Clone)]
+pub struct SearchMatch {
+    pub item: DriveItem,
+    pub rank: f32,
+    /// Where the words were found, marked with `<mark>`
+    pub highlight: Option<String>,
+}
+
+/// An item holding a document or file whose contents were never indexed
+#[derive(Debug, Clone)]
+pub struct UnindexedItem {
+    pub item_id: Id,
+    pub mime_type: String,
+    pub source: ItemSource,
+}
+
+/// Where the contents of an item's source are read from
+#[derive(Debug, Clone)]
+pub enum ItemSource {
+    /// The saved content of a document, spreadsheet or other editor item
+    Document(DocumentContent),
+    /// Where an uploaded file's data is stored
+    File { storage_path: String },
+}
+
+#[derive(Debug, Serialize)]
+pub struct SearchResult {
+    #[serde(flatten)]
+    pub item: DriveItemResponse,
+    pub rank: f32,
+    pub highlight: Option<String>,
+}
+
+/// Totals for everything below a folder, leaving out the trash
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FolderStats {
+    pub folder_id: Id,
+    pub total_size: i64,
+    pub file_count: i64,
+    pub folder_count: i64,
+}
+
 impl Drive {
     pub fn new(owner_id: Id, name: String, drive_type: DriveType) -> Self {
         let now = chrono::Utc::now();
@@ -310,6 +606,7 @@ impl Drive {
             storage_used: 0,
             settings: DriveSettings::default(),
             is_shared: false,
+            team_id: None,
             created_at: now,
             updated_at: now,
         }
@@ -391,9 +688,14 @@ impl DriveItem {
         parent_id: Option<Id>,
     ) -> Self {
         let now = chrono::Utc::now();
+        let id = uuid::Uuid::new_v4();
+        let thumbnail_url = match item_type {
+            DriveItemType::File => super::thumbnail_url(id, &mime_type),
+            _ => None,
+        };
         
         Self {
-            id: uuid::Uuid::new_v4(),
+            id,
             drive_id,
             parent_id,
             name,
@@ -411,13 +713,14 @@ impl DriveItem {
                 description: None,
                 tags: Vec::new(),
                 custom_properties: HashMap::new(),
-                thumbnail_url: None,
+                thumbnail_url,
                 preview_url: None,
                 download_url: None,
                 web_view_url: None,
                 version: 1,
                 checksum: None,
             },
+            source_id: None,
             is_starred: false,
             is_trashed: false,
             created_at: now,
@@ -427,33 +730,19 @@ impl DriveItem {
         }
     }
 
-    pub fn can_user_access(&self, user_id: Id, required_permission: &str) -> bool {
-        // Owner has all permissions
-        if self.permissions.owner_id == user_id {
-            return true;
-        }
-
-        // Check shared permissions
-        for share in &self.permissions.shared_with {
-            if share.user_id == user_id {
-                return match required_permission {
-                    "view" => share.permissions.can_view,
-                    "comment" => share.permissions.can_comment,
-                    "edit" => share.permissions.can_edit,
-                    "share" => share.permissions.can_share,
-                    "download" => share.permissions.can_download,
-                    _ => false,
-                };
-            }
-        }
+    /// Whether the item itself lets `user_id` do this, through ownership, a
+    /// share or public access. Roles held on the drive are checked by the
+    /// authorization service.
+    pub fn can_user_access(&self, user_id: Id, access: ItemAccess) -> bool {
+        self.effective_access(user_id, &[]).allows(access)
+    }
 
-        // Check public access
-        match self.permissions.public_access {
-            PublicAccessLevel::Private => false,
-            PublicAccessLevel::ViewOnly => required_permission == "view",
-            PublicAccessLevel::CommentOnly => matches!(required_permission, "view" | "comment"),
-            PublicAccessLevel::EditAccess => matches!(required_permission, "view" | "comment" | "edit"),
-        }
+    /// What `user_id` may do with the item through its own grants and those
+    /// of the folders holding it. `ancestors` are those folders, nearest
+    /// first; for a folder item the folder itself comes first. Expired
+    /// shares don't count.
+    pub fn effective_access(&self, user_id: Id, ancestors: &[Folder]) -> EffectiveAccess {
+        self.permissions.effective_access(self.id, user_id, ancestors)
     }
 
     pub fn move_to_trash(&mut self) {
@@ -473,6 +762,131 @@ impl DriveItem {
     }
 }
 
+impl ItemPermissions {
+    /// Access to the item these permissions belong to; see
+    /// [`DriveItem::effective_access`]
+    pub fn effective_access(&self, item_id: Id, user_id: Id, ancestors: &[Folder]) -> EffectiveAccess {
+        let now = chrono::Utc::now();
+        let mut access = EffectiveAccess {
+            item_id,
+            user_id,
+            permissions: SharingPermissions::none(),
+            grants: Vec::new(),
+        };
+
+        if self.owner_id == user_id {
+            access.add(AccessGrant {
+                source: AccessSource::Owner,
+                role: Some(ShareRole::Owner),
+                permissions: ShareRole::Owner.permissions(),
+                granted_by: None,
+                expires_at: None,
+                inherited_from: None,
+            });
+        }
+        for share in &self.shared_with {
+            if share.user_id == user_id && share.expires_at.is_none_or(|expires_at| expires_at > now) {
+                access.add(AccessGrant {
+                    source: AccessSource::Share,
+                    role: Some(share.role.clone()),
+                    permissions: share.permissions.clone(),
+                    granted_by: Some(share.granted_by),
+                    expires_at: share.expires_at,
+                    inherited_from: None,
+                });
+            }
+        }
+        if let Some(permissions) = self.public_access.permissions() {
+            access.add(AccessGrant {
+                source: AccessSource::PublicAccess,
+                role: None,
+                permissions,
+                granted_by: None,
+                expires_at: None,
+                inherited_from: None,
+            });
+        }
+
+        for folder in ancestors {
+            // A folder's own shares are its item's shares, counted above
+            if folder.id != item_id {
+                access.add_folder_grants(folder, user_id, now);
+            }
+            if !folder.permissions.inherit_permissions {
+                break;
+            }
+        }
+        access
+    }
+}
+
+impl EffectiveAccess {
+    fn add_folder_grants(&mut self, folder: &Folder, user_id: Id, now: Timestamp) {
+        let inherited_from = InheritedFrom {
+            folder_id: folder.id,
+            path: folder.path.clone(),
+        };
+
+        if folder.permissions.owner_id == user_id {
+            self.add(AccessGrant {
+                source: AccessSource::Owner,
+                role: Some(ShareRole::Owner),
+                permissions: ShareRole::Owner.permissions(),
+                granted_by: None,
+                expires_at: None,
+                inherited_from: Some(inherited_from.clone()),
+            });
+        }
+        for share in &folder.permissions.shared_with {
+            if share.user_id == user_id && share.expires_at.is_none_or(|expires_at| expires_at > now) {
+                self.add(AccessGrant {
+                    source: AccessSource::Share,
+                    role: Some(share.role.clone()),
+                    permissions: share.permissions.clone(),
+                    granted_by: Some(share.granted_by),
+                    expires_at: share.expires_at,
+                    inherited_from: Some(inherited_from.clone()),
+                });
+            }
+        }
+        if let Some(permissions) = folder.permissions.public_access.permissions() {
+            self.add(AccessGrant {
+                source: AccessSource::PublicAccess,
+                role: None,
+                permissions,
+                granted_by: None,
+                expires_at: None,
+                inherited_from: Some(inherited_from),
+            });
+        }
+    }
+}
+
+impl DriveItemResponse {
+    pub fn from_item(item: DriveItem, owner: ItemOwner) -> Self {
+        let mut metadata = item.metadata;
+        if metadata.thumbnail_url.is_none() && item.item_type == DriveItemType::File {
+            metadata.thumbnail_url = super::thumbnail_url(item.id, &item.mime_type);
+        }
+
+        Self {
+            id: item.id,
+            name: item.name,
+            item_type: item.item_type,
+            mime_type: item.mime_type,
+            size: item.size,
+            path: item.path,
+            is_starred: item.is_starred,
+            is_trashed: item.is_trashed,
+            permissions: item.permissions,
+            metadata,
+            created_at: item.created_at,
+            updated_at: item.updated_at,
+            owner,
+        }
+    }
+}
+
 impl Default for DriveSettings {
     fn default() -> Self {
         Self {
@@ -1,7 +1,41 @@
-use kingshare_core::{Id, Timestamp};
+use kingshare_core::{Error, Id, Result, Timestamp};
 use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 use validator::Validate;
 
+/// Outcome of the malware scan that runs after every upload
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScanStatus {
+    #[default]
+    Pending,
+    Clean,
+    Infected,
+}
+
+impl ScanStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Clean => "clean",
+            Self::Infected => "infected",
+        }
+    }
+}
+
+impl FromStr for ScanStatus {
+    type Err = Error;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value {
+            "pending" => Ok(Self::Pending),
+            "clean" => Ok(Self::Clean),
+            "infected" => Ok(Self::Infected),
+            other => Err(Error::Internal(format!("Unknown scan status '{}'", other))),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct File {
     pub id: Id,
@@ -14,6 +48,9 @@ pub struct File {
     pub checksum: String,
     pub is_public: bool,
     pub download_count: i64,
+    pub scan_status: ScanStatus,
+    pub scan_signature: Option<String>, // Name of the detected malware, if any
+    pub scanned_at: Option<Timestamp>,
     pub created_at: Timestamp,
     pub updated_at: Timestamp,
     pub expires_at: Option<Timestamp>,
@@ -52,6 +89,7 @@ pub struct FileMetadata {
     pub size: i64,
     pub is_public: bool,
     pub download_count: i64,
+    pub scan_status: ScanStatus,
     pub created_at: Timestamp,
     pub expires_at: Option<Timestamp>,
     pub owner: FileOwner,
@@ -86,6 +124,9 @@ impl File {
             checksum,
             is_public: false,
             download_count: 0,
+            scan_status: ScanStatus::Pending,
+            scan_signature: None,
+            scanned_at: None,
             created_at: now,
             updated_at: now,
             expires_at: None,
@@ -100,6 +141,19 @@ impl File {
         }
     }
 
+    /// Content can only be handed out once the malware scan has passed
+    pub fn ensure_scan_passed(&self) -> Result<()> {
+        match self.scan_status {
+            ScanStatus::Clean => Ok(()),
+            ScanStatus::Pending => Err(Error::Conflict(
+                "File is still being scanned for malware, try again shortly".to_string(),
+            )),
+            ScanStatus::Infected => Err(Error::Authorization(
+                "File has been quarantined because malware was detected".to_string(),
+            )),
+        }
+    }
+
     pub fn increment_download_count(&mut self) {
         self.download_count += 1;
         self.updated_at = chrono::Utc::now();
@@ -0,0 +1,159 @@
+use chrono::Duration;
+use kingshare_core::{Error, Id, Timestamp};
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+/// What failed attempts are counted against
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AttemptKind {
+    /// Logins for one email address, whether or not an account has it
+    Account,
+    /// Password guesses on one share
+    Share,
+    /// Logins and share passwords from one client address
+    Ip,
+}
+
+impl AttemptKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Account => "account",
+            Self::Share => "share",
+            Self::Ip => "ip",
+        }
+    }
+}
+
+impl fmt::Display for AttemptKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for AttemptKind {
+    type Err = Error;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value {
+            "account" => Ok(Self::Account),
+            "share" => Ok(Self::Share),
+            "ip" => Ok(Self::Ip),
+            _ => Err(Error::Validation(format!("Unknown attempt kind '{}'", value))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct AttemptKey {
+    pub kind: AttemptKind,
+    pub subject: String,
+}
+
+impl AttemptKey {
+    /// Emails are compared the way logins look them up
+    pub fn account(email: &str) -> Self {
+        Self {
+            kind: AttemptKind::Account,
+            subject: email.trim().to_lowercase(),
+        }
+    }
+
+    pub fn share(share_id: Id) -> Self {
+        Self {
+            kind: AttemptKind::Share,
+            subject: share_id.to_string(),
+        }
+    }
+
+    pub fn ip(address: &str) -> Self {
+        Self {
+            kind: AttemptKind::Ip,
+            subject: address.trim().to_string(),
+        }
+    }
+}
+
+impl fmt::Display for AttemptKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.kind, self.subject)
+    }
+}
+
+/// Recent failures against one key and how long it must wait
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FailedAttempts {
+    pub key: AttemptKey,
+    pub failures: u32,
+    pub last_failure_at: Timestamp,
+    pub blocked_until: Option<Timestamp>,
+    /// Set when the block is a lockout rather than a backoff delay
+    pub locked: bool,
+}
+
+impl FailedAttempts {
+    /// Whole seconds until the key may try again, if it is blocked
+    pub fn retry_after(&self, now: Timestamp) -> Option<u64> {
+        let remaining = self.blocked_until? - now;
+        if remaining <= Duration::zero() {
+            return None;
+        }
+        // Round up so clients don't retry a moment too early
+        Some(((remaining.num_milliseconds() + 999) / 1000) as u64)
+    }
+}
+
+/// How many failures a key gets and how it is slowed down after that
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AttemptLimits {
+    pub free_attempts: u32,
+    pub lockout_threshold: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LockoutPolicy {
+    pub account: AttemptLimits,
+    pub ip: AttemptLimits,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    pub lockout_duration: Duration,
+    /// Failures further apart than this don't add up
+    pub attempt_window: Duration,
+}
+
+/// What a key has to wait after its latest failure
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttemptBlock {
+    Backoff(Duration),
+    Lockout(Duration),
+}
+
+impl LockoutPolicy {
+    pub fn limits(&self, kind: AttemptKind) -> AttemptLimits {
+        match kind {
+            AttemptKind::Account | AttemptKind::Share => self.account,
+            AttemptKind::Ip => self.ip,
+        }
+    }
+
+    /// The wait after `failures` consecutive failures: none for the free
+    /// attempts, then a delay doubling each time up to `max_delay`, and a
+    /// lockout from the threshold on
+    pub fn block_after(&self, kind: AttemptKind, failures: u32) -> Option<AttemptBlock> {
+        let limits = self.limits(kind);
+        if failures >= limits.lockout_threshold {
+            return Some(AttemptBlock::Lockout(self.lockout_duration));
+        }
+        if failures <= limits.free_attempts {
+            return None;
+        }
+
+        let doublings = (failures - limits.free_attempts - 1).min(30);
+        let delay = self
+            .base_delay
+            .checked_mul(1 << doublings)
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay);
+        Some(AttemptBlock::Backoff(delay))
+    }
+}
@@ -0,0 +1,81 @@
+use kingshare_core::{Id, Timestamp};
+use serde::{Deserialize, Serialize};
+
+/// Recovery codes handed out when 2FA is confirmed or the codes are regenerated
+pub const RECOVERY_CODE_COUNT: usize = 10;
+
+/// A user's TOTP secret. It only guards logins once a code from it has been confirmed.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TotpEnrollment {
+    pub user_id: Id,
+    /// Base32, as entered into authenticator apps
+    pub secret: String,
+    pub created_at: Timestamp,
+    pub confirmed_at: Option<Timestamp>,
+    /// Highest time step accepted so far, so a code can't be replayed
+    pub last_used_step: Option<i64>,
+}
+
+impl TotpEnrollment {
+    pub fn new(user_id: Id, secret: String) -> Self {
+        Self {
+            user_id,
+            secret,
+            created_at: chrono::Utc::now(),
+            confirmed_at: None,
+            last_used_step: None,
+        }
+    }
+
+    pub fn is_confirmed(&self) -> bool {
+        self.confirmed_at.is_some()
+    }
+}
+
+/// A single-use recovery code; only its hash is stored
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RecoveryCode {
+    pub id: Id,
+    pub user_id: Id,
+    pub code_hash: String,
+    pub created_at: Timestamp,
+    pub used_at: Option<Timestamp>,
+}
+
+impl RecoveryCode {
+    pub fn new(user_id: Id, code_hash: String) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4(),
+            user_id,
+            code_hash,
+            created_at: chrono::Utc::now(),
+            used_at: None,
+        }
+    }
+}
+
+/// What a user needs to add the account to an authenticator app
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TotpSetup {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MfaStatus {
+    pub enabled: bool,
+    /// Whether the user's role must have 2FA enabled
+    pub required: bool,
+    pub recovery_codes_remaining: u64,
+}
+
+/// The user a pending two-step login belongs to
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MfaChallenge {
+    pub user_id: Id,
+    /// Token ID, denied once the challenge has been answered
+    pub challenge_id: Id,
+    /// The user has no 2FA yet but their role requires it; they enroll during login
+    pub enrollment_required: bool,
+    pub expires_at: Timestamp,
+}
@@ -7,6 +7,20 @@ pub mod drive;
 pub mod collaboration;
 pub mod spreadsheet;
 pub mod forms;
+pub mod upload;
+pub mod blob;
+pub mod thumbnail;
+pub mod quota;
+pub mod token;
+pub mod mfa;
+pub mod account_token;
+pub mod oidc;
+pub mod api_token;
+pub mod lockout;
+pub mod security_event;
+pub mod role;
+pub mod team;
+pub mod search_query;
 
 pub use user::*;
 pub use file::*;
@@ -16,4 +30,18 @@ pub use document::*;
 pub use drive::*;
 pub use collaboration::*;
 pub use spreadsheet::*;
-pub use forms::*;
\ No newline at end of file
+pub use forms::*;
+pub use upload::*;
+pub use blob::*;
+pub use thumbnail::*;
+pub use quota::*;
+pub use token::*;
+pub use mfa::*;
+pub use account_token::*;
+pub use oidc::*;
+pub use api_token::*;
+pub use lockout::*;
+pub use security_event::*;
+pub use role::*;
+pub use team::*;
+pub use search_query::*;
\ No newline at end of file
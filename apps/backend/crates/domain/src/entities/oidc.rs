@@ -0,0 +1,96 @@
+use crate::entities::UserRole;
+use kingshare_core::{Id, Timestamp};
+use serde::{Deserialize, Serialize};
+
+/// An account at an identity provider, linked to the local user it signs in as
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UserIdentity {
+    pub id: Id,
+    pub user_id: Id,
+    pub issuer: String,
+    /// The IdP's stable identifier for the account (`sub`)
+    pub subject: String,
+    /// Address the IdP reported at the latest login
+    pub email: Option<String>,
+    pub created_at: Timestamp,
+    pub last_login_at: Option<Timestamp>,
+}
+
+impl UserIdentity {
+    pub fn new(user_id: Id, issuer: String, subject: String, email: Option<String>) -> Self {
+        let now = chrono::Utc::now();
+        Self {
+            id: uuid::Uuid::new_v4(),
+            user_id,
+            issuer,
+            subject,
+            email,
+            created_at: now,
+            last_login_at: Some(now),
+        }
+    }
+}
+
+/// Claims of a validated ID token
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct OidcIdentity {
+    pub issuer: String,
+    pub subject: String,
+    pub email: Option<String>,
+    pub email_verified: bool,
+    pub given_name: Option<String>,
+    pub family_name: Option<String>,
+    pub name: Option<String>,
+    pub preferred_username: Option<String>,
+    pub groups: Vec<String>,
+}
+
+/// A login waiting for the IdP to redirect back. The state travels through
+/// the browser; the nonce and PKCE verifier never leave the server.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OidcLoginState {
+    pub state: String,
+    pub nonce: String,
+    pub code_verifier: String,
+    pub expires_at: Timestamp,
+}
+
+impl OidcLoginState {
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= chrono::Utc::now()
+    }
+}
+
+/// Where to send the browser to start a login, and what to remember until it returns
+#[derive(Debug, Clone, PartialEq)]
+pub struct OidcAuthorization {
+    pub authorization_url: String,
+    pub login: OidcLoginState,
+}
+
+/// Which role IdP group membership grants. Admin membership wins over guest;
+/// members of neither are regular users.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OidcRoleMapping {
+    pub admin_groups: Vec<String>,
+    pub guest_groups: Vec<String>,
+}
+
+impl OidcRoleMapping {
+    /// The role for someone in `groups`, or `None` when no groups are mapped
+    /// and roles are managed locally
+    pub fn role_for(&self, groups: &[String]) -> Option<UserRole> {
+        if self.admin_groups.is_empty() && self.guest_groups.is_empty() {
+            return None;
+        }
+
+        let member_of = |mapped: &[String]| groups.iter().any(|group| mapped.contains(group));
+        Some(if member_of(&self.admin_groups) {
+            UserRole::Admin
+        } else if member_of(&self.guest_groups) {
+            UserRole::Guest
+        } else {
+            UserRole::User
+        })
+    }
+}
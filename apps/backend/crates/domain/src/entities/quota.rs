@@ -0,0 +1,66 @@
+use kingshare_core::Id;
+use serde::{Deserialize, Serialize};
+
+/// What a storage reservation is charged against
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "type", content = "id", rename_all = "lowercase")]
+pub enum QuotaScope {
+    User(Id),
+    Drive(Id),
+}
+
+impl QuotaScope {
+    pub fn id(&self) -> Id {
+        match self {
+            QuotaScope::User(id) | QuotaScope::Drive(id) => *id,
+        }
+    }
+
+    pub fn describe(&self) -> &'static str {
+        match self {
+            QuotaScope::User(_) => "user",
+            QuotaScope::Drive(_) => "drive",
+        }
+    }
+}
+
+/// Storage used against a quota. `quota` is `None` when the scope is unlimited.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct QuotaUsage {
+    pub scope: QuotaScope,
+    /// User notified about this scope's usage; the drive owner for drives
+    pub owner_id: Id,
+    pub used: i64,
+    pub quota: Option<i64>,
+}
+
+impl QuotaUsage {
+    pub fn available(&self) -> Option<i64> {
+        self.quota.map(|quota| (quota - self.used).max(0))
+    }
+
+    pub fn usage_percentage(&self) -> Option<f64> {
+        match self.quota {
+            Some(0) => Some(100.0),
+            Some(quota) => Some(self.used as f64 / quota as f64 * 100.0),
+            None => None,
+        }
+    }
+
+    /// Whether growing by `added` bytes took usage from below `percent` of the
+    /// quota to at or above it
+    pub fn crossed_soft_limit(&self, added: i64, percent: u8) -> bool {
+        let Some(quota) = self.quota else {
+            return false;
+        };
+        let threshold = quota as i128 * percent as i128 / 100;
+        let before = (self.used - added) as i128;
+        before < threshold && self.used as i128 >= threshold
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetQuotaRequest {
+    /// Bytes; `null` falls back to the configured default for users
+    pub quota: Option<i64>,
+}
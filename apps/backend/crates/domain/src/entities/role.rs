@@ -0,0 +1,305 @@
+use kingshare_core::{Error, Id, Timestamp};
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+use validator::Validate;
+
+/// Names of the roles every installation has. The first three back the
+/// account roles: admins hold `admin` everywhere, users and guests hold
+/// `user` and `guest` on what they own.
+pub const ADMIN_ROLE: &str = "admin";
+pub const USER_ROLE: &str = "user";
+pub const GUEST_ROLE: &str = "guest";
+/// Given to the members of a new team drive
+pub const EDITOR_ROLE: &str = "editor";
+
+/// Something a role allows. Permissions don't imply each other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Permission {
+    #[serde(rename = "files:read")]
+    FilesRead,
+    #[serde(rename = "files:write")]
+    FilesWrite,
+    #[serde(rename = "shares:manage")]
+    SharesManage,
+    #[serde(rename = "drive:read")]
+    DriveRead,
+    #[serde(rename = "drive:comment")]
+    DriveComment,
+    #[serde(rename = "drive:write")]
+    DriveWrite,
+    #[serde(rename = "drive:share")]
+    DriveShare,
+    #[serde(rename = "drive:manage")]
+    DriveManage,
+    #[serde(rename = "documents:read")]
+    DocumentsRead,
+    #[serde(rename = "documents:write")]
+    DocumentsWrite,
+    #[serde(rename = "spreadsheets:read")]
+    SpreadsheetsRead,
+    #[serde(rename = "spreadsheets:write")]
+    SpreadsheetsWrite,
+    #[serde(rename = "forms:read")]
+    FormsRead,
+    #[serde(rename = "forms:write")]
+    FormsWrite,
+    #[serde(rename = "forms:responses:read")]
+    FormsResponsesRead,
+    #[serde(rename = "users:manage")]
+    UsersManage,
+    #[serde(rename = "roles:manage")]
+    RolesManage,
+    #[serde(rename = "quotas:manage")]
+    QuotasManage,
+    #[serde(rename = "storage:manage")]
+    StorageManage,
+    #[serde(rename = "security:manage")]
+    SecurityManage,
+}
+
+impl Permission {
+    pub const ALL: [Permission; 20] = [
+        Self::FilesRead,
+        Self::FilesWrite,
+        Self::SharesManage,
+        Self::DriveRead,
+        Self::DriveComment,
+        Self::DriveWrite,
+        Self::DriveShare,
+        Self::DriveManage,
+        Self::DocumentsRead,
+        Self::DocumentsWrite,
+        Self::SpreadsheetsRead,
+        Self::SpreadsheetsWrite,
+        Self::FormsRead,
+        Self::FormsWrite,
+        Self::FormsResponsesRead,
+        Self::UsersManage,
+        Self::RolesManage,
+        Self::QuotasManage,
+        Self::StorageManage,
+        Self::SecurityManage,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::FilesRead => "files:read",
+            Self::FilesWrite => "files:write",
+            Self::SharesManage => "shares:manage",
+            Self::DriveRead => "drive:read",
+            Self::DriveComment => "drive:comment",
+            Self::DriveWrite => "drive:write",
+            Self::DriveShare => "drive:share",
+            Self::DriveManage => "drive:manage",
+            Self::DocumentsRead => "documents:read",
+            Self::DocumentsWrite => "documents:write",
+            Self::SpreadsheetsRead => "spreadsheets:read",
+            Self::SpreadsheetsWrite => "spreadsheets:write",
+            Self::FormsRead => "forms:read",
+            Self::FormsWrite => "forms:write",
+            Self::FormsResponsesRead => "forms:responses:read",
+            Self::UsersManage => "users:manage",
+            Self::RolesManage => "roles:manage",
+            Self::QuotasManage => "quotas:manage",
+            Self::StorageManage => "storage:manage",
+            Self::SecurityManage => "security:manage",
+        }
+    }
+
+    /// Permissions over the installation itself rather than over content.
+    /// They only count when granted globally.
+    pub fn is_administrative(&self) -> bool {
+        matches!(
+            self,
+            Self::UsersManage | Self::RolesManage | Self::QuotasManage | Self::StorageManage | Self::SecurityManage
+        )
+    }
+}
+
+impl fmt::Display for Permission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Permission {
+    type Err = Error;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|permission| permission.as_str() == value)
+            .ok_or_else(|| Error::Validation(format!("Unknown permission '{}'", value)))
+    }
+}
+
+/// A named set of permissions
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Role {
+    pub id: Id,
+    pub name: String,
+    pub description: Option<String>,
+    pub permissions: Vec<Permission>,
+    /// Built-in roles can't be changed or deleted
+    pub is_system: bool,
+    pub created_at: Timestamp,
+    pub updated_at: Timestamp,
+}
+
+impl Role {
+    pub fn new(name: String, description: Option<String>, permissions: Vec<Permission>) -> Self {
+        let now = chrono::Utc::now();
+        Self {
+            id: uuid::Uuid::new_v4(),
+            name,
+            description,
+            permissions: normalize_permissions(permissions),
+            is_system: false,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    pub fn grants(&self, permission: Permission) -> bool {
+        self.permissions.contains(&permission)
+    }
+}
+
+/// Sorted and without duplicates, the way roles store them
+pub fn normalize_permissions(mut permissions: Vec<Permission>) -> Vec<Permission> {
+    permissions.sort();
+    permissions.dedup();
+    permissions
+}
+
+/// Where a role assignment applies
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "type", content = "id", rename_all = "snake_case")]
+pub enum RoleScope {
+    Global,
+    Drive(Id),
+    Team(Id),
+}
+
+impl RoleScope {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Global => "global",
+            Self::Drive(_) => "drive",
+            Self::Team(_) => "team",
+        }
+    }
+
+    pub fn id(&self) -> Option<Id> {
+        match self {
+            Self::Global => None,
+            Self::Drive(id) | Self::Team(id) => Some(*id),
+        }
+    }
+
+    /// Rebuild a scope from its stored kind and id
+    pub fn from_parts(kind: &str, id: Option<Id>) -> Result<Self, Error> {
+        match (kind, id) {
+            ("global", None) => Ok(Self::Global),
+            ("drive", Some(id)) => Ok(Self::Drive(id)),
+            ("team", Some(id)) => Ok(Self::Team(id)),
+            _ => Err(Error::Validation(format!("Invalid role scope '{}'", kind))),
+        }
+    }
+
+    /// Whether an assignment with this scope covers `resource`
+    pub fn covers(&self, resource: &Resource, drive_team_id: Option<Id>) -> bool {
+        match self {
+            Self::Global => true,
+            Self::Drive(id) => resource.drive_id == Some(*id),
+            Self::Team(id) => resource.team_id == Some(*id) || drive_team_id == Some(*id),
+        }
+    }
+}
+
+impl fmt::Display for RoleScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.id() {
+            Some(id) => write!(f, "{}:{}", self.kind(), id),
+            None => f.write_str(self.kind()),
+        }
+    }
+}
+
+/// A role held by a user within a scope
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RoleAssignment {
+    pub id: Id,
+    pub user_id: Id,
+    pub role_id: Id,
+    pub scope: RoleScope,
+    pub granted_by: Option<Id>,
+    pub created_at: Timestamp,
+}
+
+impl RoleAssignment {
+    pub fn new(user_id: Id, role_id: Id, scope: RoleScope, granted_by: Option<Id>) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4(),
+            user_id,
+            role_id,
+            scope,
+            granted_by,
+            created_at: chrono::Utc::now(),
+        }
+    }
+}
+
+/// What an authorization check is about: who owns it and where it lives.
+/// Administrative checks use `Resource::global()`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Resource {
+    pub owner_id: Option<Id>,
+    pub drive_id: Option<Id>,
+    pub team_id: Option<Id>,
+}
+
+impl Resource {
+    pub fn global() -> Self {
+        Self::default()
+    }
+
+    pub fn owned_by(owner_id: Id) -> Self {
+        Self {
+            owner_id: Some(owner_id),
+            ..Self::default()
+        }
+    }
+
+    pub fn in_drive(mut self, drive_id: Id) -> Self {
+        self.drive_id = Some(drive_id);
+        self
+    }
+
+    pub fn in_team(mut self, team_id: Id) -> Self {
+        self.team_id = Some(team_id);
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct CreateRoleRequest {
+    #[validate(length(min = 1, max = 64))]
+    pub name: String,
+    #[validate(length(max = 500))]
+    pub description: Option<String>,
+    pub permissions: Vec<Permission>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct UpdateRoleRequest {
+    #[validate(length(max = 500))]
+    pub description: Option<String>,
+    pub permissions: Option<Vec<Permission>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssignRoleRequest {
+    pub role_id: Id,
+    pub scope: RoleScope,
+}
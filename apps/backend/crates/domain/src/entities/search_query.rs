@@ -0,0 +1,294 @@
+use super::DriveItemType;
+use chrono::{Days, NaiveDate, NaiveTime, TimeZone, Utc};
+use kingshare_core::{Error, Id, Result, Timestamp};
+
+const OPERATORS: [&str; 5] = ["type", "owner", "modified", "tag", "in"];
+
+/// Operator names of the item types, as in `type:spreadsheet`
+const ITEM_TYPES: [(&str, DriveItemType); 8] = [
+    ("file", DriveItemType::File),
+    ("folder", DriveItemType::Folder),
+    ("document", DriveItemType::Document),
+    ("spreadsheet", DriveItemType::Spreadsheet),
+    ("presentation", DriveItemType::Presentation),
+    ("form", DriveItemType::Form),
+    ("drawing", DriveItemType::Drawing),
+    ("shortcut", DriveItemType::Shortcut),
+];
+
+/// A search written in the drive query language, such as
+/// `type:spreadsheet owner:me modified:>2026-01-01 tag:finance "exact phrase" -draft in:<folder-id>`.
+///
+/// Every term has to match, and a leading `-` excludes what a term matches
+/// instead. Repeating `type:`, `owner:` or `in:` matches any of the values.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DriveQuery {
+    pub terms: Vec<QueryTerm>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryTerm {
+    pub kind: TermKind,
+    pub negated: bool,
+    /// Where the term starts in the query, counting characters from 1
+    pub position: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TermKind {
+    /// Matched with stemming against names, tags, descriptions and contents
+    Word(String),
+    /// Matched as the same words in the same order
+    Phrase(String),
+    Type(DriveItemType),
+    Owner(OwnerRef),
+    Modified(DateComparison, NaiveDate),
+    /// Compared without regard to case
+    Tag(String),
+    /// Anywhere below a folder
+    In(Id),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OwnerRef {
+    /// Whoever is searching
+    Me,
+    User(Id),
+}
+
+/// How `modified:` compares, from the `<`, `<=`, `=`, `>=` or `>` before the date
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateComparison {
+    Before,
+    OnOrBefore,
+    On,
+    OnOrAfter,
+    After,
+}
+
+impl DriveQuery {
+    /// Syntax errors are `Error::Validation` naming where in the query they are
+    pub fn parse(input: &str) -> Result<Self> {
+        let mut parser = Parser { chars: input.chars().collect(), at: 0 };
+        let mut terms = Vec::new();
+        while let Some(term) = parser.next_term()? {
+            terms.push(term);
+        }
+        Ok(Self { terms })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// What `pick` takes from the included terms, or from the excluded ones
+    pub fn values<'a, T>(&'a self, negated: bool, pick: impl Fn(&'a TermKind) -> Option<T>) -> Vec<T> {
+        self.terms
+            .iter()
+            .filter(|term| term.negated == negated)
+            .filter_map(|term| pick(&term.kind))
+            .collect()
+    }
+
+    /// The owners to match or to leave out, with `me` standing for `user_id`
+    pub fn owner_ids(&self, negated: bool, user_id: Id) -> Vec<Id> {
+        self.values(negated, |kind| match kind {
+            TermKind::Owner(OwnerRef::Me) => Some(user_id),
+            TermKind::Owner(OwnerRef::User(owner_id)) => Some(*owner_id),
+            _ => None,
+        })
+    }
+
+    /// The span every `modified:` term allows, as an inclusive start and an
+    /// exclusive end. Dates are whole days in UTC.
+    pub fn modified_range(&self) -> (Option<Timestamp>, Option<Timestamp>) {
+        let mut range: (Option<Timestamp>, Option<Timestamp>) = (None, None);
+        for term in &self.terms {
+            let TermKind::Modified(comparison, date) = &term.kind else {
+                continue;
+            };
+            let comparison = if term.negated { comparison.negate() } else { *comparison };
+            let (start, end) = comparison.range(*date);
+            range.0 = range.0.max(start);
+            range.1 = match (range.1, end) {
+                (Some(current), Some(end)) => Some(current.min(end)),
+                (current, end) => current.or(end),
+            };
+        }
+        range
+    }
+}
+
+impl DateComparison {
+    /// The comparison matching every other day. `On` has none and is kept.
+    pub fn negate(self) -> Self {
+        match self {
+            Self::Before => Self::OnOrAfter,
+            Self::OnOrBefore => Self::After,
+            Self::On => Self::On,
+            Self::OnOrAfter => Self::Before,
+            Self::After => Self::OnOrBefore,
+        }
+    }
+
+    fn range(self, date: NaiveDate) -> (Option<Timestamp>, Option<Timestamp>) {
+        let start = start_of_day(date);
+        let next = start_of_day(date + Days::new(1));
+        match self {
+            Self::Before => (None, Some(start)),
+            Self::OnOrBefore => (None, Some(next)),
+            Self::On => (Some(start), Some(next)),
+            Self::OnOrAfter => (Some(start), None),
+            Self::After => (Some(next), None),
+        }
+    }
+}
+
+fn start_of_day(date: NaiveDate) -> Timestamp {
+    Utc.from_utc_datetime(&date.and_time(NaiveTime::MIN))
+}
+
+struct Parser {
+    chars: Vec<char>,
+    at: usize,
+}
+
+impl Parser {
+    fn next_term(&mut self) -> Result<Option<QueryTerm>> {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.at += 1;
+        }
+        let Some(first) = self.peek() else {
+            return Ok(None);
+        };
+
+        let start = self.at;
+        let negated = first == '-';
+        if negated {
+            self.at += 1;
+            if self.peek().is_none_or(char::is_whitespace) {
+                return Err(syntax_error("Expected a term after '-'", start));
+            }
+        }
+
+        let kind = if self.peek() == Some('"') {
+            TermKind::Phrase(self.quoted()?)
+        } else if let Some(operator) = self.operator() {
+            self.operator_term(&operator, negated)?
+        } else {
+            TermKind::Word(self.bare()?)
+        };
+        Ok(Some(QueryTerm { kind, negated, position: start + 1 }))
+    }
+
+    /// The operator name before a `:`, consuming it and the colon. Other
+    /// names, as in `re:budget` or `http://`, are left to be read as words.
+    fn operator(&mut self) -> Option<String> {
+        let length = self.chars[self.at..].iter().take_while(|c| c.is_ascii_alphabetic()).count();
+        if length == 0 || self.chars.get(self.at + length) != Some(&':') {
+            return None;
+        }
+        let name = self.chars[self.at..self.at + length].iter().collect::<String>().to_lowercase();
+        if !OPERATORS.contains(&name.as_str()) {
+            return None;
+        }
+        self.at += length + 1;
+        Some(name)
+    }
+
+    fn operator_term(&mut self, operator: &str, negated: bool) -> Result<TermKind> {
+        let value_at = self.at;
+        let value = match self.peek() {
+            Some('"') => self.quoted()?,
+            Some(c) if !c.is_whitespace() => self.bare()?,
+            _ => return Err(syntax_error(&format!("Expected a value after '{}:'", operator), value_at)),
+        };
+
+        match operator {
+            "type" => {
+                let lowered = value.to_lowercase();
+                ITEM_TYPES
+                    .iter()
+                    .find(|(name, _)| *name == lowered)
+                    .map(|(_, item_type)| TermKind::Type(item_type.clone()))
+                    .ok_or_else(|| {
+                        let names: Vec<&str> = ITEM_TYPES.iter().map(|(name, _)| *name).collect();
+                        syntax_error(
+                            &format!("Unknown type '{}', expected one of {}", value, names.join(", ")),
+                            value_at,
+                        )
+                    })
+            }
+            "owner" if value.eq_ignore_ascii_case("me") => Ok(TermKind::Owner(OwnerRef::Me)),
+            "owner" => Id::parse_str(&value)
+                .map(|owner_id| TermKind::Owner(OwnerRef::User(owner_id)))
+                .map_err(|_| syntax_error("Expected 'me' or a user id", value_at)),
+            "modified" => {
+                let (comparison, date) = parse_date_comparison(&value)
+                    .ok_or_else(|| syntax_error("Expected a date such as '>2026-01-01'", value_at))?;
+                if negated && comparison == DateComparison::On {
+                    return Err(syntax_error("Excluding a date needs '<', '<=', '>=' or '>'", value_at));
+                }
+                Ok(TermKind::Modified(comparison, date))
+            }
+            "tag" => Ok(TermKind::Tag(value.to_lowercase())),
+            _ => Id::parse_str(&value)
+                .map(TermKind::In)
+                .map_err(|_| syntax_error("Expected a folder id", value_at)),
+        }
+    }
+
+    /// Text up to the closing quote, consuming both quotes
+    fn quoted(&mut self) -> Result<String> {
+        let open = self.at;
+        let length = self.chars[open + 1..]
+            .iter()
+            .position(|&c| c == '"')
+            .ok_or_else(|| syntax_error("Unclosed quote", open))?;
+        let text: String = self.chars[open + 1..open + 1 + length].iter().collect();
+        self.at = open + length + 2;
+        if self.peek().is_some_and(|c| !c.is_whitespace()) {
+            return Err(syntax_error("Expected a space after the closing quote", self.at));
+        }
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        if text.is_empty() {
+            return Err(syntax_error("Empty quotes", open));
+        }
+        Ok(text)
+    }
+
+    /// Text up to the next space
+    fn bare(&mut self) -> Result<String> {
+        let start = self.at;
+        while let Some(c) = self.peek().filter(|c| !c.is_whitespace()) {
+            if c == '"' {
+                return Err(syntax_error("Quotes can only start a term", self.at));
+            }
+            self.at += 1;
+        }
+        Ok(self.chars[start..self.at].iter().collect())
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.at).copied()
+    }
+}
+
+fn parse_date_comparison(value: &str) -> Option<(DateComparison, NaiveDate)> {
+    let (comparison, date) = [
+        ("<=", DateComparison::OnOrBefore),
+        (">=", DateComparison::OnOrAfter),
+        ("<", DateComparison::Before),
+        (">", DateComparison::After),
+        ("=", DateComparison::On),
+    ]
+    .into_iter()
+    .find_map(|(symbol, comparison)| Some((comparison, value.strip_prefix(symbol)?)))
+    .unwrap_or((DateComparison::On, value));
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").ok().map(|date| (comparison, date))
+}
+
+/// `at` counts characters from 0
+fn syntax_error(message: &str, at: usize) -> Error {
+    Error::Validation(format!("{} at position {}", message, at + 1))
+}
@@ -0,0 +1,106 @@
+use kingshare_core::{Error, Id, Timestamp};
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+/// Security-relevant events kept for administrators to review
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SecurityEventType {
+    /// Too many failed attempts locked an account, share or address
+    LockoutStarted,
+    /// An administrator lifted a lockout
+    LockoutCleared,
+    /// A user was given a role
+    RoleAssigned,
+    /// A role assignment was taken away
+    RoleRevoked,
+    /// An administrator changed a user's account role
+    UserRoleChanged,
+    UserDeactivated,
+    UserReactivated,
+    /// An administrator made a user choose a new password
+    PasswordResetForced,
+    /// An administrator signed in as another user
+    UserImpersonated,
+    /// A user's files and drives were handed to another user
+    OwnershipTransferred,
+    UserDeleted,
+}
+
+impl SecurityEventType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::LockoutStarted => "lockout_started",
+            Self::LockoutCleared => "lockout_cleared",
+            Self::RoleAssigned => "role_assigned",
+            Self::RoleRevoked => "role_revoked",
+            Self::UserRoleChanged => "user_role_changed",
+            Self::UserDeactivated => "user_deactivated",
+            Self::UserReactivated => "user_reactivated",
+            Self::PasswordResetForced => "password_reset_forced",
+            Self::UserImpersonated => "user_impersonated",
+            Self::OwnershipTransferred => "ownership_transferred",
+            Self::UserDeleted => "user_deleted",
+        }
+    }
+}
+
+impl fmt::Display for SecurityEventType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for SecurityEventType {
+    type Err = Error;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value {
+            "lockout_started" => Ok(Self::LockoutStarted),
+            "lockout_cleared" => Ok(Self::LockoutCleared),
+            "role_assigned" => Ok(Self::RoleAssigned),
+            "role_revoked" => Ok(Self::RoleRevoked),
+            "user_role_changed" => Ok(Self::UserRoleChanged),
+            "user_deactivated" => Ok(Self::UserDeactivated),
+            "user_reactivated" => Ok(Self::UserReactivated),
+            "password_reset_forced" => Ok(Self::PasswordResetForced),
+            "user_impersonated" => Ok(Self::UserImpersonated),
+            "ownership_transferred" => Ok(Self::OwnershipTransferred),
+            "user_deleted" => Ok(Self::UserDeleted),
+            _ => Err(Error::Validation(format!("Unknown security event '{}'", value))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SecurityEvent {
+    pub id: Id,
+    pub event_type: SecurityEventType,
+    /// Who did it; `None` for events the system raised on its own
+    pub actor_id: Option<Id>,
+    /// What it concerns, such as `account:ann@example.com` or `user:<id>`
+    pub subject: String,
+    pub ip_address: Option<String>,
+    pub details: serde_json::Value,
+    pub created_at: Timestamp,
+}
+
+impl SecurityEvent {
+    pub fn new(
+        event_type: SecurityEventType,
+        actor_id: Option<Id>,
+        subject: impl Into<String>,
+        ip_address: Option<String>,
+        details: serde_json::Value,
+    ) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4(),
+            event_type,
+            actor_id,
+            subject: subject.into(),
+            ip_address,
+            details,
+            created_at: chrono::Utc::now(),
+        }
+    }
+}
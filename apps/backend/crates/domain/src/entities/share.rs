@@ -1,14 +1,24 @@
 use kingshare_core::{Id, Timestamp};
+use rand::{rngs::OsRng, RngCore};
 use serde::{Deserialize, Serialize};
+use subtle::ConstantTimeEq;
 use validator::Validate;
 
+/// Random bytes in a share token; 128 bits keeps links unguessable
+const SHARE_TOKEN_BYTES: usize = 16;
+
+/// Prefix of the PHC strings argon2 produces
+const PASSWORD_HASH_PREFIX: &str = "$argon2";
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Share {
     pub id: Id,
     pub file_id: Id,
     pub owner_id: Id,
     pub share_token: String,
-    pub password: Option<String>,
+    /// Argon2 hash of the share password. Shares created before passwords
+    /// were hashed hold the plaintext until it is upgraded.
+    pub password_hash: Option<String>,
     pub max_downloads: Option<i32>,
     pub download_count: i32,
     pub is_active: bool,
@@ -78,7 +88,7 @@ impl Share {
             file_id,
             owner_id,
             share_token: Self::generate_share_token(),
-            password: None,
+            password_hash: None,
             max_downloads: None,
             download_count: 0,
             is_active: true,
@@ -89,14 +99,9 @@ impl Share {
     }
 
     pub fn generate_share_token() -> String {
-        use std::collections::hash_map::DefaultHasher;
-        use std::hash::{Hash, Hasher};
-        
-        let mut hasher = DefaultHasher::new();
-        uuid::Uuid::new_v4().hash(&mut hasher);
-        chrono::Utc::now().timestamp_millis().hash(&mut hasher);
-        
-        format!("{:x}", hasher.finish())
+        let mut bytes = [0u8; SHARE_TOKEN_BYTES];
+        OsRng.fill_bytes(&mut bytes);
+        bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
     }
 
     pub fn is_expired(&self) -> bool {
@@ -124,15 +129,28 @@ impl Share {
         self.updated_at = chrono::Utc::now();
     }
 
-    pub fn set_password(&mut self, password: Option<String>) {
-        self.password = password;
+    pub fn set_password_hash(&mut self, password_hash: Option<String>) {
+        self.password_hash = password_hash;
         self.updated_at = chrono::Utc::now();
     }
 
-    pub fn verify_password(&self, password: &str) -> bool {
-        match &self.password {
-            Some(stored_password) => stored_password == password,
-            None => true, // No password required
+    pub fn has_password(&self) -> bool {
+        self.password_hash.is_some()
+    }
+
+    /// Whether the stored password is still plaintext from before hashing
+    pub fn has_legacy_password(&self) -> bool {
+        self.password_hash
+            .as_deref()
+            .is_some_and(|stored| !stored.starts_with(PASSWORD_HASH_PREFIX))
+    }
+
+    /// Compare against a plaintext password left over from before hashing,
+    /// in constant time
+    pub fn matches_legacy_password(&self, password: &str) -> bool {
+        match &self.password_hash {
+            Some(stored) if self.has_legacy_password() => stored.as_bytes().ct_eq(password.as_bytes()).into(),
+            _ => false,
         }
     }
 
@@ -1,3 +1,4 @@
+use super::document::{CellValue as DocumentCellValue, DocumentContent, SpreadsheetSheet};
 use kingshare_core::{Id, Timestamp};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -724,6 +725,41 @@ impl Spreadsheet {
         sheet_id
     }
 
+    /// The sheets as document content, which search indexes the cells of
+    pub fn document_content(&self) -> DocumentContent {
+        let sheets = self
+            .sheets
+            .iter()
+            .map(|sheet| SpreadsheetSheet {
+                id: sheet.id.clone(),
+                name: sheet.title.clone(),
+                cells: sheet
+                    .cells
+                    .iter()
+                    .map(|(reference, cell)| {
+                        let value = match &cell.value {
+                            CellValue::String(text) => DocumentCellValue::Text(text.clone()),
+                            CellValue::Number(number) => DocumentCellValue::Number(*number),
+                            CellValue::Boolean(value) => DocumentCellValue::Boolean(*value),
+                            CellValue::Date(date) => DocumentCellValue::Date(*date),
+                            CellValue::Empty | CellValue::Error(_) => DocumentCellValue::Empty,
+                        };
+                        (reference.clone(), value)
+                    })
+                    .collect(),
+                formulas: sheet
+                    .cells
+                    .iter()
+                    .filter_map(|(reference, cell)| Some((reference.clone(), cell.formula.clone()?)))
+                    .collect(),
+                formatting: HashMap::new(),
+                row_count: sheet.grid_properties.row_count,
+                column_count: sheet.grid_properties.column_count,
+            })
+            .collect();
+        DocumentContent::Spreadsheet { sheets }
+    }
+
     pub fn get_sheet(&self, sheet_id: &str) -> Option<&Sheet> {
         self.sheets.iter().find(|s| s.id == sheet_id)
     }
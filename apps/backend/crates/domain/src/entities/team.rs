@@ -0,0 +1,34 @@
+use kingshare_core::{Id, Timestamp};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+/// A group of users working together. Roles can be assigned per team, and
+/// apply to the team's drives.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Team {
+    pub id: Id,
+    pub name: String,
+    pub description: Option<String>,
+    pub created_by: Option<Id>,
+    pub created_at: Timestamp,
+}
+
+impl Team {
+    pub fn new(name: String, description: Option<String>, created_by: Option<Id>) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4(),
+            name,
+            description,
+            created_by,
+            created_at: chrono::Utc::now(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct CreateTeamRequest {
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+    #[validate(length(max = 500))]
+    pub description: Option<String>,
+}
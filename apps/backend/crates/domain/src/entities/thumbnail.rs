@@ -0,0 +1,76 @@
+use kingshare_core::{Error, Id, Timestamp};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Content types we render thumbnails for
+pub const THUMBNAIL_CONTENT_TYPES: [&str; 4] = ["image/jpeg", "image/png", "image/gif", "image/webp"];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThumbnailSize {
+    Small,
+    #[default]
+    Medium,
+    Large,
+}
+
+impl ThumbnailSize {
+    pub const ALL: [ThumbnailSize; 3] = [Self::Small, Self::Medium, Self::Large];
+
+    /// Longest edge of the rendered thumbnail, in pixels
+    pub fn max_dimension(&self) -> u32 {
+        match self {
+            Self::Small => 128,
+            Self::Medium => 256,
+            Self::Large => 1024,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Small => "small",
+            Self::Medium => "medium",
+            Self::Large => "large",
+        }
+    }
+}
+
+impl FromStr for ThumbnailSize {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "small" => Ok(Self::Small),
+            "medium" => Ok(Self::Medium),
+            "large" => Ok(Self::Large),
+            other => Err(Error::BadRequest(format!(
+                "Invalid thumbnail size '{}', expected small, medium or large",
+                other
+            ))),
+        }
+    }
+}
+
+/// A rendered thumbnail of stored content. Keyed by the source blob's checksum,
+/// so every file with the same content shares one set of thumbnails.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Thumbnail {
+    pub checksum: String,
+    pub size: ThumbnailSize,
+    pub storage_path: String,
+    pub thumbnail_checksum: String,
+    pub content_type: String,
+    pub byte_size: i64,
+    pub width: i32,
+    pub height: i32,
+    pub created_at: Timestamp,
+}
+
+pub fn supports_thumbnail(content_type: &str) -> bool {
+    THUMBNAIL_CONTENT_TYPES.contains(&content_type)
+}
+
+/// API path of a file's thumbnail, if its type gets one
+pub fn thumbnail_url(file_id: Id, content_type: &str) -> Option<String> {
+    supports_thumbnail(content_type).then(|| format!("/api/v1/files/{}/thumbnail", file_id))
+}
@@ -0,0 +1,178 @@
+use kingshare_core::{Error, Id, Timestamp};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Why a refresh-token family was revoked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RevocationReason {
+    Logout,
+    /// A refresh token was presented after it had already been rotated
+    ReuseDetected,
+    /// The user ended the session from another one
+    SessionEnded,
+    /// The password was reset, signing out every session
+    PasswordReset,
+    /// An administrator deactivated the account
+    AccountDeactivated,
+}
+
+impl RevocationReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Logout => "logout",
+            Self::ReuseDetected => "reuse_detected",
+            Self::SessionEnded => "session_ended",
+            Self::PasswordReset => "password_reset",
+            Self::AccountDeactivated => "account_deactivated",
+        }
+    }
+}
+
+impl FromStr for RevocationReason {
+    type Err = Error;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value {
+            "logout" => Ok(Self::Logout),
+            "reuse_detected" => Ok(Self::ReuseDetected),
+            "session_ended" => Ok(Self::SessionEnded),
+            "password_reset" => Ok(Self::PasswordReset),
+            "account_deactivated" => Ok(Self::AccountDeactivated),
+            other => Err(Error::Internal(format!("Unknown revocation reason '{}'", other))),
+        }
+    }
+}
+
+/// The client a sign-in came from, as reported by the request that started it
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ClientInfo {
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+}
+
+impl ClientInfo {
+    pub fn new(user_agent: Option<String>, ip_address: Option<String>) -> Self {
+        Self {
+            user_agent,
+            ip_address,
+        }
+    }
+
+    /// A short, human-readable label such as "Firefox on Linux"
+    pub fn device(&self) -> Option<String> {
+        let user_agent = self.user_agent.as_deref()?;
+
+        // Order matters: Edge and Opera also claim Chrome, and Chrome claims Safari
+        let browser = [
+            ("Edg/", "Edge"),
+            ("OPR/", "Opera"),
+            ("Firefox/", "Firefox"),
+            ("Chrome/", "Chrome"),
+            ("Safari/", "Safari"),
+            ("curl/", "curl"),
+        ]
+        .iter()
+        .find(|(marker, _)| user_agent.contains(marker))
+        .map(|(_, name)| *name);
+
+        let os = [
+            ("Android", "Android"),
+            ("iPhone", "iOS"),
+            ("iPad", "iPadOS"),
+            ("Windows", "Windows"),
+            ("Mac OS X", "macOS"),
+            ("Linux", "Linux"),
+        ]
+        .iter()
+        .find(|(marker, _)| user_agent.contains(marker))
+        .map(|(_, name)| *name);
+
+        match (browser, os) {
+            (Some(browser), Some(os)) => Some(format!("{} on {}", browser, os)),
+            (Some(name), None) | (None, Some(name)) => Some(name.to_string()),
+            (None, None) => Some("Unknown device".to_string()),
+        }
+    }
+}
+
+/// Every refresh token issued since one sign-in. Rotation keeps the family;
+/// revoking it signs that session out, including its outstanding access tokens.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RefreshTokenFamily {
+    pub id: Id,
+    pub user_id: Id,
+    pub created_at: Timestamp,
+    pub last_used_at: Timestamp,
+    pub device: Option<String>,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub revoked_at: Option<Timestamp>,
+    pub revoked_reason: Option<RevocationReason>,
+}
+
+impl RefreshTokenFamily {
+    pub fn new(user_id: Id, client: &ClientInfo) -> Self {
+        let now = chrono::Utc::now();
+        Self {
+            id: uuid::Uuid::new_v4(),
+            user_id,
+            created_at: now,
+            last_used_at: now,
+            device: client.device(),
+            user_agent: client.user_agent.clone(),
+            ip_address: client.ip_address.clone(),
+            revoked_at: None,
+            revoked_reason: None,
+        }
+    }
+
+    pub fn is_revoked(&self) -> bool {
+        self.revoked_at.is_some()
+    }
+}
+
+/// A live session as shown to its user
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SessionInfo {
+    pub id: Id,
+    pub device: Option<String>,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub created_at: Timestamp,
+    pub last_used_at: Timestamp,
+    /// Whether this is the session making the request
+    pub current: bool,
+}
+
+impl SessionInfo {
+    pub fn from_family(family: RefreshTokenFamily, current_session_id: Option<Id>) -> Self {
+        Self {
+            current: current_session_id == Some(family.id),
+            id: family.id,
+            device: family.device,
+            user_agent: family.user_agent,
+            ip_address: family.ip_address,
+            created_at: family.created_at,
+            last_used_at: family.last_used_at,
+        }
+    }
+}
+
+/// One refresh token, identified by its `jti`. It can be redeemed once;
+/// spent tokens are kept until they expire so a replay can be recognised.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RefreshToken {
+    pub id: Id,
+    pub family_id: Id,
+    pub user_id: Id,
+    pub issued_at: Timestamp,
+    pub expires_at: Timestamp,
+    pub consumed_at: Option<Timestamp>,
+}
+
+impl RefreshToken {
+    pub fn is_consumed(&self) -> bool {
+        self.consumed_at.is_some()
+    }
+}
@@ -0,0 +1,112 @@
+use kingshare_core::{Id, Timestamp};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+/// Default lifetime of an unfinished upload session
+pub const UPLOAD_SESSION_TTL_HOURS: i64 = 24;
+
+/// How long a claim to append a chunk holds if its request never finishes
+pub const APPEND_CLAIM_TTL_MINUTES: i64 = 60;
+
+/// A resumable (tus-style) upload in progress
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UploadSession {
+    pub id: Id,
+    pub owner_id: Id,
+    pub filename: String,
+    pub content_type: String,
+    pub total_size: i64,
+    pub offset: i64,
+    pub storage_handle: String, // Opaque handle returned by the storage backend
+    pub finalizing: bool,
+    pub file_id: Option<Id>, // Set once finalized
+    pub created_at: Timestamp,
+    pub updated_at: Timestamp,
+    pub expires_at: Timestamp,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct CreateUploadSessionRequest {
+    #[validate(length(min = 1, max = 255))]
+    pub filename: String,
+
+    #[validate(length(min = 1, max = 100))]
+    pub content_type: String,
+
+    #[validate(range(min = 1))]
+    pub size: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UploadSessionInfo {
+    pub id: Id,
+    pub filename: String,
+    pub content_type: String,
+    pub total_size: i64,
+    pub offset: i64,
+    pub is_complete: bool,
+    pub created_at: Timestamp,
+    pub expires_at: Timestamp,
+}
+
+impl UploadSession {
+    pub fn new(
+        owner_id: Id,
+        filename: String,
+        content_type: String,
+        total_size: i64,
+        storage_handle: String,
+    ) -> Self {
+        let now = chrono::Utc::now();
+        Self {
+            id: uuid::Uuid::new_v4(),
+            owner_id,
+            filename,
+            content_type,
+            total_size,
+            offset: 0,
+            storage_handle,
+            finalizing: false,
+            file_id: None,
+            created_at: now,
+            updated_at: now,
+            expires_at: now + chrono::Duration::hours(UPLOAD_SESSION_TTL_HOURS),
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        chrono::Utc::now() > self.expires_at
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.offset >= self.total_size
+    }
+
+    pub fn is_finalized(&self) -> bool {
+        self.file_id.is_some()
+    }
+
+    pub fn remaining(&self) -> i64 {
+        self.total_size - self.offset
+    }
+
+    pub fn advance(&mut self, new_offset: i64) {
+        self.offset = new_offset;
+        self.updated_at = chrono::Utc::now();
+    }
+}
+
+impl From<UploadSession> for UploadSessionInfo {
+    fn from(session: UploadSession) -> Self {
+        Self {
+            id: session.id,
+            is_complete: session.is_complete(),
+            filename: session.filename,
+            content_type: session.content_type,
+            total_size: session.total_size,
+            offset: session.offset,
+            created_at: session.created_at,
+            expires_at: session.expires_at,
+        }
+    }
+}
@@ -26,6 +26,25 @@ pub enum UserRole {
     Guest,
 }
 
+impl UserRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UserRole::Admin => "Admin",
+            UserRole::User => "User",
+            UserRole::Guest => "Guest",
+        }
+    }
+
+    /// The built-in role that holds this account role's permissions
+    pub fn system_role(&self) -> &'static str {
+        match self {
+            UserRole::Admin => super::ADMIN_ROLE,
+            UserRole::User => super::USER_ROLE,
+            UserRole::Guest => super::GUEST_ROLE,
+        }
+    }
+}
+
 impl Default for UserRole {
     fn default() -> Self {
         UserRole::User
@@ -62,6 +81,42 @@ pub struct UpdateUserRequest {
     pub last_name: Option<String>,
 }
 
+/// Narrows a user search; unset fields match every user
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct UserFilter {
+    /// Matched against the email address, username and full name
+    pub query: Option<String>,
+    pub role: Option<UserRole>,
+    pub is_active: Option<bool>,
+    pub is_verified: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChangeUserRoleRequest {
+    pub role: UserRole,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferOwnershipRequest {
+    pub to_user_id: Id,
+    /// Delete the old account once everything has moved
+    #[serde(default)]
+    pub delete_user: bool,
+}
+
+/// What moved when a user's content was handed to another user
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct OwnershipTransfer {
+    pub files: u64,
+    pub shares: u64,
+    pub drives: u64,
+    pub folders: u64,
+    pub drive_items: u64,
+    pub documents: u64,
+    /// Storage usage moved to the new owner's quota
+    pub bytes: i64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserProfile {
     pub id: Id,
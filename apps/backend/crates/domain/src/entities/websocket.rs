@@ -2,6 +2,9 @@ use kingshare_core::{Id, Timestamp};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+/// Connection metadata key holding the session the connection authenticated with
+pub const SESSION_METADATA_KEY: &str = "session_id";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WebSocketConnection {
     pub connection_id: String,
@@ -11,17 +14,28 @@ pub struct WebSocketConnection {
     pub metadata: HashMap<String, String>,
 }
 
+impl WebSocketConnection {
+    pub fn session_id(&self) -> Option<Id> {
+        self.metadata
+            .get(SESSION_METADATA_KEY)
+            .and_then(|session_id| session_id.parse().ok())
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", content = "data")]
 pub enum WebSocketMessage {
     // Authentication
     Authenticate { token: String },
     AuthenticationResult { success: bool, message: String },
+    /// Sent just before the server closes a connection whose session was ended
+    SessionRevoked { session_id: Id },
     
     // File operations
     FileUploaded { file_id: Id, filename: String, size: i64 },
     FileDeleted { file_id: Id, filename: String },
     FileShared { share_id: Id, file_id: Id, share_token: String },
+    FileQuarantined { file_id: Id, filename: String, signature: String },
     
     // Share operations
     ShareAccessed { share_id: Id, file_id: Id, filename: String },
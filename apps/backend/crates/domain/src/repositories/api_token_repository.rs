@@ -0,0 +1,19 @@
+use crate::entities::ApiToken;
+use async_trait::async_trait;
+use kingshare_core::{Id, Result};
+use mockall::automock;
+
+#[automock]
+#[async_trait]
+pub trait ApiTokenRepository: Send + Sync {
+    async fn create(&self, token: ApiToken) -> Result<ApiToken>;
+    async fn find_by_hash(&self, token_hash: &str) -> Result<Option<ApiToken>>;
+    /// A user's tokens that are neither revoked nor expired, newest first
+    async fn list_active(&self, user_id: Id) -> Result<Vec<ApiToken>>;
+    async fn count_active(&self, user_id: Id) -> Result<u64>;
+    /// Revoke one of the user's tokens. Returns false if they have no such live token.
+    async fn revoke(&self, id: Id, user_id: Id) -> Result<bool>;
+    /// Record that the token was used. Writes are coalesced to one a minute,
+    /// so busy scripts don't update the row on every request.
+    async fn record_use(&self, id: Id) -> Result<()>;
+}
@@ -0,0 +1,24 @@
+use crate::entities::Blob;
+use async_trait::async_trait;
+use kingshare_core::{Result, Timestamp};
+use mockall::automock;
+
+#[automock]
+#[async_trait]
+pub trait BlobRepository: Send + Sync {
+    /// Take a reference on the blob for `checksum`, registering it at `storage_path`
+    /// if it is new. The returned blob's path wins over `storage_path` when they differ.
+    /// Fails with `Conflict` while the blob is being garbage collected.
+    async fn acquire(&self, checksum: &str, storage_path: &str, size: i64) -> Result<Blob>;
+    /// Drop one reference; returns the updated blob, or `None` if it had none left
+    async fn release(&self, checksum: &str) -> Result<Option<Blob>>;
+    async fn find_by_checksum(&self, checksum: &str) -> Result<Option<Blob>>;
+    /// Subset of `paths` that belong to a known blob
+    async fn find_known_paths(&self, paths: Vec<String>) -> Result<Vec<String>>;
+    async fn find_unreferenced(&self, idle_since: Timestamp, limit: i64) -> Result<Vec<Blob>>;
+    /// Mark the blob for collection if it is still unreferenced, so nothing can
+    /// take a new reference while its content is removed; returns whether it was marked
+    async fn mark_collecting(&self, checksum: &str) -> Result<bool>;
+    /// Delete a marked row once its content is gone; returns whether it was deleted
+    async fn delete_unreferenced(&self, checksum: &str) -> Result<bool>;
+}
@@ -1,7 +1,8 @@
 use crate::entities::{
     Drive, Folder, DriveItem, DriveActivity, CreateDriveRequest, CreateFolderRequest,
-    UpdateFolderRequest, ShareItemRequest, CreateSharingLinkRequest, DriveItemResponse,
-    FolderContents, ActivityType,
+    UpdateFolderRequest, ShareItemRequest, CreateSharingLinkRequest,
+    FolderContents, FolderStats, ActivityType, DocumentContent, DriveQuery, SearchFilters, SearchMatch, SearchResult,
+    SearchScope, UnindexedItem,
 };
 use kingshare_core::{Id, Result};
 use std::collections::HashMap;
@@ -12,6 +13,10 @@ pub trait DriveRepository: Send + Sync {
     async fn create_drive(&self, drive: Drive) -> Result<Drive>;
     async fn get_drive_by_id(&self, drive_id: Id) -> Result<Option<Drive>>;
     async fn get_drives_by_owner(&self, owner_id: Id) -> Result<Vec<Drive>>;
+    /// Drives the user holds a role in, directly or through one of the drive's team
+    async fn get_drives_for_member(&self, user_id: Id) -> Result<Vec<Drive>>;
+    /// Drives holding the items a search scope looks through
+    async fn get_search_scope_drives(&self, scope: SearchScope, user_id: Id) -> Result<Vec<Drive>>;
     async fn update_drive(&self, drive: Drive) -> Result<Drive>;
     async fn delete_drive(&self, drive_id: Id) -> Result<()>;
     async fn update_storage_usage(&self, drive_id: Id, size_delta: i64) -> Result<()>;
@@ -23,7 +28,14 @@ pub trait DriveRepository: Send + Sync {
     async fn update_folder(&self, folder: Folder) -> Result<Folder>;
     async fn delete_folder(&self, folder_id: Id) -> Result<()>;
     async fn move_folder(&self, folder_id: Id, new_parent_id: Option<Id>) -> Result<()>;
+    /// Built from the folder's ancestors, such as `/Finance/2024`
     async fn get_folder_path(&self, folder_id: Id) -> Result<String>;
+    /// The folders holding an item, nearest first up to the drive root. A
+    /// folder item starts with its own folder.
+    async fn get_item_ancestors(&self, item_id: Id) -> Result<Vec<Folder>>;
+    /// Everything below a folder, nearest first, down to `max_depth` levels
+    async fn get_descendants(&self, folder_id: Id, max_depth: Option<u32>) -> Result<Vec<DriveItem>>;
+    async fn get_folder_stats(&self, folder_id: Id) -> Result<FolderStats>;
 
     // Drive item management
     async fn create_drive_item(&self, item: DriveItem) -> Result<DriveItem>;
@@ -33,7 +45,14 @@ pub trait DriveRepository: Send + Sync {
     async fn update_drive_item(&self, item: DriveItem) -> Result<DriveItem>;
     async fn delete_drive_item(&self, item_id: Id) -> Result<()>;
     async fn move_drive_item(&self, item_id: Id, new_parent_id: Option<Id>) -> Result<()>;
-    async fn copy_drive_item(&self, item_id: Id, new_parent_id: Option<Id>, new_name: Option<String>) -> Result<DriveItem>;
+    /// Copy an item, and for folders everything in them, as owned by `owner_id`
+    async fn copy_drive_item(
+        &self,
+        item_id: Id,
+        new_parent_id: Option<Id>,
+        new_name: Option<String>,
+        owner_id: Id,
+    ) -> Result<DriveItem>;
 
     // Trash management
     async fn move_to_trash(&self, item_id: Id) -> Result<()>;
@@ -43,16 +62,37 @@ pub trait DriveRepository: Send + Sync {
     async fn permanently_delete(&self, item_id: Id) -> Result<()>;
 
     // Sharing and permissions
-    async fn share_item(&self, item_id: Id, share_request: ShareItemRequest) -> Result<()>;
+    async fn share_item(&self, item_id: Id, shared_by: Id, share_request: ShareItemRequest) -> Result<()>;
     async fn unshare_item(&self, item_id: Id, user_id: Id) -> Result<()>;
-    async fn create_sharing_link(&self, item_id: Id, link_request: CreateSharingLinkRequest) -> Result<String>;
+    async fn create_sharing_link(
+        &self,
+        item_id: Id,
+        created_by: Id,
+        link_request: CreateSharingLinkRequest,
+    ) -> Result<String>;
     async fn get_sharing_link(&self, item_id: Id) -> Result<Option<String>>;
     async fn revoke_sharing_link(&self, item_id: Id) -> Result<()>;
     async fn get_shared_with_me(&self, user_id: Id) -> Result<Vec<DriveItem>>;
     async fn get_shared_by_me(&self, user_id: Id) -> Result<Vec<DriveItem>>;
 
     // Search and filtering
-    async fn search_drive_items(&self, drive_id: Id, query: &str, filters: HashMap<String, String>) -> Result<Vec<DriveItem>>;
+    /// Ranked matches of `query` in names, tags, descriptions and indexed
+    /// contents. `user_id` is who is searching, for `owner:me`, the
+    /// per-user filters and the collection scopes.
+    async fn search_drive_items(
+        &self,
+        scope: SearchScope,
+        user_id: Id,
+        query: &DriveQuery,
+        filters: &SearchFilters,
+    ) -> Result<Vec<SearchMatch>>;
+    /// Replace the text an item's contents are searched by
+    async fn index_item_content(&self, item_id: Id, content: &str) -> Result<()>;
+    /// Items holding the document or uploaded file `source_id`
+    async fn get_items_by_source(&self, source_id: Id) -> Result<Vec<Id>>;
+    /// Up to `limit` items holding a document or file whose contents were
+    /// never indexed. Items holding files are left out unless `include_files`.
+    async fn get_unindexed_items(&self, limit: u32, include_files: bool) -> Result<Vec<UnindexedItem>>;
     async fn get_recent_items(&self, user_id: Id, limit: u32) -> Result<Vec<DriveItem>>;
     async fn get_starred_items(&self, user_id: Id) -> Result<Vec<DriveItem>>;
     async fn star_item(&self, item_id: Id, user_id: Id) -> Result<()>;
@@ -67,7 +107,7 @@ pub trait DriveRepository: Send + Sync {
     // Batch operations
     async fn batch_move_items(&self, item_ids: Vec<Id>, new_parent_id: Option<Id>) -> Result<()>;
     async fn batch_delete_items(&self, item_ids: Vec<Id>) -> Result<()>;
-    async fn batch_share_items(&self, item_ids: Vec<Id>, share_request: ShareItemRequest) -> Result<()>;
+    async fn batch_share_items(&self, item_ids: Vec<Id>, shared_by: Id, share_request: ShareItemRequest) -> Result<()>;
 
     // Storage analytics
     async fn get_storage_usage_by_type(&self, drive_id: Id) -> Result<HashMap<String, i64>>;
@@ -101,7 +141,24 @@ pub trait DriveService: Send + Sync {
     async fn share_with_link(&self, item_id: Id, access_level: String, user_id: Id) -> Result<String>;
     async fn get_item_permissions(&self, item_id: Id, user_id: Id) -> Result<Vec<String>>;
     
-    async fn search_items(&self, drive_id: Id, query: &str, user_id: Id) -> Result<Vec<DriveItemResponse>>;
+    async fn search_items(
+        &self,
+        drive_id: Id,
+        query: &DriveQuery,
+        filters: SearchFilters,
+        user_id: Id,
+    ) -> Result<Vec<SearchResult>>;
+    /// Make a document or spreadsheet item searchable by its content
+    async fn index_document(&self, item_id: Id, content: &DocumentContent) -> Result<()>;
+    /// Make an uploaded file searchable by its text, for the types text can be read from
+    async fn index_file(&self, item_id: Id, data: &[u8]) -> Result<()>;
+    /// Reindex the items holding a document, after it's saved
+    async fn index_document_items(&self, document_id: Id, content: &DocumentContent) -> Result<()>;
+    /// Reindex the items holding an uploaded file, once it's stored
+    async fn index_file_items(&self, file_id: Id, storage_path: &str) -> Result<()>;
+    /// Index the items whose document or file never was, such as ones from
+    /// before search. Returns how many were indexed.
+    async fn backfill_index(&self) -> Result<u64>;
     async fn get_recent_activity(&self, drive_id: Id, user_id: Id, limit: u32) -> Result<Vec<DriveActivity>>;
     
     async fn cleanup_trash(&self, drive_id: Id, older_than_days: u32) -> Result<u32>; // returns count of deleted items
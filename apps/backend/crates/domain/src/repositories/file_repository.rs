@@ -1,4 +1,4 @@
-use crate::entities::{File, FileMetadata};
+use crate::entities::{File, FileMetadata, ScanStatus};
 use async_trait::async_trait;
 use kingshare_core::{Error, Id, PaginatedResponse, PaginationParams, Result};
 use mockall::automock;
@@ -18,4 +18,17 @@ pub trait FileRepository: Send + Sync {
     async fn get_total_size_by_owner(&self, owner_id: Id) -> Result<i64>;
     async fn find_expired_files(&self) -> Result<Vec<File>>;
     async fn cleanup_expired_files(&self) -> Result<u64>;
+    /// Record a scan verdict on every file with this content that doesn't have it yet.
+    /// Infected files keep their status whatever a later scan says.
+    /// A `quarantine_path` also repoints the blob and its files at the moved content.
+    /// Returns the files whose status changed.
+    async fn record_scan_result(
+        &self,
+        checksum: &str,
+        status: ScanStatus,
+        signature: Option<String>,
+        quarantine_path: Option<String>,
+    ) -> Result<Vec<File>>;
+    /// One file per distinct content still waiting for a verdict
+    async fn find_pending_scan(&self, limit: i64) -> Result<Vec<File>>;
 }
\ No newline at end of file
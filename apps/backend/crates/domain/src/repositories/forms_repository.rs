@@ -20,6 +20,10 @@ pub trait FormsRepository: Send + Sync {
     async fn publish_form(&self, form_id: Id) -> Result<()>;
     async fn unpublish_form(&self, form_id: Id) -> Result<()>;
 
+    // Access control: forms belong to whoever owns their document
+    async fn get_form_owner(&self, form_id: Id) -> Result<Option<Id>>;
+    async fn get_field_form_id(&self, field_id: &str) -> Result<Option<Id>>;
+
     // Form sections and fields
     async fn add_form_section(&self, form_id: Id, section: FormSection) -> Result<FormSection>;
     async fn update_form_section(&self, section: FormSection) -> Result<FormSection>;
@@ -0,0 +1,19 @@
+use crate::entities::{OidcLoginState, UserIdentity};
+use async_trait::async_trait;
+use kingshare_core::{Id, Result};
+use mockall::automock;
+
+#[automock]
+#[async_trait]
+pub trait IdentityRepository: Send + Sync {
+    async fn find_identity(&self, issuer: &str, subject: &str) -> Result<Option<UserIdentity>>;
+    /// Link an IdP account to a user. Fails with `Conflict` if the account is
+    /// already linked.
+    async fn create_identity(&self, identity: UserIdentity) -> Result<UserIdentity>;
+    async fn record_identity_login(&self, id: Id, email: Option<String>) -> Result<()>;
+    /// Remember a login until the IdP redirects back, dropping expired ones
+    async fn save_login_state(&self, login: OidcLoginState) -> Result<()>;
+    /// Remove and return the login with this state, unless it has expired;
+    /// a state can only be taken once
+    async fn take_login_state(&self, state: &str) -> Result<Option<OidcLoginState>>;
+}
@@ -0,0 +1,32 @@
+use crate::entities::{AttemptKey, FailedAttempts, LockoutPolicy};
+use async_trait::async_trait;
+use kingshare_core::{Result, Timestamp};
+use mockall::automock;
+
+#[automock]
+#[async_trait]
+pub trait LockoutRepository: Send + Sync {
+    async fn find(&self, keys: &[AttemptKey]) -> Result<Vec<FailedAttempts>>;
+    /// Count a failure. The count starts over when the previous failure is
+    /// older than `window_start`.
+    async fn record_failure(&self, key: &AttemptKey, window_start: Timestamp) -> Result<FailedAttempts>;
+    /// Count an attempt as a failure before it is checked, and block the key
+    /// as `policy` says for the new count, in one step. A blocked key is left
+    /// alone; the flag says whether the attempt was counted.
+    async fn reserve_attempt(
+        &self,
+        key: &AttemptKey,
+        window_start: Timestamp,
+        policy: &LockoutPolicy,
+    ) -> Result<(FailedAttempts, bool)>;
+    /// Give back an attempt reserved for an address that turned out right,
+    /// lifting the block it set
+    async fn release_attempt(&self, key: &AttemptKey, blocked_until: Option<Timestamp>) -> Result<()>;
+    async fn block(&self, key: &AttemptKey, until: Timestamp, locked: bool) -> Result<()>;
+    /// Forget a key's failures. Returns false if it had none.
+    async fn clear(&self, key: &AttemptKey) -> Result<bool>;
+    /// Keys that are blocked right now, locked ones first
+    async fn list_blocked(&self) -> Result<Vec<FailedAttempts>>;
+    /// Drop records that are no longer blocked and older than `window_start`
+    async fn prune(&self, window_start: Timestamp) -> Result<u64>;
+}
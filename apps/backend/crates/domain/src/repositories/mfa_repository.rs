@@ -0,0 +1,30 @@
+use crate::entities::{RecoveryCode, TotpEnrollment};
+use async_trait::async_trait;
+use kingshare_core::{Id, Result};
+use mockall::automock;
+
+#[automock]
+#[async_trait]
+pub trait MfaRepository: Send + Sync {
+    async fn find_enrollment(&self, user_id: Id) -> Result<Option<TotpEnrollment>>;
+    /// Store an unconfirmed secret, replacing any earlier unconfirmed one.
+    /// Fails with `Conflict` if the user already has 2FA enabled.
+    async fn save_pending_enrollment(&self, enrollment: TotpEnrollment) -> Result<TotpEnrollment>;
+    /// Confirm the pending enrollment with the code from `step` and replace the
+    /// user's recovery codes. Returns `false` if there was nothing to confirm.
+    async fn confirm_enrollment(
+        &self,
+        user_id: Id,
+        step: i64,
+        recovery_codes: Vec<RecoveryCode>,
+    ) -> Result<bool>;
+    /// Accept a code from `step` if it is later than any step used before;
+    /// concurrent calls with the same step can't both succeed
+    async fn record_totp_step(&self, user_id: Id, step: i64) -> Result<bool>;
+    async fn replace_recovery_codes(&self, user_id: Id, recovery_codes: Vec<RecoveryCode>) -> Result<()>;
+    /// Spend a recovery code. Returns whether it existed and was unused.
+    async fn consume_recovery_code(&self, user_id: Id, code_hash: &str) -> Result<bool>;
+    async fn count_unused_recovery_codes(&self, user_id: Id) -> Result<u64>;
+    /// Remove the enrollment together with its recovery codes
+    async fn delete_enrollment(&self, user_id: Id) -> Result<()>;
+}
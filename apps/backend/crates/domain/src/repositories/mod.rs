@@ -5,6 +5,18 @@ pub mod drive_repository;
 pub mod collaboration_repository;
 pub mod spreadsheet_repository;
 pub mod forms_repository;
+pub mod upload_session_repository;
+pub mod blob_repository;
+pub mod thumbnail_repository;
+pub mod quota_repository;
+pub mod token_repository;
+pub mod mfa_repository;
+pub mod identity_repository;
+pub mod api_token_repository;
+pub mod lockout_repository;
+pub mod security_event_repository;
+pub mod role_repository;
+pub mod team_repository;
 
 pub use user_repository::*;
 pub use file_repository::*;
@@ -12,4 +24,16 @@ pub use share_repository::*;
 pub use drive_repository::*;
 pub use collaboration_repository::*;
 pub use spreadsheet_repository::*;
-pub use forms_repository::*;
\ No newline at end of file
+pub use forms_repository::*;
+pub use upload_session_repository::*;
+pub use blob_repository::*;
+pub use thumbnail_repository::*;
+pub use quota_repository::*;
+pub use token_repository::*;
+pub use mfa_repository::*;
+pub use identity_repository::*;
+pub use api_token_repository::*;
+pub use lockout_repository::*;
+pub use security_event_repository::*;
+pub use role_repository::*;
+pub use team_repository::*;
\ No newline at end of file
@@ -0,0 +1,18 @@
+use crate::entities::{QuotaScope, QuotaUsage};
+use async_trait::async_trait;
+use kingshare_core::Result;
+use mockall::automock;
+
+#[automock]
+#[async_trait]
+pub trait QuotaRepository: Send + Sync {
+    /// Atomically add `bytes` to the scope's usage if it still fits under the quota.
+    /// Returns the updated usage, or `Error::QuotaExceeded` leaving usage untouched.
+    async fn reserve(&self, scope: QuotaScope, bytes: i64) -> Result<QuotaUsage>;
+    /// Give back `bytes` previously reserved; usage never drops below zero
+    async fn release(&self, scope: QuotaScope, bytes: i64) -> Result<()>;
+    async fn get_usage(&self, scope: QuotaScope) -> Result<QuotaUsage>;
+    /// Set the scope's quota. `None` restores the default for users and is
+    /// rejected for drives, which always have one.
+    async fn set_quota(&self, scope: QuotaScope, quota: Option<i64>) -> Result<QuotaUsage>;
+}
@@ -0,0 +1,23 @@
+use crate::entities::{Role, RoleAssignment};
+use async_trait::async_trait;
+use kingshare_core::{Id, Result};
+use mockall::automock;
+
+#[automock]
+#[async_trait]
+pub trait RoleRepository: Send + Sync {
+    async fn create_role(&self, role: Role) -> Result<Role>;
+    async fn find_role(&self, id: Id) -> Result<Option<Role>>;
+    async fn find_role_by_name(&self, name: &str) -> Result<Option<Role>>;
+    async fn find_roles(&self, ids: &[Id]) -> Result<Vec<Role>>;
+    async fn list_roles(&self) -> Result<Vec<Role>>;
+    async fn update_role(&self, role: Role) -> Result<Role>;
+    /// Delete a role along with its assignments. Returns false if it didn't exist.
+    async fn delete_role(&self, id: Id) -> Result<bool>;
+
+    async fn create_assignment(&self, assignment: RoleAssignment) -> Result<RoleAssignment>;
+    async fn find_assignment(&self, assignment: &RoleAssignment) -> Result<Option<RoleAssignment>>;
+    /// Remove an assignment, returning it if it existed
+    async fn delete_assignment(&self, id: Id) -> Result<Option<RoleAssignment>>;
+    async fn list_assignments(&self, user_id: Id) -> Result<Vec<RoleAssignment>>;
+}
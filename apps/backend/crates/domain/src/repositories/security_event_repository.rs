@@ -0,0 +1,12 @@
+use crate::entities::{SecurityEvent, SecurityEventType};
+use async_trait::async_trait;
+use kingshare_core::Result;
+use mockall::automock;
+
+#[automock]
+#[async_trait]
+pub trait SecurityEventRepository: Send + Sync {
+    async fn record(&self, event: SecurityEvent) -> Result<()>;
+    /// Newest first
+    async fn list_recent(&self, event_type: Option<SecurityEventType>, limit: u32) -> Result<Vec<SecurityEvent>>;
+}
@@ -17,4 +17,8 @@ pub trait ShareRepository: Send + Sync {
     async fn find_expired_shares(&self) -> Result<Vec<Share>>;
     async fn cleanup_expired_shares(&self) -> Result<u64>;
     async fn count_by_owner(&self, owner_id: Id) -> Result<u64>;
+    /// Shares whose password is still stored as plaintext
+    async fn find_with_legacy_passwords(&self) -> Result<Vec<Share>>;
+    /// Swap the stored password for its hash, unless it changed meanwhile
+    async fn replace_password_hash(&self, id: Id, current: &str, password_hash: &str) -> Result<bool>;
 }
\ No newline at end of file
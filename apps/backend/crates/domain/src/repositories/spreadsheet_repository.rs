@@ -16,6 +16,13 @@ pub trait SpreadsheetRepository: Send + Sync {
     async fn update_spreadsheet(&self, spreadsheet: Spreadsheet) -> Result<Spreadsheet>;
     async fn delete_spreadsheet(&self, spreadsheet_id: Id) -> Result<()>;
 
+    // Access control: spreadsheets belong to whoever owns their document
+    async fn get_document_owner(&self, document_id: Id) -> Result<Option<Id>>;
+    async fn get_spreadsheet_owner(&self, spreadsheet_id: Id) -> Result<Option<Id>>;
+    async fn get_sheet_spreadsheet_id(&self, sheet_id: &str) -> Result<Option<Id>>;
+    async fn get_chart_spreadsheet_id(&self, chart_id: &str) -> Result<Option<Id>>;
+    async fn get_pivot_table_spreadsheet_id(&self, pivot_table_id: &str) -> Result<Option<Id>>;
+
     // Sheet management
     async fn create_sheet(&self, spreadsheet_id: Id, sheet: Sheet) -> Result<Sheet>;
     async fn get_sheet_by_id(&self, sheet_id: &str) -> Result<Option<Sheet>>;
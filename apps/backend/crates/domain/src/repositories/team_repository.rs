@@ -0,0 +1,17 @@
+use crate::entities::Team;
+use async_trait::async_trait;
+use kingshare_core::{Id, Result};
+use mockall::automock;
+
+#[automock]
+#[async_trait]
+pub trait TeamRepository: Send + Sync {
+    async fn create(&self, team: Team) -> Result<Team>;
+    async fn find_by_id(&self, id: Id) -> Result<Option<Team>>;
+    async fn find_by_name(&self, name: &str) -> Result<Option<Team>>;
+    async fn list(&self) -> Result<Vec<Team>>;
+    /// Returns false if the team didn't exist
+    async fn delete(&self, id: Id) -> Result<bool>;
+    /// The team a drive belongs to, if any
+    async fn find_drive_team(&self, drive_id: Id) -> Result<Option<Id>>;
+}
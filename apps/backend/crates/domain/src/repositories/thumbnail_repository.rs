@@ -0,0 +1,13 @@
+use crate::entities::{Thumbnail, ThumbnailSize};
+use async_trait::async_trait;
+use kingshare_core::Result;
+use mockall::automock;
+
+#[automock]
+#[async_trait]
+pub trait ThumbnailRepository: Send + Sync {
+    /// Fails with `Error::Conflict` if this size already exists for the content
+    async fn create(&self, thumbnail: Thumbnail) -> Result<Thumbnail>;
+    async fn find(&self, checksum: &str, size: ThumbnailSize) -> Result<Option<Thumbnail>>;
+    async fn find_by_checksum(&self, checksum: &str) -> Result<Vec<Thumbnail>>;
+}
@@ -0,0 +1,37 @@
+use crate::entities::{RefreshToken, RefreshTokenFamily, RevocationReason};
+use async_trait::async_trait;
+use kingshare_core::{Id, Result, Timestamp};
+use mockall::automock;
+
+#[automock]
+#[async_trait]
+pub trait TokenRepository: Send + Sync {
+    async fn create_family(&self, family: RefreshTokenFamily) -> Result<RefreshTokenFamily>;
+    async fn find_family(&self, family_id: Id) -> Result<Option<RefreshTokenFamily>>;
+    /// Families that are not revoked, most recently used first
+    async fn find_active_families(&self, user_id: Id) -> Result<Vec<RefreshTokenFamily>>;
+    /// Record a newly issued refresh token and mark its family as used
+    async fn add_refresh_token(&self, token: RefreshToken) -> Result<()>;
+    /// Spend a refresh token. Returns `None` unless the token exists, is unexpired,
+    /// unspent and its family is not revoked; concurrent calls can't both succeed.
+    async fn consume_refresh_token(&self, token_id: Id) -> Result<Option<RefreshToken>>;
+    async fn find_refresh_token(&self, token_id: Id) -> Result<Option<RefreshToken>>;
+    /// Returns whether the family was live before this call
+    async fn revoke_family(&self, family_id: Id, reason: RevocationReason) -> Result<bool>;
+    /// Revoke every live family of a user except `except`. Returns the revoked ids.
+    async fn revoke_user_families(
+        &self,
+        user_id: Id,
+        except: Option<Id>,
+        reason: RevocationReason,
+    ) -> Result<Vec<Id>>;
+    /// Deny an access token until it would have expired anyway. Returns
+    /// false when it was already denied, so single-use tokens can be spent
+    /// by whichever caller gets there first.
+    async fn deny_access_token(&self, jti: Id, user_id: Id, expires_at: Timestamp) -> Result<bool>;
+    /// Whether the access token was denied or belongs to a revoked family
+    async fn is_access_token_revoked(&self, jti: Id, family_id: Option<Id>) -> Result<bool>;
+    /// Delete expired refresh tokens and denylist entries, and families with no
+    /// live tokens left. Returns the number of rows removed.
+    async fn prune_expired(&self, now: Timestamp) -> Result<u64>;
+}
@@ -0,0 +1,28 @@
+use crate::entities::UploadSession;
+use async_trait::async_trait;
+use kingshare_core::{Id, Result, Timestamp};
+use mockall::automock;
+
+#[automock]
+#[async_trait]
+pub trait UploadSessionRepository: Send + Sync {
+    async fn create(&self, session: UploadSession) -> Result<UploadSession>;
+    async fn find_by_id(&self, id: Id) -> Result<Option<UploadSession>>;
+    /// Record the offset storage reports. Returns false when it moved or a
+    /// chunk is being appended.
+    async fn update_offset(&self, id: Id, expected_offset: i64, new_offset: i64) -> Result<bool>;
+    /// Claim `offset` for one chunk, returning when the claim was taken. `None`
+    /// when the session is past it, finalizing, or another chunk holds a claim.
+    async fn claim_append(&self, id: Id, offset: i64) -> Result<Option<Timestamp>>;
+    /// Record where a claimed append ended and drop the claim. Returns false
+    /// when the claim lapsed and was taken over.
+    async fn finish_append(&self, id: Id, claimed_at: Timestamp, new_offset: i64) -> Result<bool>;
+    async fn release_append(&self, id: Id, claimed_at: Timestamp) -> Result<()>;
+    /// Returns false when the session is already being, or has been, finalized,
+    /// or a chunk is being appended
+    async fn claim_finalize(&self, id: Id) -> Result<bool>;
+    async fn release_finalize(&self, id: Id) -> Result<()>;
+    async fn mark_finalized(&self, id: Id, file_id: Id) -> Result<()>;
+    async fn delete(&self, id: Id) -> Result<()>;
+    async fn find_expired(&self) -> Result<Vec<UploadSession>>;
+}
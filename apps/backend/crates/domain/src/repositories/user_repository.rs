@@ -1,4 +1,4 @@
-use crate::entities::{User, UserProfile};
+use crate::entities::{OwnershipTransfer, User, UserFilter, UserProfile, UserRole};
 use crate::value_objects::Email;
 use async_trait::async_trait;
 use kingshare_core::{Error, Id, PaginatedResponse, PaginationParams, Result};
@@ -18,4 +18,9 @@ pub trait UserRepository: Send + Sync {
     async fn exists_by_username(&self, username: &str) -> Result<bool>;
     async fn count(&self) -> Result<u64>;
     async fn find_active_users(&self, params: PaginationParams) -> Result<PaginatedResponse<UserProfile>>;
+    async fn search(&self, filter: &UserFilter, params: PaginationParams) -> Result<PaginatedResponse<UserProfile>>;
+    async fn count_active_by_role(&self, role: UserRole) -> Result<u64>;
+    /// Hand everything `from_user_id` owns to `to_user_id` in one transaction,
+    /// storage usage included
+    async fn transfer_ownership(&self, from_user_id: Id, to_user_id: Id) -> Result<OwnershipTransfer>;
 }
\ No newline at end of file
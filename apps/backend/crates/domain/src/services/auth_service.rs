@@ -1,4 +1,5 @@
 use async_trait::async_trait;
+use crate::entities::{AccountToken, AccountTokenPurpose, ApiTokenSecret, ClientInfo, MfaChallenge};
 use kingshare_core::{Error, Id, Result};
 use mockall::automock;
 use serde::{Deserialize, Serialize};
@@ -8,6 +9,23 @@ pub struct TokenPair {
     pub access_token: String,
     pub refresh_token: String,
     pub expires_in: i64,
+    /// The session (refresh-token family) the pair belongs to
+    pub session_id: Id,
+}
+
+/// Returned instead of a token pair when a password login needs a second factor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MfaChallengeToken {
+    pub challenge_token: String,
+    pub expires_in: i64,
+    pub enrollment_required: bool,
+}
+
+/// A signed link token for email verification or password reset
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedAccountToken {
+    pub token: String,
+    pub expires_in: i64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -18,6 +36,41 @@ pub struct Claims {
     pub role: String,
     pub exp: i64,
     pub iat: i64,
+    #[serde(default)]
+    pub jti: String, // Token ID, used to deny the token before it expires
+    #[serde(default)]
+    pub sid: Option<String>, // Refresh-token family (session) the token was issued in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub act: Option<String>, // Administrator acting as the user, when impersonating
+}
+
+impl Claims {
+    /// The user the token was issued to
+    pub fn user_id(&self) -> Result<Id> {
+        self.sub
+            .parse()
+            .map_err(|_| Error::Authentication("Invalid token subject".to_string()))
+    }
+
+    /// The administrator behind an impersonation token
+    pub fn impersonator_id(&self) -> Option<Id> {
+        self.act.as_deref().and_then(|act| act.parse().ok())
+    }
+}
+
+/// A short-lived access token for an administrator acting as another user.
+/// It comes without a refresh token and can't be renewed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImpersonationToken {
+    pub access_token: String,
+    pub expires_in: i64,
+}
+
+/// A redeemed refresh token: the session it belonged to, ready for the next token pair
+#[derive(Debug, Clone, PartialEq)]
+pub struct RefreshGrant {
+    pub user_id: Id,
+    pub session_id: Id,
 }
 
 #[automock]
@@ -25,8 +78,54 @@ pub struct Claims {
 pub trait AuthService: Send + Sync {
     async fn hash_password(&self, password: &str) -> Result<String>;
     async fn verify_password(&self, password: &str, hash: &str) -> Result<bool>;
-    async fn generate_tokens(&self, user_id: Id, email: &str, username: &str, role: &str) -> Result<TokenPair>;
+    /// Start a new session and issue its first token pair
+    async fn generate_tokens(
+        &self,
+        user_id: Id,
+        email: &str,
+        username: &str,
+        role: &str,
+        client: &ClientInfo,
+    ) -> Result<TokenPair>;
     async fn verify_token(&self, token: &str) -> Result<Claims>;
-    async fn refresh_token(&self, refresh_token: &str) -> Result<TokenPair>;
+    /// Spend a refresh token. Replaying one that was already spent revokes its
+    /// whole family, signing out whoever holds the current token as well.
+    async fn redeem_refresh_token(&self, refresh_token: &str) -> Result<RefreshGrant>;
+    /// Issue the next token pair in the session of a redeemed refresh token
+    async fn rotate_tokens(&self, grant: &RefreshGrant, email: &str, username: &str, role: &str) -> Result<TokenPair>;
+    /// Deny an access token and end the session it was issued in
     async fn revoke_token(&self, token: &str) -> Result<()>;
+    /// Issue an access token that acts as the user on behalf of `actor_id`
+    async fn issue_impersonation_token(
+        &self,
+        user_id: Id,
+        email: &str,
+        username: &str,
+        role: &str,
+        actor_id: Id,
+    ) -> Result<ImpersonationToken>;
+    /// Issue the short-lived token that lets a password login finish with a second factor
+    async fn issue_mfa_challenge(&self, user_id: Id, enrollment_required: bool) -> Result<MfaChallengeToken>;
+    /// Rejects challenges that are expired, malformed or already spent
+    async fn verify_mfa_challenge(&self, token: &str) -> Result<MfaChallenge>;
+    /// Spend a challenge so it can't be answered again. Fails with
+    /// `Error::Authentication` when it was already spent.
+    async fn consume_mfa_challenge(&self, challenge: &MfaChallenge) -> Result<()>;
+    /// Sign a single-use account link. `fingerprint` is the account state the
+    /// link is bound to, such as the email address it verifies.
+    async fn issue_account_token(
+        &self,
+        user_id: Id,
+        purpose: AccountTokenPurpose,
+        fingerprint: &str,
+    ) -> Result<SignedAccountToken>;
+    /// Rejects tokens that are expired, malformed, spent or meant for another purpose
+    async fn verify_account_token(&self, token: &str, purpose: AccountTokenPurpose) -> Result<AccountToken>;
+    /// Spend a token, provided the account still matches `fingerprint`
+    async fn consume_account_token(&self, token: &AccountToken, fingerprint: &str) -> Result<()>;
+    /// Generate a personal access token secret and its lookup hash
+    fn generate_api_token(&self) -> ApiTokenSecret;
+    fn hash_api_token(&self, token: &str) -> String;
+    /// Drop expired refresh tokens, sessions and denylist entries
+    async fn prune_expired_tokens(&self) -> Result<u64>;
 }
\ No newline at end of file
@@ -2,11 +2,16 @@ use async_trait::async_trait;
 use kingshare_core::{Error, Id, Result};
 use mockall::automock;
 
+/// Content sniffing looks at this many bytes from each end of a file
+pub const CONTENT_SNIFF_WINDOW: usize = 64 * 1024;
+
 #[derive(Debug, Clone)]
 pub struct FileValidationResult {
     pub is_valid: bool,
     pub errors: Vec<String>,
     pub warnings: Vec<String>,
+    pub detected_type: Option<String>,
+    pub content_type: String, // What to store the file as, after the content type policy
 }
 
 #[derive(Debug, Clone)]
@@ -17,12 +22,21 @@ pub struct FileAnalysis {
     pub metadata: std::collections::HashMap<String, String>,
 }
 
+/// An encoded thumbnail image
+#[derive(Debug, Clone)]
+pub struct GeneratedThumbnail {
+    pub data: Vec<u8>,
+    pub content_type: String,
+    pub width: u32,
+    pub height: u32,
+}
+
 #[automock]
 #[async_trait]
 pub trait FileService: Send + Sync {
     async fn validate_file(&self, filename: &str, content_type: &str, size: u64, data: &[u8]) -> Result<FileValidationResult>;
     async fn analyze_file(&self, filename: &str, data: &[u8]) -> Result<FileAnalysis>;
-    async fn generate_thumbnail(&self, file_path: &str, max_width: u32, max_height: u32) -> Result<Option<Vec<u8>>>;
+    async fn generate_thumbnail(&self, data: &[u8], content_type: &str, max_dimension: u32) -> Result<Option<GeneratedThumbnail>>;
     async fn extract_metadata(&self, filename: &str, data: &[u8]) -> Result<std::collections::HashMap<String, String>>;
     async fn is_allowed_file_type(&self, content_type: &str) -> bool;
     async fn get_max_file_size(&self) -> u64;
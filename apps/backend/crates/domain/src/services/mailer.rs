@@ -0,0 +1,45 @@
+use async_trait::async_trait;
+use kingshare_core::Result;
+use mockall::automock;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EmailMessage {
+    pub to: String,
+    pub subject: String,
+    pub text_body: String,
+    pub html_body: Option<String>,
+}
+
+/// Messages the application sends, each rendered from its own templates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmailTemplate {
+    VerifyEmail,
+    PasswordReset,
+    PasswordChanged,
+}
+
+impl EmailTemplate {
+    pub const ALL: [EmailTemplate; 3] = [Self::VerifyEmail, Self::PasswordReset, Self::PasswordChanged];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::VerifyEmail => "verify_email",
+            Self::PasswordReset => "password_reset",
+            Self::PasswordChanged => "password_changed",
+        }
+    }
+}
+
+/// Delivers email. An `Ok` means the message was handed off, not that it arrived.
+#[automock]
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, message: &EmailMessage) -> Result<()>;
+}
+
+/// Renders the subject and bodies of an `EmailTemplate`
+#[automock]
+pub trait MailTemplates: Send + Sync {
+    fn render(&self, template: EmailTemplate, to: &str, context: &serde_json::Value) -> Result<EmailMessage>;
+}
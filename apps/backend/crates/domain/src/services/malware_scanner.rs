@@ -0,0 +1,19 @@
+use crate::services::FileStream;
+use async_trait::async_trait;
+use kingshare_core::Result;
+use mockall::automock;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScanVerdict {
+    Clean,
+    Infected { signature: String },
+}
+
+/// Virus scanner consulted after every upload. An `Err` means the content could
+/// not be scanned, not that it is unsafe; the file stays pending.
+#[automock]
+#[async_trait]
+pub trait MalwareScanner: Send + Sync {
+    async fn scan_stream(&self, stream: FileStream) -> Result<ScanVerdict>;
+    async fn ping(&self) -> Result<()>;
+}
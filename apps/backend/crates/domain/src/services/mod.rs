@@ -1,9 +1,17 @@
 pub mod auth_service;
 pub mod file_service;
+pub mod mailer;
+pub mod malware_scanner;
+pub mod oidc_provider;
 pub mod storage_service;
+pub mod totp_service;
 pub mod websocket_service;
 
 pub use auth_service::*;
 pub use file_service::*;
+pub use mailer::*;
+pub use malware_scanner::*;
+pub use oidc_provider::*;
 pub use storage_service::*;
+pub use totp_service::*;
 pub use websocket_service::*;
\ No newline at end of file
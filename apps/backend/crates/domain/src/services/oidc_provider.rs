@@ -0,0 +1,16 @@
+use crate::entities::{OidcAuthorization, OidcIdentity, OidcLoginState};
+use async_trait::async_trait;
+use kingshare_core::Result;
+use mockall::automock;
+
+/// An OpenID Connect identity provider, driven through the authorization
+/// code flow with PKCE
+#[automock]
+#[async_trait]
+pub trait OidcProvider: Send + Sync {
+    /// A fresh state, nonce and PKCE verifier, and the authorization URL carrying them
+    async fn authorization_request(&self) -> Result<OidcAuthorization>;
+    /// Redeem the code the IdP redirected back with and validate the ID token
+    /// it returns against the provider's signing keys and `login`'s nonce
+    async fn exchange_code(&self, code: &str, login: &OidcLoginState) -> Result<OidcIdentity>;
+}
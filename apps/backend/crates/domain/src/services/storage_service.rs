@@ -1,7 +1,18 @@
+use crate::value_objects::ByteRange;
 use async_trait::async_trait;
-use kingshare_core::{Error, Result};
+use kingshare_core::{Result, Timestamp};
+use bytes::Bytes;
+use futures_util::stream::Stream;
 use mockall::automock;
-use std::path::Path;
+use std::pin::Pin;
+use std::time::Duration;
+use tokio::io::AsyncRead;
+
+/// Byte source for streaming writes into storage
+pub type FileReader = Pin<Box<dyn AsyncRead + Send>>;
+
+/// Chunked byte stream for serving stored files without buffering them
+pub type FileStream = Pin<Box<dyn Stream<Item = std::io::Result<Bytes>> + Send>>;
 
 #[derive(Debug, Clone)]
 pub struct StoredFile {
@@ -17,14 +28,53 @@ pub struct FileUpload {
     pub data: Vec<u8>,
 }
 
+/// An object present in storage, as seen when scanning for orphans
+#[derive(Debug, Clone)]
+pub struct StoredObject {
+    pub path: String,
+    pub size: u64,
+    pub last_modified: Timestamp,
+}
+
+/// Location a client can upload to directly, bypassing the API
+#[derive(Debug, Clone)]
+pub struct PresignedUpload {
+    pub url: String,
+    pub path: String,
+}
+
 #[automock]
 #[async_trait]
 pub trait StorageService: Send + Sync {
     async fn store_file(&self, upload: FileUpload) -> Result<StoredFile>;
+    async fn store_file_stream(&self, filename: &str, content_type: &str, reader: FileReader) -> Result<StoredFile>;
     async fn get_file(&self, path: &str) -> Result<Vec<u8>>;
+    async fn get_file_stream(&self, path: &str) -> Result<FileStream>;
+    async fn get_file_range_stream(&self, path: &str, range: ByteRange) -> Result<FileStream>;
     async fn delete_file(&self, path: &str) -> Result<()>;
     async fn file_exists(&self, path: &str) -> Result<bool>;
     async fn get_file_size(&self, path: &str) -> Result<u64>;
     async fn calculate_checksum(&self, data: &[u8]) -> String;
-    async fn cleanup_orphaned_files(&self, valid_paths: Vec<String>) -> Result<u64>;
+    async fn list_files(&self) -> Result<Vec<StoredObject>>;
+
+    // Move infected content out of the regular tree; returns its new path.
+    // Content that is already quarantined stays where it is.
+    async fn quarantine_file(&self, path: &str) -> Result<String>;
+
+    // Re-wrap data keys under the current master key; returns how many changed
+    async fn rotate_encryption_keys(&self) -> Result<u64>;
+
+    // Direct client access; `None` when the backend can't issue signed URLs
+    async fn presigned_get_url(&self, path: &str, expires_in: Duration) -> Result<Option<String>>;
+    async fn presigned_put_url(&self, filename: &str, content_type: &str, expires_in: Duration) -> Result<Option<PresignedUpload>>;
+
+    // Resumable uploads. A chunk is streamed from `reader` and rejected once it
+    // runs past `limit` bytes; returns the new offset. A chunk that fails is
+    // rolled back to `offset` where the backend can; S3 keeps the parts it
+    // already completed, which `upload_offset` then reports.
+    async fn begin_upload(&self, filename: &str, content_type: &str) -> Result<String>;
+    async fn append_upload_chunk(&self, handle: &str, offset: u64, reader: FileReader, limit: u64) -> Result<u64>;
+    async fn upload_offset(&self, handle: &str) -> Result<u64>;
+    async fn complete_upload(&self, handle: &str, filename: &str) -> Result<StoredFile>;
+    async fn abort_upload(&self, handle: &str) -> Result<()>;
 }
\ No newline at end of file
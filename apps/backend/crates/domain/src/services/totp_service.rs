@@ -0,0 +1,19 @@
+use kingshare_core::{Result, Timestamp};
+use mockall::automock;
+
+/// RFC 6238 one-time passwords and the recovery codes that stand in for them
+#[automock]
+pub trait TotpService: Send + Sync {
+    /// A new random base32 secret
+    fn generate_secret(&self) -> String;
+    /// The `otpauth://` URI authenticator apps import, usually via a QR code
+    fn otpauth_uri(&self, secret: &str, account_name: &str) -> String;
+    /// The code for `secret` at `time`
+    fn generate_code(&self, secret: &str, time: Timestamp) -> Result<String>;
+    /// The time step `code` belongs to, if it is valid at `now`. Codes from the
+    /// neighbouring steps are accepted to allow for clock drift.
+    fn verify_code(&self, secret: &str, code: &str, now: Timestamp) -> Result<Option<i64>>;
+    fn generate_recovery_codes(&self, count: usize) -> Vec<String>;
+    /// Hash of a recovery code as entered, ignoring case, spaces and dashes
+    fn hash_recovery_code(&self, code: &str) -> String;
+}
@@ -15,6 +15,9 @@ pub trait WebSocketService: Send + Sync {
     async fn send_to_user(&self, user_id: Id, message: WebSocketMessage) -> Result<()>;
     async fn broadcast(&self, message: WebSocketMessage) -> Result<()>;
     async fn broadcast_to_users(&self, user_ids: Vec<Id>, message: WebSocketMessage) -> Result<()>;
+    /// Close a user's connections that were opened in any of `session_ids`.
+    /// Returns the number of connections closed.
+    async fn close_session_connections(&self, user_id: Id, session_ids: Vec<Id>) -> Result<u64>;
     async fn get_online_users(&self) -> Result<Vec<Id>>;
     async fn is_user_online(&self, user_id: Id) -> Result<bool>;
     async fn cleanup_inactive_connections(&self, timeout_seconds: u64) -> Result<u64>;
@@ -186,4 +186,116 @@ impl From<Password> for String {
     fn from(password: Password) -> Self {
         password.0
     }
-}
\ No newline at end of file
+}
+
+/// Inclusive byte range within a stored file, as used by HTTP `Range` requests
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum RangeError {
+    #[error("Malformed range header: {0}")]
+    Malformed(String),
+
+    #[error("Range not satisfiable for size {size}")]
+    Unsatisfiable { size: u64 },
+}
+
+impl ByteRange {
+    const UNIT_PREFIX: &'static str = "bytes=";
+    const MAX_RANGES: usize = 16;
+
+    pub fn new(start: u64, end: u64) -> Self {
+        ByteRange { start, end }
+    }
+
+    /// Bounds are inclusive; a range ending before it starts covers nothing
+    pub fn len(&self) -> u64 {
+        if self.is_empty() {
+            0
+        } else {
+            self.end - self.start + 1
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.end < self.start
+    }
+
+    /// Parse a `Range` header value against a resource of `size` bytes.
+    ///
+    /// Unsatisfiable specs are dropped; the request fails only if none remain.
+    pub fn parse_header(value: &str, size: u64) -> Result<Vec<ByteRange>, RangeError> {
+        let specs = value
+            .trim()
+            .strip_prefix(Self::UNIT_PREFIX)
+            .ok_or_else(|| RangeError::Malformed(value.to_string()))?;
+
+        let specs: Vec<&str> = specs
+            .split(',')
+            .map(str::trim)
+            .filter(|spec| !spec.is_empty())
+            .collect();
+
+        // Refuse pathological headers rather than fanning out into many reads
+        if specs.is_empty() || specs.len() > Self::MAX_RANGES {
+            return Err(RangeError::Malformed(value.to_string()));
+        }
+
+        let mut ranges = Vec::new();
+        for spec in specs {
+            let (first, last) = spec
+                .split_once('-')
+                .ok_or_else(|| RangeError::Malformed(value.to_string()))?;
+            let first = first.trim();
+            let last = last.trim();
+
+            let range = if first.is_empty() {
+                // Suffix range: the final N bytes
+                let suffix: u64 = last
+                    .parse()
+                    .map_err(|_| RangeError::Malformed(value.to_string()))?;
+                if suffix == 0 || size == 0 {
+                    None
+                } else {
+                    Some(ByteRange::new(size.saturating_sub(suffix), size - 1))
+                }
+            } else {
+                let start: u64 = first
+                    .parse()
+                    .map_err(|_| RangeError::Malformed(value.to_string()))?;
+                let end = if last.is_empty() {
+                    u64::MAX
+                } else {
+                    last.parse()
+                        .map_err(|_| RangeError::Malformed(value.to_string()))?
+                };
+                if end < start {
+                    return Err(RangeError::Malformed(value.to_string()));
+                }
+                if start >= size {
+                    None
+                } else {
+                    Some(ByteRange::new(start, end.min(size - 1)))
+                }
+            };
+
+            if let Some(range) = range {
+                ranges.push(range);
+            }
+        }
+
+        if ranges.is_empty() {
+            return Err(RangeError::Unsatisfiable { size });
+        }
+
+        Ok(ranges)
+    }
+
+    pub fn content_range(&self, size: u64) -> String {
+        format!("bytes {}-{}/{}", self.start, self.end, size)
+    }
+}
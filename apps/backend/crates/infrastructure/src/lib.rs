@@ -7,5 +7,14 @@ pub use repositories::*;
 pub use services::*;
 
 // Re-export commonly used implementations
-pub use repositories::{PostgresFileRepository, PostgresShareRepository, PostgresUserRepository};
-pub use services::{DefaultFileService, InMemoryWebSocketService, JwtAuthService, LocalStorageService};
\ No newline at end of file
+pub use repositories::{
+    InMemoryTokenRepository, PostgresApiTokenRepository, PostgresBlobRepository, PostgresDriveRepository,
+    PostgresFileRepository, PostgresIdentityRepository, PostgresLockoutRepository, PostgresMfaRepository, PostgresQuotaRepository,
+    PostgresRoleRepository, PostgresSecurityEventRepository, PostgresShareRepository, PostgresTeamRepository,
+    PostgresThumbnailRepository, PostgresTokenRepository, PostgresUploadSessionRepository, PostgresUserRepository,
+};
+pub use services::{
+    ClamdScanner, DefaultDriveService, DefaultFileService, DefaultTotpService, FileMailer, HandlebarsMailTemplates,
+    InMemoryMailer, InMemoryWebSocketService, JwtAuthService, KeyRing, LocalStorageService, OidcClient,
+    S3StorageService, SmtpMailer,
+};
\ No newline at end of file
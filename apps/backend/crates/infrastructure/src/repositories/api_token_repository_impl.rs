@@ -0,0 +1,183 @@
+use async_trait::async_trait;
+use kingshare_core::{Error, Id, Result};
+use kingshare_domain::{
+    entities::{ApiScope, ApiToken},
+    repositories::ApiTokenRepository,
+};
+use sqlx::PgPool;
+use tracing::{info, instrument, warn};
+
+#[derive(Debug, Clone)]
+pub struct PostgresApiTokenRepository {
+    pool: PgPool,
+}
+
+impl PostgresApiTokenRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+/// Scopes dropped from a later release are ignored rather than failing the
+/// whole token, which can only narrow what it is allowed to do
+fn parse_scopes(scopes: Vec<String>) -> Vec<ApiScope> {
+    scopes
+        .into_iter()
+        .filter_map(|scope| match scope.parse() {
+            Ok(scope) => Some(scope),
+            Err(_) => {
+                warn!(scope = %scope, "Ignoring unknown API token scope");
+                None
+            }
+        })
+        .collect()
+}
+
+#[async_trait]
+impl ApiTokenRepository for PostgresApiTokenRepository {
+    #[instrument(skip(self, token), fields(user_id = %token.user_id))]
+    async fn create(&self, token: ApiToken) -> Result<ApiToken> {
+        let scopes: Vec<String> = token.scopes.iter().map(|scope| scope.as_str().to_string()).collect();
+
+        sqlx::query!(
+            r#"
+            INSERT INTO api_tokens (id, user_id, name, display_prefix, token_hash, scopes, drive_id,
+                                    created_at, expires_at, last_used_at, revoked_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+            "#,
+            token.id,
+            token.user_id,
+            token.name,
+            token.display_prefix,
+            token.token_hash,
+            &scopes,
+            token.drive_id,
+            token.created_at,
+            token.expires_at,
+            token.last_used_at,
+            token.revoked_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        info!(token_id = %token.id, "API token created");
+        Ok(token)
+    }
+
+    #[instrument(skip(self, token_hash))]
+    async fn find_by_hash(&self, token_hash: &str) -> Result<Option<ApiToken>> {
+        let row = sqlx::query!(
+            r#"
+            SELECT id, user_id, name, display_prefix, token_hash, scopes, drive_id,
+                   created_at, expires_at, last_used_at, revoked_at
+            FROM api_tokens WHERE token_hash = $1
+            "#,
+            token_hash
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        Ok(row.map(|row| ApiToken {
+            id: row.id,
+            user_id: row.user_id,
+            name: row.name,
+            display_prefix: row.display_prefix,
+            token_hash: row.token_hash,
+            scopes: parse_scopes(row.scopes),
+            drive_id: row.drive_id,
+            created_at: row.created_at,
+            expires_at: row.expires_at,
+            last_used_at: row.last_used_at,
+            revoked_at: row.revoked_at,
+        }))
+    }
+
+    #[instrument(skip(self))]
+    async fn list_active(&self, user_id: Id) -> Result<Vec<ApiToken>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT id, user_id, name, display_prefix, token_hash, scopes, drive_id,
+                   created_at, expires_at, last_used_at, revoked_at
+            FROM api_tokens
+            WHERE user_id = $1 AND revoked_at IS NULL AND expires_at > NOW()
+            ORDER BY created_at DESC
+            "#,
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| ApiToken {
+                id: row.id,
+                user_id: row.user_id,
+                name: row.name,
+                display_prefix: row.display_prefix,
+                token_hash: row.token_hash,
+                scopes: parse_scopes(row.scopes),
+                drive_id: row.drive_id,
+                created_at: row.created_at,
+                expires_at: row.expires_at,
+                last_used_at: row.last_used_at,
+                revoked_at: row.revoked_at,
+            })
+            .collect())
+    }
+
+    #[instrument(skip(self))]
+    async fn count_active(&self, user_id: Id) -> Result<u64> {
+        let count = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) as "count!" FROM api_tokens
+            WHERE user_id = $1 AND revoked_at IS NULL AND expires_at > NOW()
+            "#,
+            user_id
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        Ok(count as u64)
+    }
+
+    #[instrument(skip(self))]
+    async fn revoke(&self, id: Id, user_id: Id) -> Result<bool> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE api_tokens SET revoked_at = NOW()
+            WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL
+            "#,
+            id,
+            user_id
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        let revoked = result.rows_affected() > 0;
+        if revoked {
+            info!(token_id = %id, "API token revoked");
+        }
+        Ok(revoked)
+    }
+
+    #[instrument(skip(self))]
+    async fn record_use(&self, id: Id) -> Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE api_tokens SET last_used_at = NOW()
+            WHERE id = $1 AND (last_used_at IS NULL OR last_used_at < NOW() - INTERVAL '1 minute')
+            "#,
+            id
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,177 @@
+use async_trait::async_trait;
+use kingshare_core::{Error, Result, Timestamp};
+use kingshare_domain::{entities::Blob, repositories::BlobRepository};
+use sqlx::PgPool;
+use tracing::{info, instrument};
+
+#[derive(Debug, Clone)]
+pub struct PostgresBlobRepository {
+    pool: PgPool,
+}
+
+impl PostgresBlobRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl BlobRepository for PostgresBlobRepository {
+    #[instrument(skip(self))]
+    async fn acquire(&self, checksum: &str, storage_path: &str, size: i64) -> Result<Blob> {
+        // Single statement so concurrent uploads of the same content both count.
+        // A blob marked for collection may already have lost its content.
+        let row = sqlx::query!(
+            r#"
+            INSERT INTO blobs (checksum, storage_path, size, ref_count)
+            VALUES ($1, $2, $3, 1)
+            ON CONFLICT (checksum)
+            DO UPDATE SET ref_count = blobs.ref_count + 1
+            WHERE NOT blobs.collecting
+            RETURNING checksum, storage_path, size, ref_count, created_at, updated_at
+            "#,
+            checksum,
+            storage_path,
+            size
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(Error::Database)?
+        .ok_or_else(|| {
+            Error::Conflict("This content is being removed from storage; try again shortly".to_string())
+        })?;
+
+        Ok(Blob {
+            checksum: row.checksum,
+            storage_path: row.storage_path,
+            size: row.size,
+            ref_count: row.ref_count,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn release(&self, checksum: &str) -> Result<Option<Blob>> {
+        let row = sqlx::query!(
+            r#"
+            UPDATE blobs
+            SET ref_count = ref_count - 1
+            WHERE checksum = $1 AND ref_count > 0
+            RETURNING checksum, storage_path, size, ref_count, created_at, updated_at
+            "#,
+            checksum
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        Ok(row.map(|row| Blob {
+            checksum: row.checksum,
+            storage_path: row.storage_path,
+            size: row.size,
+            ref_count: row.ref_count,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        }))
+    }
+
+    #[instrument(skip(self))]
+    async fn find_by_checksum(&self, checksum: &str) -> Result<Option<Blob>> {
+        let row = sqlx::query!(
+            r#"
+            SELECT checksum, storage_path, size, ref_count, created_at, updated_at
+            FROM blobs WHERE checksum = $1
+            "#,
+            checksum
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        Ok(row.map(|row| Blob {
+            checksum: row.checksum,
+            storage_path: row.storage_path,
+            size: row.size,
+            ref_count: row.ref_count,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        }))
+    }
+
+    #[instrument(skip(self, paths))]
+    async fn find_known_paths(&self, paths: Vec<String>) -> Result<Vec<String>> {
+        let rows = sqlx::query!(
+            "SELECT storage_path FROM blobs WHERE storage_path = ANY($1)",
+            &paths
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        Ok(rows.into_iter().map(|row| row.storage_path).collect())
+    }
+
+    #[instrument(skip(self))]
+    async fn find_unreferenced(&self, idle_since: Timestamp, limit: i64) -> Result<Vec<Blob>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT checksum, storage_path, size, ref_count, created_at, updated_at
+            FROM blobs
+            WHERE ref_count = 0 AND updated_at < $1
+            ORDER BY updated_at
+            LIMIT $2
+            "#,
+            idle_since,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        let blobs = rows
+            .into_iter()
+            .map(|row| Blob {
+                checksum: row.checksum,
+                storage_path: row.storage_path,
+                size: row.size,
+                ref_count: row.ref_count,
+                created_at: row.created_at,
+                updated_at: row.updated_at,
+            })
+            .collect();
+
+        Ok(blobs)
+    }
+
+    #[instrument(skip(self))]
+    async fn mark_collecting(&self, checksum: &str) -> Result<bool> {
+        // Re-check the count so a blob acquired since it was listed survives
+        let result = sqlx::query!(
+            "UPDATE blobs SET collecting = TRUE WHERE checksum = $1 AND ref_count = 0",
+            checksum
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    #[instrument(skip(self))]
+    async fn delete_unreferenced(&self, checksum: &str) -> Result<bool> {
+        let result = sqlx::query!(
+            "DELETE FROM blobs WHERE checksum = $1 AND ref_count = 0 AND collecting",
+            checksum
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        let deleted = result.rows_affected() == 1;
+        if deleted {
+            info!(checksum = %checksum, "Blob deleted successfully");
+        }
+        Ok(deleted)
+    }
+}
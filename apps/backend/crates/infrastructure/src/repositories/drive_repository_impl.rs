@@ -0,0 +1,2431 @@
+use super::user_repository_impl::like_pattern;
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHasher, SaltString},
+    Argon2,
+};
+use async_trait::async_trait;
+use kingshare_core::{Error, Id, Result, Timestamp};
+use kingshare_domain::{
+    entities::{
+        ActivityDetails, CreateSharingLinkRequest, DocumentContent, Drive, DriveActivity, DriveItem,
+        DriveItemResponse, DriveItemType, DriveQuery, Folder, FolderContents, FolderPermissions, FolderStats,
+        ItemMetadata, ItemOwner, ItemPermissions, ItemSource, SearchFilters, SearchMatch, SearchScope, Share,
+        ShareItemRequest, TermKind, UnindexedItem, FOLDER_MIME_TYPE,
+    },
+    entities::document::TextFormat,
+    entities::drive::PublicAccessLevel,
+    repositories::DriveRepository,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sqlx::{PgConnection, PgPool};
+use std::collections::HashMap;
+use tracing::{info, instrument};
+
+/// Search results are capped; narrow the query to see more
+const SEARCH_LIMIT: i64 = 200;
+/// Bytes of an item's text kept for search; tsvectors are capped at 1 MB
+const MAX_INDEXED_CONTENT: usize = 256 * 1024;
+const HIGHLIGHT_OPTIONS: &str = "StartSel=<mark>, StopSel=</mark>, MaxFragments=2, MaxWords=20, MinWords=5";
+
+#[derive(Debug, Clone)]
+pub struct PostgresDriveRepository {
+    pool: PgPool,
+    default_user_quota: Option<i64>,
+}
+
+impl PostgresDriveRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            pool,
+            default_user_quota: None,
+        }
+    }
+
+    /// Quota for users without one of their own when charging for drive
+    /// items. Without it such users are unlimited.
+    pub fn with_default_user_quota(mut self, default_user_quota: Option<i64>) -> Self {
+        self.default_user_quota = default_user_quota;
+        self
+    }
+}
+
+struct DriveRow {
+    id: Id,
+    owner_id: Id,
+    name: String,
+    description: Option<String>,
+    drive_type: String,
+    storage_quota: i64,
+    storage_used: i64,
+    settings: serde_json::Value,
+    is_shared: bool,
+    team_id: Option<Id>,
+    created_at: Timestamp,
+    updated_at: Timestamp,
+}
+
+impl TryFrom<DriveRow> for Drive {
+    type Error = Error;
+
+    fn try_from(row: DriveRow) -> Result<Self> {
+        Ok(Drive {
+            id: row.id,
+            owner_id: row.owner_id,
+            name: row.name,
+            description: row.description,
+            drive_type: parse_enum(&row.drive_type)?,
+            storage_quota: row.storage_quota,
+            storage_used: row.storage_used,
+            // Drives created in SQL, such as the sample data, start with `{}`
+            settings: serde_json::from_value(row.settings).unwrap_or_default(),
+            is_shared: row.is_shared,
+            team_id: row.team_id,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        })
+    }
+}
+
+struct FolderRow {
+    id: Id,
+    drive_id: Id,
+    parent_id: Option<Id>,
+    name: String,
+    description: Option<String>,
+    color: Option<String>,
+    path: String,
+    owner_id: Id,
+    permissions: serde_json::Value,
+    shares: serde_json::Value,
+    is_starred: bool,
+    is_trashed: bool,
+    created_at: Timestamp,
+    updated_at: Timestamp,
+    trashed_at: Option<Timestamp>,
+}
+
+impl TryFrom<FolderRow> for Folder {
+    type Error = Error;
+
+    fn try_from(row: FolderRow) -> Result<Self> {
+        let stored: StoredFolderPermissions = serde_json::from_value(row.permissions)?;
+        Ok(Folder {
+            id: row.id,
+            drive_id: row.drive_id,
+            parent_id: row.parent_id,
+            name: row.name,
+            description: row.description,
+            color: row.color,
+            path: row.path,
+            permissions: FolderPermissions {
+                owner_id: row.owner_id,
+                shared_with: serde_json::from_value(row.shares)?,
+                public_access: stored.public_access,
+                inherit_permissions: stored.inherit_permissions,
+            },
+            is_starred: row.is_starred,
+            is_trashed: row.is_trashed,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+            trashed_at: row.trashed_at,
+        })
+    }
+}
+
+struct ItemRow {
+    id: Id,
+    drive_id: Id,
+    parent_id: Option<Id>,
+    name: String,
+    item_type: String,
+    mime_type: String,
+    size: i64,
+    path: String,
+    owner_id: Id,
+    metadata: serde_json::Value,
+    permissions: serde_json::Value,
+    source_id: Option<Id>,
+    shares: serde_json::Value,
+    link: Option<serde_json::Value>,
+    is_starred: bool,
+    is_trashed: bool,
+    created_at: Timestamp,
+    updated_at: Timestamp,
+    trashed_at: Option<Timestamp>,
+    last_accessed_at: Option<Timestamp>,
+}
+
+impl TryFrom<ItemRow> for DriveItem {
+    type Error = Error;
+
+    fn try_from(row: ItemRow) -> Result<Self> {
+        let stored: StoredItemPermissions = serde_json::from_value(row.permissions)?;
+        Ok(DriveItem {
+            id: row.id,
+            drive_id: row.drive_id,
+            parent_id: row.parent_id,
+            name: row.name,
+            item_type: parse_enum(&row.item_type)?,
+            mime_type: row.mime_type,
+            size: row.size,
+            path: row.path,
+            permissions: ItemPermissions {
+                owner_id: row.owner_id,
+                shared_with: serde_json::from_value(row.shares)?,
+                public_access: stored.public_access,
+                sharing_link: row.link.map(serde_json::from_value).transpose()?,
+            },
+            metadata: serde_json::from_value(row.metadata)?,
+            source_id: row.source_id,
+            is_starred: row.is_starred,
+            is_trashed: row.is_trashed,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+            trashed_at: row.trashed_at,
+            last_accessed_at: row.last_accessed_at,
+        })
+    }
+}
+
+struct ActivityRow {
+    id: Id,
+    drive_id: Id,
+    item_id: Option<Id>,
+    user_id: Id,
+    activity_type: String,
+    details: serde_json::Value,
+    ip_address: Option<String>,
+    user_agent: Option<String>,
+    created_at: Timestamp,
+}
+
+impl TryFrom<ActivityRow> for DriveActivity {
+    type Error = Error;
+
+    fn try_from(row: ActivityRow) -> Result<Self> {
+        Ok(DriveActivity {
+            id: row.id,
+            drive_id: row.drive_id,
+            item_id: row.item_id,
+            user_id: row.user_id,
+            activity_type: parse_enum(&row.activity_type)?,
+            details: serde_json::from_value::<ActivityDetails>(row.details)?,
+            ip_address: row.ip_address,
+            user_agent: row.user_agent,
+            created_at: row.created_at,
+        })
+    }
+}
+
+/// The part of an item's permissions kept in `drive_items.permissions`;
+/// shares and links have tables of their own
+#[derive(Serialize, Deserialize)]
+struct StoredItemPermissions {
+    #[serde(default = "private_access")]
+    public_access: PublicAccessLevel,
+}
+
+/// `folders.permissions`; the owner is `folders.owner_id` and shares are
+/// those of the folder's item
+#[derive(Serialize, Deserialize)]
+struct StoredFolderPermissions {
+    #[serde(default = "private_access")]
+    public_access: PublicAccessLevel,
+    #[serde(default = "inherits")]
+    inherit_permissions: bool,
+}
+
+fn private_access() -> PublicAccessLevel {
+    PublicAccessLevel::Private
+}
+
+fn inherits() -> bool {
+    true
+}
+
+/// Enums are stored by their variant name, such as `Personal` or `Spreadsheet`
+fn enum_name<T: Serialize>(value: &T) -> Result<String> {
+    match serde_json::to_value(value)? {
+        serde_json::Value::String(name) => Ok(name),
+        other => Err(Error::Internal(format!("Unexpected stored value {}", other))),
+    }
+}
+
+fn parse_enum<T: DeserializeOwned>(name: &str) -> Result<T> {
+    Ok(serde_json::from_value(serde_json::Value::String(name.to_string()))?)
+}
+
+fn item_permissions_json(permissions: &ItemPermissions) -> Result<serde_json::Value> {
+    Ok(serde_json::to_value(StoredItemPermissions {
+        public_access: permissions.public_access.clone(),
+    })?)
+}
+
+fn folder_permissions_json(permissions: &FolderPermissions) -> Result<serde_json::Value> {
+    Ok(serde_json::to_value(StoredFolderPermissions {
+        public_access: permissions.public_access.clone(),
+        inherit_permissions: permissions.inherit_permissions,
+    })?)
+}
+
+/// Path of a child, given its parent's path ("" for the drive root)
+fn child_path(parent_path: &str, name: &str) -> String {
+    format!("{}/{}", parent_path.trim_end_matches('/'), name)
+}
+
+fn validate_name(name: &str) -> Result<()> {
+    if name.trim().is_empty() {
+        return Err(Error::Validation("Name can't be empty".to_string()));
+    }
+    if name.contains('/') {
+        return Err(Error::Validation("Names can't contain '/'".to_string()));
+    }
+    Ok(())
+}
+
+/// How a search scope is passed to SQL: its name and, for a drive, the drive id
+fn scope_parts(scope: SearchScope) -> (&'static str, Option<Id>) {
+    match scope {
+        SearchScope::Drive(drive_id) => ("drive", Some(drive_id)),
+        SearchScope::Recent => ("recent", None),
+        SearchScope::Starred => ("starred", None),
+        SearchScope::SharedWithMe => ("shared_with_me", None),
+    }
+}
+
+/// The drive item that lists a folder alongside files; it shares the folder's id
+fn folder_item(folder: &Folder) -> DriveItem {
+    let mut item = DriveItem::new(
+        folder.drive_id,
+        folder.permissions.owner_id,
+        folder.name.clone(),
+        DriveItemType::Folder,
+        FOLDER_MIME_TYPE.to_string(),
+        0,
+        folder.parent_id,
+    );
+    item.id = folder.id;
+    item.path = folder.path.clone();
+    item.metadata.description = folder.description.clone();
+    item.created_at = folder.created_at;
+    item.updated_at = folder.updated_at;
+    item
+}
+
+fn hash_link_password(password: &str) -> Result<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| Error::PasswordHash(e.to_string()))
+}
+
+/// Load items by id, in the order of `ids`
+async fn load_items(conn: &mut PgConnection, ids: &[Id]) -> Result<Vec<DriveItem>> {
+    if ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let rows = sqlx::query_as!(
+        ItemRow,
+        r#"
+        SELECT i.id, i.drive_id, i.parent_id, i.name, i.item_type, i.mime_type, i.size, i.path,
+               i.owner_id, i.metadata, i.permissions, i.source_id,
+               COALESCE((
+                   SELECT jsonb_agg(jsonb_build_object(
+                       'user_id', s.shared_with_user_id,
+                       'permissions', s.permissions,
+                       'role', s.role,
+                       'granted_by', s.shared_by_user_id,
+                       'granted_at', s.created_at,
+                       'expires_at', s.expires_at,
+                       'notification_sent', s.notification_sent
+                   ) ORDER BY s.created_at)
+                   FROM item_shares s WHERE s.item_id = i.id
+               ), '[]'::jsonb) AS "shares!",
+               (
+                   SELECT jsonb_build_object(
+                       'token', l.token,
+                       'access_level', l.access_level,
+                       'password_protected', l.password_hash IS NOT NULL,
+                       'password_hash', NULL,
+                       'expires_at', l.expires_at,
+                       'access_count', l.access_count,
+                       'created_at', l.created_at
+                   )
+                   FROM sharing_links l
+                   WHERE l.item_id = i.id AND (l.expires_at IS NULL OR l.expires_at > NOW())
+                   ORDER BY l.created_at DESC
+                   LIMIT 1
+               ) AS link,
+               i.is_starred, i.is_trashed, i.created_at, i.updated_at, i.trashed_at, i.last_accessed_at
+        FROM drive_items i
+        WHERE i.id = ANY($1)
+        "#,
+        ids
+    )
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(Error::Database)?;
+
+    let mut items: HashMap<Id, DriveItem> = HashMap::with_capacity(rows.len());
+    for row in rows {
+        let item = DriveItem::try_from(row)?;
+        items.insert(item.id, item);
+    }
+    Ok(ids.iter().filter_map(|id| items.remove(id)).collect())
+}
+
+async fn load_item(conn: &mut PgConnection, id: Id) -> Result<DriveItem> {
+    load_items(conn, &[id])
+        .await?
+        .pop()
+        .ok_or_else(|| Error::NotFound("Item not found".to_string()))
+}
+
+async fn insert_item(conn: &mut PgConnection, item: &DriveItem) -> Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO drive_items (
+            id, drive_id, parent_id, name, item_type, mime_type, size, path, owner_id,
+            metadata, permissions, source_id, is_starred, is_trashed, created_at, updated_at, trashed_at,
+            last_accessed_at
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18)
+        "#,
+        item.id,
+        item.drive_id,
+        item.parent_id,
+        item.name,
+        enum_name(&item.item_type)?,
+        item.mime_type,
+        item.size,
+        item.path,
+        item.permissions.owner_id,
+        serde_json::to_value(&item.metadata)?,
+        item_permissions_json(&item.permissions)?,
+        item.source_id,
+        item.is_starred,
+        item.is_trashed,
+        item.created_at,
+        item.updated_at,
+        item.trashed_at,
+        item.last_accessed_at
+    )
+    .execute(&mut *conn)
+    .await
+    .map_err(Error::Database)?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO drive_item_tree (ancestor_id, descendant_id, depth)
+        SELECT $1::UUID, $1::UUID, 0
+        UNION ALL
+        SELECT ancestor_id, $1, depth + 1 FROM drive_item_tree WHERE descendant_id = $2
+        "#,
+        item.id,
+        item.parent_id
+    )
+    .execute(&mut *conn)
+    .await
+    .map_err(Error::Database)?;
+    Ok(())
+}
+
+async fn insert_folder(conn: &mut PgConnection, folder: &Folder) -> Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO folders (
+            id, drive_id, parent_id, name, description, color, path, owner_id, permissions,
+            is_starred, is_trashed, created_at, updated_at, trashed_at
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
+        "#,
+        folder.id,
+        folder.drive_id,
+        folder.parent_id,
+        folder.name,
+        folder.description,
+        folder.color,
+        folder.path,
+        folder.permissions.owner_id,
+        folder_permissions_json(&folder.permissions)?,
+        folder.is_starred,
+        folder.is_trashed,
+        folder.created_at,
+        folder.updated_at,
+        folder.trashed_at
+    )
+    .execute(&mut *conn)
+    .await
+    .map_err(Error::Database)?;
+
+    insert_item(conn, &folder_item(folder)).await
+}
+
+/// Path of the folder new children go into, after checking it can take them
+async fn destination_path(conn: &mut PgConnection, drive_id: Id, parent_id: Option<Id>) -> Result<String> {
+    let Some(parent_id) = parent_id else {
+        return Ok(String::new());
+    };
+
+    let parent = sqlx::query!(
+        "SELECT drive_id, path, is_trashed FROM folders WHERE id = $1",
+        parent_id
+    )
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(Error::Database)?
+    .ok_or_else(|| Error::NotFound("Folder not found".to_string()))?;
+
+    if parent.drive_id != drive_id {
+        return Err(Error::BadRequest("The folder is in another drive".to_string()));
+    }
+    if parent.is_trashed {
+        return Err(Error::BadRequest("The folder is in the trash".to_string()));
+    }
+    Ok(parent.path)
+}
+
+/// Hold the shape of a drive's tree until the transaction ends. Moves take
+/// it before any row, so two of them can't each pass the check that a folder
+/// isn't going below itself and then form a loop together.
+async fn lock_drive_tree(conn: &mut PgConnection, drive_id: Id) -> Result<()> {
+    sqlx::query!(
+        r#"SELECT 1 AS "locked!" FROM pg_advisory_xact_lock(hashtextextended('drive_tree:' || $1::text, 0))"#,
+        drive_id.to_string()
+    )
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(Error::Database)?;
+    Ok(())
+}
+
+/// Whether `candidate_id` is `folder_id` or somewhere below it
+async fn is_within(conn: &mut PgConnection, folder_id: Id, candidate_id: Id) -> Result<bool> {
+    let within = sqlx::query_scalar!(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM drive_item_tree WHERE ancestor_id = $1 AND descendant_id = $2
+        ) AS "within!"
+        "#,
+        folder_id,
+        candidate_id
+    )
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(Error::Database)?;
+    Ok(within)
+}
+
+/// Hang an item, and whatever is below it, under `new_parent_id` in the tree
+async fn reattach(conn: &mut PgConnection, item_id: Id, new_parent_id: Option<Id>) -> Result<()> {
+    sqlx::query!(
+        r#"
+        DELETE FROM drive_item_tree
+        WHERE descendant_id IN (SELECT descendant_id FROM drive_item_tree WHERE ancestor_id = $1)
+          AND ancestor_id NOT IN (SELECT descendant_id FROM drive_item_tree WHERE ancestor_id = $1)
+        "#,
+        item_id
+    )
+    .execute(&mut *conn)
+    .await
+    .map_err(Error::Database)?;
+    sqlx::query!(
+        r#"
+        INSERT INTO drive_item_tree (ancestor_id, descendant_id, depth)
+        SELECT above.ancestor_id, below.descendant_id, above.depth + below.depth + 1
+        FROM drive_item_tree above CROSS JOIN drive_item_tree below
+        WHERE above.descendant_id = $2 AND below.ancestor_id = $1
+        "#,
+        item_id,
+        new_parent_id
+    )
+    .execute(&mut *conn)
+    .await
+    .map_err(Error::Database)?;
+    Ok(())
+}
+
+/// Rename or move a folder, carrying the paths of everything below it along.
+/// The caller holds the drive's tree lock.
+async fn relocate_folder(conn: &mut PgConnection, folder_id: Id, new_parent_id: Option<Id>, name: &str) -> Result<String> {
+    validate_name(name)?;
+    let folder = sqlx::query!(
+        "SELECT drive_id, parent_id, path FROM folders WHERE id = $1 FOR UPDATE",
+        folder_id
+    )
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(Error::Database)?
+    .ok_or_else(|| Error::NotFound("Folder not found".to_string()))?;
+
+    let parent_path = destination_path(conn, folder.drive_id, new_parent_id).await?;
+    if let Some(parent_id) = new_parent_id {
+        if is_within(conn, folder_id, parent_id).await? {
+            return Err(Error::BadRequest("A folder can't be moved into itself".to_string()));
+        }
+    }
+
+    sqlx::query!(
+        "UPDATE folders SET parent_id = $2, name = $3 WHERE id = $1",
+        folder_id,
+        new_parent_id,
+        name
+    )
+    .execute(&mut *conn)
+    .await
+    .map_err(Error::Database)?;
+    sqlx::query!(
+        "UPDATE drive_items SET parent_id = $2, name = $3 WHERE id = $1",
+        folder_id,
+        new_parent_id,
+        name
+    )
+    .execute(&mut *conn)
+    .await
+    .map_err(Error::Database)?;
+    if new_parent_id != folder.parent_id {
+        reattach(conn, folder_id, new_parent_id).await?;
+    }
+
+    let new_path = child_path(&parent_path, name);
+    if new_path != folder.path {
+        // Every path below starts with the folder's own
+        let keep_from = folder.path.chars().count() as i32 + 1;
+        sqlx::query!(
+            r#"
+            UPDATE folders SET path = $2 || substr(path, $3)
+            WHERE id IN (SELECT descendant_id FROM drive_item_tree WHERE ancestor_id = $1)
+            "#,
+            folder_id,
+            new_path,
+            keep_from
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(Error::Database)?;
+        sqlx::query!(
+            r#"
+            UPDATE drive_items SET path = $2 || substr(path, $3)
+            WHERE id IN (SELECT descendant_id FROM drive_item_tree WHERE ancestor_id = $1)
+            "#,
+            folder_id,
+            new_path,
+            keep_from
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(Error::Database)?;
+    }
+    Ok(new_path)
+}
+
+/// Put an item under `new_parent_id` with `name`
+async fn place_item(conn: &mut PgConnection, item_id: Id, new_parent_id: Option<Id>, name: Option<&str>) -> Result<()> {
+    // Items never change drives, so the drive can be read before its lock
+    let drive_id = sqlx::query_scalar!("SELECT drive_id FROM drive_items WHERE id = $1", item_id)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(Error::Database)?
+        .ok_or_else(|| Error::NotFound("Item not found".to_string()))?;
+    lock_drive_tree(conn, drive_id).await?;
+
+    let item = sqlx::query!(
+        "SELECT drive_id, parent_id, item_type, name FROM drive_items WHERE id = $1 FOR UPDATE",
+        item_id
+    )
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(Error::Database)?
+    .ok_or_else(|| Error::NotFound("Item not found".to_string()))?;
+    let name = name.unwrap_or(&item.name);
+
+    if item.item_type == "Folder" {
+        relocate_folder(conn, item_id, new_parent_id, name).await?;
+        return Ok(());
+    }
+
+    validate_name(name)?;
+    let parent_path = destination_path(conn, item.drive_id, new_parent_id).await?;
+    sqlx::query!(
+        "UPDATE drive_items SET parent_id = $2, name = $3, path = $4 WHERE id = $1",
+        item_id,
+        new_parent_id,
+        name,
+        child_path(&parent_path, name)
+    )
+    .execute(&mut *conn)
+    .await
+    .map_err(Error::Database)?;
+    if new_parent_id != item.parent_id {
+        reattach(conn, item_id, new_parent_id).await?;
+    }
+    Ok(())
+}
+
+/// Bytes one owner's items take up in a drive
+struct OwnerUsage {
+    owner_id: Id,
+    bytes: i64,
+}
+
+/// What the live items below and including `item_id` take up, by owner
+async fn subtree_usage(conn: &mut PgConnection, item_id: Id) -> Result<Vec<OwnerUsage>> {
+    sqlx::query_as!(
+        OwnerUsage,
+        r#"
+        SELECT i.owner_id, SUM(i.size)::BIGINT AS "bytes!"
+        FROM drive_item_tree t JOIN drive_items i ON i.id = t.descendant_id
+        WHERE t.ancestor_id = $1 AND NOT i.is_trashed
+        GROUP BY i.owner_id
+        ORDER BY i.owner_id
+        "#,
+        item_id
+    )
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(Error::Database)
+}
+
+/// Charge owners and the drive for storage in the caller's transaction. A
+/// charge past a quota fails with `Error::QuotaExceeded`, and dropping the
+/// transaction undoes the charges made before it.
+async fn charge_usage(
+    conn: &mut PgConnection,
+    drive_id: Id,
+    usage: &[OwnerUsage],
+    default_user_quota: Option<i64>,
+) -> Result<()> {
+    // Owners in id order, then the drive, so concurrent charges lock rows alike
+    for OwnerUsage { owner_id, bytes } in usage.iter().filter(|usage| usage.bytes > 0) {
+        let charged = sqlx::query!(
+            r#"
+            UPDATE users
+            SET storage_used = storage_used + $2
+            WHERE id = $1
+              AND (COALESCE(storage_quota, $3) IS NULL
+                   OR storage_used + $2 <= COALESCE(storage_quota, $3))
+            "#,
+            owner_id,
+            bytes,
+            default_user_quota
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(Error::Database)?
+        .rows_affected();
+        if charged == 0 {
+            return Err(Error::QuotaExceeded(format!("{} bytes don't fit in the owner's storage quota", bytes)));
+        }
+    }
+
+    let bytes: i64 = usage.iter().map(|usage| usage.bytes).sum();
+    if bytes == 0 {
+        return Ok(());
+    }
+    let charged = sqlx::query!(
+        "UPDATE drives SET storage_used = storage_used + $2 WHERE id = $1 AND storage_used + $2 <= storage_quota",
+        drive_id,
+        bytes
+    )
+    .execute(&mut *conn)
+    .await
+    .map_err(Error::Database)?
+    .rows_affected();
+    if charged == 0 {
+        return Err(Error::QuotaExceeded(format!("{} bytes don't fit in the drive's storage quota", bytes)));
+    }
+    Ok(())
+}
+
+/// Give back what `charge_usage` took; usage never drops below zero
+async fn release_usage(conn: &mut PgConnection, drive_id: Id, usage: &[OwnerUsage]) -> Result<()> {
+    for OwnerUsage { owner_id, bytes } in usage.iter().filter(|usage| usage.bytes > 0) {
+        sqlx::query!(
+            "UPDATE users SET storage_used = GREATEST(storage_used - $2, 0) WHERE id = $1",
+            owner_id,
+            bytes
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(Error::Database)?;
+    }
+
+    let bytes: i64 = usage.iter().map(|usage| usage.bytes).sum();
+    if bytes > 0 {
+        sqlx::query!(
+            "UPDATE drives SET storage_used = GREATEST(storage_used - $2, 0) WHERE id = $1",
+            drive_id,
+            bytes
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(Error::Database)?;
+    }
+    Ok(())
+}
+
+/// Delete an item for good; folders take everything in them along
+async fn delete_item(conn: &mut PgConnection, item_id: Id) -> Result<()> {
+    let usage = subtree_usage(conn, item_id).await?;
+    let deleted = sqlx::query!("DELETE FROM drive_items WHERE id = $1 RETURNING drive_id, item_type", item_id)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(Error::Database)?
+        .ok_or_else(|| Error::NotFound("Item not found".to_string()))?;
+    release_usage(conn, deleted.drive_id, &usage).await?;
+
+    if deleted.item_type == "Folder" {
+        sqlx::query!("DELETE FROM folders WHERE id = $1", item_id)
+            .execute(&mut *conn)
+            .await
+            .map_err(Error::Database)?;
+    }
+    Ok(())
+}
+
+async fn share_with(conn: &mut PgConnection, item_id: Id, shared_by: Id, request: &ShareItemRequest) -> Result<()> {
+    let owner_id = sqlx::query_scalar!("SELECT owner_id FROM drive_items WHERE id = $1", item_id)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(Error::Database)?
+        .ok_or_else(|| Error::NotFound("Item not found".to_string()))?;
+
+    let role = enum_name(&request.role)?;
+    let permissions = serde_json::to_value(request.role.permissions())?;
+    for user_id in request.user_ids.iter().filter(|user_id| **user_id != owner_id) {
+        sqlx::query!(
+            r#"
+            INSERT INTO item_shares (id, item_id, shared_with_user_id, shared_by_user_id, permissions, role, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (item_id, shared_with_user_id) DO UPDATE
+            SET shared_by_user_id = EXCLUDED.shared_by_user_id,
+                permissions = EXCLUDED.permissions,
+                role = EXCLUDED.role,
+                expires_at = EXCLUDED.expires_at,
+                created_at = NOW()
+            "#,
+            Id::new_v4(),
+            item_id,
+            user_id,
+            shared_by,
+            permissions,
+            role,
+            request.expires_at
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(Error::Database)?;
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl DriveRepository for PostgresDriveRepository {
+    #[instrument(skip(self, drive), fields(drive_id = %drive.id))]
+    async fn create_drive(&self, drive: Drive) -> Result<Drive> {
+        validate_name(&drive.name)?;
+        sqlx::query!(
+            r#"
+            INSERT INTO drives (
+                id, owner_id, name, description, drive_type, storage_quota, storage_used,
+                is_shared, settings, team_id, created_at, updated_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+            "#,
+            drive.id,
+            drive.owner_id,
+            drive.name,
+            drive.description,
+            enum_name(&drive.drive_type)?,
+            drive.storage_quota,
+            drive.storage_used,
+            drive.is_shared,
+            serde_json::to_value(&drive.settings)?,
+            drive.team_id,
+            drive.created_at,
+            drive.updated_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        info!(drive_id = %drive.id, owner_id = %drive.owner_id, "Drive created");
+        Ok(drive)
+    }
+
+    #[instrument(skip(self))]
+    async fn get_drive_by_id(&self, drive_id: Id) -> Result<Option<Drive>> {
+        let row = sqlx::query_as!(
+            DriveRow,
+            r#"
+            SELECT id, owner_id, name, description, drive_type, storage_quota, storage_used,
+                   settings, is_shared, team_id, created_at, updated_at
+            FROM drives WHERE id = $1
+            "#,
+            drive_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        row.map(Drive::try_from).transpose()
+    }
+
+    #[instrument(skip(self))]
+    async fn get_drives_by_owner(&self, owner_id: Id) -> Result<Vec<Drive>> {
+        let rows = sqlx::query_as!(
+            DriveRow,
+            r#"
+            SELECT id, owner_id, name, description, drive_type, storage_quota, storage_used,
+                   settings, is_shared, team_id, created_at, updated_at
+            FROM drives WHERE owner_id = $1
+            ORDER BY created_at
+            "#,
+            owner_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        rows.into_iter().map(Drive::try_from).collect()
+    }
+
+    #[instrument(skip(self))]
+    async fn get_drives_for_member(&self, user_id: Id) -> Result<Vec<Drive>> {
+        let rows = sqlx::query_as!(
+            DriveRow,
+            r#"
+            SELECT d.id, d.owner_id, d.name, d.description, d.drive_type, d.storage_quota, d.storage_used,
+                   d.settings, d.is_shared, d.team_id, d.created_at, d.updated_at
+            FROM drives d
+            WHERE EXISTS (
+                SELECT 1 FROM role_assignments a
+                WHERE a.user_id = $1
+                  AND ((a.scope_type = 'drive' AND a.scope_id = d.id)
+                       OR (a.scope_type = 'team' AND a.scope_id = d.team_id))
+            )
+            ORDER BY d.created_at
+            "#,
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        rows.into_iter().map(Drive::try_from).collect()
+    }
+
+    #[instrument(skip(self))]
+    async fn get_search_scope_drives(&self, scope: SearchScope, user_id: Id) -> Result<Vec<Drive>> {
+        let (scope_name, drive_id) = scope_parts(scope);
+        let rows = sqlx::query_as!(
+            DriveRow,
+            r#"
+            SELECT d.id, d.owner_id, d.name, d.description, d.drive_type, d.storage_quota, d.storage_used,
+                   d.settings, d.is_shared, d.team_id, d.created_at, d.updated_at
+            FROM drives d
+            WHERE ($1 = 'drive' AND d.id = $2)
+               OR d.id IN (
+                   SELECT i.drive_id FROM drive_items i
+                   WHERE i.id IN (
+                       SELECT item_id FROM drive_activities
+                       WHERE $1 = 'recent' AND user_id = $3 AND item_id IS NOT NULL
+                       UNION ALL
+                       SELECT item_id FROM item_stars
+                       WHERE $1 = 'starred' AND user_id = $3
+                       UNION ALL
+                       SELECT item_id FROM item_shares
+                       WHERE $1 = 'shared_with_me' AND shared_with_user_id = $3
+                         AND (expires_at IS NULL OR expires_at > NOW())
+                   )
+               )
+            ORDER BY d.created_at
+            "#,
+            scope_name,
+            drive_id,
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        rows.into_iter().map(Drive::try_from).collect()
+    }
+
+    /// Storage usage is kept by the quota service and isn't written here
+    #[instrument(skip(self, drive), fields(drive_id = %drive.id))]
+    async fn update_drive(&self, drive: Drive) -> Result<Drive> {
+        validate_name(&drive.name)?;
+        let result = sqlx::query!(
+            r#"
+            UPDATE drives
+            SET name = $2, description = $3, drive_type = $4, storage_quota = $5,
+                is_shared = $6, settings = $7, team_id = $8, updated_at = $9
+            WHERE id = $1
+            "#,
+            drive.id,
+            drive.name,
+            drive.description,
+            enum_name(&drive.drive_type)?,
+            drive.storage_quota,
+            drive.is_shared,
+            serde_json::to_value(&drive.settings)?,
+            drive.team_id,
+            drive.updated_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        if result.rows_affected() == 0 {
+            return Err(Error::NotFound("Drive not found".to_string()));
+        }
+        Ok(drive)
+    }
+
+    #[instrument(skip(self))]
+    async fn delete_drive(&self, drive_id: Id) -> Result<()> {
+        let result = sqlx::query!("DELETE FROM drives WHERE id = $1", drive_id)
+            .execute(&self.pool)
+            .await
+            .map_err(Error::Database)?;
+
+        if result.rows_affected() == 0 {
+            return Err(Error::NotFound("Drive not found".to_string()));
+        }
+        info!(drive_id = %drive_id, "Drive deleted");
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn update_storage_usage(&self, drive_id: Id, size_delta: i64) -> Result<()> {
+        sqlx::query!(
+            "UPDATE drives SET storage_used = GREATEST(storage_used + $2, 0) WHERE id = $1",
+            drive_id,
+            size_delta
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+        Ok(())
+    }
+
+    /// The folder's path is worked out from its parent
+    #[instrument(skip(self, folder), fields(folder_id = %folder.id))]
+    async fn create_folder(&self, mut folder: Folder) -> Result<Folder> {
+        validate_name(&folder.name)?;
+        let mut tx = self.pool.begin().await.map_err(Error::Database)?;
+
+        let parent_path = destination_path(&mut tx, folder.drive_id, folder.parent_id).await?;
+        folder.path = child_path(&parent_path, &folder.name);
+        insert_folder(&mut tx, &folder).await?;
+
+        tx.commit().await.map_err(Error::Database)?;
+        Ok(folder)
+    }
+
+    #[instrument(skip(self))]
+    async fn get_folder_by_id(&self, folder_id: Id) -> Result<Option<Folder>> {
+        let row = sqlx::query_as!(
+            FolderRow,
+            r#"
+            SELECT f.id, f.drive_id, f.parent_id, f.name, f.description, f.color, f.path, f.owner_id,
+                   f.permissions, COALESCE((
+                       SELECT jsonb_agg(jsonb_build_object(
+                           'user_id', s.shared_with_user_id,
+                           'permissions', s.permissions,
+                           'role', s.role,
+                           'granted_by', s.shared_by_user_id,
+                           'granted_at', s.created_at,
+                           'expires_at', s.expires_at
+                       ) ORDER BY s.created_at)
+                       FROM item_shares s WHERE s.item_id = f.id
+                   ), '[]'::jsonb) AS "shares!",
+                   f.is_starred, f.is_trashed, f.created_at, f.updated_at, f.trashed_at
+            FROM folders f WHERE f.id = $1
+            "#,
+            folder_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        row.map(Folder::try_from).transpose()
+    }
+
+    #[instrument(skip(self))]
+    async fn get_folders_by_parent(&self, parent_id: Option<Id>, drive_id: Id) -> Result<Vec<Folder>> {
+        let rows = sqlx::query_as!(
+            FolderRow,
+            r#"
+            SELECT f.id, f.drive_id, f.parent_id, f.name, f.description, f.color, f.path, f.owner_id,
+                   f.permissions, COALESCE((
+                       SELECT jsonb_agg(jsonb_build_object(
+                           'user_id', s.shared_with_user_id,
+                           'permissions', s.permissions,
+                           'role', s.role,
+                           'granted_by', s.shared_by_user_id,
+                           'granted_at', s.created_at,
+                           'expires_at', s.expires_at
+                       ) ORDER BY s.created_at)
+                       FROM item_shares s WHERE s.item_id = f.id
+                   ), '[]'::jsonb) AS "shares!",
+                   f.is_starred, f.is_trashed, f.created_at, f.updated_at, f.trashed_at
+            FROM folders f
+            WHERE f.drive_id = $1 AND f.parent_id IS NOT DISTINCT FROM $2 AND NOT f.is_trashed
+            ORDER BY lower(f.name), f.id
+            "#,
+            drive_id,
+            parent_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        rows.into_iter().map(Folder::try_from).collect()
+    }
+
+    /// A changed name or parent moves the folder and everything in it. Trash
+    /// state is changed through the trash methods.
+    #[instrument(skip(self, folder), fields(folder_id = %folder.id))]
+    async fn update_folder(&self, mut folder: Folder) -> Result<Folder> {
+        let mut tx = self.pool.begin().await.map_err(Error::Database)?;
+
+        lock_drive_tree(&mut tx, folder.drive_id).await?;
+        folder.path = relocate_folder(&mut tx, folder.id, folder.parent_id, &folder.name).await?;
+        sqlx::query!(
+            r#"
+            UPDATE folders
+            SET description = $2, color = $3, permissions = $4, is_starred = $5, updated_at = $6
+            WHERE id = $1
+            "#,
+            folder.id,
+            folder.description,
+            folder.color,
+            folder_permissions_json(&folder.permissions)?,
+            folder.is_starred,
+            folder.updated_at
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(Error::Database)?;
+
+        tx.commit().await.map_err(Error::Database)?;
+        Ok(folder)
+    }
+
+    #[instrument(skip(self))]
+    async fn delete_folder(&self, folder_id: Id) -> Result<()> {
+        let mut tx = self.pool.begin().await.map_err(Error::Database)?;
+
+        let usage = subtree_usage(&mut tx, folder_id).await?;
+        sqlx::query!("DELETE FROM drive_items WHERE id = $1", folder_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(Error::Database)?;
+        let drive_id = sqlx::query_scalar!("DELETE FROM folders WHERE id = $1 RETURNING drive_id", folder_id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(Error::Database)?
+            .ok_or_else(|| Error::NotFound("Folder not found".to_string()))?;
+        release_usage(&mut tx, drive_id, &usage).await?;
+
+        tx.commit().await.map_err(Error::Database)?;
+        info!(folder_id = %folder_id, "Folder deleted");
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn move_folder(&self, folder_id: Id, new_parent_id: Option<Id>) -> Result<()> {
+        let mut tx = self.pool.begin().await.map_err(Error::Database)?;
+        place_item(&mut tx, folder_id, new_parent_id, None).await?;
+        tx.commit().await.map_err(Error::Database)?;
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn get_folder_path(&self, folder_id: Id) -> Result<String> {
+        let path = sqlx::query_scalar!(
+            r#"
+            SELECT string_agg(f.name, '/' ORDER BY t.depth DESC)
+            FROM drive_item_tree t JOIN folders f ON f.id = t.ancestor_id
+            WHERE t.descendant_id = $1
+            "#,
+            folder_id
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(Error::Database)?
+        .ok_or_else(|| Error::NotFound("Folder not found".to_string()))?;
+
+        Ok(format!("/{}", path))
+    }
+
+    #[instrument(skip(self))]
+    async fn get_item_ancestors(&self, item_id: Id) -> Result<Vec<Folder>> {
+        let rows = sqlx::query_as!(
+            FolderRow,
+            r#"
+            SELECT f.id, f.drive_id, f.parent_id, f.name, f.description, f.color, f.path, f.owner_id,
+                   f.permissions, COALESCE((
+                       SELECT jsonb_agg(jsonb_build_object(
+                           'user_id', s.shared_with_user_id,
+                           'permissions', s.permissions,
+                           'role', s.role,
+                           'granted_by', s.shared_by_user_id,
+                           'granted_at', s.created_at,
+                           'expires_at', s.expires_at
+                       ) ORDER BY s.created_at)
+                       FROM item_shares s WHERE s.item_id = f.id
+                   ), '[]'::jsonb) AS "shares!",
+                   f.is_starred, f.is_trashed, f.created_at, f.updated_at, f.trashed_at
+            FROM drive_item_tree t JOIN folders f ON f.id = t.ancestor_id
+            WHERE t.descendant_id = $1
+            ORDER BY t.depth
+            "#,
+            item_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        rows.into_iter().map(Folder::try_from).collect()
+    }
+
+    #[instrument(skip(self))]
+    async fn get_descendants(&self, folder_id: Id, max_depth: Option<u32>) -> Result<Vec<DriveItem>> {
+        let mut conn = self.pool.acquire().await.map_err(Error::Database)?;
+        let ids = sqlx::query_scalar!(
+            r#"
+            SELECT i.id
+            FROM drive_item_tree t JOIN drive_items i ON i.id = t.descendant_id
+            WHERE t.ancestor_id = $1 AND t.depth > 0 AND ($2::INTEGER IS NULL OR t.depth <= $2)
+            ORDER BY t.depth, i.item_type = 'Folder' DESC, lower(i.name), i.id
+            "#,
+            folder_id,
+            max_depth.map(|depth| depth.min(i32::MAX as u32) as i32)
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(Error::Database)?;
+
+        load_items(&mut conn, &ids).await
+    }
+
+    #[instrument(skip(self))]
+    async fn get_folder_stats(&self, folder_id: Id) -> Result<FolderStats> {
+        let stats = sqlx::query!(
+            r#"
+            SELECT COALESCE(SUM(i.size) FILTER (WHERE i.item_type <> 'Folder'), 0)::BIGINT AS "total_size!",
+                   COUNT(*) FILTER (WHERE i.item_type <> 'Folder') AS "file_count!",
+                   COUNT(*) FILTER (WHERE i.item_type = 'Folder') AS "folder_count!"
+            FROM drive_item_tree t JOIN drive_items i ON i.id = t.descendant_id
+            WHERE t.ancestor_id = $1 AND t.depth > 0 AND NOT i.is_trashed
+            "#,
+            folder_id
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        if stats.file_count == 0 && stats.folder_count == 0 && self.get_folder_by_id(folder_id).await?.is_none() {
+            return Err(Error::NotFound("Folder not found".to_string()));
+        }
+        Ok(FolderStats {
+            folder_id,
+            total_size: stats.total_size,
+            file_count: stats.file_count,
+            folder_count: stats.folder_count,
+        })
+    }
+
+    /// Folders are created with `create_folder`
+    #[instrument(skip(self, item), fields(item_id = %item.id))]
+    async fn create_drive_item(&self, mut item: DriveItem) -> Result<DriveItem> {
+        if item.item_type == DriveItemType::Folder {
+            return Err(Error::BadRequest("Folders are created as folders".to_string()));
+        }
+        validate_name(&item.name)?;
+        let mut tx = self.pool.begin().await.map_err(Error::Database)?;
+
+        let parent_path = destination_path(&mut tx, item.drive_id, item.parent_id).await?;
+        item.path = child_path(&parent_path, &item.name);
+        insert_item(&mut tx, &item).await?;
+        let usage = [OwnerUsage { owner_id: item.permissions.owner_id, bytes: item.size }];
+        charge_usage(&mut tx, item.drive_id, &usage, self.default_user_quota).await?;
+
+        let created = load_item(&mut tx, item.id).await?;
+        tx.commit().await.map_err(Error::Database)?;
+        Ok(created)
+    }
+
+    #[instrument(skip(self))]
+    async fn get_drive_item_by_id(&self, item_id: Id) -> Result<Option<DriveItem>> {
+        let mut conn = self.pool.acquire().await.map_err(Error::Database)?;
+        Ok(load_items(&mut conn, &[item_id]).await?.pop())
+    }
+
+    #[instrument(skip(self))]
+    async fn get_drive_items_by_parent(&self, parent_id: Option<Id>, drive_id: Id) -> Result<Vec<DriveItem>> {
+        let mut conn = self.pool.acquire().await.map_err(Error::Database)?;
+        let ids = sqlx::query_scalar!(
+            r#"
+            SELECT id FROM drive_items
+            WHERE drive_id = $1 AND parent_id IS NOT DISTINCT FROM $2 AND NOT is_trashed
+            ORDER BY item_type = 'Folder' DESC, lower(name), id
+            "#,
+            drive_id,
+            parent_id
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(Error::Database)?;
+
+        load_items(&mut conn, &ids).await
+    }
+
+    #[instrument(skip(self))]
+    async fn get_drive_items_by_drive(&self, drive_id: Id, limit: Option<u32>, offset: Option<u32>) -> Result<Vec<DriveItem>> {
+        let mut conn = self.pool.acquire().await.map_err(Error::Database)?;
+        let ids = sqlx::query_scalar!(
+            r#"
+            SELECT id FROM drive_items
+            WHERE drive_id = $1 AND NOT is_trashed
+            ORDER BY path, id
+            LIMIT $2 OFFSET $3
+            "#,
+            drive_id,
+            limit.map(i64::from),
+            i64::from(offset.unwrap_or(0))
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(Error::Database)?;
+
+        load_items(&mut conn, &ids).await
+    }
+
+    /// A changed name or parent moves the item. Trash state and sharing are
+    /// changed through their own methods.
+    #[instrument(skip(self, item), fields(item_id = %item.id))]
+    async fn update_drive_item(&self, item: DriveItem) -> Result<DriveItem> {
+        let mut tx = self.pool.begin().await.map_err(Error::Database)?;
+
+        place_item(&mut tx, item.id, item.parent_id, Some(&item.name)).await?;
+        sqlx::query!(
+            r#"
+            UPDATE drive_items
+            SET mime_type = $2, size = $3, metadata = $4, permissions = $5, is_starred = $6,
+                last_accessed_at = $7, updated_at = $8
+            WHERE id = $1
+            "#,
+            item.id,
+            item.mime_type,
+            item.size,
+            serde_json::to_value(&item.metadata)?,
+            item_permissions_json(&item.permissions)?,
+            item.is_starred,
+            item.last_accessed_at,
+            item.updated_at
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(Error::Database)?;
+
+        let updated = load_item(&mut tx, item.id).await?;
+        tx.commit().await.map_err(Error::Database)?;
+        Ok(updated)
+    }
+
+    #[instrument(skip(self))]
+    async fn delete_drive_item(&self, item_id: Id) -> Result<()> {
+        let mut tx = self.pool.begin().await.map_err(Error::Database)?;
+        delete_item(&mut tx, item_id).await?;
+        tx.commit().await.map_err(Error::Database)?;
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn move_drive_item(&self, item_id: Id, new_parent_id: Option<Id>) -> Result<()> {
+        let mut tx = self.pool.begin().await.map_err(Error::Database)?;
+        place_item(&mut tx, item_id, new_parent_id, None).await?;
+        tx.commit().await.map_err(Error::Database)?;
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn copy_drive_item(
+        &self,
+        item_id: Id,
+        new_parent_id: Option<Id>,
+        new_name: Option<String>,
+        owner_id: Id,
+    ) -> Result<DriveItem> {
+        let mut tx = self.pool.begin().await.map_err(Error::Database)?;
+
+        let source = load_item(&mut tx, item_id).await?;
+        if let (DriveItemType::Folder, Some(parent_id)) = (&source.item_type, new_parent_id) {
+            if is_within(&mut tx, source.id, parent_id).await? {
+                return Err(Error::BadRequest("A folder can't be copied into itself".to_string()));
+            }
+        }
+
+        // Copies start private, unstarred and at version 1
+        let copy_id = Id::new_v4();
+        let mut pending = vec![(source, new_parent_id, new_name, copy_id)];
+        while let Some((source, parent_id, name, id)) = pending.pop() {
+            let name = name.unwrap_or_else(|| source.name.clone());
+            validate_name(&name)?;
+            let parent_path = destination_path(&mut tx, source.drive_id, parent_id).await?;
+
+            let mut copy = DriveItem::new(
+                source.drive_id,
+                owner_id,
+                name,
+                source.item_type.clone(),
+                source.mime_type.clone(),
+                source.size,
+                parent_id,
+            );
+            copy.id = id;
+            copy.path = child_path(&parent_path, &copy.name);
+            copy.metadata = ItemMetadata {
+                description: source.metadata.description.clone(),
+                tags: source.metadata.tags.clone(),
+                custom_properties: source.metadata.custom_properties.clone(),
+                checksum: source.metadata.checksum.clone(),
+                ..copy.metadata
+            };
+            // A copy holds the same document or file
+            copy.source_id = source.source_id;
+
+            if source.item_type != DriveItemType::Folder {
+                insert_item(&mut tx, &copy).await?;
+                continue;
+            }
+
+            let source_folder = sqlx::query!(
+                "SELECT description, color FROM folders WHERE id = $1",
+                source.id
+            )
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(Error::Database)?;
+            let mut folder = Folder::new(copy.drive_id, owner_id, copy.name.clone(), parent_id);
+            folder.id = copy.id;
+            folder.path = copy.path.clone();
+            folder.description = source_folder.description;
+            folder.color = source_folder.color;
+            insert_folder(&mut tx, &folder).await?;
+
+            let child_ids = sqlx::query_scalar!(
+                "SELECT id FROM drive_items WHERE parent_id = $1 AND NOT is_trashed",
+                source.id
+            )
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(Error::Database)?;
+            for child in load_items(&mut tx, &child_ids).await? {
+                pending.push((child, Some(folder.id), None, Id::new_v4()));
+            }
+        }
+
+        // The copy belongs to `owner_id`, so it counts against their quota
+        let copy = load_item(&mut tx, copy_id).await?;
+        let usage = subtree_usage(&mut tx, copy_id).await?;
+        charge_usage(&mut tx, copy.drive_id, &usage, self.default_user_quota).await?;
+        tx.commit().await.map_err(Error::Database)?;
+
+        info!(item_id = %item_id, copy_id = %copy_id, "Item copied");
+        Ok(copy)
+    }
+
+    /// Folders take everything in them to the trash. What was already there
+    /// keeps its own trash date, so it isn't restored with the folder.
+    /// Trashed items stop counting against quotas until they are restored.
+    #[instrument(skip(self))]
+    async fn move_to_trash(&self, item_id: Id) -> Result<()> {
+        let mut tx = self.pool.begin().await.map_err(Error::Database)?;
+
+        let item = sqlx::query!(
+            "SELECT drive_id, item_type, is_trashed FROM drive_items WHERE id = $1 FOR UPDATE",
+            item_id
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(Error::Database)?
+        .ok_or_else(|| Error::NotFound("Item not found".to_string()))?;
+        if item.is_trashed {
+            return Ok(());
+        }
+
+        // Only the rows flipped here are released, so an item trashed twice
+        // gives its space back once
+        let now = chrono::Utc::now();
+        let usage = sqlx::query_as!(
+            OwnerUsage,
+            r#"
+            WITH trashed AS (
+                UPDATE drive_items SET is_trashed = TRUE, trashed_at = $2
+                WHERE id IN (SELECT descendant_id FROM drive_item_tree WHERE ancestor_id = $1)
+                  AND NOT is_trashed
+                RETURNING owner_id, size
+            )
+            SELECT owner_id, SUM(size)::BIGINT AS "bytes!"
+            FROM trashed GROUP BY owner_id ORDER BY owner_id
+            "#,
+            item_id,
+            now
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(Error::Database)?;
+        if item.item_type == "Folder" {
+            sqlx::query!(
+                r#"
+                UPDATE folders SET is_trashed = TRUE, trashed_at = $2
+                WHERE id IN (SELECT descendant_id FROM drive_item_tree WHERE ancestor_id = $1)
+                  AND NOT is_trashed
+                "#,
+                item_id,
+                now
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(Error::Database)?;
+        }
+        release_usage(&mut tx, item.drive_id, &usage).await?;
+
+        tx.commit().await.map_err(Error::Database)?;
+        Ok(())
+    }
+
+    /// Brings back what went to the trash with the item, charging for it
+    /// again. Nothing is restored if it no longer fits in a quota.
+    #[instrument(skip(self))]
+    async fn restore_from_trash(&self, item_id: Id) -> Result<()> {
+        let mut tx = self.pool.begin().await.map_err(Error::Database)?;
+
+        let item = sqlx::query!(
+            r#"
+            SELECT i.drive_id, i.item_type, i.trashed_at, p.is_trashed AS "parent_trashed?"
+            FROM drive_items i LEFT JOIN folders p ON p.id = i.parent_id
+            WHERE i.id = $1
+            FOR UPDATE OF i
+            "#,
+            item_id
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(Error::Database)?
+        .ok_or_else(|| Error::NotFound("Item not found".to_string()))?;
+        let Some(trashed_at) = item.trashed_at else {
+            return Ok(());
+        };
+        if item.parent_trashed == Some(true) {
+            return Err(Error::BadRequest("Restore the folder it was in first".to_string()));
+        }
+
+        let usage = sqlx::query_as!(
+            OwnerUsage,
+            r#"
+            WITH restored AS (
+                UPDATE drive_items SET is_trashed = FALSE, trashed_at = NULL
+                WHERE id IN (SELECT descendant_id FROM drive_item_tree WHERE ancestor_id = $1)
+                  AND trashed_at = $2
+                RETURNING owner_id, size
+            )
+            SELECT owner_id, SUM(size)::BIGINT AS "bytes!"
+            FROM restored GROUP BY owner_id ORDER BY owner_id
+            "#,
+            item_id,
+            trashed_at
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(Error::Database)?;
+        if item.item_type == "Folder" {
+            sqlx::query!(
+                r#"
+                UPDATE folders SET is_trashed = FALSE, trashed_at = NULL
+                WHERE id IN (SELECT descendant_id FROM drive_item_tree WHERE ancestor_id = $1)
+                  AND trashed_at = $2
+                "#,
+                item_id,
+                trashed_at
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(Error::Database)?;
+        }
+        charge_usage(&mut tx, item.drive_id, &usage, self.default_user_quota).await?;
+
+        tx.commit().await.map_err(Error::Database)?;
+        Ok(())
+    }
+
+    /// What was put in the trash, without the contents of trashed folders
+    #[instrument(skip(self))]
+    async fn get_trash_items(&self, drive_id: Id) -> Result<Vec<DriveItem>> {
+        let mut conn = self.pool.acquire().await.map_err(Error::Database)?;
+        let ids = sqlx::query_scalar!(
+            r#"
+            SELECT i.id FROM drive_items i
+            LEFT JOIN folders p ON p.id = i.parent_id
+            WHERE i.drive_id = $1 AND i.is_trashed AND (p.id IS NULL OR NOT p.is_trashed)
+            ORDER BY i.trashed_at DESC, i.id
+            "#,
+            drive_id
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(Error::Database)?;
+
+        load_items(&mut conn, &ids).await
+    }
+
+    #[instrument(skip(self))]
+    async fn empty_trash(&self, drive_id: Id) -> Result<()> {
+        let mut tx = self.pool.begin().await.map_err(Error::Database)?;
+
+        let items = sqlx::query!(
+            "DELETE FROM drive_items WHERE drive_id = $1 AND is_trashed",
+            drive_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(Error::Database)?
+        .rows_affected();
+        sqlx::query!("DELETE FROM folders WHERE drive_id = $1 AND is_trashed", drive_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(Error::Database)?;
+
+        tx.commit().await.map_err(Error::Database)?;
+        info!(drive_id = %drive_id, items, "Trash emptied");
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn permanently_delete(&self, item_id: Id) -> Result<()> {
+        self.delete_drive_item(item_id).await
+    }
+
+    /// Sharing again with someone replaces their earlier share. The owner
+    /// needs no share and is skipped.
+    #[instrument(skip(self, share_request))]
+    async fn share_item(&self, item_id: Id, shared_by: Id, share_request: ShareItemRequest) -> Result<()> {
+        let mut tx = self.pool.begin().await.map_err(Error::Database)?;
+        share_with(&mut tx, item_id, shared_by, &share_request).await?;
+        tx.commit().await.map_err(Error::Database)?;
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn unshare_item(&self, item_id: Id, user_id: Id) -> Result<()> {
+        let result = sqlx::query!(
+            "DELETE FROM item_shares WHERE item_id = $1 AND shared_with_user_id = $2",
+            item_id,
+            user_id
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        if result.rows_affected() == 0 {
+            return Err(Error::NotFound("Share not found".to_string()));
+        }
+        Ok(())
+    }
+
+    /// An item has at most one link; a new link replaces the old one
+    #[instrument(skip(self, link_request))]
+    async fn create_sharing_link(
+        &self,
+        item_id: Id,
+        created_by: Id,
+        link_request: CreateSharingLinkRequest,
+    ) -> Result<String> {
+        if link_request.access_level == PublicAccessLevel::Private {
+            return Err(Error::BadRequest("A link needs an access level other than private".to_string()));
+        }
+        let password_hash = link_request.password.as_deref().map(hash_link_password).transpose()?;
+        let token = Share::generate_share_token();
+
+        let mut tx = self.pool.begin().await.map_err(Error::Database)?;
+        sqlx::query!("DELETE FROM sharing_links WHERE item_id = $1", item_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(Error::Database)?;
+        sqlx::query!(
+            r#"
+            INSERT INTO sharing_links (id, item_id, token, access_level, password_hash, expires_at, created_by)
+            SELECT $1, id, $3, $4, $5, $6, $7 FROM drive_items WHERE id = $2
+            "#,
+            Id::new_v4(),
+            item_id,
+            token,
+            enum_name(&link_request.access_level)?,
+            password_hash,
+            link_request.expires_at,
+            created_by
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(Error::Database)?
+        .rows_affected()
+        .eq(&1)
+        .then_some(())
+        .ok_or_else(|| Error::NotFound("Item not found".to_string()))?;
+        tx.commit().await.map_err(Error::Database)?;
+
+        Ok(token)
+    }
+
+    #[instrument(skip(self))]
+    async fn get_sharing_link(&self, item_id: Id) -> Result<Option<String>> {
+        sqlx::query_scalar!(
+            r#"
+            SELECT token FROM sharing_links
+            WHERE item_id = $1 AND (expires_at IS NULL OR expires_at > NOW())
+            ORDER BY created_at DESC
+            LIMIT 1
+            "#,
+            item_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(Error::Database)
+    }
+
+    #[instrument(skip(self))]
+    async fn revoke_sharing_link(&self, item_id: Id) -> Result<()> {
+        sqlx::query!("DELETE FROM sharing_links WHERE item_id = $1", item_id)
+            .execute(&self.pool)
+            .await
+            .map_err(Error::Database)?;
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn get_shared_with_me(&self, user_id: Id) -> Result<Vec<DriveItem>> {
+        let mut conn = self.pool.acquire().await.map_err(Error::Database)?;
+        let ids = sqlx::query_scalar!(
+            r#"
+            SELECT i.id FROM drive_items i
+            JOIN item_shares s ON s.item_id = i.id
+            WHERE s.shared_with_user_id = $1
+              AND (s.expires_at IS NULL OR s.expires_at > NOW())
+              AND NOT i.is_trashed
+            ORDER BY s.created_at DESC, i.id
+            "#,
+            user_id
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(Error::Database)?;
+
+        load_items(&mut conn, &ids).await
+    }
+
+    #[instrument(skip(self))]
+    async fn get_shared_by_me(&self, user_id: Id) -> Result<Vec<DriveItem>> {
+        let mut conn = self.pool.acquire().await.map_err(Error::Database)?;
+        let ids = sqlx::query_scalar!(
+            r#"
+            SELECT i.id FROM drive_items i
+            WHERE NOT i.is_trashed
+              AND EXISTS (SELECT 1 FROM item_shares s WHERE s.item_id = i.id AND s.shared_by_user_id = $1)
+            ORDER BY i.updated_at DESC, i.id
+            "#,
+            user_id
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(Error::Database)?;
+
+        load_items(&mut conn, &ids).await
+    }
+
+    /// Words are matched with stemming and quoted phrases as written; a name
+    /// containing the words as typed also matches. Excluded words and phrases
+    /// drop every item they match. With no words, items come in the scope's
+    /// own order: recently changed, worked with, starred or shared first.
+    #[instrument(skip(self, query, filters))]
+    async fn search_drive_items(
+        &self,
+        scope: SearchScope,
+        user_id: Id,
+        query: &DriveQuery,
+        filters: &SearchFilters,
+    ) -> Result<Vec<SearchMatch>> {
+        let (scope_name, drive_id) = scope_parts(scope);
+        let text = |negated| {
+            query.values(negated, |kind| match kind {
+                TermKind::Word(text) | TermKind::Phrase(text) => Some(text.as_str()),
+                _ => None,
+            })
+        };
+        let (included, excluded) = (text(false), text(true));
+        // Quoted for websearch_to_tsquery, so words such as `or` stay words
+        let websearch = |terms: &[&str], separator: &str| {
+            terms.iter().map(|term| format!("\"{}\"", term)).collect::<Vec<_>>().join(separator)
+        };
+        let item_types = |negated| -> Result<Vec<String>> {
+            query
+                .values(negated, |kind| match kind {
+                    TermKind::Type(item_type) => Some(enum_name(item_type)),
+                    _ => None,
+                })
+                .into_iter()
+                .collect()
+        };
+        let tags = |negated| {
+            query.values(negated, |kind| match kind {
+                TermKind::Tag(tag) => Some(tag.clone()),
+                _ => None,
+            })
+        };
+        let folder_ids = |negated| {
+            query.values(negated, |kind| match kind {
+                TermKind::In(folder_id) => Some(*folder_id),
+                _ => None,
+            })
+        };
+
+        let item_type = filters.item_type.as_ref().map(enum_name).transpose()?;
+        // Drop the leading wildcard to match from the start
+        let mime_prefix = filters.mime_type.as_deref().map(|mime_type| like_pattern(mime_type)[1..].to_string());
+        let (modified_after, modified_before) = query.modified_range();
+        let modified_after = modified_after.max(filters.modified_after);
+        let modified_before = match (modified_before, filters.modified_before) {
+            (Some(before), Some(filter)) => Some(before.min(filter)),
+            (before, filter) => before.or(filter),
+        };
+
+        let mut conn = self.pool.acquire().await.map_err(Error::Database)?;
+        let rows = sqlx::query!(
+            r#"
+            WITH search AS (
+                SELECT CASE WHEN $4 = '' THEN NULL ELSE websearch_to_tsquery('english', $4) END AS query,
+                       CASE WHEN $5 = '' THEN NULL ELSE websearch_to_tsquery('english', $5) END AS excluded
+            ),
+            scoped AS (
+                SELECT id AS item_id, updated_at AS scoped_at FROM drive_items
+                WHERE $1 = 'drive' AND drive_id = $2
+                UNION ALL
+                SELECT item_id, MAX(created_at) FROM drive_activities
+                WHERE $1 = 'recent' AND user_id = $3 AND item_id IS NOT NULL
+                GROUP BY item_id
+                UNION ALL
+                SELECT item_id, created_at FROM item_stars
+                WHERE $1 = 'starred' AND user_id = $3
+                UNION ALL
+                SELECT item_id, MAX(created_at) FROM item_shares
+                WHERE $1 = 'shared_with_me' AND shared_with_user_id = $3
+                  AND (expires_at IS NULL OR expires_at > NOW())
+                GROUP BY item_id
+            )
+            SELECT i.id,
+                   COALESCE(ts_rank(i.search_vector || COALESCE(c.search_vector, ''), search.query), 0)::REAL AS "rank!",
+                   CASE
+                       WHEN c.search_vector @@ search.query THEN ts_headline('english', c.content, search.query, $24)
+                       WHEN to_tsvector('english', COALESCE(i.metadata->>'description', '')) @@ search.query
+                           THEN ts_headline('english', i.metadata->>'description', search.query, $24)
+                   END AS highlight
+            FROM scoped
+            JOIN drive_items i ON i.id = scoped.item_id
+            CROSS JOIN search
+            LEFT JOIN drive_item_contents c ON c.item_id = i.id
+            CROSS JOIN LATERAL (
+                SELECT COALESCE(array_agg(lower(tag)), '{}') AS tags
+                FROM jsonb_array_elements_text(COALESCE(i.metadata->'tags', '[]')) tag
+            ) item_tags
+            WHERE (search.query IS NULL
+                   OR (i.search_vector || COALESCE(c.search_vector, '')) @@ search.query
+                   OR i.name ILIKE $6)
+              AND (search.excluded IS NULL
+                   OR NOT (i.search_vector || COALESCE(c.search_vector, '')) @@ search.excluded)
+              AND ($7::TEXT IS NULL OR i.item_type = $7)
+              AND (cardinality($8::TEXT[]) = 0 OR i.item_type = ANY($8))
+              AND NOT i.item_type = ANY($9::TEXT[])
+              AND ($10::TEXT IS NULL OR i.mime_type ILIKE $10)
+              AND ($11::UUID IS NULL OR i.owner_id = $11)
+              AND (cardinality($12::UUID[]) = 0 OR i.owner_id = ANY($12))
+              AND NOT i.owner_id = ANY($13::UUID[])
+              AND ($14::TIMESTAMPTZ IS NULL OR i.updated_at >= $14)
+              AND ($15::TIMESTAMPTZ IS NULL OR i.updated_at < $15)
+              AND item_tags.tags @> $16::TEXT[]
+              AND NOT item_tags.tags && $17::TEXT[]
+              AND (cardinality($18::UUID[]) = 0 OR EXISTS (
+                  SELECT 1 FROM drive_item_tree t
+                  WHERE t.descendant_id = i.id AND t.depth > 0 AND t.ancestor_id = ANY($18)
+              ))
+              AND NOT EXISTS (
+                  SELECT 1 FROM drive_item_tree t
+                  WHERE t.descendant_id = i.id AND t.depth > 0 AND t.ancestor_id = ANY($19::UUID[])
+              )
+              AND ($20::BOOLEAN IS NULL OR $20 = EXISTS (
+                  SELECT 1 FROM item_stars st WHERE st.item_id = i.id AND st.user_id = $3
+              ))
+              AND (NOT $21 OR EXISTS (
+                  SELECT 1 FROM drive_item_tree t
+                  JOIN item_shares s ON s.item_id = t.ancestor_id
+                  WHERE t.descendant_id = i.id AND s.shared_with_user_id = $3
+                    AND (s.expires_at IS NULL OR s.expires_at > NOW())
+              ))
+              AND i.is_trashed = $22
+              -- Sharing reaches down to the first folder that stops inheriting
+              AND ($25::UUID[] IS NULL OR i.drive_id = ANY($25) OR EXISTS (
+                  SELECT 1 FROM drive_item_tree t
+                  LEFT JOIN folders f ON f.id = t.ancestor_id AND t.depth > 0
+                  WHERE t.descendant_id = i.id
+                    AND NOT EXISTS (
+                        SELECT 1 FROM drive_item_tree nearer
+                        JOIN folders stop ON stop.id = nearer.ancestor_id
+                        WHERE nearer.descendant_id = i.id AND nearer.depth < t.depth
+                          AND stop.permissions->>'inherit_permissions' = 'false'
+                    )
+                    AND (COALESCE(f.owner_id, i.owner_id) = $3
+                         OR COALESCE(f.permissions, i.permissions)->>'public_access' <> 'Private'
+                         OR EXISTS (
+                             SELECT 1 FROM item_shares s
+                             WHERE s.item_id = t.ancestor_id AND s.shared_with_user_id = $3
+                               AND (s.expires_at IS NULL OR s.expires_at > NOW())
+                               AND (s.permissions->>'can_view')::BOOLEAN
+                         ))
+              ))
+            ORDER BY 2 DESC, scoped.scoped_at DESC, i.id
+            LIMIT $23
+            "#,
+            scope_name,
+            drive_id,
+            user_id,
+            websearch(&included, " "),
+            websearch(&excluded, " or "),
+            like_pattern(&included.join(" ")),
+            item_type,
+            &item_types(false)?,
+            &item_types(true)?,
+            mime_prefix,
+            filters.owner_id,
+            &query.owner_ids(false, user_id),
+            &query.owner_ids(true, user_id),
+            modified_after,
+            modified_before,
+            &tags(false),
+            &tags(true),
+            &folder_ids(false),
+            &folder_ids(true),
+            filters.starred,
+            filters.shared_with_me,
+            filters.trashed,
+            SEARCH_LIMIT,
+            HIGHLIGHT_OPTIONS,
+            filters.readable_drives.as_deref()
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(Error::Database)?;
+
+        let ids: Vec<Id> = rows.iter().map(|row| row.id).collect();
+        let mut items: HashMap<Id, DriveItem> = load_items(&mut conn, &ids)
+            .await?
+            .into_iter()
+            .map(|item| (item.id, item))
+            .collect();
+        Ok(rows
+            .into_iter()
+            .filter_map(|row| {
+                Some(SearchMatch {
+                    item: items.remove(&row.id)?,
+                    rank: row.rank,
+                    highlight: row.highlight,
+                })
+            })
+            .collect())
+    }
+
+    /// Long contents are cut to what Postgres can index
+    #[instrument(skip(self, content), fields(len = content.len()))]
+    async fn index_item_content(&self, item_id: Id, content: &str) -> Result<()> {
+        let mut end = content.len().min(MAX_INDEXED_CONTENT);
+        while !content.is_char_boundary(end) {
+            end -= 1;
+        }
+
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO drive_item_contents (item_id, content, indexed_at)
+            SELECT id, $2, NOW() FROM drive_items WHERE id = $1
+            ON CONFLICT (item_id) DO UPDATE SET content = EXCLUDED.content, indexed_at = EXCLUDED.indexed_at
+            "#,
+            item_id,
+            &content[..end]
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        if result.rows_affected() == 0 {
+            return Err(Error::NotFound("Item not found".to_string()));
+        }
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn get_items_by_source(&self, source_id: Id) -> Result<Vec<Id>> {
+        sqlx::query_scalar!("SELECT id FROM drive_items WHERE source_id = $1 ORDER BY id", source_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(Error::Database)
+    }
+
+    /// Sources are documents or files with the item's `source_id`; items
+    /// whose source has gone are passed by
+    #[instrument(skip(self))]
+    async fn get_unindexed_items(&self, limit: u32, include_files: bool) -> Result<Vec<UnindexedItem>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT i.id, i.mime_type, d.content AS "document_content?", f.storage_path AS "storage_path?"
+            FROM drive_items i
+            LEFT JOIN documents d ON d.id = i.source_id
+            LEFT JOIN files f ON f.id = i.source_id AND $2
+            WHERE i.source_id IS NOT NULL
+              AND (d.id IS NOT NULL OR f.id IS NOT NULL)
+              AND NOT EXISTS (SELECT 1 FROM drive_item_contents c WHERE c.item_id = i.id)
+            ORDER BY i.created_at, i.id
+            LIMIT $1
+            "#,
+            i64::from(limit),
+            include_files
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|row| {
+                let source = match (row.document_content, row.storage_path) {
+                    // Content from before the current format has no words to find
+                    (Some(content), _) => ItemSource::Document(serde_json::from_value(content).unwrap_or_else(|_| {
+                        DocumentContent::Text { content: String::new(), format: TextFormat::PlainText }
+                    })),
+                    (None, Some(storage_path)) => ItemSource::File { storage_path },
+                    (None, None) => return None,
+                };
+                Some(UnindexedItem { item_id: row.id, mime_type: row.mime_type, source })
+            })
+            .collect())
+    }
+
+    /// Items the user worked with most recently, going by drive activity
+    #[instrument(skip(self))]
+    async fn get_recent_items(&self, user_id: Id, limit: u32) -> Result<Vec<DriveItem>> {
+        let mut conn = self.pool.acquire().await.map_err(Error::Database)?;
+        let ids = sqlx::query_scalar!(
+            r#"
+            SELECT i.id FROM drive_items i
+            JOIN (
+                SELECT item_id, MAX(created_at) AS last_activity
+                FROM drive_activities
+                WHERE user_id = $1 AND item_id IS NOT NULL
+                GROUP BY item_id
+            ) a ON a.item_id = i.id
+            WHERE NOT i.is_trashed
+            ORDER BY a.last_activity DESC, i.id
+            LIMIT $2
+            "#,
+            user_id,
+            i64::from(limit)
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(Error::Database)?;
+
+        load_items(&mut conn, &ids).await
+    }
+
+    #[instrument(skip(self))]
+    async fn get_starred_items(&self, user_id: Id) -> Result<Vec<DriveItem>> {
+        let mut conn = self.pool.acquire().await.map_err(Error::Database)?;
+        let ids = sqlx::query_scalar!(
+            r#"
+            SELECT i.id FROM drive_items i
+            JOIN item_stars s ON s.item_id = i.id
+            WHERE s.user_id = $1 AND NOT i.is_trashed
+            ORDER BY s.created_at DESC, i.id
+            "#,
+            user_id
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(Error::Database)?;
+
+        load_items(&mut conn, &ids).await
+    }
+
+    #[instrument(skip(self))]
+    async fn star_item(&self, item_id: Id, user_id: Id) -> Result<()> {
+        let mut tx = self.pool.begin().await.map_err(Error::Database)?;
+
+        let owner_id = sqlx::query_scalar!("SELECT owner_id FROM drive_items WHERE id = $1", item_id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(Error::Database)?
+            .ok_or_else(|| Error::NotFound("Item not found".to_string()))?;
+        sqlx::query!(
+            "INSERT INTO item_stars (item_id, user_id) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+            item_id,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(Error::Database)?;
+        if owner_id == user_id {
+            sqlx::query!("UPDATE drive_items SET is_starred = TRUE WHERE id = $1", item_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(Error::Database)?;
+        }
+
+        tx.commit().await.map_err(Error::Database)?;
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn unstar_item(&self, item_id: Id, user_id: Id) -> Result<()> {
+        let mut tx = self.pool.begin().await.map_err(Error::Database)?;
+
+        sqlx::query!(
+            "DELETE FROM item_stars WHERE item_id = $1 AND user_id = $2",
+            item_id,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(Error::Database)?;
+        sqlx::query!(
+            "UPDATE drive_items SET is_starred = FALSE WHERE id = $1 AND owner_id = $2",
+            item_id,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(Error::Database)?;
+
+        tx.commit().await.map_err(Error::Database)?;
+        Ok(())
+    }
+
+    #[instrument(skip(self, activity), fields(drive_id = %activity.drive_id))]
+    async fn log_activity(&self, activity: DriveActivity) -> Result<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO drive_activities (id, drive_id, item_id, user_id, activity_type, details, ip_address, user_agent, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7::TEXT::INET, $8, $9)
+            "#,
+            activity.id,
+            activity.drive_id,
+            activity.item_id,
+            activity.user_id,
+            enum_name(&activity.activity_type)?,
+            serde_json::to_value(&activity.details)?,
+            activity.ip_address,
+            activity.user_agent,
+            activity.created_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn get_drive_activity(&self, drive_id: Id, limit: Option<u32>, offset: Option<u32>) -> Result<Vec<DriveActivity>> {
+        let rows = sqlx::query_as!(
+            ActivityRow,
+            r#"
+            SELECT id, drive_id, item_id, user_id, activity_type, details,
+                   host(ip_address) AS ip_address, user_agent, created_at
+            FROM drive_activities
+            WHERE drive_id = $1
+            ORDER BY created_at DESC, id
+            LIMIT $2 OFFSET $3
+            "#,
+            drive_id,
+            limit.map(i64::from),
+            i64::from(offset.unwrap_or(0))
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        rows.into_iter().map(DriveActivity::try_from).collect()
+    }
+
+    #[instrument(skip(self))]
+    async fn get_item_activity(&self, item_id: Id, limit: Option<u32>) -> Result<Vec<DriveActivity>> {
+        let rows = sqlx::query_as!(
+            ActivityRow,
+            r#"
+            SELECT id, drive_id, item_id, user_id, activity_type, details,
+                   host(ip_address) AS ip_address, user_agent, created_at
+            FROM drive_activities
+            WHERE item_id = $1
+            ORDER BY created_at DESC, id
+            LIMIT $2
+            "#,
+            item_id,
+            limit.map(i64::from)
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        rows.into_iter().map(DriveActivity::try_from).collect()
+    }
+
+    #[instrument(skip(self))]
+    async fn get_user_activity(&self, user_id: Id, limit: Option<u32>) -> Result<Vec<DriveActivity>> {
+        let rows = sqlx::query_as!(
+            ActivityRow,
+            r#"
+            SELECT id, drive_id, item_id, user_id, activity_type, details,
+                   host(ip_address) AS ip_address, user_agent, created_at
+            FROM drive_activities
+            WHERE user_id = $1
+            ORDER BY created_at DESC, id
+            LIMIT $2
+            "#,
+            user_id,
+            limit.map(i64::from)
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        rows.into_iter().map(DriveActivity::try_from).collect()
+    }
+
+    /// All items move or none do
+    #[instrument(skip(self, item_ids), fields(count = item_ids.len()))]
+    async fn batch_move_items(&self, item_ids: Vec<Id>, new_parent_id: Option<Id>) -> Result<()> {
+        let mut tx = self.pool.begin().await.map_err(Error::Database)?;
+        for item_id in item_ids {
+            place_item(&mut tx, item_id, new_parent_id, None).await?;
+        }
+        tx.commit().await.map_err(Error::Database)?;
+        Ok(())
+    }
+
+    /// All items are deleted or none are
+    #[instrument(skip(self, item_ids), fields(count = item_ids.len()))]
+    async fn batch_delete_items(&self, item_ids: Vec<Id>) -> Result<()> {
+        let mut tx = self.pool.begin().await.map_err(Error::Database)?;
+        for item_id in item_ids {
+            // An item may already be gone with a folder deleted before it
+            match delete_item(&mut tx, item_id).await {
+                Ok(()) | Err(Error::NotFound(_)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        tx.commit().await.map_err(Error::Database)?;
+        Ok(())
+    }
+
+    #[instrument(skip(self, item_ids, share_request), fields(count = item_ids.len()))]
+    async fn batch_share_items(&self, item_ids: Vec<Id>, shared_by: Id, share_request: ShareItemRequest) -> Result<()> {
+        let mut tx = self.pool.begin().await.map_err(Error::Database)?;
+        for item_id in item_ids {
+            share_with(&mut tx, item_id, shared_by, &share_request).await?;
+        }
+        tx.commit().await.map_err(Error::Database)?;
+        Ok(())
+    }
+
+    /// Bytes per item type, leaving out the trash
+    #[instrument(skip(self))]
+    async fn get_storage_usage_by_type(&self, drive_id: Id) -> Result<HashMap<String, i64>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT item_type, COALESCE(SUM(size), 0)::BIGINT AS "bytes!"
+            FROM drive_items
+            WHERE drive_id = $1 AND NOT is_trashed AND item_type <> 'Folder'
+            GROUP BY item_type
+            "#,
+            drive_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        Ok(rows.into_iter().map(|row| (row.item_type, row.bytes)).collect())
+    }
+
+    /// Bytes per owner, leaving out the trash
+    #[instrument(skip(self))]
+    async fn get_storage_usage_by_user(&self, drive_id: Id) -> Result<HashMap<Id, i64>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT owner_id, COALESCE(SUM(size), 0)::BIGINT AS "bytes!"
+            FROM drive_items
+            WHERE drive_id = $1 AND NOT is_trashed AND item_type <> 'Folder'
+            GROUP BY owner_id
+            "#,
+            drive_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        Ok(rows.into_iter().map(|row| (row.owner_id, row.bytes)).collect())
+    }
+
+    #[instrument(skip(self))]
+    async fn get_largest_files(&self, drive_id: Id, limit: u32) -> Result<Vec<DriveItem>> {
+        let mut conn = self.pool.acquire().await.map_err(Error::Database)?;
+        let ids = sqlx::query_scalar!(
+            r#"
+            SELECT id FROM drive_items
+            WHERE drive_id = $1 AND NOT is_trashed AND item_type <> 'Folder'
+            ORDER BY size DESC, id
+            LIMIT $2
+            "#,
+            drive_id,
+            i64::from(limit)
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(Error::Database)?;
+
+        load_items(&mut conn, &ids).await
+    }
+
+    /// A page of a folder, or of the drive root when `folder_id` is `None`.
+    /// Folders come first; `sort_by` is `name` (the default), `size`, `type`,
+    /// `created_at` or `updated_at`, and `sort_order` is `asc` or `desc`.
+    #[instrument(skip(self))]
+    async fn get_folder_contents(
+        &self,
+        folder_id: Option<Id>,
+        drive_id: Id,
+        limit: u32,
+        offset: u32,
+        sort_by: Option<String>,
+        sort_order: Option<String>,
+    ) -> Result<FolderContents> {
+        let sort_by = sort_by.unwrap_or_else(|| "name".to_string());
+        if !matches!(sort_by.as_str(), "name" | "size" | "type" | "created_at" | "updated_at") {
+            return Err(Error::BadRequest(format!("Can't sort by {}", sort_by)));
+        }
+        let ascending = match sort_order.as_deref() {
+            None | Some("asc") => true,
+            Some("desc") => false,
+            Some(other) => return Err(Error::BadRequest(format!("Unknown sort order {}", other))),
+        };
+
+        let folder = match folder_id {
+            Some(folder_id) => self
+                .get_folder_by_id(folder_id)
+                .await?
+                .filter(|folder| folder.drive_id == drive_id)
+                .ok_or_else(|| Error::NotFound("Folder not found".to_string()))?,
+            None => {
+                // The drive root isn't stored as a folder
+                let drive = self
+                    .get_drive_by_id(drive_id)
+                    .await?
+                    .ok_or_else(|| Error::NotFound("Drive not found".to_string()))?;
+                let mut root = Folder::new(drive.id, drive.owner_id, drive.name, None);
+                root.id = drive.id;
+                root.created_at = drive.created_at;
+                root.updated_at = drive.updated_at;
+                root
+            }
+        };
+
+        let mut conn = self.pool.acquire().await.map_err(Error::Database)?;
+        let total_count = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) AS "count!" FROM drive_items
+            WHERE drive_id = $1 AND parent_id IS NOT DISTINCT FROM $2 AND NOT is_trashed
+            "#,
+            drive_id,
+            folder_id
+        )
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(Error::Database)?;
+
+        let ids = sqlx::query_scalar!(
+            r#"
+            SELECT id FROM drive_items
+            WHERE drive_id = $1 AND parent_id IS NOT DISTINCT FROM $2 AND NOT is_trashed
+            ORDER BY item_type = 'Folder' DESC,
+                CASE WHEN $3 = 'name' AND $4 THEN lower(name) END ASC,
+                CASE WHEN $3 = 'name' AND NOT $4 THEN lower(name) END DESC,
+                CASE WHEN $3 = 'type' AND $4 THEN mime_type END ASC,
+                CASE WHEN $3 = 'type' AND NOT $4 THEN mime_type END DESC,
+                CASE WHEN $3 = 'size' AND $4 THEN size END ASC,
+                CASE WHEN $3 = 'size' AND NOT $4 THEN size END DESC,
+                CASE WHEN $3 = 'created_at' AND $4 THEN created_at END ASC,
+                CASE WHEN $3 = 'created_at' AND NOT $4 THEN created_at END DESC,
+                CASE WHEN $3 = 'updated_at' AND $4 THEN updated_at END ASC,
+                CASE WHEN $3 = 'updated_at' AND NOT $4 THEN updated_at END DESC,
+                lower(name), id
+            LIMIT $5 OFFSET $6
+            "#,
+            drive_id,
+            folder_id,
+            sort_by,
+            ascending,
+            i64::from(limit),
+            i64::from(offset)
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(Error::Database)?;
+        let items = load_items(&mut conn, &ids).await?;
+
+        let mut owner_ids: Vec<Id> = items.iter().map(|item| item.permissions.owner_id).collect();
+        owner_ids.sort();
+        owner_ids.dedup();
+        let owners: HashMap<Id, ItemOwner> = sqlx::query!(
+            "SELECT id, username, first_name, last_name, email FROM users WHERE id = ANY($1)",
+            &owner_ids
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(Error::Database)?
+        .into_iter()
+        .map(|row| {
+            let owner = ItemOwner {
+                id: row.id,
+                username: row.username,
+                full_name: format!("{} {}", row.first_name, row.last_name),
+                email: row.email,
+            };
+            (row.id, owner)
+        })
+        .collect();
+
+        let items = items
+            .into_iter()
+            .filter_map(|item| {
+                let owner = owners.get(&item.permissions.owner_id)?.clone();
+                Some(DriveItemResponse::from_item(item, owner))
+            })
+            .collect::<Vec<_>>();
+
+        Ok(FolderContents {
+            folder,
+            has_more: i64::from(offset) + (ids.len() as i64) < total_count,
+            total_count,
+            items,
+        })
+    }
+}
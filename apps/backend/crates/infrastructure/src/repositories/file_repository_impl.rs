@@ -1,7 +1,7 @@
 use async_trait::async_trait;
 use kingshare_core::{Error, Id, PaginatedResponse, PaginationInfo, PaginationParams, Result};
 use kingshare_domain::{
-    entities::{File, FileMetadata, FileOwner},
+    entities::{File, FileMetadata, FileOwner, ScanStatus},
     repositories::FileRepository,
 };
 use sqlx::PgPool;
@@ -25,9 +25,9 @@ impl FileRepository for PostgresFileRepository {
         sqlx::query!(
             r#"
             INSERT INTO files (id, owner_id, filename, original_filename, content_type, size, 
-                             storage_path, checksum, is_public, download_count, created_at, 
-                             updated_at, expires_at)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+                             storage_path, checksum, is_public, download_count, scan_status,
+                             scan_signature, scanned_at, created_at, updated_at, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16)
             "#,
             file.id,
             file.owner_id,
@@ -39,6 +39,9 @@ impl FileRepository for PostgresFileRepository {
             file.checksum,
             file.is_public,
             file.download_count,
+            file.scan_status.as_str(),
+            file.scan_signature,
+            file.scanned_at,
             file.created_at,
             file.updated_at,
             file.expires_at
@@ -56,8 +59,8 @@ impl FileRepository for PostgresFileRepository {
         let row = sqlx::query!(
             r#"
             SELECT id, owner_id, filename, original_filename, content_type, size,
-                   storage_path, checksum, is_public, download_count, created_at,
-                   updated_at, expires_at
+                   storage_path, checksum, is_public, download_count, scan_status,
+                   scan_signature, scanned_at, created_at, updated_at, expires_at
             FROM files WHERE id = $1
             "#,
             id
@@ -78,6 +81,9 @@ impl FileRepository for PostgresFileRepository {
                 checksum: row.checksum,
                 is_public: row.is_public,
                 download_count: row.download_count,
+                scan_status: row.scan_status.parse()?,
+                scan_signature: row.scan_signature,
+                scanned_at: row.scanned_at,
                 created_at: row.created_at,
                 updated_at: row.updated_at,
                 expires_at: row.expires_at,
@@ -98,7 +104,7 @@ impl FileRepository for PostgresFileRepository {
         let rows = sqlx::query!(
             r#"
             SELECT f.id, f.filename, f.original_filename, f.content_type, f.size,
-                   f.is_public, f.download_count, f.created_at, f.expires_at,
+                   f.is_public, f.download_count, f.scan_status, f.created_at, f.expires_at,
                    u.id as owner_id, u.username, u.first_name, u.last_name
             FROM files f
             JOIN users u ON f.owner_id = u.id
@@ -118,23 +124,26 @@ impl FileRepository for PostgresFileRepository {
 
         let files: Vec<FileMetadata> = rows
             .into_iter()
-            .map(|row| FileMetadata {
-                id: row.id,
-                filename: row.filename,
-                original_filename: row.original_filename,
-                content_type: row.content_type,
-                size: row.size,
-                is_public: row.is_public,
-                download_count: row.download_count,
-                created_at: row.created_at,
-                expires_at: row.expires_at,
-                owner: FileOwner {
-                    id: row.owner_id,
-                    username: row.username,
-                    full_name: format!("{} {}", row.first_name, row.last_name),
-                },
+            .map(|row| {
+                Ok(FileMetadata {
+                    id: row.id,
+                    filename: row.filename,
+                    original_filename: row.original_filename,
+                    content_type: row.content_type,
+                    size: row.size,
+                    is_public: row.is_public,
+                    download_count: row.download_count,
+                    scan_status: row.scan_status.parse()?,
+                    created_at: row.created_at,
+                    expires_at: row.expires_at,
+                    owner: FileOwner {
+                        id: row.owner_id,
+                        username: row.username,
+                        full_name: format!("{} {}", row.first_name, row.last_name),
+                    },
+                })
             })
-            .collect();
+            .collect::<Result<_>>()?;
 
         let pagination = PaginationInfo::new(params.page.unwrap_or(1), params.limit(), total);
 
@@ -155,7 +164,7 @@ impl FileRepository for PostgresFileRepository {
         let rows = sqlx::query!(
             r#"
             SELECT f.id, f.filename, f.original_filename, f.content_type, f.size,
-                   f.is_public, f.download_count, f.created_at, f.expires_at,
+                   f.is_public, f.download_count, f.scan_status, f.created_at, f.expires_at,
                    u.id as owner_id, u.username, u.first_name, u.last_name
             FROM files f
             JOIN users u ON f.owner_id = u.id
@@ -180,23 +189,26 @@ impl FileRepository for PostgresFileRepository {
 
         let files: Vec<FileMetadata> = rows
             .into_iter()
-            .map(|row| FileMetadata {
-                id: row.id,
-                filename: row.filename,
-                original_filename: row.original_filename,
-                content_type: row.content_type,
-                size: row.size,
-                is_public: row.is_public,
-                download_count: row.download_count,
-                created_at: row.created_at,
-                expires_at: row.expires_at,
-                owner: FileOwner {
-                    id: row.owner_id,
-                    username: row.username,
-                    full_name: format!("{} {}", row.first_name, row.last_name),
-                },
+            .map(|row| {
+                Ok(FileMetadata {
+                    id: row.id,
+                    filename: row.filename,
+                    original_filename: row.original_filename,
+                    content_type: row.content_type,
+                    size: row.size,
+                    is_public: row.is_public,
+                    download_count: row.download_count,
+                    scan_status: row.scan_status.parse()?,
+                    created_at: row.created_at,
+                    expires_at: row.expires_at,
+                    owner: FileOwner {
+                        id: row.owner_id,
+                        username: row.username,
+                        full_name: format!("{} {}", row.first_name, row.last_name),
+                    },
+                })
             })
-            .collect();
+            .collect::<Result<_>>()?;
 
         let pagination = PaginationInfo::new(params.page.unwrap_or(1), params.limit(), total);
 
@@ -213,7 +225,8 @@ impl FileRepository for PostgresFileRepository {
             UPDATE files 
             SET filename = $2, original_filename = $3, content_type = $4, size = $5,
                 storage_path = $6, checksum = $7, is_public = $8, download_count = $9,
-                updated_at = $10, expires_at = $11
+                scan_status = $10, scan_signature = $11, scanned_at = $12,
+                updated_at = $13, expires_at = $14
             WHERE id = $1
             "#,
             file.id,
@@ -225,6 +238,9 @@ impl FileRepository for PostgresFileRepository {
             file.checksum,
             file.is_public,
             file.download_count,
+            file.scan_status.as_str(),
+            file.scan_signature,
+            file.scanned_at,
             file.updated_at,
             file.expires_at
         )
@@ -265,8 +281,8 @@ impl FileRepository for PostgresFileRepository {
         let row = sqlx::query!(
             r#"
             SELECT id, owner_id, filename, original_filename, content_type, size,
-                   storage_path, checksum, is_public, download_count, created_at,
-                   updated_at, expires_at
+                   storage_path, checksum, is_public, download_count, scan_status,
+                   scan_signature, scanned_at, created_at, updated_at, expires_at
             FROM files WHERE checksum = $1
             "#,
             checksum
@@ -287,6 +303,9 @@ impl FileRepository for PostgresFileRepository {
                 checksum: row.checksum,
                 is_public: row.is_public,
                 download_count: row.download_count,
+                scan_status: row.scan_status.parse()?,
+                scan_signature: row.scan_signature,
+                scanned_at: row.scanned_at,
                 created_at: row.created_at,
                 updated_at: row.updated_at,
                 expires_at: row.expires_at,
@@ -326,8 +345,8 @@ impl FileRepository for PostgresFileRepository {
         let rows = sqlx::query!(
             r#"
             SELECT id, owner_id, filename, original_filename, content_type, size,
-                   storage_path, checksum, is_public, download_count, created_at,
-                   updated_at, expires_at
+                   storage_path, checksum, is_public, download_count, scan_status,
+                   scan_signature, scanned_at, created_at, updated_at, expires_at
             FROM files 
             WHERE expires_at IS NOT NULL AND expires_at <= NOW()
             "#
@@ -336,26 +355,28 @@ impl FileRepository for PostgresFileRepository {
         .await
         .map_err(Error::Database)?;
 
-        let files = rows
-            .into_iter()
-            .map(|row| File {
-                id: row.id,
-                owner_id: row.owner_id,
-                filename: row.filename,
-                original_filename: row.original_filename,
-                content_type: row.content_type,
-                size: row.size,
-                storage_path: row.storage_path,
-                checksum: row.checksum,
-                is_public: row.is_public,
-                download_count: row.download_count,
-                created_at: row.created_at,
-                updated_at: row.updated_at,
-                expires_at: row.expires_at,
+        rows.into_iter()
+            .map(|row| {
+                Ok(File {
+                    id: row.id,
+                    owner_id: row.owner_id,
+                    filename: row.filename,
+                    original_filename: row.original_filename,
+                    content_type: row.content_type,
+                    size: row.size,
+                    storage_path: row.storage_path,
+                    checksum: row.checksum,
+                    is_public: row.is_public,
+                    download_count: row.download_count,
+                    scan_status: row.scan_status.parse()?,
+                    scan_signature: row.scan_signature,
+                    scanned_at: row.scanned_at,
+                    created_at: row.created_at,
+                    updated_at: row.updated_at,
+                    expires_at: row.expires_at,
+                })
             })
-            .collect();
-
-        Ok(files)
+            .collect()
     }
 
     #[instrument(skip(self))]
@@ -371,4 +392,123 @@ impl FileRepository for PostgresFileRepository {
         info!(deleted_count = deleted_count, "Expired files cleaned up");
         Ok(deleted_count)
     }
+
+    #[instrument(skip(self))]
+    async fn record_scan_result(
+        &self,
+        checksum: &str,
+        status: ScanStatus,
+        signature: Option<String>,
+        quarantine_path: Option<String>,
+    ) -> Result<Vec<File>> {
+        let mut tx = self.pool.begin().await.map_err(Error::Database)?;
+
+        // The blob row is the source of truth for where new uploads of this content go
+        if let Some(path) = &quarantine_path {
+            sqlx::query!(
+                "UPDATE blobs SET storage_path = $2 WHERE checksum = $1",
+                checksum,
+                path
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(Error::Database)?;
+        }
+
+        // Infected is final: a later clean verdict, e.g. from a scanner that
+        // lost a signature, must not hand quarantined content back out
+        let rows = sqlx::query!(
+            r#"
+            UPDATE files
+            SET scan_status = $2, scan_signature = $3, scanned_at = NOW(),
+                storage_path = COALESCE($4, storage_path), updated_at = NOW()
+            WHERE checksum = $1 AND scan_status <> $2 AND scan_status <> 'infected'
+            RETURNING id, owner_id, filename, original_filename, content_type, size,
+                      storage_path, checksum, is_public, download_count, scan_status,
+                      scan_signature, scanned_at, created_at, updated_at, expires_at
+            "#,
+            checksum,
+            status.as_str(),
+            signature,
+            quarantine_path
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(Error::Database)?;
+
+        tx.commit().await.map_err(Error::Database)?;
+
+        info!(
+            checksum = %checksum,
+            scan_status = status.as_str(),
+            file_count = rows.len(),
+            "Scan result recorded"
+        );
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(File {
+                    id: row.id,
+                    owner_id: row.owner_id,
+                    filename: row.filename,
+                    original_filename: row.original_filename,
+                    content_type: row.content_type,
+                    size: row.size,
+                    storage_path: row.storage_path,
+                    checksum: row.checksum,
+                    is_public: row.is_public,
+                    download_count: row.download_count,
+                    scan_status: row.scan_status.parse()?,
+                    scan_signature: row.scan_signature,
+                    scanned_at: row.scanned_at,
+                    created_at: row.created_at,
+                    updated_at: row.updated_at,
+                    expires_at: row.expires_at,
+                })
+            })
+            .collect()
+    }
+
+    #[instrument(skip(self))]
+    async fn find_pending_scan(&self, limit: i64) -> Result<Vec<File>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT DISTINCT ON (checksum)
+                   id, owner_id, filename, original_filename, content_type, size,
+                   storage_path, checksum, is_public, download_count, scan_status,
+                   scan_signature, scanned_at, created_at, updated_at, expires_at
+            FROM files
+            WHERE scan_status = 'pending'
+            ORDER BY checksum, created_at
+            LIMIT $1
+            "#,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(File {
+                    id: row.id,
+                    owner_id: row.owner_id,
+                    filename: row.filename,
+                    original_filename: row.original_filename,
+                    content_type: row.content_type,
+                    size: row.size,
+                    storage_path: row.storage_path,
+                    checksum: row.checksum,
+                    is_public: row.is_public,
+                    download_count: row.download_count,
+                    scan_status: row.scan_status.parse()?,
+                    scan_signature: row.scan_signature,
+                    scanned_at: row.scanned_at,
+                    created_at: row.created_at,
+                    updated_at: row.updated_at,
+                    expires_at: row.expires_at,
+                })
+            })
+            .collect()
+    }
 }
\ No newline at end of file
@@ -0,0 +1,137 @@
+use async_trait::async_trait;
+use kingshare_core::{Error, Id, Result};
+use kingshare_domain::{
+    entities::{OidcLoginState, UserIdentity},
+    repositories::IdentityRepository,
+};
+use sqlx::PgPool;
+use tracing::{info, instrument};
+
+#[derive(Debug, Clone)]
+pub struct PostgresIdentityRepository {
+    pool: PgPool,
+}
+
+impl PostgresIdentityRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl IdentityRepository for PostgresIdentityRepository {
+    #[instrument(skip(self))]
+    async fn find_identity(&self, issuer: &str, subject: &str) -> Result<Option<UserIdentity>> {
+        let row = sqlx::query!(
+            r#"
+            SELECT id, user_id, issuer, subject, email, created_at, last_login_at
+            FROM user_identities WHERE issuer = $1 AND subject = $2
+            "#,
+            issuer,
+            subject
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        Ok(row.map(|row| UserIdentity {
+            id: row.id,
+            user_id: row.user_id,
+            issuer: row.issuer,
+            subject: row.subject,
+            email: row.email,
+            created_at: row.created_at,
+            last_login_at: row.last_login_at,
+        }))
+    }
+
+    #[instrument(skip(self, identity), fields(user_id = %identity.user_id))]
+    async fn create_identity(&self, identity: UserIdentity) -> Result<UserIdentity> {
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO user_identities (id, user_id, issuer, subject, email, created_at, last_login_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (issuer, subject) DO NOTHING
+            "#,
+            identity.id,
+            identity.user_id,
+            identity.issuer,
+            identity.subject,
+            identity.email,
+            identity.created_at,
+            identity.last_login_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        if result.rows_affected() == 0 {
+            return Err(Error::Conflict("Identity is already linked to an account".to_string()));
+        }
+
+        info!(identity_id = %identity.id, "Identity linked");
+        Ok(identity)
+    }
+
+    #[instrument(skip(self, email))]
+    async fn record_identity_login(&self, id: Id, email: Option<String>) -> Result<()> {
+        sqlx::query!(
+            "UPDATE user_identities SET email = $2, last_login_at = NOW() WHERE id = $1",
+            id,
+            email
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self, login))]
+    async fn save_login_state(&self, login: OidcLoginState) -> Result<()> {
+        // Abandoned logins are only ever read by their state, so clear them out here
+        sqlx::query!("DELETE FROM oidc_login_states WHERE expires_at <= NOW()")
+            .execute(&self.pool)
+            .await
+            .map_err(Error::Database)?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO oidc_login_states (state, nonce, code_verifier, expires_at)
+            VALUES ($1, $2, $3, $4)
+            "#,
+            login.state,
+            login.nonce,
+            login.code_verifier,
+            login.expires_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self, state))]
+    async fn take_login_state(&self, state: &str) -> Result<Option<OidcLoginState>> {
+        let row = sqlx::query!(
+            r#"
+            DELETE FROM oidc_login_states WHERE state = $1
+            RETURNING state, nonce, code_verifier, expires_at
+            "#,
+            state
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        Ok(row
+            .map(|row| OidcLoginState {
+                state: row.state,
+                nonce: row.nonce,
+                code_verifier: row.code_verifier,
+                expires_at: row.expires_at,
+            })
+            .filter(|login| !login.is_expired()))
+    }
+}
@@ -0,0 +1,287 @@
+use async_trait::async_trait;
+use kingshare_core::{Error, Result, Timestamp};
+use kingshare_domain::{
+    entities::{AttemptBlock, AttemptKey, FailedAttempts, LockoutPolicy},
+    repositories::LockoutRepository,
+};
+use sqlx::PgPool;
+use tracing::{info, instrument};
+
+#[derive(Debug, Clone)]
+pub struct PostgresLockoutRepository {
+    pool: PgPool,
+}
+
+impl PostgresLockoutRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+fn to_failed_attempts(
+    kind: String,
+    subject: String,
+    failures: i32,
+    last_failure_at: Timestamp,
+    blocked_until: Option<Timestamp>,
+    locked: bool,
+) -> Result<FailedAttempts> {
+    Ok(FailedAttempts {
+        key: AttemptKey {
+            kind: kind.parse()?,
+            subject,
+        },
+        failures: failures.max(0) as u32,
+        last_failure_at,
+        blocked_until,
+        locked,
+    })
+}
+
+#[async_trait]
+impl LockoutRepository for PostgresLockoutRepository {
+    #[instrument(skip(self, keys))]
+    async fn find(&self, keys: &[AttemptKey]) -> Result<Vec<FailedAttempts>> {
+        let kinds: Vec<String> = keys.iter().map(|key| key.kind.as_str().to_string()).collect();
+        let subjects: Vec<String> = keys.iter().map(|key| key.subject.clone()).collect();
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT a.kind, a.subject, a.failures, a.last_failure_at, a.blocked_until, a.locked
+            FROM failed_attempts a
+            JOIN UNNEST($1::text[], $2::text[]) AS k(kind, subject)
+              ON a.kind = k.kind AND a.subject = k.subject
+            "#,
+            &kinds,
+            &subjects
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        rows.into_iter()
+            .map(|row| {
+                to_failed_attempts(
+                    row.kind,
+                    row.subject,
+                    row.failures,
+                    row.last_failure_at,
+                    row.blocked_until,
+                    row.locked,
+                )
+            })
+            .collect()
+    }
+
+    #[instrument(skip(self), fields(key = %key))]
+    async fn record_failure(&self, key: &AttemptKey, window_start: Timestamp) -> Result<FailedAttempts> {
+        // A stale record starts over, dropping any block it still carries
+        let row = sqlx::query!(
+            r#"
+            INSERT INTO failed_attempts (kind, subject, failures, last_failure_at)
+            VALUES ($1, $2, 1, NOW())
+            ON CONFLICT (kind, subject) DO UPDATE SET
+                failures = CASE WHEN failed_attempts.last_failure_at < $3 THEN 1
+                                ELSE failed_attempts.failures + 1 END,
+                blocked_until = CASE WHEN failed_attempts.last_failure_at < $3 THEN NULL
+                                     ELSE failed_attempts.blocked_until END,
+                locked = failed_attempts.locked AND failed_attempts.last_failure_at >= $3,
+                last_failure_at = NOW()
+            RETURNING kind, subject, failures, last_failure_at, blocked_until, locked
+            "#,
+            key.kind.as_str(),
+            key.subject,
+            window_start
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        to_failed_attempts(
+            row.kind,
+            row.subject,
+            row.failures,
+            row.last_failure_at,
+            row.blocked_until,
+            row.locked,
+        )
+    }
+
+    #[instrument(skip(self, policy), fields(key = %key))]
+    async fn reserve_attempt(
+        &self,
+        key: &AttemptKey,
+        window_start: Timestamp,
+        policy: &LockoutPolicy,
+    ) -> Result<(FailedAttempts, bool)> {
+        let mut tx = self.pool.begin().await.map_err(Error::Database)?;
+
+        // Locks the key's record, so attempts against it are counted one by one
+        let row = sqlx::query!(
+            r#"
+            INSERT INTO failed_attempts (kind, subject, failures, last_failure_at)
+            VALUES ($1, $2, 0, NOW())
+            ON CONFLICT (kind, subject) DO UPDATE SET kind = EXCLUDED.kind
+            RETURNING kind, subject, failures, last_failure_at, blocked_until, locked
+            "#,
+            key.kind.as_str(),
+            key.subject
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(Error::Database)?;
+        let attempts = to_failed_attempts(
+            row.kind,
+            row.subject,
+            row.failures,
+            row.last_failure_at,
+            row.blocked_until,
+            row.locked,
+        )?;
+
+        let now = chrono::Utc::now();
+        // A stale record starts over, dropping any block it still carries
+        let stale = attempts.last_failure_at < window_start;
+        if !stale && attempts.retry_after(now).is_some() {
+            return Ok((attempts, false));
+        }
+        let failures = if stale { 1 } else { attempts.failures + 1 };
+
+        let (blocked_until, locked) = match policy.block_after(key.kind, failures) {
+            Some(AttemptBlock::Backoff(delay)) => (Some(now + delay), false),
+            Some(AttemptBlock::Lockout(duration)) => (Some(now + duration), true),
+            None => (None, false),
+        };
+        let row = sqlx::query!(
+            r#"
+            UPDATE failed_attempts
+            SET failures = $3, last_failure_at = NOW(), blocked_until = $4, locked = $5
+            WHERE kind = $1 AND subject = $2
+            RETURNING kind, subject, failures, last_failure_at, blocked_until, locked
+            "#,
+            key.kind.as_str(),
+            key.subject,
+            failures as i32,
+            blocked_until,
+            locked
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(Error::Database)?;
+        tx.commit().await.map_err(Error::Database)?;
+
+        let attempts = to_failed_attempts(
+            row.kind,
+            row.subject,
+            row.failures,
+            row.last_failure_at,
+            row.blocked_until,
+            row.locked,
+        )?;
+        Ok((attempts, true))
+    }
+
+    #[instrument(skip(self), fields(key = %key))]
+    async fn release_attempt(&self, key: &AttemptKey, blocked_until: Option<Timestamp>) -> Result<()> {
+        // A block set since by another attempt stays
+        sqlx::query!(
+            r#"
+            UPDATE failed_attempts SET
+                failures = GREATEST(failures - 1, 0),
+                locked = locked AND blocked_until IS DISTINCT FROM $3,
+                blocked_until = CASE WHEN blocked_until IS NOT DISTINCT FROM $3 THEN NULL
+                                     ELSE blocked_until END
+            WHERE kind = $1 AND subject = $2
+            "#,
+            key.kind.as_str(),
+            key.subject,
+            blocked_until
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self), fields(key = %key))]
+    async fn block(&self, key: &AttemptKey, until: Timestamp, locked: bool) -> Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE failed_attempts SET blocked_until = $3, locked = $4
+            WHERE kind = $1 AND subject = $2
+            "#,
+            key.kind.as_str(),
+            key.subject,
+            until,
+            locked
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self), fields(key = %key))]
+    async fn clear(&self, key: &AttemptKey) -> Result<bool> {
+        let result = sqlx::query!(
+            "DELETE FROM failed_attempts WHERE kind = $1 AND subject = $2",
+            key.kind.as_str(),
+            key.subject
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[instrument(skip(self))]
+    async fn list_blocked(&self) -> Result<Vec<FailedAttempts>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT kind, subject, failures, last_failure_at, blocked_until, locked
+            FROM failed_attempts
+            WHERE blocked_until > NOW()
+            ORDER BY locked DESC, blocked_until DESC
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        rows.into_iter()
+            .map(|row| {
+                to_failed_attempts(
+                    row.kind,
+                    row.subject,
+                    row.failures,
+                    row.last_failure_at,
+                    row.blocked_until,
+                    row.locked,
+                )
+            })
+            .collect()
+    }
+
+    #[instrument(skip(self))]
+    async fn prune(&self, window_start: Timestamp) -> Result<u64> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM failed_attempts
+            WHERE last_failure_at < $1 AND (blocked_until IS NULL OR blocked_until <= NOW())
+            "#,
+            window_start
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        let pruned = result.rows_affected();
+        if pruned > 0 {
+            info!(pruned, "Stale failed attempts pruned");
+        }
+        Ok(pruned)
+    }
+}
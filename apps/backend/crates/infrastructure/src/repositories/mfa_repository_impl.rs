@@ -0,0 +1,214 @@
+use async_trait::async_trait;
+use kingshare_core::{Error, Id, Result};
+use kingshare_domain::{
+    entities::{RecoveryCode, TotpEnrollment},
+    repositories::MfaRepository,
+};
+use sqlx::{PgPool, Postgres, Transaction};
+use tracing::instrument;
+
+#[derive(Debug, Clone)]
+pub struct PostgresMfaRepository {
+    pool: PgPool,
+}
+
+impl PostgresMfaRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    async fn replace_codes(
+        tx: &mut Transaction<'_, Postgres>,
+        user_id: Id,
+        recovery_codes: Vec<RecoveryCode>,
+    ) -> Result<()> {
+        sqlx::query!("DELETE FROM mfa_recovery_codes WHERE user_id = $1", user_id)
+            .execute(&mut **tx)
+            .await
+            .map_err(Error::Database)?;
+
+        for code in recovery_codes {
+            sqlx::query!(
+                r#"
+                INSERT INTO mfa_recovery_codes (id, user_id, code_hash, created_at)
+                VALUES ($1, $2, $3, $4)
+                "#,
+                code.id,
+                user_id,
+                code.code_hash,
+                code.created_at
+            )
+            .execute(&mut **tx)
+            .await
+            .map_err(Error::Database)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl MfaRepository for PostgresMfaRepository {
+    #[instrument(skip(self))]
+    async fn find_enrollment(&self, user_id: Id) -> Result<Option<TotpEnrollment>> {
+        let row = sqlx::query!(
+            r#"
+            SELECT user_id, secret, created_at, confirmed_at, last_used_step
+            FROM user_totp WHERE user_id = $1
+            "#,
+            user_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        Ok(row.map(|row| TotpEnrollment {
+            user_id: row.user_id,
+            secret: row.secret,
+            created_at: row.created_at,
+            confirmed_at: row.confirmed_at,
+            last_used_step: row.last_used_step,
+        }))
+    }
+
+    #[instrument(skip(self, enrollment), fields(user_id = %enrollment.user_id))]
+    async fn save_pending_enrollment(&self, enrollment: TotpEnrollment) -> Result<TotpEnrollment> {
+        // A confirmed secret is never overwritten; it has to be disabled first
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO user_totp (user_id, secret, created_at)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (user_id) DO UPDATE
+            SET secret = EXCLUDED.secret, created_at = EXCLUDED.created_at, last_used_step = NULL
+            WHERE user_totp.confirmed_at IS NULL
+            "#,
+            enrollment.user_id,
+            enrollment.secret,
+            enrollment.created_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        if result.rows_affected() == 0 {
+            return Err(Error::Conflict("Two-factor authentication is already enabled".to_string()));
+        }
+
+        Ok(enrollment)
+    }
+
+    #[instrument(skip(self, recovery_codes))]
+    async fn confirm_enrollment(
+        &self,
+        user_id: Id,
+        step: i64,
+        recovery_codes: Vec<RecoveryCode>,
+    ) -> Result<bool> {
+        let mut tx = self.pool.begin().await.map_err(Error::Database)?;
+
+        let confirmed = sqlx::query!(
+            r#"
+            UPDATE user_totp
+            SET confirmed_at = NOW(), last_used_step = $2
+            WHERE user_id = $1 AND confirmed_at IS NULL
+            "#,
+            user_id,
+            step
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(Error::Database)?
+        .rows_affected()
+            > 0;
+
+        if !confirmed {
+            tx.rollback().await.map_err(Error::Database)?;
+            return Ok(false);
+        }
+
+        Self::replace_codes(&mut tx, user_id, recovery_codes).await?;
+        tx.commit().await.map_err(Error::Database)?;
+        Ok(true)
+    }
+
+    #[instrument(skip(self))]
+    async fn record_totp_step(&self, user_id: Id, step: i64) -> Result<bool> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE user_totp
+            SET last_used_step = $2
+            WHERE user_id = $1
+              AND confirmed_at IS NOT NULL
+              AND (last_used_step IS NULL OR last_used_step < $2)
+            "#,
+            user_id,
+            step
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[instrument(skip(self, recovery_codes))]
+    async fn replace_recovery_codes(&self, user_id: Id, recovery_codes: Vec<RecoveryCode>) -> Result<()> {
+        let mut tx = self.pool.begin().await.map_err(Error::Database)?;
+        Self::replace_codes(&mut tx, user_id, recovery_codes).await?;
+        tx.commit().await.map_err(Error::Database)?;
+        Ok(())
+    }
+
+    #[instrument(skip(self, code_hash))]
+    async fn consume_recovery_code(&self, user_id: Id, code_hash: &str) -> Result<bool> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE mfa_recovery_codes
+            SET used_at = NOW()
+            WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL
+            "#,
+            user_id,
+            code_hash
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[instrument(skip(self))]
+    async fn count_unused_recovery_codes(&self, user_id: Id) -> Result<u64> {
+        let count = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) AS "count!"
+            FROM mfa_recovery_codes
+            WHERE user_id = $1 AND used_at IS NULL
+            "#,
+            user_id
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        Ok(count as u64)
+    }
+
+    #[instrument(skip(self))]
+    async fn delete_enrollment(&self, user_id: Id) -> Result<()> {
+        let mut tx = self.pool.begin().await.map_err(Error::Database)?;
+
+        sqlx::query!("DELETE FROM mfa_recovery_codes WHERE user_id = $1", user_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(Error::Database)?;
+
+        sqlx::query!("DELETE FROM user_totp WHERE user_id = $1", user_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(Error::Database)?;
+
+        tx.commit().await.map_err(Error::Database)?;
+        Ok(())
+    }
+}
@@ -1,7 +1,33 @@
 pub mod user_repository_impl;
 pub mod file_repository_impl;
 pub mod share_repository_impl;
+pub mod upload_session_repository_impl;
+pub mod blob_repository_impl;
+pub mod thumbnail_repository_impl;
+pub mod quota_repository_impl;
+pub mod token_repository_impl;
+pub mod mfa_repository_impl;
+pub mod identity_repository_impl;
+pub mod api_token_repository_impl;
+pub mod lockout_repository_impl;
+pub mod security_event_repository_impl;
+pub mod role_repository_impl;
+pub mod team_repository_impl;
+pub mod drive_repository_impl;
 
 pub use user_repository_impl::PostgresUserRepository;
 pub use file_repository_impl::PostgresFileRepository;
-pub use share_repository_impl::PostgresShareRepository;
\ No newline at end of file
+pub use share_repository_impl::PostgresShareRepository;
+pub use upload_session_repository_impl::PostgresUploadSessionRepository;
+pub use blob_repository_impl::PostgresBlobRepository;
+pub use thumbnail_repository_impl::PostgresThumbnailRepository;
+pub use quota_repository_impl::PostgresQuotaRepository;
+pub use token_repository_impl::{InMemoryTokenRepository, PostgresTokenRepository};
+pub use mfa_repository_impl::PostgresMfaRepository;
+pub use identity_repository_impl::PostgresIdentityRepository;
+pub use api_token_repository_impl::PostgresApiTokenRepository;
+pub use lockout_repository_impl::PostgresLockoutRepository;
+pub use security_event_repository_impl::PostgresSecurityEventRepository;
+pub use role_repository_impl::PostgresRoleRepository;
+pub use team_repository_impl::PostgresTeamRepository;
+pub use drive_repository_impl::PostgresDriveRepository;
\ No newline at end of file
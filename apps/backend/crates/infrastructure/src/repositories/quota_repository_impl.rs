@@ -0,0 +1,206 @@
+use async_trait::async_trait;
+use kingshare_core::{Error, Id, Result};
+use kingshare_domain::{
+    entities::{QuotaScope, QuotaUsage},
+    repositories::QuotaRepository,
+};
+use sqlx::PgPool;
+use tracing::instrument;
+
+#[derive(Debug, Clone)]
+pub struct PostgresQuotaRepository {
+    pool: PgPool,
+    default_user_quota: Option<i64>,
+}
+
+impl PostgresQuotaRepository {
+    pub fn new(pool: PgPool, default_user_quota: Option<i64>) -> Self {
+        Self {
+            pool,
+            default_user_quota,
+        }
+    }
+
+    fn not_found(scope: QuotaScope) -> Error {
+        match scope {
+            QuotaScope::User(_) => Error::NotFound("User not found".to_string()),
+            QuotaScope::Drive(_) => Error::NotFound("Drive not found".to_string()),
+        }
+    }
+
+    async fn usage_after_rejection(&self, scope: QuotaScope, bytes: i64) -> Error {
+        match self.get_usage(scope).await {
+            Ok(usage) => Error::QuotaExceeded(format!(
+                "{} bytes requested but only {} of the {}'s {} byte quota are available",
+                bytes,
+                usage.available().unwrap_or(0),
+                scope.describe(),
+                usage.quota.unwrap_or(0)
+            )),
+            Err(e) => e,
+        }
+    }
+}
+
+#[async_trait]
+impl QuotaRepository for PostgresQuotaRepository {
+    #[instrument(skip(self))]
+    async fn reserve(&self, scope: QuotaScope, bytes: i64) -> Result<QuotaUsage> {
+        if bytes < 0 {
+            return Err(Error::Validation("Cannot reserve a negative amount of storage".to_string()));
+        }
+
+        // The quota check and the increment are one statement, so concurrent
+        // reservations serialize on the row and can't overshoot together
+        let usage = match scope {
+            QuotaScope::User(user_id) => sqlx::query!(
+                r#"
+                UPDATE users
+                SET storage_used = storage_used + $2
+                WHERE id = $1
+                  AND (COALESCE(storage_quota, $3) IS NULL
+                       OR storage_used + $2 <= COALESCE(storage_quota, $3))
+                RETURNING id, storage_used, COALESCE(storage_quota, $3) AS "quota?"
+                "#,
+                user_id,
+                bytes,
+                self.default_user_quota
+            )
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(Error::Database)?
+            .map(|row| QuotaUsage {
+                scope,
+                owner_id: row.id,
+                used: row.storage_used,
+                quota: row.quota,
+            }),
+            QuotaScope::Drive(drive_id) => sqlx::query!(
+                r#"
+                UPDATE drives
+                SET storage_used = storage_used + $2
+                WHERE id = $1 AND storage_used + $2 <= storage_quota
+                RETURNING owner_id, storage_used, storage_quota
+                "#,
+                drive_id,
+                bytes
+            )
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(Error::Database)?
+            .map(|row| QuotaUsage {
+                scope,
+                owner_id: row.owner_id,
+                used: row.storage_used,
+                quota: Some(row.storage_quota),
+            }),
+        };
+
+        match usage {
+            Some(usage) => Ok(usage),
+            None => Err(self.usage_after_rejection(scope, bytes).await),
+        }
+    }
+
+    #[instrument(skip(self))]
+    async fn release(&self, scope: QuotaScope, bytes: i64) -> Result<()> {
+        match scope {
+            QuotaScope::User(user_id) => {
+                sqlx::query!(
+                    "UPDATE users SET storage_used = GREATEST(storage_used - $2, 0) WHERE id = $1",
+                    user_id,
+                    bytes
+                )
+                .execute(&self.pool)
+                .await
+                .map_err(Error::Database)?;
+            }
+            QuotaScope::Drive(drive_id) => {
+                sqlx::query!(
+                    "UPDATE drives SET storage_used = GREATEST(storage_used - $2, 0) WHERE id = $1",
+                    drive_id,
+                    bytes
+                )
+                .execute(&self.pool)
+                .await
+                .map_err(Error::Database)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn get_usage(&self, scope: QuotaScope) -> Result<QuotaUsage> {
+        let usage = match scope {
+            QuotaScope::User(user_id) => sqlx::query!(
+                r#"
+                SELECT id, storage_used, COALESCE(storage_quota, $2) AS "quota?"
+                FROM users WHERE id = $1
+                "#,
+                user_id,
+                self.default_user_quota
+            )
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(Error::Database)?
+            .map(|row| QuotaUsage {
+                scope,
+                owner_id: row.id,
+                used: row.storage_used,
+                quota: row.quota,
+            }),
+            QuotaScope::Drive(drive_id) => sqlx::query!(
+                "SELECT owner_id, storage_used, storage_quota FROM drives WHERE id = $1",
+                drive_id
+            )
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(Error::Database)?
+            .map(|row| QuotaUsage {
+                scope,
+                owner_id: row.owner_id,
+                used: row.storage_used,
+                quota: Some(row.storage_quota),
+            }),
+        };
+
+        usage.ok_or_else(|| Self::not_found(scope))
+    }
+
+    #[instrument(skip(self))]
+    async fn set_quota(&self, scope: QuotaScope, quota: Option<i64>) -> Result<QuotaUsage> {
+        if quota.is_some_and(|quota| quota < 0) {
+            return Err(Error::Validation("Storage quota cannot be negative".to_string()));
+        }
+
+        let updated: Option<Id> = match scope {
+            QuotaScope::User(user_id) => sqlx::query_scalar!(
+                "UPDATE users SET storage_quota = $2 WHERE id = $1 RETURNING id",
+                user_id,
+                quota
+            )
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(Error::Database)?,
+            QuotaScope::Drive(drive_id) => {
+                let quota = quota.ok_or_else(|| {
+                    Error::Validation("Drives must have a storage quota".to_string())
+                })?;
+                sqlx::query_scalar!(
+                    "UPDATE drives SET storage_quota = $2 WHERE id = $1 RETURNING id",
+                    drive_id,
+                    quota
+                )
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(Error::Database)?
+            }
+        };
+
+        match updated {
+            Some(_) => self.get_usage(scope).await,
+            None => Err(Self::not_found(scope)),
+        }
+    }
+}
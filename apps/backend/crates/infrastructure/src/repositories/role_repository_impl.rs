@@ -0,0 +1,303 @@
+use async_trait::async_trait;
+use kingshare_core::{Error, Id, Result};
+use kingshare_domain::{
+    entities::{Permission, Role, RoleAssignment, RoleScope},
+    repositories::RoleRepository,
+};
+use sqlx::PgPool;
+use tracing::{info, instrument, warn};
+
+#[derive(Debug, Clone)]
+pub struct PostgresRoleRepository {
+    pool: PgPool,
+}
+
+impl PostgresRoleRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+/// Permissions dropped from a later release are ignored rather than failing
+/// the whole role, which can only narrow what it allows
+fn parse_permissions(permissions: Vec<String>) -> Vec<Permission> {
+    permissions
+        .into_iter()
+        .filter_map(|permission| match permission.parse() {
+            Ok(permission) => Some(permission),
+            Err(_) => {
+                warn!(permission = %permission, "Ignoring unknown role permission");
+                None
+            }
+        })
+        .collect()
+}
+
+fn permission_names(permissions: &[Permission]) -> Vec<String> {
+    permissions.iter().map(|permission| permission.as_str().to_string()).collect()
+}
+
+#[async_trait]
+impl RoleRepository for PostgresRoleRepository {
+    #[instrument(skip(self, role), fields(name = %role.name))]
+    async fn create_role(&self, role: Role) -> Result<Role> {
+        sqlx::query!(
+            r#"
+            INSERT INTO roles (id, name, description, permissions, is_system, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+            role.id,
+            role.name,
+            role.description,
+            &permission_names(&role.permissions),
+            role.is_system,
+            role.created_at,
+            role.updated_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        info!(role_id = %role.id, "Role created");
+        Ok(role)
+    }
+
+    #[instrument(skip(self))]
+    async fn find_role(&self, id: Id) -> Result<Option<Role>> {
+        let row = sqlx::query!(
+            r#"
+            SELECT id, name, description, permissions, is_system, created_at, updated_at
+            FROM roles WHERE id = $1
+            "#,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        Ok(row.map(|row| Role {
+            id: row.id,
+            name: row.name,
+            description: row.description,
+            permissions: parse_permissions(row.permissions),
+            is_system: row.is_system,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        }))
+    }
+
+    #[instrument(skip(self))]
+    async fn find_role_by_name(&self, name: &str) -> Result<Option<Role>> {
+        let row = sqlx::query!(
+            r#"
+            SELECT id, name, description, permissions, is_system, created_at, updated_at
+            FROM roles WHERE name = $1
+            "#,
+            name
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        Ok(row.map(|row| Role {
+            id: row.id,
+            name: row.name,
+            description: row.description,
+            permissions: parse_permissions(row.permissions),
+            is_system: row.is_system,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        }))
+    }
+
+    #[instrument(skip(self, ids))]
+    async fn find_roles(&self, ids: &[Id]) -> Result<Vec<Role>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT id, name, description, permissions, is_system, created_at, updated_at
+            FROM roles WHERE id = ANY($1)
+            "#,
+            ids
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| Role {
+                id: row.id,
+                name: row.name,
+                description: row.description,
+                permissions: parse_permissions(row.permissions),
+                is_system: row.is_system,
+                created_at: row.created_at,
+                updated_at: row.updated_at,
+            })
+            .collect())
+    }
+
+    #[instrument(skip(self))]
+    async fn list_roles(&self) -> Result<Vec<Role>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT id, name, description, permissions, is_system, created_at, updated_at
+            FROM roles
+            ORDER BY is_system DESC, name
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| Role {
+                id: row.id,
+                name: row.name,
+                description: row.description,
+                permissions: parse_permissions(row.permissions),
+                is_system: row.is_system,
+                created_at: row.created_at,
+                updated_at: row.updated_at,
+            })
+            .collect())
+    }
+
+    #[instrument(skip(self, role), fields(role_id = %role.id))]
+    async fn update_role(&self, role: Role) -> Result<Role> {
+        sqlx::query!(
+            r#"
+            UPDATE roles
+            SET description = $2, permissions = $3, updated_at = $4
+            WHERE id = $1
+            "#,
+            role.id,
+            role.description,
+            &permission_names(&role.permissions),
+            role.updated_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        Ok(role)
+    }
+
+    #[instrument(skip(self))]
+    async fn delete_role(&self, id: Id) -> Result<bool> {
+        let result = sqlx::query!("DELETE FROM roles WHERE id = $1", id)
+            .execute(&self.pool)
+            .await
+            .map_err(Error::Database)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[instrument(skip(self, assignment), fields(user_id = %assignment.user_id, role_id = %assignment.role_id))]
+    async fn create_assignment(&self, assignment: RoleAssignment) -> Result<RoleAssignment> {
+        sqlx::query!(
+            r#"
+            INSERT INTO role_assignments (id, user_id, role_id, scope_type, scope_id, granted_by, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+            assignment.id,
+            assignment.user_id,
+            assignment.role_id,
+            assignment.scope.kind(),
+            assignment.scope.id(),
+            assignment.granted_by,
+            assignment.created_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        Ok(assignment)
+    }
+
+    #[instrument(skip(self, assignment))]
+    async fn find_assignment(&self, assignment: &RoleAssignment) -> Result<Option<RoleAssignment>> {
+        let row = sqlx::query!(
+            r#"
+            SELECT id, user_id, role_id, scope_type, scope_id, granted_by, created_at
+            FROM role_assignments
+            WHERE user_id = $1 AND role_id = $2 AND scope_type = $3 AND scope_id IS NOT DISTINCT FROM $4
+            "#,
+            assignment.user_id,
+            assignment.role_id,
+            assignment.scope.kind(),
+            assignment.scope.id()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        row.map(|row| {
+            Ok(RoleAssignment {
+                id: row.id,
+                user_id: row.user_id,
+                role_id: row.role_id,
+                scope: RoleScope::from_parts(&row.scope_type, row.scope_id)?,
+                granted_by: row.granted_by,
+                created_at: row.created_at,
+            })
+        })
+        .transpose()
+    }
+
+    #[instrument(skip(self))]
+    async fn delete_assignment(&self, id: Id) -> Result<Option<RoleAssignment>> {
+        let row = sqlx::query!(
+            r#"
+            DELETE FROM role_assignments WHERE id = $1
+            RETURNING id, user_id, role_id, scope_type, scope_id, granted_by, created_at
+            "#,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        row.map(|row| {
+            Ok(RoleAssignment {
+                id: row.id,
+                user_id: row.user_id,
+                role_id: row.role_id,
+                scope: RoleScope::from_parts(&row.scope_type, row.scope_id)?,
+                granted_by: row.granted_by,
+                created_at: row.created_at,
+            })
+        })
+        .transpose()
+    }
+
+    #[instrument(skip(self))]
+    async fn list_assignments(&self, user_id: Id) -> Result<Vec<RoleAssignment>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT id, user_id, role_id, scope_type, scope_id, granted_by, created_at
+            FROM role_assignments
+            WHERE user_id = $1
+            ORDER BY created_at
+            "#,
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(RoleAssignment {
+                    id: row.id,
+                    user_id: row.user_id,
+                    role_id: row.role_id,
+                    scope: RoleScope::from_parts(&row.scope_type, row.scope_id)?,
+                    granted_by: row.granted_by,
+                    created_at: row.created_at,
+                })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,76 @@
+use async_trait::async_trait;
+use kingshare_core::{Error, Result};
+use kingshare_domain::{
+    entities::{SecurityEvent, SecurityEventType},
+    repositories::SecurityEventRepository,
+};
+use sqlx::PgPool;
+use tracing::instrument;
+
+#[derive(Debug, Clone)]
+pub struct PostgresSecurityEventRepository {
+    pool: PgPool,
+}
+
+impl PostgresSecurityEventRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl SecurityEventRepository for PostgresSecurityEventRepository {
+    #[instrument(skip(self, event), fields(event_type = %event.event_type))]
+    async fn record(&self, event: SecurityEvent) -> Result<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO security_events (id, event_type, actor_id, subject, ip_address, details, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+            event.id,
+            event.event_type.as_str(),
+            event.actor_id,
+            event.subject,
+            event.ip_address,
+            event.details,
+            event.created_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn list_recent(&self, event_type: Option<SecurityEventType>, limit: u32) -> Result<Vec<SecurityEvent>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT id, event_type, actor_id, subject, ip_address, details, created_at
+            FROM security_events
+            WHERE $1::text IS NULL OR event_type = $1
+            ORDER BY created_at DESC
+            LIMIT $2
+            "#,
+            event_type.map(|event_type| event_type.as_str()),
+            i64::from(limit)
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(SecurityEvent {
+                    id: row.id,
+                    event_type: row.event_type.parse()?,
+                    actor_id: row.actor_id,
+                    subject: row.subject,
+                    ip_address: row.ip_address,
+                    details: row.details,
+                    created_at: row.created_at,
+                })
+            })
+            .collect()
+    }
+}
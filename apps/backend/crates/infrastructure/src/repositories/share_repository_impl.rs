@@ -24,7 +24,7 @@ impl ShareRepository for PostgresShareRepository {
     async fn create(&self, share: Share) -> Result<Share> {
         sqlx::query!(
             r#"
-            INSERT INTO shares (id, file_id, owner_id, share_token, password, max_downloads,
+            INSERT INTO shares (id, file_id, owner_id, share_token, password_hash, max_downloads,
                               download_count, is_active, created_at, updated_at, expires_at)
             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
             "#,
@@ -32,7 +32,7 @@ impl ShareRepository for PostgresShareRepository {
             share.file_id,
             share.owner_id,
             share.share_token,
-            share.password,
+            share.password_hash,
             share.max_downloads,
             share.download_count,
             share.is_active,
@@ -52,7 +52,7 @@ impl ShareRepository for PostgresShareRepository {
     async fn find_by_id(&self, id: Id) -> Result<Option<Share>> {
         let row = sqlx::query!(
             r#"
-            SELECT id, file_id, owner_id, share_token, password, max_downloads,
+            SELECT id, file_id, owner_id, share_token, password_hash, max_downloads,
                    download_count, is_active, created_at, updated_at, expires_at
             FROM shares WHERE id = $1
             "#,
@@ -68,7 +68,7 @@ impl ShareRepository for PostgresShareRepository {
                 file_id: row.file_id,
                 owner_id: row.owner_id,
                 share_token: row.share_token,
-                password: row.password,
+                password_hash: row.password_hash,
                 max_downloads: row.max_downloads,
                 download_count: row.download_count,
                 is_active: row.is_active,
@@ -84,7 +84,7 @@ impl ShareRepository for PostgresShareRepository {
     async fn find_by_token(&self, token: &str) -> Result<Option<ShareInfo>> {
         let row = sqlx::query!(
             r#"
-            SELECT s.id, s.share_token, s.password, s.max_downloads, s.download_count,
+            SELECT s.id, s.share_token, s.password_hash, s.max_downloads, s.download_count,
                    s.is_active, s.created_at, s.expires_at,
                    f.id as file_id, f.filename, f.content_type, f.size
             FROM shares s
@@ -110,6 +110,9 @@ impl ShareRepository for PostgresShareRepository {
                     checksum: String::new(),
                     is_public: false,
                     download_count: 0,
+                    scan_status: Default::default(),
+                    scan_signature: None,
+                    scanned_at: None,
                     created_at: chrono::Utc::now(),
                     updated_at: chrono::Utc::now(),
                     expires_at: None,
@@ -118,7 +121,7 @@ impl ShareRepository for PostgresShareRepository {
                 Ok(Some(ShareInfo {
                     id: row.id,
                     share_token: row.share_token,
-                    has_password: row.password.is_some(),
+                    has_password: row.password_hash.is_some(),
                     max_downloads: row.max_downloads,
                     download_count: row.download_count,
                     is_active: row.is_active,
@@ -141,7 +144,7 @@ impl ShareRepository for PostgresShareRepository {
     async fn find_by_file(&self, file_id: Id) -> Result<Vec<Share>> {
         let rows = sqlx::query!(
             r#"
-            SELECT id, file_id, owner_id, share_token, password, max_downloads,
+            SELECT id, file_id, owner_id, share_token, password_hash, max_downloads,
                    download_count, is_active, created_at, updated_at, expires_at
             FROM shares WHERE file_id = $1
             ORDER BY created_at DESC
@@ -159,7 +162,7 @@ impl ShareRepository for PostgresShareRepository {
                 file_id: row.file_id,
                 owner_id: row.owner_id,
                 share_token: row.share_token,
-                password: row.password,
+                password_hash: row.password_hash,
                 max_downloads: row.max_downloads,
                 download_count: row.download_count,
                 is_active: row.is_active,
@@ -183,7 +186,7 @@ impl ShareRepository for PostgresShareRepository {
 
         let rows = sqlx::query!(
             r#"
-            SELECT s.id, s.share_token, s.password, s.max_downloads, s.download_count,
+            SELECT s.id, s.share_token, s.password_hash, s.max_downloads, s.download_count,
                    s.is_active, s.created_at, s.expires_at,
                    f.id as file_id, f.filename, f.content_type, f.size
             FROM shares s
@@ -216,6 +219,9 @@ impl ShareRepository for PostgresShareRepository {
                     checksum: String::new(),
                     is_public: false,
                     download_count: 0,
+                    scan_status: Default::default(),
+                    scan_signature: None,
+                    scanned_at: None,
                     created_at: chrono::Utc::now(),
                     updated_at: chrono::Utc::now(),
                     expires_at: None,
@@ -224,7 +230,7 @@ impl ShareRepository for PostgresShareRepository {
                 ShareInfo {
                     id: row.id,
                     share_token: row.share_token,
-                    has_password: row.password.is_some(),
+                    has_password: row.password_hash.is_some(),
                     max_downloads: row.max_downloads,
                     download_count: row.download_count,
                     is_active: row.is_active,
@@ -254,12 +260,12 @@ impl ShareRepository for PostgresShareRepository {
         sqlx::query!(
             r#"
             UPDATE shares 
-            SET password = $2, max_downloads = $3, download_count = $4, is_active = $5,
+            SET password_hash = $2, max_downloads = $3, download_count = $4, is_active = $5,
                 updated_at = $6, expires_at = $7
             WHERE id = $1
             "#,
             share.id,
-            share.password,
+            share.password_hash,
             share.max_downloads,
             share.download_count,
             share.is_active,
@@ -305,7 +311,7 @@ impl ShareRepository for PostgresShareRepository {
     async fn find_expired_shares(&self) -> Result<Vec<Share>> {
         let rows = sqlx::query!(
             r#"
-            SELECT id, file_id, owner_id, share_token, password, max_downloads,
+            SELECT id, file_id, owner_id, share_token, password_hash, max_downloads,
                    download_count, is_active, created_at, updated_at, expires_at
             FROM shares 
             WHERE expires_at IS NOT NULL AND expires_at <= NOW()
@@ -322,7 +328,7 @@ impl ShareRepository for PostgresShareRepository {
                 file_id: row.file_id,
                 owner_id: row.owner_id,
                 share_token: row.share_token,
-                password: row.password,
+                password_hash: row.password_hash,
                 max_downloads: row.max_downloads,
                 download_count: row.download_count,
                 is_active: row.is_active,
@@ -361,4 +367,53 @@ impl ShareRepository for PostgresShareRepository {
 
         Ok(count.unwrap_or(0) as u64)
     }
-}
\ No newline at end of file
+
+    #[instrument(skip(self))]
+    async fn find_with_legacy_passwords(&self) -> Result<Vec<Share>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT id, file_id, owner_id, share_token, password_hash, max_downloads,
+                   download_count, is_active, created_at, updated_at, expires_at
+            FROM shares
+            WHERE password_hash IS NOT NULL AND password_hash NOT LIKE '$argon2%'
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        let shares = rows
+            .into_iter()
+            .map(|row| Share {
+                id: row.id,
+                file_id: row.file_id,
+                owner_id: row.owner_id,
+                share_token: row.share_token,
+                password_hash: row.password_hash,
+                max_downloads: row.max_downloads,
+                download_count: row.download_count,
+                is_active: row.is_active,
+                created_at: row.created_at,
+                updated_at: row.updated_at,
+                expires_at: row.expires_at,
+            })
+            .collect();
+
+        Ok(shares)
+    }
+
+    #[instrument(skip(self, current, password_hash))]
+    async fn replace_password_hash(&self, id: Id, current: &str, password_hash: &str) -> Result<bool> {
+        let result = sqlx::query!(
+            "UPDATE shares SET password_hash = $3 WHERE id = $1 AND password_hash = $2",
+            id,
+            current,
+            password_hash
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -0,0 +1,127 @@
+use async_trait::async_trait;
+use kingshare_core::{Error, Id, Result};
+use kingshare_domain::{entities::Team, repositories::TeamRepository};
+use sqlx::PgPool;
+use tracing::{info, instrument};
+
+#[derive(Debug, Clone)]
+pub struct PostgresTeamRepository {
+    pool: PgPool,
+}
+
+impl PostgresTeamRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl TeamRepository for PostgresTeamRepository {
+    #[instrument(skip(self, team), fields(name = %team.name))]
+    async fn create(&self, team: Team) -> Result<Team> {
+        sqlx::query!(
+            r#"
+            INSERT INTO teams (id, name, description, created_by, created_at)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+            team.id,
+            team.name,
+            team.description,
+            team.created_by,
+            team.created_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        info!(team_id = %team.id, "Team created");
+        Ok(team)
+    }
+
+    #[instrument(skip(self))]
+    async fn find_by_id(&self, id: Id) -> Result<Option<Team>> {
+        let row = sqlx::query!(
+            "SELECT id, name, description, created_by, created_at FROM teams WHERE id = $1",
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        Ok(row.map(|row| Team {
+            id: row.id,
+            name: row.name,
+            description: row.description,
+            created_by: row.created_by,
+            created_at: row.created_at,
+        }))
+    }
+
+    #[instrument(skip(self))]
+    async fn find_by_name(&self, name: &str) -> Result<Option<Team>> {
+        let row = sqlx::query!(
+            "SELECT id, name, description, created_by, created_at FROM teams WHERE name = $1",
+            name
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        Ok(row.map(|row| Team {
+            id: row.id,
+            name: row.name,
+            description: row.description,
+            created_by: row.created_by,
+            created_at: row.created_at,
+        }))
+    }
+
+    #[instrument(skip(self))]
+    async fn list(&self) -> Result<Vec<Team>> {
+        let rows = sqlx::query!("SELECT id, name, description, created_by, created_at FROM teams ORDER BY name")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(Error::Database)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| Team {
+                id: row.id,
+                name: row.name,
+                description: row.description,
+                created_by: row.created_by,
+                created_at: row.created_at,
+            })
+            .collect())
+    }
+
+    #[instrument(skip(self))]
+    async fn delete(&self, id: Id) -> Result<bool> {
+        // Assignments scoped to the team go with it
+        let mut tx = self.pool.begin().await.map_err(Error::Database)?;
+        sqlx::query!(
+            "DELETE FROM role_assignments WHERE scope_type = 'team' AND scope_id = $1",
+            id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(Error::Database)?;
+        let result = sqlx::query!("DELETE FROM teams WHERE id = $1", id)
+            .execute(&mut *tx)
+            .await
+            .map_err(Error::Database)?;
+        tx.commit().await.map_err(Error::Database)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[instrument(skip(self))]
+    async fn find_drive_team(&self, drive_id: Id) -> Result<Option<Id>> {
+        let team_id = sqlx::query_scalar!("SELECT team_id FROM drives WHERE id = $1", drive_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(Error::Database)?;
+
+        Ok(team_id.flatten())
+    }
+}
@@ -0,0 +1,127 @@
+use async_trait::async_trait;
+use kingshare_core::{Error, Result};
+use kingshare_domain::{
+    entities::{Thumbnail, ThumbnailSize},
+    repositories::ThumbnailRepository,
+};
+use sqlx::PgPool;
+use tracing::instrument;
+
+#[derive(Debug, Clone)]
+pub struct PostgresThumbnailRepository {
+    pool: PgPool,
+}
+
+impl PostgresThumbnailRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl ThumbnailRepository for PostgresThumbnailRepository {
+    #[instrument(skip(self, thumbnail), fields(checksum = %thumbnail.checksum))]
+    async fn create(&self, thumbnail: Thumbnail) -> Result<Thumbnail> {
+        let row = sqlx::query!(
+            r#"
+            INSERT INTO thumbnails (
+                checksum, size, storage_path, thumbnail_checksum, content_type,
+                byte_size, width, height, created_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            ON CONFLICT (checksum, size) DO NOTHING
+            RETURNING checksum, size, storage_path, thumbnail_checksum, content_type,
+                      byte_size, width, height, created_at
+            "#,
+            thumbnail.checksum,
+            thumbnail.size.as_str(),
+            thumbnail.storage_path,
+            thumbnail.thumbnail_checksum,
+            thumbnail.content_type,
+            thumbnail.byte_size,
+            thumbnail.width,
+            thumbnail.height,
+            thumbnail.created_at
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(Error::Database)?
+        .ok_or_else(|| Error::Conflict("Thumbnail already exists".to_string()))?;
+
+        Ok(Thumbnail {
+            checksum: row.checksum,
+            size: row.size.parse()?,
+            storage_path: row.storage_path,
+            thumbnail_checksum: row.thumbnail_checksum,
+            content_type: row.content_type,
+            byte_size: row.byte_size,
+            width: row.width,
+            height: row.height,
+            created_at: row.created_at,
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn find(&self, checksum: &str, size: ThumbnailSize) -> Result<Option<Thumbnail>> {
+        let row = sqlx::query!(
+            r#"
+            SELECT checksum, size, storage_path, thumbnail_checksum, content_type,
+                   byte_size, width, height, created_at
+            FROM thumbnails
+            WHERE checksum = $1 AND size = $2
+            "#,
+            checksum,
+            size.as_str()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        row.map(|row| {
+            Ok::<_, Error>(Thumbnail {
+                checksum: row.checksum,
+                size: row.size.parse()?,
+                storage_path: row.storage_path,
+                thumbnail_checksum: row.thumbnail_checksum,
+                content_type: row.content_type,
+                byte_size: row.byte_size,
+                width: row.width,
+                height: row.height,
+                created_at: row.created_at,
+            })
+        })
+        .transpose()
+    }
+
+    #[instrument(skip(self))]
+    async fn find_by_checksum(&self, checksum: &str) -> Result<Vec<Thumbnail>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT checksum, size, storage_path, thumbnail_checksum, content_type,
+                   byte_size, width, height, created_at
+            FROM thumbnails
+            WHERE checksum = $1
+            "#,
+            checksum
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(Thumbnail {
+                    checksum: row.checksum,
+                    size: row.size.parse()?,
+                    storage_path: row.storage_path,
+                    thumbnail_checksum: row.thumbnail_checksum,
+                    content_type: row.content_type,
+                    byte_size: row.byte_size,
+                    width: row.width,
+                    height: row.height,
+                    created_at: row.created_at,
+                })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,462 @@
+use async_trait::async_trait;
+use kingshare_core::{Error, Id, Result, Timestamp};
+use kingshare_domain::{
+    entities::{RefreshToken, RefreshTokenFamily, RevocationReason},
+    repositories::TokenRepository,
+};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tracing::{info, instrument};
+
+#[derive(Debug, Clone)]
+pub struct PostgresTokenRepository {
+    pool: PgPool,
+}
+
+impl PostgresTokenRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl TokenRepository for PostgresTokenRepository {
+    #[instrument(skip(self, family), fields(family_id = %family.id))]
+    async fn create_family(&self, family: RefreshTokenFamily) -> Result<RefreshTokenFamily> {
+        sqlx::query!(
+            r#"
+            INSERT INTO refresh_token_families
+                (id, user_id, created_at, last_used_at, device, user_agent, ip_address)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+            family.id,
+            family.user_id,
+            family.created_at,
+            family.last_used_at,
+            family.device,
+            family.user_agent,
+            family.ip_address
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        Ok(family)
+    }
+
+    #[instrument(skip(self))]
+    async fn find_family(&self, family_id: Id) -> Result<Option<RefreshTokenFamily>> {
+        let row = sqlx::query!(
+            r#"
+            SELECT id, user_id, created_at, last_used_at, device, user_agent, ip_address,
+                   revoked_at, revoked_reason
+            FROM refresh_token_families WHERE id = $1
+            "#,
+            family_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        row.map(|row| {
+            Ok(RefreshTokenFamily {
+                id: row.id,
+                user_id: row.user_id,
+                created_at: row.created_at,
+                last_used_at: row.last_used_at,
+                device: row.device,
+                user_agent: row.user_agent,
+                ip_address: row.ip_address,
+                revoked_at: row.revoked_at,
+                revoked_reason: row.revoked_reason.map(|reason| reason.parse()).transpose()?,
+            })
+        })
+        .transpose()
+    }
+
+    #[instrument(skip(self))]
+    async fn find_active_families(&self, user_id: Id) -> Result<Vec<RefreshTokenFamily>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT id, user_id, created_at, last_used_at, device, user_agent, ip_address
+            FROM refresh_token_families
+            WHERE user_id = $1 AND revoked_at IS NULL
+            ORDER BY last_used_at DESC
+            "#,
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| RefreshTokenFamily {
+                id: row.id,
+                user_id: row.user_id,
+                created_at: row.created_at,
+                last_used_at: row.last_used_at,
+                device: row.device,
+                user_agent: row.user_agent,
+                ip_address: row.ip_address,
+                revoked_at: None,
+                revoked_reason: None,
+            })
+            .collect())
+    }
+
+    #[instrument(skip(self, token), fields(token_id = %token.id, family_id = %token.family_id))]
+    async fn add_refresh_token(&self, token: RefreshToken) -> Result<()> {
+        let mut tx = self.pool.begin().await.map_err(Error::Database)?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO refresh_tokens (id, family_id, user_id, issued_at, expires_at)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+            token.id,
+            token.family_id,
+            token.user_id,
+            token.issued_at,
+            token.expires_at
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(Error::Database)?;
+
+        sqlx::query!(
+            "UPDATE refresh_token_families SET last_used_at = $2 WHERE id = $1",
+            token.family_id,
+            token.issued_at
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(Error::Database)?;
+
+        tx.commit().await.map_err(Error::Database)?;
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn consume_refresh_token(&self, token_id: Id) -> Result<Option<RefreshToken>> {
+        let row = sqlx::query!(
+            r#"
+            UPDATE refresh_tokens t
+            SET consumed_at = NOW()
+            FROM refresh_token_families f
+            WHERE t.id = $1
+              AND t.family_id = f.id
+              AND t.consumed_at IS NULL
+              AND t.expires_at > NOW()
+              AND f.revoked_at IS NULL
+            RETURNING t.id, t.family_id, t.user_id, t.issued_at, t.expires_at, t.consumed_at
+            "#,
+            token_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        Ok(row.map(|row| RefreshToken {
+            id: row.id,
+            family_id: row.family_id,
+            user_id: row.user_id,
+            issued_at: row.issued_at,
+            expires_at: row.expires_at,
+            consumed_at: row.consumed_at,
+        }))
+    }
+
+    #[instrument(skip(self))]
+    async fn find_refresh_token(&self, token_id: Id) -> Result<Option<RefreshToken>> {
+        let row = sqlx::query!(
+            r#"
+            SELECT id, family_id, user_id, issued_at, expires_at, consumed_at
+            FROM refresh_tokens WHERE id = $1
+            "#,
+            token_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        Ok(row.map(|row| RefreshToken {
+            id: row.id,
+            family_id: row.family_id,
+            user_id: row.user_id,
+            issued_at: row.issued_at,
+            expires_at: row.expires_at,
+            consumed_at: row.consumed_at,
+        }))
+    }
+
+    #[instrument(skip(self))]
+    async fn revoke_family(&self, family_id: Id, reason: RevocationReason) -> Result<bool> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE refresh_token_families
+            SET revoked_at = NOW(), revoked_reason = $2
+            WHERE id = $1 AND revoked_at IS NULL
+            "#,
+            family_id,
+            reason.as_str()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[instrument(skip(self))]
+    async fn revoke_user_families(
+        &self,
+        user_id: Id,
+        except: Option<Id>,
+        reason: RevocationReason,
+    ) -> Result<Vec<Id>> {
+        let revoked = sqlx::query_scalar!(
+            r#"
+            UPDATE refresh_token_families
+            SET revoked_at = NOW(), revoked_reason = $3
+            WHERE user_id = $1
+              AND revoked_at IS NULL
+              AND ($2::uuid IS NULL OR id <> $2)
+            RETURNING id
+            "#,
+            user_id,
+            except,
+            reason.as_str()
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        Ok(revoked)
+    }
+
+    #[instrument(skip(self))]
+    async fn deny_access_token(&self, jti: Id, user_id: Id, expires_at: Timestamp) -> Result<bool> {
+        let denied = sqlx::query!(
+            r#"
+            INSERT INTO revoked_access_tokens (jti, user_id, expires_at)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (jti) DO NOTHING
+            "#,
+            jti,
+            user_id,
+            expires_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Error::Database)?
+        .rows_affected();
+
+        Ok(denied == 1)
+    }
+
+    #[instrument(skip(self))]
+    async fn is_access_token_revoked(&self, jti: Id, family_id: Option<Id>) -> Result<bool> {
+        let revoked = sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS (SELECT 1 FROM revoked_access_tokens WHERE jti = $1)
+                OR EXISTS (
+                    SELECT 1 FROM refresh_token_families
+                    WHERE id = $2 AND revoked_at IS NOT NULL
+                ) AS "revoked!"
+            "#,
+            jti,
+            family_id
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        Ok(revoked)
+    }
+
+    #[instrument(skip(self))]
+    async fn prune_expired(&self, now: Timestamp) -> Result<u64> {
+        let mut tx = self.pool.begin().await.map_err(Error::Database)?;
+
+        let tokens = sqlx::query!("DELETE FROM refresh_tokens WHERE expires_at <= $1", now)
+            .execute(&mut *tx)
+            .await
+            .map_err(Error::Database)?
+            .rows_affected();
+
+        // A family without tokens can't be redeemed or replayed any more
+        let families = sqlx::query!(
+            r#"
+            DELETE FROM refresh_token_families f
+            WHERE NOT EXISTS (SELECT 1 FROM refresh_tokens t WHERE t.family_id = f.id)
+            "#
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(Error::Database)?
+        .rows_affected();
+
+        let denied = sqlx::query!("DELETE FROM revoked_access_tokens WHERE expires_at <= $1", now)
+            .execute(&mut *tx)
+            .await
+            .map_err(Error::Database)?
+            .rows_affected();
+
+        tx.commit().await.map_err(Error::Database)?;
+
+        info!(
+            tokens = tokens,
+            families = families,
+            denied_access_tokens = denied,
+            "Pruned expired tokens"
+        );
+        Ok(tokens + families + denied)
+    }
+}
+
+#[derive(Debug, Default)]
+struct TokenStore {
+    families: HashMap<Id, RefreshTokenFamily>,
+    refresh_tokens: HashMap<Id, RefreshToken>,
+    denied_access_tokens: HashMap<Id, Timestamp>,
+}
+
+/// Process-local `TokenRepository` for tests and single-process tools. Revocations
+/// are not shared between processes, so servers use `PostgresTokenRepository`.
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryTokenRepository {
+    store: Arc<Mutex<TokenStore>>,
+}
+
+impl InMemoryTokenRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn store(&self) -> std::sync::MutexGuard<'_, TokenStore> {
+        self.store.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[async_trait]
+impl TokenRepository for InMemoryTokenRepository {
+    async fn create_family(&self, family: RefreshTokenFamily) -> Result<RefreshTokenFamily> {
+        self.store().families.insert(family.id, family.clone());
+        Ok(family)
+    }
+
+    async fn find_family(&self, family_id: Id) -> Result<Option<RefreshTokenFamily>> {
+        Ok(self.store().families.get(&family_id).cloned())
+    }
+
+    async fn find_active_families(&self, user_id: Id) -> Result<Vec<RefreshTokenFamily>> {
+        let mut families: Vec<_> = self
+            .store()
+            .families
+            .values()
+            .filter(|family| family.user_id == user_id && !family.is_revoked())
+            .cloned()
+            .collect();
+        families.sort_by_key(|family| std::cmp::Reverse(family.last_used_at));
+        Ok(families)
+    }
+
+    async fn add_refresh_token(&self, token: RefreshToken) -> Result<()> {
+        let mut store = self.store();
+        let family = store
+            .families
+            .get_mut(&token.family_id)
+            .ok_or_else(|| Error::NotFound("Refresh token family not found".to_string()))?;
+        family.last_used_at = token.issued_at;
+        store.refresh_tokens.insert(token.id, token);
+        Ok(())
+    }
+
+    async fn consume_refresh_token(&self, token_id: Id) -> Result<Option<RefreshToken>> {
+        let now = chrono::Utc::now();
+        let mut store = self.store();
+        let TokenStore {
+            families,
+            refresh_tokens,
+            ..
+        } = &mut *store;
+
+        let Some(token) = refresh_tokens.get_mut(&token_id) else {
+            return Ok(None);
+        };
+        let family_revoked = families
+            .get(&token.family_id)
+            .is_none_or(RefreshTokenFamily::is_revoked);
+        if token.is_consumed() || token.expires_at <= now || family_revoked {
+            return Ok(None);
+        }
+
+        token.consumed_at = Some(now);
+        Ok(Some(token.clone()))
+    }
+
+    async fn find_refresh_token(&self, token_id: Id) -> Result<Option<RefreshToken>> {
+        Ok(self.store().refresh_tokens.get(&token_id).cloned())
+    }
+
+    async fn revoke_family(&self, family_id: Id, reason: RevocationReason) -> Result<bool> {
+        let mut store = self.store();
+        match store.families.get_mut(&family_id) {
+            Some(family) if !family.is_revoked() => {
+                family.revoked_at = Some(chrono::Utc::now());
+                family.revoked_reason = Some(reason);
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn revoke_user_families(
+        &self,
+        user_id: Id,
+        except: Option<Id>,
+        reason: RevocationReason,
+    ) -> Result<Vec<Id>> {
+        let now = chrono::Utc::now();
+        let mut revoked = Vec::new();
+        for family in self.store().families.values_mut() {
+            if family.user_id == user_id && !family.is_revoked() && Some(family.id) != except {
+                family.revoked_at = Some(now);
+                family.revoked_reason = Some(reason);
+                revoked.push(family.id);
+            }
+        }
+        Ok(revoked)
+    }
+
+    async fn deny_access_token(&self, jti: Id, _user_id: Id, expires_at: Timestamp) -> Result<bool> {
+        Ok(self.store().denied_access_tokens.insert(jti, expires_at).is_none())
+    }
+
+    async fn is_access_token_revoked(&self, jti: Id, family_id: Option<Id>) -> Result<bool> {
+        let store = self.store();
+        let family_revoked = family_id
+            .and_then(|family_id| store.families.get(&family_id))
+            .is_some_and(RefreshTokenFamily::is_revoked);
+        Ok(family_revoked || store.denied_access_tokens.contains_key(&jti))
+    }
+
+    async fn prune_expired(&self, now: Timestamp) -> Result<u64> {
+        let mut store = self.store();
+        let before = store.refresh_tokens.len() + store.families.len() + store.denied_access_tokens.len();
+
+        store.refresh_tokens.retain(|_, token| token.expires_at > now);
+        let TokenStore {
+            families,
+            refresh_tokens,
+            denied_access_tokens,
+        } = &mut *store;
+        families.retain(|id, _| refresh_tokens.values().any(|token| token.family_id == *id));
+        denied_access_tokens.retain(|_, expires_at| *expires_at > now);
+
+        let after = store.refresh_tokens.len() + store.families.len() + store.denied_access_tokens.len();
+        Ok((before - after) as u64)
+    }
+}
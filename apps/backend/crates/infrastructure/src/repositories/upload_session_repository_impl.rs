@@ -0,0 +1,252 @@
+use async_trait::async_trait;
+use kingshare_core::{Error, Id, Result, Timestamp};
+use kingshare_domain::{
+    entities::{UploadSession, APPEND_CLAIM_TTL_MINUTES},
+    repositories::UploadSessionRepository,
+};
+use sqlx::PgPool;
+use tracing::{info, instrument};
+
+#[derive(Debug, Clone)]
+pub struct PostgresUploadSessionRepository {
+    pool: PgPool,
+}
+
+impl PostgresUploadSessionRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Append claims taken before this have lapsed
+    fn lapsed_claims() -> Timestamp {
+        chrono::Utc::now() - chrono::Duration::minutes(APPEND_CLAIM_TTL_MINUTES)
+    }
+}
+
+#[async_trait]
+impl UploadSessionRepository for PostgresUploadSessionRepository {
+    #[instrument(skip(self, session))]
+    async fn create(&self, session: UploadSession) -> Result<UploadSession> {
+        sqlx::query!(
+            r#"
+            INSERT INTO upload_sessions (id, owner_id, filename, content_type, total_size,
+                                         "offset", storage_handle, created_at, updated_at, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            "#,
+            session.id,
+            session.owner_id,
+            session.filename,
+            session.content_type,
+            session.total_size,
+            session.offset,
+            session.storage_handle,
+            session.created_at,
+            session.updated_at,
+            session.expires_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        info!(upload_id = %session.id, "Upload session created successfully");
+        Ok(session)
+    }
+
+    #[instrument(skip(self))]
+    async fn find_by_id(&self, id: Id) -> Result<Option<UploadSession>> {
+        let row = sqlx::query!(
+            r#"
+            SELECT id, owner_id, filename, content_type, total_size, "offset" as offset_,
+                   storage_handle, finalizing, file_id, created_at, updated_at, expires_at
+            FROM upload_sessions WHERE id = $1
+            "#,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        Ok(row.map(|row| UploadSession {
+            id: row.id,
+            owner_id: row.owner_id,
+            filename: row.filename,
+            content_type: row.content_type,
+            total_size: row.total_size,
+            offset: row.offset_,
+            storage_handle: row.storage_handle,
+            finalizing: row.finalizing,
+            file_id: row.file_id,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+            expires_at: row.expires_at,
+        }))
+    }
+
+    #[instrument(skip(self))]
+    async fn update_offset(&self, id: Id, expected_offset: i64, new_offset: i64) -> Result<bool> {
+        // Compare-and-set, and never under a live append, which is still writing
+        let result = sqlx::query!(
+            r#"
+            UPDATE upload_sessions
+            SET "offset" = $3, updated_at = NOW()
+            WHERE id = $1 AND "offset" = $2
+              AND (appending_since IS NULL OR appending_since <= $4)
+            "#,
+            id,
+            expected_offset,
+            new_offset,
+            Self::lapsed_claims()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    #[instrument(skip(self))]
+    async fn claim_append(&self, id: Id, offset: i64) -> Result<Option<Timestamp>> {
+        let claimed_at = sqlx::query_scalar!(
+            r#"
+            UPDATE upload_sessions
+            SET appending_since = NOW(), updated_at = NOW()
+            WHERE id = $1 AND "offset" = $2 AND NOT finalizing AND file_id IS NULL
+              AND (appending_since IS NULL OR appending_since <= $3)
+            RETURNING appending_since AS "appending_since!"
+            "#,
+            id,
+            offset,
+            Self::lapsed_claims()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        Ok(claimed_at)
+    }
+
+    #[instrument(skip(self))]
+    async fn finish_append(&self, id: Id, claimed_at: Timestamp, new_offset: i64) -> Result<bool> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE upload_sessions
+            SET "offset" = $3, appending_since = NULL, updated_at = NOW()
+            WHERE id = $1 AND appending_since = $2
+            "#,
+            id,
+            claimed_at,
+            new_offset
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    #[instrument(skip(self))]
+    async fn release_append(&self, id: Id, claimed_at: Timestamp) -> Result<()> {
+        sqlx::query!(
+            "UPDATE upload_sessions SET appending_since = NULL, updated_at = NOW() WHERE id = $1 AND appending_since = $2",
+            id,
+            claimed_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn claim_finalize(&self, id: Id) -> Result<bool> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE upload_sessions
+            SET finalizing = TRUE, updated_at = NOW()
+            WHERE id = $1 AND NOT finalizing AND file_id IS NULL
+              AND (appending_since IS NULL OR appending_since <= $2)
+            "#,
+            id,
+            Self::lapsed_claims()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    #[instrument(skip(self))]
+    async fn release_finalize(&self, id: Id) -> Result<()> {
+        sqlx::query!(
+            "UPDATE upload_sessions SET finalizing = FALSE, updated_at = NOW() WHERE id = $1 AND file_id IS NULL",
+            id
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn mark_finalized(&self, id: Id, file_id: Id) -> Result<()> {
+        sqlx::query!(
+            "UPDATE upload_sessions SET finalizing = FALSE, file_id = $2, updated_at = NOW() WHERE id = $1",
+            id,
+            file_id
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn delete(&self, id: Id) -> Result<()> {
+        sqlx::query!("DELETE FROM upload_sessions WHERE id = $1", id)
+            .execute(&self.pool)
+            .await
+            .map_err(Error::Database)?;
+
+        info!(upload_id = %id, "Upload session deleted successfully");
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn find_expired(&self) -> Result<Vec<UploadSession>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT id, owner_id, filename, content_type, total_size, "offset" as offset_,
+                   storage_handle, finalizing, file_id, created_at, updated_at, expires_at
+            FROM upload_sessions
+            WHERE expires_at <= NOW()
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        let sessions = rows
+            .into_iter()
+            .map(|row| UploadSession {
+                id: row.id,
+                owner_id: row.owner_id,
+                filename: row.filename,
+                content_type: row.content_type,
+                total_size: row.total_size,
+                offset: row.offset_,
+                storage_handle: row.storage_handle,
+                finalizing: row.finalizing,
+                file_id: row.file_id,
+                created_at: row.created_at,
+                updated_at: row.updated_at,
+                expires_at: row.expires_at,
+            })
+            .collect();
+
+        Ok(sessions)
+    }
+}
@@ -1,7 +1,7 @@
 use async_trait::async_trait;
 use kingshare_core::{Error, Id, PaginatedResponse, PaginationInfo, PaginationParams, Result};
 use kingshare_domain::{
-    entities::{User, UserProfile, UserRole},
+    entities::{OwnershipTransfer, User, UserFilter, UserProfile, UserRole},
     repositories::UserRepository,
     value_objects::Email,
 };
@@ -393,4 +393,198 @@ impl UserRepository for PostgresUserRepository {
             pagination,
         })
     }
-}
\ No newline at end of file
+
+    #[instrument(skip(self, filter))]
+    async fn search(&self, filter: &UserFilter, params: PaginationParams) -> Result<PaginatedResponse<UserProfile>> {
+        let limit = params.limit() as i64;
+        let offset = params.offset() as i64;
+        let pattern = filter
+            .query
+            .as_deref()
+            .map(str::trim)
+            .filter(|query| !query.is_empty())
+            .map(like_pattern);
+        let role = filter.role.as_ref().map(UserRole::as_str);
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT id, email, username, first_name, last_name, is_active, is_verified,
+                   role, created_at, last_login_at
+            FROM users
+            WHERE ($1::text IS NULL
+                   OR email ILIKE $1 OR username ILIKE $1 OR first_name || ' ' || last_name ILIKE $1)
+              AND ($2::text IS NULL OR role = $2)
+              AND ($3::bool IS NULL OR is_active = $3)
+              AND ($4::bool IS NULL OR is_verified = $4)
+            ORDER BY created_at DESC
+            LIMIT $5 OFFSET $6
+            "#,
+            pattern,
+            role,
+            filter.is_active,
+            filter.is_verified,
+            limit,
+            offset
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        let total = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) FROM users
+            WHERE ($1::text IS NULL
+                   OR email ILIKE $1 OR username ILIKE $1 OR first_name || ' ' || last_name ILIKE $1)
+              AND ($2::text IS NULL OR role = $2)
+              AND ($3::bool IS NULL OR is_active = $3)
+              AND ($4::bool IS NULL OR is_verified = $4)
+            "#,
+            pattern,
+            role,
+            filter.is_active,
+            filter.is_verified
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(Error::Database)?
+        .unwrap_or(0) as u64;
+
+        let profiles: Vec<UserProfile> = rows
+            .into_iter()
+            .map(|row| UserProfile {
+                id: row.id,
+                email: row.email,
+                username: row.username,
+                first_name: row.first_name,
+                last_name: row.last_name,
+                is_active: row.is_active,
+                is_verified: row.is_verified,
+                role: parse_role(&row.role),
+                created_at: row.created_at,
+                last_login_at: row.last_login_at,
+            })
+            .collect();
+
+        Ok(PaginatedResponse {
+            data: profiles,
+            pagination: PaginationInfo::new(params.page.unwrap_or(1), params.limit(), total),
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn count_active_by_role(&self, role: UserRole) -> Result<u64> {
+        let count = sqlx::query_scalar!(
+            "SELECT COUNT(*) FROM users WHERE role = $1 AND is_active",
+            role.as_str()
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(Error::Database)?;
+
+        Ok(count.unwrap_or(0) as u64)
+    }
+
+    #[instrument(skip(self))]
+    async fn transfer_ownership(&self, from_user_id: Id, to_user_id: Id) -> Result<OwnershipTransfer> {
+        let mut tx = self.pool.begin().await.map_err(Error::Database)?;
+
+        // Lock both accounts in a fixed order so crossing transfers can't deadlock
+        let usage = sqlx::query!(
+            "SELECT id, storage_used FROM users WHERE id = ANY($1) ORDER BY id FOR UPDATE",
+            &[from_user_id, to_user_id][..]
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(Error::Database)?;
+        let bytes = usage
+            .iter()
+            .find(|row| row.id == from_user_id)
+            .map(|row| row.storage_used)
+            .ok_or_else(|| Error::NotFound("User not found".to_string()))?;
+
+        let files = sqlx::query!("UPDATE files SET owner_id = $2 WHERE owner_id = $1", from_user_id, to_user_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(Error::Database)?
+            .rows_affected();
+        let shares = sqlx::query!("UPDATE shares SET owner_id = $2 WHERE owner_id = $1", from_user_id, to_user_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(Error::Database)?
+            .rows_affected();
+        let drives = sqlx::query!("UPDATE drives SET owner_id = $2 WHERE owner_id = $1", from_user_id, to_user_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(Error::Database)?
+            .rows_affected();
+        let folders = sqlx::query!("UPDATE folders SET owner_id = $2 WHERE owner_id = $1", from_user_id, to_user_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(Error::Database)?
+            .rows_affected();
+        let drive_items = sqlx::query!(
+            "UPDATE drive_items SET owner_id = $2 WHERE owner_id = $1",
+            from_user_id,
+            to_user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(Error::Database)?
+        .rows_affected();
+        let documents = sqlx::query!(
+            "UPDATE documents SET owner_id = $2 WHERE owner_id = $1",
+            from_user_id,
+            to_user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(Error::Database)?
+        .rows_affected();
+
+        // Usage follows the files; the new owner may end up over their quota
+        sqlx::query!(
+            r#"
+            UPDATE users
+            SET storage_used = CASE WHEN id = $1 THEN 0 ELSE storage_used + $3 END
+            WHERE id IN ($1, $2)
+            "#,
+            from_user_id,
+            to_user_id,
+            bytes
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(Error::Database)?;
+
+        tx.commit().await.map_err(Error::Database)?;
+
+        info!(from_user_id = %from_user_id, to_user_id = %to_user_id, files, drives, "Ownership transferred");
+        Ok(OwnershipTransfer {
+            files,
+            shares,
+            drives,
+            folders,
+            drive_items,
+            documents,
+            bytes,
+        })
+    }
+}
+
+fn parse_role(role: &str) -> UserRole {
+    match role {
+        "Admin" => UserRole::Admin,
+        "Guest" => UserRole::Guest,
+        _ => UserRole::User,
+    }
+}
+
+/// A case-insensitive substring match for `ILIKE`, with wildcards in the
+/// search text taken literally
+pub(crate) fn like_pattern(query: &str) -> String {
+    let escaped = query
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{}%", escaped)
+}
@@ -1,14 +1,29 @@
 use async_trait::async_trait;
 use argon2::{
-    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    password_hash::{
+        rand_core::{OsRng, RngCore},
+        PasswordHash, PasswordHasher, PasswordVerifier, SaltString,
+    },
     Argon2,
 };
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
 use chrono::{Duration, Utc};
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
 use kingshare_core::{config::AuthConfig, Error, Id, Result};
-use kingshare_domain::services::{AuthService, Claims, TokenPair};
+use kingshare_domain::{
+    entities::{
+        AccountToken, AccountTokenPurpose, ApiTokenSecret, ClientInfo, MfaChallenge, RefreshToken,
+        RefreshTokenFamily, RevocationReason, API_TOKEN_PREFIX,
+    },
+    repositories::TokenRepository,
+    services::{
+        AuthService, Claims, ImpersonationToken, MfaChallengeToken, RefreshGrant, SignedAccountToken, TokenPair,
+    },
+};
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use subtle::ConstantTimeEq;
 use tracing::{info, instrument, warn};
 
 #[derive(Clone)]
@@ -16,8 +31,7 @@ pub struct JwtAuthService {
     config: AuthConfig,
     encoding_key: EncodingKey,
     decoding_key: DecodingKey,
-    // In production, use Redis or database for token blacklist
-    revoked_tokens: std::sync::Arc<std::sync::RwLock<HashSet<String>>>,
+    token_repository: Arc<dyn TokenRepository>,
 }
 
 impl std::fmt::Debug for JwtAuthService {
@@ -26,8 +40,7 @@ impl std::fmt::Debug for JwtAuthService {
             .field("config", &self.config)
             .field("encoding_key", &"[REDACTED]")
             .field("decoding_key", &"[REDACTED]")
-            .field("revoked_tokens", &self.revoked_tokens)
-            .finish()
+            .finish_non_exhaustive()
     }
 }
 
@@ -37,10 +50,47 @@ struct RefreshClaims {
     exp: i64,
     iat: i64,
     token_type: String,
+    jti: String,
+    sid: String,
+}
+
+const MFA_CHALLENGE_TOKEN_TYPE: &str = "mfa_challenge";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct MfaChallengeClaims {
+    sub: String,
+    exp: i64,
+    iat: i64,
+    token_type: String,
+    jti: String,
+    enroll: bool,
+}
+
+const ACCOUNT_TOKEN_TYPE: &str = "account";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct AccountTokenClaims {
+    sub: String,
+    exp: i64,
+    iat: i64,
+    token_type: String,
+    jti: String,
+    purpose: String,
+    fph: String, // Fingerprint hash
+}
+
+/// The fingerprint is hashed so links don't carry account details
+fn fingerprint_hash(fingerprint: &str) -> String {
+    hex::encode(Sha256::digest(fingerprint.as_bytes()))
+}
+
+fn parse_token_id(value: &str, field: &str) -> Result<Id> {
+    uuid::Uuid::parse_str(value)
+        .map_err(|_| Error::Authentication(format!("Token has an invalid {}", field)))
 }
 
 impl JwtAuthService {
-    pub fn new(config: AuthConfig) -> Self {
+    pub fn new(config: AuthConfig, token_repository: Arc<dyn TokenRepository>) -> Self {
         let encoding_key = EncodingKey::from_secret(config.jwt_secret.as_bytes());
         let decoding_key = DecodingKey::from_secret(config.jwt_secret.as_bytes());
 
@@ -48,22 +98,88 @@ impl JwtAuthService {
             config,
             encoding_key,
             decoding_key,
-            revoked_tokens: std::sync::Arc::new(std::sync::RwLock::new(HashSet::new())),
+            token_repository,
         }
     }
 
-    fn is_token_revoked(&self, token: &str) -> bool {
-        self.revoked_tokens
-            .read()
-            .unwrap()
-            .contains(token)
+    /// Issue an access token and the next refresh token of `session_id`
+    async fn issue_tokens(
+        &self,
+        session_id: Id,
+        user_id: Id,
+        email: &str,
+        username: &str,
+        role: &str,
+    ) -> Result<TokenPair> {
+        let now = Utc::now();
+        let access_exp = now + Duration::seconds(self.config.jwt_expiration);
+        let refresh_exp = now + Duration::seconds(self.config.refresh_token_expiration);
+        let refresh_id = uuid::Uuid::new_v4();
+
+        // Access token claims
+        let access_claims = Claims {
+            sub: user_id.to_string(),
+            email: email.to_string(),
+            username: username.to_string(),
+            role: role.to_string(),
+            exp: access_exp.timestamp(),
+            iat: now.timestamp(),
+            jti: uuid::Uuid::new_v4().to_string(),
+            sid: Some(session_id.to_string()),
+            act: None,
+        };
+
+        // Refresh token claims
+        let refresh_claims = RefreshClaims {
+            sub: user_id.to_string(),
+            exp: refresh_exp.timestamp(),
+            iat: now.timestamp(),
+            token_type: "refresh".to_string(),
+            jti: refresh_id.to_string(),
+            sid: session_id.to_string(),
+        };
+
+        let access_token = encode(&Header::default(), &access_claims, &self.encoding_key)
+            .map_err(Error::Jwt)?;
+
+        let refresh_token = encode(&Header::default(), &refresh_claims, &self.encoding_key)
+            .map_err(Error::Jwt)?;
+
+        self.token_repository
+            .add_refresh_token(RefreshToken {
+                id: refresh_id,
+                family_id: session_id,
+                user_id,
+                issued_at: now,
+                expires_at: refresh_exp,
+                consumed_at: None,
+            })
+            .await?;
+
+        info!(user_id = %user_id, session_id = %session_id, "Tokens generated successfully");
+
+        Ok(TokenPair {
+            access_token,
+            refresh_token,
+            expires_in: self.config.jwt_expiration,
+            session_id,
+        })
     }
 
-    fn revoke_token_internal(&self, token: &str) {
-        self.revoked_tokens
-            .write()
-            .unwrap()
-            .insert(token.to_string());
+    fn decode_refresh_claims(&self, refresh_token: &str) -> Result<RefreshClaims> {
+        let token_data = decode::<RefreshClaims>(
+            refresh_token,
+            &self.decoding_key,
+            &Validation::default(),
+        )
+        .map_err(Error::Jwt)?;
+
+        // Verify it's a refresh token
+        if token_data.claims.token_type != "refresh" {
+            return Err(Error::Authentication("Invalid token type".to_string()));
+        }
+
+        Ok(token_data.claims)
     }
 }
 
@@ -102,57 +218,25 @@ impl AuthService for JwtAuthService {
         }
     }
 
-    #[instrument(skip(self))]
+    #[instrument(skip(self, client))]
     async fn generate_tokens(
         &self,
         user_id: Id,
         email: &str,
         username: &str,
         role: &str,
+        client: &ClientInfo,
     ) -> Result<TokenPair> {
-        let now = Utc::now();
-        let access_exp = now + Duration::seconds(self.config.jwt_expiration);
-        let refresh_exp = now + Duration::seconds(self.config.refresh_token_expiration);
-
-        // Access token claims
-        let access_claims = Claims {
-            sub: user_id.to_string(),
-            email: email.to_string(),
-            username: username.to_string(),
-            role: role.to_string(),
-            exp: access_exp.timestamp(),
-            iat: now.timestamp(),
-        };
-
-        // Refresh token claims
-        let refresh_claims = RefreshClaims {
-            sub: user_id.to_string(),
-            exp: refresh_exp.timestamp(),
-            iat: now.timestamp(),
-            token_type: "refresh".to_string(),
-        };
-
-        let access_token = encode(&Header::default(), &access_claims, &self.encoding_key)
-            .map_err(Error::Jwt)?;
+        let family = self
+            .token_repository
+            .create_family(RefreshTokenFamily::new(user_id, client))
+            .await?;
 
-        let refresh_token = encode(&Header::default(), &refresh_claims, &self.encoding_key)
-            .map_err(Error::Jwt)?;
-
-        info!(user_id = %user_id, "Tokens generated successfully");
-
-        Ok(TokenPair {
-            access_token,
-            refresh_token,
-            expires_in: self.config.jwt_expiration,
-        })
+        self.issue_tokens(family.id, user_id, email, username, role).await
     }
 
     #[instrument(skip(self, token))]
     async fn verify_token(&self, token: &str) -> Result<Claims> {
-        if self.is_token_revoked(token) {
-            return Err(Error::Authentication("Token has been revoked".to_string()));
-        }
-
         let token_data = decode::<Claims>(
             token,
             &self.decoding_key,
@@ -166,55 +250,310 @@ impl AuthService for JwtAuthService {
             return Err(Error::Authentication("Token has expired".to_string()));
         }
 
+        // Tokens without an ID can't be revoked, so they aren't accepted either
+        let jti = parse_token_id(&token_data.claims.jti, "ID")?;
+        let session_id = token_data
+            .claims
+            .sid
+            .as_deref()
+            .map(|sid| parse_token_id(sid, "session"))
+            .transpose()?;
+
+        if self
+            .token_repository
+            .is_access_token_revoked(jti, session_id)
+            .await?
+        {
+            return Err(Error::Authentication("Token has been revoked".to_string()));
+        }
+
         info!(user_id = %token_data.claims.sub, "Token verified successfully");
         Ok(token_data.claims)
     }
 
     #[instrument(skip(self, refresh_token))]
-    async fn refresh_token(&self, refresh_token: &str) -> Result<TokenPair> {
-        if self.is_token_revoked(refresh_token) {
-            return Err(Error::Authentication("Refresh token has been revoked".to_string()));
+    async fn redeem_refresh_token(&self, refresh_token: &str) -> Result<RefreshGrant> {
+        let claims = self.decode_refresh_claims(refresh_token)?;
+        let token_id = parse_token_id(&claims.jti, "ID")?;
+        let session_id = parse_token_id(&claims.sid, "session")?;
+        let user_id = parse_token_id(&claims.sub, "subject")?;
+
+        match self.token_repository.consume_refresh_token(token_id).await? {
+            Some(token) if token.family_id == session_id && token.user_id == user_id => {
+                Ok(RefreshGrant {
+                    user_id,
+                    session_id,
+                })
+            }
+            Some(_) => Err(Error::Authentication("Refresh token does not match its session".to_string())),
+            None => {
+                let spent = self
+                    .token_repository
+                    .find_refresh_token(token_id)
+                    .await?
+                    .filter(RefreshToken::is_consumed);
+
+                if let Some(token) = spent {
+                    // Either the legitimate client or an attacker holds a stolen
+                    // copy; we can't tell which, so neither keeps the session
+                    self.token_repository
+                        .revoke_family(token.family_id, RevocationReason::ReuseDetected)
+                        .await?;
+                    warn!(
+                        user_id = %token.user_id,
+                        session_id = %token.family_id,
+                        "Refresh token reuse detected, session revoked"
+                    );
+                    return Err(Error::Authentication(
+                        "Refresh token has already been used; the session has been revoked".to_string(),
+                    ));
+                }
+
+                Err(Error::Authentication("Refresh token has been revoked".to_string()))
+            }
         }
+    }
 
-        let token_data = decode::<RefreshClaims>(
-            refresh_token,
-            &self.decoding_key,
-            &Validation::default(),
-        )
-        .map_err(Error::Jwt)?;
+    #[instrument(skip(self, grant), fields(user_id = %grant.user_id, session_id = %grant.session_id))]
+    async fn rotate_tokens(
+        &self,
+        grant: &RefreshGrant,
+        email: &str,
+        username: &str,
+        role: &str,
+    ) -> Result<TokenPair> {
+        self.issue_tokens(grant.session_id, grant.user_id, email, username, role)
+            .await
+    }
 
-        // Check if refresh token is expired
-        let now = Utc::now().timestamp();
-        if token_data.claims.exp < now {
-            return Err(Error::Authentication("Refresh token has expired".to_string()));
+    #[instrument(skip(self, token))]
+    async fn revoke_token(&self, token: &str) -> Result<()> {
+        // Refresh tokens end their session; access tokens are also denied outright
+        let session_id = match decode::<Claims>(token, &self.decoding_key, &Validation::default()) {
+            Ok(token_data) => {
+                let claims = token_data.claims;
+                let user_id = parse_token_id(&claims.sub, "subject")?;
+                let jti = parse_token_id(&claims.jti, "ID")?;
+                let expires_at = chrono::DateTime::from_timestamp(claims.exp, 0)
+                    .ok_or_else(|| Error::Authentication("Token has an invalid expiry".to_string()))?;
+
+                self.token_repository
+                    .deny_access_token(jti, user_id, expires_at)
+                    .await?;
+                claims.sid.as_deref().map(|sid| parse_token_id(sid, "session")).transpose()?
+            }
+            Err(_) => Some(parse_token_id(&self.decode_refresh_claims(token)?.sid, "session")?),
+        };
+
+        if let Some(session_id) = session_id {
+            self.token_repository
+                .revoke_family(session_id, RevocationReason::Logout)
+                .await?;
         }
 
-        // Verify it's a refresh token
-        if token_data.claims.token_type != "refresh" {
+        info!("Token revoked successfully");
+        Ok(())
+    }
+
+    #[instrument(skip(self, email, username, role))]
+    async fn issue_impersonation_token(
+        &self,
+        user_id: Id,
+        email: &str,
+        username: &str,
+        role: &str,
+        actor_id: Id,
+    ) -> Result<ImpersonationToken> {
+        let now = Utc::now();
+        let expires_in = self.config.impersonation_expiration;
+        // No session: the token can be revoked by its ID but never refreshed
+        let claims = Claims {
+            sub: user_id.to_string(),
+            email: email.to_string(),
+            username: username.to_string(),
+            role: role.to_string(),
+            exp: (now + Duration::seconds(expires_in)).timestamp(),
+            iat: now.timestamp(),
+            jti: uuid::Uuid::new_v4().to_string(),
+            sid: None,
+            act: Some(actor_id.to_string()),
+        };
+
+        let access_token = encode(&Header::default(), &claims, &self.encoding_key)
+            .map_err(Error::Jwt)?;
+
+        info!(user_id = %user_id, actor_id = %actor_id, "Impersonation token issued");
+        Ok(ImpersonationToken {
+            access_token,
+            expires_in,
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn issue_mfa_challenge(&self, user_id: Id, enrollment_required: bool) -> Result<MfaChallengeToken> {
+        let now = Utc::now();
+        let expires_in = self.config.mfa.challenge_expiration;
+        let claims = MfaChallengeClaims {
+            sub: user_id.to_string(),
+            exp: (now + Duration::seconds(expires_in)).timestamp(),
+            iat: now.timestamp(),
+            token_type: MFA_CHALLENGE_TOKEN_TYPE.to_string(),
+            jti: uuid::Uuid::new_v4().to_string(),
+            enroll: enrollment_required,
+        };
+
+        let challenge_token = encode(&Header::default(), &claims, &self.encoding_key)
+            .map_err(Error::Jwt)?;
+
+        info!(user_id = %user_id, enrollment_required, "MFA challenge issued");
+        Ok(MfaChallengeToken {
+            challenge_token,
+            expires_in,
+            enrollment_required,
+        })
+    }
+
+    #[instrument(skip(self, token))]
+    async fn verify_mfa_challenge(&self, token: &str) -> Result<MfaChallenge> {
+        let claims = decode::<MfaChallengeClaims>(token, &self.decoding_key, &Validation::default())
+            .map_err(|_| Error::Authentication("Invalid or expired MFA challenge".to_string()))?
+            .claims;
+
+        // Access and refresh tokens must not stand in for a challenge
+        if claims.token_type != MFA_CHALLENGE_TOKEN_TYPE {
             return Err(Error::Authentication("Invalid token type".to_string()));
         }
 
-        // Revoke the old refresh token
-        self.revoke_token_internal(refresh_token);
+        let challenge_id = parse_token_id(&claims.jti, "ID")?;
+        if self
+            .token_repository
+            .is_access_token_revoked(challenge_id, None)
+            .await?
+        {
+            return Err(Error::Authentication("MFA challenge has already been used".to_string()));
+        }
 
-        // For a real implementation, you'd fetch user details from database
-        // For now, we'll use placeholder values
-        let user_id = uuid::Uuid::parse_str(&token_data.claims.sub)
-            .map_err(|e| Error::Validation(e.to_string()))?;
+        Ok(MfaChallenge {
+            user_id: parse_token_id(&claims.sub, "subject")?,
+            challenge_id,
+            enrollment_required: claims.enroll,
+            expires_at: chrono::DateTime::from_timestamp(claims.exp, 0)
+                .ok_or_else(|| Error::Authentication("Token has an invalid expiry".to_string()))?,
+        })
+    }
 
-        // Generate new token pair
-        self.generate_tokens(
-            user_id,
-            "user@example.com", // This should come from database
-            "username",         // This should come from database
-            "User",            // This should come from database
-        ).await
+    #[instrument(skip(self, challenge), fields(user_id = %challenge.user_id))]
+    async fn consume_mfa_challenge(&self, challenge: &MfaChallenge) -> Result<()> {
+        // Challenges share the access-token denylist; their IDs never collide
+        let consumed = self
+            .token_repository
+            .deny_access_token(challenge.challenge_id, challenge.user_id, challenge.expires_at)
+            .await?;
+        if !consumed {
+            return Err(Error::Authentication("MFA challenge has already been used".to_string()));
+        }
+        Ok(())
+    }
+
+    #[instrument(skip(self, fingerprint))]
+    async fn issue_account_token(
+        &self,
+        user_id: Id,
+        purpose: AccountTokenPurpose,
+        fingerprint: &str,
+    ) -> Result<SignedAccountToken> {
+        let now = Utc::now();
+        let expires_in = match purpose {
+            AccountTokenPurpose::VerifyEmail => self.config.email_verification_expiration,
+            AccountTokenPurpose::ResetPassword => self.config.password_reset_expiration,
+        };
+        let claims = AccountTokenClaims {
+            sub: user_id.to_string(),
+            exp: (now + Duration::seconds(expires_in)).timestamp(),
+            iat: now.timestamp(),
+            token_type: ACCOUNT_TOKEN_TYPE.to_string(),
+            jti: uuid::Uuid::new_v4().to_string(),
+            purpose: purpose.as_str().to_string(),
+            fph: fingerprint_hash(fingerprint),
+        };
+
+        let token = encode(&Header::default(), &claims, &self.encoding_key)
+            .map_err(Error::Jwt)?;
+
+        info!(user_id = %user_id, purpose = purpose.as_str(), "Account token issued");
+        Ok(SignedAccountToken { token, expires_in })
     }
 
     #[instrument(skip(self, token))]
-    async fn revoke_token(&self, token: &str) -> Result<()> {
-        self.revoke_token_internal(token);
-        info!("Token revoked successfully");
+    async fn verify_account_token(&self, token: &str, purpose: AccountTokenPurpose) -> Result<AccountToken> {
+        let claims = decode::<AccountTokenClaims>(token, &self.decoding_key, &Validation::default())
+            .map_err(|_| Error::Authentication("Invalid or expired link".to_string()))?
+            .claims;
+
+        if claims.token_type != ACCOUNT_TOKEN_TYPE {
+            return Err(Error::Authentication("Invalid token type".to_string()));
+        }
+        // A verification link must not reset a password, and vice versa
+        if claims.purpose.parse::<AccountTokenPurpose>()? != purpose {
+            return Err(Error::Authentication("Invalid or expired link".to_string()));
+        }
+
+        let token_id = parse_token_id(&claims.jti, "ID")?;
+        if self
+            .token_repository
+            .is_access_token_revoked(token_id, None)
+            .await?
+        {
+            return Err(Error::Authentication("This link has already been used".to_string()));
+        }
+
+        Ok(AccountToken {
+            token_id,
+            user_id: parse_token_id(&claims.sub, "subject")?,
+            purpose,
+            fingerprint: claims.fph,
+            expires_at: chrono::DateTime::from_timestamp(claims.exp, 0)
+                .ok_or_else(|| Error::Authentication("Token has an invalid expiry".to_string()))?,
+        })
+    }
+
+    #[instrument(skip(self, token, fingerprint), fields(user_id = %token.user_id))]
+    async fn consume_account_token(&self, token: &AccountToken, fingerprint: &str) -> Result<()> {
+        let expected = fingerprint_hash(fingerprint);
+        if !bool::from(expected.as_bytes().ct_eq(token.fingerprint.as_bytes())) {
+            return Err(Error::Authentication("This link is no longer valid".to_string()));
+        }
+
+        // Like MFA challenges, account tokens share the access-token denylist
+        let consumed = self
+            .token_repository
+            .deny_access_token(token.token_id, token.user_id, token.expires_at)
+            .await?;
+        if !consumed {
+            return Err(Error::Authentication("This link has already been used".to_string()));
+        }
         Ok(())
     }
-}
\ No newline at end of file
+
+    fn generate_api_token(&self) -> ApiTokenSecret {
+        let mut bytes = [0u8; 32];
+        OsRng.fill_bytes(&mut bytes);
+        let token = format!("{}{}", API_TOKEN_PREFIX, URL_SAFE_NO_PAD.encode(bytes));
+
+        ApiTokenSecret {
+            token_hash: self.hash_api_token(&token),
+            token,
+        }
+    }
+
+    fn hash_api_token(&self, token: &str) -> String {
+        // 256 random bits need no salt or slow hash, and an unsalted hash
+        // lets the token be looked up directly
+        hex::encode(Sha256::digest(token.as_bytes()))
+    }
+
+    #[instrument(skip(self))]
+    async fn prune_expired_tokens(&self) -> Result<u64> {
+        self.token_repository.prune_expired(Utc::now()).await
+    }
+}
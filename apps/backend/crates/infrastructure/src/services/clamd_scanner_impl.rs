@@ -0,0 +1,201 @@
+use async_trait::async_trait;
+use futures_util::TryStreamExt;
+use kingshare_core::{config::ScannerConfig, Error, Result};
+use kingshare_domain::services::{FileStream, MalwareScanner, ScanVerdict};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tracing::{info, instrument, warn};
+
+const INSTREAM_CHUNK_SIZE: usize = 64 * 1024;
+const MAX_REPLY_SIZE: usize = 4096;
+
+trait Connection: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Connection for T {}
+
+#[derive(Debug, Clone)]
+enum ClamdAddress {
+    Tcp(String),
+    #[cfg(unix)]
+    Unix(std::path::PathBuf),
+}
+
+/// `MalwareScanner` speaking the clamd protocol, streaming content with INSTREAM
+#[derive(Debug, Clone)]
+pub struct ClamdScanner {
+    address: ClamdAddress,
+    timeout: Duration,
+}
+
+impl ClamdScanner {
+    /// `address` is `host:port` (optionally prefixed with `tcp://`) or `unix:/path/to/socket`
+    pub fn new(address: &str, timeout: Duration) -> Result<Self> {
+        let address = if let Some(path) = address.strip_prefix("unix:") {
+            #[cfg(unix)]
+            {
+                ClamdAddress::Unix(path.into())
+            }
+            #[cfg(not(unix))]
+            {
+                return Err(Error::BadRequest(format!(
+                    "Unix sockets are not supported on this platform: {}",
+                    path
+                )));
+            }
+        } else {
+            let address = address.strip_prefix("tcp://").unwrap_or(address);
+            if address.is_empty() {
+                return Err(Error::BadRequest("clamd address must not be empty".to_string()));
+            }
+            ClamdAddress::Tcp(address.to_string())
+        };
+
+        Ok(Self { address, timeout })
+    }
+
+    pub fn from_config(config: &ScannerConfig) -> Result<Self> {
+        Self::new(&config.clamd_address, Duration::from_secs(config.timeout_seconds))
+    }
+
+    fn clamd_error(action: &str, error: impl std::fmt::Display) -> Error {
+        Error::Internal(format!("Failed to {} clamd: {}", action, error))
+    }
+
+    async fn connect(&self) -> Result<Box<dyn Connection>> {
+        match &self.address {
+            ClamdAddress::Tcp(address) => {
+                let stream = TcpStream::connect(address)
+                    .await
+                    .map_err(|e| Self::clamd_error("connect to", e))?;
+                Ok(Box::new(stream))
+            }
+            #[cfg(unix)]
+            ClamdAddress::Unix(path) => {
+                let stream = tokio::net::UnixStream::connect(path)
+                    .await
+                    .map_err(|e| Self::clamd_error("connect to", e))?;
+                Ok(Box::new(stream))
+            }
+        }
+    }
+
+    async fn write_chunk(connection: &mut Box<dyn Connection>, data: &[u8]) -> std::io::Result<()> {
+        connection.write_all(&(data.len() as u32).to_be_bytes()).await?;
+        connection.write_all(data).await
+    }
+
+    /// Replies to `z`-prefixed commands are terminated by a NUL byte
+    async fn read_reply(connection: &mut Box<dyn Connection>) -> Result<String> {
+        let mut reply = Vec::new();
+        let mut buffer = [0u8; 512];
+
+        loop {
+            let read = connection
+                .read(&mut buffer)
+                .await
+                .map_err(|e| Self::clamd_error("read reply from", e))?;
+            if read == 0 {
+                break;
+            }
+
+            reply.extend_from_slice(&buffer[..read]);
+            if let Some(end) = reply.iter().position(|&byte| byte == 0) {
+                reply.truncate(end);
+                break;
+            }
+            if reply.len() > MAX_REPLY_SIZE {
+                return Err(Self::clamd_error("read reply from", "reply too long"));
+            }
+        }
+
+        Ok(String::from_utf8_lossy(&reply).trim().to_string())
+    }
+
+    fn parse_reply(reply: &str) -> Result<ScanVerdict> {
+        let result = reply.strip_prefix("stream:").unwrap_or(reply).trim();
+
+        if result == "OK" {
+            Ok(ScanVerdict::Clean)
+        } else if let Some(signature) = result.strip_suffix(" FOUND") {
+            Ok(ScanVerdict::Infected {
+                signature: signature.trim().to_string(),
+            })
+        } else if reply.is_empty() {
+            Err(Self::clamd_error("scan with", "connection closed without a reply"))
+        } else {
+            Err(Self::clamd_error("scan with", reply))
+        }
+    }
+
+    async fn instream(&self, mut stream: FileStream) -> Result<ScanVerdict> {
+        let mut connection = self.connect().await?;
+        connection
+            .write_all(b"zINSTREAM\0")
+            .await
+            .map_err(|e| Self::clamd_error("send command to", e))?;
+
+        while let Some(chunk) = stream
+            .try_next()
+            .await
+            .map_err(|e| Error::Internal(format!("Failed to read file for scanning: {}", e)))?
+        {
+            for part in chunk.chunks(INSTREAM_CHUNK_SIZE) {
+                if let Err(e) = Self::write_chunk(&mut connection, part).await {
+                    // clamd hangs up once the stream exceeds StreamMaxLength; its reply says so
+                    return match Self::read_reply(&mut connection).await {
+                        Ok(reply) if !reply.is_empty() => Self::parse_reply(&reply),
+                        _ => Err(Self::clamd_error("send data to", e)),
+                    };
+                }
+            }
+        }
+
+        // A zero-length chunk ends the stream
+        connection
+            .write_all(&0u32.to_be_bytes())
+            .await
+            .map_err(|e| Self::clamd_error("send data to", e))?;
+
+        let reply = Self::read_reply(&mut connection).await?;
+        Self::parse_reply(&reply)
+    }
+}
+
+#[async_trait]
+impl MalwareScanner for ClamdScanner {
+    #[instrument(skip(self, stream))]
+    async fn scan_stream(&self, stream: FileStream) -> Result<ScanVerdict> {
+        let verdict = tokio::time::timeout(self.timeout, self.instream(stream))
+            .await
+            .map_err(|_| Error::Internal("Malware scan timed out".to_string()))??;
+
+        match &verdict {
+            ScanVerdict::Clean => info!("Malware scan passed"),
+            ScanVerdict::Infected { signature } => warn!(signature = %signature, "Malware detected"),
+        }
+        Ok(verdict)
+    }
+
+    #[instrument(skip(self))]
+    async fn ping(&self) -> Result<()> {
+        let ping = async {
+            let mut connection = self.connect().await?;
+            connection
+                .write_all(b"zPING\0")
+                .await
+                .map_err(|e| Self::clamd_error("send command to", e))?;
+            Self::read_reply(&mut connection).await
+        };
+
+        let reply = tokio::time::timeout(self.timeout, ping)
+            .await
+            .map_err(|_| Self::clamd_error("ping", "timed out"))??;
+
+        if reply == "PONG" {
+            Ok(())
+        } else {
+            Err(Self::clamd_error("ping", format!("unexpected reply '{}'", reply)))
+        }
+    }
+}
@@ -0,0 +1,519 @@
+//! Content type detection from magic bytes.
+//!
+//! Only the first and last `CONTENT_SNIFF_WINDOW` bytes are examined: signatures
+//! sit at the start of a file, while a zip's central directory (which names the
+//! entries that tell OOXML, ODF and plain archives apart) sits at the end.
+
+use kingshare_domain::services::CONTENT_SNIFF_WINDOW;
+
+/// How much of the head is classified as text or binary
+const TEXT_SAMPLE_LEN: usize = 8 * 1024;
+
+/// Types that only say which family the content belongs to; a more specific
+/// declared type from the same family is kept.
+const GENERIC_TYPES: &[&str] = &[
+    "text/plain",
+    "application/x-ole-storage",
+    "application/zip",
+    "video/x-matroska",
+    "video/mp4",
+    "audio/ogg",
+];
+
+const TEXT_FAMILY: &[&str] = &[
+    "image/svg+xml",
+    "application/json",
+    "application/xml",
+    "application/rtf",
+    "application/javascript",
+];
+
+const OLE_FAMILY: &[&str] = &[
+    "application/msword",
+    "application/vnd.ms-excel",
+    "application/vnd.ms-powerpoint",
+    "application/vnd.ms-outlook",
+];
+
+const ZIP_FAMILY: &[&str] = &[
+    "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+    "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+    "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+    "application/vnd.oasis.opendocument.text",
+    "application/vnd.oasis.opendocument.spreadsheet",
+    "application/vnd.oasis.opendocument.presentation",
+    "application/epub+zip",
+    "application/java-archive",
+];
+
+/// Native code and scripts; never accepted, whatever was declared
+const EXECUTABLE_TYPES: &[&str] = &[
+    "application/x-msdownload",
+    "application/x-executable",
+    "application/x-mach-binary",
+    "application/java-archive",
+    "application/java-vm",
+    "application/wasm",
+    "application/vnd.android.dex",
+    "text/x-shellscript",
+];
+
+/// Types with a signature we can check; a file declared as one of these must match it
+const SIGNATURE_TYPES: &[&str] = &[
+    "image/jpeg",
+    "image/png",
+    "image/gif",
+    "image/webp",
+    "image/tiff",
+    "image/heic",
+    "image/avif",
+    "application/pdf",
+    "application/zip",
+    "application/x-rar-compressed",
+    "application/x-7z-compressed",
+    "application/gzip",
+    "application/x-bzip2",
+    "application/x-xz",
+    "application/zstd",
+    "application/x-tar",
+    "audio/mpeg",
+    "audio/wav",
+    "audio/ogg",
+    "audio/flac",
+    "audio/mp4",
+    "audio/midi",
+    "video/mp4",
+    "video/avi",
+    "video/quicktime",
+    "video/webm",
+    "video/x-matroska",
+    "video/mpeg",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SniffedType {
+    pub content_type: &'static str,
+    pub charset: Option<&'static str>, // Only for text
+}
+
+impl SniffedType {
+    fn binary(content_type: &'static str) -> Option<Self> {
+        Some(Self { content_type, charset: None })
+    }
+
+    pub fn is_executable(&self) -> bool {
+        EXECUTABLE_TYPES.contains(&self.content_type)
+    }
+
+    /// Whether this only names a family (text, zip, OLE, ...) rather than a format
+    pub fn is_generic(&self) -> bool {
+        GENERIC_TYPES.contains(&self.content_type)
+    }
+}
+
+/// Detect the content type of `data`, which may be a whole file or its first
+/// and last `CONTENT_SNIFF_WINDOW` bytes joined together
+pub fn sniff(data: &[u8]) -> Option<SniffedType> {
+    if data.is_empty() {
+        return None;
+    }
+
+    let head = &data[..data.len().min(CONTENT_SNIFF_WINDOW)];
+    let tail = &data[data.len().saturating_sub(CONTENT_SNIFF_WINDOW)..];
+
+    sniff_executable(head)
+        .or_else(|| sniff_image(head))
+        .or_else(|| sniff_document(head, tail))
+        .or_else(|| sniff_archive(head))
+        .or_else(|| sniff_unicode_text(head))
+        .or_else(|| sniff_media(head))
+        .or_else(|| sniff_text(head))
+}
+
+/// Lower-cased type without parameters, with common aliases folded together
+pub fn canonical_type(content_type: &str) -> String {
+    let essence = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+
+    let canonical = match essence.as_str() {
+        "image/jpg" | "image/pjpeg" => "image/jpeg",
+        "image/x-png" => "image/png",
+        "application/x-pdf" => "application/pdf",
+        "application/x-zip-compressed" | "application/x-zip" => "application/zip",
+        "application/vnd.rar" | "application/x-rar" => "application/x-rar-compressed",
+        "application/x-gzip" => "application/gzip",
+        "audio/mp3" | "audio/x-mpeg" => "audio/mpeg",
+        "audio/x-wav" | "audio/wave" | "audio/vnd.wave" => "audio/wav",
+        "audio/x-flac" => "audio/flac",
+        "audio/x-m4a" => "audio/mp4",
+        "video/x-msvideo" | "video/msvideo" => "video/avi",
+        "text/xml" => "application/xml",
+        "text/rtf" => "application/rtf",
+        "text/javascript" | "application/x-javascript" => "application/javascript",
+        "application/x-sh" => "text/x-shellscript",
+        other => return other.to_string(),
+    };
+    canonical.to_string()
+}
+
+/// Whether two types can describe the same content, e.g. a declared type and a
+/// sniffed one. A generic type is compatible with every member of its family.
+pub fn is_compatible(a: &str, b: &str) -> bool {
+    let (a, b) = (canonical_type(a), canonical_type(b));
+    a == b || family_contains(&a, &b) || family_contains(&b, &a)
+}
+
+/// Whether a file declared as `content_type` must carry a recognizable signature
+pub fn has_signature(content_type: &str) -> bool {
+    let canonical = canonical_type(content_type);
+    SIGNATURE_TYPES.contains(&canonical.as_str())
+        || ZIP_FAMILY.contains(&canonical.as_str())
+        || OLE_FAMILY.contains(&canonical.as_str())
+}
+
+/// The canonical type a file extension implies, if we know it
+pub fn type_for_extension(extension: &str) -> Option<&'static str> {
+    let content_type = match extension.to_ascii_lowercase().as_str() {
+        "jpg" | "jpeg" | "jpe" | "jfif" => "image/jpeg",
+        "png" => "image/png",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "tif" | "tiff" => "image/tiff",
+        "heic" | "heif" => "image/heic",
+        "avif" => "image/avif",
+        "pdf" => "application/pdf",
+        "doc" => "application/msword",
+        "xls" => "application/vnd.ms-excel",
+        "ppt" => "application/vnd.ms-powerpoint",
+        "msg" => "application/vnd.ms-outlook",
+        "docx" => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        "xlsx" => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        "pptx" => "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+        "odt" => "application/vnd.oasis.opendocument.text",
+        "ods" => "application/vnd.oasis.opendocument.spreadsheet",
+        "odp" => "application/vnd.oasis.opendocument.presentation",
+        "epub" => "application/epub+zip",
+        "rtf" => "application/rtf",
+        "txt" | "text" | "log" | "md" => "text/plain",
+        "csv" => "text/csv",
+        "html" | "htm" => "text/html",
+        "css" => "text/css",
+        "js" | "mjs" => "application/javascript",
+        "json" => "application/json",
+        "xml" => "application/xml",
+        "sh" => "text/x-shellscript",
+        "zip" => "application/zip",
+        "jar" => "application/java-archive",
+        "rar" => "application/x-rar-compressed",
+        "7z" => "application/x-7z-compressed",
+        "gz" | "tgz" => "application/gzip",
+        "bz2" => "application/x-bzip2",
+        "xz" => "application/x-xz",
+        "zst" => "application/zstd",
+        "tar" => "application/x-tar",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "ogg" | "oga" | "opus" => "audio/ogg",
+        "flac" => "audio/flac",
+        "m4a" => "audio/mp4",
+        "mid" | "midi" => "audio/midi",
+        "mp4" | "m4v" => "video/mp4",
+        "mov" | "qt" => "video/quicktime",
+        "avi" => "video/avi",
+        "webm" => "video/webm",
+        "mkv" => "video/x-matroska",
+        "mpg" | "mpeg" => "video/mpeg",
+        "exe" | "dll" | "sys" | "scr" | "com" | "pif" => "application/x-msdownload",
+        "msi" => "application/x-ole-storage",
+        "wasm" => "application/wasm",
+        _ => return None,
+    };
+    Some(content_type)
+}
+
+fn family_contains(generic: &str, specific: &str) -> bool {
+    match generic {
+        "application/octet-stream" => true,
+        "text/plain" => specific.starts_with("text/") || TEXT_FAMILY.contains(&specific),
+        "application/x-ole-storage" => OLE_FAMILY.contains(&specific),
+        "application/zip" => ZIP_FAMILY.contains(&specific),
+        "video/x-matroska" => specific == "video/webm" || specific == "audio/webm",
+        "video/mp4" => specific == "audio/mp4" || specific == "video/x-m4v",
+        "audio/ogg" => specific == "video/ogg" || specific == "application/ogg",
+        _ => false,
+    }
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|window| window == needle)
+}
+
+fn utf16le(name: &str) -> Vec<u8> {
+    name.encode_utf16().flat_map(|unit| unit.to_le_bytes()).collect()
+}
+
+fn sniff_executable(head: &[u8]) -> Option<SniffedType> {
+    if head.starts_with(b"MZ") {
+        return SniffedType::binary("application/x-msdownload");
+    }
+    if head.starts_with(b"\x7FELF") {
+        return SniffedType::binary("application/x-executable");
+    }
+    if head.len() >= 4 {
+        match &head[..4] {
+            // 32/64-bit Mach-O in either byte order
+            [0xFE, 0xED, 0xFA, 0xCE | 0xCF] | [0xCE | 0xCF, 0xFA, 0xED, 0xFE] => {
+                return SniffedType::binary("application/x-mach-binary");
+            }
+            // Universal binaries and Java classes share this magic; both are code
+            [0xCA, 0xFE, 0xBA, 0xBE] => return SniffedType::binary("application/java-vm"),
+            _ => {}
+        }
+    }
+    if head.starts_with(b"\0asm") {
+        return SniffedType::binary("application/wasm");
+    }
+    if head.starts_with(b"dex\n") {
+        return SniffedType::binary("application/vnd.android.dex");
+    }
+    None
+}
+
+fn sniff_image(head: &[u8]) -> Option<SniffedType> {
+    if head.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        return SniffedType::binary("image/jpeg");
+    }
+    if head.starts_with(b"\x89PNG\r\n\x1A\n") {
+        return SniffedType::binary("image/png");
+    }
+    if head.starts_with(b"GIF87a") || head.starts_with(b"GIF89a") {
+        return SniffedType::binary("image/gif");
+    }
+    if head.starts_with(b"II*\0") || head.starts_with(b"MM\0*") {
+        return SniffedType::binary("image/tiff");
+    }
+    None
+}
+
+fn sniff_document(head: &[u8], tail: &[u8]) -> Option<SniffedType> {
+    if head.starts_with(b"%PDF-") {
+        return SniffedType::binary("application/pdf");
+    }
+
+    if head.starts_with(&[0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1]) {
+        // Compound file: look for the stream names each Office format uses
+        let names = [
+            ("WordDocument", "application/msword"),
+            ("Workbook", "application/vnd.ms-excel"),
+            ("Book", "application/vnd.ms-excel"),
+            ("PowerPoint Document", "application/vnd.ms-powerpoint"),
+            ("__substg1.0_", "application/vnd.ms-outlook"),
+        ];
+        for (name, content_type) in names {
+            let name = utf16le(name);
+            if contains(head, &name) || contains(tail, &name) {
+                return SniffedType::binary(content_type);
+            }
+        }
+        return SniffedType::binary("application/x-ole-storage");
+    }
+
+    if head.starts_with(b"PK\x03\x04") || head.starts_with(b"PK\x05\x06") || head.starts_with(b"PK\x07\x08") {
+        return Some(sniff_zip(head, tail));
+    }
+
+    None
+}
+
+fn sniff_zip(head: &[u8], tail: &[u8]) -> SniffedType {
+    // ODF and EPUB store their type uncompressed as the first entry
+    if head.len() > 38 && &head[30..38] == b"mimetype" {
+        let known = [
+            "application/vnd.oasis.opendocument.text",
+            "application/vnd.oasis.opendocument.spreadsheet",
+            "application/vnd.oasis.opendocument.presentation",
+            "application/epub+zip",
+        ];
+        if let Some(content_type) = known.into_iter().find(|known| head[38..].starts_with(known.as_bytes())) {
+            return SniffedType { content_type, charset: None };
+        }
+    }
+
+    let has_entry = |name: &[u8]| contains(head, name) || contains(tail, name);
+
+    let content_type = if has_entry(b"[Content_Types].xml") {
+        if has_entry(b"word/") {
+            "application/vnd.openxmlformats-officedocument.wordprocessingml.document"
+        } else if has_entry(b"xl/") {
+            "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"
+        } else if has_entry(b"ppt/") {
+            "application/vnd.openxmlformats-officedocument.presentationml.presentation"
+        } else {
+            "application/zip"
+        }
+    } else if has_entry(b"META-INF/MANIFEST.MF") {
+        "application/java-archive"
+    } else {
+        "application/zip"
+    };
+
+    SniffedType { content_type, charset: None }
+}
+
+fn sniff_archive(head: &[u8]) -> Option<SniffedType> {
+    if head.starts_with(b"Rar!\x1A\x07") {
+        return SniffedType::binary("application/x-rar-compressed");
+    }
+    if head.starts_with(&[0x37, 0x7A, 0xBC, 0xAF, 0x27, 0x1C]) {
+        return SniffedType::binary("application/x-7z-compressed");
+    }
+    if head.starts_with(&[0x1F, 0x8B, 0x08]) {
+        return SniffedType::binary("application/gzip");
+    }
+    if head.starts_with(b"BZh") {
+        return SniffedType::binary("application/x-bzip2");
+    }
+    if head.starts_with(&[0xFD, b'7', b'z', b'X', b'Z', 0x00]) {
+        return SniffedType::binary("application/x-xz");
+    }
+    if head.starts_with(&[0x28, 0xB5, 0x2F, 0xFD]) {
+        return SniffedType::binary("application/zstd");
+    }
+    if head.len() >= 262 && &head[257..262] == b"ustar" {
+        return SniffedType::binary("application/x-tar");
+    }
+    None
+}
+
+fn sniff_media(head: &[u8]) -> Option<SniffedType> {
+    if head.len() >= 12 && head.starts_with(b"RIFF") {
+        return match &head[8..12] {
+            b"WAVE" => SniffedType::binary("audio/wav"),
+            b"AVI " => SniffedType::binary("video/avi"),
+            b"WEBP" => SniffedType::binary("image/webp"),
+            _ => None,
+        };
+    }
+
+    // ISO base media file format: the major brand tells the flavour apart
+    if head.len() >= 12 && &head[4..8] == b"ftyp" {
+        return match &head[8..12] {
+            b"qt  " => SniffedType::binary("video/quicktime"),
+            b"M4A " | b"M4B " => SniffedType::binary("audio/mp4"),
+            b"heic" | b"heix" | b"heim" | b"heis" | b"mif1" | b"msf1" => SniffedType::binary("image/heic"),
+            b"avif" | b"avis" => SniffedType::binary("image/avif"),
+            brand if brand.starts_with(b"3g") => SniffedType::binary("video/3gpp"),
+            _ => SniffedType::binary("video/mp4"),
+        };
+    }
+
+    if head.starts_with(&[0x1A, 0x45, 0xDF, 0xA3]) {
+        // The EBML header names the doc type right after the magic
+        let header = &head[..head.len().min(64)];
+        return if contains(header, b"webm") {
+            SniffedType::binary("video/webm")
+        } else {
+            SniffedType::binary("video/x-matroska")
+        };
+    }
+
+    if head.starts_with(b"OggS") {
+        return SniffedType::binary("audio/ogg");
+    }
+    if head.starts_with(b"fLaC") {
+        return SniffedType::binary("audio/flac");
+    }
+    if head.starts_with(b"MThd") {
+        return SniffedType::binary("audio/midi");
+    }
+    if head.starts_with(&[0x00, 0x00, 0x01, 0xBA]) || head.starts_with(&[0x00, 0x00, 0x01, 0xB3]) {
+        return SniffedType::binary("video/mpeg");
+    }
+    if head.starts_with(b"ID3") {
+        return SniffedType::binary("audio/mpeg");
+    }
+
+    // Bare MPEG audio frame: 11 sync bits, then a non-reserved version and layer
+    if head.len() >= 3 && head[0] == 0xFF && head[1] & 0xE0 == 0xE0 {
+        let version = (head[1] >> 3) & 0x03;
+        let layer = (head[1] >> 1) & 0x03;
+        let bitrate = head[2] >> 4;
+        if version != 0x01 && bitrate != 0x0F {
+            return match layer {
+                0x00 => SniffedType::binary("audio/aac"), // ADTS
+                _ => SniffedType::binary("audio/mpeg"),
+            };
+        }
+    }
+
+    None
+}
+
+/// Text with a byte order mark. Checked before media, since a UTF-16 mark
+/// also looks like the start of an MPEG audio frame.
+fn sniff_unicode_text(head: &[u8]) -> Option<SniffedType> {
+    let sample = &head[..head.len().min(TEXT_SAMPLE_LEN)];
+
+    // The longer UTF-32 marks go first
+    let boms: [(&[u8], &'static str); 5] = [
+        (&[0xEF, 0xBB, 0xBF], "utf-8"),
+        (&[0xFF, 0xFE, 0x00, 0x00], "utf-32le"),
+        (&[0x00, 0x00, 0xFE, 0xFF], "utf-32be"),
+        (&[0xFF, 0xFE], "utf-16le"),
+        (&[0xFE, 0xFF], "utf-16be"),
+    ];
+
+    let (bom, charset) = boms.into_iter().find(|(bom, _)| sample.starts_with(bom))?;
+    match charset {
+        "utf-8" => classify_text(&sample[bom.len()..], charset),
+        _ => Some(SniffedType { content_type: "text/plain", charset: Some(charset) }),
+    }
+}
+
+fn sniff_text(head: &[u8]) -> Option<SniffedType> {
+    let sample = &head[..head.len().min(TEXT_SAMPLE_LEN)];
+
+    let charset = match std::str::from_utf8(sample) {
+        Ok(text) if text.is_ascii() => "us-ascii",
+        Ok(_) => "utf-8",
+        // The sample may end in the middle of a multi-byte character
+        Err(e) if e.error_len().is_none() => "utf-8",
+        // Anything else is some single-byte encoding, if it is text at all
+        Err(_) => "iso-8859-1",
+    };
+
+    let controls = sample
+        .iter()
+        .filter(|&&byte| byte < 0x20 && !matches!(byte, b'\t' | b'\n' | b'\r' | 0x0C | 0x1B))
+        .count();
+    if sample.contains(&0) || controls * 100 > sample.len() {
+        return None;
+    }
+
+    classify_text(sample, charset)
+}
+
+fn classify_text(text: &[u8], charset: &'static str) -> Option<SniffedType> {
+    let start = text.iter().position(|byte| !byte.is_ascii_whitespace()).unwrap_or(text.len());
+    let trimmed = &text[start..];
+    let prefix = trimmed[..trimmed.len().min(512)].to_ascii_lowercase();
+
+    let content_type = if trimmed.starts_with(b"#!") {
+        "text/x-shellscript"
+    } else if trimmed.starts_with(b"{\\rtf") {
+        "application/rtf"
+    } else if prefix.starts_with(b"<!doctype html") || prefix.starts_with(b"<html") {
+        "text/html"
+    } else if prefix.starts_with(b"<svg") || (prefix.starts_with(b"<?xml") && contains(&prefix, b"<svg")) {
+        "image/svg+xml"
+    } else {
+        "text/plain"
+    };
+
+    Some(SniffedType { content_type, charset: Some(charset) })
+}
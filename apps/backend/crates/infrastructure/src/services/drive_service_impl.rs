@@ -0,0 +1,396 @@
+use async_trait::async_trait;
+use kingshare_core::{Error, Id, Result};
+use kingshare_domain::{
+    entities::{
+        ActivityType, CreateSharingLinkRequest, DocumentContent, Drive, DriveActivity, DriveItem, DriveItemResponse,
+        DriveQuery, DriveType, Folder, ItemAccess, ItemOwner, ItemSource, RoleAssignment, RoleScope, SearchFilters,
+        SearchResult, SearchScope, ShareItemRequest, ShareRole, UnindexedItem, EDITOR_ROLE,
+    },
+    entities::drive::PublicAccessLevel,
+    repositories::{DriveRepository, DriveService, RoleRepository, UserRepository},
+    services::StorageService,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::text_extractor::{extract_text, reads_text};
+use tracing::{info, instrument, warn};
+
+const PERSONAL_DRIVE_NAME: &str = "My Drive";
+/// Items indexed per query while backfilling
+const BACKFILL_BATCH_SIZE: u32 = 100;
+
+/// The text of a stored file, or nothing for types text isn't read from
+async fn file_text(storage_service: &dyn StorageService, mime_type: &str, storage_path: &str) -> Result<String> {
+    if !reads_text(mime_type) {
+        return Ok(String::new());
+    }
+    let data = storage_service.get_file(storage_path).await?;
+    Ok(extract_text(mime_type, &data)?.unwrap_or_default())
+}
+
+/// Drive operations built on the drive repository. Callers check that the
+/// user may act on the drive or item first.
+#[derive(Clone)]
+pub struct DefaultDriveService {
+    drive_repository: Arc<dyn DriveRepository>,
+    role_repository: Arc<dyn RoleRepository>,
+    user_repository: Arc<dyn UserRepository>,
+    storage_service: Option<Arc<dyn StorageService>>,
+}
+
+impl DefaultDriveService {
+    pub fn new(
+        drive_repository: Arc<dyn DriveRepository>,
+        role_repository: Arc<dyn RoleRepository>,
+        user_repository: Arc<dyn UserRepository>,
+    ) -> Self {
+        Self {
+            drive_repository,
+            role_repository,
+            user_repository,
+            storage_service: None,
+        }
+    }
+
+    /// Read uploaded files from storage to index their text. Without it only
+    /// documents are indexed by their contents.
+    pub fn with_storage(mut self, storage_service: Arc<dyn StorageService>) -> Self {
+        self.storage_service = Some(storage_service);
+        self
+    }
+
+    async fn source_text(&self, item: &UnindexedItem) -> Result<String> {
+        match (&item.source, &self.storage_service) {
+            (ItemSource::Document(content), _) => Ok(content.plain_text()),
+            (ItemSource::File { storage_path }, Some(storage_service)) => {
+                file_text(storage_service.as_ref(), &item.mime_type, storage_path).await
+            }
+            (ItemSource::File { .. }, None) => Err(Error::Internal("Files can't be read without storage".to_string())),
+        }
+    }
+
+    async fn find_item(&self, item_id: Id) -> Result<DriveItem> {
+        self.drive_repository
+            .get_drive_item_by_id(item_id)
+            .await?
+            .ok_or_else(|| Error::NotFound("Item not found".to_string()))
+    }
+
+    async fn log(&self, item: &DriveItem, user_id: Id, activity_type: ActivityType, action: &str) -> Result<()> {
+        let activity = DriveActivity::new(item.drive_id, user_id, activity_type, item.name.clone(), action.to_string())
+            .with_item(item.id);
+        self.drive_repository.log_activity(activity).await
+    }
+
+    async fn owners(&self, owner_ids: Vec<Id>) -> Result<HashMap<Id, ItemOwner>> {
+        let mut owners = HashMap::new();
+        for owner_id in owner_ids {
+            if owners.contains_key(&owner_id) {
+                continue;
+            }
+            if let Some(user) = self.user_repository.find_by_id(owner_id).await? {
+                owners.insert(
+                    owner_id,
+                    ItemOwner {
+                        id: user.id,
+                        full_name: user.full_name(),
+                        username: user.username,
+                        email: user.email.into(),
+                    },
+                );
+            }
+        }
+        Ok(owners)
+    }
+}
+
+#[async_trait]
+impl DriveService for DefaultDriveService {
+    /// A user has one personal drive; asking again returns it
+    #[instrument(skip(self))]
+    async fn create_personal_drive(&self, user_id: Id) -> Result<Drive> {
+        let existing = self.drive_repository.get_drives_by_owner(user_id).await?;
+        if let Some(drive) = existing.into_iter().find(|drive| drive.drive_type == DriveType::Personal) {
+            return Ok(drive);
+        }
+
+        let drive = Drive::new(user_id, PERSONAL_DRIVE_NAME.to_string(), DriveType::Personal);
+        self.drive_repository.create_drive(drive).await
+    }
+
+    /// The members are made editors of the new drive
+    #[instrument(skip(self, team_members), fields(members = team_members.len()))]
+    async fn create_team_drive(&self, owner_id: Id, name: String, team_members: Vec<Id>) -> Result<Drive> {
+        let mut drive = Drive::new(owner_id, name, DriveType::Team);
+        drive.is_shared = true;
+        let drive = self.drive_repository.create_drive(drive).await?;
+
+        let members: Vec<Id> = team_members.into_iter().filter(|member| *member != owner_id).collect();
+        if !members.is_empty() {
+            let role = self
+                .role_repository
+                .find_role_by_name(EDITOR_ROLE)
+                .await?
+                .ok_or_else(|| Error::Internal(format!("The {} role is missing", EDITOR_ROLE)))?;
+            for member in members {
+                let assignment = RoleAssignment::new(member, role.id, RoleScope::Drive(drive.id), Some(owner_id));
+                if self.role_repository.find_assignment(&assignment).await?.is_none() {
+                    self.role_repository.create_assignment(assignment).await?;
+                }
+            }
+        }
+
+        info!(drive_id = %drive.id, owner_id = %owner_id, "Team drive created");
+        Ok(drive)
+    }
+
+    /// Drives the user owns, then those they are a member of
+    #[instrument(skip(self))]
+    async fn get_user_drives(&self, user_id: Id) -> Result<Vec<Drive>> {
+        let mut drives = self.drive_repository.get_drives_by_owner(user_id).await?;
+        for drive in self.drive_repository.get_drives_for_member(user_id).await? {
+            if !drives.iter().any(|owned| owned.id == drive.id) {
+                drives.push(drive);
+            }
+        }
+        Ok(drives)
+    }
+
+    async fn get_drive_quota_usage(&self, drive_id: Id) -> Result<(i64, i64)> {
+        let drive = self
+            .drive_repository
+            .get_drive_by_id(drive_id)
+            .await?
+            .ok_or_else(|| Error::NotFound("Drive not found".to_string()))?;
+        Ok((drive.storage_used, drive.storage_quota))
+    }
+
+    /// The folder at `path`, such as `/Finance/2024`, creating whatever is missing
+    #[instrument(skip(self))]
+    async fn create_folder_hierarchy(&self, drive_id: Id, path: &str, owner_id: Id) -> Result<Folder> {
+        let mut parent: Option<Folder> = None;
+        for name in path.split('/').filter(|segment| !segment.is_empty()) {
+            let parent_id = parent.as_ref().map(|folder| folder.id);
+            let existing = self
+                .drive_repository
+                .get_folders_by_parent(parent_id, drive_id)
+                .await?
+                .into_iter()
+                .find(|folder| folder.name == name);
+            let folder = match existing {
+                Some(folder) => folder,
+                None => {
+                    self.drive_repository
+                        .create_folder(Folder::new(drive_id, owner_id, name.to_string(), parent_id))
+                        .await?
+                }
+            };
+            parent = Some(folder);
+        }
+
+        parent.ok_or_else(|| Error::BadRequest("The path names no folder".to_string()))
+    }
+
+    #[instrument(skip(self))]
+    async fn move_item_to_folder(&self, item_id: Id, folder_id: Option<Id>, user_id: Id) -> Result<()> {
+        let item = self.find_item(item_id).await?;
+        self.drive_repository.move_drive_item(item_id, folder_id).await?;
+        self.log(&item, user_id, ActivityType::Move, "Moved item").await
+    }
+
+    /// A copy next to the original, owned by the user making it
+    #[instrument(skip(self))]
+    async fn duplicate_item(&self, item_id: Id, new_name: Option<String>, user_id: Id) -> Result<DriveItem> {
+        let item = self.find_item(item_id).await?;
+        let name = new_name.unwrap_or_else(|| format!("Copy of {}", item.name));
+        let copy = self
+            .drive_repository
+            .copy_drive_item(item_id, item.parent_id, Some(name), user_id)
+            .await?;
+        self.log(&copy, user_id, ActivityType::Copy, "Copied item").await?;
+        Ok(copy)
+    }
+
+    /// `permissions` is a share role, such as `Editor`
+    #[instrument(skip(self, user_ids))]
+    async fn share_with_users(&self, item_id: Id, user_ids: Vec<Id>, permissions: String, user_id: Id) -> Result<()> {
+        let role: ShareRole = serde_json::from_value(serde_json::Value::String(permissions.clone()))
+            .map_err(|_| Error::BadRequest(format!("Unknown share role {}", permissions)))?;
+        let item = self.find_item(item_id).await?;
+
+        let request = ShareItemRequest {
+            user_ids,
+            role,
+            message: None,
+            notify_users: false,
+            expires_at: None,
+        };
+        self.drive_repository.share_item(item_id, user_id, request).await?;
+        self.log(&item, user_id, ActivityType::Share, "Shared item").await
+    }
+
+    /// `access_level` is a public access level, such as `ViewOnly`
+    #[instrument(skip(self))]
+    async fn share_with_link(&self, item_id: Id, access_level: String, user_id: Id) -> Result<String> {
+        let access_level: PublicAccessLevel = serde_json::from_value(serde_json::Value::String(access_level.clone()))
+            .map_err(|_| Error::BadRequest(format!("Unknown access level {}", access_level)))?;
+        let item = self.find_item(item_id).await?;
+
+        let request = CreateSharingLinkRequest {
+            access_level,
+            password: None,
+            expires_at: None,
+        };
+        let token = self.drive_repository.create_sharing_link(item_id, user_id, request).await?;
+        self.log(&item, user_id, ActivityType::Share, "Created sharing link").await?;
+        Ok(token)
+    }
+
+    /// What sharing of the item and the folders holding it lets the user do,
+    /// such as `view` and `comment`; roles held on the drive aren't included
+    async fn get_item_permissions(&self, item_id: Id, user_id: Id) -> Result<Vec<String>> {
+        let item = self.find_item(item_id).await?;
+        let ancestors = self.drive_repository.get_item_ancestors(item_id).await?;
+        let access = item.effective_access(user_id, &ancestors);
+        let accesses = [
+            (ItemAccess::View, "view"),
+            (ItemAccess::Comment, "comment"),
+            (ItemAccess::Edit, "edit"),
+            (ItemAccess::Share, "share"),
+            (ItemAccess::Download, "download"),
+        ];
+        Ok(accesses
+            .into_iter()
+            .filter(|(item_access, _)| access.allows(*item_access))
+            .map(|(_, name)| name.to_string())
+            .collect())
+    }
+
+    /// Best matches first. Callers set `readable_drives` in the filters to leave
+    /// out what the user may not see.
+    #[instrument(skip(self, filters))]
+    async fn search_items(
+        &self,
+        drive_id: Id,
+        query: &DriveQuery,
+        filters: SearchFilters,
+        user_id: Id,
+    ) -> Result<Vec<SearchResult>> {
+        let matches = self
+            .drive_repository
+            .search_drive_items(SearchScope::Drive(drive_id), user_id, query, &filters)
+            .await?;
+        let owner_ids = matches.iter().map(|found| found.item.permissions.owner_id).collect();
+        let owners = self.owners(owner_ids).await?;
+
+        Ok(matches
+            .into_iter()
+            .filter_map(|found| {
+                let owner = owners.get(&found.item.permissions.owner_id)?.clone();
+                Some(SearchResult {
+                    item: DriveItemResponse::from_item(found.item, owner),
+                    rank: found.rank,
+                    highlight: found.highlight,
+                })
+            })
+            .collect())
+    }
+
+    #[instrument(skip(self, content))]
+    async fn index_document(&self, item_id: Id, content: &DocumentContent) -> Result<()> {
+        self.drive_repository.index_item_content(item_id, &content.plain_text()).await
+    }
+
+    /// Files of types text isn't read from are left as they are
+    #[instrument(skip(self, data), fields(len = data.len()))]
+    async fn index_file(&self, item_id: Id, data: &[u8]) -> Result<()> {
+        let item = self.find_item(item_id).await?;
+        let Some(text) = extract_text(&item.mime_type, data)? else {
+            return Ok(());
+        };
+        self.drive_repository.index_item_content(item_id, &text).await
+    }
+
+    #[instrument(skip(self, content))]
+    async fn index_document_items(&self, document_id: Id, content: &DocumentContent) -> Result<()> {
+        let text = content.plain_text();
+        for item_id in self.drive_repository.get_items_by_source(document_id).await? {
+            self.drive_repository.index_item_content(item_id, &text).await?;
+        }
+        Ok(())
+    }
+
+    /// Files are indexed as empty when text isn't read from their type, so
+    /// the backfill passes them by. Does nothing without storage.
+    #[instrument(skip(self))]
+    async fn index_file_items(&self, file_id: Id, storage_path: &str) -> Result<()> {
+        let Some(storage_service) = &self.storage_service else {
+            return Ok(());
+        };
+        for item_id in self.drive_repository.get_items_by_source(file_id).await? {
+            let item = self.find_item(item_id).await?;
+            let text = file_text(storage_service.as_ref(), &item.mime_type, storage_path).await?;
+            self.drive_repository.index_item_content(item_id, &text).await?;
+        }
+        Ok(())
+    }
+
+    /// Contents that can't be read are indexed as empty, so they aren't
+    /// tried again on every run
+    #[instrument(skip(self))]
+    async fn backfill_index(&self) -> Result<u64> {
+        let mut indexed = 0;
+        loop {
+            let batch = self
+                .drive_repository
+                .get_unindexed_items(BACKFILL_BATCH_SIZE, self.storage_service.is_some())
+                .await?;
+            for item in &batch {
+                let text = self.source_text(item).await.unwrap_or_else(|e| {
+                    warn!(item_id = %item.item_id, error = %e, "Item contents can't be read for search");
+                    String::new()
+                });
+                self.drive_repository.index_item_content(item.item_id, &text).await?;
+                indexed += 1;
+            }
+            if batch.len() < BACKFILL_BATCH_SIZE as usize {
+                break;
+            }
+        }
+
+        if indexed > 0 {
+            info!(indexed, "Indexed drive item contents for search");
+        }
+        Ok(indexed)
+    }
+
+    #[instrument(skip(self))]
+    async fn get_recent_activity(&self, drive_id: Id, _user_id: Id, limit: u32) -> Result<Vec<DriveActivity>> {
+        self.drive_repository.get_drive_activity(drive_id, Some(limit), None).await
+    }
+
+    /// Delete what has been in the trash longer than `older_than_days`
+    #[instrument(skip(self))]
+    async fn cleanup_trash(&self, drive_id: Id, older_than_days: u32) -> Result<u32> {
+        let cutoff = chrono::Utc::now() - chrono::Duration::days(i64::from(older_than_days));
+        let mut deleted = 0;
+        for item in self.drive_repository.get_trash_items(drive_id).await? {
+            if item.trashed_at.is_some_and(|trashed_at| trashed_at < cutoff) {
+                self.drive_repository.permanently_delete(item.id).await?;
+                deleted += 1;
+            }
+        }
+
+        if deleted > 0 {
+            info!(drive_id = %drive_id, deleted, "Old trash deleted");
+        }
+        Ok(deleted)
+    }
+
+    /// Bytes of everything in the folder and the folders below it, leaving out the trash
+    #[instrument(skip(self))]
+    async fn calculate_folder_size(&self, folder_id: Id) -> Result<i64> {
+        Ok(self.drive_repository.get_folder_stats(folder_id).await?.total_size)
+    }
+}
@@ -0,0 +1,285 @@
+use aes_gcm::{
+    aead::{rand_core::RngCore, Aead, KeyInit, OsRng, Payload},
+    Aes256Gcm, Key, Nonce,
+};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use kingshare_core::{config::EncryptionConfig, Error, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
+
+/// Plaintext bytes per encrypted chunk. Every chunk carries its own tag, so a
+/// range can be served by decrypting only the chunks it covers.
+pub const CHUNK_SIZE: usize = 64 * 1024;
+pub const TAG_SIZE: usize = 16;
+
+const KEY_SIZE: usize = 32;
+const NONCE_SIZE: usize = 12;
+const NONCE_PREFIX_SIZE: usize = 7;
+const ENVELOPE_VERSION: u8 = 1;
+const WRAP_AAD: &[u8] = b"kingshare-data-key-v1";
+
+struct MasterKey {
+    id: String,
+    cipher: Aes256Gcm,
+}
+
+impl MasterKey {
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() != KEY_SIZE {
+            return Err(Error::Internal(format!(
+                "Master key must be {} bytes, got {}",
+                KEY_SIZE,
+                bytes.len()
+            )));
+        }
+
+        // Keys are identified by fingerprint so the config never has to name them
+        let id = hex::encode(&Sha256::digest(bytes)[..8]);
+
+        Ok(Self {
+            id,
+            cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(bytes)),
+        })
+    }
+
+    fn from_base64(encoded: &str) -> Result<Self> {
+        let bytes = BASE64
+            .decode(encoded.trim())
+            .map_err(|e| Error::Internal(format!("Invalid master key encoding: {}", e)))?;
+        Self::from_bytes(&bytes)
+    }
+
+    fn from_file(path: &str) -> Result<Self> {
+        let contents = std::fs::read(path).map_err(|e| {
+            Error::Internal(format!("Failed to read master key file {}: {}", path, e))
+        })?;
+
+        if contents.len() == KEY_SIZE {
+            return Self::from_bytes(&contents);
+        }
+
+        let encoded = String::from_utf8(contents)
+            .map_err(|_| Error::Internal(format!("Invalid master key file {}", path)))?;
+        Self::from_base64(&encoded)
+    }
+}
+
+/// Wrapped data key and layout of one encrypted blob, stored under `.keys/` beside the blob tree
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyEnvelope {
+    pub version: u8,
+    pub key_id: String,
+    pub wrap_nonce: String,
+    pub wrapped_key: String,
+    pub nonce_prefix: String,
+    pub chunk_size: u32,
+    pub size: u64,
+}
+
+impl KeyEnvelope {
+    /// Number of chunks the plaintext was split into; empty content is still one chunk
+    pub fn chunk_count(&self) -> u64 {
+        let chunk_size = self.chunk_size as u64;
+        self.size.div_ceil(chunk_size).max(1)
+    }
+
+    /// Plaintext length of the chunk at `index`
+    pub fn chunk_len(&self, index: u64) -> usize {
+        let chunk_size = self.chunk_size as u64;
+        if index + 1 == self.chunk_count() {
+            (self.size - index * chunk_size) as usize
+        } else {
+            chunk_size as usize
+        }
+    }
+}
+
+/// The master key new data keys are wrapped with, plus retired keys that can
+/// still unwrap envelopes written before a rotation.
+pub struct KeyRing {
+    current: MasterKey,
+    previous: Vec<MasterKey>,
+}
+
+impl KeyRing {
+    pub fn from_config(config: &EncryptionConfig) -> Result<Self> {
+        let current = match (&config.master_key, &config.master_key_file) {
+            (Some(key), _) => MasterKey::from_base64(key)?,
+            (None, Some(path)) => MasterKey::from_file(path)?,
+            (None, None) => {
+                return Err(Error::Internal(
+                    "Encryption requires master_key or master_key_file".to_string(),
+                ))
+            }
+        };
+
+        let mut previous = Vec::new();
+        for key in &config.previous_master_keys {
+            previous.push(MasterKey::from_base64(key)?);
+        }
+        for path in &config.previous_master_key_files {
+            previous.push(MasterKey::from_file(path)?);
+        }
+
+        Ok(Self { current, previous })
+    }
+
+    pub fn current_key_id(&self) -> &str {
+        &self.current.id
+    }
+
+    /// Create a fresh data key for a new blob
+    pub fn generate(&self) -> Result<(KeyEnvelope, ChunkCipher)> {
+        let mut data_key = [0u8; KEY_SIZE];
+        let mut nonce_prefix = [0u8; NONCE_PREFIX_SIZE];
+        OsRng.fill_bytes(&mut data_key);
+        OsRng.fill_bytes(&mut nonce_prefix);
+
+        let (wrap_nonce, wrapped_key) = self.wrap(&data_key)?;
+        let envelope = KeyEnvelope {
+            version: ENVELOPE_VERSION,
+            key_id: self.current.id.clone(),
+            wrap_nonce,
+            wrapped_key,
+            nonce_prefix: BASE64.encode(nonce_prefix),
+            chunk_size: CHUNK_SIZE as u32,
+            size: 0,
+        };
+
+        Ok((envelope, ChunkCipher::new(&data_key, nonce_prefix)))
+    }
+
+    /// Unwrap the data key of an existing blob
+    pub fn open(&self, envelope: &KeyEnvelope) -> Result<ChunkCipher> {
+        let data_key = self.unwrap(envelope)?;
+
+        let nonce_prefix: [u8; NONCE_PREFIX_SIZE] = BASE64
+            .decode(&envelope.nonce_prefix)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| Error::Internal("Invalid key envelope nonce".to_string()))?;
+
+        Ok(ChunkCipher::new(&data_key, nonce_prefix))
+    }
+
+    /// Re-wrap a data key under the current master key; `None` if it already is
+    pub fn rewrap(&self, envelope: &KeyEnvelope) -> Result<Option<KeyEnvelope>> {
+        if envelope.key_id == self.current.id {
+            return Ok(None);
+        }
+
+        let data_key = self.unwrap(envelope)?;
+        let (wrap_nonce, wrapped_key) = self.wrap(&data_key)?;
+
+        Ok(Some(KeyEnvelope {
+            key_id: self.current.id.clone(),
+            wrap_nonce,
+            wrapped_key,
+            ..envelope.clone()
+        }))
+    }
+
+    fn wrap(&self, data_key: &[u8]) -> Result<(String, String)> {
+        let mut nonce = [0u8; NONCE_SIZE];
+        OsRng.fill_bytes(&mut nonce);
+
+        let wrapped = self
+            .current
+            .cipher
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload { msg: data_key, aad: WRAP_AAD },
+            )
+            .map_err(|_| Error::Internal("Failed to wrap data key".to_string()))?;
+
+        Ok((BASE64.encode(nonce), BASE64.encode(wrapped)))
+    }
+
+    fn unwrap(&self, envelope: &KeyEnvelope) -> Result<Vec<u8>> {
+        if envelope.version != ENVELOPE_VERSION {
+            return Err(Error::Internal(format!(
+                "Unsupported key envelope version {}",
+                envelope.version
+            )));
+        }
+
+        let master_key = std::iter::once(&self.current)
+            .chain(&self.previous)
+            .find(|key| key.id == envelope.key_id)
+            .ok_or_else(|| {
+                Error::Internal(format!("Master key {} is not configured", envelope.key_id))
+            })?;
+
+        let nonce = BASE64
+            .decode(&envelope.wrap_nonce)
+            .ok()
+            .filter(|nonce| nonce.len() == NONCE_SIZE)
+            .ok_or_else(|| Error::Internal("Invalid key envelope nonce".to_string()))?;
+        let wrapped_key = BASE64
+            .decode(&envelope.wrapped_key)
+            .map_err(|_| Error::Internal("Invalid wrapped data key".to_string()))?;
+
+        master_key
+            .cipher
+            .decrypt(
+                Nonce::from_slice(&nonce),
+                Payload { msg: &wrapped_key, aad: WRAP_AAD },
+            )
+            .map_err(|_| Error::Internal("Failed to unwrap data key".to_string()))
+    }
+}
+
+impl fmt::Debug for KeyRing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeyRing")
+            .field("current", &self.current.id)
+            .field("previous", &self.previous.iter().map(|key| &key.id).collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// AES-256-GCM over numbered chunks. The nonce is the blob's random prefix, the
+/// chunk index and a final-chunk flag, so chunks can't be reordered or dropped
+/// and a truncated blob fails to decrypt.
+pub struct ChunkCipher {
+    cipher: Aes256Gcm,
+    nonce_prefix: [u8; NONCE_PREFIX_SIZE],
+}
+
+impl ChunkCipher {
+    fn new(data_key: &[u8], nonce_prefix: [u8; NONCE_PREFIX_SIZE]) -> Self {
+        Self {
+            cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(data_key)),
+            nonce_prefix,
+        }
+    }
+
+    fn nonce(&self, index: u64, last: bool) -> std::io::Result<[u8; NONCE_SIZE]> {
+        let index = u32::try_from(index).map_err(|_| {
+            std::io::Error::new(std::io::ErrorKind::InvalidInput, "Too many encrypted chunks")
+        })?;
+
+        let mut nonce = [0u8; NONCE_SIZE];
+        nonce[..NONCE_PREFIX_SIZE].copy_from_slice(&self.nonce_prefix);
+        nonce[NONCE_PREFIX_SIZE..NONCE_SIZE - 1].copy_from_slice(&index.to_be_bytes());
+        nonce[NONCE_SIZE - 1] = last as u8;
+        Ok(nonce)
+    }
+
+    pub fn encrypt_chunk(&self, index: u64, last: bool, plaintext: &[u8]) -> std::io::Result<Vec<u8>> {
+        let nonce = self.nonce(index, last)?;
+        self.cipher
+            .encrypt(Nonce::from_slice(&nonce), plaintext)
+            .map_err(|_| std::io::Error::other("Failed to encrypt chunk"))
+    }
+
+    pub fn decrypt_chunk(&self, index: u64, last: bool, ciphertext: &[u8]) -> std::io::Result<Vec<u8>> {
+        let nonce = self.nonce(index, last)?;
+        self.cipher
+            .decrypt(Nonce::from_slice(&nonce), ciphertext)
+            .map_err(|_| {
+                std::io::Error::new(std::io::ErrorKind::InvalidData, "Encrypted chunk failed authentication")
+            })
+    }
+}
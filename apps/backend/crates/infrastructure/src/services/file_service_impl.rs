@@ -1,14 +1,24 @@
+use super::content_sniffer::{self, SniffedType};
 use async_trait::async_trait;
-use kingshare_core::{Error, Result};
-use kingshare_domain::services::{FileAnalysis, FileService, FileValidationResult};
+use kingshare_core::{config::ContentTypePolicy, Error, Result};
+use image::{codecs::jpeg::JpegEncoder, ImageFormat, ImageReader, Limits};
+use kingshare_domain::services::{
+    FileAnalysis, FileService, FileValidationResult, GeneratedThumbnail,
+};
 use std::collections::HashMap;
-use tracing::{info, instrument, warn};
+use std::io::Cursor;
+use tracing::{info, instrument};
+
+const THUMBNAIL_JPEG_QUALITY: u8 = 80;
+const THUMBNAIL_MAX_SOURCE_DIMENSION: u32 = 16_384;
+const THUMBNAIL_MAX_DECODE_ALLOC: u64 = 512 * 1024 * 1024;
 
 #[derive(Debug, Clone)]
 pub struct DefaultFileService {
     max_file_size: u64,
     allowed_types: Vec<String>,
     blocked_extensions: Vec<String>,
+    content_type_policy: ContentTypePolicy,
 }
 
 impl DefaultFileService {
@@ -62,9 +72,15 @@ impl DefaultFileService {
                 "dll".to_string(),
                 "sys".to_string(),
             ],
+            content_type_policy: ContentTypePolicy::default(),
         }
     }
 
+    pub fn with_content_type_policy(mut self, policy: ContentTypePolicy) -> Self {
+        self.content_type_policy = policy;
+        self
+    }
+
     fn get_file_extension(filename: &str) -> Option<String> {
         std::path::Path::new(filename)
             .extension()
@@ -72,35 +88,102 @@ impl DefaultFileService {
             .map(|ext| ext.to_lowercase())
     }
 
-    fn detect_content_type_from_bytes(data: &[u8]) -> Option<String> {
-        // Simple magic number detection
-        if data.len() < 4 {
-            return None;
+    /// Check the declared type against the file extension and the sniffed content
+    /// under the configured policy. Returns the type to store the file as.
+    fn resolve_content_type(
+        &self,
+        declared: &str,
+        extension_type: Option<&'static str>,
+        detected: Option<SniffedType>,
+        data: &[u8],
+        errors: &mut Vec<String>,
+        warnings: &mut Vec<String>,
+    ) -> String {
+        let canonical = content_sniffer::canonical_type(declared);
+
+        // (message, whether the override policy rejects it)
+        let mut mismatches = Vec::new();
+
+        if let Some(sniffed) = detected {
+            if sniffed.is_executable() {
+                errors.push(format!(
+                    "File content is an executable ({}) and is not allowed",
+                    sniffed.content_type
+                ));
+            }
+
+            if !content_sniffer::is_compatible(&canonical, sniffed.content_type) {
+                mismatches.push((
+                    format!(
+                        "Declared content type '{}' doesn't match detected type '{}'",
+                        declared, sniffed.content_type
+                    ),
+                    false,
+                ));
+            }
+
+            if let Some(extension_type) = extension_type {
+                if !content_sniffer::is_compatible(extension_type, sniffed.content_type) {
+                    mismatches.push((
+                        format!(
+                            "File extension implies '{}' but the content is '{}'",
+                            extension_type, sniffed.content_type
+                        ),
+                        true,
+                    ));
+                }
+            }
+        } else if !data.is_empty() {
+            // Content we can't identify must not claim a format that has a signature
+            for claimed in [Some(canonical.as_str()), extension_type].into_iter().flatten() {
+                if content_sniffer::has_signature(claimed) {
+                    mismatches.push((
+                        format!("File content is not valid '{}'", claimed),
+                        true,
+                    ));
+                    break;
+                }
+            }
         }
 
-        match &data[0..4] {
-            [0xFF, 0xD8, 0xFF, _] => Some("image/jpeg".to_string()),
-            [0x89, 0x50, 0x4E, 0x47] => Some("image/png".to_string()),
-            [0x47, 0x49, 0x46, 0x38] => Some("image/gif".to_string()),
-            [0x25, 0x50, 0x44, 0x46] => Some("application/pdf".to_string()),
-            [0x50, 0x4B, 0x03, 0x04] | [0x50, 0x4B, 0x05, 0x06] | [0x50, 0x4B, 0x07, 0x08] => {
-                Some("application/zip".to_string())
+        if let Some(extension_type) = extension_type {
+            if !content_sniffer::is_compatible(extension_type, &canonical) {
+                mismatches.push((
+                    format!(
+                        "File extension implies '{}' but the declared type is '{}'",
+                        extension_type, declared
+                    ),
+                    false,
+                ));
             }
-            _ => None,
         }
-    }
 
-    fn is_text_file(data: &[u8]) -> bool {
-        // Check if file appears to be text by looking for non-printable characters
-        let sample_size = std::cmp::min(data.len(), 1024);
-        let sample = &data[0..sample_size];
-        
-        let non_text_chars = sample.iter()
-            .filter(|&&byte| byte < 32 && byte != 9 && byte != 10 && byte != 13)
-            .count();
-        
-        // If less than 5% are non-text characters, consider it text
-        (non_text_chars as f64 / sample_size as f64) < 0.05
+        for (message, rejected_on_override) in mismatches {
+            match self.content_type_policy {
+                ContentTypePolicy::Strict => errors.push(message),
+                ContentTypePolicy::Override if rejected_on_override => errors.push(message),
+                _ => warnings.push(message),
+            }
+        }
+
+        if self.content_type_policy == ContentTypePolicy::Permissive {
+            return declared.to_string();
+        }
+
+        match detected {
+            Some(sniffed) if !sniffed.is_generic() => sniffed.content_type.to_string(),
+            // A generic sniff (plain text, a zip, ...) keeps a more specific claim from its family
+            Some(sniffed) => [Some(canonical.as_str()), extension_type]
+                .into_iter()
+                .flatten()
+                .find(|claimed| {
+                    *claimed != "application/octet-stream"
+                        && content_sniffer::is_compatible(claimed, sniffed.content_type)
+                })
+                .unwrap_or(sniffed.content_type)
+                .to_string(),
+            None => canonical,
+        }
     }
 }
 
@@ -136,19 +219,21 @@ impl FileService for DefaultFileService {
             }
         }
 
-        // Content type validation
-        if !self.is_allowed_file_type(content_type).await {
-            errors.push(format!("Content type '{}' is not allowed", content_type));
-        }
+        // Content type validation against the bytes themselves
+        let detected = content_sniffer::sniff(data);
+        let extension_type = Self::get_file_extension(filename)
+            .and_then(|extension| content_sniffer::type_for_extension(&extension));
+        let effective_type = self.resolve_content_type(
+            content_type,
+            extension_type,
+            detected,
+            data,
+            &mut errors,
+            &mut warnings,
+        );
 
-        // Content validation - check if declared content type matches actual content
-        if let Some(detected_type) = Self::detect_content_type_from_bytes(data) {
-            if detected_type != content_type {
-                warnings.push(format!(
-                    "Declared content type '{}' doesn't match detected type '{}'",
-                    content_type, detected_type
-                ));
-            }
+        if !self.is_allowed_file_type(&effective_type).await {
+            errors.push(format!("Content type '{}' is not allowed", effective_type));
         }
 
         // Filename validation
@@ -173,6 +258,7 @@ impl FileService for DefaultFileService {
         info!(
             filename = %filename,
             content_type = %content_type,
+            effective_type = %effective_type,
             size = size,
             is_valid = is_valid,
             errors_count = errors.len(),
@@ -184,6 +270,8 @@ impl FileService for DefaultFileService {
             is_valid,
             errors,
             warnings,
+            detected_type: detected.map(|sniffed| sniffed.content_type.to_string()),
+            content_type: effective_type,
         })
     }
 
@@ -196,14 +284,13 @@ impl FileService for DefaultFileService {
         metadata.insert("filename".to_string(), filename.to_string());
 
         // Detect content type
-        let detected_type = Self::detect_content_type_from_bytes(data)
-            .unwrap_or_else(|| {
-                if Self::is_text_file(data) {
-                    "text/plain".to_string()
-                } else {
-                    "application/octet-stream".to_string()
-                }
-            });
+        let sniffed = content_sniffer::sniff(data);
+        let detected_type = sniffed
+            .map(|sniffed| sniffed.content_type.to_string())
+            .unwrap_or_else(|| "application/octet-stream".to_string());
+        if let Some(charset) = sniffed.and_then(|sniffed| sniffed.charset) {
+            metadata.insert("charset".to_string(), charset.to_string());
+        }
 
         // Safety analysis
         let is_safe = self.is_file_safe(filename, &detected_type, data).await;
@@ -238,14 +325,35 @@ impl FileService for DefaultFileService {
     #[instrument(skip(self, data))]
     async fn generate_thumbnail(
         &self,
-        _file_path: &str,
-        _max_width: u32,
-        _max_height: u32,
-    ) -> Result<Option<Vec<u8>>> {
-        // Thumbnail generation would be implemented here
-        // This would typically use image processing libraries like image-rs
-        warn!("Thumbnail generation not implemented");
-        Ok(None)
+        data: &[u8],
+        content_type: &str,
+        max_dimension: u32,
+    ) -> Result<Option<GeneratedThumbnail>> {
+        let format = match content_type {
+            "image/jpeg" => ImageFormat::Jpeg,
+            "image/png" => ImageFormat::Png,
+            "image/gif" => ImageFormat::Gif,
+            "image/webp" => ImageFormat::WebP,
+            _ => return Ok(None),
+        };
+
+        // Decoding and resizing are CPU-bound; keep them off the async workers
+        let data = data.to_vec();
+        let thumbnail = tokio::task::spawn_blocking(move || {
+            Self::render_thumbnail(&data, format, max_dimension)
+        })
+        .await
+        .map_err(|e| Error::Internal(format!("Thumbnail task failed: {}", e)))??;
+
+        info!(
+            content_type = %content_type,
+            width = thumbnail.width,
+            height = thumbnail.height,
+            size = thumbnail.data.len(),
+            "Thumbnail generated"
+        );
+
+        Ok(Some(thumbnail))
     }
 
     #[instrument(skip(self, data))]
@@ -263,8 +371,8 @@ impl FileService for DefaultFileService {
             metadata.insert("extension".to_string(), extension);
         }
 
-        if let Some(detected_type) = Self::detect_content_type_from_bytes(data) {
-            metadata.insert("detected_content_type".to_string(), detected_type);
+        if let Some(sniffed) = content_sniffer::sniff(data) {
+            metadata.insert("detected_content_type".to_string(), sniffed.content_type.to_string());
         }
 
         // Calculate hash
@@ -278,7 +386,10 @@ impl FileService for DefaultFileService {
     }
 
     async fn is_allowed_file_type(&self, content_type: &str) -> bool {
-        self.allowed_types.contains(&content_type.to_string())
+        let content_type = content_sniffer::canonical_type(content_type);
+        self.allowed_types
+            .iter()
+            .any(|allowed| content_sniffer::canonical_type(allowed) == content_type)
     }
 
     async fn get_max_file_size(&self) -> u64 {
@@ -287,6 +398,50 @@ impl FileService for DefaultFileService {
 }
 
 impl DefaultFileService {
+    fn render_thumbnail(data: &[u8], format: ImageFormat, max_dimension: u32) -> Result<GeneratedThumbnail> {
+        let mut reader = ImageReader::with_format(Cursor::new(data), format);
+
+        // A few bytes of compressed input can claim an enormous canvas
+        let mut limits = Limits::default();
+        limits.max_image_width = Some(THUMBNAIL_MAX_SOURCE_DIMENSION);
+        limits.max_image_height = Some(THUMBNAIL_MAX_SOURCE_DIMENSION);
+        limits.max_alloc = Some(THUMBNAIL_MAX_DECODE_ALLOC);
+        reader.limits(limits);
+
+        let image = reader
+            .decode()
+            .map_err(|e| Error::BadRequest(format!("Failed to decode image: {}", e)))?;
+
+        // Never upscale images that are already small enough
+        let image = if image.width() > max_dimension || image.height() > max_dimension {
+            image.thumbnail(max_dimension, max_dimension)
+        } else {
+            image
+        };
+
+        // JPEG for opaque images, PNG where transparency has to survive
+        let mut encoded = Cursor::new(Vec::new());
+        let content_type = if image.color().has_alpha() {
+            image
+                .write_to(&mut encoded, ImageFormat::Png)
+                .map_err(|e| Error::Internal(format!("Failed to encode thumbnail: {}", e)))?;
+            "image/png"
+        } else {
+            image
+                .to_rgb8()
+                .write_with_encoder(JpegEncoder::new_with_quality(&mut encoded, THUMBNAIL_JPEG_QUALITY))
+                .map_err(|e| Error::Internal(format!("Failed to encode thumbnail: {}", e)))?;
+            "image/jpeg"
+        };
+
+        Ok(GeneratedThumbnail {
+            data: encoded.into_inner(),
+            content_type: content_type.to_string(),
+            width: image.width(),
+            height: image.height(),
+        })
+    }
+
     async fn is_file_safe(&self, filename: &str, content_type: &str, data: &[u8]) -> bool {
         // Check file extension
         if let Some(extension) = Self::get_file_extension(filename) {
@@ -301,12 +456,8 @@ impl DefaultFileService {
         }
 
         // Check for executable signatures
-        if data.len() >= 2 {
-            match &data[0..2] {
-                [0x4D, 0x5A] => return false, // PE executable
-                [0x7F, 0x45] => return false, // ELF executable
-                _ => {}
-            }
+        if content_sniffer::sniff(data).is_some_and(|sniffed| sniffed.is_executable()) {
+            return false;
         }
 
         // Additional safety checks could be added here
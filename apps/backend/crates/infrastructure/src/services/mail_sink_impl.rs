@@ -0,0 +1,81 @@
+use crate::services::smtp_mailer_impl::{build_message, parse_mailbox};
+use async_trait::async_trait;
+use chrono::Utc;
+use kingshare_core::{Error, Result};
+use kingshare_domain::services::{EmailMessage, Mailer};
+use lettre::message::Mailbox;
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+use tracing::{info, instrument};
+
+/// `Mailer` that writes each message to an `.eml` file instead of sending it,
+/// so mail can be read during development without an SMTP server
+#[derive(Debug, Clone)]
+pub struct FileMailer {
+    outbox: PathBuf,
+    from: Mailbox,
+}
+
+impl FileMailer {
+    pub fn new(outbox: impl Into<PathBuf>, from_address: &str) -> Result<Self> {
+        let outbox = outbox.into();
+        std::fs::create_dir_all(&outbox).map_err(Error::Io)?;
+
+        Ok(Self {
+            outbox,
+            from: parse_mailbox(from_address)?,
+        })
+    }
+}
+
+#[async_trait]
+impl Mailer for FileMailer {
+    #[instrument(skip(self, message), fields(to = %message.to))]
+    async fn send(&self, message: &EmailMessage) -> Result<()> {
+        let email = build_message(&self.from, message)?;
+        let path = self.outbox.join(format!(
+            "{}-{}.eml",
+            Utc::now().format("%Y%m%dT%H%M%S%.3fZ"),
+            uuid::Uuid::new_v4()
+        ));
+
+        tokio::fs::write(&path, email.formatted())
+            .await
+            .map_err(Error::Io)?;
+
+        info!(path = %path.display(), subject = %message.subject, "Email written to outbox");
+        Ok(())
+    }
+}
+
+/// `Mailer` that keeps messages in memory, for tests
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryMailer {
+    messages: Arc<Mutex<Vec<EmailMessage>>>,
+}
+
+impl InMemoryMailer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every message sent so far, oldest first
+    pub fn sent(&self) -> Vec<EmailMessage> {
+        self.messages.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+#[async_trait]
+impl Mailer for InMemoryMailer {
+    async fn send(&self, message: &EmailMessage) -> Result<()> {
+        // Same validation as a real transport
+        parse_mailbox(&message.to)?;
+        self.messages
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(message.clone());
+        Ok(())
+    }
+}
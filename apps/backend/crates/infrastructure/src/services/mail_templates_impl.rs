@@ -0,0 +1,95 @@
+use handlebars::Handlebars;
+use kingshare_core::{Error, Result};
+use kingshare_domain::services::{EmailMessage, EmailTemplate, MailTemplates};
+use std::path::Path;
+use tracing::info;
+
+/// Built-in subject, text body and HTML body of a template
+fn built_in(template: EmailTemplate) -> [&'static str; 3] {
+    match template {
+        EmailTemplate::VerifyEmail => [
+            include_str!("../../templates/mail/verify_email.subject.hbs"),
+            include_str!("../../templates/mail/verify_email.txt.hbs"),
+            include_str!("../../templates/mail/verify_email.html.hbs"),
+        ],
+        EmailTemplate::PasswordReset => [
+            include_str!("../../templates/mail/password_reset.subject.hbs"),
+            include_str!("../../templates/mail/password_reset.txt.hbs"),
+            include_str!("../../templates/mail/password_reset.html.hbs"),
+        ],
+        EmailTemplate::PasswordChanged => [
+            include_str!("../../templates/mail/password_changed.subject.hbs"),
+            include_str!("../../templates/mail/password_changed.txt.hbs"),
+            include_str!("../../templates/mail/password_changed.html.hbs"),
+        ],
+    }
+}
+
+/// `MailTemplates` backed by Handlebars. Each template has `<name>.subject.hbs`,
+/// `<name>.txt.hbs` and `<name>.html.hbs` parts; any of them can be replaced by
+/// a file of the same name in the override directory.
+#[derive(Debug)]
+pub struct HandlebarsMailTemplates {
+    // Subjects and plain-text bodies must not be HTML-escaped
+    text: Handlebars<'static>,
+    html: Handlebars<'static>,
+}
+
+impl HandlebarsMailTemplates {
+    pub fn new(override_dir: Option<&str>) -> Result<Self> {
+        let mut text = Handlebars::new();
+        text.set_strict_mode(true);
+        text.register_escape_fn(handlebars::no_escape);
+        let mut html = Handlebars::new();
+        html.set_strict_mode(true);
+
+        for template in EmailTemplate::ALL {
+            let name = template.name();
+            let [subject, text_body, html_body] = built_in(template);
+            let part = |suffix: &str, built_in: &str| -> Result<String> {
+                let Some(dir) = override_dir else {
+                    return Ok(built_in.to_string());
+                };
+                let path = Path::new(dir).join(format!("{}.{}.hbs", name, suffix));
+                if !path.exists() {
+                    return Ok(built_in.to_string());
+                }
+                info!(path = %path.display(), "Using mail template override");
+                std::fs::read_to_string(&path).map_err(Error::Io)
+            };
+
+            Self::register(&mut text, &format!("{}.subject", name), &part("subject", subject)?)?;
+            Self::register(&mut text, &format!("{}.txt", name), &part("txt", text_body)?)?;
+            Self::register(&mut html, &format!("{}.html", name), &part("html", html_body)?)?;
+        }
+
+        Ok(Self { text, html })
+    }
+
+    fn register(registry: &mut Handlebars<'static>, name: &str, template: &str) -> Result<()> {
+        registry
+            .register_template_string(name, template)
+            .map_err(|e| Error::Internal(format!("Invalid mail template '{}': {}", name, e)))
+    }
+
+    fn render_part(registry: &Handlebars<'static>, name: &str, context: &serde_json::Value) -> Result<String> {
+        registry
+            .render(name, context)
+            .map_err(|e| Error::Internal(format!("Failed to render mail template '{}': {}", name, e)))
+    }
+}
+
+impl MailTemplates for HandlebarsMailTemplates {
+    fn render(&self, template: EmailTemplate, to: &str, context: &serde_json::Value) -> Result<EmailMessage> {
+        let name = template.name();
+        let subject = Self::render_part(&self.text, &format!("{}.subject", name), context)?;
+
+        Ok(EmailMessage {
+            to: to.to_string(),
+            // Headers can't span lines
+            subject: subject.lines().map(str::trim).collect::<Vec<_>>().join(" ").trim().to_string(),
+            text_body: Self::render_part(&self.text, &format!("{}.txt", name), context)?,
+            html_body: Some(Self::render_part(&self.html, &format!("{}.html", name), context)?),
+        })
+    }
+}
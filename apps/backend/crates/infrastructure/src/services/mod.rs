@@ -1,9 +1,30 @@
 pub mod auth_service_impl;
+pub mod clamd_scanner_impl;
+pub mod content_sniffer;
+pub mod drive_service_impl;
+pub mod encryption;
+pub mod mail_sink_impl;
+pub mod mail_templates_impl;
+pub mod oidc_provider_impl;
 pub mod storage_service_impl;
+pub mod smtp_mailer_impl;
+pub mod totp_service_impl;
+pub mod s3_storage_service_impl;
+pub mod text_extractor;
+pub mod upload_digests;
 pub mod file_service_impl;
 pub mod websocket_service_impl;
 
 pub use auth_service_impl::JwtAuthService;
+pub use clamd_scanner_impl::ClamdScanner;
+pub use drive_service_impl::DefaultDriveService;
+pub use encryption::KeyRing;
+pub use mail_sink_impl::{FileMailer, InMemoryMailer};
+pub use mail_templates_impl::HandlebarsMailTemplates;
+pub use oidc_provider_impl::OidcClient;
 pub use storage_service_impl::LocalStorageService;
+pub use smtp_mailer_impl::SmtpMailer;
+pub use totp_service_impl::DefaultTotpService;
+pub use s3_storage_service_impl::S3StorageService;
 pub use file_service_impl::DefaultFileService;
 pub use websocket_service_impl::InMemoryWebSocketService;
\ No newline at end of file
@@ -0,0 +1,401 @@
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use async_trait::async_trait;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::{
+    decode, decode_header,
+    jwk::{Jwk, PublicKeyUse},
+    Algorithm, DecodingKey, Validation,
+};
+use kingshare_core::{config::OidcConfig, Error, Result};
+use kingshare_domain::{
+    entities::{OidcAuthorization, OidcIdentity, OidcLoginState},
+    services::OidcProvider,
+};
+use serde::Deserialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    sync::RwLock,
+    time::{Duration, Instant},
+};
+use tracing::{info, instrument, warn};
+use url::Url;
+
+/// An unknown key ID triggers a JWKS refetch at most this often, so forged
+/// tokens can't make every login call the identity provider
+const JWKS_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Clock skew tolerated on ID token timestamps, in seconds
+const ID_TOKEN_LEEWAY: u64 = 60;
+
+/// Signature algorithms accepted on ID tokens. HMAC is left out: it would make
+/// the client secret the verification key.
+const ALLOWED_ALGORITHMS: &[Algorithm] = &[
+    Algorithm::RS256,
+    Algorithm::RS384,
+    Algorithm::RS512,
+    Algorithm::PS256,
+    Algorithm::PS384,
+    Algorithm::PS512,
+    Algorithm::ES256,
+    Algorithm::ES384,
+    Algorithm::EdDSA,
+];
+
+/// The parts of the discovery document the code flow needs
+#[derive(Debug, Clone, Deserialize)]
+struct ProviderMetadata {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    jwks_uri: String,
+    code_challenge_methods_supported: Option<Vec<String>>,
+    token_endpoint_auth_methods_supported: Option<Vec<String>>,
+}
+
+impl ProviderMetadata {
+    /// `client_secret_basic` is the default per the spec; only post the secret
+    /// in the form when the provider doesn't take basic auth
+    fn posts_client_secret(&self) -> bool {
+        self.token_endpoint_auth_methods_supported
+            .as_ref()
+            .is_some_and(|methods| {
+                !methods.iter().any(|m| m == "client_secret_basic")
+                    && methods.iter().any(|m| m == "client_secret_post")
+            })
+    }
+}
+
+struct SigningKeys {
+    keys: Vec<Jwk>,
+    fetched_at: Instant,
+}
+
+#[derive(Deserialize)]
+struct JwksDocument {
+    keys: Vec<Value>,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    id_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TokenErrorResponse {
+    error: String,
+    error_description: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct IdTokenClaims {
+    sub: String,
+    nonce: Option<String>,
+    azp: Option<String>,
+    email: Option<String>,
+    // Some providers send the flag as a string
+    email_verified: Option<Value>,
+    given_name: Option<String>,
+    family_name: Option<String>,
+    name: Option<String>,
+    preferred_username: Option<String>,
+    #[serde(flatten)]
+    other: HashMap<String, Value>,
+}
+
+/// `OidcProvider` for any OpenID Connect provider publishing a discovery
+/// document. Discovery and signing keys are fetched on first use and cached;
+/// keys are refetched when a token names one that isn't known yet.
+pub struct OidcClient {
+    config: OidcConfig,
+    http: reqwest::Client,
+    metadata: RwLock<Option<ProviderMetadata>>,
+    signing_keys: RwLock<Option<SigningKeys>>,
+}
+
+impl OidcClient {
+    pub fn new(config: OidcConfig) -> Result<Self> {
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.timeout_seconds))
+            .build()?;
+
+        Ok(Self {
+            config,
+            http,
+            metadata: RwLock::new(None),
+            signing_keys: RwLock::new(None),
+        })
+    }
+
+    /// Fetch the discovery document now instead of at the first login
+    pub async fn discover(&self) -> Result<()> {
+        self.metadata().await.map(|_| ())
+    }
+
+    async fn metadata(&self) -> Result<ProviderMetadata> {
+        let cached = self.metadata.read().unwrap_or_else(|poisoned| poisoned.into_inner()).clone();
+        if let Some(metadata) = cached {
+            return Ok(metadata);
+        }
+
+        let issuer = self.config.issuer_url.trim_end_matches('/');
+        let url = format!("{}/.well-known/openid-configuration", issuer);
+        let metadata: ProviderMetadata = self
+            .http
+            .get(&url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        // Tokens are checked against the discovered issuer, so it has to be the configured one
+        if metadata.issuer.trim_end_matches('/') != issuer {
+            return Err(Error::Internal(format!(
+                "Identity provider reports issuer {} instead of {}",
+                metadata.issuer, issuer
+            )));
+        }
+        if let Some(methods) = &metadata.code_challenge_methods_supported {
+            if !methods.iter().any(|method| method == "S256") {
+                return Err(Error::Internal(
+                    "Identity provider does not support PKCE with S256".to_string(),
+                ));
+            }
+        }
+
+        info!(issuer = %metadata.issuer, "Loaded identity provider configuration");
+        *self.metadata.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(metadata.clone());
+        Ok(metadata)
+    }
+
+    async fn signing_key(&self, metadata: &ProviderMetadata, kid: Option<&str>) -> Result<DecodingKey> {
+        let recently_fetched = {
+            let cached = self.signing_keys.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+            if let Some(signing_keys) = cached.as_ref() {
+                if let Some(jwk) = find_key(&signing_keys.keys, kid) {
+                    return Ok(DecodingKey::from_jwk(jwk)?);
+                }
+                signing_keys.fetched_at.elapsed() < JWKS_REFRESH_INTERVAL
+            } else {
+                false
+            }
+        };
+
+        if recently_fetched {
+            return Err(Error::Authentication("ID token is signed with an unknown key".to_string()));
+        }
+
+        let document: JwksDocument = self
+            .http
+            .get(&metadata.jwks_uri)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        // Skip key types this library can't use rather than rejecting the whole set
+        let keys: Vec<Jwk> = document
+            .keys
+            .into_iter()
+            .filter_map(|key| serde_json::from_value(key).ok())
+            .collect();
+
+        let key = find_key(&keys, kid).map(DecodingKey::from_jwk).transpose()?;
+        *self.signing_keys.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(SigningKeys {
+            keys,
+            fetched_at: Instant::now(),
+        });
+
+        key.ok_or_else(|| Error::Authentication("ID token is signed with an unknown key".to_string()))
+    }
+
+    async fn redeem_code(&self, metadata: &ProviderMetadata, code: &str, code_verifier: &str) -> Result<String> {
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", self.config.redirect_uri.as_str()),
+            ("code_verifier", code_verifier),
+            ("client_id", self.config.client_id.as_str()),
+        ];
+
+        let mut request = self.http.post(&metadata.token_endpoint);
+        if let Some(secret) = &self.config.client_secret {
+            if metadata.posts_client_secret() {
+                form.push(("client_secret", secret.as_str()));
+            } else {
+                request = request.basic_auth(form_encode(&self.config.client_id), Some(form_encode(secret)));
+            }
+        }
+
+        let response = request.form(&form).send().await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let reason = match response.json::<TokenErrorResponse>().await {
+                Ok(error) => error.error_description.unwrap_or(error.error),
+                Err(_) => status.to_string(),
+            };
+            warn!(status = %status, reason = %reason, "Identity provider refused the authorization code");
+            return Err(Error::Authentication(format!("Identity provider refused the login: {}", reason)));
+        }
+
+        response
+            .json::<TokenResponse>()
+            .await?
+            .id_token
+            .ok_or_else(|| Error::Authentication("Identity provider returned no ID token".to_string()))
+    }
+
+    async fn validate_id_token(
+        &self,
+        metadata: &ProviderMetadata,
+        id_token: &str,
+        nonce: &str,
+    ) -> Result<OidcIdentity> {
+        let header = decode_header(id_token).map_err(invalid_id_token)?;
+        if !ALLOWED_ALGORITHMS.contains(&header.alg) {
+            return Err(Error::Authentication(format!(
+                "ID token signature algorithm {:?} is not accepted",
+                header.alg
+            )));
+        }
+
+        let key = self.signing_key(metadata, header.kid.as_deref()).await?;
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&metadata.issuer]);
+        validation.set_audience(&[&self.config.client_id]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+        validation.leeway = ID_TOKEN_LEEWAY;
+
+        let mut claims = decode::<IdTokenClaims>(id_token, &key, &validation)
+            .map_err(invalid_id_token)?
+            .claims;
+
+        if claims.nonce.as_deref() != Some(nonce) {
+            return Err(Error::Authentication("ID token nonce does not match the login".to_string()));
+        }
+        if claims.azp.as_deref().is_some_and(|azp| azp != self.config.client_id) {
+            return Err(Error::Authentication("ID token was issued to another client".to_string()));
+        }
+
+        let groups = claims
+            .other
+            .remove(&self.config.groups_claim)
+            .map(string_list)
+            .unwrap_or_default();
+
+        Ok(OidcIdentity {
+            issuer: metadata.issuer.clone(),
+            subject: claims.sub,
+            email: claims.email,
+            email_verified: claims.email_verified.as_ref().is_some_and(is_true),
+            given_name: claims.given_name,
+            family_name: claims.family_name,
+            name: claims.name,
+            preferred_username: claims.preferred_username,
+            groups,
+        })
+    }
+}
+
+#[async_trait]
+impl OidcProvider for OidcClient {
+    #[instrument(skip(self))]
+    async fn authorization_request(&self) -> Result<OidcAuthorization> {
+        let metadata = self.metadata().await?;
+
+        let login = OidcLoginState {
+            state: random_token(),
+            nonce: random_token(),
+            code_verifier: random_token(),
+            expires_at: chrono::Utc::now() + chrono::Duration::seconds(self.config.login_expiration),
+        };
+        let scope = self.config.scopes.join(" ");
+        let url = Url::parse_with_params(
+            &metadata.authorization_endpoint,
+            &[
+                ("response_type", "code"),
+                ("client_id", self.config.client_id.as_str()),
+                ("redirect_uri", self.config.redirect_uri.as_str()),
+                ("scope", scope.as_str()),
+                ("state", login.state.as_str()),
+                ("nonce", login.nonce.as_str()),
+                ("code_challenge", code_challenge(&login.code_verifier).as_str()),
+                ("code_challenge_method", "S256"),
+            ],
+        )
+        .map_err(|e| Error::Internal(format!("Invalid authorization endpoint: {}", e)))?;
+
+        Ok(OidcAuthorization {
+            authorization_url: url.into(),
+            login,
+        })
+    }
+
+    #[instrument(skip(self, code, login))]
+    async fn exchange_code(&self, code: &str, login: &OidcLoginState) -> Result<OidcIdentity> {
+        let metadata = self.metadata().await?;
+        let id_token = self.redeem_code(&metadata, code, &login.code_verifier).await?;
+        self.validate_id_token(&metadata, &id_token, &login.nonce).await
+    }
+}
+
+/// 256 random bits, base64url encoded: valid as state, nonce and PKCE verifier
+fn random_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// The S256 PKCE challenge for `verifier` (RFC 7636)
+fn code_challenge(verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+/// Without a key ID the set must hold exactly one signing key
+fn find_key<'a>(keys: &'a [Jwk], kid: Option<&str>) -> Option<&'a Jwk> {
+    let mut signing_keys = keys
+        .iter()
+        .filter(|key| key.common.public_key_use != Some(PublicKeyUse::Encryption));
+
+    match kid {
+        Some(kid) => signing_keys.find(|key| key.common.key_id.as_deref() == Some(kid)),
+        None => match (signing_keys.next(), signing_keys.next()) {
+            (Some(key), None) => Some(key),
+            _ => None,
+        },
+    }
+}
+
+/// Client credentials are form-encoded before basic auth (RFC 6749 section 2.3.1)
+fn form_encode(value: &str) -> String {
+    url::form_urlencoded::byte_serialize(value.as_bytes()).collect()
+}
+
+fn string_list(value: Value) -> Vec<String> {
+    match value {
+        Value::String(group) => vec![group],
+        Value::Array(groups) => groups
+            .into_iter()
+            .filter_map(|group| match group {
+                Value::String(group) => Some(group),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn is_true(value: &Value) -> bool {
+    match value {
+        Value::Bool(flag) => *flag,
+        Value::String(flag) => flag.eq_ignore_ascii_case("true"),
+        _ => false,
+    }
+}
+
+fn invalid_id_token(e: jsonwebtoken::errors::Error) -> Error {
+    warn!(error = %e, "Rejected ID token");
+    Error::Authentication("Invalid ID token".to_string())
+}
@@ -0,0 +1,820 @@
+use super::upload_digests::UploadDigests;
+use async_trait::async_trait;
+use aws_sdk_s3::{
+    config::{Credentials, Region},
+    error::{DisplayErrorContext, ProvideErrorMetadata},
+    presigning::PresigningConfig,
+    primitives::ByteStream,
+    types::{CompletedMultipartUpload, CompletedPart},
+    Client,
+};
+use kingshare_core::{config::S3Config, Error, Result};
+use kingshare_domain::{
+    services::{
+        FileReader, FileStream, FileUpload, PresignedUpload, StorageService, StoredFile,
+        StoredObject,
+    },
+    value_objects::ByteRange,
+};
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+use tokio::io::AsyncReadExt;
+use tokio_util::io::ReaderStream;
+use tracing::{info, instrument, warn};
+
+const OBJECTS_PREFIX: &str = "objects/";
+const UPLOADS_PREFIX: &str = "uploads/";
+const QUARANTINE_PREFIX: &str = "objects/quarantine/"; // Kept under objects/ so orphan scans still see it
+const PART_SIZE: usize = 8 * 1024 * 1024;
+const MIN_PART_SIZE: usize = 5 * 1024 * 1024; // S3 minimum for every part but the last
+const IO_BUFFER_SIZE: usize = 64 * 1024;
+
+/// `StorageService` backed by any S3-compatible object store (AWS S3, MinIO, ...)
+#[derive(Debug, Clone)]
+pub struct S3StorageService {
+    client: Client,
+    bucket: String,
+    max_file_size: u64,
+    upload_digests: UploadDigests,
+}
+
+/// Resumable upload state encoded in the opaque handle: `<object id>:<S3 upload id>`
+struct MultipartHandle {
+    key: String,
+    tail_key: String,
+    upload_id: String,
+}
+
+impl S3StorageService {
+    pub fn new(config: &S3Config, max_file_size: u64) -> Self {
+        let credentials = Credentials::new(
+            config.access_key_id.clone(),
+            config.secret_access_key.clone(),
+            None,
+            None,
+            "kingshare-config",
+        );
+
+        let mut builder = aws_sdk_s3::Config::builder()
+            .region(Region::new(config.region.clone()))
+            .credentials_provider(credentials)
+            .force_path_style(config.force_path_style);
+
+        if let Some(endpoint) = &config.endpoint {
+            builder = builder.endpoint_url(endpoint);
+        }
+
+        Self {
+            client: Client::from_conf(builder.build()),
+            bucket: config.bucket.clone(),
+            max_file_size,
+            upload_digests: UploadDigests::default(),
+        }
+    }
+
+    /// Verify the bucket is reachable, creating it if it does not exist yet
+    #[instrument(skip(self))]
+    pub async fn ensure_bucket(&self) -> Result<()> {
+        match self.client.head_bucket().bucket(&self.bucket).send().await {
+            Ok(_) => Ok(()),
+            Err(e) if e.as_service_error().map(|e| e.is_not_found()).unwrap_or(false) => {
+                self.client
+                    .create_bucket()
+                    .bucket(&self.bucket)
+                    .send()
+                    .await
+                    .map_err(|e| Self::s3_error("create bucket", e))?;
+
+                info!(bucket = %self.bucket, "Storage bucket created");
+                Ok(())
+            }
+            Err(e) => Err(Self::s3_error("check bucket", e)),
+        }
+    }
+
+    fn s3_error<E: std::error::Error>(action: &str, error: E) -> Error {
+        Error::Internal(format!("Failed to {}: {}", action, DisplayErrorContext(error)))
+    }
+
+    fn new_object_key() -> (uuid::Uuid, String) {
+        let object_id = uuid::Uuid::new_v4();
+        (object_id, format!("{}{}", OBJECTS_PREFIX, object_id))
+    }
+
+    fn validate_key(path: &str) -> Result<&str> {
+        // Security check: only objects written by this service may be addressed
+        if !path.starts_with(OBJECTS_PREFIX) || path.contains("..") {
+            return Err(Error::BadRequest("Invalid file path".to_string()));
+        }
+        Ok(path)
+    }
+
+    fn parse_handle(handle: &str) -> Result<MultipartHandle> {
+        let (object_id, upload_id) = handle
+            .split_once(':')
+            .ok_or_else(|| Error::BadRequest("Invalid upload handle".to_string()))?;
+
+        let object_id = uuid::Uuid::parse_str(object_id)
+            .map_err(|_| Error::BadRequest("Invalid upload handle".to_string()))?;
+
+        Ok(MultipartHandle {
+            key: format!("{}{}", OBJECTS_PREFIX, object_id),
+            tail_key: format!("{}{}.tail", UPLOADS_PREFIX, object_id),
+            upload_id: upload_id.to_string(),
+        })
+    }
+
+    async fn read_part(reader: &mut FileReader, buffer: &mut Vec<u8>) -> Result<bool> {
+        buffer.clear();
+        (&mut *reader)
+            .take(PART_SIZE as u64)
+            .read_to_end(buffer)
+            .await
+            .map_err(|e| Error::BadRequest(format!("Failed to read file data: {}", e)))?;
+
+        // A short part means the reader is exhausted
+        Ok(buffer.len() < PART_SIZE)
+    }
+
+    async fn put_object(&self, key: &str, content_type: &str, data: Vec<u8>) -> Result<()> {
+        self.client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .content_type(content_type)
+            .body(ByteStream::from(data))
+            .send()
+            .await
+            .map_err(|e| Self::s3_error("store object", e))?;
+        Ok(())
+    }
+
+    async fn create_multipart(&self, key: &str, content_type: &str) -> Result<String> {
+        let output = self
+            .client
+            .create_multipart_upload()
+            .bucket(&self.bucket)
+            .key(key)
+            .content_type(content_type)
+            .send()
+            .await
+            .map_err(|e| Self::s3_error("start multipart upload", e))?;
+
+        output
+            .upload_id()
+            .map(str::to_string)
+            .ok_or_else(|| Error::Internal("Multipart upload returned no upload id".to_string()))
+    }
+
+    async fn upload_part(
+        &self,
+        key: &str,
+        upload_id: &str,
+        part_number: i32,
+        data: Vec<u8>,
+    ) -> Result<CompletedPart> {
+        let output = self
+            .client
+            .upload_part()
+            .bucket(&self.bucket)
+            .key(key)
+            .upload_id(upload_id)
+            .part_number(part_number)
+            .body(ByteStream::from(data))
+            .send()
+            .await
+            .map_err(|e| Self::s3_error("upload part", e))?;
+
+        Ok(CompletedPart::builder()
+            .part_number(part_number)
+            .set_e_tag(output.e_tag().map(str::to_string))
+            .build())
+    }
+
+    async fn complete_multipart(
+        &self,
+        key: &str,
+        upload_id: &str,
+        parts: Vec<CompletedPart>,
+    ) -> Result<()> {
+        self.client
+            .complete_multipart_upload()
+            .bucket(&self.bucket)
+            .key(key)
+            .upload_id(upload_id)
+            .multipart_upload(
+                CompletedMultipartUpload::builder()
+                    .set_parts(Some(parts))
+                    .build(),
+            )
+            .send()
+            .await
+            .map_err(|e| Self::s3_error("complete multipart upload", e))?;
+        Ok(())
+    }
+
+    async fn abort_multipart(&self, key: &str, upload_id: &str) -> Result<()> {
+        match self
+            .client
+            .abort_multipart_upload()
+            .bucket(&self.bucket)
+            .key(key)
+            .upload_id(upload_id)
+            .send()
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(e) if e.as_service_error().map(|e| e.is_no_such_upload()).unwrap_or(false) => Ok(()),
+            Err(e) => Err(Self::s3_error("abort multipart upload", e)),
+        }
+    }
+
+    async fn uploaded_size(&self, handle: &MultipartHandle) -> Result<(Vec<CompletedPart>, u64)> {
+        let mut parts = Vec::new();
+        let mut size = 0u64;
+        let mut marker: Option<String> = None;
+
+        loop {
+            let output = self
+                .client
+                .list_parts()
+                .bucket(&self.bucket)
+                .key(&handle.key)
+                .upload_id(&handle.upload_id)
+                .set_part_number_marker(marker.take())
+                .send()
+                .await
+                .map_err(|e| match e.code() {
+                    Some("NoSuchUpload") => Error::NotFound("Upload not found".to_string()),
+                    _ => Self::s3_error("list upload parts", e),
+                })?;
+
+            for part in output.parts() {
+                size += part.size().unwrap_or(0) as u64;
+                parts.push(
+                    CompletedPart::builder()
+                        .set_part_number(part.part_number())
+                        .set_e_tag(part.e_tag().map(str::to_string))
+                        .build(),
+                );
+            }
+
+            match output.next_part_number_marker() {
+                Some(next) if output.is_truncated().unwrap_or(false) => {
+                    marker = Some(next.to_string());
+                }
+                _ => break,
+            }
+        }
+
+        parts.sort_by_key(|part| part.part_number());
+        Ok((parts, size))
+    }
+
+    async fn read_tail(&self, handle: &MultipartHandle) -> Result<Vec<u8>> {
+        match self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(&handle.tail_key)
+            .send()
+            .await
+        {
+            Ok(output) => Ok(output
+                .body
+                .collect()
+                .await
+                .map_err(|e| Self::s3_error("read upload tail", e))?
+                .into_bytes()
+                .to_vec()),
+            Err(e) if e.as_service_error().map(|e| e.is_no_such_key()).unwrap_or(false) => {
+                Ok(Vec::new())
+            }
+            Err(e) => Err(Self::s3_error("read upload tail", e)),
+        }
+    }
+
+    async fn delete_object(&self, key: &str) -> Result<()> {
+        self.client
+            .delete_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(|e| Self::s3_error("delete object", e))?;
+        Ok(())
+    }
+
+    async fn checksum_object(&self, key: &str) -> Result<String> {
+        let output = self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(|e| Self::s3_error("read object", e))?;
+
+        let mut reader = output.body.into_async_read();
+        let mut hasher = Sha256::new();
+        let mut buffer = vec![0u8; IO_BUFFER_SIZE];
+
+        loop {
+            let read = reader.read(&mut buffer).await.map_err(|e| {
+                Error::Internal(format!("Failed to read object: {}", e))
+            })?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+        }
+
+        Ok(format!("{:x}", hasher.finalize()))
+    }
+
+    async fn stream_object(&self, key: &str, range: Option<ByteRange>) -> Result<FileStream> {
+        let key = Self::validate_key(key)?;
+
+        let output = self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .set_range(range.map(|range| format!("bytes={}-{}", range.start, range.end)))
+            .send()
+            .await
+            .map_err(|e| match e.as_service_error().map(|e| e.is_no_such_key()) {
+                Some(true) => Error::NotFound("File not found".to_string()),
+                _ => Self::s3_error("read object", e),
+            })?;
+
+        Ok(Box::pin(ReaderStream::with_capacity(
+            output.body.into_async_read(),
+            IO_BUFFER_SIZE,
+        )))
+    }
+
+    async fn write_stream(
+        &self,
+        key: &str,
+        content_type: &str,
+        mut reader: FileReader,
+        upload_id: &mut Option<String>,
+    ) -> Result<(u64, String)> {
+        let mut hasher = Sha256::new();
+        let mut buffer = Vec::with_capacity(PART_SIZE);
+        let mut parts = Vec::new();
+        let mut size = 0u64;
+
+        loop {
+            let eof = Self::read_part(&mut reader, &mut buffer).await?;
+
+            size += buffer.len() as u64;
+            if size > self.max_file_size {
+                return Err(Error::BadRequest(format!(
+                    "File size exceeds maximum allowed size {}",
+                    self.max_file_size
+                )));
+            }
+            hasher.update(&buffer);
+
+            // Small files go up in a single request
+            if eof && upload_id.is_none() {
+                self.put_object(key, content_type, std::mem::take(&mut buffer))
+                    .await?;
+                break;
+            }
+
+            if !buffer.is_empty() || parts.is_empty() {
+                let id = match upload_id {
+                    Some(id) => id.clone(),
+                    None => {
+                        let id = self.create_multipart(key, content_type).await?;
+                        upload_id.insert(id).clone()
+                    }
+                };
+                let part_number = parts.len() as i32 + 1;
+                parts.push(
+                    self.upload_part(key, &id, part_number, std::mem::take(&mut buffer))
+                        .await?,
+                );
+                buffer = Vec::with_capacity(PART_SIZE);
+            }
+
+            if eof {
+                if let Some(id) = upload_id.as_deref() {
+                    self.complete_multipart(key, id, parts).await?;
+                }
+                break;
+            }
+        }
+
+        Ok((size, format!("{:x}", hasher.finalize())))
+    }
+}
+
+#[async_trait]
+impl StorageService for S3StorageService {
+    #[instrument(skip(self, upload))]
+    async fn store_file(&self, upload: FileUpload) -> Result<StoredFile> {
+        // Validate file size
+        if upload.data.len() as u64 > self.max_file_size {
+            return Err(Error::BadRequest(format!(
+                "File size {} exceeds maximum allowed size {}",
+                upload.data.len(),
+                self.max_file_size
+            )));
+        }
+
+        let reader: FileReader = Box::pin(std::io::Cursor::new(upload.data));
+        self.store_file_stream(&upload.filename, &upload.content_type, reader)
+            .await
+    }
+
+    #[instrument(skip(self, reader))]
+    async fn store_file_stream(
+        &self,
+        _filename: &str,
+        content_type: &str,
+        reader: FileReader,
+    ) -> Result<StoredFile> {
+        let (_, key) = Self::new_object_key();
+        let mut upload_id = None;
+
+        let (size, checksum) = match self
+            .write_stream(&key, content_type, reader, &mut upload_id)
+            .await
+        {
+            Ok(result) => result,
+            Err(e) => {
+                if let Some(upload_id) = upload_id {
+                    let _ = self.abort_multipart(&key, &upload_id).await;
+                }
+                return Err(e);
+            }
+        };
+
+        info!(
+            checksum = %checksum,
+            key = %key,
+            size = size,
+            "File stored successfully"
+        );
+
+        Ok(StoredFile {
+            path: key,
+            size,
+            checksum,
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn get_file(&self, path: &str) -> Result<Vec<u8>> {
+        let key = Self::validate_key(path)?;
+
+        let output = self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(|e| match e.as_service_error().map(|e| e.is_no_such_key()) {
+                Some(true) => Error::NotFound("File not found".to_string()),
+                _ => Self::s3_error("read object", e),
+            })?;
+
+        let data = output
+            .body
+            .collect()
+            .await
+            .map_err(|e| Self::s3_error("read object", e))?;
+
+        Ok(data.into_bytes().to_vec())
+    }
+
+    #[instrument(skip(self))]
+    async fn get_file_stream(&self, path: &str) -> Result<FileStream> {
+        self.stream_object(path, None).await
+    }
+
+    #[instrument(skip(self))]
+    async fn get_file_range_stream(&self, path: &str, range: ByteRange) -> Result<FileStream> {
+        self.stream_object(path, Some(range)).await
+    }
+
+    #[instrument(skip(self))]
+    async fn delete_file(&self, path: &str) -> Result<()> {
+        let key = Self::validate_key(path)?;
+
+        // S3 deletes are idempotent, so a missing object is not an error
+        self.delete_object(key).await?;
+
+        info!(key = %key, "File deleted successfully");
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn file_exists(&self, path: &str) -> Result<bool> {
+        let key = Self::validate_key(path)?;
+
+        match self.client.head_object().bucket(&self.bucket).key(key).send().await {
+            Ok(_) => Ok(true),
+            Err(e) if e.as_service_error().map(|e| e.is_not_found()).unwrap_or(false) => Ok(false),
+            Err(e) => Err(Self::s3_error("check object", e)),
+        }
+    }
+
+    #[instrument(skip(self))]
+    async fn get_file_size(&self, path: &str) -> Result<u64> {
+        let key = Self::validate_key(path)?;
+
+        let output = self
+            .client
+            .head_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(|e| match e.as_service_error().map(|e| e.is_not_found()) {
+                Some(true) => Error::NotFound("File not found".to_string()),
+                _ => Self::s3_error("check object", e),
+            })?;
+
+        Ok(output.content_length().unwrap_or(0).max(0) as u64)
+    }
+
+    async fn calculate_checksum(&self, data: &[u8]) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(data);
+        format!("{:x}", hasher.finalize())
+    }
+
+    #[instrument(skip(self))]
+    async fn list_files(&self) -> Result<Vec<StoredObject>> {
+        let mut files = Vec::new();
+        let mut continuation_token: Option<String> = None;
+
+        loop {
+            let output = self
+                .client
+                .list_objects_v2()
+                .bucket(&self.bucket)
+                .prefix(OBJECTS_PREFIX)
+                .set_continuation_token(continuation_token.take())
+                .send()
+                .await
+                .map_err(|e| Self::s3_error("list objects", e))?;
+
+            for object in output.contents() {
+                let Some(key) = object.key() else { continue };
+                let last_modified = object
+                    .last_modified()
+                    .and_then(|date| chrono::DateTime::from_timestamp(date.secs(), date.subsec_nanos()))
+                    .unwrap_or_else(chrono::Utc::now);
+
+                files.push(StoredObject {
+                    path: key.to_string(),
+                    size: object.size().unwrap_or(0).max(0) as u64,
+                    last_modified,
+                });
+            }
+
+            match output.next_continuation_token() {
+                Some(token) if output.is_truncated().unwrap_or(false) => {
+                    continuation_token = Some(token.to_string());
+                }
+                _ => break,
+            }
+        }
+
+        Ok(files)
+    }
+
+    #[instrument(skip(self))]
+    async fn quarantine_file(&self, path: &str) -> Result<String> {
+        let key = Self::validate_key(path)?;
+        if key.starts_with(QUARANTINE_PREFIX) {
+            return Ok(key.to_string());
+        }
+
+        // S3 has no rename: copy, then drop the original
+        let target = format!("{}{}", QUARANTINE_PREFIX, &key[OBJECTS_PREFIX.len()..]);
+        self.client
+            .copy_object()
+            .bucket(&self.bucket)
+            .copy_source(format!("{}/{}", self.bucket, key))
+            .key(&target)
+            .send()
+            .await
+            .map_err(|e| Self::s3_error("quarantine object", e))?;
+        self.delete_object(key).await?;
+
+        warn!(key = %key, quarantine_key = %target, "Object moved to quarantine");
+        Ok(target)
+    }
+
+    async fn rotate_encryption_keys(&self) -> Result<u64> {
+        // Objects rely on the bucket's server-side encryption; there are no data keys here
+        Ok(0)
+    }
+
+    #[instrument(skip(self))]
+    async fn presigned_get_url(&self, path: &str, expires_in: Duration) -> Result<Option<String>> {
+        let key = Self::validate_key(path)?;
+
+        let presigning = PresigningConfig::expires_in(expires_in)
+            .map_err(|e| Error::BadRequest(format!("Invalid URL expiration: {}", e)))?;
+
+        let request = self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .presigned(presigning)
+            .await
+            .map_err(|e| Self::s3_error("presign download", e))?;
+
+        Ok(Some(request.uri().to_string()))
+    }
+
+    #[instrument(skip(self))]
+    async fn presigned_put_url(
+        &self,
+        _filename: &str,
+        content_type: &str,
+        expires_in: Duration,
+    ) -> Result<Option<PresignedUpload>> {
+        let (_, key) = Self::new_object_key();
+
+        let presigning = PresigningConfig::expires_in(expires_in)
+            .map_err(|e| Error::BadRequest(format!("Invalid URL expiration: {}", e)))?;
+
+        let request = self
+            .client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(&key)
+            .content_type(content_type)
+            .presigned(presigning)
+            .await
+            .map_err(|e| Self::s3_error("presign upload", e))?;
+
+        Ok(Some(PresignedUpload {
+            url: request.uri().to_string(),
+            path: key,
+        }))
+    }
+
+    #[instrument(skip(self))]
+    async fn begin_upload(&self, _filename: &str, content_type: &str) -> Result<String> {
+        let (object_id, key) = Self::new_object_key();
+        let upload_id = self.create_multipart(&key, content_type).await?;
+
+        info!(key = %key, "Multipart upload started");
+        Ok(format!("{}:{}", object_id, upload_id))
+    }
+
+    #[instrument(skip(self, reader))]
+    async fn append_upload_chunk(
+        &self,
+        handle_str: &str,
+        offset: u64,
+        mut reader: FileReader,
+        limit: u64,
+    ) -> Result<u64> {
+        let handle = Self::parse_handle(handle_str)?;
+
+        let (parts, parts_size) = self.uploaded_size(&handle).await?;
+        let mut tail = self.read_tail(&handle).await?;
+        let current_offset = parts_size + tail.len() as u64;
+
+        if offset != current_offset {
+            return Err(Error::Conflict(format!(
+                "Upload offset mismatch: expected {}, got {}",
+                current_offset, offset
+            )));
+        }
+
+        // Chunks are buffered in a tail object until they add up to a valid
+        // part size, so clients can resume with arbitrarily small chunks.
+        // A large chunk is uploaded part by part as it arrives.
+        let mut part_number = parts.len() as i32;
+        let mut tail_stored = !tail.is_empty();
+        let mut new_offset = current_offset;
+        let mut buffer = vec![0u8; IO_BUFFER_SIZE];
+        loop {
+            let read = reader
+                .read(&mut buffer)
+                .await
+                .map_err(|e| Error::BadRequest(format!("Failed to read upload chunk: {}", e)))?;
+            if read == 0 {
+                break;
+            }
+
+            if new_offset - current_offset + read as u64 > limit {
+                return Err(Error::BadRequest(format!(
+                    "Chunk exceeds remaining upload length {}",
+                    limit
+                )));
+            }
+            if new_offset + read as u64 > self.max_file_size {
+                return Err(Error::BadRequest(format!(
+                    "File size exceeds maximum allowed size {}",
+                    self.max_file_size
+                )));
+            }
+
+            tail.extend_from_slice(&buffer[..read]);
+            self.upload_digests.update(handle_str, new_offset, &buffer[..read]);
+            new_offset += read as u64;
+
+            if tail.len() >= PART_SIZE {
+                part_number += 1;
+                self.upload_part(&handle.key, &handle.upload_id, part_number, std::mem::take(&mut tail))
+                    .await?;
+                // The stored tail is part of what was just uploaded
+                if tail_stored {
+                    self.delete_object(&handle.tail_key).await?;
+                    tail_stored = false;
+                }
+            }
+        }
+
+        if tail.len() >= MIN_PART_SIZE {
+            self.upload_part(&handle.key, &handle.upload_id, part_number + 1, tail)
+                .await?;
+            if tail_stored {
+                self.delete_object(&handle.tail_key).await?;
+            }
+        } else if !tail.is_empty() {
+            self.put_object(&handle.tail_key, "application/octet-stream", tail)
+                .await?;
+        }
+
+        Ok(new_offset)
+    }
+
+    #[instrument(skip(self))]
+    async fn upload_offset(&self, handle: &str) -> Result<u64> {
+        let handle = Self::parse_handle(handle)?;
+
+        let (_, parts_size) = self.uploaded_size(&handle).await?;
+        let tail = self.read_tail(&handle).await?;
+
+        Ok(parts_size + tail.len() as u64)
+    }
+
+    #[instrument(skip(self))]
+    async fn complete_upload(&self, handle_str: &str, _filename: &str) -> Result<StoredFile> {
+        let handle = Self::parse_handle(handle_str)?;
+
+        let (mut parts, mut size) = self.uploaded_size(&handle).await?;
+        let tail = self.read_tail(&handle).await?;
+
+        // The buffered remainder becomes the final part, which may be small
+        if !tail.is_empty() || parts.is_empty() {
+            size += tail.len() as u64;
+            let part_number = parts.len() as i32 + 1;
+            parts.push(
+                self.upload_part(&handle.key, &handle.upload_id, part_number, tail)
+                    .await?,
+            );
+        }
+
+        self.complete_multipart(&handle.key, &handle.upload_id, parts)
+            .await?;
+        let _ = self.delete_object(&handle.tail_key).await;
+
+        // S3 does not expose a whole-object SHA-256; read the object back only
+        // when this process did not hash every chunk as it arrived
+        let checksum = match self.upload_digests.finish(handle_str, size) {
+            Some(checksum) => checksum,
+            None => self.checksum_object(&handle.key).await?,
+        };
+
+        info!(
+            checksum = %checksum,
+            key = %handle.key,
+            size = size,
+            "File stored successfully"
+        );
+
+        Ok(StoredFile {
+            path: handle.key,
+            size,
+            checksum,
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn abort_upload(&self, handle: &str) -> Result<()> {
+        self.upload_digests.forget(handle);
+        let handle = Self::parse_handle(handle)?;
+
+        self.abort_multipart(&handle.key, &handle.upload_id).await?;
+        self.delete_object(&handle.tail_key).await?;
+
+        Ok(())
+    }
+}
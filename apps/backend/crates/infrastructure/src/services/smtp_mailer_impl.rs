@@ -0,0 +1,109 @@
+use async_trait::async_trait;
+use kingshare_core::{
+    config::{SmtpConfig, SmtpSecurity},
+    Error, Result,
+};
+use kingshare_domain::services::{EmailMessage, Mailer};
+use lettre::{
+    message::{header::ContentType, Mailbox, MultiPart},
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use std::time::Duration;
+use tracing::{info, instrument};
+
+fn mail_error(action: &str, error: impl std::fmt::Display) -> Error {
+    Error::Internal(format!("Failed to {}: {}", action, error))
+}
+
+pub(crate) fn parse_mailbox(address: &str) -> Result<Mailbox> {
+    address
+        .parse()
+        .map_err(|e| Error::Validation(format!("Invalid email address '{}': {}", address, e)))
+}
+
+/// The MIME message for `message`, multipart when it has an HTML body
+pub(crate) fn build_message(from: &Mailbox, message: &EmailMessage) -> Result<Message> {
+    let builder = Message::builder()
+        .from(from.clone())
+        .to(parse_mailbox(&message.to)?)
+        .subject(&message.subject);
+
+    let built = match &message.html_body {
+        Some(html) => builder.multipart(MultiPart::alternative_plain_html(
+            message.text_body.clone(),
+            html.clone(),
+        )),
+        None => builder
+            .header(ContentType::TEXT_PLAIN)
+            .body(message.text_body.clone()),
+    };
+
+    built.map_err(|e| mail_error("build email", e))
+}
+
+/// `Mailer` that relays through an SMTP server
+#[derive(Clone)]
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl std::fmt::Debug for SmtpMailer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SmtpMailer")
+            .field("from", &self.from)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SmtpMailer {
+    pub fn new(config: &SmtpConfig, from_address: &str) -> Result<Self> {
+        let builder = match config.security {
+            SmtpSecurity::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.host),
+            SmtpSecurity::StartTls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host)
+                .map_err(|e| mail_error("configure SMTP", e))?,
+            SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.host)
+                .map_err(|e| mail_error("configure SMTP", e))?,
+        };
+
+        let mut builder = builder
+            .port(config.port)
+            .timeout(Some(Duration::from_secs(config.timeout_seconds)));
+        if let Some(username) = &config.username {
+            builder = builder.credentials(Credentials::new(
+                username.clone(),
+                config.password.clone().unwrap_or_default(),
+            ));
+        }
+
+        Ok(Self {
+            transport: builder.build(),
+            from: parse_mailbox(from_address)?,
+        })
+    }
+
+    /// Check that the server accepts connections
+    pub async fn ping(&self) -> Result<()> {
+        match self.transport.test_connection().await {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(Error::Internal("SMTP server did not accept the connection".to_string())),
+            Err(e) => Err(mail_error("connect to SMTP server", e)),
+        }
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    #[instrument(skip(self, message), fields(to = %message.to))]
+    async fn send(&self, message: &EmailMessage) -> Result<()> {
+        let email = build_message(&self.from, message)?;
+        self.transport
+            .send(email)
+            .await
+            .map_err(|e| mail_error("send email", e))?;
+
+        info!(subject = %message.subject, "Email sent");
+        Ok(())
+    }
+}
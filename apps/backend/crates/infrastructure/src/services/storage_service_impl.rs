@@ -1,21 +1,73 @@
+use super::encryption::{ChunkCipher, KeyEnvelope, KeyRing, CHUNK_SIZE, TAG_SIZE};
+use super::upload_digests::UploadDigests;
 use async_trait::async_trait;
+use bytes::Bytes;
+use futures_util::TryStreamExt;
 use kingshare_core::{Error, Result};
-use kingshare_domain::services::{FileUpload, StorageService, StoredFile};
+use kingshare_domain::{
+    services::{
+        FileReader, FileStream, FileUpload, PresignedUpload, StorageService, StoredFile,
+        StoredObject,
+    },
+    value_objects::ByteRange,
+};
 use sha2::{Digest, Sha256};
+use std::io::SeekFrom;
 use std::path::{Path, PathBuf};
-use tokio::fs;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::{
+    fs,
+    io::{AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWriteExt},
+};
+use tokio_util::io::{ReaderStream, StreamReader};
 use tracing::{info, instrument, warn};
 
+const PARTIAL_UPLOADS_DIR: &str = ".uploads";
+const KEYS_DIR: &str = ".keys";
+const QUARANTINE_DIR: &str = ".quarantine";
+const KEY_SUFFIX: &str = ".key";
+const KEY_TMP_SUFFIX: &str = ".key.tmp";
+const IO_BUFFER_SIZE: usize = 64 * 1024;
+const RECORD_HEADER_SIZE: u64 = 4;
+
+/// Content spooled into a partial file, not yet moved to its final path
+struct SpooledBlob {
+    size: u64,
+    checksum: String,
+    envelope: Option<KeyEnvelope>,
+}
+
+/// Complete records found in an encrypted partial upload
+struct RecordScan {
+    size: u64,
+    records: u64,
+    valid_len: u64,
+}
+
+/// Position within an encrypted blob while streaming it out
+struct DecryptState {
+    file: fs::File,
+    cipher: ChunkCipher,
+    envelope: KeyEnvelope,
+    index: u64,
+    skip: usize,
+    remaining: u64,
+    buffer: Vec<u8>,
+}
+
 #[derive(Debug, Clone)]
 pub struct LocalStorageService {
     storage_path: PathBuf,
     max_file_size: u64,
+    key_ring: Option<Arc<KeyRing>>,
+    upload_digests: UploadDigests,
 }
 
 impl LocalStorageService {
     pub fn new(storage_path: impl AsRef<Path>, max_file_size: u64) -> Result<Self> {
         let storage_path = storage_path.as_ref().to_path_buf();
-        
+
         // Create storage directory if it doesn't exist
         std::fs::create_dir_all(&storage_path)
             .map_err(|e| Error::Internal(format!("Failed to create storage directory: {}", e)))?;
@@ -23,49 +75,261 @@ impl LocalStorageService {
         Ok(Self {
             storage_path,
             max_file_size,
+            key_ring: None,
+            upload_digests: UploadDigests::default(),
         })
     }
 
+    /// Encrypt everything written from now on. Blobs stored before encryption
+    /// was enabled have no key envelope and are still served as plaintext.
+    pub fn with_encryption(mut self, key_ring: KeyRing) -> Self {
+        self.key_ring = Some(Arc::new(key_ring));
+        self
+    }
+
     fn generate_file_path(&self, checksum: &str, extension: &str) -> PathBuf {
         // Create a directory structure based on checksum for better distribution
         let prefix = &checksum[..2];
         let subdir = &checksum[2..4];
-        
+
         self.storage_path
             .join(prefix)
             .join(subdir)
             .join(format!("{}{}", checksum, extension))
     }
 
-    fn extract_extension(filename: &str) -> String {
-        Path::new(filename)
-            .extension()
-            .and_then(|ext| ext.to_str())
-            .map(|ext| format!(".{}", ext))
-            .unwrap_or_default()
+    fn partial_upload_path(&self, handle: &str) -> Result<PathBuf> {
+        // Handles are UUIDs we generated; anything else could escape the storage directory
+        let id = uuid::Uuid::parse_str(handle)
+            .map_err(|_| Error::BadRequest("Invalid upload handle".to_string()))?;
+
+        Ok(self
+            .storage_path
+            .join(PARTIAL_UPLOADS_DIR)
+            .join(format!("{}.part", id)))
     }
-}
 
-#[async_trait]
-impl StorageService for LocalStorageService {
-    #[instrument(skip(self, upload))]
-    async fn store_file(&self, upload: FileUpload) -> Result<StoredFile> {
-        // Validate file size
-        if upload.data.len() as u64 > self.max_file_size {
-            return Err(Error::BadRequest(format!(
-                "File size {} exceeds maximum allowed size {}",
-                upload.data.len(),
-                self.max_file_size
-            )));
+    /// Key envelopes live in a tree of their own so they can never collide with
+    /// a blob, whatever extension the uploaded file had.
+    fn key_path(&self, blob_path: &Path) -> Result<PathBuf> {
+        let relative = match blob_path.strip_prefix(&self.storage_path) {
+            Ok(relative) => relative.to_path_buf(),
+            Err(_) => {
+                let canonical_storage = self.storage_path.canonicalize().map_err(|e| {
+                    Error::Internal(format!("Failed to canonicalize storage path: {}", e))
+                })?;
+                blob_path
+                    .strip_prefix(&canonical_storage)
+                    .map_err(|_| Error::BadRequest("Invalid file path".to_string()))?
+                    .to_path_buf()
+            }
+        };
+
+        let mut key_path = self.storage_path.join(KEYS_DIR).join(relative).into_os_string();
+        key_path.push(KEY_SUFFIX);
+        Ok(PathBuf::from(key_path))
+    }
+
+    /// The blob a key envelope (or an interrupted rewrite of one) belongs to
+    fn envelope_blob_path(&self, key_path: &Path) -> Option<PathBuf> {
+        let relative = key_path.strip_prefix(self.storage_path.join(KEYS_DIR)).ok()?;
+        let relative = relative.to_str()?;
+        let blob = relative
+            .strip_suffix(KEY_TMP_SUFFIX)
+            .or_else(|| relative.strip_suffix(KEY_SUFFIX))?;
+        Some(self.storage_path.join(blob))
+    }
+
+    async fn read_envelope(&self, blob_path: &Path) -> Result<Option<KeyEnvelope>> {
+        let key_path = self.key_path(blob_path)?;
+
+        match fs::read(&key_path).await {
+            Ok(contents) => serde_json::from_slice(&contents)
+                .map(Some)
+                .map_err(|e| Error::Internal(format!("Invalid key envelope: {}", e))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(Error::Internal(format!("Failed to read key envelope: {}", e))),
+        }
+    }
+
+    async fn write_envelope(&self, blob_path: &Path, envelope: &KeyEnvelope) -> Result<()> {
+        let key_path = self.key_path(blob_path)?;
+
+        if let Some(parent) = key_path.parent() {
+            fs::create_dir_all(parent).await.map_err(|e| {
+                Error::Internal(format!("Failed to create key directory: {}", e))
+            })?;
+        }
+
+        let contents = serde_json::to_vec(envelope)?;
+
+        // Write aside and rename so a crash never leaves a half-written envelope
+        let mut tmp_path = key_path.clone().into_os_string();
+        tmp_path.push(".tmp");
+        fs::write(&tmp_path, contents).await.map_err(|e| {
+            Error::Internal(format!("Failed to write key envelope: {}", e))
+        })?;
+        fs::rename(&tmp_path, &key_path).await.map_err(|e| {
+            Error::Internal(format!("Failed to write key envelope: {}", e))
+        })?;
+
+        Ok(())
+    }
+
+    async fn remove_envelope(&self, blob_path: &Path) {
+        if let Ok(key_path) = self.key_path(blob_path) {
+            let _ = fs::remove_file(&key_path).await;
+        }
+    }
+
+    /// The data key of an encrypted blob, or `None` for a plaintext one
+    async fn open_cipher(&self, blob_path: &Path) -> Result<Option<(KeyEnvelope, ChunkCipher)>> {
+        let Some(envelope) = self.read_envelope(blob_path).await? else {
+            return Ok(None);
+        };
+
+        let key_ring = self.key_ring.as_ref().ok_or_else(|| {
+            Error::Internal("File is encrypted but no master key is configured".to_string())
+        })?;
+        let cipher = key_ring.open(&envelope)?;
+
+        Ok(Some((envelope, cipher)))
+    }
+
+    async fn open_stored_file(path: &Path) -> Result<fs::File> {
+        fs::File::open(path).await.map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => Error::NotFound("File not found".to_string()),
+            _ => Error::Internal(format!("Failed to open file: {}", e)),
+        })
+    }
+
+    async fn checksum_file(path: &Path) -> Result<String> {
+        let mut file = fs::File::open(path).await.map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => Error::NotFound("Upload not found".to_string()),
+            _ => Error::Internal(format!("Failed to open upload: {}", e)),
+        })?;
+
+        let mut hasher = Sha256::new();
+        let mut buffer = vec![0u8; IO_BUFFER_SIZE];
+
+        loop {
+            let read = file.read(&mut buffer).await.map_err(|e| {
+                Error::Internal(format!("Failed to read upload: {}", e))
+            })?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+        }
+
+        Ok(format!("{:x}", hasher.finalize()))
+    }
+
+    /// Fill `buffer` unless the reader runs out first
+    async fn read_chunk<R: AsyncRead + Unpin + ?Sized>(
+        reader: &mut R,
+        buffer: &mut [u8],
+    ) -> std::io::Result<usize> {
+        let mut filled = 0;
+        while filled < buffer.len() {
+            let read = reader.read(&mut buffer[filled..]).await?;
+            if read == 0 {
+                break;
+            }
+            filled += read;
+        }
+        Ok(filled)
+    }
+
+    async fn write_stream(&self, path: &Path, mut reader: FileReader) -> Result<SpooledBlob> {
+        let mut file = fs::File::create(path).await.map_err(|e| {
+            Error::Internal(format!("Failed to create file: {}", e))
+        })?;
+
+        let encryption = self.key_ring.as_ref().map(|key_ring| key_ring.generate()).transpose()?;
+
+        let mut hasher = Sha256::new();
+        let mut current = vec![0u8; CHUNK_SIZE];
+        let mut next = vec![0u8; CHUNK_SIZE];
+        let mut size = 0u64;
+        let mut index = 0u64;
+
+        let mut len = Self::read_chunk(&mut reader, &mut current).await.map_err(|e| {
+            Error::BadRequest(format!("Failed to read file data: {}", e))
+        })?;
+
+        loop {
+            size += len as u64;
+            if size > self.max_file_size {
+                return Err(Error::BadRequest(format!(
+                    "File size exceeds maximum allowed size {}",
+                    self.max_file_size
+                )));
+            }
+            hasher.update(&current[..len]);
+
+            // A short read means the reader is exhausted; otherwise look ahead so the
+            // final chunk can be marked as such before it is encrypted
+            let next_len = if len == CHUNK_SIZE {
+                Self::read_chunk(&mut reader, &mut next).await.map_err(|e| {
+                    Error::BadRequest(format!("Failed to read file data: {}", e))
+                })?
+            } else {
+                0
+            };
+            let last = next_len == 0;
+
+            let written = match &encryption {
+                Some((_, cipher)) => {
+                    let ciphertext = cipher
+                        .encrypt_chunk(index, last, &current[..len])
+                        .map_err(|e| Error::Internal(format!("Failed to encrypt file: {}", e)))?;
+                    file.write_all(&ciphertext).await
+                }
+                None => file.write_all(&current[..len]).await,
+            };
+            written.map_err(|e| {
+                Error::Internal(format!("Failed to write file to storage: {}", e))
+            })?;
+
+            if last {
+                break;
+            }
+            std::mem::swap(&mut current, &mut next);
+            len = next_len;
+            index += 1;
         }
 
-        // Calculate checksum
-        let checksum = self.calculate_checksum(&upload.data).await;
-        
-        // Generate file path
-        let extension = Self::extract_extension(&upload.filename);
-        let file_path = self.generate_file_path(&checksum, &extension);
-        
+        file.flush().await.map_err(|e| {
+            Error::Internal(format!("Failed to write file to storage: {}", e))
+        })?;
+
+        Ok(SpooledBlob {
+            size,
+            checksum: format!("{:x}", hasher.finalize()),
+            envelope: encryption.map(|(envelope, _)| KeyEnvelope { size, ..envelope }),
+        })
+    }
+
+    async fn promote_to_storage(
+        &self,
+        part_path: &Path,
+        filename: &str,
+        spooled: SpooledBlob,
+    ) -> Result<StoredFile> {
+        let SpooledBlob { size, checksum, envelope } = spooled;
+
+        // Generate file path. Every encrypted copy has its own data key, so copies
+        // can't share a path; the blob table deduplicates them instead.
+        let extension = Self::extract_extension(filename);
+        let file_path = match envelope {
+            Some(_) => self.generate_file_path(
+                &checksum,
+                &format!("-{}{}", uuid::Uuid::new_v4().simple(), extension),
+            ),
+            None => self.generate_file_path(&checksum, &extension),
+        };
+
         // Create directory structure
         if let Some(parent) = file_path.parent() {
             fs::create_dir_all(parent).await.map_err(|e| {
@@ -80,36 +344,42 @@ impl StorageService for LocalStorageService {
                 path = %file_path.display(),
                 "File already exists, using existing file"
             );
+            let _ = fs::remove_file(part_path).await;
         } else {
-            // Write file to storage
-            fs::write(&file_path, &upload.data).await.map_err(|e| {
-                Error::Internal(format!("Failed to write file to storage: {}", e))
-            })?;
+            // The envelope goes first so the blob is never visible without its key
+            if let Some(envelope) = &envelope {
+                self.write_envelope(&file_path, envelope).await?;
+            }
+
+            if let Err(e) = fs::rename(part_path, &file_path).await {
+                self.remove_envelope(&file_path).await;
+                return Err(Error::Internal(format!("Failed to move file into storage: {}", e)));
+            }
 
             info!(
                 checksum = %checksum,
                 path = %file_path.display(),
-                size = upload.data.len(),
+                size = size,
+                encrypted = envelope.is_some(),
                 "File stored successfully"
             );
         }
 
         Ok(StoredFile {
             path: file_path.to_string_lossy().to_string(),
-            size: upload.data.len() as u64,
+            size,
             checksum,
         })
     }
 
-    #[instrument(skip(self))]
-    async fn get_file(&self, path: &str) -> Result<Vec<u8>> {
+    fn resolve_stored_path(&self, path: &str) -> Result<PathBuf> {
         let file_path = Path::new(path);
-        
+
         // Security check: ensure path is within storage directory
         let canonical_storage = self.storage_path.canonicalize().map_err(|e| {
             Error::Internal(format!("Failed to canonicalize storage path: {}", e))
         })?;
-        
+
         let canonical_file = file_path.canonicalize().map_err(|_| {
             Error::NotFound("File not found".to_string())
         })?;
@@ -118,22 +388,286 @@ impl StorageService for LocalStorageService {
             return Err(Error::BadRequest("Invalid file path".to_string()));
         }
 
-        // Read file
-        fs::read(&file_path).await.map_err(|e| match e.kind() {
-            std::io::ErrorKind::NotFound => Error::NotFound("File not found".to_string()),
-            _ => Error::Internal(format!("Failed to read file: {}", e)),
-        })
+        Ok(canonical_file)
+    }
+
+    /// Decrypt `range` of an encrypted blob, reading only the chunks it covers
+    async fn decrypt_range(
+        mut file: fs::File,
+        envelope: KeyEnvelope,
+        cipher: ChunkCipher,
+        start: u64,
+        len: u64,
+    ) -> Result<FileStream> {
+        let chunk_size = envelope.chunk_size as u64;
+        let index = start / chunk_size;
+
+        file.seek(SeekFrom::Start(index * (chunk_size + TAG_SIZE as u64)))
+            .await
+            .map_err(|e| Error::Internal(format!("Failed to seek file: {}", e)))?;
+
+        let state = DecryptState {
+            file,
+            cipher,
+            envelope,
+            index,
+            skip: (start % chunk_size) as usize,
+            remaining: len,
+            buffer: Vec::new(),
+        };
+
+        Ok(Box::pin(futures_util::stream::try_unfold(state, |mut state| async move {
+            if state.remaining == 0 {
+                return Ok(None);
+            }
+
+            let chunk_len = state.envelope.chunk_len(state.index);
+            state.buffer.resize(chunk_len + TAG_SIZE, 0);
+            state.file.read_exact(&mut state.buffer).await?;
+
+            let last = state.index + 1 == state.envelope.chunk_count();
+            let plaintext = state.cipher.decrypt_chunk(state.index, last, &state.buffer)?;
+
+            let end = plaintext
+                .len()
+                .min(state.skip + usize::try_from(state.remaining).unwrap_or(usize::MAX));
+            let bytes = Bytes::from(plaintext).slice(state.skip..end);
+
+            state.remaining -= bytes.len() as u64;
+            state.skip = 0;
+            state.index += 1;
+            Ok(Some((bytes, state)))
+        })))
+    }
+
+    /// Read the header of every complete record in an encrypted partial upload.
+    /// A record torn by an interrupted write is left out.
+    async fn scan_records(part_path: &Path) -> Result<RecordScan> {
+        let mut file = fs::File::open(part_path).await.map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => Error::NotFound("Upload not found".to_string()),
+            _ => Error::Internal(format!("Failed to open upload: {}", e)),
+        })?;
+        let file_len = file
+            .metadata()
+            .await
+            .map_err(|e| Error::Internal(format!("Failed to get upload metadata: {}", e)))?
+            .len();
+
+        let mut scan = RecordScan { size: 0, records: 0, valid_len: 0 };
+        let mut header = [0u8; RECORD_HEADER_SIZE as usize];
+
+        while scan.valid_len + RECORD_HEADER_SIZE <= file_len {
+            file.read_exact(&mut header).await.map_err(|e| {
+                Error::Internal(format!("Failed to read upload: {}", e))
+            })?;
+
+            let len = u32::from_be_bytes(header) as u64;
+            let record_end = scan.valid_len + RECORD_HEADER_SIZE + len + TAG_SIZE as u64;
+            if record_end > file_len {
+                break;
+            }
+
+            file.seek(SeekFrom::Start(record_end)).await.map_err(|e| {
+                Error::Internal(format!("Failed to seek upload: {}", e))
+            })?;
+            scan.size += len;
+            scan.records += 1;
+            scan.valid_len = record_end;
+        }
+
+        Ok(scan)
+    }
+
+    /// Decrypt the first `records` records of an encrypted partial upload
+    fn record_stream(file: fs::File, cipher: ChunkCipher, records: u64) -> FileStream {
+        Box::pin(futures_util::stream::try_unfold(
+            (file, cipher, 0u64),
+            move |(mut file, cipher, index)| async move {
+                if index == records {
+                    return Ok(None);
+                }
+
+                let mut header = [0u8; RECORD_HEADER_SIZE as usize];
+                file.read_exact(&mut header).await?;
+
+                let mut ciphertext = vec![0u8; u32::from_be_bytes(header) as usize + TAG_SIZE];
+                file.read_exact(&mut ciphertext).await?;
+
+                let plaintext = cipher.decrypt_chunk(index, false, &ciphertext)?;
+                Ok(Some((Bytes::from(plaintext), (file, cipher, index + 1))))
+            },
+        ))
+    }
+
+    async fn walk_files(root: &Path, skip_dirs: &[&str]) -> Result<Vec<(PathBuf, std::fs::Metadata)>> {
+        let mut files = Vec::new();
+        let mut stack = vec![root.to_path_buf()];
+
+        while let Some(current_dir) = stack.pop() {
+            let mut entries = match fs::read_dir(&current_dir).await {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(Error::Internal(format!("Failed to read directory: {}", e))),
+            };
+
+            while let Some(entry) = entries.next_entry().await.map_err(|e| {
+                Error::Internal(format!("Failed to read directory entry: {}", e))
+            })? {
+                let path = entry.path();
+
+                if path.is_dir() {
+                    if path
+                        .file_name()
+                        .and_then(|name| name.to_str())
+                        .is_some_and(|name| skip_dirs.contains(&name))
+                    {
+                        continue;
+                    }
+                    stack.push(path);
+                } else if path.is_file() {
+                    let metadata = entry.metadata().await.map_err(|e| {
+                        Error::Internal(format!("Failed to get file metadata: {}", e))
+                    })?;
+                    files.push((path, metadata));
+                }
+            }
+        }
+
+        Ok(files)
+    }
+
+    fn stored_object(path: &Path, metadata: &std::fs::Metadata) -> StoredObject {
+        let last_modified = metadata
+            .modified()
+            .map(chrono::DateTime::<chrono::Utc>::from)
+            .unwrap_or_else(|_| chrono::Utc::now());
+
+        StoredObject {
+            path: path.to_string_lossy().to_string(),
+            size: metadata.len(),
+            last_modified,
+        }
+    }
+
+    fn extract_extension(filename: &str) -> String {
+        Path::new(filename)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| format!(".{}", ext))
+            .unwrap_or_default()
+    }
+}
+
+#[async_trait]
+impl StorageService for LocalStorageService {
+    #[instrument(skip(self, upload))]
+    async fn store_file(&self, upload: FileUpload) -> Result<StoredFile> {
+        // Validate file size
+        if upload.data.len() as u64 > self.max_file_size {
+            return Err(Error::BadRequest(format!(
+                "File size {} exceeds maximum allowed size {}",
+                upload.data.len(),
+                self.max_file_size
+            )));
+        }
+
+        let reader: FileReader = Box::pin(std::io::Cursor::new(upload.data));
+        self.store_file_stream(&upload.filename, &upload.content_type, reader)
+            .await
+    }
+
+    #[instrument(skip(self, reader))]
+    async fn store_file_stream(
+        &self,
+        filename: &str,
+        _content_type: &str,
+        reader: FileReader,
+    ) -> Result<StoredFile> {
+        // Spool into a partial file while hashing, then move it to its content address
+        let part_path = self.partial_upload_path(&uuid::Uuid::new_v4().to_string())?;
+
+        if let Some(parent) = part_path.parent() {
+            fs::create_dir_all(parent).await.map_err(|e| {
+                Error::Internal(format!("Failed to create directory structure: {}", e))
+            })?;
+        }
+
+        let spooled = match self.write_stream(&part_path, reader).await {
+            Ok(spooled) => spooled,
+            Err(e) => {
+                let _ = fs::remove_file(&part_path).await;
+                return Err(e);
+            }
+        };
+
+        self.promote_to_storage(&part_path, filename, spooled)
+            .await
+    }
+
+    #[instrument(skip(self))]
+    async fn get_file(&self, path: &str) -> Result<Vec<u8>> {
+        let file_path = self.resolve_stored_path(path)?;
+
+        if self.read_envelope(Path::new(path)).await?.is_none() {
+            // Read file
+            return fs::read(&file_path).await.map_err(|e| match e.kind() {
+                std::io::ErrorKind::NotFound => Error::NotFound("File not found".to_string()),
+                _ => Error::Internal(format!("Failed to read file: {}", e)),
+            });
+        }
+
+        let mut stream = self.get_file_stream(path).await?;
+        let mut data = Vec::new();
+        while let Some(chunk) = stream.try_next().await.map_err(|e| {
+            Error::Internal(format!("Failed to decrypt file: {}", e))
+        })? {
+            data.extend_from_slice(&chunk);
+        }
+        Ok(data)
+    }
+
+    #[instrument(skip(self))]
+    async fn get_file_stream(&self, path: &str) -> Result<FileStream> {
+        let file_path = self.resolve_stored_path(path)?;
+        let file = Self::open_stored_file(&file_path).await?;
+
+        match self.open_cipher(Path::new(path)).await? {
+            Some((envelope, cipher)) => {
+                let size = envelope.size;
+                Self::decrypt_range(file, envelope, cipher, 0, size).await
+            }
+            None => Ok(Box::pin(ReaderStream::with_capacity(file, IO_BUFFER_SIZE))),
+        }
+    }
+
+    #[instrument(skip(self))]
+    async fn get_file_range_stream(&self, path: &str, range: ByteRange) -> Result<FileStream> {
+        let file_path = self.resolve_stored_path(path)?;
+        let mut file = Self::open_stored_file(&file_path).await?;
+
+        if let Some((envelope, cipher)) = self.open_cipher(Path::new(path)).await? {
+            return Self::decrypt_range(file, envelope, cipher, range.start, range.len()).await;
+        }
+
+        file.seek(SeekFrom::Start(range.start)).await.map_err(|e| {
+            Error::Internal(format!("Failed to seek file: {}", e))
+        })?;
+
+        Ok(Box::pin(ReaderStream::with_capacity(
+            file.take(range.len()),
+            IO_BUFFER_SIZE,
+        )))
     }
 
     #[instrument(skip(self))]
     async fn delete_file(&self, path: &str) -> Result<()> {
         let file_path = Path::new(path);
-        
+
         // Security check: ensure path is within storage directory
         let canonical_storage = self.storage_path.canonicalize().map_err(|e| {
             Error::Internal(format!("Failed to canonicalize storage path: {}", e))
         })?;
-        
+
         if let Ok(canonical_file) = file_path.canonicalize() {
             if !canonical_file.starts_with(&canonical_storage) {
                 return Err(Error::BadRequest("Invalid file path".to_string()));
@@ -149,6 +683,9 @@ impl StorageService for LocalStorageService {
             _ => Error::Internal(format!("Failed to delete file: {}", e)),
         })?;
 
+        // Without the blob its wrapped data key is useless
+        self.remove_envelope(file_path).await;
+
         info!(path = %path, "File deleted successfully");
 
         // Try to remove empty parent directories
@@ -168,7 +705,12 @@ impl StorageService for LocalStorageService {
     #[instrument(skip(self))]
     async fn get_file_size(&self, path: &str) -> Result<u64> {
         let file_path = Path::new(path);
-        
+
+        // Ciphertext carries a tag per chunk; report what the client will receive
+        if let Some(envelope) = self.read_envelope(file_path).await? {
+            return Ok(envelope.size);
+        }
+
         let metadata = fs::metadata(file_path).await.map_err(|e| match e.kind() {
             std::io::ErrorKind::NotFound => Error::NotFound("File not found".to_string()),
             _ => Error::Internal(format!("Failed to get file metadata: {}", e)),
@@ -185,44 +727,346 @@ impl StorageService for LocalStorageService {
     }
 
     #[instrument(skip(self))]
-    async fn cleanup_orphaned_files(&self, valid_paths: Vec<String>) -> Result<u64> {
-        let valid_paths: std::collections::HashSet<String> = valid_paths.into_iter().collect();
-        let mut removed_count = 0;
+    async fn list_files(&self) -> Result<Vec<StoredObject>> {
+        // In-progress uploads are not blobs yet, and envelopes are listed separately
+        let mut files: Vec<_> = Self::walk_files(&self.storage_path, &[PARTIAL_UPLOADS_DIR, KEYS_DIR])
+            .await?
+            .iter()
+            .map(|(path, metadata)| Self::stored_object(path, metadata))
+            .collect();
 
-        // Walk through storage directory
-        let mut stack = vec![self.storage_path.clone()];
-        
-        while let Some(current_dir) = stack.pop() {
-            let mut entries = fs::read_dir(&current_dir).await.map_err(|e| {
-                Error::Internal(format!("Failed to read directory: {}", e))
+        // Envelopes left behind by a blob that no longer exists are orphans too
+        for (path, metadata) in Self::walk_files(&self.storage_path.join(KEYS_DIR), &[]).await? {
+            let blob_exists = self
+                .envelope_blob_path(&path)
+                .is_some_and(|blob_path| blob_path.exists());
+            if !blob_exists {
+                files.push(Self::stored_object(&path, &metadata));
+            }
+        }
+
+        Ok(files)
+    }
+
+    #[instrument(skip(self))]
+    async fn quarantine_file(&self, path: &str) -> Result<String> {
+        let canonical_file = self.resolve_stored_path(path)?;
+        let canonical_storage = self.storage_path.canonicalize().map_err(|e| {
+            Error::Internal(format!("Failed to canonicalize storage path: {}", e))
+        })?;
+
+        let relative = canonical_file
+            .strip_prefix(&canonical_storage)
+            .map_err(|_| Error::BadRequest("Invalid file path".to_string()))?;
+        if relative.starts_with(QUARANTINE_DIR) {
+            return Ok(path.to_string());
+        }
+
+        let file_path = Path::new(path);
+        let target = self.storage_path.join(QUARANTINE_DIR).join(relative);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).await.map_err(|e| {
+                Error::Internal(format!("Failed to create quarantine directory: {}", e))
             })?;
+        }
 
-            while let Some(entry) = entries.next_entry().await.map_err(|e| {
-                Error::Internal(format!("Failed to read directory entry: {}", e))
-            })? {
-                let path = entry.path();
-                
-                if path.is_dir() {
-                    stack.push(path);
-                } else if path.is_file() {
-                    let path_str = path.to_string_lossy().to_string();
-                    
-                    if !valid_paths.contains(&path_str) {
-                        match fs::remove_file(&path).await {
-                            Ok(_) => {
-                                removed_count += 1;
-                                info!(path = %path_str, "Orphaned file removed");
-                            }
-                            Err(e) => {
-                                warn!(path = %path_str, error = %e, "Failed to remove orphaned file");
-                            }
-                        }
+        fs::rename(file_path, &target).await.map_err(|e| {
+            Error::Internal(format!("Failed to quarantine file: {}", e))
+        })?;
+
+        // An encrypted blob's data key has to follow it
+        let key_path = self.key_path(file_path)?;
+        if key_path.exists() {
+            let target_key_path = self.key_path(&target)?;
+            let moved = async {
+                if let Some(parent) = target_key_path.parent() {
+                    fs::create_dir_all(parent).await?;
+                }
+                fs::rename(&key_path, &target_key_path).await
+            }
+            .await;
+
+            if let Err(e) = moved {
+                let _ = fs::rename(&target, file_path).await;
+                return Err(Error::Internal(format!(
+                    "Failed to move key envelope to quarantine: {}",
+                    e
+                )));
+            }
+        }
+
+        if let Some(parent) = file_path.parent() {
+            let _ = fs::remove_dir(parent).await; // Ignore errors for non-empty directories
+        }
+
+        let quarantine_path = target.to_string_lossy().to_string();
+        warn!(path = %path, quarantine_path = %quarantine_path, "File moved to quarantine");
+        Ok(quarantine_path)
+    }
+
+    #[instrument(skip(self))]
+    async fn rotate_encryption_keys(&self) -> Result<u64> {
+        let Some(key_ring) = &self.key_ring else {
+            return Ok(0);
+        };
+
+        // Only the small envelopes are rewritten; blob contents keep their data keys
+        let mut rewrapped_count = 0;
+        for (key_path, _) in Self::walk_files(&self.storage_path.join(KEYS_DIR), &[]).await? {
+            if !key_path.to_string_lossy().ends_with(KEY_SUFFIX) {
+                continue;
+            }
+            let Some(blob_path) = self.envelope_blob_path(&key_path) else {
+                continue;
+            };
+
+            let Some(envelope) = self.read_envelope(&blob_path).await? else {
+                // Deleted while we were scanning
+                continue;
+            };
+
+            match key_ring.rewrap(&envelope) {
+                Ok(Some(rewrapped)) => {
+                    self.write_envelope(&blob_path, &rewrapped).await?;
+                    rewrapped_count += 1;
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    warn!(path = %blob_path.display(), error = %e, "Failed to re-wrap data key");
+                }
+            }
+        }
+
+        info!(
+            key_id = %key_ring.current_key_id(),
+            rewrapped_count = rewrapped_count,
+            "Encryption key rotation completed"
+        );
+        Ok(rewrapped_count)
+    }
+
+    async fn presigned_get_url(&self, _path: &str, _expires_in: Duration) -> Result<Option<String>> {
+        // Local files are only reachable through the API
+        Ok(None)
+    }
+
+    async fn presigned_put_url(
+        &self,
+        _filename: &str,
+        _content_type: &str,
+        _expires_in: Duration,
+    ) -> Result<Option<PresignedUpload>> {
+        Ok(None)
+    }
+
+    #[instrument(skip(self))]
+    async fn begin_upload(&self, _filename: &str, _content_type: &str) -> Result<String> {
+        let handle = uuid::Uuid::new_v4().to_string();
+        let part_path = self.partial_upload_path(&handle)?;
+
+        if let Some(parent) = part_path.parent() {
+            fs::create_dir_all(parent).await.map_err(|e| {
+                Error::Internal(format!("Failed to create upload directory: {}", e))
+            })?;
+        }
+
+        // Chunks are encrypted as they arrive, so plaintext never sits on disk
+        if let Some(key_ring) = &self.key_ring {
+            let (envelope, _) = key_ring.generate()?;
+            self.write_envelope(&part_path, &envelope).await?;
+        }
+
+        fs::File::create(&part_path).await.map_err(|e| {
+            Error::Internal(format!("Failed to create upload file: {}", e))
+        })?;
+
+        info!(handle = %handle, "Resumable upload started");
+        Ok(handle)
+    }
+
+    #[instrument(skip(self, reader))]
+    async fn append_upload_chunk(
+        &self,
+        handle: &str,
+        offset: u64,
+        mut reader: FileReader,
+        limit: u64,
+    ) -> Result<u64> {
+        let part_path = self.partial_upload_path(handle)?;
+        let encryption = self.open_cipher(&part_path).await?;
+
+        let (current_size, scan) = match &encryption {
+            Some(_) => {
+                let scan = Self::scan_records(&part_path).await?;
+                (scan.size, Some(scan))
+            }
+            None => (self.upload_offset(handle).await?, None),
+        };
+
+        if current_size != offset {
+            return Err(Error::Conflict(format!(
+                "Upload offset mismatch: expected {}, got {}",
+                current_size, offset
+            )));
+        }
+
+        let mut file = fs::OpenOptions::new()
+            .append(true)
+            .open(&part_path)
+            .await
+            .map_err(|e| Error::Internal(format!("Failed to open upload file: {}", e)))?;
+
+        let (mut records, start_len) = match &scan {
+            Some(scan) => {
+                // Drop a record torn by an earlier interrupted write before appending
+                file.set_len(scan.valid_len).await.map_err(|e| {
+                    Error::Internal(format!("Failed to truncate upload file: {}", e))
+                })?;
+                (scan.records, scan.valid_len)
+            }
+            None => (0, current_size),
+        };
+
+        // Written in record-sized pieces as the body arrives, so a chunk is
+        // never held in memory whole
+        let mut new_size = current_size;
+        let mut buffer = vec![0u8; CHUNK_SIZE];
+        let written: Result<()> = async {
+            loop {
+                let read = Self::read_chunk(&mut reader, &mut buffer)
+                    .await
+                    .map_err(|e| Error::BadRequest(format!("Failed to read upload chunk: {}", e)))?;
+                if read == 0 {
+                    break;
+                }
+                let chunk = &buffer[..read];
+
+                if new_size - current_size + read as u64 > limit {
+                    return Err(Error::BadRequest(format!(
+                        "Chunk exceeds remaining upload length {}",
+                        limit
+                    )));
+                }
+                if new_size + read as u64 > self.max_file_size {
+                    return Err(Error::BadRequest(format!(
+                        "File size {} exceeds maximum allowed size {}",
+                        new_size + read as u64,
+                        self.max_file_size
+                    )));
+                }
+
+                match &encryption {
+                    Some((_, cipher)) => {
+                        let ciphertext = cipher
+                            .encrypt_chunk(records, false, chunk)
+                            .map_err(|e| Error::Internal(format!("Failed to encrypt upload chunk: {}", e)))?;
+
+                        let mut record = Vec::with_capacity(RECORD_HEADER_SIZE as usize + ciphertext.len());
+                        record.extend_from_slice(&(read as u32).to_be_bytes());
+                        record.extend_from_slice(&ciphertext);
+                        file.write_all(&record).await.map_err(|e| {
+                            Error::Internal(format!("Failed to write upload chunk: {}", e))
+                        })?;
+                        records += 1;
+                    }
+                    None => {
+                        file.write_all(chunk).await.map_err(|e| {
+                            Error::Internal(format!("Failed to write upload chunk: {}", e))
+                        })?;
                     }
                 }
+
+                self.upload_digests.update(handle, new_size, chunk);
+                new_size += read as u64;
             }
+
+            file.flush().await.map_err(|e| {
+                Error::Internal(format!("Failed to flush upload chunk: {}", e))
+            })
+        }
+        .await;
+
+        // A failed chunk leaves nothing behind, so the upload stays at the
+        // offset it was claimed at
+        if let Err(e) = written {
+            self.upload_digests.forget(handle);
+            file.set_len(start_len).await.map_err(|e| {
+                Error::Internal(format!("Failed to truncate upload file: {}", e))
+            })?;
+            return Err(e);
+        }
+
+        Ok(new_size)
+    }
+
+    #[instrument(skip(self))]
+    async fn upload_offset(&self, handle: &str) -> Result<u64> {
+        let part_path = self.partial_upload_path(handle)?;
+
+        if self.read_envelope(&part_path).await?.is_some() {
+            return Ok(Self::scan_records(&part_path).await?.size);
         }
 
-        info!(removed_count = removed_count, "Orphaned files cleanup completed");
-        Ok(removed_count)
+        let metadata = fs::metadata(&part_path).await.map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => Error::NotFound("Upload not found".to_string()),
+            _ => Error::Internal(format!("Failed to get upload metadata: {}", e)),
+        })?;
+
+        Ok(metadata.len())
     }
-}
\ No newline at end of file
+
+    #[instrument(skip(self))]
+    async fn complete_upload(&self, handle: &str, filename: &str) -> Result<StoredFile> {
+        let part_path = self.partial_upload_path(handle)?;
+
+        let Some((_, cipher)) = self.open_cipher(&part_path).await? else {
+            let size = self.upload_offset(handle).await?;
+
+            // Chunks were hashed as they arrived; read the file back only when
+            // this process did not see all of them
+            let checksum = match self.upload_digests.finish(handle, size) {
+                Some(checksum) => checksum,
+                None => Self::checksum_file(&part_path).await?,
+            };
+            let spooled = SpooledBlob { size, checksum, envelope: None };
+
+            return self.promote_to_storage(&part_path, filename, spooled).await;
+        };
+
+        // Records were sized by the client's chunks; re-encrypt into the seekable
+        // blob layout, which hashes the plaintext on the way
+        self.upload_digests.forget(handle);
+        let scan = Self::scan_records(&part_path).await?;
+        let file = fs::File::open(&part_path).await.map_err(|e| {
+            Error::Internal(format!("Failed to open upload: {}", e))
+        })?;
+        let reader: FileReader = Box::pin(StreamReader::new(Self::record_stream(
+            file,
+            cipher,
+            scan.records,
+        )));
+
+        let stored = self.store_file_stream(filename, "", reader).await?;
+
+        self.remove_envelope(&part_path).await;
+        let _ = fs::remove_file(&part_path).await;
+
+        Ok(stored)
+    }
+
+    #[instrument(skip(self))]
+    async fn abort_upload(&self, handle: &str) -> Result<()> {
+        let part_path = self.partial_upload_path(handle)?;
+        self.upload_digests.forget(handle);
+        self.remove_envelope(&part_path).await;
+
+        match fs::remove_file(&part_path).await {
+            Ok(_) => {
+                info!(handle = %handle, "Resumable upload aborted");
+                Ok(())
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(Error::Internal(format!("Failed to remove upload file: {}", e))),
+        }
+    }
+}
@@ -0,0 +1,45 @@
+//! Text extraction from uploaded files, for search.
+//!
+//! Only types whose words can be read without rendering are handled: plain
+//! text and text-like formats, and PDFs with a text layer.
+
+use kingshare_core::{Error, Result};
+
+/// Text-like types besides `text/*`
+const TEXT_TYPES: &[&str] = &[
+    "application/json",
+    "application/xml",
+    "application/javascript",
+    "application/x-yaml",
+    "image/svg+xml",
+];
+
+/// Whether text is read from files of `mime_type`
+pub fn reads_text(mime_type: &str) -> bool {
+    let mime_type = essence(mime_type);
+    is_text_like(&mime_type) || mime_type == "application/pdf"
+}
+
+/// The text in `data`, or `None` when files of `mime_type` aren't read
+pub fn extract_text(mime_type: &str, data: &[u8]) -> Result<Option<String>> {
+    let mime_type = essence(mime_type);
+
+    if is_text_like(&mime_type) {
+        return Ok(Some(String::from_utf8_lossy(data).into_owned()));
+    }
+    if mime_type == "application/pdf" {
+        return pdf_extract::extract_text_from_mem(data)
+            .map(Some)
+            .map_err(|e| Error::BadRequest(format!("The PDF's text can't be read: {}", e)));
+    }
+    Ok(None)
+}
+
+/// The type without parameters such as `charset`, lowercased
+fn essence(mime_type: &str) -> String {
+    mime_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase()
+}
+
+fn is_text_like(mime_type: &str) -> bool {
+    mime_type.starts_with("text/") || TEXT_TYPES.contains(&mime_type)
+}
@@ -0,0 +1,63 @@
+//! Running SHA-256 of resumable uploads.
+//!
+//! Chunks are hashed as they are written so completing an upload does not
+//! have to read it back. The state lives in this process only: after a
+//! restart, or when chunks reach another instance, an upload has no digest
+//! and the stored bytes are hashed instead.
+
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+struct RunningDigest {
+    offset: u64,
+    hasher: Sha256,
+}
+
+#[derive(Clone, Default)]
+pub struct UploadDigests {
+    digests: Arc<Mutex<HashMap<String, RunningDigest>>>,
+}
+
+impl std::fmt::Debug for UploadDigests {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UploadDigests").field("uploads", &self.lock().len()).finish()
+    }
+}
+
+impl UploadDigests {
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, RunningDigest>> {
+        self.digests.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Hash `data` once it is written at `offset`. Bytes that do not carry on
+    /// where the digest left off drop it, since some were never seen.
+    pub fn update(&self, handle: &str, offset: u64, data: &[u8]) {
+        let mut digests = self.lock();
+        if offset == 0 {
+            digests.insert(handle.to_string(), RunningDigest { offset: 0, hasher: Sha256::new() });
+        }
+
+        match digests.get_mut(handle) {
+            Some(digest) if digest.offset == offset => {
+                digest.hasher.update(data);
+                digest.offset += data.len() as u64;
+            }
+            Some(_) => {
+                digests.remove(handle);
+            }
+            None => {}
+        }
+    }
+
+    /// The hex digest when exactly `size` bytes were hashed. The upload is
+    /// forgotten either way.
+    pub fn finish(&self, handle: &str, size: u64) -> Option<String> {
+        let digest = self.lock().remove(handle)?;
+        (digest.offset == size).then(|| format!("{:x}", digest.hasher.finalize()))
+    }
+
+    pub fn forget(&self, handle: &str) {
+        self.lock().remove(handle);
+    }
+}
@@ -4,7 +4,7 @@ use dashmap::DashMap;
 use futures_util::{sink::SinkExt, stream::StreamExt};
 use kingshare_core::{Error, Id, Result};
 use kingshare_domain::{
-    entities::{WebSocketConnection, WebSocketMessage},
+    entities::{WebSocketConnection, WebSocketMessage, SESSION_METADATA_KEY},
     services::{ConnectionStats, WebSocketService},
 };
 use std::{
@@ -43,18 +43,24 @@ impl InMemoryWebSocketService {
         websocket: WebSocket,
         connection_id: String,
         user_id: Option<Id>,
+        session_id: Option<Id>,
     ) -> Result<()> {
         let (mut ws_sender, mut ws_receiver) = websocket.split();
         let (tx, mut rx): (ConnectionSender, ConnectionReceiver) = mpsc::unbounded_channel();
 
         // If user is authenticated, add to connections
         if let Some(user_id) = user_id {
+            let mut metadata = HashMap::new();
+            if let Some(session_id) = session_id {
+                metadata.insert(SESSION_METADATA_KEY.to_string(), session_id.to_string());
+            }
+
             let connection = WebSocketConnection {
                 connection_id: connection_id.clone(),
                 user_id,
                 connected_at: chrono::Utc::now(),
                 last_activity: chrono::Utc::now(),
-                metadata: HashMap::new(),
+                metadata,
             };
 
             self.add_connection_internal(connection, tx.clone()).await?;
@@ -83,6 +89,13 @@ impl InMemoryWebSocketService {
                 if ws_sender.send(Message::Text(json_message)).await.is_err() {
                     break;
                 }
+
+                // The session behind this connection is gone; hang up once the
+                // client has been told why
+                if matches!(message, WebSocketMessage::SessionRevoked { .. }) {
+                    let _ = ws_sender.send(Message::Close(None)).await;
+                    break;
+                }
             }
         });
 
@@ -151,7 +164,7 @@ impl InMemoryWebSocketService {
         self.user_connections
             .entry(user_id)
             .or_insert_with(Vec::new)
-            .push(connection_id);
+            .push(connection_id.clone());
 
         info!(
             connection_id = %connection_id,
@@ -312,6 +325,39 @@ impl WebSocketService for InMemoryWebSocketService {
         Ok(())
     }
 
+    #[instrument(skip(self, session_ids))]
+    async fn close_session_connections(&self, user_id: Id, session_ids: Vec<Id>) -> Result<u64> {
+        let revoked: Vec<(String, Id)> = self
+            .get_user_connections(user_id)
+            .await?
+            .into_iter()
+            .filter_map(|connection| {
+                let session_id = connection.session_id()?;
+                session_ids
+                    .contains(&session_id)
+                    .then_some((connection.connection_id, session_id))
+            })
+            .collect();
+
+        for (connection_id, session_id) in &revoked {
+            // The send task closes the socket after delivering this
+            if let Err(e) = self
+                .send_to_connection(connection_id, WebSocketMessage::SessionRevoked { session_id: *session_id })
+                .await
+            {
+                warn!("Failed to notify connection {} of revoked session: {}", connection_id, e);
+            }
+            self.remove_connection(connection_id).await?;
+        }
+
+        info!(
+            user_id = %user_id,
+            closed = revoked.len(),
+            "Closed WebSocket connections of revoked sessions"
+        );
+        Ok(revoked.len() as u64)
+    }
+
     #[instrument(skip(self))]
     async fn get_online_users(&self) -> Result<Vec<Id>> {
         Ok(self.user_connections.iter().map(|entry| *entry.key()).collect())
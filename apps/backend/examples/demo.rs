@@ -1,11 +1,11 @@
 use kingshare_core::config::Config;
 use kingshare_infrastructure::{
-    DefaultFileService, JwtAuthService, LocalStorageService,
+    DefaultFileService, InMemoryTokenRepository, JwtAuthService, LocalStorageService,
     PostgresFileRepository, PostgresShareRepository, PostgresUserRepository,
 };
 use kingshare_application::services::{FileService, ShareService, UserService};
 use kingshare_domain::{
-    entities::{CreateUserRequest, CreateShareRequest, AccessShareRequest},
+    entities::{AccessShareRequest, ClientInfo, CreateShareRequest, CreateUserRequest},
 };
 use std::sync::Arc;
 use tempfile::TempDir;
@@ -33,7 +33,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     info!("Initializing services...");
 
     // Create domain services
-    let auth_service = Arc::new(JwtAuthService::new(config.auth.clone()));
+    let auth_service = Arc::new(JwtAuthService::new(
+        config.auth.clone(),
+        Arc::new(InMemoryTokenRepository::new()),
+    ));
     let storage_service = Arc::new(LocalStorageService::new(storage_path, 100 * 1024 * 1024)?);
     let file_domain_service = Arc::new(DefaultFileService::new(100 * 1024 * 1024));
 
@@ -99,6 +102,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         "demo@example.com",
         "demouser",
         "User",
+        &ClientInfo::default(),
     ).await?;
     
     info!("JWT tokens generated: expires_in={} seconds", tokens.expires_in);
@@ -1,11 +1,11 @@
 use kingshare_core::config::Config;
 use kingshare_infrastructure::{
-    Database, DefaultFileService, JwtAuthService, LocalStorageService,
+    Database, DefaultFileService, InMemoryTokenRepository, JwtAuthService, LocalStorageService,
     PostgresFileRepository, PostgresShareRepository, PostgresUserRepository,
 };
 use kingshare_application::services::{FileService, ShareService, UserService};
 use kingshare_domain::{
-    entities::{CreateUserRequest, CreateShareRequest, AccessShareRequest},
+    entities::{AccessShareRequest, ClientInfo, CreateShareRequest, CreateUserRequest},
 };
 use std::sync::Arc;
 use tempfile::TempDir;
@@ -33,7 +33,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     info!("\n🔧 Test 1: Service Initialization");
     
     // Create domain services
-    let auth_service = Arc::new(JwtAuthService::new(config.auth.clone()));
+    let auth_service = Arc::new(JwtAuthService::new(
+        config.auth.clone(),
+        Arc::new(InMemoryTokenRepository::new()),
+    ));
     let storage_service = Arc::new(LocalStorageService::new(storage_path, 100 * 1024 * 1024)?);
     let file_domain_service = Arc::new(DefaultFileService::new(100 * 1024 * 1024));
 
@@ -56,6 +59,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         "test@example.com",
         "testuser",
         "User",
+        &ClientInfo::default(),
     ).await?;
     info!("✅ JWT tokens generated (expires in {} seconds)", tokens.expires_in);
 
@@ -180,7 +184,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Test 8: Token Refresh Flow
     info!("\n🔄 Test 8: Token Refresh Flow");
     
-    let new_tokens = auth_service.refresh_token(&tokens.refresh_token).await?;
+    let grant = auth_service.redeem_refresh_token(&tokens.refresh_token).await?;
+    let new_tokens = auth_service
+        .rotate_tokens(&grant, &claims.email, &claims.username, &claims.role)
+        .await?;
     assert_ne!(tokens.access_token, new_tokens.access_token, "New access token should be different");
     info!("✅ Token refresh successful");
 
@@ -198,6 +205,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     assert!(revoked_result.is_err(), "Revoked token should fail verification");
     info!("✅ Revoked token properly rejected");
 
+    let session_result = auth_service.verify_token(&new_tokens.access_token).await;
+    assert!(session_result.is_err(), "Tokens from the revoked session should fail verification");
+    info!("✅ Revoking a token ended its session");
+
     // Test 10: Cleanup
     info!("\n🧹 Test 10: Cleanup");
     
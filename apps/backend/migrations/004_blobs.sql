@@ -0,0 +1,30 @@
+-- Content-addressed blobs shared by file rows with identical content
+CREATE TABLE blobs (
+    checksum VARCHAR(64) PRIMARY KEY,
+    storage_path VARCHAR(500) NOT NULL UNIQUE,
+    size BIGINT NOT NULL CHECK (size >= 0),
+    ref_count BIGINT NOT NULL DEFAULT 0 CHECK (ref_count >= 0),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Garbage collection only ever looks at unreferenced blobs
+CREATE INDEX idx_blobs_unreferenced ON blobs(updated_at) WHERE ref_count = 0;
+
+CREATE TRIGGER update_blobs_updated_at BEFORE UPDATE ON blobs
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+-- Backfill from existing files. Identical content stored under different
+-- extensions collapses onto one blob; the leftover copies become orphans.
+INSERT INTO blobs (checksum, storage_path, size, ref_count)
+SELECT checksum, MIN(storage_path), MAX(size), COUNT(*)
+FROM files
+GROUP BY checksum;
+
+UPDATE files f
+SET storage_path = b.storage_path
+FROM blobs b
+WHERE f.checksum = b.checksum AND f.storage_path <> b.storage_path;
+
+ALTER TABLE files
+    ADD CONSTRAINT fk_files_blob FOREIGN KEY (checksum) REFERENCES blobs(checksum);
@@ -0,0 +1,31 @@
+-- Rendered thumbnails, one row per size of each image blob
+CREATE TABLE thumbnails (
+    checksum VARCHAR(64) NOT NULL REFERENCES blobs(checksum) ON DELETE CASCADE,
+    size VARCHAR(16) NOT NULL CHECK (size IN ('small', 'medium', 'large')),
+    storage_path VARCHAR(500) NOT NULL,
+    thumbnail_checksum VARCHAR(64) NOT NULL REFERENCES blobs(checksum),
+    content_type VARCHAR(100) NOT NULL,
+    byte_size BIGINT NOT NULL CHECK (byte_size >= 0),
+    width INTEGER NOT NULL CHECK (width > 0),
+    height INTEGER NOT NULL CHECK (height > 0),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (checksum, size)
+);
+
+CREATE INDEX idx_thumbnails_thumbnail_checksum ON thumbnails(thumbnail_checksum);
+
+-- Each row holds a reference on its thumbnail blob. When the source blob is
+-- garbage collected the rows cascade away, and this hands those references
+-- back so the thumbnail content is collected in turn.
+CREATE OR REPLACE FUNCTION release_thumbnail_blob()
+RETURNS TRIGGER AS $$
+BEGIN
+    UPDATE blobs
+    SET ref_count = ref_count - 1
+    WHERE checksum = OLD.thumbnail_checksum AND ref_count > 0;
+    RETURN OLD;
+END;
+$$ language 'plpgsql';
+
+CREATE TRIGGER release_thumbnail_blob AFTER DELETE ON thumbnails
+    FOR EACH ROW EXECUTE FUNCTION release_thumbnail_blob();
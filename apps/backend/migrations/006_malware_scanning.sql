@@ -0,0 +1,12 @@
+-- Malware scan state. Files uploaded before scanning existed are treated as
+-- clean; everything uploaded from now on starts out pending.
+ALTER TABLE files
+    ADD COLUMN scan_status VARCHAR(16) NOT NULL DEFAULT 'clean'
+        CHECK (scan_status IN ('pending', 'clean', 'infected')),
+    ADD COLUMN scan_signature VARCHAR(255),
+    ADD COLUMN scanned_at TIMESTAMPTZ;
+
+ALTER TABLE files ALTER COLUMN scan_status SET DEFAULT 'pending';
+
+-- Rescans after an outage only look at files still waiting for a verdict
+CREATE INDEX idx_files_scan_pending ON files(checksum) WHERE scan_status = 'pending';
@@ -0,0 +1,24 @@
+-- Storage quotas. Usage is kept as a counter next to the quota so a
+-- reservation is a single conditional UPDATE and concurrent uploads can't
+-- both squeeze into the last bytes. A NULL user quota falls back to the
+-- configured default.
+ALTER TABLE users
+    ADD COLUMN storage_quota BIGINT CHECK (storage_quota >= 0),
+    ADD COLUMN storage_used BIGINT NOT NULL DEFAULT 0 CHECK (storage_used >= 0);
+
+ALTER TABLE drives
+    ADD CONSTRAINT chk_drives_storage_quota CHECK (storage_quota >= 0),
+    ADD CONSTRAINT chk_drives_storage_used CHECK (storage_used >= 0);
+
+-- Backfill usage from what is already stored
+UPDATE users u
+SET storage_used = usage.total
+FROM (SELECT owner_id, SUM(size) AS total FROM files GROUP BY owner_id) usage
+WHERE u.id = usage.owner_id;
+
+-- Trashed items don't count until they are restored
+UPDATE drives d
+SET storage_used = COALESCE(
+    (SELECT SUM(size) FROM drive_items WHERE drive_id = d.id AND NOT is_trashed),
+    0
+);
@@ -0,0 +1,35 @@
+-- Refresh-token families: one per sign-in. Each rotation adds a token to the
+-- family; replaying a spent token revokes the whole family.
+CREATE TABLE refresh_token_families (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_rotated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    revoked_at TIMESTAMPTZ,
+    revoked_reason VARCHAR(32) CHECK (revoked_reason IN ('logout', 'reuse_detected'))
+);
+
+CREATE INDEX idx_refresh_token_families_user_id ON refresh_token_families(user_id);
+
+-- Spent tokens are kept until they expire so a replay can be recognised
+CREATE TABLE refresh_tokens (
+    id UUID PRIMARY KEY, -- the token's jti
+    family_id UUID NOT NULL REFERENCES refresh_token_families(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    issued_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL,
+    consumed_at TIMESTAMPTZ
+);
+
+CREATE INDEX idx_refresh_tokens_family_id ON refresh_tokens(family_id);
+CREATE INDEX idx_refresh_tokens_expires_at ON refresh_tokens(expires_at);
+
+-- Access tokens denied before their natural expiry, e.g. on logout
+CREATE TABLE revoked_access_tokens (
+    jti UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    expires_at TIMESTAMPTZ NOT NULL,
+    revoked_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_revoked_access_tokens_expires_at ON revoked_access_tokens(expires_at);
@@ -0,0 +1,15 @@
+-- A refresh-token family is the server side of one sign-in; record which
+-- client it belongs to so users can review and end their sessions
+ALTER TABLE refresh_token_families RENAME COLUMN last_rotated_at TO last_used_at;
+
+ALTER TABLE refresh_token_families
+    ADD COLUMN device VARCHAR(100),
+    ADD COLUMN user_agent TEXT,
+    ADD COLUMN ip_address VARCHAR(45);
+
+ALTER TABLE refresh_token_families DROP CONSTRAINT refresh_token_families_revoked_reason_check;
+ALTER TABLE refresh_token_families ADD CONSTRAINT refresh_token_families_revoked_reason_check
+    CHECK (revoked_reason IN ('logout', 'reuse_detected', 'session_ended'));
+
+CREATE INDEX idx_refresh_token_families_active ON refresh_token_families(user_id, last_used_at DESC)
+    WHERE revoked_at IS NULL;
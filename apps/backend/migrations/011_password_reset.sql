@@ -0,0 +1,4 @@
+-- A password reset signs out every session of the account
+ALTER TABLE refresh_token_families DROP CONSTRAINT refresh_token_families_revoked_reason_check;
+ALTER TABLE refresh_token_families ADD CONSTRAINT refresh_token_families_revoked_reason_check
+    CHECK (revoked_reason IN ('logout', 'reuse_detected', 'session_ended', 'password_reset'));
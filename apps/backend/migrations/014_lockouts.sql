@@ -0,0 +1,27 @@
+-- Failed login and share password attempts, counted per account email,
+-- share and client address
+CREATE TABLE failed_attempts (
+    kind VARCHAR(16) NOT NULL,
+    subject VARCHAR(255) NOT NULL,
+    failures INTEGER NOT NULL,
+    last_failure_at TIMESTAMPTZ NOT NULL,
+    blocked_until TIMESTAMPTZ,
+    locked BOOLEAN NOT NULL DEFAULT FALSE,
+    PRIMARY KEY (kind, subject)
+);
+
+CREATE INDEX idx_failed_attempts_blocked_until ON failed_attempts(blocked_until) WHERE blocked_until IS NOT NULL;
+
+-- Security events for administrators to review
+CREATE TABLE security_events (
+    id UUID PRIMARY KEY,
+    event_type VARCHAR(50) NOT NULL,
+    actor_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    subject VARCHAR(255) NOT NULL,
+    ip_address VARCHAR(45),
+    details JSONB NOT NULL DEFAULT '{}',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_security_events_created_at ON security_events(created_at DESC);
+CREATE INDEX idx_security_events_event_type ON security_events(event_type, created_at DESC);
@@ -0,0 +1,3 @@
+-- Share passwords are stored as argon2 hashes. Existing plaintext passwords
+-- keep working and are re-hashed by the server when it starts.
+ALTER TABLE shares RENAME COLUMN password TO password_hash;
@@ -0,0 +1,73 @@
+-- Teams group users; roles assigned per team apply to the team's drives
+CREATE TABLE teams (
+    id UUID PRIMARY KEY,
+    name VARCHAR(100) NOT NULL UNIQUE,
+    description VARCHAR(500),
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+ALTER TABLE drives ADD COLUMN team_id UUID REFERENCES teams(id) ON DELETE SET NULL;
+CREATE INDEX idx_drives_team_id ON drives(team_id) WHERE team_id IS NOT NULL;
+
+-- Named sets of permissions. System roles ship with the server and can't be
+-- changed; admin, user and guest back the account roles.
+CREATE TABLE roles (
+    id UUID PRIMARY KEY,
+    name VARCHAR(64) NOT NULL UNIQUE,
+    description VARCHAR(500),
+    permissions TEXT[] NOT NULL DEFAULT '{}',
+    is_system BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Roles held by users, everywhere or within one drive or team
+CREATE TABLE role_assignments (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    role_id UUID NOT NULL REFERENCES roles(id) ON DELETE CASCADE,
+    scope_type VARCHAR(16) NOT NULL CHECK (scope_type IN ('global', 'drive', 'team')),
+    scope_id UUID,
+    granted_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK ((scope_type = 'global') = (scope_id IS NULL))
+);
+
+CREATE UNIQUE INDEX idx_role_assignments_unique
+    ON role_assignments(user_id, role_id, scope_type, COALESCE(scope_id, '00000000-0000-0000-0000-000000000000'));
+CREATE INDEX idx_role_assignments_role_id ON role_assignments(role_id);
+
+INSERT INTO roles (id, name, description, permissions, is_system) VALUES
+    (gen_random_uuid(), 'admin', 'Everything, everywhere', ARRAY[
+        'files:read', 'files:write', 'shares:manage',
+        'drive:read', 'drive:comment', 'drive:write', 'drive:share', 'drive:manage',
+        'documents:read', 'documents:write', 'spreadsheets:read', 'spreadsheets:write',
+        'forms:read', 'forms:write', 'forms:responses:read',
+        'users:manage', 'roles:manage', 'quotas:manage', 'storage:manage', 'security:manage'
+    ], TRUE),
+    (gen_random_uuid(), 'user', 'Full use of what the user owns', ARRAY[
+        'files:read', 'files:write', 'shares:manage',
+        'drive:read', 'drive:comment', 'drive:write', 'drive:share', 'drive:manage',
+        'documents:read', 'documents:write', 'spreadsheets:read', 'spreadsheets:write',
+        'forms:read', 'forms:write', 'forms:responses:read'
+    ], TRUE),
+    (gen_random_uuid(), 'guest', 'Read-only use of what the user owns', ARRAY[
+        'files:read', 'drive:read', 'drive:comment',
+        'documents:read', 'spreadsheets:read', 'forms:read'
+    ], TRUE),
+    (gen_random_uuid(), 'viewer', 'Read content', ARRAY[
+        'files:read', 'drive:read', 'documents:read', 'spreadsheets:read', 'forms:read'
+    ], TRUE),
+    (gen_random_uuid(), 'editor', 'Read and change content', ARRAY[
+        'files:read', 'files:write',
+        'drive:read', 'drive:comment', 'drive:write',
+        'documents:read', 'documents:write', 'spreadsheets:read', 'spreadsheets:write',
+        'forms:read', 'forms:write'
+    ], TRUE),
+    (gen_random_uuid(), 'manager', 'Change content, share it and manage the drive', ARRAY[
+        'files:read', 'files:write', 'shares:manage',
+        'drive:read', 'drive:comment', 'drive:write', 'drive:share', 'drive:manage',
+        'documents:read', 'documents:write', 'spreadsheets:read', 'spreadsheets:write',
+        'forms:read', 'forms:write', 'forms:responses:read'
+    ], TRUE);
@@ -0,0 +1,7 @@
+-- Deactivating an account ends its sessions
+ALTER TABLE refresh_token_families DROP CONSTRAINT refresh_token_families_revoked_reason_check;
+ALTER TABLE refresh_token_families ADD CONSTRAINT refresh_token_families_revoked_reason_check
+    CHECK (revoked_reason IN ('logout', 'reuse_detected', 'session_ended', 'password_reset', 'account_deactivated'));
+
+-- Searching users by role and status
+CREATE INDEX idx_users_role ON users(role);
@@ -0,0 +1,21 @@
+-- Folder sharing: shares, public access and whether the folder inherits
+-- from its parent. The owner stays in owner_id.
+ALTER TABLE folders ADD COLUMN permissions JSONB NOT NULL DEFAULT '{}';
+
+-- Items in a deleted folder go with it rather than surfacing at the drive root
+ALTER TABLE drive_items DROP CONSTRAINT drive_items_parent_id_fkey;
+ALTER TABLE drive_items ADD CONSTRAINT drive_items_parent_id_fkey
+    FOREIGN KEY (parent_id) REFERENCES folders(id) ON DELETE CASCADE;
+
+CREATE INDEX idx_drive_items_drive_parent ON drive_items(drive_id, parent_id);
+CREATE INDEX idx_folders_drive_parent ON folders(drive_id, parent_id);
+
+-- Stars are per user; drive_items.is_starred is the owner's own star
+CREATE TABLE item_stars (
+    item_id UUID NOT NULL REFERENCES drive_items(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, item_id)
+);
+
+CREATE INDEX idx_item_stars_item_id ON item_stars(item_id);
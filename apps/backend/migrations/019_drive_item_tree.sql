@@ -0,0 +1,19 @@
+-- Closure table over the drive tree: a row for every item and each folder
+-- above it, plus the item itself at depth 0, so subtree queries are a join
+CREATE TABLE drive_item_tree (
+    ancestor_id UUID NOT NULL REFERENCES drive_items(id) ON DELETE CASCADE,
+    descendant_id UUID NOT NULL REFERENCES drive_items(id) ON DELETE CASCADE,
+    depth INTEGER NOT NULL CHECK (depth >= 0),
+    PRIMARY KEY (ancestor_id, descendant_id)
+);
+
+CREATE INDEX idx_drive_item_tree_descendant ON drive_item_tree(descendant_id, depth);
+
+INSERT INTO drive_item_tree (ancestor_id, descendant_id, depth)
+WITH RECURSIVE tree AS (
+    SELECT id AS ancestor_id, id AS descendant_id, 0 AS depth FROM drive_items
+    UNION ALL
+    SELECT t.ancestor_id, i.id, t.depth + 1
+    FROM drive_items i JOIN tree t ON i.parent_id = t.descendant_id
+)
+SELECT ancestor_id, descendant_id, depth FROM tree;
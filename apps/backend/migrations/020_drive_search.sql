@@ -0,0 +1,21 @@
+-- Full-text search over drive items. Names are split on punctuation so
+-- `q1-budget.xlsx` is found by `budget`; names weigh most, then tags, then
+-- the description.
+ALTER TABLE drive_items ADD COLUMN search_vector TSVECTOR GENERATED ALWAYS AS (
+    setweight(to_tsvector('english', regexp_replace(name, '[[:punct:]]+', ' ', 'g')), 'A')
+    || setweight(jsonb_to_tsvector('english', COALESCE(metadata->'tags', '[]'), '["string"]'), 'B')
+    || setweight(to_tsvector('english', COALESCE(metadata->>'description', '')), 'C')
+) STORED;
+
+CREATE INDEX idx_drive_items_search ON drive_items USING GIN (search_vector);
+
+-- Text extracted from what items contain: documents, spreadsheet cells and
+-- readable uploads
+CREATE TABLE drive_item_contents (
+    item_id UUID PRIMARY KEY REFERENCES drive_items(id) ON DELETE CASCADE,
+    content TEXT NOT NULL,
+    search_vector TSVECTOR GENERATED ALWAYS AS (setweight(to_tsvector('english', content), 'D')) STORED,
+    indexed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_drive_item_contents_search ON drive_item_contents USING GIN (search_vector);
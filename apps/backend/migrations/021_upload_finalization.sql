@@ -0,0 +1,6 @@
+-- Finalizing an upload is claimed before its file is created, and the session
+-- then remembers the file so a retried finalize returns it instead of
+-- creating another one. Finalized sessions are kept until they expire.
+ALTER TABLE upload_sessions
+    ADD COLUMN finalizing BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN file_id UUID REFERENCES files(id) ON DELETE CASCADE;
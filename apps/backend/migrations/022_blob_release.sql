@@ -0,0 +1,26 @@
+-- Each file row holds a reference on its content blob, released here rather
+-- than by the application so rows removed by a cascade (such as deleting
+-- their owner) give theirs back too. Mirrors release_thumbnail_blob.
+CREATE OR REPLACE FUNCTION release_file_blob()
+RETURNS TRIGGER AS $$
+BEGIN
+    UPDATE blobs
+    SET ref_count = ref_count - 1
+    WHERE checksum = OLD.checksum AND ref_count > 0;
+    RETURN OLD;
+END;
+$$ language 'plpgsql';
+
+CREATE TRIGGER release_file_blob AFTER DELETE ON files
+    FOR EACH ROW EXECUTE FUNCTION release_file_blob();
+
+-- Recount references leaked by files that were cascaded away before
+UPDATE blobs b
+SET ref_count = (SELECT COUNT(*) FROM files f WHERE f.checksum = b.checksum)
+    + (SELECT COUNT(*) FROM thumbnails t WHERE t.thumbnail_checksum = b.checksum)
+WHERE b.ref_count <> (SELECT COUNT(*) FROM files f WHERE f.checksum = b.checksum)
+    + (SELECT COUNT(*) FROM thumbnails t WHERE t.thumbnail_checksum = b.checksum);
+
+-- Garbage collection marks a blob before removing its content from storage,
+-- and deletes the row only after. A marked blob can't be referenced again.
+ALTER TABLE blobs ADD COLUMN collecting BOOLEAN NOT NULL DEFAULT FALSE;
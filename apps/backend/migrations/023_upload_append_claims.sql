@@ -0,0 +1,4 @@
+-- An append claims its session's offset before writing, so two chunks sent
+-- for the same offset can't both reach storage. A claim left by a crashed
+-- request lapses after a while and the offset is read back from storage.
+ALTER TABLE upload_sessions ADD COLUMN appending_since TIMESTAMPTZ;
@@ -0,0 +1,6 @@
+-- The document or uploaded file a drive item holds. Saving the document or
+-- storing the file reindexes the item's contents; items that were never
+-- indexed, such as ones from before search, are picked up by a backfill.
+ALTER TABLE drive_items ADD COLUMN source_id UUID;
+
+CREATE INDEX idx_drive_items_source_id ON drive_items(source_id) WHERE source_id IS NOT NULL;